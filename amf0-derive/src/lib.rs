@@ -0,0 +1,228 @@
+//! This crate provides `#[derive(FromAmf0Object)]` for converting AMF0 objects into structs.
+//!
+//! The derive generates an implementation of `TryFrom<rml_amf0::Amf0Value>` that accepts both
+//! objects and ECMA arrays, and returns an `rml_amf0::Amf0ConversionError` when the value is
+//! not an object, a required property is missing, or a property has an unexpected type.
+//!
+//! Each field reads the property named by its `#[amf0(key = "...")]` attribute, or the property
+//! with the same name as the field when no attribute is given.  Field types must implement
+//! `rml_amf0::FromAmf0Value`, which includes `f64`, `bool`, `String`, `Vec<Amf0Value>` and other
+//! structs deriving `FromAmf0Object`.  Fields of type `Option<T>` are `None` when the property
+//! is absent, null, or undefined.
+//!
+//! # Examples
+//!
+//! ```
+//! #[macro_use]
+//! extern crate rml_amf0_derive;
+//! extern crate rml_amf0;
+//!
+//! use rml_amf0::Amf0Value;
+//! use std::collections::HashMap;
+//! use std::convert::TryFrom;
+//!
+//! #[derive(FromAmf0Object)]
+//! struct Metadata {
+//!     #[amf0(key = "width")]
+//!     width: f64,
+//!
+//!     #[amf0(key = "encoder")]
+//!     encoder: Option<String>,
+//! }
+//!
+//! fn main() {
+//!     let mut properties = HashMap::new();
+//!     properties.insert("width".to_string(), Amf0Value::Number(1920.0));
+//!
+//!     let metadata = Metadata::try_from(Amf0Value::Object(properties)).unwrap();
+//!     assert_eq!(metadata.width, 1920.0);
+//!     assert_eq!(metadata.encoder, None);
+//! }
+//! ```
+
+#![deny(missing_docs)]
+
+extern crate proc_macro;
+extern crate proc_macro2;
+#[macro_use]
+extern crate quote;
+extern crate syn;
+
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use syn::{
+    parse_macro_input, Data, DeriveInput, Error, Field, Fields, GenericArgument, LitStr,
+    PathArguments, Type,
+};
+
+/// Generates `TryFrom<Amf0Value>` and `FromAmf0Value` implementations for a struct with named
+/// fields.  See the crate documentation for the supported field types and attributes.
+#[proc_macro_derive(FromAmf0Object, attributes(amf0))]
+pub fn derive_from_amf0_object(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    match expand(input) {
+        Ok(tokens) => tokens.into(),
+        Err(error) => error.to_compile_error().into(),
+    }
+}
+
+fn expand(input: DeriveInput) -> Result<TokenStream2, Error> {
+    let fields = match input.data {
+        Data::Struct(ref data) => match data.fields {
+            Fields::Named(ref fields) => &fields.named,
+            _ => {
+                return Err(Error::new_spanned(
+                    &input.ident,
+                    "FromAmf0Object can only be derived for structs with named fields",
+                ))
+            }
+        },
+
+        _ => {
+            return Err(Error::new_spanned(
+                &input.ident,
+                "FromAmf0Object can only be derived for structs",
+            ))
+        }
+    };
+
+    let field_values = fields
+        .iter()
+        .map(expand_field)
+        .collect::<Result<Vec<_>, Error>>()?;
+
+    let name = &input.ident;
+    let (impl_generics, type_generics, where_clause) = input.generics.split_for_impl();
+
+    Ok(quote! {
+        impl #impl_generics ::std::convert::TryFrom<::rml_amf0::Amf0Value>
+            for #name #type_generics #where_clause
+        {
+            type Error = ::rml_amf0::Amf0ConversionError;
+
+            fn try_from(value: ::rml_amf0::Amf0Value) -> ::std::result::Result<Self, Self::Error> {
+                let mut properties = match value {
+                    ::rml_amf0::Amf0Value::Object(properties) => properties,
+                    ::rml_amf0::Amf0Value::EcmaArray(properties) => properties,
+                    _ => {
+                        return ::std::result::Result::Err(
+                            ::rml_amf0::Amf0ConversionError::UnexpectedType {
+                                expected: "object",
+                            },
+                        )
+                    }
+                };
+
+                ::std::result::Result::Ok(#name {
+                    #(#field_values,)*
+                })
+            }
+        }
+
+        impl #impl_generics ::rml_amf0::FromAmf0Value for #name #type_generics #where_clause {
+            fn from_amf0_value(
+                value: ::rml_amf0::Amf0Value,
+            ) -> ::std::result::Result<Self, ::rml_amf0::Amf0ConversionError> {
+                <Self as ::std::convert::TryFrom<::rml_amf0::Amf0Value>>::try_from(value)
+            }
+        }
+    })
+}
+
+fn expand_field(field: &Field) -> Result<TokenStream2, Error> {
+    let ident = field
+        .ident
+        .as_ref()
+        .expect("Named fields should always have an identifier");
+
+    let key = get_key(field)?.unwrap_or_else(|| ident.to_string());
+    let convert = |ty: &Type| {
+        quote! {
+            <#ty as ::rml_amf0::FromAmf0Value>::from_amf0_value(value).map_err(|error| {
+                ::rml_amf0::Amf0ConversionError::InvalidProperty {
+                    key: #key.to_string(),
+                    source: ::std::boxed::Box::new(error),
+                }
+            })?
+        }
+    };
+
+    let value = match get_option_inner_type(&field.ty) {
+        Some(inner_type) => {
+            let conversion = convert(inner_type);
+            quote! {
+                match properties.remove(#key) {
+                    ::std::option::Option::None
+                    | ::std::option::Option::Some(::rml_amf0::Amf0Value::Null)
+                    | ::std::option::Option::Some(::rml_amf0::Amf0Value::Undefined) => {
+                        ::std::option::Option::None
+                    }
+
+                    ::std::option::Option::Some(value) => {
+                        ::std::option::Option::Some(#conversion)
+                    }
+                }
+            }
+        }
+
+        None => {
+            let conversion = convert(&field.ty);
+            quote! {
+                match properties.remove(#key) {
+                    ::std::option::Option::Some(value) => #conversion,
+                    ::std::option::Option::None => {
+                        return ::std::result::Result::Err(
+                            ::rml_amf0::Amf0ConversionError::MissingProperty {
+                                key: #key.to_string(),
+                            },
+                        )
+                    }
+                }
+            }
+        }
+    };
+
+    Ok(quote! { #ident: #value })
+}
+
+/// Returns the key specified by the field's `#[amf0(key = "...")]` attribute, if it has one
+fn get_key(field: &Field) -> Result<Option<String>, Error> {
+    let mut key = None;
+    for attribute in field.attrs.iter().filter(|x| x.path().is_ident("amf0")) {
+        attribute.parse_nested_meta(|meta| {
+            if meta.path.is_ident("key") {
+                let value: LitStr = meta.value()?.parse()?;
+                key = Some(value.value());
+                Ok(())
+            } else {
+                Err(meta.error("unsupported amf0 attribute, expected `key`"))
+            }
+        })?;
+    }
+
+    Ok(key)
+}
+
+/// Returns `T` if the type is an `Option<T>`
+fn get_option_inner_type(ty: &Type) -> Option<&Type> {
+    let path = match *ty {
+        Type::Path(ref type_path) if type_path.qself.is_none() => &type_path.path,
+        _ => return None,
+    };
+
+    let segment = path.segments.last()?;
+    if segment.ident != "Option" {
+        return None;
+    }
+
+    match segment.arguments {
+        PathArguments::AngleBracketed(ref arguments) if arguments.args.len() == 1 => {
+            match arguments.args.first() {
+                Some(GenericArgument::Type(ref inner)) => Some(inner),
+                _ => None,
+            }
+        }
+
+        _ => None,
+    }
+}
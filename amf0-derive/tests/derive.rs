@@ -0,0 +1,172 @@
+extern crate rml_amf0;
+#[macro_use]
+extern crate rml_amf0_derive;
+
+use rml_amf0::{Amf0ConversionError, Amf0Value};
+use std::collections::HashMap;
+use std::convert::TryFrom;
+
+#[derive(FromAmf0Object, Debug, PartialEq)]
+struct OnMetaData {
+    #[amf0(key = "width")]
+    width: f64,
+
+    #[amf0(key = "height")]
+    height: f64,
+
+    #[amf0(key = "framerate")]
+    frame_rate: Option<f64>,
+
+    #[amf0(key = "stereo")]
+    stereo: Option<bool>,
+
+    #[amf0(key = "encoder")]
+    encoder: Option<String>,
+
+    #[amf0(key = "trackinfo")]
+    track_info: Option<Vec<Amf0Value>>,
+
+    #[amf0(key = "encoderSettings")]
+    encoder_settings: Option<EncoderSettings>,
+}
+
+#[derive(FromAmf0Object, Debug, PartialEq)]
+struct EncoderSettings {
+    #[amf0(key = "preset")]
+    preset: String,
+
+    #[amf0(key = "lowLatency")]
+    low_latency: bool,
+
+    // Without an attribute the field name is used as the key
+    profile: String,
+}
+
+fn metadata_properties() -> HashMap<String, Amf0Value> {
+    let mut settings = HashMap::new();
+    settings.insert(
+        "preset".to_string(),
+        Amf0Value::Utf8String("veryfast".to_string()),
+    );
+    settings.insert("lowLatency".to_string(), Amf0Value::Boolean(true));
+    settings.insert(
+        "profile".to_string(),
+        Amf0Value::Utf8String("high".to_string()),
+    );
+
+    let mut properties = HashMap::new();
+    properties.insert("width".to_string(), Amf0Value::Number(1920.0));
+    properties.insert("height".to_string(), Amf0Value::Number(1080.0));
+    properties.insert("framerate".to_string(), Amf0Value::Number(30.0));
+    properties.insert("stereo".to_string(), Amf0Value::Boolean(true));
+    properties.insert(
+        "encoder".to_string(),
+        Amf0Value::Utf8String("obs-output module".to_string()),
+    );
+    properties.insert(
+        "trackinfo".to_string(),
+        Amf0Value::StrictArray(vec![Amf0Value::Null]),
+    );
+    properties.insert("encoderSettings".to_string(), Amf0Value::Object(settings));
+    properties
+}
+
+#[test]
+fn can_convert_on_metadata_ecma_array() {
+    let metadata = OnMetaData::try_from(Amf0Value::EcmaArray(metadata_properties())).unwrap();
+
+    assert_eq!(
+        metadata,
+        OnMetaData {
+            width: 1920.0,
+            height: 1080.0,
+            frame_rate: Some(30.0),
+            stereo: Some(true),
+            encoder: Some("obs-output module".to_string()),
+            track_info: Some(vec![Amf0Value::Null]),
+            encoder_settings: Some(EncoderSettings {
+                preset: "veryfast".to_string(),
+                low_latency: true,
+                profile: "high".to_string(),
+            }),
+        }
+    );
+}
+
+#[test]
+fn absent_and_null_optional_properties_are_none() {
+    let mut properties = metadata_properties();
+    properties.remove("framerate");
+    properties.remove("encoderSettings");
+    properties.insert("encoder".to_string(), Amf0Value::Null);
+
+    let metadata = OnMetaData::try_from(Amf0Value::Object(properties)).unwrap();
+
+    assert_eq!(metadata.frame_rate, None, "Unexpected frame rate");
+    assert_eq!(metadata.encoder, None, "Unexpected encoder");
+    assert_eq!(
+        metadata.encoder_settings, None,
+        "Unexpected encoder settings"
+    );
+}
+
+#[test]
+fn error_when_required_property_missing() {
+    let mut properties = metadata_properties();
+    properties.remove("height");
+
+    match OnMetaData::try_from(Amf0Value::Object(properties)) {
+        Err(Amf0ConversionError::MissingProperty { ref key }) if key == "height" => (),
+        x => panic!("Expected missing property error, got {:?}", x),
+    }
+}
+
+#[test]
+fn error_when_property_has_wrong_type() {
+    let mut properties = metadata_properties();
+    properties.insert(
+        "width".to_string(),
+        Amf0Value::Utf8String("1920".to_string()),
+    );
+
+    match OnMetaData::try_from(Amf0Value::Object(properties)) {
+        Err(Amf0ConversionError::InvalidProperty {
+            ref key,
+            ref source,
+        }) if key == "width" => match **source {
+            Amf0ConversionError::UnexpectedType { expected: "number" } => (),
+            ref x => panic!("Unexpected source error: {:?}", x),
+        },
+
+        x => panic!("Expected invalid property error, got {:?}", x),
+    }
+}
+
+#[test]
+fn nested_struct_errors_include_the_property_path() {
+    let mut properties = metadata_properties();
+    properties.insert(
+        "encoderSettings".to_string(),
+        Amf0Value::Object(HashMap::new()),
+    );
+
+    match OnMetaData::try_from(Amf0Value::Object(properties)) {
+        Err(Amf0ConversionError::InvalidProperty {
+            ref key,
+            ref source,
+        }) if key == "encoderSettings" => match **source {
+            Amf0ConversionError::MissingProperty { ref key } if key == "preset" => (),
+            ref x => panic!("Unexpected source error: {:?}", x),
+        },
+
+        x => panic!("Expected invalid property error, got {:?}", x),
+    }
+}
+
+#[test]
+fn error_when_value_is_not_an_object() {
+    match OnMetaData::try_from(Amf0Value::Number(5.0)) {
+        Err(Amf0ConversionError::UnexpectedType { expected: "object" }) => (),
+        x => panic!("Expected unexpected type error, got {:?}", x),
+    }
+}
@@ -0,0 +1,78 @@
+use errors::Amf0ConversionError;
+use Amf0Value;
+
+/// Converts an `Amf0Value` into a strongly typed value.
+///
+/// This is implemented for the basic types AMF0 values map to, and is what
+/// `#[derive(FromAmf0Object)]` from the `rml_amf0_derive` crate uses to convert each property of
+/// an object.  Structs that derive `FromAmf0Object` implement it as well, so they can be nested
+/// inside each other.
+pub trait FromAmf0Value: Sized {
+    /// Converts the value, returning an error if it is not of the expected type
+    fn from_amf0_value(value: Amf0Value) -> Result<Self, Amf0ConversionError>;
+}
+
+impl FromAmf0Value for f64 {
+    fn from_amf0_value(value: Amf0Value) -> Result<Self, Amf0ConversionError> {
+        value
+            .get_number()
+            .ok_or(Amf0ConversionError::UnexpectedType { expected: "number" })
+    }
+}
+
+impl FromAmf0Value for bool {
+    fn from_amf0_value(value: Amf0Value) -> Result<Self, Amf0ConversionError> {
+        value
+            .get_boolean()
+            .ok_or(Amf0ConversionError::UnexpectedType {
+                expected: "boolean",
+            })
+    }
+}
+
+impl FromAmf0Value for String {
+    fn from_amf0_value(value: Amf0Value) -> Result<Self, Amf0ConversionError> {
+        value
+            .get_string()
+            .ok_or(Amf0ConversionError::UnexpectedType { expected: "string" })
+    }
+}
+
+impl FromAmf0Value for Vec<Amf0Value> {
+    fn from_amf0_value(value: Amf0Value) -> Result<Self, Amf0ConversionError> {
+        match value {
+            Amf0Value::StrictArray(values) => Ok(values),
+            _ => Err(Amf0ConversionError::UnexpectedType {
+                expected: "strict array",
+            }),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn can_convert_basic_values() {
+        assert_eq!(f64::from_amf0_value(Amf0Value::Number(1.5)).unwrap(), 1.5);
+        assert!(bool::from_amf0_value(Amf0Value::Boolean(true)).unwrap());
+        assert_eq!(
+            String::from_amf0_value(Amf0Value::Utf8String("abc".to_string())).unwrap(),
+            "abc"
+        );
+        assert_eq!(
+            Vec::<Amf0Value>::from_amf0_value(Amf0Value::StrictArray(vec![Amf0Value::Null]))
+                .unwrap(),
+            vec![Amf0Value::Null]
+        );
+    }
+
+    #[test]
+    fn error_when_value_is_of_different_type() {
+        match f64::from_amf0_value(Amf0Value::Utf8String("1".to_string())) {
+            Err(Amf0ConversionError::UnexpectedType { expected: "number" }) => (),
+            x => panic!("Expected unexpected type error, got {:?}", x),
+        }
+    }
+}
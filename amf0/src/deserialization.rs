@@ -14,12 +14,37 @@ struct ObjectProperty {
     value: Amf0Value,
 }
 
+/// Options that control how strictly AMF0 values are deserialized
+#[derive(Clone, Debug, Default)]
+pub struct Amf0DeserializationConfig {
+    /// When true, an object containing the same key more than once is rejected with an
+    /// `Amf0DeserializationError::DuplicateObjectKey` error.  When false the last value seen for
+    /// a key is kept.
+    pub strict_no_duplicate_keys: bool,
+}
+
+impl Amf0DeserializationConfig {
+    /// Creates a new configuration with the default (lenient) options
+    pub fn new() -> Amf0DeserializationConfig {
+        Amf0DeserializationConfig::default()
+    }
+}
+
 /// Turns any readable byte stream and converts it into an array of AMF0 values
 pub fn deserialize<R: Read>(bytes: &mut R) -> Result<Vec<Amf0Value>, Amf0DeserializationError> {
+    deserialize_with_config(bytes, &Amf0DeserializationConfig::default())
+}
+
+/// Turns any readable byte stream and converts it into an array of AMF0 values, using the
+/// specified options
+pub fn deserialize_with_config<R: Read>(
+    bytes: &mut R,
+    config: &Amf0DeserializationConfig,
+) -> Result<Vec<Amf0Value>, Amf0DeserializationError> {
     let mut results = vec![];
 
     loop {
-        match read_next_value(bytes)? {
+        match read_next_value(bytes, config)? {
             Some(x) => results.push(x),
             None => break,
         };
@@ -28,7 +53,20 @@ pub fn deserialize<R: Read>(bytes: &mut R) -> Result<Vec<Amf0Value>, Amf0Deseria
     Ok(results)
 }
 
-fn read_next_value<R: Read>(bytes: &mut R) -> Result<Option<Amf0Value>, Amf0DeserializationError> {
+/// Reads exactly one AMF0 value from the byte stream, leaving the reader positioned at the
+/// first byte after that value.  This is useful when the AMF0 value is embedded in a larger
+/// structure, as a `Cursor`'s position can be used to determine how many bytes were consumed.
+pub fn deserialize_one<R: Read>(bytes: &mut R) -> Result<Amf0Value, Amf0DeserializationError> {
+    match read_next_value(bytes, &Amf0DeserializationConfig::default())? {
+        Some(value) => Ok(value),
+        None => Err(Amf0DeserializationError::UnexpectedEof),
+    }
+}
+
+fn read_next_value<R: Read>(
+    bytes: &mut R,
+    config: &Amf0DeserializationConfig,
+) -> Result<Option<Amf0Value>, Amf0DeserializationError> {
     let mut buffer: [u8; 1] = [0];
     let bytes_read = bytes.read(&mut buffer)?;
 
@@ -45,10 +83,12 @@ fn read_next_value<R: Read>(bytes: &mut R) -> Result<Option<Amf0Value>, Amf0Dese
         markers::NULL_MARKER => parse_null().map(Some),
         markers::UNDEFINED_MARKER => parse_undefined().map(Some),
         markers::NUMBER_MARKER => parse_number(bytes).map(Some),
-        markers::OBJECT_MARKER => parse_object(bytes).map(Some),
-        markers::ECMA_ARRAY_MARKER => parse_ecma_array(bytes).map(Some),
+        markers::OBJECT_MARKER => parse_object(bytes, config).map(Some),
+        markers::ECMA_ARRAY_MARKER => parse_ecma_array(bytes, config).map(Some),
         markers::STRING_MARKER => parse_string(bytes).map(Some),
-        markers::STRICT_ARRAY_MARKER => parse_strict_array(bytes).map(Some),
+        markers::LONG_STRING_MARKER => parse_long_string(bytes).map(Some),
+        markers::DATE_MARKER => parse_date(bytes).map(Some),
+        markers::STRICT_ARRAY_MARKER => parse_strict_array(bytes, config).map(Some),
         _ => Err(Amf0DeserializationError::UnknownMarker { marker: buffer[0] }),
     }
 }
@@ -60,6 +100,16 @@ fn parse_number<R: Read>(bytes: &mut R) -> Result<Amf0Value, Amf0Deserialization
     Ok(value)
 }
 
+fn parse_date<R: Read>(bytes: &mut R) -> Result<Amf0Value, Amf0DeserializationError> {
+    let millis_since_epoch = bytes.read_f64::<BigEndian>()?;
+    let tz_offset = bytes.read_i16::<BigEndian>()?;
+
+    Ok(Amf0Value::Date {
+        millis_since_epoch,
+        tz_offset,
+    })
+}
+
 fn parse_null() -> Result<Amf0Value, Amf0DeserializationError> {
     Ok(Amf0Value::Null)
 }
@@ -87,24 +137,53 @@ fn parse_string<R: Read>(bytes: &mut R) -> Result<Amf0Value, Amf0Deserialization
     Ok(Amf0Value::Utf8String(value))
 }
 
-fn parse_object<R: Read>(bytes: &mut R) -> Result<Amf0Value, Amf0DeserializationError> {
+fn parse_long_string<R: Read>(bytes: &mut R) -> Result<Amf0Value, Amf0DeserializationError> {
+    let length = bytes.read_u32::<BigEndian>()? as u64;
+
+    // Read through `take()` so a corrupt length can't cause a huge allocation up front
+    let mut buffer = Vec::new();
+    bytes.by_ref().take(length).read_to_end(&mut buffer)?;
+    if buffer.len() as u64 != length {
+        return Err(Amf0DeserializationError::UnexpectedEof);
+    }
+
+    let value = String::from_utf8(buffer)?;
+    Ok(Amf0Value::Utf8String(value))
+}
+
+fn parse_object<R: Read>(
+    bytes: &mut R,
+    config: &Amf0DeserializationConfig,
+) -> Result<Amf0Value, Amf0DeserializationError> {
+    let properties = parse_object_properties(bytes, config)?;
+    Ok(Amf0Value::Object(properties))
+}
+
+fn parse_object_properties<R: Read>(
+    bytes: &mut R,
+    config: &Amf0DeserializationConfig,
+) -> Result<HashMap<String, Amf0Value>, Amf0DeserializationError> {
     let mut properties = HashMap::new();
 
-    loop {
-        match parse_object_property(bytes)? {
-            Some(property) => properties.insert(property.label, property.value),
-            None => break,
-        };
+    while let Some(property) = parse_object_property(bytes, config)? {
+        if config.strict_no_duplicate_keys && properties.contains_key(&property.label) {
+            return Err(Amf0DeserializationError::DuplicateObjectKey {
+                key: property.label,
+            });
+        }
+
+        properties.insert(property.label, property.value);
     }
 
-    let deserialized_value = Amf0Value::Object(properties);
-    Ok(deserialized_value)
+    Ok(properties)
 }
 
-fn parse_ecma_array<R: Read>(bytes: &mut R) -> Result<Amf0Value, Amf0DeserializationError> {
+fn parse_ecma_array<R: Read>(
+    bytes: &mut R,
+    config: &Amf0DeserializationConfig,
+) -> Result<Amf0Value, Amf0DeserializationError> {
     // An ECMA array is an array of values indexed via strings instead of numeric indexes (so
-    // essentially a hash map).  It seems functionally equivalent to an object so for simplicity
-    // treat it as such.
+    // essentially a hash map).  Its properties are encoded the same way as an object's.
 
     // While the spec says it gives you the count of items in the array, it is vague about if
     // the object end marker is used.  In real world usages I have found the associative array
@@ -113,15 +192,19 @@ fn parse_ecma_array<R: Read>(bytes: &mut R) -> Result<Amf0Value, Amf0Deserializa
     // like we can ignore the associative count and just read exactly as we would an object.
 
     let _associative_count = bytes.read_u32::<BigEndian>()?;
-    parse_object(bytes)
+    let properties = parse_object_properties(bytes, config)?;
+    Ok(Amf0Value::EcmaArray(properties))
 }
 
-fn parse_strict_array<R: Read>(bytes: &mut R) -> Result<Amf0Value, Amf0DeserializationError> {
+fn parse_strict_array<R: Read>(
+    bytes: &mut R,
+    config: &Amf0DeserializationConfig,
+) -> Result<Amf0Value, Amf0DeserializationError> {
     let _array_count = bytes.read_u32::<BigEndian>()?;
     let mut values: Vec<Amf0Value> = Vec::new();
 
     for _ in 0.._array_count {
-        match read_next_value(bytes)? {
+        match read_next_value(bytes, config)? {
             Some(value) => {
                 values.push(value);
             }
@@ -134,6 +217,7 @@ fn parse_strict_array<R: Read>(bytes: &mut R) -> Result<Amf0Value, Amf0Deseriali
 
 fn parse_object_property<R: Read>(
     bytes: &mut R,
+    config: &Amf0DeserializationConfig,
 ) -> Result<Option<ObjectProperty>, Amf0DeserializationError> {
     let label_length = bytes.read_u16::<BigEndian>()?;
     if label_length == 0 {
@@ -152,7 +236,7 @@ fn parse_object_property<R: Read>(
 
     let label = String::from_utf8(label_buffer)?;
 
-    match read_next_value(bytes)? {
+    match read_next_value(bytes, config)? {
         None => Err(Amf0DeserializationError::UnexpectedEof),
         Some(property_value) => Ok(Some(ObjectProperty {
             label,
@@ -164,9 +248,11 @@ fn parse_object_property<R: Read>(
 #[cfg(test)]
 mod tests {
     use super::super::Amf0Value;
-    use super::deserialize;
+    use super::{deserialize, deserialize_one, deserialize_with_config, Amf0DeserializationConfig};
     use byteorder::{BigEndian, WriteBytesExt};
+    use errors::Amf0DeserializationError;
     use markers;
+    use serialization::serialize;
     use std::collections::HashMap;
     use std::io::Cursor;
 
@@ -249,6 +335,64 @@ mod tests {
         assert_eq!(result, expected);
     }
 
+    #[test]
+    fn can_deserialize_long_string() {
+        let value = "a".repeat(70_000);
+
+        let mut vector = vec![];
+        vector.write_u8(markers::LONG_STRING_MARKER).unwrap();
+        vector.write_u32::<BigEndian>(value.len() as u32).unwrap();
+        vector.extend(value.as_bytes());
+
+        let mut input = Cursor::new(vector);
+        let result = deserialize(&mut input).unwrap();
+
+        let expected = vec![Amf0Value::Utf8String(value)];
+        assert_eq!(result, expected);
+    }
+
+    #[test]
+    fn error_when_long_string_is_truncated() {
+        let mut vector = vec![];
+        vector.write_u8(markers::LONG_STRING_MARKER).unwrap();
+        vector.write_u32::<BigEndian>(u32::MAX).unwrap();
+        vector.extend(b"abc");
+
+        match deserialize(&mut Cursor::new(vector)) {
+            Err(Amf0DeserializationError::UnexpectedEof) => (),
+            x => panic!("Expected UnexpectedEof error, instead received {:?}", x),
+        }
+    }
+
+    #[test]
+    fn can_deserialize_date_inside_object() {
+        let mut vector = vec![];
+        vector.write_u8(markers::OBJECT_MARKER).unwrap();
+        vector.write_u16::<BigEndian>(9).unwrap();
+        vector.extend(b"createdAt");
+        vector.write_u8(markers::DATE_MARKER).unwrap();
+        vector.write_f64::<BigEndian>(1_600_000_000_000.0).unwrap();
+        vector.write_i16::<BigEndian>(0).unwrap();
+        vector
+            .write_u16::<BigEndian>(markers::UTF_8_EMPTY_MARKER)
+            .unwrap();
+        vector.write_u8(markers::OBJECT_END_MARKER).unwrap();
+
+        let mut input = Cursor::new(vector);
+        let result = deserialize(&mut input).unwrap();
+
+        let mut properties = HashMap::new();
+        properties.insert(
+            "createdAt".to_string(),
+            Amf0Value::Date {
+                millis_since_epoch: 1_600_000_000_000.0,
+                tz_offset: 0,
+            },
+        );
+
+        assert_eq!(result, vec![Amf0Value::Object(properties)]);
+    }
+
     #[test]
     fn can_deserialize_null() {
         let mut vector = vec![];
@@ -315,7 +459,7 @@ mod tests {
             Amf0Value::Utf8String("second".to_string()),
         );
 
-        let expected = vec![Amf0Value::Object(properties)];
+        let expected = vec![Amf0Value::EcmaArray(properties)];
         assert_eq!(result, expected);
     }
 
@@ -330,4 +474,114 @@ mod tests {
         let expected = vec![Amf0Value::Undefined];
         assert_eq!(result, expected);
     }
+
+    #[test]
+    fn undefined_values_survive_round_trip() {
+        let mut properties = HashMap::new();
+        properties.insert("missing".to_string(), Amf0Value::Undefined);
+
+        let input = vec![
+            Amf0Value::Undefined,
+            Amf0Value::Null,
+            Amf0Value::Object(properties),
+        ];
+
+        let bytes = serialize(&input).unwrap();
+        assert_eq!(
+            bytes[0],
+            markers::UNDEFINED_MARKER,
+            "Unexpected first marker"
+        );
+        assert_eq!(bytes[1], markers::NULL_MARKER, "Unexpected second marker");
+
+        let result = deserialize(&mut Cursor::new(bytes)).unwrap();
+        assert_eq!(result, input);
+    }
+
+    #[test]
+    fn duplicate_object_keys_keep_last_value_by_default() {
+        let mut input = Cursor::new(get_object_with_duplicate_key());
+        let result = deserialize(&mut input).unwrap();
+
+        let mut properties = HashMap::new();
+        properties.insert("token".to_string(), Amf0Value::Number(2.0));
+
+        let expected = vec![Amf0Value::Object(properties)];
+        assert_eq!(result, expected);
+    }
+
+    #[test]
+    fn error_on_duplicate_object_keys_in_strict_mode() {
+        let mut config = Amf0DeserializationConfig::new();
+        config.strict_no_duplicate_keys = true;
+
+        let mut input = Cursor::new(get_object_with_duplicate_key());
+        match deserialize_with_config(&mut input, &config) {
+            Err(Amf0DeserializationError::DuplicateObjectKey { ref key }) if key == "token" => (),
+            x => panic!("Expected duplicate object key error, instead got {:?}", x),
+        }
+    }
+
+    fn get_object_with_duplicate_key() -> Vec<u8> {
+        let mut vector = vec![];
+        vector.push(markers::OBJECT_MARKER);
+        for value in &[1.0, 2.0] {
+            vector.write_u16::<BigEndian>(5).unwrap();
+            vector.extend("token".as_bytes());
+            vector.push(markers::NUMBER_MARKER);
+            vector.write_f64::<BigEndian>(*value).unwrap();
+        }
+
+        vector
+            .write_u16::<BigEndian>(markers::UTF_8_EMPTY_MARKER)
+            .unwrap();
+        vector.push(markers::OBJECT_END_MARKER);
+        vector
+    }
+
+    #[test]
+    fn can_deserialize_one_value_at_a_time() {
+        let mut vector = vec![];
+        vector.push(markers::NUMBER_MARKER);
+        vector.write_f64::<BigEndian>(332.0).unwrap();
+        vector.push(markers::STRING_MARKER);
+        vector.write_u16::<BigEndian>(4).unwrap();
+        vector.extend("test".as_bytes());
+        vector.push(markers::BOOLEAN_MARKER);
+        vector.push(1);
+
+        let mut input = Cursor::new(vector);
+        let first = deserialize_one(&mut input).unwrap();
+        assert_eq!(first, Amf0Value::Number(332.0), "Unexpected first value");
+        assert_eq!(input.position(), 9, "Unexpected position after first value");
+
+        let second = deserialize_one(&mut input).unwrap();
+        assert_eq!(
+            second,
+            Amf0Value::Utf8String("test".to_string()),
+            "Unexpected second value"
+        );
+        assert_eq!(
+            input.position(),
+            16,
+            "Unexpected position after second value"
+        );
+
+        let third = deserialize_one(&mut input).unwrap();
+        assert_eq!(third, Amf0Value::Boolean(true), "Unexpected third value");
+        assert_eq!(
+            input.position(),
+            18,
+            "Unexpected position after third value"
+        );
+    }
+
+    #[test]
+    fn deserialize_one_errors_when_no_value_remains() {
+        let mut input = Cursor::new(Vec::<u8>::new());
+        match deserialize_one(&mut input) {
+            Err(Amf0DeserializationError::UnexpectedEof) => (),
+            x => panic!("Expected UnexpectedEof error, instead received {:?}", x),
+        }
+    }
 }
@@ -9,7 +9,10 @@ pub enum Amf0DeserializationError {
     ///
     /// This error is encountered when we see a maker value that we do not recognize.
     #[error("Encountered unknown marker: {marker}")]
-    UnknownMarker { marker: u8 },
+    UnknownMarker {
+        /// The marker byte that was read
+        marker: u8,
+    },
 
     /// Object properties consist of a name and value pair.  It is expected that every property
     /// has a valid string name, and if the name is empty this error is raised.
@@ -29,17 +32,69 @@ pub enum Amf0DeserializationError {
     /// UTF-8 this error will be raised.
     #[error("Failed to read a utf8 string from the byte buffer: {0}")]
     StringParseError(#[from] string::FromUtf8Error),
+
+    /// An object contained the same property name more than once while deserializing with
+    /// `strict_no_duplicate_keys` enabled.
+    #[error("Object contained the duplicate key '{key}'")]
+    DuplicateObjectKey {
+        /// The property name that appeared more than once
+        key: String,
+    },
 }
 
 /// Errors raised during to the serialization process
 #[derive(Debug, Error)]
 pub enum Amf0SerializationError {
-    /// Amf0 strings cannot be more than 65,535 characters, so if a string was provided
-    /// with a larger length than this than this error is raised.
-    #[error("String length greater than 65,535")]
+    /// Amf0 strings cannot be more than 4,294,967,295 bytes, even when written as long strings,
+    /// so if a string was provided with a larger length than this than this error is raised.
+    #[error("String length greater than 4,294,967,295")]
     NormalStringTooLong,
 
+    /// Objects and arrays were nested deeper than the configured `max_depth`.  This guards
+    /// against stack overflows from serializing values with an excessive amount of nesting.
+    #[error("Values were nested {depth} levels deep, which exceeds the maximum depth")]
+    MaxDepthExceeded {
+        /// The nesting depth that exceeded the maximum
+        depth: usize,
+    },
+
     /// An I/O error occurred while writing to the output buffer.
     #[error("Failed to write to byte buffer")]
     BufferWriteError(#[from] io::Error),
 }
+
+/// Errors raised when converting between AMF0 values and other formats
+#[derive(Debug, Error)]
+pub enum Amf0ConversionError {
+    /// AMF0 numbers are 64 bit floating point values, so this error is raised when a number
+    /// cannot be represented as one.
+    #[error("The number {number} cannot be represented as an AMF0 number")]
+    UnrepresentableNumber {
+        /// The textual form of the number that could not be converted
+        number: String,
+    },
+
+    /// The value was not of the type it was being converted to
+    #[error("Expected the AMF0 value to be a {expected}")]
+    UnexpectedType {
+        /// The name of the AMF0 type that was expected
+        expected: &'static str,
+    },
+
+    /// An object being converted into a struct did not have a property for a required field
+    #[error("The required property '{key}' was not present")]
+    MissingProperty {
+        /// The name of the missing property
+        key: String,
+    },
+
+    /// The value of an object's property could not be converted into the type of its field
+    #[error("The property '{key}' could not be converted: {source}")]
+    InvalidProperty {
+        /// The name of the property that could not be converted
+        key: String,
+
+        /// Why the property's value could not be converted
+        source: Box<Amf0ConversionError>,
+    },
+}
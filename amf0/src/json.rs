@@ -0,0 +1,154 @@
+//! This module contains functionality to convert AMF0 values to and from JSON values.  This is
+//! only available when the `serde-json` feature is enabled.
+
+use errors::Amf0ConversionError;
+use serde_json::{Map, Number, Value};
+use Amf0Value;
+
+// Largest integer that a 64 bit float can represent without losing precision
+const MAX_SAFE_INTEGER: f64 = 9_007_199_254_740_991.0;
+
+impl Amf0Value {
+    /// Converts a JSON value into its equivalent AMF0 value.  JSON arrays are converted into
+    /// strict arrays, and JSON objects are converted into AMF0 objects.
+    pub fn from_json_value(value: &Value) -> Result<Amf0Value, Amf0ConversionError> {
+        match *value {
+            Value::Null => Ok(Amf0Value::Null),
+            Value::Bool(value) => Ok(Amf0Value::Boolean(value)),
+            Value::Number(ref number) => match number.as_f64() {
+                Some(number) => Ok(Amf0Value::Number(number)),
+                None => Err(Amf0ConversionError::UnrepresentableNumber {
+                    number: number.to_string(),
+                }),
+            },
+            Value::String(ref value) => Ok(Amf0Value::Utf8String(value.clone())),
+            Value::Array(ref values) => {
+                let values = values
+                    .iter()
+                    .map(Amf0Value::from_json_value)
+                    .collect::<Result<Vec<_>, _>>()?;
+
+                Ok(Amf0Value::StrictArray(values))
+            }
+            Value::Object(ref properties) => {
+                let properties = properties
+                    .iter()
+                    .map(|(key, value)| Ok((key.clone(), Amf0Value::from_json_value(value)?)))
+                    .collect::<Result<_, Amf0ConversionError>>()?;
+
+                Ok(Amf0Value::Object(properties))
+            }
+        }
+    }
+
+    /// Converts the AMF0 value into its equivalent JSON value.
+    ///
+    /// Since JSON has no concept of undefined values, ECMA arrays, or dates, undefined values are
+    /// converted to JSON nulls, ECMA arrays are converted into JSON objects, and dates are
+    /// converted to their number of milliseconds since the Unix epoch.  Numbers that
+    /// are not finite cannot be represented in JSON and are converted to nulls as well, while
+    /// numbers that are exact integers are converted to JSON integers.
+    pub fn to_json_value(&self) -> Value {
+        match *self {
+            // Exact integers are kept as JSON integers so they round trip cleanly
+            Amf0Value::Number(value) if value.fract() == 0.0 && value.abs() <= MAX_SAFE_INTEGER => {
+                Value::from(value as i64)
+            }
+            Amf0Value::Number(value) => match Number::from_f64(value) {
+                Some(number) => Value::Number(number),
+                None => Value::Null,
+            },
+            Amf0Value::Boolean(value) => Value::Bool(value),
+            Amf0Value::Utf8String(ref value) => Value::String(value.clone()),
+            Amf0Value::Object(ref properties) | Amf0Value::EcmaArray(ref properties) => {
+                let properties = properties
+                    .iter()
+                    .map(|(key, value)| (key.clone(), value.to_json_value()))
+                    .collect::<Map<_, _>>();
+
+                Value::Object(properties)
+            }
+            Amf0Value::StrictArray(ref values) => {
+                Value::Array(values.iter().map(|value| value.to_json_value()).collect())
+            }
+            Amf0Value::Null | Amf0Value::Undefined => Value::Null,
+            Amf0Value::Date {
+                millis_since_epoch, ..
+            } => Amf0Value::Number(millis_since_epoch).to_json_value(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::Value;
+    use std::collections::HashMap;
+    use Amf0Value;
+
+    #[test]
+    fn can_convert_scalar_json_values() {
+        let cases = vec![
+            (Value::Null, Amf0Value::Null),
+            (Value::Bool(true), Amf0Value::Boolean(true)),
+            (Value::from(12.5), Amf0Value::Number(12.5)),
+            (Value::from(3), Amf0Value::Number(3.0)),
+            (
+                Value::from("test"),
+                Amf0Value::Utf8String("test".to_string()),
+            ),
+        ];
+
+        for (json, amf0) in cases {
+            assert_eq!(Amf0Value::from_json_value(&json).unwrap(), amf0);
+            assert_eq!(amf0.to_json_value(), json);
+        }
+    }
+
+    #[test]
+    fn can_convert_json_arrays_and_objects() {
+        let json: Value = serde_json::from_str(
+            r#"{"app": "live", "flags": [true, null], "nested": {"width": 1920}}"#,
+        )
+        .unwrap();
+
+        let mut nested = HashMap::new();
+        nested.insert("width".to_string(), Amf0Value::Number(1920.0));
+
+        let mut properties = HashMap::new();
+        properties.insert("app".to_string(), Amf0Value::Utf8String("live".to_string()));
+        properties.insert(
+            "flags".to_string(),
+            Amf0Value::StrictArray(vec![Amf0Value::Boolean(true), Amf0Value::Null]),
+        );
+        properties.insert("nested".to_string(), Amf0Value::Object(nested));
+        let expected = Amf0Value::Object(properties);
+
+        let amf0 = Amf0Value::from_json_value(&json).unwrap();
+        assert_eq!(amf0, expected, "Unexpected amf0 value");
+        assert_eq!(
+            amf0.to_json_value(),
+            json,
+            "Unexpected round trip json value"
+        );
+    }
+
+    #[test]
+    fn json_only_values_converted_to_nearest_equivalent() {
+        let mut properties = HashMap::new();
+        properties.insert("a".to_string(), Amf0Value::Undefined);
+        properties.insert("b".to_string(), Amf0Value::Number(f64::NAN));
+        properties.insert(
+            "c".to_string(),
+            Amf0Value::Date {
+                millis_since_epoch: 1_600_000_000_000.0,
+                tz_offset: 0,
+            },
+        );
+
+        let json = Amf0Value::EcmaArray(properties).to_json_value();
+        let expected: Value =
+            serde_json::from_str(r#"{"a": null, "b": null, "c": 1600000000000}"#).unwrap();
+
+        assert_eq!(json, expected);
+    }
+}
@@ -29,33 +29,77 @@
 //! assert_eq!(input, results);
 //! ```
 
+#![deny(missing_docs)]
+
 #[macro_use]
 extern crate byteorder;
+#[cfg(feature = "serde-json")]
+extern crate serde_json;
 extern crate thiserror;
 
+mod conversion;
 mod deserialization;
 mod errors;
+#[cfg(feature = "serde-json")]
+mod json;
+mod pretty_print;
 mod serialization;
 
-pub use deserialization::deserialize;
-pub use errors::{Amf0DeserializationError, Amf0SerializationError};
-pub use serialization::serialize;
+pub use conversion::FromAmf0Value;
+pub use deserialization::{
+    deserialize, deserialize_one, deserialize_with_config, Amf0DeserializationConfig,
+};
+pub use errors::{Amf0ConversionError, Amf0DeserializationError, Amf0SerializationError};
+pub use serialization::{
+    serialize, serialize_into, serialize_with_config, Amf0SerializationConfig,
+};
 
+use std::borrow::Cow;
 use std::collections::HashMap;
 
 /// An Enum representing the different supported types of Amf0 values
 #[derive(PartialEq, Debug, Clone)]
 pub enum Amf0Value {
+    /// A 64 bit floating point number (marker `0x00`).  AMF0 has no separate integer type, so
+    /// all numeric values are encoded this way.
     Number(f64),
+
+    /// A boolean value (marker `0x01`)
     Boolean(bool),
+
+    /// A UTF-8 string (marker `0x02`).  Strings longer than 65,535 bytes are encoded as long
+    /// strings (marker `0x0c`) instead.
     Utf8String(String),
+
+    /// An anonymous object made up of named properties (marker `0x03`)
     Object(HashMap<String, Amf0Value>),
+
+    /// An associative array of named properties (marker `0x08`).  It is encoded the same way as
+    /// an object, with an additional count of the properties it contains.
+    EcmaArray(HashMap<String, Amf0Value>),
+
+    /// An ordered list of values (marker `0x0a`)
     StrictArray(Vec<Amf0Value>),
+
+    /// The null value (marker `0x05`)
     Null,
+
+    /// The undefined value (marker `0x06`)
     Undefined,
+
+    /// A point in time (marker `0x0b`)
+    Date {
+        /// The number of milliseconds since the Unix epoch, in UTC
+        millis_since_epoch: f64,
+
+        /// The time zone offset.  The AMF0 specification says this is reserved and should be
+        /// 0, but it is kept so dates are serialized back out exactly as they were received.
+        tz_offset: i16,
+    },
 }
 
 impl Amf0Value {
+    /// Returns the number contained in the value, or `None` if the value is not a number
     pub fn get_number(self) -> Option<f64> {
         match self {
             Amf0Value::Number(value) => Some(value),
@@ -63,6 +107,53 @@ impl Amf0Value {
         }
     }
 
+    /// Returns the number as a `u32`, or `None` if the value is not a number or the number
+    /// cannot be exactly represented as a `u32`.
+    pub fn get_u32(self) -> Option<u32> {
+        self.get_integer(u32::MIN as f64, u32::MAX as f64)
+            .map(|x| x as u32)
+    }
+
+    /// Returns the number as an `i32`, or `None` if the value is not a number or the number
+    /// cannot be exactly represented as an `i32`.
+    pub fn get_i32(self) -> Option<i32> {
+        self.get_integer(i32::MIN as f64, i32::MAX as f64)
+            .map(|x| x as i32)
+    }
+
+    /// Returns the number as a `u64`, or `None` if the value is not a number or the number
+    /// cannot be exactly represented as a `u64`.
+    pub fn get_u64(self) -> Option<u64> {
+        self.get_integer(u64::MIN as f64, u64::MAX as f64)
+            .map(|x| x as u64)
+    }
+
+    /// Returns the number as an `i64`, or `None` if the value is not a number or the number
+    /// cannot be exactly represented as an `i64`.
+    pub fn get_i64(self) -> Option<i64> {
+        self.get_integer(i64::MIN as f64, i64::MAX as f64)
+            .map(|x| x as i64)
+    }
+
+    /// Returns the number as a `usize`, or `None` if the value is not a number or the number
+    /// cannot be exactly represented as a `usize`.
+    pub fn get_usize(self) -> Option<usize> {
+        self.get_integer(usize::MIN as f64, usize::MAX as f64)
+            .map(|x| x as usize)
+    }
+
+    /// Returns the number of milliseconds since the Unix epoch of a date, or `None` if the value
+    /// is not a date
+    pub fn get_date(self) -> Option<f64> {
+        match self {
+            Amf0Value::Date {
+                millis_since_epoch, ..
+            } => Some(millis_since_epoch),
+            _ => None,
+        }
+    }
+
+    /// Returns the boolean contained in the value, or `None` if the value is not a boolean
     pub fn get_boolean(self) -> Option<bool> {
         match self {
             Amf0Value::Boolean(value) => Some(value),
@@ -70,6 +161,7 @@ impl Amf0Value {
         }
     }
 
+    /// Returns the string contained in the value, or `None` if the value is not a string
     pub fn get_string(self) -> Option<String> {
         match self {
             Amf0Value::Utf8String(value) => Some(value),
@@ -77,12 +169,169 @@ impl Amf0Value {
         }
     }
 
+    /// Returns a reference to the string contained in the value, or `None` if the value is not a
+    /// string.  Unlike `get_string()` this does not consume the value.
+    pub fn as_str(&self) -> Option<&str> {
+        match *self {
+            Amf0Value::Utf8String(ref value) => Some(value),
+            _ => None,
+        }
+    }
+
+    /// Returns the textual content of the value, or `None` if the value does not contain text.
+    /// The text is borrowed when possible, and is only allocated for values whose content has
+    /// to be converted to form a valid string.
+    pub fn as_str_lossy(&self) -> Option<Cow<'_, str>> {
+        self.as_str().map(Cow::Borrowed)
+    }
+
+    /// Returns the properties of the value, or `None` if the value is not an object
     pub fn get_object_properties(self) -> Option<HashMap<String, Amf0Value>> {
         match self {
             Amf0Value::Object(properties) => Some(properties),
             _ => None,
         }
     }
+
+    /// Returns the properties of the value, or `None` if the value is not an ECMA array
+    pub fn get_ecma_array_properties(self) -> Option<HashMap<String, Amf0Value>> {
+        match self {
+            Amf0Value::EcmaArray(properties) => Some(properties),
+            _ => None,
+        }
+    }
+
+    /// Returns an iterator over the object's properties without consuming the value, or `None`
+    /// if the value is not an object.  Properties are returned in order of their names, so the
+    /// order is the same every time.
+    ///
+    /// ```
+    /// use rml_amf0::Amf0Value;
+    /// use std::collections::HashMap;
+    ///
+    /// let object = Amf0Value::Object(HashMap::new())
+    ///     .with_property("fpad".to_string(), Amf0Value::Boolean(false))
+    ///     .with_property("app".to_string(), Amf0Value::Utf8String("live".to_string()));
+    ///
+    /// let names: Vec<&str> = object.object_iter().unwrap().map(|(name, _)| name).collect();
+    /// assert_eq!(names, vec!["app", "fpad"]);
+    /// ```
+    pub fn object_iter(&self) -> Option<impl Iterator<Item = (&str, &Amf0Value)> + '_> {
+        match *self {
+            Amf0Value::Object(ref properties) => Some(sorted_properties(properties)),
+            _ => None,
+        }
+    }
+
+    /// Returns an iterator over the ECMA array's properties without consuming the value, or
+    /// `None` if the value is not an ECMA array.  Properties are returned in order of their names.
+    pub fn ecma_array_iter(&self) -> Option<impl Iterator<Item = (&str, &Amf0Value)> + '_> {
+        match *self {
+            Amf0Value::EcmaArray(ref properties) => Some(sorted_properties(properties)),
+            _ => None,
+        }
+    }
+
+    /// Combines the properties of two objects into a new object.  When both objects contain a
+    /// property with the same name, the property from `override_` is kept.  An error is returned
+    /// if either value is not an object.
+    ///
+    /// ```
+    /// use rml_amf0::Amf0Value;
+    /// use std::collections::HashMap;
+    ///
+    /// let base = Amf0Value::Object(HashMap::new())
+    ///     .with_property("app".to_string(), Amf0Value::Utf8String("live".to_string()))
+    ///     .with_property("fpad".to_string(), Amf0Value::Boolean(false));
+    /// let extras = Amf0Value::Object(HashMap::new())
+    ///     .with_property("fpad".to_string(), Amf0Value::Boolean(true));
+    ///
+    /// let merged = Amf0Value::merge(base, extras).unwrap();
+    /// let properties = merged.get_object_properties().unwrap();
+    /// assert_eq!(properties.len(), 2);
+    /// assert_eq!(properties["fpad"], Amf0Value::Boolean(true));
+    /// ```
+    pub fn merge(base: Amf0Value, override_: Amf0Value) -> Result<Amf0Value, Amf0ConversionError> {
+        let mut properties = base
+            .get_object_properties()
+            .ok_or(Amf0ConversionError::UnexpectedType { expected: "object" })?;
+        let overrides = override_
+            .get_object_properties()
+            .ok_or(Amf0ConversionError::UnexpectedType { expected: "object" })?;
+
+        properties.extend(overrides);
+        Ok(Amf0Value::Object(properties))
+    }
+
+    /// Returns the object with the specified property added to it, replacing any existing
+    /// property with the same name.  Values that are not objects are returned unchanged.
+    pub fn with_property(mut self, key: String, value: Amf0Value) -> Self {
+        if let Amf0Value::Object(ref mut properties) = self {
+            properties.insert(key, value);
+        }
+
+        self
+    }
+
+    /// Returns the number if it is a whole number within the range of an integer type with the
+    /// specified minimum and maximum values.
+    fn get_integer(self, min: f64, max: f64) -> Option<f64> {
+        // The maximum of 64 bit integer types rounds up to the next power of two when converted
+        // to a float, which is one past the largest representable value
+        let max_exclusive = max + 1.0;
+        match self {
+            Amf0Value::Number(value)
+                if value.fract() == 0.0 && value >= min && value < max_exclusive =>
+            {
+                Some(value)
+            }
+
+            _ => None,
+        }
+    }
+
+    /// Returns the number of bytes this value will take up once it has been amf0 serialized,
+    /// including its type marker.
+    ///
+    /// Nested values are walked without recursion, so this can't overflow the stack no matter
+    /// how deeply the value is nested.
+    pub fn byte_len(&self) -> usize {
+        let mut len = 0;
+        let mut remaining = vec![self];
+        while let Some(value) = remaining.pop() {
+            len += match *value {
+                Amf0Value::Number(_) => 9,
+                Amf0Value::Boolean(_) => 2,
+                Amf0Value::Utf8String(ref value) if value.len() > u16::MAX as usize => {
+                    5 + value.len()
+                }
+                Amf0Value::Utf8String(ref value) => 3 + value.len(),
+                Amf0Value::Object(ref properties) => {
+                    remaining.extend(properties.values());
+
+                    // object marker + property names + empty utf8 name + object end marker
+                    1 + property_names_len(properties) + 3
+                }
+                Amf0Value::EcmaArray(ref properties) => {
+                    remaining.extend(properties.values());
+
+                    // array marker + count + property names + empty utf8 name + object end marker
+                    5 + property_names_len(properties) + 3
+                }
+                Amf0Value::StrictArray(ref values) => {
+                    remaining.extend(values);
+
+                    // array marker + count
+                    5
+                }
+                Amf0Value::Null => 1,
+                Amf0Value::Undefined => 1,
+                Amf0Value::Date { .. } => 11,
+            };
+        }
+
+        len
+    }
 }
 
 mod markers {
@@ -95,5 +344,235 @@ mod markers {
     pub const ECMA_ARRAY_MARKER: u8 = 8;
     pub const OBJECT_END_MARKER: u8 = 9;
     pub const STRICT_ARRAY_MARKER: u8 = 10;
+    pub const DATE_MARKER: u8 = 11;
+    pub const LONG_STRING_MARKER: u8 = 12;
     pub const UTF_8_EMPTY_MARKER: u16 = 0;
 }
+
+// Hash map iteration order is arbitrary, so properties are sorted to give a stable order
+fn sorted_properties(
+    properties: &HashMap<String, Amf0Value>,
+) -> impl Iterator<Item = (&str, &Amf0Value)> {
+    let mut sorted = properties
+        .iter()
+        .map(|(name, value)| (name.as_str(), value))
+        .collect::<Vec<_>>();
+
+    sorted.sort_by_key(|(name, _)| *name);
+    sorted.into_iter()
+}
+
+// The serialized length of an object's property names, each with its 2 byte length prefix
+fn property_names_len(properties: &HashMap<String, Amf0Value>) -> usize {
+    properties.keys().map(|name| 2 + name.len()).sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use {Amf0ConversionError, Amf0Value};
+
+    #[test]
+    fn as_str_borrows_string_values() {
+        let value = Amf0Value::Utf8String("foo".to_string());
+
+        assert_eq!(value.as_str(), Some("foo"));
+        assert_eq!(
+            value.as_str_lossy().as_ref().map(|x| x.as_ref()),
+            Some("foo")
+        );
+    }
+
+    #[test]
+    fn as_str_returns_none_for_non_strings() {
+        assert_eq!(Amf0Value::Number(5.0).as_str(), None);
+        assert_eq!(Amf0Value::Null.as_str(), None);
+        assert_eq!(Amf0Value::Object(Default::default()).as_str(), None);
+        assert!(Amf0Value::Boolean(true).as_str_lossy().is_none());
+    }
+
+    #[test]
+    fn integer_getters_return_whole_numbers() {
+        assert_eq!(Amf0Value::Number(5.0).get_u32(), Some(5));
+        assert_eq!(Amf0Value::Number(-5.0).get_i32(), Some(-5));
+        assert_eq!(Amf0Value::Number(5.0).get_u64(), Some(5));
+        assert_eq!(Amf0Value::Number(-5.0).get_i64(), Some(-5));
+        assert_eq!(Amf0Value::Number(5.0).get_usize(), Some(5));
+    }
+
+    #[test]
+    fn integer_getters_return_none_for_fractional_and_non_finite_numbers() {
+        assert_eq!(Amf0Value::Number(1.5).get_u32(), None);
+        assert_eq!(Amf0Value::Number(-1.5).get_i64(), None);
+        assert_eq!(Amf0Value::Number(f64::NAN).get_u64(), None);
+        assert_eq!(Amf0Value::Number(f64::INFINITY).get_i64(), None);
+    }
+
+    #[test]
+    fn integer_getters_return_none_for_non_numbers() {
+        assert_eq!(Amf0Value::Utf8String("5".to_string()).get_u32(), None);
+        assert_eq!(Amf0Value::Null.get_i32(), None);
+    }
+
+    #[test]
+    fn integer_getters_respect_type_boundaries() {
+        assert_eq!(Amf0Value::Number(4294967295.0).get_u32(), Some(u32::MAX));
+        assert_eq!(Amf0Value::Number(4294967296.0).get_u32(), None);
+        assert_eq!(Amf0Value::Number(-1.0).get_u32(), None);
+        assert_eq!(Amf0Value::Number(-1.0).get_usize(), None);
+        assert_eq!(Amf0Value::Number(2147483647.0).get_i32(), Some(i32::MAX));
+        assert_eq!(Amf0Value::Number(2147483648.0).get_i32(), None);
+        assert_eq!(Amf0Value::Number(-2147483648.0).get_i32(), Some(i32::MIN));
+        assert_eq!(Amf0Value::Number(-2147483649.0).get_i32(), None);
+        assert_eq!(
+            Amf0Value::Number(9223372036854775808.0).get_i64(),
+            None,
+            "2^63 is one past the maximum i64"
+        );
+        assert_eq!(
+            Amf0Value::Number(-9223372036854775808.0).get_i64(),
+            Some(i64::MIN)
+        );
+        assert_eq!(
+            Amf0Value::Number(18446744073709551616.0).get_u64(),
+            None,
+            "2^64 is one past the maximum u64"
+        );
+        assert_eq!(
+            Amf0Value::Number(9007199254740992.0).get_u64(),
+            Some(9007199254740992)
+        );
+    }
+
+    #[test]
+    fn merge_prefers_override_properties_on_key_collision() {
+        let base = create_object(&[("app", "live"), ("flashVer", "FMLE/3.0")]);
+        let override_ = create_object(&[("flashVer", "LNX 9,0,124,2"), ("tcUrl", "rtmp://host")]);
+
+        let merged = Amf0Value::merge(base, override_).unwrap();
+
+        let expected = create_object(&[
+            ("app", "live"),
+            ("flashVer", "LNX 9,0,124,2"),
+            ("tcUrl", "rtmp://host"),
+        ]);
+        assert_eq!(merged, expected);
+    }
+
+    #[test]
+    fn merge_with_empty_base_returns_override_properties() {
+        let override_ = create_object(&[("app", "live")]);
+        let merged = Amf0Value::merge(create_object(&[]), override_.clone()).unwrap();
+
+        assert_eq!(merged, override_);
+    }
+
+    #[test]
+    fn merge_with_empty_override_returns_base_properties() {
+        let base = create_object(&[("app", "live")]);
+        let merged = Amf0Value::merge(base.clone(), create_object(&[])).unwrap();
+
+        assert_eq!(merged, base);
+    }
+
+    #[test]
+    fn merge_errors_when_either_value_is_not_an_object() {
+        let object = create_object(&[("app", "live")]);
+        let ecma_array = Amf0Value::EcmaArray(HashMap::new());
+
+        match Amf0Value::merge(Amf0Value::Null, object.clone()) {
+            Err(Amf0ConversionError::UnexpectedType { expected: "object" }) => (),
+            x => panic!("Expected UnexpectedType error, instead received {:?}", x),
+        }
+
+        match Amf0Value::merge(object, ecma_array) {
+            Err(Amf0ConversionError::UnexpectedType { expected: "object" }) => (),
+            x => panic!("Expected UnexpectedType error, instead received {:?}", x),
+        }
+    }
+
+    #[test]
+    fn with_property_adds_and_replaces_properties() {
+        let value = create_object(&[("app", "live"), ("type", "nonprivate")])
+            .with_property(
+                "type".to_string(),
+                Amf0Value::Utf8String("private".to_string()),
+            )
+            .with_property("fpad".to_string(), Amf0Value::Boolean(false));
+
+        let mut expected = HashMap::new();
+        expected.insert("app".to_string(), Amf0Value::Utf8String("live".to_string()));
+        expected.insert(
+            "type".to_string(),
+            Amf0Value::Utf8String("private".to_string()),
+        );
+        expected.insert("fpad".to_string(), Amf0Value::Boolean(false));
+        assert_eq!(value, Amf0Value::Object(expected));
+    }
+
+    #[test]
+    fn with_property_leaves_non_objects_unchanged() {
+        let value = Amf0Value::Number(5.0).with_property("a".to_string(), Amf0Value::Null);
+        assert_eq!(value, Amf0Value::Number(5.0));
+    }
+
+    #[test]
+    fn object_iter_returns_properties_in_name_order() {
+        let value = create_object(&[("c", "3"), ("a", "1"), ("b", "2")]);
+        let properties = value
+            .object_iter()
+            .unwrap()
+            .map(|(name, value)| (name, value.as_str().unwrap()))
+            .collect::<Vec<_>>();
+
+        assert_eq!(properties, vec![("a", "1"), ("b", "2"), ("c", "3")]);
+    }
+
+    #[test]
+    fn object_iter_returns_none_for_non_objects() {
+        let ecma_array = Amf0Value::EcmaArray(HashMap::new());
+
+        assert!(Amf0Value::Number(5.0).object_iter().is_none());
+        assert!(Amf0Value::Null.object_iter().is_none());
+        assert!(ecma_array.object_iter().is_none());
+        assert!(ecma_array.ecma_array_iter().is_some());
+        assert!(create_object(&[]).ecma_array_iter().is_none());
+    }
+
+    #[test]
+    fn get_ecma_array_properties_returns_properties_of_ecma_arrays_only() {
+        let mut properties = HashMap::new();
+        properties.insert("duration".to_string(), Amf0Value::Number(10.5));
+        properties.insert(
+            "encoder".to_string(),
+            Amf0Value::Utf8String("Lavf58".to_string()),
+        );
+
+        let ecma_array = Amf0Value::EcmaArray(properties.clone());
+        let object = Amf0Value::Object(properties.clone());
+
+        assert_eq!(ecma_array.get_ecma_array_properties(), Some(properties));
+        assert_eq!(object.get_ecma_array_properties(), None);
+        assert_eq!(Amf0Value::Null.get_ecma_array_properties(), None);
+    }
+
+    #[test]
+    fn get_date_returns_milliseconds_of_dates_only() {
+        let date = Amf0Value::Date {
+            millis_since_epoch: 1_600_000_000_000.0,
+            tz_offset: 0,
+        };
+
+        assert_eq!(date.get_date(), Some(1_600_000_000_000.0));
+        assert_eq!(Amf0Value::Number(1_600_000_000_000.0).get_date(), None);
+    }
+
+    fn create_object(properties: &[(&str, &str)]) -> Amf0Value {
+        let properties = properties
+            .iter()
+            .map(|(key, value)| (key.to_string(), Amf0Value::Utf8String(value.to_string())))
+            .collect();
+
+        Amf0Value::Object(properties)
+    }
+}
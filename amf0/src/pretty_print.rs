@@ -0,0 +1,115 @@
+//! This module contains functionality to format AMF0 values in a human readable form, which is
+//! mostly useful for debug output of deeply nested objects.
+
+use std::collections::HashMap;
+use Amf0Value;
+
+const INDENT: &str = "  ";
+
+impl Amf0Value {
+    /// Formats the value in a human readable, multi-line form.  Each level of nesting inside
+    /// of objects and arrays is indented by 2 spaces more than the previous level, with `indent`
+    /// being the nesting level the value itself starts at.
+    ///
+    /// Numbers that are exact integers are printed without a trailing `.0`, strings are wrapped
+    /// in double quotes, and object properties are printed in alphabetical order so the output
+    /// is consistent.
+    pub fn pretty_print(&self, indent: usize) -> String {
+        match *self {
+            Amf0Value::Number(value) => format!("{}", value),
+            Amf0Value::Boolean(value) => format!("{}", value),
+            Amf0Value::Utf8String(ref value) => format!("\"{}\"", value),
+            Amf0Value::Object(ref properties) => pretty_print_properties(properties, indent),
+            Amf0Value::EcmaArray(ref properties) => pretty_print_properties(properties, indent),
+            Amf0Value::StrictArray(ref values) => {
+                if values.is_empty() {
+                    return "[]".to_string();
+                }
+
+                let lines = values
+                    .iter()
+                    .map(|value| {
+                        format!(
+                            "{}{}",
+                            INDENT.repeat(indent + 1),
+                            value.pretty_print(indent + 1)
+                        )
+                    })
+                    .collect::<Vec<_>>();
+
+                format!("[\n{}\n{}]", lines.join(",\n"), INDENT.repeat(indent))
+            }
+            Amf0Value::Null => "null".to_string(),
+            Amf0Value::Undefined => "undefined".to_string(),
+            Amf0Value::Date {
+                millis_since_epoch, ..
+            } => format!("Date({})", millis_since_epoch),
+        }
+    }
+}
+
+fn pretty_print_properties(properties: &HashMap<String, Amf0Value>, indent: usize) -> String {
+    if properties.is_empty() {
+        return "{}".to_string();
+    }
+
+    let mut keys = properties.keys().collect::<Vec<_>>();
+    keys.sort();
+
+    let lines = keys
+        .into_iter()
+        .map(|key| {
+            format!(
+                "{}\"{}\": {}",
+                INDENT.repeat(indent + 1),
+                key,
+                properties[key].pretty_print(indent + 1)
+            )
+        })
+        .collect::<Vec<_>>();
+
+    format!("{{\n{}\n{}}}", lines.join(",\n"), INDENT.repeat(indent))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use Amf0Value;
+
+    #[test]
+    fn can_pretty_print_nested_object() {
+        let mut inner = HashMap::new();
+        inner.insert("enabled".to_string(), Amf0Value::Boolean(true));
+        inner.insert("empty".to_string(), Amf0Value::Null);
+        inner.insert(
+            "values".to_string(),
+            Amf0Value::StrictArray(vec![
+                Amf0Value::Number(1.0),
+                Amf0Value::Utf8String("two".to_string()),
+            ]),
+        );
+
+        let mut outer = HashMap::new();
+        outer.insert("app".to_string(), Amf0Value::Utf8String("live".to_string()));
+        outer.insert("fps".to_string(), Amf0Value::Number(29.97));
+        outer.insert("height".to_string(), Amf0Value::Number(720.0));
+        outer.insert("inner".to_string(), Amf0Value::Object(inner));
+
+        let expected = "{\n  \"app\": \"live\",\n  \"fps\": 29.97,\n  \"height\": 720,\n  \"inner\": {\n    \"empty\": null,\n    \"enabled\": true,\n    \"values\": [\n      1,\n      \"two\"\n    ]\n  }\n}";
+
+        assert_eq!(Amf0Value::Object(outer).pretty_print(0), expected);
+    }
+
+    #[test]
+    fn pretty_print_uses_starting_indent_for_nested_lines() {
+        let value = Amf0Value::StrictArray(vec![Amf0Value::Undefined]);
+
+        assert_eq!(value.pretty_print(1), "[\n    undefined\n  ]");
+    }
+
+    #[test]
+    fn can_pretty_print_empty_containers() {
+        assert_eq!(Amf0Value::Object(HashMap::new()).pretty_print(0), "{}");
+        assert_eq!(Amf0Value::StrictArray(Vec::new()).pretty_print(0), "[]");
+    }
+}
@@ -8,29 +8,128 @@ use markers;
 use std::collections::HashMap;
 use Amf0Value;
 
+/// The default maximum depth that objects and arrays can be nested when serializing
+const DEFAULT_MAX_DEPTH: usize = 64;
+
+/// Options that control how AMF0 values are serialized
+#[derive(Clone, Debug)]
+pub struct Amf0SerializationConfig {
+    /// The maximum number of objects and arrays that can be nested inside each other.  Values
+    /// nested deeper than this are rejected with an `Amf0SerializationError::MaxDepthExceeded`
+    /// error.  Defaults to 64.
+    pub max_depth: usize,
+
+    /// When true, numbers holding a whole value in the `i32` range are written with the bit
+    /// pattern of the exact integer.  IEEE 754 only has one such pattern for every integer
+    /// except zero, so in practice this writes negative zero as positive zero, for parsers
+    /// that don't treat negative zero as an integer.  Defaults to false.
+    pub prefer_integer_encoding: bool,
+}
+
+impl Amf0SerializationConfig {
+    /// Creates a new configuration with the default options
+    pub fn new() -> Amf0SerializationConfig {
+        Amf0SerializationConfig::default()
+    }
+}
+
+impl Default for Amf0SerializationConfig {
+    fn default() -> Self {
+        Amf0SerializationConfig {
+            max_depth: DEFAULT_MAX_DEPTH,
+            prefer_integer_encoding: false,
+        }
+    }
+}
+
 /// Serializes values into an amf0 encoded vector of bytes
 pub fn serialize(values: &Vec<Amf0Value>) -> Result<Vec<u8>, Amf0SerializationError> {
-    let mut bytes = vec![];
+    serialize_with_config(values, &Amf0SerializationConfig::default())
+}
+
+/// Serializes values into an amf0 encoded vector of bytes, using the specified options
+pub fn serialize_with_config(
+    values: &Vec<Amf0Value>,
+    config: &Amf0SerializationConfig,
+) -> Result<Vec<u8>, Amf0SerializationError> {
+    let mut bytes = Vec::new();
     for value in values {
-        serialize_value(value, &mut bytes)?;
+        serialize_value(value, &mut bytes, config, 0)?;
     }
 
     Ok(bytes)
 }
 
-fn serialize_value(value: &Amf0Value, bytes: &mut Vec<u8>) -> Result<(), Amf0SerializationError> {
+/// Serializes values onto the end of an existing vector of bytes, using the specified options.
+/// This allows callers that know roughly how large the output will be (such as from
+/// `Amf0Value::byte_len()`) to allocate it up front.
+pub fn serialize_into(
+    values: &[Amf0Value],
+    bytes: &mut Vec<u8>,
+    config: &Amf0SerializationConfig,
+) -> Result<(), Amf0SerializationError> {
+    for value in values {
+        serialize_value(value, bytes, config, 0)?;
+    }
+
+    Ok(())
+}
+
+// `depth` is the number of objects and arrays the value is nested inside of
+fn serialize_value(
+    value: &Amf0Value,
+    bytes: &mut Vec<u8>,
+    config: &Amf0SerializationConfig,
+    depth: usize,
+) -> Result<(), Amf0SerializationError> {
     match *value {
         Amf0Value::Boolean(val) => Ok(serialize_bool(val, bytes)),
         Amf0Value::Null => Ok(serialize_null(bytes)),
         Amf0Value::Undefined => Ok(serialize_undefined(bytes)),
-        Amf0Value::Number(val) => serialize_number(val, bytes),
+        Amf0Value::Date {
+            millis_since_epoch,
+            tz_offset,
+        } => serialize_date(millis_since_epoch, tz_offset, bytes),
+        Amf0Value::Number(val) => serialize_number(val, bytes, config),
         Amf0Value::Utf8String(ref val) => serialize_string(val, bytes),
-        Amf0Value::Object(ref val) => serialize_object(val, bytes),
-        Amf0Value::StrictArray(ref val) => serialize_strict_array(val, bytes),
+        Amf0Value::Object(ref val) => {
+            serialize_object(val, bytes, config, enter_container(config, depth)?)
+        }
+        Amf0Value::EcmaArray(ref val) => {
+            serialize_ecma_array(val, bytes, config, enter_container(config, depth)?)
+        }
+        Amf0Value::StrictArray(ref val) => {
+            serialize_strict_array(val, bytes, config, enter_container(config, depth)?)
+        }
+    }
+}
+
+fn enter_container(
+    config: &Amf0SerializationConfig,
+    depth: usize,
+) -> Result<usize, Amf0SerializationError> {
+    let depth = depth + 1;
+    if depth > config.max_depth {
+        return Err(Amf0SerializationError::MaxDepthExceeded { depth });
     }
+
+    Ok(depth)
 }
 
-fn serialize_number(value: f64, bytes: &mut Vec<u8>) -> Result<(), Amf0SerializationError> {
+fn serialize_number(
+    value: f64,
+    bytes: &mut Vec<u8>,
+    config: &Amf0SerializationConfig,
+) -> Result<(), Amf0SerializationError> {
+    let is_integer = value == value.floor() && value >= i32::MIN as f64 && value <= i32::MAX as f64;
+
+    let value = if config.prefer_integer_encoding && is_integer {
+        // Round trip through the integer to get its exact bit pattern
+        value as i32 as f64
+    } else {
+        value
+    };
+
     bytes.push(markers::NUMBER_MARKER);
     bytes.write_f64::<BigEndian>(value)?;
     Ok(())
@@ -42,12 +141,19 @@ fn serialize_bool(value: bool, bytes: &mut Vec<u8>) {
 }
 
 fn serialize_string(value: &String, bytes: &mut Vec<u8>) -> Result<(), Amf0SerializationError> {
-    if value.len() > (u16::max_value() as usize) {
+    if value.len() > (u32::max_value() as usize) {
         return Err(Amf0SerializationError::NormalStringTooLong);
     }
 
-    bytes.push(markers::STRING_MARKER);
-    bytes.write_u16::<BigEndian>(value.len() as u16)?;
+    // Strings too long for a 16 bit length prefix are written as long strings instead
+    if value.len() > (u16::max_value() as usize) {
+        bytes.push(markers::LONG_STRING_MARKER);
+        bytes.write_u32::<BigEndian>(value.len() as u32)?;
+    } else {
+        bytes.push(markers::STRING_MARKER);
+        bytes.write_u16::<BigEndian>(value.len() as u16)?;
+    }
+
     bytes.extend(value.as_bytes());
     Ok(())
 }
@@ -60,17 +166,49 @@ fn serialize_undefined(bytes: &mut Vec<u8>) {
     bytes.push(markers::UNDEFINED_MARKER);
 }
 
+fn serialize_date(
+    millis_since_epoch: f64,
+    tz_offset: i16,
+    bytes: &mut Vec<u8>,
+) -> Result<(), Amf0SerializationError> {
+    bytes.push(markers::DATE_MARKER);
+    bytes.write_f64::<BigEndian>(millis_since_epoch)?;
+    bytes.write_i16::<BigEndian>(tz_offset)?;
+    Ok(())
+}
+
 fn serialize_object(
     properties: &HashMap<String, Amf0Value>,
     bytes: &mut Vec<u8>,
+    config: &Amf0SerializationConfig,
+    depth: usize,
 ) -> Result<(), Amf0SerializationError> {
     bytes.push(markers::OBJECT_MARKER);
+    serialize_properties(properties, bytes, config, depth)
+}
+
+fn serialize_ecma_array(
+    properties: &HashMap<String, Amf0Value>,
+    bytes: &mut Vec<u8>,
+    config: &Amf0SerializationConfig,
+    depth: usize,
+) -> Result<(), Amf0SerializationError> {
+    bytes.push(markers::ECMA_ARRAY_MARKER);
+    bytes.write_u32::<BigEndian>(properties.len() as u32)?;
+    serialize_properties(properties, bytes, config, depth)
+}
 
+fn serialize_properties(
+    properties: &HashMap<String, Amf0Value>,
+    bytes: &mut Vec<u8>,
+    config: &Amf0SerializationConfig,
+    depth: usize,
+) -> Result<(), Amf0SerializationError> {
     for (name, value) in properties {
         // TODO: Add check that property name isn't greater than a u16
         bytes.write_u16::<BigEndian>(name.len() as u16)?;
         bytes.extend(name.as_bytes());
-        serialize_value(&value, bytes)?;
+        serialize_value(&value, bytes, config, depth)?;
     }
 
     bytes.write_u16::<BigEndian>(markers::UTF_8_EMPTY_MARKER)?;
@@ -81,13 +219,15 @@ fn serialize_object(
 fn serialize_strict_array(
     array: &Vec<Amf0Value>,
     bytes: &mut Vec<u8>,
+    config: &Amf0SerializationConfig,
+    depth: usize,
 ) -> Result<(), Amf0SerializationError> {
     bytes.push(markers::STRICT_ARRAY_MARKER);
 
     bytes.write_u32::<BigEndian>(array.len() as u32)?;
 
     for value in array {
-        serialize_value(&value, bytes)?;
+        serialize_value(&value, bytes, config, depth)?;
     }
 
     Ok(())
@@ -97,10 +237,16 @@ fn serialize_strict_array(
 mod tests {
     use super::super::errors::Amf0SerializationError;
     use super::super::Amf0Value;
-    use super::serialize;
+    use super::{
+        serialize, serialize_into, serialize_with_config, Amf0SerializationConfig,
+        DEFAULT_MAX_DEPTH,
+    };
+    use byteorder::ReadBytesExt;
     use byteorder::{BigEndian, WriteBytesExt};
+    use deserialize;
     use markers;
     use std::collections::HashMap;
+    use std::io::Cursor;
 
     #[test]
     fn can_serialize_strict_array() {
@@ -211,20 +357,88 @@ mod tests {
     }
 
     #[test]
-    fn error_when_string_length_greater_than_u16() {
-        let mut value = String::new();
-        let max = (u16::max_value() as u32) + 1;
-        for _ in 0..max {
-            value.push('a');
+    fn can_serialize_ecma_array() {
+        let mut properties = HashMap::new();
+        properties.insert("test1".to_string(), Amf0Value::Number(1.0));
+        properties.insert(
+            "test2".to_string(),
+            Amf0Value::Utf8String("second".to_string()),
+        );
+
+        let input = vec![Amf0Value::EcmaArray(properties)];
+        let result = serialize(&input).unwrap();
+
+        let mut cursor = Cursor::new(&result);
+        assert_eq!(
+            cursor.read_u8().unwrap(),
+            markers::ECMA_ARRAY_MARKER,
+            "Unexpected marker"
+        );
+        assert_eq!(
+            cursor.read_u32::<BigEndian>().unwrap(),
+            2,
+            "Unexpected entry count"
+        );
+
+        let deserialized = deserialize(&mut Cursor::new(&result)).unwrap();
+        assert_eq!(deserialized, input, "Unexpected deserialized values");
+    }
+
+    #[test]
+    fn can_serialize_empty_ecma_array() {
+        let input = vec![Amf0Value::EcmaArray(HashMap::new())];
+        let result = serialize(&input).unwrap();
+
+        let mut expected = vec![];
+        expected.push(markers::ECMA_ARRAY_MARKER);
+        expected.write_u32::<BigEndian>(0).unwrap();
+        expected
+            .write_u16::<BigEndian>(markers::UTF_8_EMPTY_MARKER)
+            .unwrap();
+        expected.push(markers::OBJECT_END_MARKER);
+
+        assert_eq!(result, expected);
+    }
+
+    #[test]
+    fn long_string_marker_only_used_when_string_length_greater_than_u16() {
+        let max = u16::max_value() as usize;
+        for (length, marker) in &[
+            (max, markers::STRING_MARKER),
+            (max + 1, markers::LONG_STRING_MARKER),
+        ] {
+            let value = "a".repeat(*length);
+            let input = vec![Amf0Value::Utf8String(value)];
+            let result = serialize(&input).unwrap();
+
+            assert_eq!(
+                result[0], *marker,
+                "Unexpected marker for length {}",
+                length
+            );
+            assert_eq!(
+                deserialize(&mut Cursor::new(&result)).unwrap(),
+                input,
+                "String of length {} did not round trip",
+                length
+            );
         }
+    }
 
-        let input = vec![Amf0Value::Utf8String(value)];
-        let result = serialize(&input);
+    #[test]
+    fn can_serialize_long_string() {
+        let value = "b".repeat(70_000);
+
+        let input = vec![Amf0Value::Utf8String(value.clone())];
+        let result = serialize(&input).unwrap();
 
-        assert!(match result {
-            Err(Amf0SerializationError::NormalStringTooLong) => true,
-            _ => false,
-        });
+        let mut expected = vec![];
+        expected.write_u8(markers::LONG_STRING_MARKER).unwrap();
+        expected.write_u32::<BigEndian>(value.len() as u32).unwrap();
+        expected.extend(value.as_bytes());
+
+        assert_eq!(result, expected);
+        assert_eq!(deserialize(&mut Cursor::new(&result)).unwrap(), input);
     }
 
     #[test]
@@ -237,4 +451,176 @@ mod tests {
 
         assert_eq!(result, expected);
     }
+
+    #[test]
+    fn can_serialize_date() {
+        let input = vec![Amf0Value::Date {
+            millis_since_epoch: 1_700_000_000_123.0,
+            tz_offset: -300,
+        }];
+        let result = serialize(&input).unwrap();
+
+        let mut expected = vec![];
+        expected.write_u8(markers::DATE_MARKER).unwrap();
+        expected
+            .write_f64::<BigEndian>(1_700_000_000_123.0)
+            .unwrap();
+        expected.write_i16::<BigEndian>(-300).unwrap();
+
+        assert_eq!(result, expected);
+        assert_eq!(deserialize(&mut Cursor::new(&result)).unwrap(), input);
+    }
+
+    #[test]
+    fn can_serialize_objects_nested_up_to_max_depth() {
+        for depth in &[63, 64] {
+            let input = vec![create_nested_object(*depth)];
+            let result = serialize(&input);
+
+            assert!(result.is_ok(), "Depth {} failed: {:?}", depth, result);
+        }
+    }
+
+    #[test]
+    fn error_when_objects_nested_deeper_than_max_depth() {
+        let input = vec![create_nested_object(65)];
+        match serialize(&input) {
+            Err(Amf0SerializationError::MaxDepthExceeded { depth: 65 }) => (),
+            x => panic!("Expected max depth exceeded error, got {:?}", x),
+        }
+    }
+
+    #[test]
+    fn max_depth_can_be_configured() {
+        let mut config = Amf0SerializationConfig::new();
+        config.max_depth = 2;
+
+        let input = vec![Amf0Value::StrictArray(vec![create_nested_object(2)])];
+        match serialize_with_config(&input, &config) {
+            Err(Amf0SerializationError::MaxDepthExceeded { depth: 3 }) => (),
+            x => panic!("Expected max depth exceeded error, got {:?}", x),
+        }
+    }
+
+    #[test]
+    fn integer_encoding_writes_negative_zero_as_positive_zero() {
+        let mut config = Amf0SerializationConfig::new();
+        config.prefer_integer_encoding = true;
+
+        let input = vec![Amf0Value::Number(-0.0)];
+        let standard = serialize(&input).unwrap();
+        let integer = serialize_with_config(&input, &config).unwrap();
+
+        assert_ne!(
+            standard, integer,
+            "Negative zero should use a different bit pattern"
+        );
+        assert_eq!(
+            integer,
+            vec![markers::NUMBER_MARKER, 0, 0, 0, 0, 0, 0, 0, 0]
+        );
+
+        for bytes in [standard, integer] {
+            let result = deserialize(&mut Cursor::new(bytes)).unwrap();
+            assert_eq!(result, vec![Amf0Value::Number(0.0)]);
+        }
+    }
+
+    #[test]
+    fn integer_encoding_leaves_other_numbers_unchanged() {
+        let mut config = Amf0SerializationConfig::new();
+        config.prefer_integer_encoding = true;
+
+        let input = vec![
+            Amf0Value::Number(1.0),
+            Amf0Value::Number(-2147483648.0),
+            Amf0Value::Number(1.5),
+            Amf0Value::Number(4294967296.0),
+        ];
+
+        let result = serialize_with_config(&input, &config).unwrap();
+        assert_eq!(result, serialize(&input).unwrap());
+        assert_eq!(deserialize(&mut Cursor::new(result)).unwrap(), input);
+    }
+
+    fn create_nested_object(depth: usize) -> Amf0Value {
+        let mut value = Amf0Value::Object(HashMap::new());
+        for _ in 1..depth {
+            let mut properties = HashMap::new();
+            properties.insert("child".to_string(), value);
+            value = Amf0Value::Object(properties);
+        }
+
+        value
+    }
+
+    #[test]
+    fn serialize_into_appends_to_existing_bytes() {
+        let mut bytes = vec![9_u8];
+        let config = Amf0SerializationConfig::default();
+        serialize_into(&[Amf0Value::Boolean(true)], &mut bytes, &config).unwrap();
+
+        let expected = vec![9_u8, markers::BOOLEAN_MARKER, 1];
+        assert_eq!(bytes, expected);
+    }
+
+    #[test]
+    fn error_when_serializing_very_deeply_nested_value() {
+        let mut value = Amf0Value::StrictArray(Vec::new());
+        for _ in 1..100_000 {
+            value = Amf0Value::StrictArray(vec![value]);
+        }
+
+        let values = vec![value];
+        match serialize(&values) {
+            Err(Amf0SerializationError::MaxDepthExceeded { depth }) => {
+                assert_eq!(depth, DEFAULT_MAX_DEPTH + 1, "Unexpected depth");
+            }
+
+            x => panic!("Expected MaxDepthExceeded error, instead got {:?}", x),
+        }
+
+        assert_eq!(values[0].byte_len(), 500_000, "Unexpected byte length");
+
+        // Dropping the value would recurse once per level, so it has to be taken apart first
+        let mut remaining = values;
+        while let Some(value) = remaining.pop() {
+            if let Amf0Value::StrictArray(children) = value {
+                remaining.extend(children);
+            }
+        }
+    }
+
+    #[test]
+    fn byte_len_matches_serialized_length() {
+        let mut properties = HashMap::new();
+        properties.insert("test".to_string(), Amf0Value::Number(332.0));
+        properties.insert("name".to_string(), Amf0Value::Utf8String("abc".to_string()));
+
+        let input = vec![
+            Amf0Value::Number(15.0),
+            Amf0Value::Boolean(true),
+            Amf0Value::Utf8String("some string".to_string()),
+            Amf0Value::Utf8String("a".repeat(70_000)),
+            Amf0Value::Null,
+            Amf0Value::Undefined,
+            Amf0Value::Date {
+                millis_since_epoch: 0.0,
+                tz_offset: 0,
+            },
+            Amf0Value::Object(properties.clone()),
+            Amf0Value::EcmaArray(properties),
+            Amf0Value::StrictArray(vec![Amf0Value::Number(1.0), Amf0Value::Null]),
+        ];
+
+        for value in input {
+            let expected = serialize(&vec![value.clone()]).unwrap().len();
+            assert_eq!(
+                value.byte_len(),
+                expected,
+                "Incorrect byte length for {:?}",
+                value
+            );
+        }
+    }
 }
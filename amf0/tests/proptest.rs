@@ -0,0 +1,68 @@
+extern crate proptest;
+extern crate rml_amf0;
+
+use proptest::collection::{hash_map, vec};
+use proptest::num::f64;
+use proptest::prelude::*;
+use rml_amf0::{deserialize, serialize, Amf0Value};
+use std::io::Cursor;
+
+// NaN is excluded since it never compares equal to itself
+fn any_number() -> impl Strategy<Value = f64> {
+    f64::POSITIVE | f64::NEGATIVE | f64::NORMAL | f64::SUBNORMAL | f64::ZERO | f64::INFINITE
+}
+
+// Strings whose encoded length is close to either side of the 65,535 byte limit of a normal
+// string, so both normal and long string markers are exercised
+fn boundary_length_string() -> impl Strategy<Value = String> {
+    (any::<char>(), 65_530usize..65_542).prop_map(|(character, target_bytes)| {
+        let repeats = target_bytes / character.len_utf8();
+        character.to_string().repeat(repeats)
+    })
+}
+
+fn scalar_value() -> impl Strategy<Value = Amf0Value> {
+    prop_oneof![
+        Just(Amf0Value::Null),
+        Just(Amf0Value::Undefined),
+        any::<bool>().prop_map(Amf0Value::Boolean),
+        any_number().prop_map(Amf0Value::Number),
+        "\\PC{0,64}".prop_map(Amf0Value::Utf8String),
+        boundary_length_string().prop_map(Amf0Value::Utf8String),
+        (any_number(), any::<i16>()).prop_map(|(millis_since_epoch, tz_offset)| {
+            Amf0Value::Date {
+                millis_since_epoch,
+                tz_offset,
+            }
+        }),
+    ]
+}
+
+fn any_value() -> impl Strategy<Value = Amf0Value> {
+    scalar_value().prop_recursive(4, 64, 8, |inner| {
+        prop_oneof![
+            vec(inner.clone(), 0..8).prop_map(Amf0Value::StrictArray),
+            hash_map("[a-zA-Z0-9_]{1,16}", inner.clone(), 0..8).prop_map(Amf0Value::Object),
+            hash_map("[a-zA-Z0-9_]{1,16}", inner, 0..8).prop_map(Amf0Value::EcmaArray),
+        ]
+    })
+}
+
+proptest! {
+    #[test]
+    fn serialized_values_deserialize_to_same_values(input in vec(any_value(), 0..16)) {
+        let bytes = serialize(&input).unwrap();
+        let mut cursor = Cursor::new(bytes);
+        let result = deserialize(&mut cursor).unwrap();
+
+        prop_assert_eq!(result, input);
+    }
+
+    #[test]
+    fn serialized_length_matches_byte_len(input in vec(scalar_value(), 0..16)) {
+        let bytes = serialize(&input).unwrap();
+        let expected: usize = input.iter().map(|value| value.byte_len()).sum();
+
+        prop_assert_eq!(bytes.len(), expected);
+    }
+}
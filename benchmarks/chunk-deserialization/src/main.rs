@@ -0,0 +1,127 @@
+extern crate bytes;
+extern crate rml_rtmp;
+
+use bytes::Bytes;
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::SystemTime;
+
+use rml_rtmp::chunk_io::{ChunkDeserializer, ChunkSerializer, DropPolicy};
+use rml_rtmp::messages::RtmpMessage;
+use rml_rtmp::time::RtmpTimestamp;
+
+const ITERATION_COUNT: u32 = 200_000;
+const VIDEO_SIZE: usize = 10_000;
+const CHUNK_SIZE: u32 = 65_536;
+
+/// Wraps the system allocator to count allocations, so the copying done by each
+/// deserialization mode is visible.
+struct CountingAllocator;
+
+static ALLOCATION_COUNT: AtomicUsize = AtomicUsize::new(0);
+static ALLOCATED_BYTES: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATION_COUNT.fetch_add(1, Ordering::Relaxed);
+        ALLOCATED_BYTES.fetch_add(layout.size(), Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+fn main() {
+    let args: Vec<_> = std::env::args().collect();
+    let iteration_count = if args.len() >= 2 {
+        args[1].parse::<u32>().unwrap()
+    } else {
+        ITERATION_COUNT
+    };
+
+    let packet = create_video_packet();
+    println!(
+        "Running {} iterations of a {} byte single chunk video message",
+        iteration_count,
+        packet.len()
+    );
+
+    let mut deserializer = create_deserializer();
+    run("&[u8] input", iteration_count, || {
+        deserializer.get_next_message(&packet[..]).unwrap().unwrap()
+    });
+
+    let mut deserializer = create_deserializer();
+    run("Bytes input", iteration_count, || {
+        deserializer
+            .get_next_message_bytes(packet.clone())
+            .unwrap()
+            .unwrap()
+    });
+}
+
+fn run<F, T>(name: &str, iteration_count: u32, mut deserialize: F)
+where
+    F: FnMut() -> T,
+{
+    let starting_allocation_count = ALLOCATION_COUNT.load(Ordering::Relaxed);
+    let starting_allocated_bytes = ALLOCATED_BYTES.load(Ordering::Relaxed);
+    let start = SystemTime::now();
+
+    for _ in 0..iteration_count {
+        deserialize();
+    }
+
+    let elapsed = start.elapsed().unwrap();
+    let allocation_count = ALLOCATION_COUNT.load(Ordering::Relaxed) - starting_allocation_count;
+    let allocated_bytes = ALLOCATED_BYTES.load(Ordering::Relaxed) - starting_allocated_bytes;
+    let total_ns = elapsed.as_secs() * 1_000_000_000 + elapsed.subsec_nanos() as u64;
+    let average_ns = total_ns / iteration_count as u64;
+    let megabytes_per_second =
+        (VIDEO_SIZE as f64 * iteration_count as f64) / (total_ns as f64 / 1_000.0);
+
+    println!(
+        "{}: took {}.{:09} seconds (avg {}ns, {:.0} MB/s), {} allocations totaling {} bytes",
+        name,
+        elapsed.as_secs(),
+        elapsed.subsec_nanos(),
+        average_ns,
+        megabytes_per_second,
+        allocation_count,
+        allocated_bytes
+    );
+}
+
+fn create_video_packet() -> Bytes {
+    let mut serializer = ChunkSerializer::new();
+    serializer
+        .set_max_chunk_size(CHUNK_SIZE, RtmpTimestamp::new(0))
+        .unwrap();
+
+    let message = RtmpMessage::VideoData {
+        data: Bytes::from(vec![1_u8; VIDEO_SIZE]),
+    };
+    let payload = message
+        .into_message_payload(RtmpTimestamp::new(0), 1)
+        .unwrap();
+
+    // Force a full header so every iteration can be deserialized on its own
+    let packet = serializer
+        .serialize(&payload, true, DropPolicy::MustDeliver)
+        .unwrap();
+
+    Bytes::from(packet.bytes)
+}
+
+fn create_deserializer() -> ChunkDeserializer {
+    let mut deserializer = ChunkDeserializer::new();
+    deserializer
+        .set_max_chunk_size(CHUNK_SIZE as usize)
+        .unwrap();
+    deserializer
+}
@@ -3,11 +3,13 @@ extern crate rml_amf0;
 extern crate rml_rtmp;
 
 use bytes::Bytes;
+use std::alloc::{GlobalAlloc, Layout, System};
 use std::collections::HashMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
 use std::time::SystemTime;
 
 use rml_amf0::Amf0Value;
-use rml_rtmp::chunk_io::ChunkSerializer;
+use rml_rtmp::chunk_io::{ChunkSerializer, DropPolicy};
 use rml_rtmp::messages::{MessagePayload, RtmpMessage};
 use rml_rtmp::sessions::{
     ServerSession, ServerSessionConfig, ServerSessionEvent, ServerSessionResult,
@@ -18,6 +20,28 @@ const ITERATION_COUNT: u32 = 50_000;
 static APP_NAME: &'static str = "live";
 static STREAM_KEY: &'static str = "stream_key";
 
+/// Wraps the system allocator to count allocations, so we can verify relaying video data to
+/// multiple players does not copy the video data for each player.
+struct CountingAllocator;
+
+static ALLOCATION_COUNT: AtomicUsize = AtomicUsize::new(0);
+static ALLOCATED_BYTES: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATION_COUNT.fetch_add(1, Ordering::Relaxed);
+        ALLOCATED_BYTES.fetch_add(layout.size(), Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
 fn main() {
     let args: Vec<_> = std::env::args().collect();
     let iteration_count = if args.len() >= 2 {
@@ -41,9 +65,11 @@ fn main() {
         .into_message_payload(RtmpTimestamp::new(0), 1)
         .unwrap();
     let video_packet = publisher_serializer
-        .serialize(&video_payload, true, true)
+        .serialize(&video_payload, true, DropPolicy::DropAggressively)
         .unwrap();
 
+    let starting_allocation_count = ALLOCATION_COUNT.load(Ordering::Relaxed);
+    let starting_allocated_bytes = ALLOCATED_BYTES.load(Ordering::Relaxed);
     let start = SystemTime::now();
 
     for _ in 0..iteration_count {
@@ -61,10 +87,20 @@ fn main() {
                         timestamp,
                     } => {
                         player1
-                            .send_video_data(1, data.clone(), timestamp.clone(), true)
+                            .send_video_data(
+                                1,
+                                data.clone(),
+                                timestamp.clone(),
+                                DropPolicy::DropAggressively,
+                            )
                             .unwrap();
                         player2
-                            .send_video_data(1, data.clone(), timestamp.clone(), true)
+                            .send_video_data(
+                                1,
+                                data.clone(),
+                                timestamp.clone(),
+                                DropPolicy::DropAggressively,
+                            )
                             .unwrap();
                     }
 
@@ -75,6 +111,8 @@ fn main() {
     }
 
     let elapsed = start.elapsed().unwrap();
+    let allocation_count = ALLOCATION_COUNT.load(Ordering::Relaxed) - starting_allocation_count;
+    let allocated_bytes = ALLOCATED_BYTES.load(Ordering::Relaxed) - starting_allocated_bytes;
     let total_ns = elapsed.as_secs() * 1_000_000_000 + elapsed.subsec_nanos() as u64;
     let average_ns = total_ns / iteration_count as u64;
 
@@ -84,6 +122,14 @@ fn main() {
         elapsed.subsec_nanos(),
         average_ns
     );
+
+    println!(
+        "{} allocations totaling {} bytes (avg {} allocations and {} bytes per iteration)",
+        allocation_count,
+        allocated_bytes,
+        allocation_count / iteration_count as usize,
+        allocated_bytes / iteration_count as usize
+    );
 }
 
 fn create_publishing_session() -> (ServerSession, ChunkSerializer) {
@@ -116,7 +162,9 @@ fn perform_connection(
     serializer: &mut ChunkSerializer,
 ) {
     let connect_payload = create_connect_message(app_name.to_string(), 15, 0, 0.0);
-    let connect_packet = serializer.serialize(&connect_payload, true, false).unwrap();
+    let connect_packet = serializer
+        .serialize(&connect_payload, true, DropPolicy::MustDeliver)
+        .unwrap();
     let connect_results = session.handle_input(&connect_packet.bytes[..]).unwrap();
 
     for result in connect_results {
@@ -124,10 +172,7 @@ fn perform_connection(
             ServerSessionResult::OutboundResponse(_) => (),
             ServerSessionResult::UnhandleableMessageReceived(_) => (),
             ServerSessionResult::RaisedEvent(event) => match event {
-                ServerSessionEvent::ConnectionRequested {
-                    app_name: _,
-                    request_id,
-                } => {
+                ServerSessionEvent::ConnectionRequested { request_id, .. } => {
                     session.accept_request(request_id).unwrap();
                 }
 
@@ -173,7 +218,9 @@ fn create_active_stream(session: &mut ServerSession, serializer: &mut ChunkSeria
     let payload = message
         .into_message_payload(RtmpTimestamp::new(0), 0)
         .unwrap();
-    let packet = serializer.serialize(&payload, true, false).unwrap();
+    let packet = serializer
+        .serialize(&payload, true, DropPolicy::MustDeliver)
+        .unwrap();
     let _ = session.handle_input(&packet.bytes[..]).unwrap();
 
     1
@@ -194,7 +241,7 @@ fn start_publishing(session: &mut ServerSession, serializer: &mut ChunkSerialize
         .into_message_payload(RtmpTimestamp::new(0), 1)
         .unwrap();
     let publish_packet = serializer
-        .serialize(&publish_payload, false, false)
+        .serialize(&publish_payload, false, DropPolicy::MustDeliver)
         .unwrap();
     let publish_results = session.handle_input(&publish_packet.bytes[..]).unwrap();
 
@@ -208,6 +255,7 @@ fn start_publishing(session: &mut ServerSession, serializer: &mut ChunkSerialize
                     stream_key: _,
                     mode: _,
                     request_id,
+                    stream_id: _,
                 } => {
                     session.accept_request(request_id).unwrap();
                 }
@@ -234,7 +282,9 @@ fn start_playing(session: &mut ServerSession, serializer: &mut ChunkSerializer)
     let play_payload = message
         .into_message_payload(RtmpTimestamp::new(0), 1)
         .unwrap();
-    let play_packet = serializer.serialize(&play_payload, false, false).unwrap();
+    let play_packet = serializer
+        .serialize(&play_payload, false, DropPolicy::MustDeliver)
+        .unwrap();
     let play_results = session.handle_input(&play_packet.bytes[..]).unwrap();
 
     for result in play_results {
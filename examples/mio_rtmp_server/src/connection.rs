@@ -1,6 +1,6 @@
 use mio::net::TcpStream;
 use mio::{Poll, PollOpt, Ready, Token};
-use rml_rtmp::chunk_io::Packet;
+use rml_rtmp::chunk_io::{DropPolicy, Packet};
 use rml_rtmp::handshake::{Handshake, HandshakeProcessResult, PeerType};
 use std::collections::VecDeque;
 use std::fs;
@@ -143,7 +143,13 @@ impl Connection {
             self.dropped_packet_count = 0;
         }
 
-        if packet.can_be_dropped && self.send_queue.len() > 10 {
+        let should_drop = match packet.drop_policy {
+            DropPolicy::MustDeliver => false,
+            DropPolicy::DropUnderLoad => self.send_queue.len() > 50,
+            DropPolicy::DropAggressively => self.send_queue.len() > 10,
+        };
+
+        if should_drop {
             self.dropped_packet_count += 1;
             Ok(())
         } else {
@@ -6,7 +6,11 @@ extern crate rml_rtmp;
 extern crate slab;
 
 mod connection;
+mod pull_retry;
 mod server;
+mod stats;
+#[cfg(feature = "stats-http")]
+mod stats_http;
 
 use clap::App;
 use mio::net::{TcpListener, TcpStream};
@@ -15,13 +19,19 @@ use slab::Slab;
 use std::collections::HashSet;
 use std::net::SocketAddr;
 use std::str::FromStr;
-use std::time::SystemTime;
+#[cfg(feature = "stats-http")]
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime};
 
 use connection::{Connection, ConnectionError, ReadResult};
+use pull_retry::{PullReconnector, PullRetryPolicy};
 use server::{Server, ServerResult};
 
 const SERVER: Token = Token(std::usize::MAX - 1);
 
+// How often the stats snapshot is refreshed
+const STATS_INTERVAL: Duration = Duration::from_secs(1);
+
 type ClosedTokens = HashSet<usize>;
 enum EventResult {
     None,
@@ -35,6 +45,7 @@ struct PullOptions {
     app: String,
     stream: String,
     target: String,
+    retry_policy: PullRetryPolicy,
 }
 
 #[derive(Debug)]
@@ -66,35 +77,32 @@ fn main() {
     let mut server = Server::new(&app_options.push);
     let mut connection_count = 1;
     let mut connections = Slab::new();
+    let mut pull_token = None;
+    let mut pull_reconnector = PullReconnector::new(match app_options.pull {
+        Some(ref pull) => pull.retry_policy.clone(),
+        None => PullRetryPolicy::new(),
+    });
 
     if let Some(ref pull) = app_options.pull {
-        println!(
-            "Starting pull client for rtmp://{}/{}/{}",
-            pull.host, pull.app, pull.stream
-        );
-
-        let mut pull_host = pull.host.clone();
-        if !pull_host.contains(":") {
-            pull_host = pull_host + ":1935";
-        }
-
-        let addr = SocketAddr::from_str(&pull_host).unwrap();
-        let stream = TcpStream::connect(&addr).unwrap();
-        let connection = Connection::new(stream, connection_count, app_options.log_io, false);
-        let token = connections.insert(connection);
-        connection_count += 1;
-
-        println!("Pull client started with connection id {}", token);
-        connections[token].token = Some(Token(token));
-        connections[token].register(&mut poll).unwrap();
-        server.register_pull_client(
-            token,
-            pull.app.clone(),
-            pull.stream.clone(),
-            pull.target.clone(),
+        pull_token = start_pull_client(
+            pull,
+            &mut server,
+            &mut connections,
+            &mut poll,
+            &app_options,
+            &mut connection_count,
+            &mut pull_reconnector,
         );
     }
 
+    #[cfg(feature = "stats-http")]
+    let latest_stats = {
+        let latest_stats = Arc::new(Mutex::new(None));
+        stats_http::start(latest_stats.clone());
+        latest_stats
+    };
+
+    let mut stats_published_at = Instant::now();
     let mut events = Events::with_capacity(1024);
     let mut outer_started_at = SystemTime::now();
     let mut inner_started_at;
@@ -102,7 +110,13 @@ fn main() {
     let mut poll_count = 0_u32;
 
     loop {
-        poll.poll(&mut events, None).unwrap();
+        let mut timeout = pull_reconnector.poll_timeout(Instant::now());
+        if cfg!(feature = "stats-http") {
+            // Wake up regularly so the served stats stay fresh while the server is idle
+            timeout = Some(timeout.map_or(STATS_INTERVAL, |t| t.min(STATS_INTERVAL)));
+        }
+
+        poll.poll(&mut events, timeout).unwrap();
 
         inner_started_at = SystemTime::now();
         poll_count += 1;
@@ -145,6 +159,10 @@ fn main() {
                                 }
 
                                 ReadResult::HandshakeCompleted { buffer, byte_count } => {
+                                    if pull_token == Some(token) {
+                                        pull_reconnector.connection_established();
+                                    }
+
                                     // Server will understand that the first call to
                                     // handle_read_bytes signifies that handshaking is completed
                                     connections_to_close = handle_read_bytes(
@@ -171,6 +189,40 @@ fn main() {
                 println!("Closing connection id {}", token);
                 connections.remove(token);
                 server.notify_connection_closed(token);
+
+                if pull_token == Some(token) {
+                    pull_token = None;
+                    match pull_reconnector.connection_closed(Instant::now()) {
+                        Some(delay) => println!(
+                            "Pull client disconnected, reconnecting in {} ms",
+                            delay.as_millis()
+                        ),
+                        None => println!("Pull client disconnected, no reconnect attempts remain"),
+                    }
+                }
+            }
+        }
+
+        if pull_reconnector.take_due_reconnect(Instant::now()) {
+            if let Some(ref pull) = app_options.pull {
+                pull_token = start_pull_client(
+                    pull,
+                    &mut server,
+                    &mut connections,
+                    &mut poll,
+                    &app_options,
+                    &mut connection_count,
+                    &mut pull_reconnector,
+                );
+            }
+        }
+
+        if stats_published_at.elapsed() >= STATS_INTERVAL {
+            stats_published_at = Instant::now();
+
+            #[cfg(feature = "stats-http")]
+            {
+                *latest_stats.lock().unwrap() = Some(server.snapshot());
             }
         }
 
@@ -190,6 +242,8 @@ fn main() {
                      seconds_since_start,
                      (total_ns / poll_count) / 1000);
 
+            println!("Server stats: {}", server.snapshot().to_json());
+
             // Reset so each notification is per that interval
             total_ns = 0;
             poll_count = 0;
@@ -210,6 +264,15 @@ fn get_app_options() -> AppOptions {
             app: pull_matches.value_of("app").unwrap().to_string(),
             stream: pull_matches.value_of("stream").unwrap().to_string(),
             target: pull_matches.value_of("target").unwrap().to_string(),
+            retry_policy: {
+                let mut policy = PullRetryPolicy::new();
+                policy.max_attempts = value_t!(pull_matches, "max-attempts", u32).ok();
+                if let Ok(delay) = value_t!(pull_matches, "retry-delay", u64) {
+                    policy.initial_delay_ms = delay;
+                }
+
+                policy
+            },
         }),
     };
 
@@ -233,6 +296,57 @@ fn get_app_options() -> AppOptions {
     app_options
 }
 
+/// Connects the pull client to the upstream server.  If the connection can't even be started a
+/// reconnect is scheduled, and `None` is returned.
+fn start_pull_client(
+    pull: &PullOptions,
+    server: &mut Server,
+    connections: &mut Slab<Connection>,
+    poll: &mut Poll,
+    app_options: &AppOptions,
+    connection_count: &mut usize,
+    pull_reconnector: &mut PullReconnector,
+) -> Option<usize> {
+    println!(
+        "Starting pull client for rtmp://{}/{}/{}",
+        pull.host, pull.app, pull.stream
+    );
+
+    let mut pull_host = pull.host.clone();
+    if !pull_host.contains(":") {
+        pull_host = pull_host + ":1935";
+    }
+
+    let addr = SocketAddr::from_str(&pull_host).unwrap();
+    let stream = match TcpStream::connect(&addr) {
+        Ok(stream) => stream,
+        Err(error) => {
+            println!("Pull client failed to connect: {}", error);
+            if let Some(delay) = pull_reconnector.connection_closed(Instant::now()) {
+                println!("Reconnecting in {} ms", delay.as_millis());
+            }
+
+            return None;
+        }
+    };
+
+    let connection = Connection::new(stream, *connection_count, app_options.log_io, false);
+    let token = connections.insert(connection);
+    *connection_count += 1;
+
+    println!("Pull client started with connection id {}", token);
+    connections[token].token = Some(Token(token));
+    connections[token].register(poll).unwrap();
+    server.register_pull_client(
+        token,
+        pull.app.clone(),
+        pull.stream.clone(),
+        pull.target.clone(),
+    );
+
+    Some(token)
+}
+
 fn process_event(
     event: &Ready,
     connections: &mut Slab<Connection>,
@@ -0,0 +1,180 @@
+use std::time::{Duration, Instant};
+
+/// Controls how the pull client reconnects after its connection to the upstream server is lost
+#[derive(Debug, Clone)]
+pub struct PullRetryPolicy {
+    /// How many reconnects to attempt in a row before giving up, or `None` to retry forever
+    pub max_attempts: Option<u32>,
+
+    /// How long to wait before the first reconnect attempt
+    pub initial_delay_ms: u64,
+
+    /// How much the delay is multiplied by for each consecutive failed attempt
+    pub backoff_factor: f32,
+
+    /// The longest to ever wait between reconnect attempts
+    pub max_delay_ms: u64,
+}
+
+impl PullRetryPolicy {
+    pub fn new() -> PullRetryPolicy {
+        PullRetryPolicy {
+            max_attempts: None,
+            initial_delay_ms: 1000,
+            backoff_factor: 2.0,
+            max_delay_ms: 30_000,
+        }
+    }
+
+    /// Returns how long to wait before the specified reconnect attempt (starting at 1), or
+    /// `None` if no attempts remain.
+    pub fn delay_for_attempt(&self, attempt: u32) -> Option<Duration> {
+        if let Some(max_attempts) = self.max_attempts {
+            if attempt > max_attempts {
+                return None;
+            }
+        }
+
+        let exponent = attempt.saturating_sub(1) as i32;
+        let delay = self.initial_delay_ms as f64 * (self.backoff_factor as f64).powi(exponent);
+        let delay = delay.min(self.max_delay_ms as f64) as u64;
+        Some(Duration::from_millis(delay))
+    }
+}
+
+impl Default for PullRetryPolicy {
+    fn default() -> Self {
+        PullRetryPolicy::new()
+    }
+}
+
+/// Keeps track of when the pull client should next be reconnected
+pub struct PullReconnector {
+    policy: PullRetryPolicy,
+    failed_attempts: u32,
+    reconnect_at: Option<Instant>,
+}
+
+impl PullReconnector {
+    pub fn new(policy: PullRetryPolicy) -> PullReconnector {
+        PullReconnector {
+            policy,
+            failed_attempts: 0,
+            reconnect_at: None,
+        }
+    }
+
+    /// Schedules a reconnect after the pull client's connection was closed.  Returns the delay
+    /// until the reconnect, or `None` if the policy has run out of attempts.
+    pub fn connection_closed(&mut self, now: Instant) -> Option<Duration> {
+        self.failed_attempts += 1;
+        let delay = self.policy.delay_for_attempt(self.failed_attempts);
+        self.reconnect_at = delay.map(|delay| now + delay);
+        delay
+    }
+
+    /// Resets the backoff once the pull client has successfully connected
+    pub fn connection_established(&mut self) {
+        self.failed_attempts = 0;
+    }
+
+    /// How long the event loop can wait for events before a reconnect is due
+    pub fn poll_timeout(&self, now: Instant) -> Option<Duration> {
+        self.reconnect_at
+            .map(|reconnect_at| reconnect_at.saturating_duration_since(now))
+    }
+
+    /// Returns true if a scheduled reconnect is due, in which case it is no longer scheduled
+    pub fn take_due_reconnect(&mut self, now: Instant) -> bool {
+        match self.reconnect_at {
+            Some(reconnect_at) if reconnect_at <= now => {
+                self.reconnect_at = None;
+                true
+            }
+
+            _ => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn policy() -> PullRetryPolicy {
+        PullRetryPolicy {
+            max_attempts: Some(4),
+            initial_delay_ms: 500,
+            backoff_factor: 3.0,
+            max_delay_ms: 10_000,
+        }
+    }
+
+    #[test]
+    fn delays_back_off_until_max_delay() {
+        let policy = policy();
+
+        assert_eq!(
+            policy.delay_for_attempt(1),
+            Some(Duration::from_millis(500))
+        );
+        assert_eq!(
+            policy.delay_for_attempt(2),
+            Some(Duration::from_millis(1500))
+        );
+        assert_eq!(
+            policy.delay_for_attempt(3),
+            Some(Duration::from_millis(4500))
+        );
+        assert_eq!(
+            policy.delay_for_attempt(4),
+            Some(Duration::from_millis(10_000))
+        );
+        assert_eq!(policy.delay_for_attempt(5), None);
+    }
+
+    #[test]
+    fn reconnect_is_due_after_delay_following_disconnect() {
+        let mut reconnector = PullReconnector::new(policy());
+        let disconnected_at = Instant::now();
+
+        assert_eq!(reconnector.poll_timeout(disconnected_at), None);
+        assert_eq!(
+            reconnector.connection_closed(disconnected_at),
+            Some(Duration::from_millis(500))
+        );
+
+        let before_delay = disconnected_at + Duration::from_millis(499);
+        assert_eq!(
+            reconnector.poll_timeout(before_delay),
+            Some(Duration::from_millis(1))
+        );
+        assert!(!reconnector.take_due_reconnect(before_delay));
+
+        let after_delay = disconnected_at + Duration::from_millis(500);
+        assert!(reconnector.take_due_reconnect(after_delay));
+        assert!(
+            !reconnector.take_due_reconnect(after_delay),
+            "Reconnect should only be due once"
+        );
+        assert_eq!(reconnector.poll_timeout(after_delay), None);
+    }
+
+    #[test]
+    fn gives_up_when_attempts_are_exhausted_and_resets_on_connect() {
+        let mut reconnector = PullReconnector::new(policy());
+        let now = Instant::now();
+
+        for _ in 0..4 {
+            assert!(reconnector.connection_closed(now).is_some());
+        }
+
+        assert_eq!(reconnector.connection_closed(now), None);
+
+        reconnector.connection_established();
+        assert_eq!(
+            reconnector.connection_closed(now),
+            Some(Duration::from_millis(500))
+        );
+    }
+}
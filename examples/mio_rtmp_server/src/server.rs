@@ -1,17 +1,20 @@
 use super::PushOptions;
 use bytes::Bytes;
-use rml_rtmp::chunk_io::Packet;
+use rml_rtmp::chunk_io::{DropPolicy, Packet};
+use rml_rtmp::codec_utils::{get_video_drop_policy, is_video_keyframe, is_video_sequence_header};
 use rml_rtmp::sessions::{
     ClientSession, ClientSessionConfig, ClientSessionEvent, ClientSessionResult,
 };
-use rml_rtmp::sessions::{PublishRequestType, StreamMetadata};
+use rml_rtmp::sessions::{PublishRequestType, RequestId, StreamMetadata};
 use rml_rtmp::sessions::{
     ServerSession, ServerSessionConfig, ServerSessionEvent, ServerSessionResult,
 };
 use rml_rtmp::time::RtmpTimestamp;
 use slab::Slab;
+use stats::{BitrateMeter, RtmpServerStats};
 use std::collections::{HashMap, HashSet};
 use std::rc::Rc;
+use std::time::{Instant, SystemTime};
 
 enum ReceivedDataType {
     Audio,
@@ -105,6 +108,10 @@ pub struct Server {
     channels: HashMap<String, MediaChannel>,
     pull_client: Option<PullClient>,
     push_client: Option<PushClient>,
+    bytes_received_total: u64,
+    bytes_sent_total: u64,
+    video_bitrate: BitrateMeter,
+    audio_bitrate: BitrateMeter,
 }
 
 impl Server {
@@ -127,6 +134,40 @@ impl Server {
             channels: HashMap::new(),
             pull_client: None,
             push_client,
+            bytes_received_total: 0,
+            bytes_sent_total: 0,
+            video_bitrate: BitrateMeter::new(Instant::now()),
+            audio_bitrate: BitrateMeter::new(Instant::now()),
+        }
+    }
+
+    /// Takes a snapshot of the server's current health
+    pub fn snapshot(&self) -> RtmpServerStats {
+        let now = Instant::now();
+        let pull_connections = self.pull_client.iter().count();
+        let push_connections = self
+            .push_client
+            .iter()
+            .filter(|client| client.connection_id.is_some())
+            .count();
+
+        RtmpServerStats {
+            timestamp: SystemTime::now(),
+            active_connections: self.clients.len() + pull_connections + push_connections,
+            active_publishers: self
+                .channels
+                .values()
+                .filter(|channel| channel.publishing_client_id.is_some())
+                .count(),
+            active_players: self
+                .channels
+                .values()
+                .map(|channel| channel.watching_client_ids.len())
+                .sum(),
+            bytes_received_total: self.bytes_received_total,
+            bytes_sent_total: self.bytes_sent_total,
+            current_video_bps: self.video_bitrate.bps(now),
+            current_audio_bps: self.audio_bitrate.bps(now),
         }
     }
 
@@ -137,17 +178,21 @@ impl Server {
         stream: String,
         target_stream: String,
     ) {
-        // Pre-create the target channel.
-        self.channels
+        // Pre-create the target channel.  It will already exist if the pull client is
+        // reconnecting, in which case it needs to point to the new connection.
+        let channel = self
+            .channels
             .entry(target_stream.clone())
             .or_insert(MediaChannel {
-                publishing_client_id: Some(connection_id),
+                publishing_client_id: None,
                 watching_client_ids: HashSet::new(),
                 metadata: None,
                 video_sequence_header: None,
                 audio_sequence_header: None,
             });
 
+        channel.publishing_client_id = Some(connection_id);
+
         self.pull_client = Some(PullClient {
             session: None,
             pull_app: app,
@@ -171,6 +216,7 @@ impl Server {
         bytes: &[u8],
     ) -> Result<Vec<ServerResult>, String> {
         let mut server_results = Vec::new();
+        self.bytes_received_total += bytes.len() as u64;
 
         let push_client_connection_id = self.push_client.as_ref().map_or(None, |c| {
             if let Some(connection_id) = c.connection_id {
@@ -292,6 +338,12 @@ impl Server {
             self.handle_server_session_results(connection_id, client_results, &mut server_results);
         }
 
+        for result in &server_results {
+            if let ServerResult::OutboundPacket { packet, .. } = result {
+                self.bytes_sent_total += packet.bytes.len() as u64;
+            }
+        }
+
         Ok(server_results)
     }
 
@@ -356,6 +408,7 @@ impl Server {
             ServerSessionEvent::ConnectionRequested {
                 request_id,
                 app_name,
+                ..
             } => {
                 self.handle_connection_requested(
                     executed_connection_id,
@@ -370,6 +423,7 @@ impl Server {
                 app_name,
                 stream_key,
                 mode: _,
+                stream_id: _,
             } => {
                 self.handle_publish_requested(
                     executed_connection_id,
@@ -447,7 +501,7 @@ impl Server {
     fn handle_connection_requested(
         &mut self,
         requested_connection_id: usize,
-        request_id: u32,
+        request_id: RequestId,
         app_name: String,
         server_results: &mut Vec<ServerResult>,
     ) {
@@ -487,7 +541,7 @@ impl Server {
     fn handle_publish_requested(
         &mut self,
         requested_connection_id: usize,
-        request_id: u32,
+        request_id: RequestId,
         app_name: String,
         stream_key: String,
         server_results: &mut Vec<ServerResult>,
@@ -568,7 +622,7 @@ impl Server {
     fn handle_play_requested(
         &mut self,
         requested_connection_id: usize,
-        request_id: u32,
+        request_id: RequestId,
         app_name: String,
         stream_key: String,
         stream_id: u32,
@@ -635,7 +689,7 @@ impl Server {
                                 stream_id,
                                 data.clone(),
                                 RtmpTimestamp::new(0),
-                                false,
+                                DropPolicy::MustDeliver,
                             ) {
                                 Ok(packet) => packet,
                                 Err(error) => {
@@ -662,7 +716,7 @@ impl Server {
                                 stream_id,
                                 data.clone(),
                                 RtmpTimestamp::new(0),
-                                false,
+                                DropPolicy::MustDeliver,
                             ) {
                                 Ok(packet) => packet,
                                 Err(error) => {
@@ -765,6 +819,11 @@ impl Server {
         data_type: ReceivedDataType,
         server_results: &mut Vec<ServerResult>,
     ) {
+        match data_type {
+            ReceivedDataType::Video => self.video_bitrate.record(Instant::now(), data.len()),
+            ReceivedDataType::Audio => self.audio_bitrate.record(Instant::now(), data.len()),
+        }
+
         {
             let channel = match self.channels.get_mut(&stream_key) {
                 Some(channel) => channel,
@@ -775,7 +834,7 @@ impl Server {
             // distributed to any late coming watchers
             match data_type {
                 ReceivedDataType::Video => {
-                    if is_video_sequence_header(data.clone()) {
+                    if is_video_sequence_header(&data) {
                         channel.video_sequence_header = Some(data.clone());
                     }
                 }
@@ -801,8 +860,7 @@ impl Server {
                 let should_send_to_client = match data_type {
                     ReceivedDataType::Video => {
                         client.has_received_video_keyframe
-                            || (is_video_sequence_header(data.clone())
-                                || is_video_keyframe(data.clone()))
+                            || (is_video_sequence_header(&data) || is_video_keyframe(&data))
                     }
 
                     ReceivedDataType::Audio => {
@@ -819,10 +877,10 @@ impl Server {
                         active_stream_id,
                         data.clone(),
                         timestamp.clone(),
-                        true,
+                        DropPolicy::DropUnderLoad,
                     ),
                     ReceivedDataType::Video => {
-                        if is_video_keyframe(data.clone()) {
+                        if is_video_keyframe(&data) {
                             client.has_received_video_keyframe = true;
                         }
 
@@ -830,7 +888,7 @@ impl Server {
                             active_stream_id,
                             data.clone(),
                             timestamp.clone(),
-                            true,
+                            get_video_drop_policy(&data),
                         )
                     }
                 };
@@ -859,17 +917,21 @@ impl Server {
             if let Some(ref mut client) = self.push_client {
                 if client.state == PushState::Pushing {
                     let result = match data_type {
-                        ReceivedDataType::Video => client
-                            .session
-                            .as_mut()
-                            .unwrap()
-                            .publish_video_data(data.clone(), timestamp.clone(), true),
-
-                        ReceivedDataType::Audio => client
-                            .session
-                            .as_mut()
-                            .unwrap()
-                            .publish_audio_data(data.clone(), timestamp.clone(), true),
+                        ReceivedDataType::Video => {
+                            client.session.as_mut().unwrap().publish_video_data(
+                                data.clone(),
+                                timestamp.clone(),
+                                get_video_drop_policy(&data),
+                            )
+                        }
+
+                        ReceivedDataType::Audio => {
+                            client.session.as_mut().unwrap().publish_audio_data(
+                                data.clone(),
+                                timestamp.clone(),
+                                DropPolicy::DropUnderLoad,
+                            )
+                        }
                     };
 
                     match result {
@@ -937,13 +999,13 @@ impl Server {
                     // initiate the connect to the RTMP app
                     client.state = PullState::Connecting;
 
-                    let result = client
+                    let results = client
                         .session
                         .as_mut()
                         .unwrap()
                         .request_connection(client.pull_app.clone())
                         .unwrap();
-                    new_results.push(result);
+                    new_results.extend(results);
                 }
 
                 _ => (),
@@ -1084,20 +1146,20 @@ impl Server {
                     // to initiate the connection process
                     client.state = PushState::Connecting;
 
-                    let result = match client
+                    let results = match client
                         .session
                         .as_mut()
                         .unwrap()
                         .request_connection(client.push_app.clone())
                     {
-                        Ok(result) => result,
+                        Ok(results) => results,
                         Err(error) => {
                             println!("Failed to request connection for push client: {:?}", error);
                             return;
                         }
                     };
 
-                    new_results.push(result);
+                    new_results.extend(results);
                 }
                 _ => (),
             }
@@ -1170,7 +1232,11 @@ impl Server {
                         .session
                         .as_mut()
                         .unwrap()
-                        .publish_video_data(bytes.clone(), RtmpTimestamp::new(0), false)
+                        .publish_video_data(
+                            bytes.clone(),
+                            RtmpTimestamp::new(0),
+                            DropPolicy::MustDeliver,
+                        )
                         .unwrap();
 
                     new_results.push(result);
@@ -1181,7 +1247,11 @@ impl Server {
                         .session
                         .as_mut()
                         .unwrap()
-                        .publish_audio_data(bytes.clone(), RtmpTimestamp::new(0), false)
+                        .publish_audio_data(
+                            bytes.clone(),
+                            RtmpTimestamp::new(0),
+                            DropPolicy::MustDeliver,
+                        )
                         .unwrap();
 
                     new_results.push(result);
@@ -1195,17 +1265,7 @@ impl Server {
     }
 }
 
-fn is_video_sequence_header(data: Bytes) -> bool {
-    // This is assuming h264.
-    return data.len() >= 2 && data[0] == 0x17 && data[1] == 0x00;
-}
-
 fn is_audio_sequence_header(data: Bytes) -> bool {
     // This is assuming aac
     return data.len() >= 2 && data[0] == 0xaf && data[1] == 0x00;
 }
-
-fn is_video_keyframe(data: Bytes) -> bool {
-    // assumings h264
-    return data.len() >= 2 && data[0] == 0x17 && data[1] != 0x00; // 0x00 is the sequence header, don't count that for now
-}
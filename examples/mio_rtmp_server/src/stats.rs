@@ -0,0 +1,133 @@
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+// How long the bytes of each bitrate measurement are gathered for
+const BITRATE_WINDOW: Duration = Duration::from_secs(1);
+
+/// A point in time snapshot of the server's health
+#[derive(Debug, Clone, PartialEq)]
+pub struct RtmpServerStats {
+    pub timestamp: SystemTime,
+    pub active_connections: usize,
+    pub active_publishers: usize,
+    pub active_players: usize,
+    pub bytes_received_total: u64,
+    pub bytes_sent_total: u64,
+    pub current_video_bps: u64,
+    pub current_audio_bps: u64,
+}
+
+impl RtmpServerStats {
+    /// Formats the stats as a JSON object, with the timestamp in milliseconds since the epoch
+    pub fn to_json(&self) -> String {
+        let timestamp = self
+            .timestamp
+            .duration_since(UNIX_EPOCH)
+            .map(|duration| duration.as_millis())
+            .unwrap_or(0);
+
+        format!(
+            "{{\"timestamp\":{},\"active_connections\":{},\"active_publishers\":{},\
+             \"active_players\":{},\"bytes_received_total\":{},\"bytes_sent_total\":{},\
+             \"current_video_bps\":{},\"current_audio_bps\":{}}}",
+            timestamp,
+            self.active_connections,
+            self.active_publishers,
+            self.active_players,
+            self.bytes_received_total,
+            self.bytes_sent_total,
+            self.current_video_bps,
+            self.current_audio_bps
+        )
+    }
+}
+
+/// Measures the bitrate of the bytes recorded during the most recently completed window
+pub struct BitrateMeter {
+    window_started_at: Instant,
+    window_bytes: u64,
+    last_bps: u64,
+}
+
+impl BitrateMeter {
+    pub fn new(now: Instant) -> BitrateMeter {
+        BitrateMeter {
+            window_started_at: now,
+            window_bytes: 0,
+            last_bps: 0,
+        }
+    }
+
+    pub fn record(&mut self, now: Instant, byte_count: usize) {
+        let elapsed = now.saturating_duration_since(self.window_started_at);
+        if elapsed >= BITRATE_WINDOW {
+            self.last_bps = if elapsed < BITRATE_WINDOW * 2 {
+                self.window_bytes * 8 * 1000 / elapsed.as_millis() as u64
+            } else {
+                // Nothing was recorded for a whole window, so the bytes were from a burst
+                // that's no longer representative of the current bitrate
+                0
+            };
+
+            self.window_started_at = now;
+            self.window_bytes = 0;
+        }
+
+        self.window_bytes += byte_count as u64;
+    }
+
+    /// Returns the bitrate in bits per second, which drops to zero if nothing has been
+    /// recorded for more than a full window.
+    pub fn bps(&self, now: Instant) -> u64 {
+        if now.saturating_duration_since(self.window_started_at) >= BITRATE_WINDOW * 2 {
+            0
+        } else {
+            self.last_bps
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bitrate_is_measured_over_completed_window() {
+        let start = Instant::now();
+        let mut meter = BitrateMeter::new(start);
+
+        meter.record(start, 1000);
+        meter.record(start + Duration::from_millis(500), 1500);
+        assert_eq!(meter.bps(start + Duration::from_millis(500)), 0);
+
+        meter.record(start + Duration::from_millis(1000), 100);
+        assert_eq!(meter.bps(start + Duration::from_millis(1000)), 20_000);
+        assert_eq!(meter.bps(start + Duration::from_millis(3000)), 0);
+    }
+
+    #[test]
+    fn stats_can_be_shared_across_threads() {
+        fn assert_send_sync<T: Send + Sync>() {}
+        assert_send_sync::<RtmpServerStats>();
+    }
+
+    #[test]
+    fn stats_are_formatted_as_json() {
+        let stats = RtmpServerStats {
+            timestamp: UNIX_EPOCH + Duration::from_millis(1500),
+            active_connections: 3,
+            active_publishers: 1,
+            active_players: 2,
+            bytes_received_total: 100,
+            bytes_sent_total: 200,
+            current_video_bps: 3000,
+            current_audio_bps: 128,
+        };
+
+        assert_eq!(
+            stats.to_json(),
+            "{\"timestamp\":1500,\"active_connections\":3,\"active_publishers\":1,\
+             \"active_players\":2,\"bytes_received_total\":100,\"bytes_sent_total\":200,\
+             \"current_video_bps\":3000,\"current_audio_bps\":128}"
+        );
+    }
+}
@@ -0,0 +1,52 @@
+use std::io::{Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+use stats::RtmpServerStats;
+
+/// The port the stats are served on
+pub const STATS_PORT: u16 = 8088;
+
+/// Serves the most recent stats snapshot as JSON to any HTTP request, on a background thread.
+/// The event loop is responsible for keeping the shared snapshot up to date.
+pub fn start(latest_stats: Arc<Mutex<Option<RtmpServerStats>>>) {
+    let listener = TcpListener::bind(("0.0.0.0", STATS_PORT)).unwrap();
+    println!("Serving stats on http://0.0.0.0:{}", STATS_PORT);
+
+    thread::spawn(move || {
+        for stream in listener.incoming() {
+            match stream {
+                Ok(stream) => {
+                    if let Err(error) = respond(stream, &latest_stats) {
+                        println!("Failed to respond to stats request: {}", error);
+                    }
+                }
+
+                Err(error) => println!("Failed to accept stats connection: {}", error),
+            }
+        }
+    });
+}
+
+fn respond(
+    mut stream: TcpStream,
+    latest_stats: &Mutex<Option<RtmpServerStats>>,
+) -> std::io::Result<()> {
+    // The request itself doesn't matter, since every path gets the stats
+    let mut buffer = [0_u8; 1024];
+    let _ = stream.read(&mut buffer)?;
+
+    let body = match *latest_stats.lock().unwrap() {
+        Some(ref stats) => stats.to_json(),
+        None => "{}".to_string(),
+    };
+
+    write!(
+        stream,
+        "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\
+         Connection: close\r\n\r\n{}",
+        body.len(),
+        body
+    )
+}
@@ -0,0 +1,78 @@
+use std::net::SocketAddr;
+use std::time::Instant;
+
+/// Information about a connection, for monitoring who is connected to the server
+#[derive(Debug, Clone)]
+pub struct ConnectionMetadata {
+    pub remote_addr: SocketAddr,
+    pub connected_at: Instant,
+
+    /// The client's `flashVer` connect property, which identifies the client software
+    pub flash_version: Option<String>,
+
+    /// The application the client connected to
+    pub app_name: Option<String>,
+}
+
+impl ConnectionMetadata {
+    pub fn new(remote_addr: SocketAddr) -> ConnectionMetadata {
+        ConnectionMetadata {
+            remote_addr,
+            connected_at: Instant::now(),
+            flash_version: None,
+            app_name: None,
+        }
+    }
+
+    /// Formats the metadata as a JSON object.  Since an `Instant` has no meaning outside of this
+    /// process, the connection time is given as the number of seconds the client has been
+    /// connected for.
+    pub fn to_json(&self, connection_id: usize) -> String {
+        format!(
+            "{{\"id\":{},\"remote_addr\":\"{}\",\"connected_secs\":{},\"flash_version\":{},\"app_name\":{}}}",
+            connection_id,
+            self.remote_addr,
+            self.connected_at.elapsed().as_secs(),
+            json_string(&self.flash_version),
+            json_string(&self.app_name)
+        )
+    }
+}
+
+fn json_string(value: &Option<String>) -> String {
+    let value = match value {
+        Some(value) => value,
+        None => return "null".to_string(),
+    };
+
+    let mut result = String::with_capacity(value.len() + 2);
+    result.push('"');
+    for character in value.chars() {
+        match character {
+            '"' => result.push_str("\\\""),
+            '\\' => result.push_str("\\\\"),
+            x if x.is_control() => result.push_str(&format!("\\u{:04x}", x as u32)),
+            x => result.push(x),
+        }
+    }
+
+    result.push('"');
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn metadata_is_formatted_as_json() {
+        let mut metadata = ConnectionMetadata::new("127.0.0.1:5000".parse().unwrap());
+        metadata.flash_version = Some("FMLE/3.0 (compatible; \"test\")".to_string());
+
+        assert_eq!(
+            metadata.to_json(3),
+            "{\"id\":3,\"remote_addr\":\"127.0.0.1:5000\",\"connected_secs\":0,\
+             \"flash_version\":\"FMLE/3.0 (compatible; \\\"test\\\")\",\"app_name\":null}"
+        );
+    }
+}
@@ -3,21 +3,49 @@ extern crate rml_rtmp;
 extern crate slab;
 
 mod connection;
+mod connection_metadata;
 mod server;
+mod stats_http;
 
 use connection::{Connection, ConnectionError, ReadResult};
 use server::{Server, ServerResult};
 use slab::Slab;
+use std::env;
 use std::net::{TcpListener, TcpStream};
-use std::sync::mpsc::{channel, Receiver, TryRecvError};
+use std::sync::mpsc::{channel, Receiver, Sender, TryRecvError};
 use std::thread;
+use std::time::Duration;
+
+const DEFAULT_STREAM_TIMEOUT_SECS: u64 = 30;
+const WATCHDOG_INTERVAL: Duration = Duration::from_secs(5);
+const STATS_ADDRESS: &str = "0.0.0.0:8088";
 
 fn main() {
+    let stream_timeout = Duration::from_secs(get_stream_timeout_secs());
     let address = "0.0.0.0:1935";
     let listener = TcpListener::bind(&address).unwrap();
 
     let (stream_sender, stream_receiver) = channel();
-    thread::spawn(|| handle_connections(stream_receiver));
+    let (watchdog_sender, watchdog_receiver) = channel();
+    let (stats_sender, stats_receiver) = channel();
+    thread::spawn(move || {
+        handle_connections(
+            stream_receiver,
+            watchdog_receiver,
+            stats_receiver,
+            stream_timeout,
+        )
+    });
+
+    stats_http::start(STATS_ADDRESS, stats_sender);
+
+    // Periodically tell the connection handler to look for publishers that have gone silent
+    thread::spawn(move || loop {
+        thread::sleep(WATCHDOG_INTERVAL);
+        if watchdog_sender.send(()).is_err() {
+            break;
+        }
+    });
 
     println!("Listening for connections on {}", address);
     for stream in listener.incoming() {
@@ -29,9 +57,29 @@ fn main() {
     }
 }
 
-fn handle_connections(connection_receiver: Receiver<TcpStream>) {
+fn get_stream_timeout_secs() -> u64 {
+    let args: Vec<String> = env::args().collect();
+    match args.iter().position(|arg| arg == "--stream-timeout-secs") {
+        None => DEFAULT_STREAM_TIMEOUT_SECS,
+        Some(index) => match args.get(index + 1).map(|value| value.parse()) {
+            Some(Ok(value)) => value,
+            _ => panic!("--stream-timeout-secs requires a number of seconds"),
+        },
+    }
+}
+
+fn handle_connections(
+    connection_receiver: Receiver<TcpStream>,
+    watchdog_receiver: Receiver<()>,
+    stats_receiver: Receiver<Sender<String>>,
+    stream_timeout: Duration,
+) {
     let mut connections = Slab::new();
-    let mut server = Server::new();
+    let mut server = Server::new(stream_timeout);
+    println!(
+        "Publishers will be disconnected after {} seconds without data",
+        server.stream_timeout().as_secs()
+    );
 
     loop {
         match connection_receiver.try_recv() {
@@ -40,14 +88,36 @@ fn handle_connections(connection_receiver: Receiver<TcpStream>) {
             Ok(stream) => {
                 let entry = connections.vacant_entry();
                 let connection_id = entry.key();
+                if let Ok(remote_addr) = stream.peer_addr() {
+                    server.register_connection(connection_id, remote_addr);
+                }
+
                 entry.insert(Connection::new(connection_id, stream));
 
                 println!("Connection {connection_id} started");
             }
         }
 
+        if let Ok(json_sender) = stats_receiver.try_recv() {
+            let connections = server
+                .list_connections()
+                .into_iter()
+                .map(|(id, metadata)| metadata.to_json(id))
+                .collect::<Vec<_>>();
+
+            let _ = json_sender.send(format!("[{}]", connections.join(",")));
+        }
+
         let mut ids_to_clear = Vec::new();
         let mut packets_to_write = Vec::new();
+        if watchdog_receiver.try_recv().is_ok() {
+            for result in server.check_publisher_health() {
+                if let ServerResult::DisconnectConnection { connection_id } = result {
+                    ids_to_clear.push(connection_id);
+                }
+            }
+        }
+
         for (connection_id, connection) in connections.iter_mut() {
             match connection.read() {
                 Err(ConnectionError::SocketClosed) => {
@@ -103,8 +173,19 @@ fn handle_connections(connection_receiver: Receiver<TcpStream>) {
             connection.write(packet.bytes);
         }
 
+        ids_to_clear.sort();
+        ids_to_clear.dedup();
         for closed_id in ids_to_clear {
-            println!("Connection {} closed", closed_id);
+            match server.connection_metadata(closed_id) {
+                Some(metadata) => println!(
+                    "Connection {} from {} closed after {} seconds",
+                    closed_id,
+                    metadata.remote_addr,
+                    metadata.connected_at.elapsed().as_secs()
+                ),
+                None => println!("Connection {} closed", closed_id),
+            }
+
             connections.remove(closed_id);
             server.notify_connection_closed(closed_id);
         }
@@ -1,13 +1,17 @@
 use bytes::Bytes;
-use rml_rtmp::chunk_io::Packet;
-use rml_rtmp::sessions::StreamMetadata;
+use connection_metadata::ConnectionMetadata;
+use rml_rtmp::chunk_io::{DropPolicy, Packet};
+use rml_rtmp::codec_utils::{get_video_drop_policy, is_video_keyframe, is_video_sequence_header};
+use rml_rtmp::sessions::{RequestId, StreamMetadata};
 use rml_rtmp::sessions::{
     ServerSession, ServerSessionConfig, ServerSessionEvent, ServerSessionResult,
 };
 use rml_rtmp::time::RtmpTimestamp;
 use slab::Slab;
 use std::collections::{HashMap, HashSet};
+use std::net::SocketAddr;
 use std::rc::Rc;
+use std::time::{Duration, Instant};
 
 enum ClientAction {
     Waiting,
@@ -25,6 +29,7 @@ struct Client {
     current_action: ClientAction,
     connection_id: usize,
     has_received_video_keyframe: bool,
+    last_data_at: Instant,
 }
 
 impl Client {
@@ -63,17 +68,72 @@ pub struct Server {
     clients: Slab<Client>,
     connection_to_client_map: HashMap<usize, usize>,
     channels: HashMap<String, MediaChannel>,
+    connection_metadata: HashMap<usize, ConnectionMetadata>,
+    stream_timeout: Duration,
 }
 
 impl Server {
-    pub fn new() -> Server {
+    /// Creates a new server.  Publishers that have not sent any audio or video data within the
+    /// stream timeout will be disconnected by `check_publisher_health()`.
+    pub fn new(stream_timeout: Duration) -> Server {
         Server {
             clients: Slab::with_capacity(1024),
             connection_to_client_map: HashMap::with_capacity(1024),
             channels: HashMap::new(),
+            connection_metadata: HashMap::new(),
+            stream_timeout,
         }
     }
 
+    /// Starts tracking the metadata of a newly accepted connection
+    pub fn register_connection(&mut self, connection_id: usize, remote_addr: SocketAddr) {
+        self.connection_metadata
+            .insert(connection_id, ConnectionMetadata::new(remote_addr));
+    }
+
+    pub fn connection_metadata(&self, connection_id: usize) -> Option<&ConnectionMetadata> {
+        self.connection_metadata.get(&connection_id)
+    }
+
+    /// Lists the metadata of every connection, ordered by connection id
+    pub fn list_connections(&self) -> Vec<(usize, &ConnectionMetadata)> {
+        let mut connections = self
+            .connection_metadata
+            .iter()
+            .map(|(id, metadata)| (*id, metadata))
+            .collect::<Vec<_>>();
+
+        connections.sort_by_key(|(id, _)| *id);
+        connections
+    }
+
+    pub fn stream_timeout(&self) -> Duration {
+        self.stream_timeout
+    }
+
+    /// Finds any publishers that have gone silent for longer than the stream timeout (e.g. due
+    /// to a network drop that never closed the socket) and requests they be disconnected.
+    pub fn check_publisher_health(&self) -> Vec<ServerResult> {
+        let mut results = Vec::new();
+        for (_, client) in self.clients.iter() {
+            if let ClientAction::Publishing(ref stream_key) = client.current_action {
+                if client.last_data_at.elapsed() > self.stream_timeout {
+                    println!(
+                        "Publisher on stream key '{}' has not sent data in over {} seconds",
+                        stream_key,
+                        self.stream_timeout.as_secs()
+                    );
+
+                    results.push(ServerResult::DisconnectConnection {
+                        connection_id: client.connection_id,
+                    });
+                }
+            }
+        }
+
+        results
+    }
+
     pub fn bytes_received(
         &mut self,
         connection_id: usize,
@@ -98,6 +158,7 @@ impl Server {
                 connection_id,
                 current_action: ClientAction::Waiting,
                 has_received_video_keyframe: false,
+                last_data_at: Instant::now(),
             };
 
             let client_id = Some(self.clients.insert(client));
@@ -120,6 +181,7 @@ impl Server {
     }
 
     pub fn notify_connection_closed(&mut self, connection_id: usize) {
+        self.connection_metadata.remove(&connection_id);
         match self.connection_to_client_map.remove(&connection_id) {
             None => (),
             Some(client_id) => {
@@ -170,6 +232,7 @@ impl Server {
             ServerSessionEvent::ConnectionRequested {
                 request_id,
                 app_name,
+                ..
             } => {
                 self.handle_connection_requested(
                     executed_connection_id,
@@ -184,6 +247,7 @@ impl Server {
                 app_name,
                 stream_key,
                 mode: _,
+                stream_id: _,
             } => {
                 self.handle_publish_requested(
                     executed_connection_id,
@@ -234,6 +298,7 @@ impl Server {
                 data,
                 timestamp,
             } => {
+                self.record_data_received(executed_connection_id);
                 self.handle_audio_video_data_received(
                     stream_key,
                     timestamp,
@@ -249,6 +314,7 @@ impl Server {
                 data,
                 timestamp,
             } => {
+                self.record_data_received(executed_connection_id);
                 self.handle_audio_video_data_received(
                     stream_key,
                     timestamp,
@@ -268,7 +334,7 @@ impl Server {
     fn handle_connection_requested(
         &mut self,
         requested_connection_id: usize,
-        request_id: u32,
+        request_id: RequestId,
         app_name: String,
         server_results: &mut Vec<ServerResult>,
     ) {
@@ -285,6 +351,17 @@ impl Server {
                 .unwrap();
             let client = self.clients.get_mut(*client_id).unwrap();
             accept_result = client.session.accept_request(request_id);
+
+            // The connect properties are only available once the request has been accepted
+            if let Some(metadata) = self.connection_metadata.get_mut(&requested_connection_id) {
+                metadata.app_name = Some(app_name);
+                metadata.flash_version = client
+                    .session
+                    .connect_properties()
+                    .and_then(|properties| properties.get("flashVer"))
+                    .and_then(|value| value.as_str())
+                    .map(|value| value.to_string());
+            }
         }
 
         match accept_result {
@@ -304,7 +381,7 @@ impl Server {
     fn handle_publish_requested(
         &mut self,
         requested_connection_id: usize,
-        request_id: u32,
+        request_id: RequestId,
         app_name: String,
         stream_key: String,
         server_results: &mut Vec<ServerResult>,
@@ -336,6 +413,7 @@ impl Server {
                 .unwrap();
             let client = self.clients.get_mut(*client_id).unwrap();
             client.current_action = ClientAction::Publishing(stream_key.clone());
+            client.last_data_at = Instant::now();
 
             let channel = self.channels.entry(stream_key).or_insert(MediaChannel {
                 publishing_client_id: None,
@@ -412,7 +490,7 @@ impl Server {
     fn handle_play_requested(
         &mut self,
         requested_connection_id: usize,
-        request_id: u32,
+        request_id: RequestId,
         app_name: String,
         stream_key: String,
         stream_id: u32,
@@ -479,7 +557,7 @@ impl Server {
                                 stream_id,
                                 data.clone(),
                                 RtmpTimestamp::new(0),
-                                false,
+                                DropPolicy::MustDeliver,
                             ) {
                                 Ok(packet) => packet,
                                 Err(error) => {
@@ -506,7 +584,7 @@ impl Server {
                                 stream_id,
                                 data.clone(),
                                 RtmpTimestamp::new(0),
-                                false,
+                                DropPolicy::MustDeliver,
                             ) {
                                 Ok(packet) => packet,
                                 Err(error) => {
@@ -597,6 +675,14 @@ impl Server {
         }
     }
 
+    fn record_data_received(&mut self, connection_id: usize) {
+        if let Some(client_id) = self.connection_to_client_map.get(&connection_id) {
+            if let Some(client) = self.clients.get_mut(*client_id) {
+                client.last_data_at = Instant::now();
+            }
+        }
+    }
+
     fn handle_audio_video_data_received(
         &mut self,
         stream_key: String,
@@ -614,7 +700,7 @@ impl Server {
         // distributed to any late coming watchers
         match data_type {
             ReceivedDataType::Video => {
-                if is_video_sequence_header(data.clone()) {
+                if is_video_sequence_header(&data) {
                     channel.video_sequence_header = Some(data.clone());
                 }
             }
@@ -640,8 +726,8 @@ impl Server {
             let should_send_to_client = match data_type {
                 ReceivedDataType::Video => {
                     client.has_received_video_keyframe
-                        || is_video_sequence_header(data.clone())
-                        || is_video_keyframe(data.clone())
+                        || is_video_sequence_header(&data)
+                        || is_video_keyframe(&data)
                 }
 
                 ReceivedDataType::Audio => {
@@ -658,10 +744,10 @@ impl Server {
                     active_stream_id,
                     data.clone(),
                     timestamp.clone(),
-                    true,
+                    DropPolicy::DropUnderLoad,
                 ),
                 ReceivedDataType::Video => {
-                    if is_video_keyframe(data.clone()) {
+                    if is_video_keyframe(&data) {
                         client.has_received_video_keyframe = true;
                     }
 
@@ -669,7 +755,7 @@ impl Server {
                         active_stream_id,
                         data.clone(),
                         timestamp.clone(),
-                        true,
+                        get_video_drop_policy(&data),
                     )
                 }
             };
@@ -713,17 +799,7 @@ impl Server {
     }
 }
 
-fn is_video_sequence_header(data: Bytes) -> bool {
-    // This is assuming h264.
-    return data.len() >= 2 && data[0] == 0x17 && data[1] == 0x00;
-}
-
 fn is_audio_sequence_header(data: Bytes) -> bool {
     // This is assuming aac
     return data.len() >= 2 && data[0] == 0xaf && data[1] == 0x00;
 }
-
-fn is_video_keyframe(data: Bytes) -> bool {
-    // assumings h264
-    return data.len() >= 2 && data[0] == 0x17 && data[1] != 0x00; // 0x00 is the sequence header, don't count that for now
-}
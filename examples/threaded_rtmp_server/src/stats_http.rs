@@ -0,0 +1,56 @@
+use std::io::{Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::mpsc::{channel, Sender};
+use std::thread;
+use std::time::Duration;
+
+const RESPONSE_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Serves the list of connections as JSON to any HTTP request.  Since the server is owned by the
+/// connection handling thread, each request sends that thread a channel to reply to with the
+/// current JSON.
+pub fn start(address: &str, json_requester: Sender<Sender<String>>) {
+    let listener = TcpListener::bind(address).unwrap();
+    println!("Serving connection stats on http://{}", address);
+
+    thread::spawn(move || {
+        for stream in listener.incoming() {
+            let stream = match stream {
+                Ok(stream) => stream,
+                Err(error) => {
+                    println!("Failed to accept stats connection: {:?}", error);
+                    continue;
+                }
+            };
+
+            let (json_sender, json_receiver) = channel();
+            if json_requester.send(json_sender).is_err() {
+                // The connection handler has shut down
+                break;
+            }
+
+            let body = match json_receiver.recv_timeout(RESPONSE_TIMEOUT) {
+                Ok(body) => body,
+                Err(_) => continue,
+            };
+
+            if let Err(error) = respond(stream, &body) {
+                println!("Failed to respond to stats request: {:?}", error);
+            }
+        }
+    });
+}
+
+fn respond(mut stream: TcpStream, body: &str) -> std::io::Result<()> {
+    // The request itself doesn't matter, since every path gets the stats
+    let mut buffer = [0_u8; 1024];
+    let _ = stream.read(&mut buffer)?;
+
+    write!(
+        stream,
+        "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\
+         Connection: close\r\n\r\n{}",
+        body.len(),
+        body
+    )
+}
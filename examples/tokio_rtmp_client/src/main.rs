@@ -0,0 +1,74 @@
+use rml_rtmp::url::{RtmpScheme, RtmpUrl};
+use std::env;
+use std::process;
+use tokio::time::Duration;
+
+mod player;
+
+use crate::player::PlayError;
+
+const DEFAULT_URL: &str = "rtmp://localhost:1935/live/stream_key";
+const PLAYBACK_DURATION: Duration = Duration::from_secs(10);
+
+const EXIT_FAILED: i32 = 1;
+const EXIT_CONNECTION_REJECTED: i32 = 2;
+const EXIT_STREAM_NOT_FOUND: i32 = 3;
+
+#[tokio::main]
+async fn main() {
+    let url = match parse_url() {
+        Ok(url) => url,
+        Err(error) => {
+            eprintln!("{}", error);
+            eprintln!("Usage: tokio_rtmp_client [--url rtmp://host[:port]/app/stream_key]");
+            process::exit(EXIT_FAILED);
+        }
+    };
+
+    let exit_code = match player::play(&url, PLAYBACK_DURATION).await {
+        Ok(stats) => {
+            println!(
+                "Disconnected after receiving {} video and {} audio frames",
+                stats.video_frames, stats.audio_frames
+            );
+
+            0
+        }
+
+        Err(PlayError::ConnectionRejected(description)) => {
+            eprintln!("The server rejected the connection: {}", description);
+            EXIT_CONNECTION_REJECTED
+        }
+
+        Err(PlayError::StreamNotFound(description)) => {
+            eprintln!("Stream '{}' was not found: {}", url.stream_key, description);
+            EXIT_STREAM_NOT_FOUND
+        }
+
+        Err(PlayError::Failed(error)) => {
+            eprintln!("Playback failed: {}", error);
+            EXIT_FAILED
+        }
+    };
+
+    process::exit(exit_code);
+}
+
+fn parse_url() -> Result<RtmpUrl, String> {
+    let args: Vec<String> = env::args().collect();
+    let url = match args.iter().position(|arg| arg == "--url") {
+        Some(index) => match args.get(index + 1) {
+            Some(value) => value.clone(),
+            None => return Err("--url requires a value".to_string()),
+        },
+
+        None => DEFAULT_URL.to_string(),
+    };
+
+    let url = RtmpUrl::parse(&url).map_err(|error| error.to_string())?;
+    if url.scheme != RtmpScheme::Rtmp {
+        return Err("Only rtmp:// urls are supported by this example".to_string());
+    }
+
+    Ok(url)
+}
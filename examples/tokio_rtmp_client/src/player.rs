@@ -0,0 +1,240 @@
+use bytes::Bytes;
+use rml_rtmp::chunk_io::Packet;
+use rml_rtmp::handshake::AsyncHandshake;
+use rml_rtmp::sessions::{
+    ClientSession, ClientSessionConfig, ClientSessionEvent, ClientSessionResult,
+};
+use rml_rtmp::url::RtmpUrl;
+use std::error::Error;
+use tokio::io::{AsyncReadExt, AsyncWriteExt, ReadHalf, WriteHalf};
+use tokio::net::TcpStream;
+use tokio::sync::mpsc;
+use tokio::time::{self, Duration, Instant};
+
+type BoxError = Box<dyn Error + Sync + Send>;
+
+const STATS_INTERVAL: Duration = Duration::from_secs(1);
+
+/// The reasons playback can end early.  The server turning the client away is split out from
+/// other failures so callers can react to each differently.
+pub enum PlayError {
+    /// The server rejected the connection request to the url's application
+    ConnectionRejected(String),
+
+    /// The server does not have a stream for the url's stream key
+    StreamNotFound(String),
+
+    /// The connection failed, or the server ended playback for another reason
+    Failed(BoxError),
+}
+
+impl<E: Error + Sync + Send + 'static> From<E> for PlayError {
+    fn from(error: E) -> Self {
+        PlayError::Failed(Box::new(error))
+    }
+}
+
+/// How much media has been received from the server
+#[derive(Default, Clone, Copy)]
+pub struct PlaybackStats {
+    pub video_frames: u64,
+    pub audio_frames: u64,
+}
+
+/// A single connection to the RTMP server that a stream is being played from.  Bytes read from
+/// the socket arrive on `incoming`, and packets to send are queued on `outgoing`, so the session
+/// never waits on the socket directly.
+struct Player {
+    session: ClientSession,
+    outgoing: mpsc::UnboundedSender<Packet>,
+    incoming: mpsc::UnboundedReceiver<Bytes>,
+    stats: PlaybackStats,
+}
+
+/// Connects to the server, plays the url's stream for the specified duration while printing
+/// statistics every second, then disconnects.
+pub async fn play(url: &RtmpUrl, duration: Duration) -> Result<PlaybackStats, PlayError> {
+    let mut stream = TcpStream::connect((url.host.as_str(), url.port)).await?;
+    let remaining_bytes = AsyncHandshake::perform_client_handshake(&mut stream)
+        .await
+        .map_err(|x| PlayError::Failed(format!("Handshake failed: {:?}", x).into()))?;
+
+    let (reader, writer) = tokio::io::split(stream);
+    let (incoming_sender, incoming) = mpsc::unbounded_channel();
+    let (outgoing, outgoing_receiver) = mpsc::unbounded_channel();
+    tokio::spawn(read_socket(reader, incoming_sender));
+    let writer_task = tokio::spawn(write_socket(writer, outgoing_receiver));
+
+    let mut config = ClientSessionConfig::new();
+    config.tc_url = Some(url.tc_url());
+
+    let (session, results) = ClientSession::new(config)?;
+    let mut player = Player {
+        session,
+        outgoing,
+        incoming,
+        stats: PlaybackStats::default(),
+    };
+
+    player.handle_results(results)?;
+    let results = player.session.handle_input(&remaining_bytes)?;
+    player.handle_results(results)?;
+
+    let results = player.session.request_connection(url.app.clone())?;
+    player.handle_results(results)?;
+    player
+        .wait_for_event(|event| matches!(event, ClientSessionEvent::ConnectionRequestAccepted))
+        .await?;
+
+    println!("Connected to app '{}'", url.app);
+
+    let result = player.session.request_playback(url.stream_key.clone())?;
+    player.handle_results(vec![result])?;
+    player
+        .wait_for_event(|event| matches!(event, ClientSessionEvent::PlaybackRequestAccepted))
+        .await?;
+
+    println!("Playing stream key '{}'", url.stream_key);
+    player.receive_media(Instant::now() + duration).await?;
+
+    let results = player.session.stop_playback()?;
+    player.handle_results(results)?;
+    let stats = player.stats;
+
+    // Dropping the player closes the outgoing channel, which lets the writer finish sending
+    // everything that was queued and close the socket.
+    drop(player);
+    writer_task
+        .await
+        .map_err(|error| PlayError::Failed(Box::new(error)))??;
+
+    Ok(stats)
+}
+
+/// Forwards everything read from the socket to the player.  The channel is closed when the
+/// socket is, which the player sees as `None` on its receiver.
+async fn read_socket(mut reader: ReadHalf<TcpStream>, sender: mpsc::UnboundedSender<Bytes>) {
+    let mut buffer = [0; 4096];
+    loop {
+        match reader.read(&mut buffer).await {
+            Ok(0) | Err(_) => break,
+            Ok(bytes_read) => {
+                let bytes = Bytes::copy_from_slice(&buffer[..bytes_read]);
+                if sender.send(bytes).is_err() {
+                    break;
+                }
+            }
+        }
+    }
+}
+
+/// Writes every packet queued by the player to the socket, shutting the socket down once the
+/// player has closed the channel.
+async fn write_socket(
+    mut writer: WriteHalf<TcpStream>,
+    mut receiver: mpsc::UnboundedReceiver<Packet>,
+) -> Result<(), std::io::Error> {
+    while let Some(packet) = receiver.recv().await {
+        writer.write_all(&packet.bytes).await?;
+    }
+
+    writer.shutdown().await
+}
+
+impl Player {
+    /// Counts the media received from the server until the deadline is reached, printing the
+    /// totals and the rate they are arriving at every second.
+    async fn receive_media(&mut self, deadline: Instant) -> Result<(), PlayError> {
+        let mut stats_timer = time::interval_at(Instant::now() + STATS_INTERVAL, STATS_INTERVAL);
+        let mut last_stats = self.stats;
+        loop {
+            tokio::select! {
+                _ = time::sleep_until(deadline) => return Ok(()),
+                _ = stats_timer.tick() => {
+                    println!(
+                        "Video frames: {} ({}/s), audio frames: {} ({}/s)",
+                        self.stats.video_frames,
+                        self.stats.video_frames - last_stats.video_frames,
+                        self.stats.audio_frames,
+                        self.stats.audio_frames - last_stats.audio_frames,
+                    );
+
+                    last_stats = self.stats;
+                }
+
+                bytes = self.incoming.recv() => {
+                    self.handle_incoming(bytes)?;
+                }
+            }
+        }
+    }
+
+    async fn wait_for_event<F>(&mut self, is_expected: F) -> Result<(), PlayError>
+    where
+        F: Fn(&ClientSessionEvent) -> bool,
+    {
+        loop {
+            let bytes = self.incoming.recv().await;
+            for event in self.handle_incoming(bytes)? {
+                if is_expected(&event) {
+                    return Ok(());
+                }
+            }
+        }
+    }
+
+    fn handle_incoming(
+        &mut self,
+        bytes: Option<Bytes>,
+    ) -> Result<Vec<ClientSessionEvent>, PlayError> {
+        let bytes = match bytes {
+            Some(bytes) => bytes,
+            None => return Err(PlayError::Failed("Connection closed by the server".into())),
+        };
+
+        let results = self.session.handle_input(&bytes)?;
+        self.handle_results(results)
+    }
+
+    /// Queues all outbound packets to be sent to the server, counts received media, and returns
+    /// any other raised events.  The server rejecting the connection or playback is returned
+    /// as an error.
+    fn handle_results(
+        &mut self,
+        results: Vec<ClientSessionResult>,
+    ) -> Result<Vec<ClientSessionEvent>, PlayError> {
+        let mut events = Vec::new();
+        for result in results {
+            match result {
+                ClientSessionResult::OutboundResponse(packet) => {
+                    if self.outgoing.send(packet).is_err() {
+                        return Err(PlayError::Failed("Connection closed".into()));
+                    }
+                }
+
+                ClientSessionResult::RaisedEvent(event) => match event {
+                    ClientSessionEvent::ConnectionRequestRejected { description } => {
+                        return Err(PlayError::ConnectionRejected(description));
+                    }
+
+                    ClientSessionEvent::PlaybackStreamNotFound { description } => {
+                        return Err(PlayError::StreamNotFound(description));
+                    }
+
+                    ClientSessionEvent::PlaybackFailed { code, description } => {
+                        let message = format!("{} ({})", description, code);
+                        return Err(PlayError::Failed(message.into()));
+                    }
+
+                    ClientSessionEvent::VideoDataReceived { .. } => self.stats.video_frames += 1,
+                    ClientSessionEvent::AudioDataReceived { .. } => self.stats.audio_frames += 1,
+                    event => events.push(event),
+                },
+
+                ClientSessionResult::UnhandleableMessageReceived(_) => (),
+            }
+        }
+
+        Ok(events)
+    }
+}
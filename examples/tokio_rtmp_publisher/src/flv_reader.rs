@@ -0,0 +1,70 @@
+use bytes::Bytes;
+use std::fs;
+use std::io;
+
+const FLV_SIGNATURE: &[u8] = b"FLV";
+const PREVIOUS_TAG_SIZE_LENGTH: usize = 4;
+const TAG_HEADER_LENGTH: usize = 11;
+
+pub const AUDIO_TAG: u8 = 8;
+pub const VIDEO_TAG: u8 = 9;
+pub const SCRIPT_DATA_TAG: u8 = 18;
+
+/// A single tag read out of an FLV file.  The data of audio, video, and script data tags are
+/// identical to the payloads of their RTMP message counterparts.
+pub struct FlvTag {
+    pub tag_type: u8,
+    pub timestamp: u32,
+    pub data: Bytes,
+}
+
+/// Reads every tag out of the specified FLV file.  Only the minimal amount of parsing needed to
+/// split the file into tags is performed, the tag contents themselves are not inspected.
+pub fn read_tags(path: &str) -> io::Result<Vec<FlvTag>> {
+    let contents = Bytes::from(fs::read(path)?);
+    if contents.len() < 9 || &contents[..3] != FLV_SIGNATURE {
+        return Err(invalid_data(format!("{} is not an FLV file", path)));
+    }
+
+    let header_length = read_u32(&contents[5..9]) as usize;
+    let mut position = header_length + PREVIOUS_TAG_SIZE_LENGTH;
+    let mut tags = Vec::new();
+
+    while position + TAG_HEADER_LENGTH <= contents.len() {
+        let header = &contents[position..position + TAG_HEADER_LENGTH];
+        let tag_type = header[0] & 0x1f;
+        let data_size = read_u24(&header[1..4]) as usize;
+        let timestamp = read_u24(&header[4..7]) | ((header[7] as u32) << 24);
+
+        let data_start = position + TAG_HEADER_LENGTH;
+        let data_end = data_start + data_size;
+        if data_end > contents.len() {
+            return Err(invalid_data(format!(
+                "FLV tag at offset {} is truncated",
+                position
+            )));
+        }
+
+        tags.push(FlvTag {
+            tag_type,
+            timestamp,
+            data: contents.slice(data_start..data_end),
+        });
+
+        position = data_end + PREVIOUS_TAG_SIZE_LENGTH;
+    }
+
+    Ok(tags)
+}
+
+fn read_u24(bytes: &[u8]) -> u32 {
+    ((bytes[0] as u32) << 16) | ((bytes[1] as u32) << 8) | bytes[2] as u32
+}
+
+fn read_u32(bytes: &[u8]) -> u32 {
+    ((bytes[0] as u32) << 24) | read_u24(&bytes[1..4])
+}
+
+fn invalid_data(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
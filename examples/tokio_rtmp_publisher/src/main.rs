@@ -0,0 +1,83 @@
+use rml_rtmp::url::{RtmpScheme, RtmpUrl};
+use std::env;
+use std::process;
+use tokio::time::{self, Duration, Instant};
+
+mod flv_reader;
+mod publisher;
+
+const INITIAL_RECONNECT_DELAY: Duration = Duration::from_secs(1);
+const MAX_RECONNECT_DELAY: Duration = Duration::from_secs(30);
+
+struct Options {
+    url: RtmpUrl,
+    input: String,
+    loop_input: bool,
+}
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let options = match parse_options() {
+        Ok(options) => options,
+        Err(error) => {
+            eprintln!("{}", error);
+            eprintln!("Usage: tokio_rtmp_publisher --url rtmp://host[:port]/app/stream_key --input file.flv [--loop]");
+            process::exit(1);
+        }
+    };
+
+    let tags = flv_reader::read_tags(&options.input)?;
+    println!("Read {} tags from {}", tags.len(), options.input);
+
+    let mut reconnect_delay = INITIAL_RECONNECT_DELAY;
+    loop {
+        let connected_at = Instant::now();
+        match publisher::publish(&options.url, &tags, options.loop_input).await {
+            Ok(()) => {
+                println!("Finished publishing {}", options.input);
+                return Ok(());
+            }
+
+            Err(error) => eprintln!("Publishing failed: {}", error),
+        }
+
+        // A connection that stayed up for a while means the server is healthy again, so the
+        // next reconnection attempt should not be penalized by earlier failures.
+        if connected_at.elapsed() >= MAX_RECONNECT_DELAY {
+            reconnect_delay = INITIAL_RECONNECT_DELAY;
+        }
+
+        println!("Reconnecting in {} seconds", reconnect_delay.as_secs());
+        time::sleep(reconnect_delay).await;
+        reconnect_delay = std::cmp::min(reconnect_delay * 2, MAX_RECONNECT_DELAY);
+    }
+}
+
+fn parse_options() -> Result<Options, String> {
+    let args: Vec<String> = env::args().collect();
+    let url = get_arg_value(&args, "--url")?;
+    let input = get_arg_value(&args, "--input")?;
+    let loop_input = args.iter().any(|arg| arg == "--loop");
+
+    let url = RtmpUrl::parse(&url).map_err(|error| error.to_string())?;
+    if url.scheme != RtmpScheme::Rtmp {
+        return Err("Only rtmp:// urls are supported by this example".to_string());
+    }
+
+    Ok(Options {
+        url,
+        input,
+        loop_input,
+    })
+}
+
+fn get_arg_value(args: &[String], name: &str) -> Result<String, String> {
+    match args.iter().position(|arg| arg == name) {
+        Some(index) => match args.get(index + 1) {
+            Some(value) => Ok(value.clone()),
+            None => Err(format!("{} requires a value", name)),
+        },
+
+        None => Err(format!("{} is required", name)),
+    }
+}
@@ -0,0 +1,235 @@
+use bytes::Bytes;
+use rml_rtmp::chunk_io::DropPolicy;
+use rml_rtmp::handshake::AsyncHandshake;
+use rml_rtmp::rml_amf0::{self, Amf0Value};
+use rml_rtmp::sessions::{
+    ClientSession, ClientSessionConfig, ClientSessionEvent, ClientSessionResult,
+    PublishRequestType, StreamMetadata,
+};
+use rml_rtmp::time::RtmpTimestamp;
+use rml_rtmp::url::RtmpUrl;
+use std::io::Cursor;
+use tokio::io::{AsyncReadExt, AsyncWriteExt, ReadHalf, WriteHalf};
+use tokio::net::TcpStream;
+use tokio::sync::mpsc;
+use tokio::time::{self, Duration, Instant};
+
+use crate::flv_reader::{FlvTag, AUDIO_TAG, SCRIPT_DATA_TAG, VIDEO_TAG};
+
+type BoxError = Box<dyn std::error::Error + Sync + Send>;
+
+/// A single connection to the RTMP server that content is being published to.
+struct Publisher {
+    session: ClientSession,
+    writer: WriteHalf<TcpStream>,
+    incoming: mpsc::UnboundedReceiver<Bytes>,
+}
+
+/// Connects to the server and publishes all tags to it.  If `loop_input` is set the tags are
+/// published repeatedly until the connection fails, otherwise this returns once every tag has
+/// been sent.  Any disconnection is returned as an error so the caller can decide to reconnect.
+pub async fn publish(url: &RtmpUrl, tags: &[FlvTag], loop_input: bool) -> Result<(), BoxError> {
+    let mut stream = TcpStream::connect((url.host.as_str(), url.port)).await?;
+    let remaining_bytes = AsyncHandshake::perform_client_handshake(&mut stream)
+        .await
+        .map_err(|x| format!("Handshake failed: {:?}", x))?;
+
+    let (reader, writer) = tokio::io::split(stream);
+    let (sender, incoming) = mpsc::unbounded_channel();
+    tokio::spawn(read_socket(reader, sender));
+
+    let mut config = ClientSessionConfig::new();
+    config.tc_url = Some(url.tc_url());
+
+    let (session, results) = ClientSession::new(config)?;
+    let mut publisher = Publisher {
+        session,
+        writer,
+        incoming,
+    };
+
+    publisher.handle_results(results).await?;
+    let results = publisher.session.handle_input(&remaining_bytes)?;
+    publisher.handle_results(results).await?;
+
+    let results = publisher.session.request_connection(url.app.clone())?;
+    publisher.handle_results(results).await?;
+    publisher
+        .wait_for_event(|event| matches!(event, ClientSessionEvent::ConnectionRequestAccepted))
+        .await?;
+
+    println!("Connected to app '{}'", url.app);
+
+    let result = publisher
+        .session
+        .request_publishing(url.stream_key.clone(), PublishRequestType::Live)?;
+    publisher.handle_results(vec![result]).await?;
+    publisher
+        .wait_for_event(|event| matches!(event, ClientSessionEvent::PublishRequestAccepted))
+        .await?;
+
+    println!("Publishing to stream key '{}'", url.stream_key);
+
+    let mut timestamp_offset = 0_u32;
+    loop {
+        publisher.send_tags(tags, timestamp_offset).await?;
+        if !loop_input {
+            break;
+        }
+
+        let last_timestamp = tags.last().map(|tag| tag.timestamp).unwrap_or(0);
+        timestamp_offset = timestamp_offset.wrapping_add(last_timestamp + 1);
+    }
+
+    let results = publisher.session.stop_publishing()?;
+    publisher.handle_results(results).await?;
+
+    Ok(())
+}
+
+/// Forwards everything read from the socket to the publisher.  The channel is closed when the
+/// socket is, which the publisher sees as `None` on its receiver.
+async fn read_socket(mut reader: ReadHalf<TcpStream>, sender: mpsc::UnboundedSender<Bytes>) {
+    let mut buffer = [0; 4096];
+    loop {
+        match reader.read(&mut buffer).await {
+            Ok(0) | Err(_) => break,
+            Ok(bytes_read) => {
+                let bytes = Bytes::copy_from_slice(&buffer[..bytes_read]);
+                if sender.send(bytes).is_err() {
+                    break;
+                }
+            }
+        }
+    }
+}
+
+impl Publisher {
+    /// Sends each tag to the server, pacing them based on their timestamps so the content is
+    /// published in real time.
+    async fn send_tags(&mut self, tags: &[FlvTag], timestamp_offset: u32) -> Result<(), BoxError> {
+        let started_at = Instant::now();
+        for tag in tags {
+            self.wait_until(started_at + Duration::from_millis(tag.timestamp as u64))
+                .await?;
+
+            let timestamp = RtmpTimestamp::new(tag.timestamp.wrapping_add(timestamp_offset));
+            let result = match tag.tag_type {
+                VIDEO_TAG => self.session.publish_video_data(
+                    tag.data.clone(),
+                    timestamp,
+                    DropPolicy::MustDeliver,
+                )?,
+
+                AUDIO_TAG => self.session.publish_audio_data(
+                    tag.data.clone(),
+                    timestamp,
+                    DropPolicy::MustDeliver,
+                )?,
+
+                SCRIPT_DATA_TAG => match read_metadata(&tag.data) {
+                    Some(metadata) => self.session.publish_metadata(&metadata)?,
+                    None => continue,
+                },
+
+                _ => continue,
+            };
+
+            self.handle_results(vec![result]).await?;
+        }
+
+        Ok(())
+    }
+
+    /// Keeps processing input from the server until the specified time is reached.
+    async fn wait_until(&mut self, deadline: Instant) -> Result<(), BoxError> {
+        loop {
+            tokio::select! {
+                _ = time::sleep_until(deadline) => return Ok(()),
+                bytes = self.incoming.recv() => {
+                    self.handle_incoming(bytes).await?;
+                }
+            }
+        }
+    }
+
+    async fn wait_for_event<F>(&mut self, is_expected: F) -> Result<(), BoxError>
+    where
+        F: Fn(&ClientSessionEvent) -> bool,
+    {
+        loop {
+            let bytes = self.incoming.recv().await;
+            for event in self.handle_incoming(bytes).await? {
+                if is_expected(&event) {
+                    return Ok(());
+                }
+            }
+        }
+    }
+
+    async fn handle_incoming(
+        &mut self,
+        bytes: Option<Bytes>,
+    ) -> Result<Vec<ClientSessionEvent>, BoxError> {
+        let bytes = match bytes {
+            Some(bytes) => bytes,
+            None => return Err("Connection closed by the server".into()),
+        };
+
+        let results = self.session.handle_input(&bytes)?;
+        self.handle_results(results).await
+    }
+
+    /// Sends all outbound packets to the server and returns any raised events.  A rejected
+    /// connection or publish request is returned as an error.
+    async fn handle_results(
+        &mut self,
+        results: Vec<ClientSessionResult>,
+    ) -> Result<Vec<ClientSessionEvent>, BoxError> {
+        let mut events = Vec::new();
+        for result in results {
+            match result {
+                ClientSessionResult::OutboundResponse(packet) => {
+                    self.writer.write_all(&packet.bytes).await?;
+                }
+
+                ClientSessionResult::RaisedEvent(
+                    ClientSessionEvent::ConnectionRequestRejected { description },
+                ) => {
+                    return Err(format!("Connection rejected: {}", description).into());
+                }
+
+                ClientSessionResult::RaisedEvent(
+                    ClientSessionEvent::UnhandleableOnStatusCode { code },
+                ) => {
+                    println!("Unhandled onStatus code received: {}", code);
+                }
+
+                ClientSessionResult::RaisedEvent(event) => events.push(event),
+                ClientSessionResult::UnhandleableMessageReceived(_) => (),
+            }
+        }
+
+        Ok(events)
+    }
+}
+
+/// Reads the `onMetaData` properties out of an FLV script data tag
+fn read_metadata(data: &Bytes) -> Option<StreamMetadata> {
+    let values = rml_amf0::deserialize(&mut Cursor::new(&data[..])).ok()?;
+    let mut values = values.into_iter();
+    match values.next() {
+        Some(Amf0Value::Utf8String(ref name)) if name == "onMetaData" => (),
+        _ => return None,
+    }
+
+    let properties = match values.next() {
+        Some(Amf0Value::Object(properties)) => properties,
+        Some(Amf0Value::EcmaArray(properties)) => properties,
+        _ => return None,
+    };
+
+    let mut metadata = StreamMetadata::new();
+    metadata.apply_metadata_values(properties);
+    Some(metadata)
+}
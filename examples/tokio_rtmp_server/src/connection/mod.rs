@@ -1,79 +1,85 @@
 mod connection_action;
 mod state;
+mod writer_config;
 
 use bytes::{Bytes, BytesMut};
 use futures::future::FutureExt;
-use rml_rtmp::chunk_io::Packet;
-use rml_rtmp::handshake::{Handshake, HandshakeProcessResult, PeerType};
+use rml_rtmp::chunk_io::{DropPolicy, Packet};
+use rml_rtmp::handshake::{AsyncHandshake, HandshakeError};
+use rml_rtmp::messages::MessagePayload;
+use rml_rtmp::recording::RecordingHandle;
 use rml_rtmp::sessions::{
-    PublishMode, ServerSession, ServerSessionConfig, ServerSessionEvent, ServerSessionResult,
+    PublishMode, RtmpSession, ServerSession, ServerSessionConfig, ServerSessionEvent,
+    ServerSessionResult, SessionOutput,
 };
 use std::collections::VecDeque;
+use std::sync::Arc;
 use tokio::io::{AsyncReadExt, AsyncWriteExt, ReadHalf, WriteHalf};
 use tokio::net::TcpStream;
 use tokio::sync::mpsc;
 use tokio::sync::mpsc::UnboundedSender;
 
-use crate::stream_manager::{ConnectionMessage, StreamManagerMessage};
+use crate::stream_manager::{ConnectionMessage, QualityPreference, StreamManagerMessage};
 use crate::{send, spawn};
 
 use connection_action::ConnectionAction;
 use state::State;
+pub use writer_config::{ConnectionWriterConfig, DroppedPacketCounter};
 
 pub struct Connection {
     id: i32,
     session: Option<ServerSession>,
     stream_manager_sender: mpsc::UnboundedSender<StreamManagerMessage>,
     state: State,
+    recording: Option<RecordingHandle>,
+    writer_config: ConnectionWriterConfig,
+    dropped_packets: Arc<DroppedPacketCounter>,
 }
 
 impl Connection {
-    pub fn new(id: i32, stream_manager: mpsc::UnboundedSender<StreamManagerMessage>) -> Self {
+    pub fn new(
+        id: i32,
+        stream_manager: mpsc::UnboundedSender<StreamManagerMessage>,
+        writer_config: ConnectionWriterConfig,
+    ) -> Self {
         Connection {
             id,
             session: None,
             stream_manager_sender: stream_manager,
             state: State::Waiting,
+            recording: None,
+            writer_config,
+            dropped_packets: Arc::new(DroppedPacketCounter::default()),
         }
     }
 
+    /// The number of optional packets that have been dropped because the client could not
+    /// keep up with them
+    pub fn dropped_packet_count(&self) -> u64 {
+        self.dropped_packets.get()
+    }
+
     pub async fn start_handshake(
         self,
         mut stream: TcpStream,
     ) -> Result<(), Box<dyn std::error::Error + Sync + Send>> {
-        let mut handshake = Handshake::new(PeerType::Server);
-
-        let server_p0_and_1 = handshake
-            .generate_outbound_p0_and_p1()
-            .map_err(|x| format!("Failed to generate p0 and p1: {:?}", x))?;
+        let remaining_bytes = match AsyncHandshake::perform_server_handshake(&mut stream).await {
+            Ok(remaining_bytes) => remaining_bytes,
 
-        stream.write_all(&server_p0_and_1).await?;
-
-        let mut buffer = [0; 4096];
-        loop {
-            let bytes_read = stream.read(&mut buffer).await?;
-            if bytes_read == 0 {
-                return Ok(());
-            }
-
-            match handshake
-                .process_bytes(&buffer[0..bytes_read])
-                .map_err(|x| format!("Connection {}: Failed to process bytes: {:?}", self.id, x))?
+            // The client disconnecting mid-handshake isn't worth reporting
+            Err(HandshakeError::Io(ref error))
+                if error.kind() == std::io::ErrorKind::UnexpectedEof =>
             {
-                HandshakeProcessResult::InProgress { response_bytes } => {
-                    stream.write_all(&response_bytes).await?;
-                }
+                return Ok(())
+            }
 
-                HandshakeProcessResult::Completed {
-                    response_bytes,
-                    remaining_bytes,
-                } => {
-                    stream.write_all(&response_bytes).await?;
-                    spawn(self.start_connection_manager(stream, remaining_bytes));
-                    return Ok(());
-                }
+            Err(error) => {
+                return Err(format!("Connection {}: Handshake failed: {:?}", self.id, error).into())
             }
-        }
+        };
+
+        spawn(self.start_connection_manager(stream, remaining_bytes));
+        Ok(())
     }
 
     async fn start_connection_manager(
@@ -93,6 +99,7 @@ impl Connection {
             connection_id: self.id,
             sender: message_sender,
             disconnection: disconnection_receiver,
+            dropped_packets: self.dropped_packets.clone(),
         };
 
         if !send(&self.stream_manager_sender, message) {
@@ -104,20 +111,21 @@ impl Connection {
             self.id,
             stream_writer,
             write_bytes_receiver,
+            self.writer_config.clone(),
+            self.dropped_packets.clone(),
         ));
 
         let config = ServerSessionConfig::new();
-        let (session, mut results) = ServerSession::new(config)
+        let (session, initial_results) = ServerSession::new(config)
             .map_err(|x| format!("Server session error occurred: {:?}", x))?;
 
         self.session = Some(session);
 
+        let mut results = to_outputs(initial_results);
         let remaining_bytes_results = self
-            .session
-            .as_mut()
-            .unwrap()
-            .handle_input(&received_bytes)
-            .map_err(|x| format!("Failed to handle input: {:?}", x))?;
+            .rtmp_session()
+            .process_bytes(&received_bytes)
+            .map_err(|x| format!("Failed to handle input: {}", x))?;
 
         results.extend(remaining_bytes_results);
 
@@ -132,10 +140,9 @@ impl Connection {
                     match message {
                         None => break,
                         Some(bytes) => {
-                           results = self.session.as_mut()
-                                .unwrap()
-                                .handle_input(&bytes)
-                                .map_err(|x| format!("Error handling input: {:?}", x))?;
+                            results = self.rtmp_session()
+                                .process_bytes(&bytes)
+                                .map_err(|x| format!("Error handling input: {}", x))?;
                         }
                     }
                 }
@@ -150,14 +157,19 @@ impl Connection {
                                 _ => (),
                             };
 
-                            results = new_results;
+                            results = to_outputs(new_results);
                         }
                     }
                 }
             }
         }
 
-        println!("Connection {}: Client disconnected", self.id);
+        self.stop_recording();
+        println!(
+            "Connection {}: Client disconnected after {} packets were dropped",
+            self.id,
+            self.dropped_packet_count()
+        );
 
         Ok(())
     }
@@ -178,19 +190,34 @@ impl Connection {
                         stream_key,
                         app_name,
                         request_id,
+                        stream_id,
+                        mode,
                     } => {
                         let new_state = State::Publishing {
                             app_name: app_name.clone(),
                             stream_key: stream_key.clone(),
                         };
 
-                        let results = self
-                            .session
-                            .as_mut()
-                            .unwrap()
-                            .accept_request(request_id.clone())
+                        let session = self.session.as_mut().unwrap();
+                        let results = session
+                            .accept_request(*request_id)
                             .map_err(|x| format!("Failed to accept request: {:?}", x))?;
 
+                        if *mode == PublishMode::Record {
+                            let path = format!("{}.flv", stream_key.replace('/', "_"));
+                            match session.begin_recording(*stream_id, &path) {
+                                Ok(handle) => {
+                                    println!("Connection {}: Recording to {}", self.id, path);
+                                    self.recording = Some(handle);
+                                }
+
+                                Err(error) => eprintln!(
+                                    "Connection {}: Failed to start recording: {}",
+                                    self.id, error
+                                ),
+                            }
+                        }
+
                         (Some(new_state), (results, ConnectionAction::None))
                     }
 
@@ -210,7 +237,7 @@ impl Connection {
                             .session
                             .as_mut()
                             .unwrap()
-                            .accept_request(request_id.clone())
+                            .accept_request(*request_id)
                             .map_err(|x| format!("Failed to accept request: {:?}", x))?;
 
                         (Some(new_state), (results, ConnectionAction::None))
@@ -251,7 +278,7 @@ impl Connection {
             ConnectionMessage::NewVideoData {
                 timestamp,
                 data,
-                can_be_dropped,
+                drop_policy,
             } => {
                 return match &self.state {
                     State::Playing { stream_id, .. } => {
@@ -259,7 +286,7 @@ impl Connection {
                             .session
                             .as_mut()
                             .unwrap()
-                            .send_video_data(*stream_id, data, timestamp, can_be_dropped)
+                            .send_video_data(*stream_id, data, timestamp, drop_policy)
                             .map_err(|x| format!("Failed to send video data: {:?}", x))?;
 
                         let results = vec![ServerSessionResult::OutboundResponse(packet)];
@@ -276,7 +303,7 @@ impl Connection {
             ConnectionMessage::NewAudioData {
                 timestamp,
                 data,
-                can_be_dropped,
+                drop_policy,
             } => {
                 return match &self.state {
                     State::Playing { stream_id, .. } => {
@@ -284,7 +311,7 @@ impl Connection {
                             .session
                             .as_mut()
                             .unwrap()
-                            .send_audio_data(*stream_id, data, timestamp, can_be_dropped)
+                            .send_audio_data(*stream_id, data, timestamp, drop_policy)
                             .map_err(|x| format!("Failed to send audio data: {:?}", x))?;
 
                         let results = vec![ServerSessionResult::OutboundResponse(packet)];
@@ -321,9 +348,14 @@ impl Connection {
         }
     }
 
+    /// The session, for the parts of the connection that only move bytes in and out of it
+    fn rtmp_session(&mut self) -> &mut dyn RtmpSession {
+        self.session.as_mut().unwrap()
+    }
+
     fn handle_session_results(
         &mut self,
-        results: &mut Vec<ServerSessionResult>,
+        results: &mut Vec<SessionOutput>,
         byte_writer: &mut UnboundedSender<Packet>,
     ) -> Result<ConnectionAction, Box<dyn std::error::Error + Sync + Send>> {
         if results.len() == 0 {
@@ -332,25 +364,33 @@ impl Connection {
 
         let mut new_results = Vec::new();
         for result in results.drain(..) {
-            match result {
-                ServerSessionResult::OutboundResponse(packet) => {
+            let event = match result {
+                SessionOutput::Send(packet) => {
                     if !send(&byte_writer, packet) {
                         break;
                     }
+
+                    continue;
                 }
 
-                ServerSessionResult::RaisedEvent(event) => {
-                    let action = self.handle_raised_event(event, &mut new_results)?;
+                SessionOutput::Event(event) => event,
+            };
+
+            match event.downcast::<ServerSessionEvent>() {
+                Ok(event) => {
+                    let action = self.handle_raised_event(*event, &mut new_results)?;
                     if action == ConnectionAction::Disconnect {
                         return Ok(ConnectionAction::Disconnect);
                     }
                 }
 
-                ServerSessionResult::UnhandleableMessageReceived(payload) => {
-                    println!(
-                        "Connection {}: Unhandleable message received: {:?}",
-                        self.id, payload
-                    );
+                Err(other) => {
+                    if let Some(payload) = other.downcast_ref::<MessagePayload>() {
+                        println!(
+                            "Connection {}: Unhandleable message received: {:?}",
+                            self.id, payload
+                        );
+                    }
                 }
             }
         }
@@ -363,12 +403,13 @@ impl Connection {
     fn handle_raised_event(
         &mut self,
         event: ServerSessionEvent,
-        new_results: &mut Vec<ServerSessionResult>,
+        new_results: &mut Vec<SessionOutput>,
     ) -> Result<ConnectionAction, Box<dyn std::error::Error + Sync + Send>> {
         match event {
             ServerSessionEvent::ConnectionRequested {
                 request_id,
                 app_name,
+                ..
             } => {
                 println!(
                     "Connection {}: Client requested connection to app {:?}",
@@ -383,18 +424,19 @@ impl Connection {
                     return Ok(ConnectionAction::Disconnect);
                 }
 
-                new_results.extend(
-                    self.session
-                        .as_mut()
-                        .unwrap()
-                        .accept_request(request_id)
-                        .map_err(|x| {
-                            format!(
-                                "Connection {}: Error occurred accepting request: {:?}",
-                                self.id, x
-                            )
-                        })?,
-                );
+                let results = self
+                    .session
+                    .as_mut()
+                    .unwrap()
+                    .accept_request(request_id)
+                    .map_err(|x| {
+                        format!(
+                            "Connection {}: Error occurred accepting request: {:?}",
+                            self.id, x
+                        )
+                    })?;
+
+                new_results.extend(to_outputs(results));
 
                 self.state = State::Connected { app_name };
             }
@@ -404,20 +446,21 @@ impl Connection {
                 app_name,
                 mode,
                 stream_key,
+                stream_id,
             } => {
                 println!(
                     "Connection {}: Client requesting publishing on {}/{} in mode {:?}",
                     self.id, app_name, stream_key, mode
                 );
 
-                if mode != PublishMode::Live {}
-
                 match &self.state {
                     State::Connected { .. } => {
                         self.state = State::PublishRequested {
-                            request_id: request_id.clone(),
+                            request_id,
                             app_name: app_name.clone(),
                             stream_key: stream_key.clone(),
+                            stream_id,
+                            mode,
                         };
 
                         let message = StreamManagerMessage::PublishRequest {
@@ -454,7 +497,7 @@ impl Connection {
                 match &self.state {
                     State::Connected { .. } => {
                         self.state = State::PlaybackRequested {
-                            request_id: request_id.clone(),
+                            request_id,
                             app_name: app_name.clone(),
                             stream_key: stream_key.clone(),
                             stream_id,
@@ -587,6 +630,8 @@ impl Connection {
 
             ServerSessionEvent::PublishStreamFinished { .. } => match &self.state {
                 State::Publishing { .. } => {
+                    self.stop_recording();
+
                     let message = StreamManagerMessage::PublishFinished {
                         connection_id: self.id,
                     };
@@ -605,11 +650,67 @@ impl Connection {
                 }
             },
 
+            // Players can switch between the streams of a quality group by calling
+            // `selectQuality(groupId, preference)` on their NetConnection
+            ServerSessionEvent::UnhandleableAmf0Command {
+                command_name,
+                additional_values,
+                ..
+            } if command_name == "selectQuality" => match &self.state {
+                State::Playing { .. } => {
+                    let group_id = additional_values.first().and_then(|x| x.as_str());
+                    let preference = additional_values
+                        .get(1)
+                        .and_then(QualityPreference::from_amf0);
+
+                    let (group_id, preference) = match (group_id, preference) {
+                        (Some(group_id), Some(preference)) => (group_id.to_string(), preference),
+                        _ => {
+                            eprintln!(
+                                "Connection {}: Invalid selectQuality arguments: {:?}",
+                                self.id, additional_values
+                            );
+                            return Ok(ConnectionAction::None);
+                        }
+                    };
+
+                    let message = StreamManagerMessage::SelectQuality {
+                        connection_id: self.id,
+                        group_id,
+                        preference,
+                    };
+
+                    if !send(&self.stream_manager_sender, message) {
+                        return Ok(ConnectionAction::Disconnect);
+                    }
+                }
+
+                _ => {
+                    eprintln!(
+                        "Connection {}: Expected client to be in playback state, was in {:?}",
+                        self.id, self.state
+                    );
+                }
+            },
+
             x => println!("Connection {}: Unknown event raised: {:?}", self.id, x),
         }
 
         Ok(ConnectionAction::None)
     }
+
+    fn stop_recording(&mut self) {
+        if let Some(recording) = self.recording.take() {
+            match recording.stop() {
+                Ok(()) => println!("Connection {}: Recording stopped", self.id),
+                Err(error) => eprintln!("Connection {}: Recording failed: {}", self.id, error),
+            }
+        }
+    }
+}
+
+fn to_outputs(results: Vec<ServerSessionResult>) -> Vec<SessionOutput> {
+    results.into_iter().map(SessionOutput::from).collect()
 }
 
 async fn connection_reader(
@@ -641,8 +742,9 @@ async fn connection_writer(
     connection_id: i32,
     mut stream: WriteHalf<TcpStream>,
     mut packets_to_send: mpsc::UnboundedReceiver<Packet>,
+    config: ConnectionWriterConfig,
+    dropped_packets: Arc<DroppedPacketCounter>,
 ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-    const BACKLOG_THRESHOLD: usize = 100;
     let mut send_queue = VecDeque::new();
 
     loop {
@@ -663,17 +765,29 @@ async fn connection_writer(
             send_queue.push_back(packet);
         }
 
-        let mut send_optional_packets = true;
-        if send_queue.len() > BACKLOG_THRESHOLD {
+        // Inter-frames are dropped as soon as we start falling behind, but audio is only dropped
+        // once the backlog gets severe, since audio gaps are more noticeable to viewers.
+        let queued_bytes: usize = send_queue.iter().map(|p: &Packet| p.bytes.len()).sum();
+        let is_backlogged =
+            send_queue.len() > config.backlog_threshold || queued_bytes > config.max_queued_bytes;
+        let is_heavily_backlogged = send_queue.len() > config.heavy_backlog_threshold;
+        if is_backlogged {
             println!(
                 "Connection {}: Too many pending packets, dropping optional ones",
                 connection_id
             );
-            send_optional_packets = false;
         }
 
         for packet in send_queue.drain(..) {
-            if send_optional_packets || !packet.can_be_dropped {
+            let should_drop = match packet.drop_policy {
+                DropPolicy::MustDeliver => false,
+                DropPolicy::DropUnderLoad => is_heavily_backlogged,
+                DropPolicy::DropAggressively => is_backlogged,
+            };
+
+            if should_drop {
+                dropped_packets.increment();
+            } else {
                 stream.write_all(packet.bytes.as_ref()).await?;
             }
         }
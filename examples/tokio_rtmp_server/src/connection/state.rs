@@ -1,3 +1,5 @@
+use rml_rtmp::sessions::{PublishMode, RequestId};
+
 #[derive(PartialEq, Debug, Clone)]
 pub enum State {
     Waiting,
@@ -7,7 +9,9 @@ pub enum State {
     PublishRequested {
         app_name: String,
         stream_key: String,
-        request_id: u32,
+        request_id: RequestId,
+        stream_id: u32,
+        mode: PublishMode,
     },
     Publishing {
         app_name: String,
@@ -16,7 +20,7 @@ pub enum State {
     PlaybackRequested {
         app_name: String,
         stream_key: String,
-        request_id: u32,
+        request_id: RequestId,
         stream_id: u32,
     },
     Playing {
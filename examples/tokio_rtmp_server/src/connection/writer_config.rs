@@ -0,0 +1,43 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// Controls when the connection writer starts dropping optional packets because the client
+/// isn't able to receive them fast enough.
+#[derive(Clone, Debug)]
+pub struct ConnectionWriterConfig {
+    /// Number of queued packets past which inter-frames are dropped
+    pub backlog_threshold: usize,
+
+    /// Number of queued packets past which audio is dropped as well
+    pub heavy_backlog_threshold: usize,
+
+    /// Number of queued bytes past which inter-frames are dropped, regardless of how many
+    /// packets are queued
+    pub max_queued_bytes: usize,
+}
+
+impl ConnectionWriterConfig {
+    pub fn new() -> Self {
+        ConnectionWriterConfig {
+            backlog_threshold: 100,
+            heavy_backlog_threshold: 500,
+            max_queued_bytes: 5 * 1024 * 1024,
+        }
+    }
+}
+
+/// Counts the packets a connection writer has dropped, so it can be shared with anything that
+/// wants to monitor the health of the connection.
+#[derive(Debug, Default)]
+pub struct DroppedPacketCounter {
+    count: AtomicU64,
+}
+
+impl DroppedPacketCounter {
+    pub fn increment(&self) {
+        self.count.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn get(&self) -> u64 {
+        self.count.load(Ordering::Relaxed)
+    }
+}
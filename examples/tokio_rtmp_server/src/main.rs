@@ -1,4 +1,6 @@
-use crate::connection::Connection;
+use crate::connection::{Connection, ConnectionWriterConfig};
+use crate::stream_manager::StreamManagerMessage;
+use std::env;
 use std::fmt::Display;
 use std::future::Future;
 use tokio::net::TcpListener;
@@ -11,14 +13,34 @@ mod stream_manager;
 async fn main() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
     let manager_sender = stream_manager::start();
 
+    // Quality groups can be passed in as `group_id=app/stream_key,app/stream_key,...` arguments
+    for argument in env::args().skip(1) {
+        let (group_id, stream_keys) = match argument.split_once('=') {
+            Some(x) => x,
+            None => {
+                eprintln!("Invalid quality group argument: {}", argument);
+                continue;
+            }
+        };
+
+        println!("Adding quality group '{}': {}", group_id, stream_keys);
+        let message = StreamManagerMessage::AddQualityGroup {
+            group_id: group_id.to_string(),
+            stream_keys: stream_keys.split(',').map(|x| x.to_string()).collect(),
+        };
+
+        send(&manager_sender, message);
+    }
+
     println!("Listening for connections on port 1935");
     let listener = TcpListener::bind("0.0.0.0:1935").await?;
     let mut current_id = 0;
+    let writer_config = ConnectionWriterConfig::new();
 
     loop {
         let (stream, connection_info) = listener.accept().await?;
 
-        let connection = Connection::new(current_id, manager_sender.clone());
+        let connection = Connection::new(current_id, manager_sender.clone(), writer_config.clone());
         println!(
             "Connection {}: Connection received from {}",
             current_id,
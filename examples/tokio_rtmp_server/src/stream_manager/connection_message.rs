@@ -1,27 +1,28 @@
 use bytes::Bytes;
-use rml_rtmp::sessions::StreamMetadata;
+use rml_rtmp::chunk_io::DropPolicy;
+use rml_rtmp::sessions::{RequestId, StreamMetadata};
 use rml_rtmp::time::RtmpTimestamp;
 
 #[derive(Debug)]
 pub enum ConnectionMessage {
     RequestAccepted {
-        request_id: u32,
+        request_id: RequestId,
     },
 
     RequestDenied {
-        request_id: u32,
+        request_id: RequestId,
     },
 
     NewVideoData {
         timestamp: RtmpTimestamp,
         data: Bytes,
-        can_be_dropped: bool,
+        drop_policy: DropPolicy,
     },
 
     NewAudioData {
         timestamp: RtmpTimestamp,
         data: Bytes,
-        can_be_dropped: bool,
+        drop_policy: DropPolicy,
     },
 
     NewMetadata {
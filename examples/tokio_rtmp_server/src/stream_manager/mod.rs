@@ -1,15 +1,20 @@
 mod connection_message;
 mod player_details;
 mod publish_details;
+mod quality_preference;
 mod stream_manager_message;
 
+use crate::connection::DroppedPacketCounter;
 use crate::send;
 use bytes::Bytes;
 use futures::future::select_all;
 use futures::future::BoxFuture;
-use rml_rtmp::sessions::StreamMetadata;
+use rml_rtmp::chunk_io::DropPolicy;
+use rml_rtmp::codec_utils::{is_video_keyframe, is_video_sequence_header};
+use rml_rtmp::sessions::{RequestId, StreamMetadata};
 use rml_rtmp::time::RtmpTimestamp;
 use std::collections::hash_map::HashMap;
+use std::sync::Arc;
 use tokio::sync::mpsc;
 use tokio::sync::mpsc::{UnboundedReceiver, UnboundedSender};
 
@@ -17,6 +22,7 @@ pub use connection_message::ConnectionMessage;
 use futures::FutureExt;
 pub use player_details::PlayerDetails;
 pub use publish_details::PublishDetails;
+pub use quality_preference::QualityPreference;
 pub use stream_manager_message::StreamManagerMessage;
 
 pub fn start() -> mpsc::UnboundedSender<StreamManagerMessage> {
@@ -34,7 +40,7 @@ enum FutureResult {
     },
     MessageReceived {
         receiver: UnboundedReceiver<StreamManagerMessage>,
-        message: Option<StreamManagerMessage>,
+        message: Option<Box<StreamManagerMessage>>,
     },
 }
 
@@ -43,6 +49,8 @@ struct StreamManager<'a> {
     publish_details: HashMap<String, PublishDetails>,
     sender_by_connection_id: HashMap<i32, mpsc::UnboundedSender<ConnectionMessage>>,
     key_by_connection_id: HashMap<i32, String>,
+    dropped_packets_by_connection_id: HashMap<i32, Arc<DroppedPacketCounter>>,
+    stream_keys_by_quality_group: HashMap<String, Vec<String>>,
     new_disconnect_futures: Vec<BoxFuture<'a, FutureResult>>,
 }
 
@@ -53,6 +61,8 @@ impl<'a> StreamManager<'a> {
             players_by_key: HashMap::new(),
             sender_by_connection_id: HashMap::new(),
             key_by_connection_id: HashMap::new(),
+            dropped_packets_by_connection_id: HashMap::new(),
+            stream_keys_by_quality_group: HashMap::new(),
             new_disconnect_futures: Vec::new(),
         }
     }
@@ -61,6 +71,7 @@ impl<'a> StreamManager<'a> {
         println!("Stream manager is removing connection id {}", connection_id);
 
         self.sender_by_connection_id.remove(&connection_id);
+        self.dropped_packets_by_connection_id.remove(&connection_id);
         if let Some(key) = self.key_by_connection_id.remove(&connection_id) {
             if let Some(players) = self.players_by_key.get_mut(&key) {
                 players.remove(&connection_id);
@@ -81,7 +92,7 @@ impl<'a> StreamManager<'a> {
             let result = receiver.recv().await;
             FutureResult::MessageReceived {
                 receiver,
-                message: result,
+                message: result.map(Box::new),
             }
         }
 
@@ -94,7 +105,7 @@ impl<'a> StreamManager<'a> {
             match result {
                 FutureResult::MessageReceived { receiver, message } => {
                     match message {
-                        Some(message) => self.handle_message(message),
+                        Some(message) => self.handle_message(*message),
                         None => return, // receiver has no more senders
                     }
 
@@ -120,8 +131,9 @@ impl<'a> StreamManager<'a> {
                 connection_id,
                 sender,
                 disconnection,
+                dropped_packets,
             } => {
-                self.handle_new_connection(connection_id, sender, disconnection);
+                self.handle_new_connection(connection_id, sender, disconnection, dropped_packets);
             }
 
             StreamManagerMessage::PublishRequest {
@@ -172,7 +184,112 @@ impl<'a> StreamManager<'a> {
             } => {
                 self.handle_new_metadata(sending_connection_id, metadata);
             }
+
+            StreamManagerMessage::AddQualityGroup {
+                group_id,
+                stream_keys,
+            } => {
+                let stream_keys = stream_keys.iter().map(|x| x.as_str()).collect::<Vec<_>>();
+                self.add_quality_group(&group_id, &stream_keys);
+            }
+
+            StreamManagerMessage::SelectQuality {
+                connection_id,
+                group_id,
+                preference,
+            } => {
+                self.select_quality(connection_id, &group_id, preference);
+            }
+        }
+    }
+
+    /// Groups streams that are different renditions of the same content, so players can switch
+    /// between them with `select_quality()`.  Stream keys are in the `app/stream_key` form.
+    fn add_quality_group(&mut self, group_id: &str, stream_keys: &[&str]) {
+        let stream_keys = stream_keys.iter().map(|x| x.to_string()).collect();
+        self.stream_keys_by_quality_group
+            .insert(group_id.to_string(), stream_keys);
+    }
+
+    /// Switches a player to the stream of the quality group that best matches its preference,
+    /// out of the streams currently being published.  The player keeps its timestamp offset, so
+    /// this assumes the renditions come from the same encoder and share their timestamps.
+    fn select_quality(
+        &mut self,
+        connection_id: i32,
+        group_id: &str,
+        preference: QualityPreference,
+    ) {
+        let stream_keys = match self.stream_keys_by_quality_group.get(group_id) {
+            Some(x) => x,
+            None => {
+                println!(
+                    "Connection {} selected quality of unknown group '{}'",
+                    connection_id, group_id
+                );
+                return;
+            }
+        };
+
+        let streams = stream_keys
+            .iter()
+            .filter_map(|key| {
+                self.publish_details
+                    .get(key)
+                    .map(|details| (key.as_str(), details.bitrate_kbps()))
+            })
+            .collect::<Vec<_>>();
+
+        let new_key = match preference.choose(&streams) {
+            Some(x) => x.to_string(),
+            None => {
+                println!(
+                    "Connection {} selected quality of group '{}' but none of its streams are being published",
+                    connection_id, group_id
+                );
+                return;
+            }
+        };
+
+        let current_key = match self.key_by_connection_id.get(&connection_id) {
+            Some(x) => x.clone(),
+            None => return,
+        };
+
+        if current_key == new_key {
+            return;
         }
+
+        let mut player = match self
+            .players_by_key
+            .get_mut(&current_key)
+            .and_then(|players| players.remove(&connection_id))
+        {
+            Some(x) => x,
+            None => {
+                println!(
+                    "Connection {} selected quality but is not playing a stream",
+                    connection_id
+                );
+                return;
+            }
+        };
+
+        println!(
+            "Switching connection {} from stream '{}' to '{}'",
+            connection_id, current_key, new_key
+        );
+
+        // Frames of the new stream can't be decoded until a keyframe of it has been received
+        player.has_received_video_keyframe = false;
+        self.players_by_key
+            .entry(new_key.clone())
+            .or_insert(HashMap::new())
+            .insert(connection_id, player);
+        self.key_by_connection_id
+            .insert(connection_id, new_key.clone());
+
+        self.send_stream_start(connection_id, &new_key);
     }
 
     fn handle_new_connection(
@@ -180,8 +297,11 @@ impl<'a> StreamManager<'a> {
         connection_id: i32,
         sender: UnboundedSender<ConnectionMessage>,
         disconnection: UnboundedReceiver<()>,
+        dropped_packets: Arc<DroppedPacketCounter>,
     ) {
         self.sender_by_connection_id.insert(connection_id, sender);
+        self.dropped_packets_by_connection_id
+            .insert(connection_id, dropped_packets);
         self.new_disconnect_futures
             .push(wait_for_client_disconnection(connection_id, disconnection).boxed());
     }
@@ -189,7 +309,7 @@ impl<'a> StreamManager<'a> {
     fn handle_publish_request(
         &mut self,
         connection_id: i32,
-        request_id: u32,
+        request_id: RequestId,
         rtmp_app: String,
         stream_key: String,
     ) {
@@ -230,15 +350,8 @@ impl<'a> StreamManager<'a> {
         }
 
         self.key_by_connection_id.insert(connection_id, key.clone());
-        self.publish_details.insert(
-            key.clone(),
-            PublishDetails {
-                video_sequence_header: None,
-                audio_sequence_header: None,
-                metadata: None,
-                connection_id,
-            },
-        );
+        self.publish_details
+            .insert(key.clone(), PublishDetails::new(connection_id));
 
         if !send(&sender, ConnectionMessage::RequestAccepted { request_id }) {
             self.cleanup_connection(connection_id);
@@ -248,7 +361,7 @@ impl<'a> StreamManager<'a> {
     fn handle_playback_request(
         &mut self,
         connection_id: i32,
-        request_id: u32,
+        request_id: RequestId,
         rtmp_app: String,
         stream_key: String,
     ) {
@@ -273,12 +386,20 @@ impl<'a> StreamManager<'a> {
             return;
         }
 
+        let dropped_packets = match self.dropped_packets_by_connection_id.get(&connection_id) {
+            Some(x) => x.clone(),
+            None => Arc::new(DroppedPacketCounter::default()),
+        };
+
         let key = format!("{}/{}", rtmp_app, stream_key);
         let connection_ids = self
             .players_by_key
             .entry(key.clone())
             .or_insert(HashMap::new());
-        connection_ids.insert(connection_id, PlayerDetails::new(connection_id));
+        connection_ids.insert(
+            connection_id,
+            PlayerDetails::new(connection_id, dropped_packets),
+        );
         self.key_by_connection_id.insert(connection_id, key.clone());
 
         if !send(&sender, ConnectionMessage::RequestAccepted { request_id }) {
@@ -287,10 +408,19 @@ impl<'a> StreamManager<'a> {
             return;
         }
 
-        // If someone is publishing on this stream already, send the latest audio and video
-        // sequence headers, so the client can view them.
+        self.send_stream_start(connection_id, &key);
+    }
 
-        let details = match self.publish_details.get(&key) {
+    /// If someone is publishing on the stream already, sends the player the latest metadata,
+    /// audio and video sequence headers, and video since the latest keyframe, so the player can
+    /// start showing the stream right away.
+    fn send_stream_start(&mut self, connection_id: i32, key: &str) {
+        let sender = match self.sender_by_connection_id.get(&connection_id) {
+            Some(x) => x,
+            None => return,
+        };
+
+        let details = match self.publish_details.get(key) {
             Some(x) => x,
             None => return,
         };
@@ -310,7 +440,7 @@ impl<'a> StreamManager<'a> {
             let message = ConnectionMessage::NewVideoData {
                 timestamp: RtmpTimestamp::new(0),
                 data: data.clone(),
-                can_be_dropped: false,
+                drop_policy: DropPolicy::MustDeliver,
             };
 
             if !send(&sender, message) {
@@ -323,7 +453,7 @@ impl<'a> StreamManager<'a> {
             let message = ConnectionMessage::NewAudioData {
                 timestamp: RtmpTimestamp::new(0),
                 data: data.clone(),
-                can_be_dropped: false,
+                drop_policy: DropPolicy::MustDeliver,
             };
 
             if !send(&sender, message) {
@@ -331,6 +461,40 @@ impl<'a> StreamManager<'a> {
                 return;
             }
         }
+
+        // Start the player from the most recent keyframe, so they don't have to wait for the
+        // publisher's next keyframe before video can be shown.
+        let player = match self
+            .players_by_key
+            .get_mut(key)
+            .and_then(|players| players.get_mut(&connection_id))
+        {
+            Some(x) => x,
+            None => return,
+        };
+
+        let messages = details
+            .frames_since_latest_keyframe()
+            .map(|frame| {
+                player.has_received_video_keyframe = true;
+                ConnectionMessage::NewVideoData {
+                    timestamp: player.rebase_timestamp(frame.timestamp),
+                    data: frame.data.clone(),
+                    drop_policy: if frame.is_keyframe {
+                        DropPolicy::MustDeliver
+                    } else {
+                        DropPolicy::DropAggressively
+                    },
+                }
+            })
+            .collect::<Vec<_>>();
+
+        for message in messages {
+            if !send(&sender, message) {
+                self.cleanup_connection(connection_id);
+                return;
+            }
+        }
     }
 
     fn handle_playback_finished(&mut self, connection_id: i32) {
@@ -361,17 +525,17 @@ impl<'a> StreamManager<'a> {
             details.audio_sequence_header = Some(data.clone());
         }
 
-        if let Some(players) = self.players_by_key.get(key.as_str()) {
-            for (player_id, _) in players {
+        if let Some(players) = self.players_by_key.get_mut(key.as_str()) {
+            for (player_id, details) in players {
                 let sender = match self.sender_by_connection_id.get_mut(player_id) {
                     Some(x) => x,
                     None => return,
                 };
 
                 let message = ConnectionMessage::NewAudioData {
-                    timestamp,
+                    timestamp: details.rebase_timestamp(timestamp),
                     data: data.clone(),
-                    can_be_dropped: true,
+                    drop_policy: DropPolicy::DropUnderLoad,
                 };
 
                 send(&sender, message);
@@ -395,16 +559,20 @@ impl<'a> StreamManager<'a> {
             None => return,
         };
 
-        let mut can_be_dropped = true;
+        let mut drop_policy = DropPolicy::DropAggressively;
         let mut is_key_frame = false;
         if is_video_sequence_header(&data) {
             details.video_sequence_header = Some(data.clone());
-            can_be_dropped = false;
+            drop_policy = DropPolicy::MustDeliver;
         } else if is_video_keyframe(&data) {
-            can_be_dropped = false;
+            drop_policy = DropPolicy::MustDeliver;
             is_key_frame = true;
         }
 
+        if !is_video_sequence_header(&data) {
+            details.buffer_video_frame(timestamp, data.clone(), is_key_frame);
+        }
+
         if let Some(players) = self.players_by_key.get_mut(key.as_str()) {
             for (player_id, mut details) in players {
                 let sender = match self.sender_by_connection_id.get_mut(player_id) {
@@ -415,7 +583,7 @@ impl<'a> StreamManager<'a> {
                 // Only send this video frame if it's a required video frame, or the player has
                 // already received at least one key frame.  If a player joins mid-stream, then
                 // there's no point in sending them video frames without an initial keyframe.
-                if can_be_dropped && !details.has_received_video_keyframe {
+                if drop_policy.can_be_dropped() && !details.has_received_video_keyframe {
                     continue;
                 }
 
@@ -423,10 +591,17 @@ impl<'a> StreamManager<'a> {
                     details.has_received_video_keyframe = true;
                 }
 
+                if let Some(dropped) = details.check_dropped_packets() {
+                    println!(
+                        "Warning: {} packets were dropped for player {} in the last 10 seconds",
+                        dropped, player_id
+                    );
+                }
+
                 let message = ConnectionMessage::NewVideoData {
-                    timestamp,
+                    timestamp: details.rebase_timestamp(timestamp),
                     data: data.clone(),
-                    can_be_dropped,
+                    drop_policy,
                 };
 
                 send(&sender, message);
@@ -463,21 +638,11 @@ impl<'a> StreamManager<'a> {
     }
 }
 
-fn is_video_sequence_header(data: &Bytes) -> bool {
-    // This is assuming h264.
-    return data.len() >= 2 && data[0] == 0x17 && data[1] == 0x00;
-}
-
 fn is_audio_sequence_header(data: &Bytes) -> bool {
     // This is assuming aac
     return data.len() >= 2 && data[0] == 0xaf && data[1] == 0x00;
 }
 
-fn is_video_keyframe(data: &Bytes) -> bool {
-    // assumings h264
-    return data.len() >= 2 && data[0] == 0x17 && data[1] != 0x00; // 0x00 is the sequence header, don't count that for now
-}
-
 async fn wait_for_client_disconnection(
     connection_id: i32,
     mut receiver: UnboundedReceiver<()>,
@@ -487,3 +652,126 @@ async fn wait_for_client_disconnection(
 
     FutureResult::Disconnection { connection_id }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const GROUP_ID: &str = "live/stream";
+    const HIGH_KEY: &str = "live/stream_high";
+    const LOW_KEY: &str = "live/stream_low";
+    const HIGH_PUBLISHER_ID: i32 = 1;
+    const LOW_PUBLISHER_ID: i32 = 2;
+    const PLAYER_ID: i32 = 3;
+
+    #[test]
+    fn player_receives_video_from_selected_quality_stream() {
+        let mut manager = StreamManager::new();
+        add_publisher(&mut manager, HIGH_PUBLISHER_ID, HIGH_KEY, 6000);
+        add_publisher(&mut manager, LOW_PUBLISHER_ID, LOW_KEY, 800);
+        manager.add_quality_group(GROUP_ID, &[HIGH_KEY, LOW_KEY]);
+        let mut receiver = add_player(&mut manager, PLAYER_ID, HIGH_KEY);
+
+        manager.select_quality(PLAYER_ID, GROUP_ID, QualityPreference::Lowest);
+        assert_eq!(
+            manager
+                .key_by_connection_id
+                .get(&PLAYER_ID)
+                .map(|x| x.as_str()),
+            Some(LOW_KEY)
+        );
+
+        manager.handle_new_video_data(HIGH_PUBLISHER_ID, RtmpTimestamp::new(0), high_keyframe());
+        manager.handle_new_video_data(LOW_PUBLISHER_ID, RtmpTimestamp::new(0), low_keyframe());
+
+        let received = received_video(&mut receiver);
+        assert_eq!(received, vec![low_keyframe()]);
+
+        manager.select_quality(PLAYER_ID, GROUP_ID, QualityPreference::ByBitrate(5000));
+        manager.handle_new_video_data(HIGH_PUBLISHER_ID, RtmpTimestamp::new(33), high_keyframe());
+        manager.handle_new_video_data(LOW_PUBLISHER_ID, RtmpTimestamp::new(33), low_keyframe());
+
+        // The high stream's buffered keyframe is sent when switching, followed by the new one
+        let received = received_video(&mut receiver);
+        assert_eq!(received, vec![high_keyframe(), high_keyframe()]);
+    }
+
+    #[test]
+    fn quality_selection_ignores_streams_not_being_published() {
+        let mut manager = StreamManager::new();
+        add_publisher(&mut manager, LOW_PUBLISHER_ID, LOW_KEY, 800);
+        manager.add_quality_group(GROUP_ID, &[HIGH_KEY, LOW_KEY]);
+        let _receiver = add_player(&mut manager, PLAYER_ID, HIGH_KEY);
+
+        manager.select_quality(PLAYER_ID, GROUP_ID, QualityPreference::Highest);
+
+        assert_eq!(
+            manager
+                .key_by_connection_id
+                .get(&PLAYER_ID)
+                .map(|x| x.as_str()),
+            Some(LOW_KEY)
+        );
+    }
+
+    fn add_publisher(manager: &mut StreamManager, connection_id: i32, key: &str, bitrate: u32) {
+        let mut metadata = StreamMetadata::new();
+        metadata.video_bitrate_kbps = Some(bitrate);
+
+        let mut details = PublishDetails::new(connection_id);
+        details.metadata = Some(metadata);
+        manager.publish_details.insert(key.to_string(), details);
+        manager
+            .key_by_connection_id
+            .insert(connection_id, key.to_string());
+    }
+
+    fn add_player(
+        manager: &mut StreamManager,
+        connection_id: i32,
+        key: &str,
+    ) -> UnboundedReceiver<ConnectionMessage> {
+        let (sender, receiver) = mpsc::unbounded_channel();
+        let (_disconnect_sender, disconnection) = mpsc::unbounded_channel();
+        let dropped_packets = Arc::new(DroppedPacketCounter::default());
+        manager.handle_new_connection(
+            connection_id,
+            sender,
+            disconnection,
+            dropped_packets.clone(),
+        );
+
+        manager
+            .players_by_key
+            .entry(key.to_string())
+            .or_insert(HashMap::new())
+            .insert(
+                connection_id,
+                PlayerDetails::new(connection_id, dropped_packets),
+            );
+        manager
+            .key_by_connection_id
+            .insert(connection_id, key.to_string());
+
+        receiver
+    }
+
+    fn received_video(receiver: &mut UnboundedReceiver<ConnectionMessage>) -> Vec<Bytes> {
+        let mut video = Vec::new();
+        while let Ok(message) = receiver.try_recv() {
+            if let ConnectionMessage::NewVideoData { data, .. } = message {
+                video.push(data);
+            }
+        }
+
+        video
+    }
+
+    fn high_keyframe() -> Bytes {
+        Bytes::from(vec![0x17, 0x01, 0x00, 0x00, 0x00, 0x65, 0x01])
+    }
+
+    fn low_keyframe() -> Bytes {
+        Bytes::from(vec![0x17, 0x01, 0x00, 0x00, 0x00, 0x65, 0x02])
+    }
+}
@@ -1,13 +1,59 @@
+use crate::connection::DroppedPacketCounter;
+use rml_rtmp::time::{RtmpTimestamp, TimestampRebaser};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+const DROPPED_PACKET_WINDOW: Duration = Duration::from_secs(10);
+const DROPPED_PACKET_WARNING_THRESHOLD: u64 = 1000;
+
 pub struct PlayerDetails {
     pub connection_id: i32,
     pub has_received_video_keyframe: bool,
+    dropped_packets: Arc<DroppedPacketCounter>,
+    drop_window_started_at: Instant,
+    dropped_at_window_start: u64,
+    timestamp_rebaser: Option<TimestampRebaser>,
 }
 
 impl PlayerDetails {
-    pub fn new(connection_id: i32) -> Self {
+    pub fn new(connection_id: i32, dropped_packets: Arc<DroppedPacketCounter>) -> Self {
+        let dropped_at_window_start = dropped_packets.get();
         PlayerDetails {
             connection_id,
             has_received_video_keyframe: false,
+            dropped_packets,
+            drop_window_started_at: Instant::now(),
+            dropped_at_window_start,
+            timestamp_rebaser: None,
+        }
+    }
+
+    /// Shifts the timestamp of media being forwarded to this player, so the stream starts at
+    /// timestamp 0 for the player even if it joined long after the publisher started.  The
+    /// first timestamp forwarded becomes the player's starting point.
+    pub fn rebase_timestamp(&mut self, timestamp: RtmpTimestamp) -> RtmpTimestamp {
+        self.timestamp_rebaser
+            .get_or_insert_with(|| TimestampRebaser::new(timestamp))
+            .rebase(timestamp)
+    }
+
+    /// Returns the number of packets dropped for this player during the last window, if it was
+    /// enough to indicate the player can't keep up with the stream.  Only returns a value once
+    /// per window.
+    pub fn check_dropped_packets(&mut self) -> Option<u64> {
+        if self.drop_window_started_at.elapsed() < DROPPED_PACKET_WINDOW {
+            return None;
+        }
+
+        let dropped_packets = self.dropped_packets.get();
+        let dropped_in_window = dropped_packets - self.dropped_at_window_start;
+        self.drop_window_started_at = Instant::now();
+        self.dropped_at_window_start = dropped_packets;
+
+        if dropped_in_window > DROPPED_PACKET_WARNING_THRESHOLD {
+            Some(dropped_in_window)
+        } else {
+            None
         }
     }
 }
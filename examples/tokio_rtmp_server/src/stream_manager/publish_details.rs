@@ -1,9 +1,104 @@
 use bytes::Bytes;
-use rml_rtmp::sessions::StreamMetadata;
+use rml_rtmp::sessions::{GopIndex, StreamMetadata};
+use rml_rtmp::time::RtmpTimestamp;
+use std::collections::VecDeque;
+
+// How much video to keep buffered for players joining the stream, in milliseconds
+const VIDEO_BUFFER_DURATION: u32 = 10_000;
+
+pub struct BufferedVideoFrame {
+    pub byte_offset: u64,
+    pub timestamp: RtmpTimestamp,
+    pub data: Bytes,
+    pub is_keyframe: bool,
+}
 
 pub struct PublishDetails {
     pub video_sequence_header: Option<Bytes>,
     pub audio_sequence_header: Option<Bytes>,
     pub metadata: Option<StreamMetadata>,
     pub connection_id: i32,
+    gop_index: GopIndex,
+    buffered_video: VecDeque<BufferedVideoFrame>,
+    next_video_byte_offset: u64,
+}
+
+impl PublishDetails {
+    pub fn new(connection_id: i32) -> Self {
+        PublishDetails {
+            video_sequence_header: None,
+            audio_sequence_header: None,
+            metadata: None,
+            connection_id,
+            gop_index: GopIndex::new(),
+            buffered_video: VecDeque::new(),
+            next_video_byte_offset: 0,
+        }
+    }
+
+    /// Returns the combined audio and video bitrate of the stream in kbps, as reported by the
+    /// publisher's metadata.  Streams without bitrate metadata are treated as 0 kbps.
+    pub fn bitrate_kbps(&self) -> u32 {
+        match &self.metadata {
+            Some(metadata) => {
+                metadata.video_bitrate_kbps.unwrap_or(0) + metadata.audio_bitrate_kbps.unwrap_or(0)
+            }
+
+            None => 0,
+        }
+    }
+
+    /// Adds a video frame to the buffer that newly joined players are started from.  Frames
+    /// older than the buffer duration are discarded, but only a whole group of pictures at a
+    /// time, so the buffer always starts with a keyframe.
+    pub fn buffer_video_frame(&mut self, timestamp: RtmpTimestamp, data: Bytes, is_keyframe: bool) {
+        if self.buffered_video.is_empty() && !is_keyframe {
+            // Frames before the first keyframe can't be decoded by new players
+            return;
+        }
+
+        let byte_offset = self.next_video_byte_offset;
+        self.next_video_byte_offset += data.len() as u64;
+        self.gop_index
+            .record_frame(timestamp, is_keyframe, byte_offset);
+        self.buffered_video.push_back(BufferedVideoFrame {
+            byte_offset,
+            timestamp,
+            data,
+            is_keyframe,
+        });
+
+        let oldest_needed = match self
+            .gop_index
+            .find_nearest_keyframe(timestamp - VIDEO_BUFFER_DURATION)
+        {
+            Some(keyframe) => keyframe,
+            None => return,
+        };
+
+        while let Some(frame) = self.buffered_video.front() {
+            if frame.byte_offset >= oldest_needed.byte_offset {
+                break;
+            }
+
+            self.buffered_video.pop_front();
+        }
+
+        self.gop_index
+            .remove_entries_before(oldest_needed.timestamp);
+    }
+
+    /// Returns the buffered video frames starting with the most recent keyframe, so a player
+    /// joining the stream can start decoding immediately instead of waiting for the next one.
+    pub fn frames_since_latest_keyframe(&self) -> impl Iterator<Item = &BufferedVideoFrame> {
+        let start_offset = self
+            .gop_index
+            .latest_keyframe()
+            .map(|keyframe| keyframe.byte_offset)
+            .unwrap_or(self.next_video_byte_offset);
+
+        self.buffered_video
+            .iter()
+            .filter(move |frame| frame.byte_offset >= start_offset)
+    }
 }
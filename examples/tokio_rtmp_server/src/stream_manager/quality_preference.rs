@@ -0,0 +1,91 @@
+use rml_rtmp::rml_amf0::Amf0Value;
+
+/// Which stream of a quality group a player wants to watch
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum QualityPreference {
+    Highest,
+    Lowest,
+    ByBitrate(u32),
+}
+
+impl QualityPreference {
+    /// Reads the preference from the argument of a `selectQuality` command, which is either the
+    /// string `highest` or `lowest`, or the preferred bitrate in kbps.
+    pub fn from_amf0(value: &Amf0Value) -> Option<Self> {
+        match value {
+            Amf0Value::Utf8String(value) if value == "highest" => Some(QualityPreference::Highest),
+            Amf0Value::Utf8String(value) if value == "lowest" => Some(QualityPreference::Lowest),
+            Amf0Value::Number(_) => value.clone().get_u32().map(QualityPreference::ByBitrate),
+            _ => None,
+        }
+    }
+
+    /// Picks the stream key that best matches the preference from the stream keys being
+    /// published and their bitrates in kbps.  When matching by bitrate, the stream closest to the
+    /// preferred bitrate is picked, preferring the lower bitrate on ties.
+    pub fn choose<'a>(&self, streams: &[(&'a str, u32)]) -> Option<&'a str> {
+        let stream = match *self {
+            QualityPreference::Highest => streams.iter().max_by_key(|(_, bitrate)| *bitrate),
+            QualityPreference::Lowest => streams.iter().min_by_key(|(_, bitrate)| *bitrate),
+            QualityPreference::ByBitrate(target) => streams
+                .iter()
+                .min_by_key(|(_, bitrate)| ((*bitrate as i64 - target as i64).abs(), *bitrate)),
+        };
+
+        stream.map(|(key, _)| *key)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const STREAMS: [(&str, u32); 3] = [
+        ("live/stream_mid", 2500),
+        ("live/stream_high", 6000),
+        ("live/stream_low", 800),
+    ];
+
+    #[test]
+    fn chooses_highest_and_lowest_bitrate_streams() {
+        assert_eq!(
+            QualityPreference::Highest.choose(&STREAMS),
+            Some("live/stream_high")
+        );
+        assert_eq!(
+            QualityPreference::Lowest.choose(&STREAMS),
+            Some("live/stream_low")
+        );
+    }
+
+    #[test]
+    fn chooses_stream_closest_to_preferred_bitrate() {
+        assert_eq!(
+            QualityPreference::ByBitrate(3000).choose(&STREAMS),
+            Some("live/stream_mid")
+        );
+        assert_eq!(
+            QualityPreference::ByBitrate(1650).choose(&STREAMS),
+            Some("live/stream_low"),
+            "Lower bitrate should be preferred on ties"
+        );
+        assert_eq!(
+            QualityPreference::ByBitrate(100_000).choose(&STREAMS),
+            Some("live/stream_high")
+        );
+        assert_eq!(QualityPreference::ByBitrate(3000).choose(&[]), None);
+    }
+
+    #[test]
+    fn can_read_preference_from_amf0_values() {
+        assert_eq!(
+            QualityPreference::from_amf0(&Amf0Value::Utf8String("lowest".to_string())),
+            Some(QualityPreference::Lowest)
+        );
+        assert_eq!(
+            QualityPreference::from_amf0(&Amf0Value::Number(1200.0)),
+            Some(QualityPreference::ByBitrate(1200))
+        );
+        assert_eq!(QualityPreference::from_amf0(&Amf0Value::Null), None);
+    }
+}
@@ -1,7 +1,9 @@
-use super::ConnectionMessage;
+use super::{ConnectionMessage, QualityPreference};
+use crate::connection::DroppedPacketCounter;
 use bytes::Bytes;
-use rml_rtmp::sessions::StreamMetadata;
+use rml_rtmp::sessions::{RequestId, StreamMetadata};
 use rml_rtmp::time::RtmpTimestamp;
+use std::sync::Arc;
 use tokio::sync::mpsc;
 
 #[derive(Debug)]
@@ -10,20 +12,21 @@ pub enum StreamManagerMessage {
         connection_id: i32,
         sender: mpsc::UnboundedSender<ConnectionMessage>,
         disconnection: mpsc::UnboundedReceiver<()>,
+        dropped_packets: Arc<DroppedPacketCounter>,
     },
 
     PublishRequest {
         connection_id: i32,
         rtmp_app: String,
         stream_key: String,
-        request_id: u32,
+        request_id: RequestId,
     },
 
     PlaybackRequest {
         connection_id: i32,
         rtmp_app: String,
         stream_key: String,
-        request_id: u32,
+        request_id: RequestId,
     },
 
     UpdatedStreamMetadata {
@@ -50,4 +53,15 @@ pub enum StreamManagerMessage {
     PlaybackFinished {
         connection_id: i32,
     },
+
+    AddQualityGroup {
+        group_id: String,
+        stream_keys: Vec<String>,
+    },
+
+    SelectQuality {
+        connection_id: i32,
+        group_id: String,
+        preference: QualityPreference,
+    },
 }
@@ -0,0 +1,18 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use rml_rtmp::chunk_io::ChunkDeserializer;
+
+// Feeds arbitrary bytes into the deserializer, as if they were received from an untrusted peer.
+// Every call must either return a message, wait for more bytes, or return an error.
+fuzz_target!(|data: &[u8]| {
+    let mut deserializer = ChunkDeserializer::new();
+    let mut input = data;
+    loop {
+        match deserializer.get_next_message(input) {
+            Ok(Some(_)) => input = &[],
+            Ok(None) => break,
+            Err(_) => break,
+        }
+    }
+});
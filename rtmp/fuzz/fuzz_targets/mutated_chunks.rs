@@ -0,0 +1,67 @@
+#![no_main]
+
+use arbitrary::Arbitrary;
+use bytes::Bytes;
+use libfuzzer_sys::fuzz_target;
+use rml_rtmp::chunk_io::{ChunkDeserializer, ChunkSerializer, DropPolicy};
+use rml_rtmp::messages::MessagePayload;
+use rml_rtmp::time::RtmpTimestamp;
+
+#[derive(Arbitrary, Debug)]
+struct Input {
+    messages: Vec<Message>,
+    mutations: Vec<Mutation>,
+}
+
+#[derive(Arbitrary, Debug)]
+struct Message {
+    type_id: u8,
+    timestamp: u32,
+    message_stream_id: u32,
+    data: Vec<u8>,
+    force_uncompressed: bool,
+}
+
+#[derive(Arbitrary, Debug)]
+struct Mutation {
+    index: usize,
+    value: u8,
+}
+
+// Serializes valid payloads into chunks, then corrupts some of the resulting bytes before
+// deserializing them.  This gets the fuzzer past the chunk header parsing much faster than
+// purely random input would.
+fuzz_target!(|input: Input| {
+    let mut serializer = ChunkSerializer::new();
+    let mut bytes = Vec::new();
+    for message in input.messages {
+        let payload = MessagePayload::new(
+            message.type_id,
+            RtmpTimestamp::new(message.timestamp),
+            message.message_stream_id,
+            Bytes::from(message.data),
+        );
+
+        match serializer.serialize(&payload, message.force_uncompressed, DropPolicy::MustDeliver) {
+            Ok(mut packet) => bytes.append(&mut packet.bytes),
+            Err(_) => return,
+        }
+    }
+
+    if !bytes.is_empty() {
+        for mutation in input.mutations {
+            let index = mutation.index % bytes.len();
+            bytes[index] = mutation.value;
+        }
+    }
+
+    let mut deserializer = ChunkDeserializer::new();
+    let mut input = &bytes[..];
+    loop {
+        match deserializer.get_next_message(input) {
+            Ok(Some(_)) => input = &[],
+            Ok(None) => break,
+            Err(_) => break,
+        }
+    }
+});
@@ -12,13 +12,30 @@ pub enum ChunkDeserializationError {
     #[error(
         "Received chunk with non-zero chunk type on csid {csid} prior to receiving a type 0 chunk"
     )]
-    NoPreviousChunkOnStream { csid: u32 },
+    NoPreviousChunkOnStream {
+        /// The chunk stream id the chunk was received on
+        csid: u32,
+    },
 
     /// The max chunk size does not allow chunk sizes more than 2,147,483,647 (since it's encoded in only
     /// 31 bytes of the SetChunkSize message), so this error occurs when a chunk size of greater than
     /// this value is attempted to be set
     #[error("Requested an invalid max chunk size of {chunk_size}.  The largest chunk size possible is 2147483647")]
-    InvalidMaxChunkSize { chunk_size: usize },
+    InvalidMaxChunkSize {
+        /// The chunk size that was requested
+        chunk_size: usize,
+    },
+
+    /// A chunk header changed the length of a message that had already been partially received
+    /// to a length smaller than the amount of data already received for it.
+    #[error("Chunk header specified a message length of {message_length} but {received_bytes} bytes were already received for the message")]
+    MessageLengthSmallerThanReceivedData {
+        /// The message length specified by the new chunk header
+        message_length: u32,
+
+        /// The number of bytes already received for the message
+        received_bytes: usize,
+    },
 
     /// An I/O error occurred while reading the input buffer
     #[error("{0}")]
@@ -1,6 +1,6 @@
 use super::chunk_header::{ChunkHeader, ChunkHeaderFormat};
 use byteorder::{BigEndian, LittleEndian, ReadBytesExt};
-use bytes::{BufMut, BytesMut};
+use bytes::{BufMut, Bytes, BytesMut};
 use chunk_io::ChunkDeserializationError;
 use messages::MessagePayload;
 use std::cmp::min;
@@ -24,7 +24,10 @@ pub struct ChunkDeserializer {
     current_payload: MessagePayload,
     current_payload_data: BytesMut,
     buffer: BytesMut,
+    pending_input: Bytes,
     previous_headers: HashMap<u32, ChunkHeader>,
+    partial_payloads: HashMap<u32, BytesMut>,
+    peeked_message: Option<MessagePayload>,
 }
 
 enum ParsedValue<T> {
@@ -60,9 +63,12 @@ impl ChunkDeserializer {
             current_header: ChunkHeader::new(),
             current_stage: ParseStage::Csid,
             buffer: BytesMut::with_capacity(4096),
+            pending_input: Bytes::new(),
             previous_headers: HashMap::new(),
-            current_payload: MessagePayload::new(),
+            current_payload: MessagePayload::default(),
             current_payload_data: BytesMut::new(),
+            partial_payloads: HashMap::new(),
+            peeked_message: None,
         }
     }
 
@@ -98,34 +104,34 @@ impl ChunkDeserializer {
     /// # extern crate rml_rtmp;
     /// # use bytes::Bytes;
     /// # use rml_rtmp::time::RtmpTimestamp;
-    /// # use rml_rtmp::chunk_io::{ChunkSerializer, ChunkDeserializer};
+    /// # use rml_rtmp::chunk_io::{ChunkSerializer, ChunkDeserializer, DropPolicy};
     /// # use rml_rtmp::messages::MessagePayload;
     /// # fn main() {
-    /// let input1 = MessagePayload {
-    ///     timestamp: RtmpTimestamp::new(55),
-    ///     message_stream_id: 1,
-    ///     type_id: 15,
-    ///     data: Bytes::from(vec![1, 2, 3, 4, 5, 6]),
-    /// };
+    /// let input1 = MessagePayload::new(
+    ///     15,
+    ///     RtmpTimestamp::new(55),
+    ///     1,
+    ///     Bytes::from(vec![1, 2, 3, 4, 5, 6]),
+    /// );
     ///
-    /// let input2 = MessagePayload {
-    ///     timestamp: RtmpTimestamp::new(65),
-    ///     message_stream_id: 1,
-    ///     type_id: 15,
-    ///     data: Bytes::from(vec![8, 9, 10]),
-    /// };
+    /// let input2 = MessagePayload::new(
+    ///     15,
+    ///     RtmpTimestamp::new(65),
+    ///     1,
+    ///     Bytes::from(vec![8, 9, 10]),
+    /// );
     ///
-    /// let input3 = MessagePayload {
-    ///     timestamp: RtmpTimestamp::new(75),
-    ///     message_stream_id: 1,
-    ///     type_id: 15,
-    ///     data: Bytes::from(vec![1, 2, 3]),
-    /// };
+    /// let input3 = MessagePayload::new(
+    ///     15,
+    ///     RtmpTimestamp::new(75),
+    ///     1,
+    ///     Bytes::from(vec![1, 2, 3]),
+    /// );
     ///
     /// let mut serializer = ChunkSerializer::new();
-    /// let mut packet1 = serializer.serialize(&input1, false, false).unwrap();
-    /// let mut packet2 = serializer.serialize(&input2, false, false).unwrap();
-    /// let mut packet3 = serializer.serialize(&input3, false, false).unwrap();
+    /// let mut packet1 = serializer.serialize(&input1, false, DropPolicy::MustDeliver).unwrap();
+    /// let mut packet2 = serializer.serialize(&input2, false, DropPolicy::MustDeliver).unwrap();
+    /// let mut packet3 = serializer.serialize(&input3, false, DropPolicy::MustDeliver).unwrap();
     ///
     /// let mut all_bytes = Vec::new();
     /// all_bytes.append(&mut packet1.bytes);
@@ -148,7 +154,58 @@ impl ChunkDeserializer {
         &mut self,
         bytes: &[u8],
     ) -> Result<Option<MessagePayload>, ChunkDeserializationError> {
-        self.buffer.extend_from_slice(bytes);
+        self.append_to_buffer(bytes);
+        self.read_next_message()
+    }
+
+    /// Attempts to read a complete RTMP message from the passed in bytes, the same way as
+    /// `get_next_message()` does.
+    ///
+    /// When the message is contained within a single RTMP chunk, the returned payload's data is a
+    /// slice of the passed in `Bytes` and no copy of the data is made.  Messages split across
+    /// multiple chunks still need to be reassembled, and are copied into a new buffer.  The
+    /// passed in bytes are only referenced without copying if no partial chunk was left over from
+    /// a previous call.
+    ///
+    /// Subsequent calls to read any remaining messages should pass in an empty `Bytes`.
+    pub fn get_next_message_bytes(
+        &mut self,
+        bytes: Bytes,
+    ) -> Result<Option<MessagePayload>, ChunkDeserializationError> {
+        if self.buffer.is_empty() && self.pending_input.is_empty() {
+            self.pending_input = bytes;
+        } else {
+            self.append_to_buffer(&bytes[..]);
+        }
+
+        self.read_next_message()
+    }
+
+    /// Returns the type id of the next message without consuming it, so it can be routed before
+    /// it is deserialized.
+    ///
+    /// The next message is assembled the same way as `get_next_message()` would, and is held
+    /// until the following `get_next_message()` call returns it.  That call should be passed an
+    /// empty slice if the bytes were already passed to this function.  Since chunks of different
+    /// chunk streams can be interleaved, this is the type of the next message to be completed,
+    /// which isn't necessarily the type in the next chunk header.  `Ok(None)` is returned if
+    /// the bytes received so far do not complete a message.
+    pub fn peek_message_type(
+        &mut self,
+        bytes: &[u8],
+    ) -> Result<Option<u8>, ChunkDeserializationError> {
+        self.append_to_buffer(bytes);
+        if self.peeked_message.is_none() {
+            self.peeked_message = self.read_next_message()?;
+        }
+
+        Ok(self.peeked_message.as_ref().map(|message| message.type_id))
+    }
+
+    fn read_next_message(&mut self) -> Result<Option<MessagePayload>, ChunkDeserializationError> {
+        if let Some(message) = self.peeked_message.take() {
+            return Ok(Some(message));
+        }
 
         loop {
             let mut complete_message = None;
@@ -196,13 +253,43 @@ impl ChunkDeserializer {
         self.max_chunk_size
     }
 
+    fn append_to_buffer(&mut self, bytes: &[u8]) {
+        if bytes.is_empty() {
+            return;
+        }
+
+        if !self.pending_input.is_empty() {
+            let pending_input = mem::take(&mut self.pending_input);
+            self.buffer.extend_from_slice(&pending_input[..]);
+        }
+
+        self.buffer.extend_from_slice(bytes);
+    }
+
+    /// Returns the bytes that have been received but not yet parsed
+    fn unparsed_bytes(&self) -> &[u8] {
+        if self.pending_input.is_empty() {
+            &self.buffer[..]
+        } else {
+            &self.pending_input[..]
+        }
+    }
+
+    fn take_bytes(&mut self, count: usize) -> Bytes {
+        if self.pending_input.is_empty() {
+            self.buffer.split_to(count).freeze()
+        } else {
+            self.pending_input.split_to(count)
+        }
+    }
+
     fn form_header(&mut self) -> Result<ParseStageResult, ChunkDeserializationError> {
-        if self.buffer.len() < 1 {
+        if self.unparsed_bytes().is_empty() {
             return Ok(ParseStageResult::NotEnoughBytes);
         }
 
-        self.current_header_format = get_format(&self.buffer[0]);
-        let (csid, next_index) = match get_csid(&self.buffer[..]) {
+        self.current_header_format = get_format(&self.unparsed_bytes()[0]);
+        let (csid, next_index) = match get_csid(self.unparsed_bytes()) {
             ParsedValue::NotEnoughBytes => return Ok(ParseStageResult::NotEnoughBytes),
             ParsedValue::Value { val, next_index } => (val, next_index),
         };
@@ -220,7 +307,11 @@ impl ChunkDeserializer {
             },
         };
 
-        let _ = self.buffer.split_to(next_index as usize);
+        // Chunks from different chunk streams can be interleaved, so continue from whatever
+        // part of a message was already received on this chunk stream
+        self.current_payload_data = self.partial_payloads.remove(&csid).unwrap_or_default();
+
+        let _ = self.take_bytes(next_index as usize);
         self.current_stage = ParseStage::InitialTimestamp;
         Ok(ParseStageResult::Success)
     }
@@ -243,13 +334,13 @@ impl ChunkDeserializer {
             return Ok(ParseStageResult::Success);
         }
 
-        if self.buffer.len() < 3 {
+        if self.unparsed_bytes().len() < 3 {
             return Ok(ParseStageResult::NotEnoughBytes);
         }
 
         let timestamp;
         {
-            let bytes = self.buffer.split_to(3);
+            let bytes = self.take_bytes(3);
             let mut cursor = Cursor::new(bytes);
             timestamp = cursor.read_u24::<BigEndian>()?;
         }
@@ -276,13 +367,13 @@ impl ChunkDeserializer {
             return Ok(ParseStageResult::Success);
         }
 
-        if self.buffer.len() < 3 {
+        if self.unparsed_bytes().len() < 3 {
             return Ok(ParseStageResult::NotEnoughBytes);
         }
 
         let length;
         {
-            let bytes = self.buffer.split_to(3);
+            let bytes = self.take_bytes(3);
             let mut cursor = Cursor::new(bytes);
             length = cursor.read_u24::<BigEndian>()?;
         }
@@ -300,12 +391,12 @@ impl ChunkDeserializer {
             return Ok(ParseStageResult::Success);
         }
 
-        if self.buffer.len() < 1 {
+        if self.unparsed_bytes().is_empty() {
             return Ok(ParseStageResult::NotEnoughBytes);
         }
 
-        self.current_header.message_type_id = self.buffer[0];
-        let _ = self.buffer.split_to(1);
+        self.current_header.message_type_id = self.unparsed_bytes()[0];
+        let _ = self.take_bytes(1);
         self.current_stage = ParseStage::MessageStreamId;
         Ok(ParseStageResult::Success)
     }
@@ -316,13 +407,13 @@ impl ChunkDeserializer {
             return Ok(ParseStageResult::Success);
         }
 
-        if self.buffer.len() < 4 {
+        if self.unparsed_bytes().len() < 4 {
             return Ok(ParseStageResult::NotEnoughBytes);
         }
 
         let stream_id;
         {
-            let bytes = self.buffer.split_to(4);
+            let bytes = self.take_bytes(4);
             let mut cursor = Cursor::new(bytes);
             stream_id = cursor.read_u32::<LittleEndian>()?;
         }
@@ -338,13 +429,13 @@ impl ChunkDeserializer {
             return Ok(ParseStageResult::Success);
         }
 
-        if self.buffer.len() < 4 {
+        if self.unparsed_bytes().len() < 4 {
             return Ok(ParseStageResult::NotEnoughBytes);
         }
 
         let timestamp;
         {
-            let bytes = self.buffer.split_to(4);
+            let bytes = self.take_bytes(4);
             let mut cursor = Cursor::new(bytes);
             timestamp = cursor.read_u32::<BigEndian>()?;
         }
@@ -355,7 +446,7 @@ impl ChunkDeserializer {
         } else if self.current_payload_data.len() == 0 {
             // Since we already added the MAX_INITIAL_TIMESTAMP to the timestamp, only add the delta difference
             self.current_header.timestamp =
-                self.current_header.timestamp + (timestamp - MAX_INITIAL_TIMESTAMP);
+                self.current_header.timestamp + timestamp.wrapping_sub(MAX_INITIAL_TIMESTAMP);
         }
 
         self.current_stage = ParseStage::MessagePayload;
@@ -368,12 +459,21 @@ impl ChunkDeserializer {
     ) -> Result<ParseStageResult, ChunkDeserializationError> {
         let mut length = self.current_header.message_length as usize;
         let current_payload_length = self.current_payload_data.len();
+        if current_payload_length > length {
+            return Err(
+                ChunkDeserializationError::MessageLengthSmallerThanReceivedData {
+                    message_length: self.current_header.message_length,
+                    received_bytes: current_payload_length,
+                },
+            );
+        }
+
         let remaining_bytes = length - current_payload_length;
         if length > self.max_chunk_size as usize {
             length = min(remaining_bytes, self.max_chunk_size as usize);
         }
 
-        if self.buffer.len() < length {
+        if self.unparsed_bytes().len() < length {
             return Ok(ParseStageResult::NotEnoughBytes);
         }
 
@@ -381,23 +481,38 @@ impl ChunkDeserializer {
         self.current_payload.type_id = self.current_header.message_type_id;
         self.current_payload.message_stream_id = self.current_header.message_stream_id;
 
-        // Make sure the we have enough capacity for the whole message data.  This
-        // helps with performance when there are smaller chunk sizes.
-        if remaining_bytes > self.current_payload_data.remaining_mut() {
-            let capacity_needed = remaining_bytes - self.current_payload_data.remaining_mut();
-            self.current_payload_data.reserve(capacity_needed);
-        }
+        if current_payload_length == 0 && remaining_bytes == length {
+            // The whole message is contained in this chunk, so no reassembly is needed and the
+            // data can be handed out without copying it
+            self.current_payload.data = self.take_bytes(length);
 
-        let bytes = self.buffer.split_to(length as usize);
-        self.current_payload_data.extend_from_slice(&bytes[..]);
+            let payload = mem::take(&mut self.current_payload);
+            *message_to_return = Some(payload)
+        } else {
+            // Make sure the we have enough capacity for the whole message data.  This
+            // helps with performance when there are smaller chunk sizes.
+            if remaining_bytes > self.current_payload_data.remaining_mut() {
+                let capacity_needed = remaining_bytes - self.current_payload_data.remaining_mut();
+                self.current_payload_data.reserve(capacity_needed);
+            }
 
-        // Check if this completes the message
-        if self.current_payload_data.len() == self.current_header.message_length as usize {
-            let data = mem::replace(&mut self.current_payload_data, BytesMut::new());
-            self.current_payload.data = data.freeze();
+            let bytes = self.take_bytes(length);
+            self.current_payload_data.extend_from_slice(&bytes[..]);
 
-            let payload = mem::replace(&mut self.current_payload, MessagePayload::new());
-            *message_to_return = Some(payload)
+            // Check if this completes the message
+            if self.current_payload_data.len() == self.current_header.message_length as usize {
+                let data = mem::replace(&mut self.current_payload_data, BytesMut::new());
+                self.current_payload.data = data.freeze();
+
+                let payload = mem::take(&mut self.current_payload);
+                *message_to_return = Some(payload)
+            }
+        }
+
+        if !self.current_payload_data.is_empty() {
+            let data = mem::take(&mut self.current_payload_data);
+            self.partial_payloads
+                .insert(self.current_header.chunk_stream_id, data);
         }
 
         // This completes the current chunk, so cycle the header into the map and start a new one
@@ -925,6 +1040,23 @@ mod tests {
         assert_eq!(&result.data[..], &payload[..], "Incorrect data");
     }
 
+    #[test]
+    fn max_chunk_size_reflects_changes() {
+        let mut deserializer = ChunkDeserializer::new();
+        assert_eq!(
+            deserializer.get_max_chunk_size(),
+            INITIAL_MAX_CHUNK_SIZE,
+            "Unexpected initial max chunk size"
+        );
+
+        deserializer.set_max_chunk_size(4096).unwrap();
+        assert_eq!(
+            deserializer.get_max_chunk_size(),
+            4096,
+            "Max chunk size was not updated"
+        );
+    }
+
     #[test]
     fn error_when_setting_chunk_size_too_large() {
         const CHUNK_SIZE_VALUE: usize = 2147483648;
@@ -937,6 +1069,29 @@ mod tests {
         }
     }
 
+    #[test]
+    fn error_when_type_1_chunk_shrinks_length_of_partially_received_message() {
+        let csid = 50;
+        let payload = [5_u8; 200];
+        let chunk_0_bytes = form_type_0_chunk(csid, 25, 5, 9, &payload, INITIAL_MAX_CHUNK_SIZE);
+        let chunk_1_bytes = form_type_1_chunk(csid, 10, 9, &[1_u8, 2_u8, 3_u8]);
+
+        // Only send the first chunk of the split message, with a 12 byte header
+        let mut deserializer = ChunkDeserializer::new();
+        let result = deserializer
+            .get_next_message(&chunk_0_bytes[..12 + INITIAL_MAX_CHUNK_SIZE])
+            .unwrap();
+        assert!(result.is_none(), "Expected no message to be returned");
+
+        match deserializer.get_next_message(&chunk_1_bytes) {
+            Err(ChunkDeserializationError::MessageLengthSmallerThanReceivedData {
+                message_length: 3,
+                received_bytes: INITIAL_MAX_CHUNK_SIZE,
+            }) => {} // success
+            x => panic!("Unexpected deserialization result of {:?}", x),
+        }
+    }
+
     #[test]
     fn type_2_chunk_that_exceeds_max_chunk_size_does_not_keep_applying_delta_to_timestamp() {
         // It was noticed that OBS does not totally conform to the RTMP specification.  It will
@@ -1001,6 +1156,157 @@ mod tests {
         );
     }
 
+    #[test]
+    fn peeking_message_type_does_not_change_messages_returned() {
+        let payload1 = [1_u8, 2_u8, 3_u8, 4_u8, 5_u8, 6_u8];
+        let payload2 = [7_u8, 8_u8];
+        let mut bytes = form_type_0_chunk(50, 25, 5, 9, &payload1, 4);
+        bytes.extend(form_type_0_chunk(51, 30, 5, 8, &payload2, 4));
+
+        let mut expected = Vec::new();
+        let mut deserializer = ChunkDeserializer::new();
+        deserializer.set_max_chunk_size(4).unwrap();
+        let mut input = &bytes[..];
+        while let Some(message) = deserializer.get_next_message(input).unwrap() {
+            input = &[];
+            expected.push(message);
+        }
+
+        let mut deserializer = ChunkDeserializer::new();
+        deserializer.set_max_chunk_size(4).unwrap();
+        assert_eq!(deserializer.peek_message_type(&bytes).unwrap(), Some(9));
+        assert_eq!(
+            deserializer.peek_message_type(&[]).unwrap(),
+            Some(9),
+            "Peeking twice should not skip the message"
+        );
+
+        let message1 = deserializer.get_next_message(&[]).unwrap();
+        assert_eq!(deserializer.peek_message_type(&[]).unwrap(), Some(8));
+        let message2 = deserializer.get_next_message(&[]).unwrap();
+        assert_eq!(deserializer.peek_message_type(&[]).unwrap(), None);
+
+        assert_eq!(vec![message1.unwrap(), message2.unwrap()], expected);
+    }
+
+    #[test]
+    fn peeking_partial_message_returns_none_until_message_is_complete() {
+        let payload = [1_u8, 2_u8, 3_u8];
+        let bytes = form_type_0_chunk(50, 25, 5, 9, &payload, INITIAL_MAX_CHUNK_SIZE);
+
+        let mut deserializer = ChunkDeserializer::new();
+        assert_eq!(deserializer.peek_message_type(&bytes[..5]).unwrap(), None);
+        assert_eq!(deserializer.peek_message_type(&bytes[5..12]).unwrap(), None);
+        assert_eq!(
+            deserializer.peek_message_type(&bytes[12..]).unwrap(),
+            Some(9)
+        );
+
+        let message = deserializer.get_next_message(&[]).unwrap().unwrap();
+        assert_eq!(message.type_id, 9, "Incorrect type id");
+        assert_eq!(&message.data[..], &payload[..], "Incorrect data");
+    }
+
+    #[test]
+    fn can_read_messages_interleaved_across_chunk_streams() {
+        let video = [1_u8, 2_u8, 3_u8, 4_u8, 5_u8, 6_u8];
+        let audio = [7_u8, 8_u8];
+        let video_chunks = form_type_0_chunk(5, 25, 1, 9, &video, 4);
+        let audio_chunk = form_type_0_chunk(4, 30, 1, 8, &audio, 4);
+
+        // The audio message arrives between the two chunks of the video message
+        let first_video_chunk_length = 12 + 4;
+        let mut bytes = video_chunks[..first_video_chunk_length].to_vec();
+        bytes.extend(audio_chunk);
+        bytes.extend(&video_chunks[first_video_chunk_length..]);
+
+        let mut deserializer = ChunkDeserializer::new();
+        deserializer.set_max_chunk_size(4).unwrap();
+        let message1 = deserializer.get_next_message(&bytes).unwrap().unwrap();
+        let message2 = deserializer.get_next_message(&[]).unwrap().unwrap();
+
+        assert_eq!(message1.type_id, 8, "Incorrect first type id");
+        assert_eq!(&message1.data[..], &audio[..], "Incorrect audio data");
+        assert_eq!(message2.type_id, 9, "Incorrect second type id");
+        assert_eq!(&message2.data[..], &video[..], "Incorrect video data");
+        assert_eq!(deserializer.get_next_message(&[]).unwrap(), None);
+    }
+
+    #[test]
+    fn peeking_returns_type_of_next_completed_message_when_chunk_streams_interleave() {
+        let video = [1_u8, 2_u8, 3_u8, 4_u8, 5_u8, 6_u8];
+        let audio = [7_u8, 8_u8];
+        let video_chunks = form_type_0_chunk(5, 25, 1, 9, &video, 4);
+        let audio_chunk = form_type_0_chunk(4, 30, 1, 8, &audio, 4);
+
+        let first_video_chunk_length = 12 + 4;
+        let mut bytes = video_chunks[..first_video_chunk_length].to_vec();
+        bytes.extend(audio_chunk);
+        bytes.extend(&video_chunks[first_video_chunk_length..]);
+
+        let mut deserializer = ChunkDeserializer::new();
+        deserializer.set_max_chunk_size(4).unwrap();
+        assert_eq!(deserializer.peek_message_type(&bytes).unwrap(), Some(8));
+
+        let message1 = deserializer.get_next_message(&[]).unwrap().unwrap();
+        assert_eq!(message1.type_id, 8, "Incorrect first type id");
+
+        assert_eq!(deserializer.peek_message_type(&[]).unwrap(), Some(9));
+        let message2 = deserializer.get_next_message(&[]).unwrap().unwrap();
+        assert_eq!(&message2.data[..], &video[..], "Incorrect video data");
+    }
+
+    #[test]
+    fn single_chunk_message_from_bytes_references_input_without_copying() {
+        let payload = [1_u8, 2_u8, 3_u8];
+        let chunk = form_type_0_chunk(50, 25, 5, 9, &payload, INITIAL_MAX_CHUNK_SIZE);
+        let input = Bytes::from(chunk);
+        let input_range = input.as_ptr() as usize..input.as_ptr() as usize + input.len();
+
+        let mut deserializer = ChunkDeserializer::new();
+        let result = deserializer
+            .get_next_message_bytes(input.clone())
+            .unwrap()
+            .unwrap();
+
+        assert_eq!(&result.data[..], &payload[..], "Incorrect data");
+        assert!(
+            input_range.contains(&(result.data.as_ptr() as usize)),
+            "Expected payload data to be a slice of the input bytes"
+        );
+    }
+
+    #[test]
+    fn can_read_split_and_partial_messages_from_bytes() {
+        let payload1 = [1_u8, 2_u8, 3_u8, 4_u8, 5_u8, 6_u8];
+        let payload2 = [7_u8, 8_u8];
+        let mut bytes = form_type_0_chunk(50, 25, 5, 9, &payload1, 4);
+        bytes.extend(form_type_0_chunk(50, 30, 5, 9, &payload2, 4));
+
+        let mut deserializer = ChunkDeserializer::new();
+        deserializer.set_max_chunk_size(4).unwrap();
+        let first_read = Bytes::from(bytes[..10].to_vec());
+        let second_read = Bytes::from(bytes[10..].to_vec());
+
+        let result1 = deserializer.get_next_message_bytes(first_read).unwrap();
+        let result2 = deserializer.get_next_message_bytes(second_read).unwrap();
+        let result3 = deserializer.get_next_message_bytes(Bytes::new()).unwrap();
+        let result4 = deserializer.get_next_message_bytes(Bytes::new()).unwrap();
+
+        assert_eq!(result1, None, "Expected no message from partial chunk");
+        assert_eq!(
+            &result2.unwrap().data[..],
+            &payload1[..],
+            "Incorrect payload 1 data"
+        );
+        assert_eq!(
+            &result3.unwrap().data[..],
+            &payload2[..],
+            "Incorrect payload 2 data"
+        );
+        assert_eq!(result4, None, "Expected no more messages");
+    }
+
     fn form_type_0_chunk(
         csid: u32,
         timestamp: u32,
@@ -30,18 +30,18 @@ provide input and output buffers with minimal allocations.
 # extern crate rml_rtmp;
 # use bytes::Bytes;
 # use rml_rtmp::time::RtmpTimestamp;
-# use rml_rtmp::chunk_io::{ChunkSerializer, ChunkDeserializer};
+# use rml_rtmp::chunk_io::{ChunkSerializer, ChunkDeserializer, DropPolicy};
 # use rml_rtmp::messages::MessagePayload;
 # fn main() {
-let input1 = MessagePayload {
-    timestamp: RtmpTimestamp::new(55),
-    message_stream_id: 1,
-    type_id: 15,
-    data: Bytes::from(vec![1, 2, 3, 4, 5, 6]),
-};
+let input1 = MessagePayload::new(
+    15,
+    RtmpTimestamp::new(55),
+    1,
+    Bytes::from(vec![1, 2, 3, 4, 5, 6]),
+);
 
 let mut serializer = ChunkSerializer::new();
-let packet1 = serializer.serialize(&input1, false, false).unwrap();
+let packet1 = serializer.serialize(&input1, false, DropPolicy::MustDeliver).unwrap();
 
 let mut deserializer = ChunkDeserializer::new();
 let output1 = deserializer.get_next_message(&packet1.bytes).unwrap().unwrap();
@@ -61,7 +61,7 @@ mod serializer;
 pub use self::deserialization_errors::ChunkDeserializationError;
 pub use self::deserializer::ChunkDeserializer;
 pub use self::serialization_errors::ChunkSerializationError;
-pub use self::serializer::{ChunkSerializer, Packet};
+pub use self::serializer::{ChunkSerializer, DropPolicy, Packet, PartialMessage};
 
 #[cfg(test)]
 mod tests {
@@ -72,31 +72,28 @@ mod tests {
 
     #[test]
     fn can_deserialize_messages_serialized_by_chunk_serializer_struct() {
-        let input1 = MessagePayload {
-            timestamp: RtmpTimestamp::new(55),
-            message_stream_id: 1,
-            type_id: 15,
-            data: Bytes::from(vec![1, 2, 3, 4, 5, 6]),
-        };
-
-        let input2 = MessagePayload {
-            timestamp: RtmpTimestamp::new(65),
-            message_stream_id: 1,
-            type_id: 15,
-            data: Bytes::from(vec![8, 9, 10]),
-        };
-
-        let input3 = MessagePayload {
-            timestamp: RtmpTimestamp::new(75),
-            message_stream_id: 1,
-            type_id: 15,
-            data: Bytes::from(vec![1, 2, 3]),
-        };
+        let input1 = MessagePayload::new(
+            15,
+            RtmpTimestamp::new(55),
+            1,
+            Bytes::from(vec![1, 2, 3, 4, 5, 6]),
+        );
+
+        let input2 =
+            MessagePayload::new(15, RtmpTimestamp::new(65), 1, Bytes::from(vec![8, 9, 10]));
+
+        let input3 = MessagePayload::new(15, RtmpTimestamp::new(75), 1, Bytes::from(vec![1, 2, 3]));
 
         let mut serializer = ChunkSerializer::new();
-        let packet1 = serializer.serialize(&input1, false, false).unwrap();
-        let packet2 = serializer.serialize(&input2, false, false).unwrap();
-        let packet3 = serializer.serialize(&input3, false, false).unwrap();
+        let packet1 = serializer
+            .serialize(&input1, false, DropPolicy::MustDeliver)
+            .unwrap();
+        let packet2 = serializer
+            .serialize(&input2, false, DropPolicy::MustDeliver)
+            .unwrap();
+        let packet3 = serializer
+            .serialize(&input3, false, DropPolicy::MustDeliver)
+            .unwrap();
 
         let mut deserializer = ChunkDeserializer::new();
         let output1 = deserializer
@@ -128,31 +125,28 @@ mod tests {
 
     #[test]
     fn can_deserialize_messages_serialized_with_decreasing_time() {
-        let input1 = MessagePayload {
-            timestamp: RtmpTimestamp::new(65),
-            message_stream_id: 1,
-            type_id: 15,
-            data: Bytes::from(vec![1, 2, 3, 4, 5, 6]),
-        };
-
-        let input2 = MessagePayload {
-            timestamp: RtmpTimestamp::new(55),
-            message_stream_id: 1,
-            type_id: 15,
-            data: Bytes::from(vec![8, 9, 10]),
-        };
-
-        let input3 = MessagePayload {
-            timestamp: RtmpTimestamp::new(45),
-            message_stream_id: 1,
-            type_id: 15,
-            data: Bytes::from(vec![1, 2, 3]),
-        };
+        let input1 = MessagePayload::new(
+            15,
+            RtmpTimestamp::new(65),
+            1,
+            Bytes::from(vec![1, 2, 3, 4, 5, 6]),
+        );
+
+        let input2 =
+            MessagePayload::new(15, RtmpTimestamp::new(55), 1, Bytes::from(vec![8, 9, 10]));
+
+        let input3 = MessagePayload::new(15, RtmpTimestamp::new(45), 1, Bytes::from(vec![1, 2, 3]));
 
         let mut serializer = ChunkSerializer::new();
-        let packet1 = serializer.serialize(&input1, false, false).unwrap();
-        let packet2 = serializer.serialize(&input2, false, false).unwrap();
-        let packet3 = serializer.serialize(&input3, false, false).unwrap();
+        let packet1 = serializer
+            .serialize(&input1, false, DropPolicy::MustDeliver)
+            .unwrap();
+        let packet2 = serializer
+            .serialize(&input2, false, DropPolicy::MustDeliver)
+            .unwrap();
+        let packet3 = serializer
+            .serialize(&input3, false, DropPolicy::MustDeliver)
+            .unwrap();
 
         let mut deserializer = ChunkDeserializer::new();
         let output1 = deserializer
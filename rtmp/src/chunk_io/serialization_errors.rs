@@ -12,14 +12,49 @@ pub enum ChunkSerializationError {
     /// when split across multiple RTMP chunks.  This error is returned if an RTMP message is passed
     /// in that is larger than this amount.
     #[error("The current message has a length of {size} bytes, which is over the allowed size of 16777215 bytes")]
-    MessageTooLong { size: u32 },
+    MessageTooLong {
+        /// The length of the message, in bytes
+        size: u32,
+    },
 
     /// The RTMP spec does not allow chunk sizes more than 2,147,483,647 (since it's encoded in only
     /// 31 bytes of the SetChunkSize message), so this error occurs when a chunk size of greater than
     /// this value is attempted to be set
     #[error("An invalid chunk size was specified.  Chunk size must be greater than 0 and less than 2147483647"
     )]
-    InvalidMaxChunkSize { attempted_chunk_size: u32 },
+    InvalidMaxChunkSize {
+        /// The chunk size that was requested
+        attempted_chunk_size: u32,
+    },
+
+    /// Chunk stream ids 0 and 1 are used by the basic header to signal 2 and 3 byte ids, and
+    /// chunk stream id 2 is reserved for protocol control messages, so this error is returned
+    /// when a message is requested to be serialized on one of those chunk streams.
+    #[error("Chunk stream id {chunk_stream_id} is reserved and cannot be used for messages")]
+    ReservedChunkStreamId {
+        /// The reserved chunk stream id that was requested
+        chunk_stream_id: u8,
+    },
+
+    /// More data was provided for a partial message than the message length it was started with
+    #[error("A partial message with a length of {message_length} bytes was given {attempted_length} bytes")]
+    PartialMessageOverflow {
+        /// The length the partial message was started with
+        message_length: u32,
+
+        /// How many bytes the message would have contained if the data had been serialized
+        attempted_length: u64,
+    },
+
+    /// A partial message was finished before all of its data was serialized
+    #[error("A partial message with a length of {message_length} bytes was finished after only {bytes_serialized} bytes")]
+    PartialMessageIncomplete {
+        /// The length the partial message was started with
+        message_length: u32,
+
+        /// How many bytes of the message were serialized
+        bytes_serialized: u32,
+    },
 
     /// An I/O error occurred while writing the output buffer
     #[error("{0}")]
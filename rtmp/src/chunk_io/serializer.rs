@@ -10,15 +10,82 @@ use time::RtmpTimestamp;
 const INITIAL_MAX_CHUNK_SIZE: u32 = 128;
 const MAX_INITIAL_TIMESTAMP: u32 = 16777215;
 
+/// Describes if a packet is allowed to be dropped instead of being sent to the peer.
+///
+/// Video and audio packets may be allowed to be dropped if there is not enough bandwidth for the
+/// current bitrate.  This allows live video to be kept in real time and to prevent getting
+/// backed up when redistributing live video when the network conditions don't allow the current
+/// bitrate.  How aggressively a packet can be dropped depends on its contents, as losing a video
+/// keyframe is much more noticeable than losing an inter-frame.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum DropPolicy {
+    /// The packet must always be sent, such as for commands, sequence headers, and keyframes
+    MustDeliver,
+
+    /// The packet may be dropped when the connection is under heavy load (e.g. audio data)
+    DropUnderLoad,
+
+    /// The packet may be dropped as soon as the connection starts falling behind
+    /// (e.g. video inter-frames)
+    DropAggressively,
+}
+
+impl DropPolicy {
+    /// Returns true if the packet is allowed to be dropped under any circumstances
+    pub fn can_be_dropped(&self) -> bool {
+        *self != DropPolicy::MustDeliver
+    }
+}
+
 /// An outbound data packet containing the at least one RTMP chunk with a single RTMP message.
-/// The packet can be flagged as droppable because video and audio packets may be allowed to be
-/// dropped if there is not enough bandwidth for the current bitrate.  This allows live video
-/// to be kept in real time and to prevent getting backed up when redistributing live video when
-/// the network conditions don't allow the current bitrate.
+/// The packet's drop policy describes if the network transport is allowed to drop it instead of
+/// sending it to the peer.
 #[derive(Debug, PartialEq)]
 pub struct Packet {
+    /// The serialized RTMP chunks that should be sent to the peer
     pub bytes: Vec<u8>,
-    pub can_be_dropped: bool,
+
+    /// Whether the packet may be dropped instead of being sent
+    pub drop_policy: DropPolicy,
+}
+
+/// An RTMP message that is being serialized a piece at a time with
+/// `ChunkSerializer::serialize_partial()`, so the whole message never needs to be in memory.
+#[derive(Debug)]
+pub struct PartialMessage {
+    csid: u32,
+    type_id: u8,
+    timestamp: RtmpTimestamp,
+    message_stream_id: u32,
+    message_length: u32,
+    max_chunk_size: u32,
+    bytes_serialized: u32,
+}
+
+impl PartialMessage {
+    /// Returns how many bytes of the message have not been serialized yet
+    pub fn remaining_length(&self) -> u32 {
+        self.message_length - self.bytes_serialized
+    }
+
+    /// Returns true once every byte of the message has been serialized
+    pub fn is_complete(&self) -> bool {
+        self.bytes_serialized == self.message_length
+    }
+
+    /// Verifies that every byte of the message has been serialized.  RTMP has no marker for the
+    /// end of a message (the peer knows a message is complete once it has received as many bytes
+    /// as the first chunk's header said it would), so this does not produce any bytes itself.
+    pub fn finish(self) -> Result<(), ChunkSerializationError> {
+        if !self.is_complete() {
+            return Err(ChunkSerializationError::PartialMessageIncomplete {
+                message_length: self.message_length,
+                bytes_serialized: self.bytes_serialized,
+            });
+        }
+
+        Ok(())
+    }
 }
 
 /// Allows serializing RTMP messages into RTMP chunks.
@@ -62,7 +129,7 @@ impl ChunkSerializer {
 
         let set_chunk_size_message = RtmpMessage::SetChunkSize { size: new_size };
         let message_payload = MessagePayload::from_rtmp_message(set_chunk_size_message, time, 0)?;
-        let packet = self.serialize(&message_payload, true, false)?;
+        let packet = self.serialize(&message_payload, true, DropPolicy::MustDeliver)?;
 
         self.max_chunk_size = new_size;
         Ok(packet)
@@ -83,16 +150,141 @@ impl ChunkSerializer {
     /// properly without it.
     ///
     /// If the message to be serialized is a video or audio data message, and it's not a a/v header,
-    /// then it can be safe to set `can_be_dropped` to `true`.  This will mark the packet so that
-    /// the network transport mechanisms can make a decision if the packet should be dropped (if
-    /// there's not enough bandwidth to keep the stream in real time) or if it should be enqueued
-    /// even if backlogged.   Setting this to `true` makes sure that if the packet is dropped that
-    /// the receiver will not have deserialization problems on any subsequent RTMP chunks.
+    /// then it can be safe to use a `drop_policy` other than `DropPolicy::MustDeliver`.  This will
+    /// mark the packet so that the network transport mechanisms can make a decision if the packet
+    /// should be dropped (if there's not enough bandwidth to keep the stream in real time) or if it
+    /// should be enqueued even if backlogged.   Marking a packet as droppable makes sure that if the
+    /// packet is dropped that the receiver will not have deserialization problems on any
+    /// subsequent RTMP chunks.
+    ///
+    /// The chunk stream the message is sent on is picked based on the message's type, so that
+    /// audio, video, and command messages are interleaved on separate chunk streams.
     pub fn serialize(
         &mut self,
         message: &MessagePayload,
         force_uncompressed: bool,
-        can_be_dropped: bool,
+        drop_policy: DropPolicy,
+    ) -> Result<Packet, ChunkSerializationError> {
+        let csid = get_csid_for_message_type(message.type_id);
+        self.serialize_with_csid(message, csid, force_uncompressed, drop_policy)
+    }
+
+    /// Serializes an RTMP message payload the same as `serialize()`, but sends it on the
+    /// specified chunk stream instead of one picked based on the message's type.
+    ///
+    /// Chunk stream ids 0, 1, and 2 are reserved by the RTMP specification, and attempting to
+    /// use them will return a `ReservedChunkStreamId` error.
+    pub fn serialize_on_stream(
+        &mut self,
+        message: &MessagePayload,
+        chunk_stream_id: u8,
+        force_uncompressed: bool,
+        drop_policy: DropPolicy,
+    ) -> Result<Packet, ChunkSerializationError> {
+        if chunk_stream_id <= 2 {
+            return Err(ChunkSerializationError::ReservedChunkStreamId { chunk_stream_id });
+        }
+
+        self.serialize_with_csid(
+            message,
+            chunk_stream_id as u32,
+            force_uncompressed,
+            drop_policy,
+        )
+    }
+
+    /// Starts serializing a message whose payload will be provided in parts with
+    /// `serialize_partial()`.  Since the length of a message is part of its first chunk's
+    /// header, the length of the whole payload must be known up front.
+    ///
+    /// Until the partial message is complete, no other message may be serialized on its chunk
+    /// stream and the maximum chunk size must not be changed.  Partial messages are always
+    /// serialized with `DropPolicy::MustDeliver`, as dropping one part would leave the peer
+    /// unable to deserialize the rest of the message.
+    pub fn begin_partial_message(
+        &self,
+        type_id: u8,
+        timestamp: RtmpTimestamp,
+        message_stream_id: u32,
+        message_length: u32,
+    ) -> Result<PartialMessage, ChunkSerializationError> {
+        if message_length > 16777215 {
+            return Err(ChunkSerializationError::MessageTooLong {
+                size: message_length,
+            });
+        }
+
+        Ok(PartialMessage {
+            csid: get_csid_for_message_type(type_id),
+            type_id,
+            timestamp,
+            message_stream_id,
+            message_length,
+            max_chunk_size: self.max_chunk_size,
+            bytes_serialized: 0,
+        })
+    }
+
+    /// Serializes the next part of a partial message's payload into RTMP chunks.  Parts do not
+    /// need to line up with chunk boundaries, as a part that ends mid chunk is continued by the
+    /// start of the next part.
+    pub fn serialize_partial(
+        &mut self,
+        message: &mut PartialMessage,
+        data: &[u8],
+    ) -> Result<Packet, ChunkSerializationError> {
+        if data.len() as u64 > message.remaining_length() as u64 {
+            return Err(ChunkSerializationError::PartialMessageOverflow {
+                message_length: message.message_length,
+                attempted_length: message.bytes_serialized as u64 + data.len() as u64,
+            });
+        }
+
+        // Each chunk header is at most 18 bytes, so this is enough that writing never reallocates
+        let chunk_count = data.len() / message.max_chunk_size as usize + 2;
+        let mut bytes = Cursor::new(Vec::with_capacity(data.len() + chunk_count * 18));
+        let mut remaining = data;
+        while !remaining.is_empty() {
+            let chunk_offset = message.bytes_serialized % message.max_chunk_size;
+            let length = min(
+                (message.max_chunk_size - chunk_offset) as usize,
+                remaining.len(),
+            );
+
+            let (slice, rest) = remaining.split_at(length);
+            if chunk_offset == 0 {
+                let header = ChunkHeader {
+                    chunk_stream_id: message.csid,
+                    timestamp: message.timestamp,
+                    timestamp_field: 0,
+                    message_type_id: message.type_id,
+                    message_stream_id: message.message_stream_id,
+                    message_length: message.message_length,
+                    can_be_dropped: false,
+                };
+
+                let continued_chunk = message.bytes_serialized > 0;
+                self.add_chunk(&mut bytes, header, false, continued_chunk, slice)?;
+            } else {
+                add_message_payload(&mut bytes, slice)?;
+            }
+
+            message.bytes_serialized += length as u32;
+            remaining = rest;
+        }
+
+        Ok(Packet {
+            bytes: bytes.into_inner(),
+            drop_policy: DropPolicy::MustDeliver,
+        })
+    }
+
+    fn serialize_with_csid(
+        &mut self,
+        message: &MessagePayload,
+        csid: u32,
+        force_uncompressed: bool,
+        drop_policy: DropPolicy,
     ) -> Result<Packet, ChunkSerializationError> {
         if message.data.len() > 16777215 {
             return Err(ChunkSerializationError::MessageTooLong {
@@ -124,41 +316,33 @@ impl ChunkSerializer {
         }
 
         for (idx, slice) in slices.into_iter().enumerate() {
-            self.add_chunk(
-                &mut bytes,
-                force_uncompressed,
-                message,
-                idx > 0,
-                slice,
-                can_be_dropped,
-            )?;
+            let header = ChunkHeader {
+                chunk_stream_id: csid,
+                timestamp: message.timestamp,
+                timestamp_field: 0,
+                message_type_id: message.type_id,
+                message_stream_id: message.message_stream_id,
+                message_length: message.data.len() as u32,
+                can_be_dropped: drop_policy.can_be_dropped(),
+            };
+
+            self.add_chunk(&mut bytes, header, force_uncompressed, idx > 0, slice)?;
         }
 
         Ok(Packet {
             bytes: bytes.into_inner(),
-            can_be_dropped,
+            drop_policy,
         })
     }
 
     fn add_chunk(
         &mut self,
         bytes: &mut Cursor<Vec<u8>>,
+        mut header: ChunkHeader,
         force_uncompressed: bool,
-        message: &MessagePayload,
         continued_chunk: bool,
         data_to_write: &[u8],
-        can_be_dropped: bool,
     ) -> Result<(), ChunkSerializationError> {
-        let mut header = ChunkHeader {
-            chunk_stream_id: get_csid_for_message_type(message.type_id),
-            timestamp: message.timestamp,
-            timestamp_field: 0,
-            message_type_id: message.type_id,
-            message_stream_id: message.message_stream_id,
-            message_length: message.data.len() as u32,
-            can_be_dropped,
-        };
-
         let header_format = if force_uncompressed {
             ChunkHeaderFormat::Full
         } else {
@@ -233,7 +417,12 @@ fn add_basic_header(
     first_byte = first_byte | format_mask;
     bytes.write_u8(first_byte)?;
 
-    // Since get_csid_for_message_type only does csids up to 6, ignore 2 and 3 byte csid formats
+    match csid {
+        x if x <= 63 => (),
+        x if x <= 319 => bytes.write_u8((x - 64) as u8)?,
+        x => bytes.write_u16::<LittleEndian>((x - 64) as u16)?,
+    }
+
     Ok(())
 }
 
@@ -299,12 +488,13 @@ fn add_message_payload(bytes: &mut dyn Write, data: &[u8]) -> Result<(), ChunkSe
 
 fn get_csid_for_message_type(message_type_id: u8) -> u32 {
     // Naive resolution, purpose (afaik) is to allow repeated messages
-    // to utilize header compression by spreading them across chunk streams
+    // to utilize header compression by spreading them across chunk streams.  Audio and video
+    // are kept on their own chunk streams so large video messages don't block audio.
     match message_type_id {
         1 | 2 | 3 | 4 | 5 | 6 => 2,
-        18 | 19 => 3,
-        9 => 4,
-        8 => 5,
+        17..=20 => 3,
+        8 => 4,
+        9 => 5,
         _ => 6,
     }
 }
@@ -335,20 +525,23 @@ mod tests {
     use super::*;
     use byteorder::{BigEndian, LittleEndian, ReadBytesExt};
     use bytes::Bytes;
+    use chunk_io::ChunkDeserializer;
     use std::io::{Cursor, Read};
     use time::RtmpTimestamp;
 
     #[test]
     fn type_0_chunk_for_first_message_with_small_timestamp() {
-        let message1 = MessagePayload {
-            timestamp: RtmpTimestamp::new(72),
-            type_id: 50,
-            message_stream_id: 12,
-            data: Bytes::from(vec![1_u8, 2_u8, 3_u8, 4_u8]),
-        };
+        let message1 = MessagePayload::new(
+            50,
+            RtmpTimestamp::new(72),
+            12,
+            Bytes::from(vec![1_u8, 2_u8, 3_u8, 4_u8]),
+        );
 
         let mut serializer = ChunkSerializer::new();
-        let packet = serializer.serialize(&message1, false, false).unwrap();
+        let packet = serializer
+            .serialize(&message1, false, DropPolicy::MustDeliver)
+            .unwrap();
 
         let mut cursor = Cursor::new(packet.bytes);
         assert_eq!(
@@ -385,15 +578,17 @@ mod tests {
 
     #[test]
     fn type_0_chunk_for_first_message_with_extended_timestamp() {
-        let message1 = MessagePayload {
-            timestamp: RtmpTimestamp::new(16777216),
-            type_id: 50,
-            message_stream_id: 12,
-            data: Bytes::from(vec![1_u8, 2_u8, 3_u8, 4_u8]),
-        };
+        let message1 = MessagePayload::new(
+            50,
+            RtmpTimestamp::new(16777216),
+            12,
+            Bytes::from(vec![1_u8, 2_u8, 3_u8, 4_u8]),
+        );
 
         let mut serializer = ChunkSerializer::new();
-        let packet = serializer.serialize(&message1, false, false).unwrap();
+        let packet = serializer
+            .serialize(&message1, false, DropPolicy::MustDeliver)
+            .unwrap();
 
         let mut cursor = Cursor::new(packet.bytes);
         assert_eq!(
@@ -436,23 +631,27 @@ mod tests {
     #[test]
     fn type_1_chunk_for_second_message_with_same_stream_id_and_different_message_length_and_different_type_id_and_small_timestamp(
     ) {
-        let message1 = MessagePayload {
-            timestamp: RtmpTimestamp::new(72),
-            type_id: 50,
-            message_stream_id: 12,
-            data: Bytes::from(vec![1_u8, 2_u8, 3_u8, 4_u8]),
-        };
+        let message1 = MessagePayload::new(
+            50,
+            RtmpTimestamp::new(72),
+            12,
+            Bytes::from(vec![1_u8, 2_u8, 3_u8, 4_u8]),
+        );
 
-        let message2 = MessagePayload {
-            timestamp: RtmpTimestamp::new(82),
-            type_id: 51,
-            message_stream_id: 12,
-            data: Bytes::from(vec![1_u8, 2_u8, 3_u8]),
-        };
+        let message2 = MessagePayload::new(
+            51,
+            RtmpTimestamp::new(82),
+            12,
+            Bytes::from(vec![1_u8, 2_u8, 3_u8]),
+        );
 
         let mut serializer = ChunkSerializer::new();
-        let _ = serializer.serialize(&message1, false, false).unwrap();
-        let packet = serializer.serialize(&message2, false, false).unwrap();
+        let _ = serializer
+            .serialize(&message1, false, DropPolicy::MustDeliver)
+            .unwrap();
+        let packet = serializer
+            .serialize(&message2, false, DropPolicy::MustDeliver)
+            .unwrap();
 
         let mut cursor = Cursor::new(packet.bytes);
         assert_eq!(
@@ -485,23 +684,27 @@ mod tests {
     #[test]
     fn type_1_chunk_for_second_message_with_same_stream_id_and_different_message_length_and_different_type_id_and_extended_timestamp(
     ) {
-        let message1 = MessagePayload {
-            timestamp: RtmpTimestamp::new(10),
-            type_id: 50,
-            message_stream_id: 12,
-            data: Bytes::from(vec![1_u8, 2_u8, 3_u8, 4_u8]),
-        };
+        let message1 = MessagePayload::new(
+            50,
+            RtmpTimestamp::new(10),
+            12,
+            Bytes::from(vec![1_u8, 2_u8, 3_u8, 4_u8]),
+        );
 
-        let message2 = MessagePayload {
-            timestamp: RtmpTimestamp::new(16777226),
-            type_id: 51,
-            message_stream_id: 12,
-            data: Bytes::from(vec![1_u8, 2_u8, 3_u8]),
-        };
+        let message2 = MessagePayload::new(
+            51,
+            RtmpTimestamp::new(16777226),
+            12,
+            Bytes::from(vec![1_u8, 2_u8, 3_u8]),
+        );
 
         let mut serializer = ChunkSerializer::new();
-        let _ = serializer.serialize(&message1, false, false).unwrap();
-        let packet = serializer.serialize(&message2, false, false).unwrap();
+        let _ = serializer
+            .serialize(&message1, false, DropPolicy::MustDeliver)
+            .unwrap();
+        let packet = serializer
+            .serialize(&message2, false, DropPolicy::MustDeliver)
+            .unwrap();
 
         let mut cursor = Cursor::new(packet.bytes);
         assert_eq!(
@@ -539,23 +742,27 @@ mod tests {
     #[test]
     fn type_2_chunk_for_second_message_with_same_stream_id_and_same_message_length_and_same_type_id_and_small_timestamp(
     ) {
-        let message1 = MessagePayload {
-            timestamp: RtmpTimestamp::new(72),
-            type_id: 50,
-            message_stream_id: 12,
-            data: Bytes::from(vec![1_u8, 2_u8, 3_u8, 4_u8]),
-        };
+        let message1 = MessagePayload::new(
+            50,
+            RtmpTimestamp::new(72),
+            12,
+            Bytes::from(vec![1_u8, 2_u8, 3_u8, 4_u8]),
+        );
 
-        let message2 = MessagePayload {
-            timestamp: RtmpTimestamp::new(82),
-            type_id: 50,
-            message_stream_id: 12,
-            data: Bytes::from(vec![5_u8, 6_u8, 7_u8, 8_u8]),
-        };
+        let message2 = MessagePayload::new(
+            50,
+            RtmpTimestamp::new(82),
+            12,
+            Bytes::from(vec![5_u8, 6_u8, 7_u8, 8_u8]),
+        );
 
         let mut serializer = ChunkSerializer::new();
-        let _ = serializer.serialize(&message1, false, false).unwrap();
-        let packet = serializer.serialize(&message2, false, false).unwrap();
+        let _ = serializer
+            .serialize(&message1, false, DropPolicy::MustDeliver)
+            .unwrap();
+        let packet = serializer
+            .serialize(&message2, false, DropPolicy::MustDeliver)
+            .unwrap();
 
         let mut cursor = Cursor::new(packet.bytes);
         assert_eq!(
@@ -582,23 +789,27 @@ mod tests {
     #[test]
     fn type_2_chunk_for_second_message_with_same_stream_id_and_same_message_length_and_same_type_id_and_extended_timestamp(
     ) {
-        let message1 = MessagePayload {
-            timestamp: RtmpTimestamp::new(10),
-            type_id: 50,
-            message_stream_id: 12,
-            data: Bytes::from(vec![1_u8, 2_u8, 3_u8, 4_u8]),
-        };
+        let message1 = MessagePayload::new(
+            50,
+            RtmpTimestamp::new(10),
+            12,
+            Bytes::from(vec![1_u8, 2_u8, 3_u8, 4_u8]),
+        );
 
-        let message2 = MessagePayload {
-            timestamp: RtmpTimestamp::new(16777226),
-            type_id: 50,
-            message_stream_id: 12,
-            data: Bytes::from(vec![5_u8, 6_u8, 7_u8, 8_u8]),
-        };
+        let message2 = MessagePayload::new(
+            50,
+            RtmpTimestamp::new(16777226),
+            12,
+            Bytes::from(vec![5_u8, 6_u8, 7_u8, 8_u8]),
+        );
 
         let mut serializer = ChunkSerializer::new();
-        let _ = serializer.serialize(&message1, false, false).unwrap();
-        let packet = serializer.serialize(&message2, false, false).unwrap();
+        let _ = serializer
+            .serialize(&message1, false, DropPolicy::MustDeliver)
+            .unwrap();
+        let packet = serializer
+            .serialize(&message2, false, DropPolicy::MustDeliver)
+            .unwrap();
 
         let mut cursor = Cursor::new(packet.bytes);
         assert_eq!(
@@ -629,31 +840,37 @@ mod tests {
 
     #[test]
     fn type_3_chunk_for_third_message_with_all_matching_details() {
-        let message1 = MessagePayload {
-            timestamp: RtmpTimestamp::new(72),
-            type_id: 50,
-            message_stream_id: 12,
-            data: Bytes::from(vec![1_u8, 2_u8, 3_u8, 4_u8]),
-        };
+        let message1 = MessagePayload::new(
+            50,
+            RtmpTimestamp::new(72),
+            12,
+            Bytes::from(vec![1_u8, 2_u8, 3_u8, 4_u8]),
+        );
 
-        let message2 = MessagePayload {
-            timestamp: RtmpTimestamp::new(82),
-            type_id: 50,
-            message_stream_id: 12,
-            data: Bytes::from(vec![5_u8, 6_u8, 7_u8, 8_u8]),
-        };
+        let message2 = MessagePayload::new(
+            50,
+            RtmpTimestamp::new(82),
+            12,
+            Bytes::from(vec![5_u8, 6_u8, 7_u8, 8_u8]),
+        );
 
-        let message3 = MessagePayload {
-            timestamp: RtmpTimestamp::new(92),
-            type_id: 50,
-            message_stream_id: 12,
-            data: Bytes::from(vec![9_u8, 10_u8, 11_u8, 12_u8]),
-        };
+        let message3 = MessagePayload::new(
+            50,
+            RtmpTimestamp::new(92),
+            12,
+            Bytes::from(vec![9_u8, 10_u8, 11_u8, 12_u8]),
+        );
 
         let mut serializer = ChunkSerializer::new();
-        let _ = serializer.serialize(&message1, false, false).unwrap();
-        let _ = serializer.serialize(&message2, false, false).unwrap();
-        let packet = serializer.serialize(&message3, false, false).unwrap();
+        let _ = serializer
+            .serialize(&message1, false, DropPolicy::MustDeliver)
+            .unwrap();
+        let _ = serializer
+            .serialize(&message2, false, DropPolicy::MustDeliver)
+            .unwrap();
+        let packet = serializer
+            .serialize(&message3, false, DropPolicy::MustDeliver)
+            .unwrap();
 
         let mut cursor = Cursor::new(packet.bytes);
         assert_eq!(
@@ -674,23 +891,27 @@ mod tests {
 
     #[test]
     fn type_0_chunks_used_when_new_message_on_different_csid_serialized() {
-        let message1 = MessagePayload {
-            timestamp: RtmpTimestamp::new(72),
-            type_id: 50,
-            message_stream_id: 12,
-            data: Bytes::from(vec![1_u8, 2_u8, 3_u8, 4_u8]),
-        };
+        let message1 = MessagePayload::new(
+            50,
+            RtmpTimestamp::new(72),
+            12,
+            Bytes::from(vec![1_u8, 2_u8, 3_u8, 4_u8]),
+        );
 
-        let message2 = MessagePayload {
-            timestamp: RtmpTimestamp::new(82),
-            type_id: 1,
-            message_stream_id: 12,
-            data: Bytes::from(vec![6_u8, 7_u8, 8_u8, 9_u8]),
-        };
+        let message2 = MessagePayload::new(
+            1,
+            RtmpTimestamp::new(82),
+            12,
+            Bytes::from(vec![6_u8, 7_u8, 8_u8, 9_u8]),
+        );
 
         let mut serializer = ChunkSerializer::new();
-        let _ = serializer.serialize(&message1, false, false).unwrap();
-        let packet = serializer.serialize(&message2, false, false).unwrap();
+        let _ = serializer
+            .serialize(&message1, false, DropPolicy::MustDeliver)
+            .unwrap();
+        let packet = serializer
+            .serialize(&message2, false, DropPolicy::MustDeliver)
+            .unwrap();
 
         let mut cursor = Cursor::new(packet.bytes);
         assert_eq!(
@@ -727,23 +948,27 @@ mod tests {
 
     #[test]
     fn type_0_chunk_for_second_message_when_forcing_uncompressed() {
-        let message1 = MessagePayload {
-            timestamp: RtmpTimestamp::new(72),
-            type_id: 50,
-            message_stream_id: 12,
-            data: Bytes::from(vec![1_u8, 2_u8, 3_u8, 4_u8]),
-        };
+        let message1 = MessagePayload::new(
+            50,
+            RtmpTimestamp::new(72),
+            12,
+            Bytes::from(vec![1_u8, 2_u8, 3_u8, 4_u8]),
+        );
 
-        let message2 = MessagePayload {
-            timestamp: RtmpTimestamp::new(82),
-            type_id: 50,
-            message_stream_id: 12,
-            data: Bytes::from(vec![5_u8, 6_u8, 7_u8, 8_u8]),
-        };
+        let message2 = MessagePayload::new(
+            50,
+            RtmpTimestamp::new(82),
+            12,
+            Bytes::from(vec![5_u8, 6_u8, 7_u8, 8_u8]),
+        );
 
         let mut serializer = ChunkSerializer::new();
-        let _ = serializer.serialize(&message1, false, false).unwrap();
-        let packet = serializer.serialize(&message2, true, false).unwrap();
+        let _ = serializer
+            .serialize(&message1, false, DropPolicy::MustDeliver)
+            .unwrap();
+        let packet = serializer
+            .serialize(&message2, true, DropPolicy::MustDeliver)
+            .unwrap();
 
         let mut cursor = Cursor::new(packet.bytes);
         assert_eq!(
@@ -784,26 +1009,19 @@ mod tests {
         payload.extend_from_slice(&[11_u8; 75]);
         payload.extend_from_slice(&[22_u8; 25]);
 
-        let message1 = MessagePayload {
-            timestamp: RtmpTimestamp::new(72),
-            type_id: 50,
-            message_stream_id: 12,
-            data: Bytes::from(payload.clone()),
-        };
+        let message1 =
+            MessagePayload::new(50, RtmpTimestamp::new(72), 12, Bytes::from(payload.clone()));
 
-        let message2 = MessagePayload {
-            timestamp: RtmpTimestamp::new(73),
-            type_id: 50,
-            message_stream_id: 12,
-            data: Bytes::from(payload),
-        };
+        let message2 = MessagePayload::new(50, RtmpTimestamp::new(73), 12, Bytes::from(payload));
 
         let mut serializer = ChunkSerializer::new();
         serializer
             .set_max_chunk_size(75, RtmpTimestamp::new(0))
             .unwrap();
 
-        let packet = serializer.serialize(&message1, false, false).unwrap();
+        let packet = serializer
+            .serialize(&message1, false, DropPolicy::MustDeliver)
+            .unwrap();
 
         let mut cursor = Cursor::new(packet.bytes);
         assert_eq!(
@@ -850,7 +1068,9 @@ mod tests {
             "Unexpected 2nd payload contents"
         );
 
-        let packet = serializer.serialize(&message2, false, false).unwrap();
+        let packet = serializer
+            .serialize(&message2, false, DropPolicy::MustDeliver)
+            .unwrap();
         let mut cursor = Cursor::new(packet.bytes);
         assert_eq!(
             cursor.read_u8().unwrap(),
@@ -893,19 +1113,21 @@ mod tests {
         payload.extend_from_slice(&[22_u8; 25]);
 
         let timestamp_value = MAX_INITIAL_TIMESTAMP + 1;
-        let message1 = MessagePayload {
-            timestamp: RtmpTimestamp::new(timestamp_value),
-            type_id: 50,
-            message_stream_id: 12,
-            data: Bytes::from(payload.clone()),
-        };
+        let message1 = MessagePayload::new(
+            50,
+            RtmpTimestamp::new(timestamp_value),
+            12,
+            Bytes::from(payload.clone()),
+        );
 
         let mut serializer = ChunkSerializer::new();
         serializer
             .set_max_chunk_size(75, RtmpTimestamp::new(0))
             .unwrap();
 
-        let packet = serializer.serialize(&message1, false, false).unwrap();
+        let packet = serializer
+            .serialize(&message1, false, DropPolicy::MustDeliver)
+            .unwrap();
 
         let mut cursor = Cursor::new(packet.bytes);
         assert_eq!(
@@ -999,24 +1221,69 @@ mod tests {
         );
     }
 
+    #[test]
+    fn type_0_chunk_comes_after_packet_droppable_under_load() {
+        let message1 = MessagePayload::new(
+            50,
+            RtmpTimestamp::new(72),
+            12,
+            Bytes::from(vec![1_u8, 2_u8, 3_u8, 4_u8]),
+        );
+
+        let message2 = MessagePayload::new(
+            50,
+            RtmpTimestamp::new(82),
+            12,
+            Bytes::from(vec![1_u8, 2_u8, 3_u8, 4_u8]),
+        );
+
+        let mut serializer = ChunkSerializer::new();
+        let packet1 = serializer
+            .serialize(&message1, false, DropPolicy::DropUnderLoad)
+            .unwrap();
+
+        assert_eq!(
+            packet1.drop_policy,
+            DropPolicy::DropUnderLoad,
+            "Unexpected drop policy"
+        );
+
+        let packet2 = serializer
+            .serialize(&message2, false, DropPolicy::MustDeliver)
+            .unwrap();
+
+        assert_eq!(
+            packet2.drop_policy,
+            DropPolicy::MustDeliver,
+            "Unexpected 2nd drop policy"
+        );
+        assert_eq!(
+            packet2.bytes[0],
+            6 | 0b00000000,
+            "Expected a type 0 chunk after a droppable packet"
+        );
+    }
+
     #[test]
     fn type_0_chunk_comes_after_droppable_packet() {
-        let message1 = MessagePayload {
-            timestamp: RtmpTimestamp::new(72),
-            type_id: 50,
-            message_stream_id: 12,
-            data: Bytes::from(vec![1_u8, 2_u8, 3_u8, 4_u8]),
-        };
+        let message1 = MessagePayload::new(
+            50,
+            RtmpTimestamp::new(72),
+            12,
+            Bytes::from(vec![1_u8, 2_u8, 3_u8, 4_u8]),
+        );
 
-        let message2 = MessagePayload {
-            timestamp: RtmpTimestamp::new(82),
-            type_id: 50,
-            message_stream_id: 12,
-            data: Bytes::from(vec![1_u8, 2_u8, 3_u8, 4_u8]),
-        };
+        let message2 = MessagePayload::new(
+            50,
+            RtmpTimestamp::new(82),
+            12,
+            Bytes::from(vec![1_u8, 2_u8, 3_u8, 4_u8]),
+        );
 
         let mut serializer = ChunkSerializer::new();
-        let packet1 = serializer.serialize(&message1, false, true).unwrap();
+        let packet1 = serializer
+            .serialize(&message1, false, DropPolicy::DropAggressively)
+            .unwrap();
 
         let mut cursor = Cursor::new(packet1.bytes);
         assert_eq!(
@@ -1041,7 +1308,8 @@ mod tests {
             "Unexpected message stream id"
         );
         assert_eq!(
-            packet1.can_be_dropped, true,
+            packet1.drop_policy,
+            DropPolicy::DropAggressively,
             "First packet was expected to be droppable"
         );
 
@@ -1054,7 +1322,9 @@ mod tests {
             "Unexpected payload contents"
         );
 
-        let packet2 = serializer.serialize(&message2, false, false).unwrap();
+        let packet2 = serializer
+            .serialize(&message2, false, DropPolicy::MustDeliver)
+            .unwrap();
         let mut cursor = Cursor::new(packet2.bytes);
         assert_eq!(
             cursor.read_u8().unwrap(),
@@ -1078,7 +1348,8 @@ mod tests {
             "Unexpected 2nd message stream id"
         );
         assert_eq!(
-            packet2.can_be_dropped, false,
+            packet2.drop_policy,
+            DropPolicy::MustDeliver,
             "Second packet was not expected to be droppable"
         );
 
@@ -1091,4 +1362,137 @@ mod tests {
             "Unexpected payload contents"
         );
     }
+
+    #[test]
+    fn audio_video_and_commands_serialized_on_different_chunk_streams() {
+        let audio = MessagePayload::new(8, RtmpTimestamp::new(0), 1, Bytes::from(vec![1_u8]));
+        let video = MessagePayload::new(9, RtmpTimestamp::new(0), 1, Bytes::from(vec![1_u8]));
+        let command = MessagePayload::new(20, RtmpTimestamp::new(0), 1, Bytes::from(vec![1_u8]));
+
+        let mut serializer = ChunkSerializer::new();
+        let audio_packet = serializer
+            .serialize(&audio, false, DropPolicy::MustDeliver)
+            .unwrap();
+        let video_packet = serializer
+            .serialize(&video, false, DropPolicy::MustDeliver)
+            .unwrap();
+        let command_packet = serializer
+            .serialize(&command, false, DropPolicy::MustDeliver)
+            .unwrap();
+
+        assert_eq!(
+            audio_packet.bytes[0] & 0b00111111,
+            4,
+            "Unexpected audio csid"
+        );
+        assert_eq!(
+            video_packet.bytes[0] & 0b00111111,
+            5,
+            "Unexpected video csid"
+        );
+        assert_eq!(
+            command_packet.bytes[0] & 0b00111111,
+            3,
+            "Unexpected command csid"
+        );
+    }
+
+    #[test]
+    fn can_serialize_on_specified_chunk_stream() {
+        let message = MessagePayload::new(9, RtmpTimestamp::new(0), 1, Bytes::from(vec![1_u8]));
+
+        let mut serializer = ChunkSerializer::new();
+        let packet = serializer
+            .serialize_on_stream(&message, 10, false, DropPolicy::MustDeliver)
+            .unwrap();
+
+        assert_eq!(packet.bytes[0], 10, "Unexpected basic header");
+    }
+
+    #[test]
+    fn two_byte_basic_header_used_for_large_chunk_stream_ids() {
+        let message =
+            MessagePayload::new(9, RtmpTimestamp::new(55), 1, Bytes::from(vec![1_u8, 2_u8]));
+
+        let mut serializer = ChunkSerializer::new();
+        let packet = serializer
+            .serialize_on_stream(&message, 100, false, DropPolicy::MustDeliver)
+            .unwrap();
+
+        assert_eq!(packet.bytes[0], 0, "Unexpected first basic header byte");
+        assert_eq!(packet.bytes[1], 36, "Unexpected second basic header byte");
+
+        let mut deserializer = ChunkDeserializer::new();
+        let result = deserializer
+            .get_next_message(&packet.bytes[..])
+            .unwrap()
+            .unwrap();
+
+        assert_eq!(result.type_id, 9, "Unexpected type id");
+        assert_eq!(
+            result.timestamp,
+            RtmpTimestamp::new(55),
+            "Unexpected timestamp"
+        );
+        assert_eq!(&result.data[..], &message.data[..], "Unexpected payload");
+    }
+
+    #[test]
+    fn partial_message_deserializes_same_as_whole_message() {
+        let data = (0..1000_u32).map(|x| x as u8).collect::<Vec<_>>();
+        let message = MessagePayload::new(9, RtmpTimestamp::new(55), 1, Bytes::from(data.clone()));
+
+        let mut serializer = ChunkSerializer::new();
+        let mut partial = serializer
+            .begin_partial_message(9, RtmpTimestamp::new(55), 1, data.len() as u32)
+            .unwrap();
+
+        // Parts deliberately end partway through chunks
+        let mut bytes = Vec::new();
+        for part in data.chunks(100) {
+            let packet = serializer.serialize_partial(&mut partial, part).unwrap();
+            bytes.extend(packet.bytes);
+        }
+
+        assert!(partial.is_complete(), "Partial message was not complete");
+        partial.finish().unwrap();
+
+        let expected = ChunkSerializer::new()
+            .serialize(&message, false, DropPolicy::MustDeliver)
+            .unwrap();
+        assert_eq!(bytes, expected.bytes, "Unexpected serialized bytes");
+    }
+
+    #[test]
+    fn error_when_partial_message_given_too_much_data() {
+        let mut serializer = ChunkSerializer::new();
+        let mut partial = serializer
+            .begin_partial_message(9, RtmpTimestamp::new(0), 1, 2)
+            .unwrap();
+
+        match serializer.serialize_partial(&mut partial, &[1_u8, 2_u8, 3_u8]) {
+            Err(ChunkSerializationError::PartialMessageOverflow {
+                message_length: 2,
+                attempted_length: 3,
+            }) => (),
+            x => panic!(
+                "Expected PartialMessageOverflow error, instead received {:?}",
+                x
+            ),
+        }
+    }
+
+    #[test]
+    fn error_when_serializing_on_reserved_chunk_stream() {
+        let message = MessagePayload::new(9, RtmpTimestamp::new(0), 1, Bytes::from(vec![1_u8]));
+
+        let mut serializer = ChunkSerializer::new();
+        match serializer.serialize_on_stream(&message, 2, false, DropPolicy::MustDeliver) {
+            Err(ChunkSerializationError::ReservedChunkStreamId { chunk_stream_id: 2 }) => (),
+            x => panic!(
+                "Expected ReservedChunkStreamId error, instead received {:?}",
+                x
+            ),
+        }
+    }
 }
@@ -0,0 +1,298 @@
+/*!
+This module contains utilities for inspecting the headers of RTMP video data, so servers can
+tell sequence headers and keyframes apart from other frames without parsing the codec data.
+
+Both the legacy FLV video tag header and the extended header from the Enhanced RTMP
+specification (<https://veovera.org/docs/enhanced/enhanced-rtmp-v2>) are supported.  The
+extended header is signaled by the high bit of the first byte, and identifies the codec with a
+FourCC instead of the 4 bit codec id, which allows codecs such as HEVC and AV1 to be sent.
+
+## Examples
+
+```
+use rml_rtmp::codec_utils::{parse_video_packet_header, VideoCodec, VideoPacketType};
+
+// Enhanced RTMP HEVC sequence start, as sent by OBS 30 and later
+let data = [0x90, b'h', b'v', b'c', b'1', 0x01, 0x01, 0x60];
+let info = parse_video_packet_header(&data).unwrap();
+
+assert!(info.is_enhanced);
+assert!(info.is_keyframe);
+assert_eq!(info.codec, VideoCodec::Hevc);
+assert_eq!(info.packet_type, VideoPacketType::SequenceHeader);
+```
+*/
+
+use chunk_io::DropPolicy;
+
+const EX_HEADER_FLAG: u8 = 0x80;
+const KEYFRAME_FRAME_TYPE: u8 = 1;
+
+/// The codecs that video data can be recognized as
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+pub enum VideoCodec {
+    /// H.264 / AVC (legacy codec id 7 or FourCC `avc1`)
+    H264,
+
+    /// H.265 / HEVC (legacy codec id 12 or FourCC `hvc1`)
+    Hevc,
+
+    /// AV1 (FourCC `av01`)
+    Av1,
+
+    /// VP9 (FourCC `vp09`)
+    Vp9,
+}
+
+/// What the video data following the header contains
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+pub enum VideoPacketType {
+    /// The decoder configuration record of the codec, which players need before any frames
+    /// can be decoded
+    SequenceHeader,
+
+    /// Coded frames, preceded by a composition time offset
+    CodedFrames,
+
+    /// Coded frames without a composition time offset, which is then implied to be zero
+    /// (enhanced headers only)
+    CodedFramesX,
+
+    /// Signals the end of the sequence
+    SequenceEnd,
+
+    /// Video metadata, such as HDR information (enhanced headers only)
+    Metadata,
+}
+
+/// Information read from the header of an RTMP video data payload
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+pub struct VideoPacketInfo {
+    /// If the payload uses the extended header from the Enhanced RTMP specification
+    pub is_enhanced: bool,
+
+    /// If the frame type of the payload signals a keyframe.  Sequence headers are usually sent
+    /// with this flag set.
+    pub is_keyframe: bool,
+
+    /// The codec of the video data
+    pub codec: VideoCodec,
+
+    /// What the video data contains
+    pub packet_type: VideoPacketType,
+}
+
+/// Reads the header of an RTMP video data payload.  `None` is returned if the payload is too
+/// short, uses an unknown codec, or uses an enhanced packet type whose layout isn't supported
+/// (such as multitrack packets).
+pub fn parse_video_packet_header(data: &[u8]) -> Option<VideoPacketInfo> {
+    let first_byte = *data.first()?;
+    if first_byte & EX_HEADER_FLAG != 0 {
+        parse_enhanced_header(first_byte, data)
+    } else {
+        parse_legacy_header(first_byte, data)
+    }
+}
+
+/// Returns true if the video data is a sequence header, in either the legacy or enhanced format
+pub fn is_video_sequence_header(data: &[u8]) -> bool {
+    match parse_video_packet_header(data) {
+        Some(info) => info.packet_type == VideoPacketType::SequenceHeader,
+        None => false,
+    }
+}
+
+/// Returns true if the video data contains a keyframe, in either the legacy or enhanced
+/// format.  Sequence headers are not counted as keyframes even though they are flagged as such.
+pub fn is_video_keyframe(data: &[u8]) -> bool {
+    match parse_video_packet_header(data) {
+        Some(info) => {
+            info.is_keyframe
+                && (info.packet_type == VideoPacketType::CodedFrames
+                    || info.packet_type == VideoPacketType::CodedFramesX)
+        }
+
+        None => false,
+    }
+}
+
+/// Returns the drop policy video data should be sent with.  Sequence headers and keyframes are
+/// required for the decoder, so must be delivered, while other frames can be dropped.
+pub fn get_video_drop_policy(data: &[u8]) -> DropPolicy {
+    if is_video_sequence_header(data) || is_video_keyframe(data) {
+        DropPolicy::MustDeliver
+    } else {
+        DropPolicy::DropAggressively
+    }
+}
+
+fn parse_legacy_header(first_byte: u8, data: &[u8]) -> Option<VideoPacketInfo> {
+    let codec = match first_byte & 0x0f {
+        7 => VideoCodec::H264,
+        12 => VideoCodec::Hevc,
+        _ => return None,
+    };
+
+    let packet_type = match *data.get(1)? {
+        0 => VideoPacketType::SequenceHeader,
+        1 => VideoPacketType::CodedFrames,
+        2 => VideoPacketType::SequenceEnd,
+        _ => return None,
+    };
+
+    Some(VideoPacketInfo {
+        is_enhanced: false,
+        is_keyframe: (first_byte >> 4) == KEYFRAME_FRAME_TYPE,
+        codec,
+        packet_type,
+    })
+}
+
+fn parse_enhanced_header(first_byte: u8, data: &[u8]) -> Option<VideoPacketInfo> {
+    let packet_type = match first_byte & 0x0f {
+        0 => VideoPacketType::SequenceHeader,
+        1 => VideoPacketType::CodedFrames,
+        2 => VideoPacketType::SequenceEnd,
+        3 => VideoPacketType::CodedFramesX,
+        4 => VideoPacketType::Metadata,
+        _ => return None,
+    };
+
+    let codec = match data.get(1..5)? {
+        b"avc1" => VideoCodec::H264,
+        b"hvc1" => VideoCodec::Hevc,
+        b"av01" => VideoCodec::Av1,
+        b"vp09" => VideoCodec::Vp9,
+        _ => return None,
+    };
+
+    Some(VideoPacketInfo {
+        is_enhanced: true,
+        is_keyframe: ((first_byte >> 4) & 0x07) == KEYFRAME_FRAME_TYPE,
+        codec,
+        packet_type,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn can_parse_legacy_h264_headers() {
+        let sequence_header = [0x17, 0x00, 0x00, 0x00, 0x00, 0x01, 0x64, 0x00, 0x1f];
+        let keyframe = [0x17, 0x01, 0x00, 0x00, 0x42, 0x00, 0x00, 0x00, 0x65];
+        let interframe = [0x27, 0x01, 0x00, 0x00, 0x42, 0x00, 0x00, 0x00, 0x41];
+
+        assert_eq!(
+            parse_video_packet_header(&sequence_header),
+            Some(VideoPacketInfo {
+                is_enhanced: false,
+                is_keyframe: true,
+                codec: VideoCodec::H264,
+                packet_type: VideoPacketType::SequenceHeader,
+            })
+        );
+
+        assert!(is_video_sequence_header(&sequence_header));
+        assert!(!is_video_keyframe(&sequence_header));
+        assert!(is_video_keyframe(&keyframe));
+        assert!(!is_video_sequence_header(&keyframe));
+        assert!(!is_video_keyframe(&interframe));
+    }
+
+    #[test]
+    fn can_parse_legacy_hevc_headers() {
+        assert!(is_video_sequence_header(&[
+            0x1c, 0x00, 0x00, 0x00, 0x00, 0x01
+        ]));
+        assert!(is_video_keyframe(&[0x1c, 0x01, 0x00, 0x00, 0x00, 0x65]));
+        assert!(!is_video_keyframe(&[0x2c, 0x01, 0x00, 0x00, 0x00]));
+        assert!(!is_video_sequence_header(&[0x1c]));
+    }
+
+    #[test]
+    fn can_parse_enhanced_hevc_headers() {
+        // Sequence start followed by the start of an HEVCDecoderConfigurationRecord
+        let sequence_start = [0x90, b'h', b'v', b'c', b'1', 0x01, 0x01, 0x60, 0x00];
+
+        // Keyframe with coded frames, followed by a composition time offset and NALU length
+        let keyframe = [
+            0x91, b'h', b'v', b'c', b'1', 0x00, 0x00, 0x00, 0x00, 0x00, 0x1d,
+        ];
+
+        // Interframe sent as CodedFramesX, so no composition time offset follows
+        let interframe = [0xa3, b'h', b'v', b'c', b'1', 0x00, 0x00, 0x0f, 0x02];
+
+        assert_eq!(
+            parse_video_packet_header(&sequence_start),
+            Some(VideoPacketInfo {
+                is_enhanced: true,
+                is_keyframe: true,
+                codec: VideoCodec::Hevc,
+                packet_type: VideoPacketType::SequenceHeader,
+            })
+        );
+
+        assert!(is_video_sequence_header(&sequence_start));
+        assert!(is_video_keyframe(&keyframe));
+        assert!(!is_video_keyframe(&interframe));
+        assert_eq!(
+            parse_video_packet_header(&interframe).map(|info| info.packet_type),
+            Some(VideoPacketType::CodedFramesX)
+        );
+    }
+
+    #[test]
+    fn can_parse_enhanced_av1_and_vp9_headers() {
+        let av1 = [0x90, b'a', b'v', b'0', b'1', 0x81, 0x08, 0x0c, 0x00];
+        let vp9 = [0x93, b'v', b'p', b'0', b'9', 0x82, 0x49, 0x83];
+
+        assert_eq!(
+            parse_video_packet_header(&av1).map(|info| info.codec),
+            Some(VideoCodec::Av1)
+        );
+        assert_eq!(
+            parse_video_packet_header(&vp9).map(|info| info.codec),
+            Some(VideoCodec::Vp9)
+        );
+        assert!(is_video_sequence_header(&av1));
+        assert!(is_video_keyframe(&vp9));
+    }
+
+    #[test]
+    fn only_sequence_headers_and_keyframes_must_be_delivered() {
+        let legacy_keyframe = [0x17, 0x01, 0x00, 0x00, 0x00, 0x65];
+        let enhanced_sequence_start = [0x90, b'h', b'v', b'c', b'1', 0x01, 0x01, 0x60];
+        let enhanced_keyframe = [0x93, b'h', b'v', b'c', b'1', 0x00, 0x00, 0x1d];
+        let interframe = [0xa3, b'h', b'v', b'c', b'1', 0x00, 0x00, 0x0f, 0x02];
+
+        assert_eq!(
+            get_video_drop_policy(&legacy_keyframe),
+            DropPolicy::MustDeliver
+        );
+        assert_eq!(
+            get_video_drop_policy(&enhanced_sequence_start),
+            DropPolicy::MustDeliver
+        );
+        assert_eq!(
+            get_video_drop_policy(&enhanced_keyframe),
+            DropPolicy::MustDeliver
+        );
+        assert_eq!(
+            get_video_drop_policy(&interframe),
+            DropPolicy::DropAggressively
+        );
+    }
+
+    #[test]
+    fn unknown_codecs_are_not_recognized() {
+        assert_eq!(parse_video_packet_header(&[]), None);
+        assert_eq!(parse_video_packet_header(&[0x12, 0x00]), None);
+        assert_eq!(
+            parse_video_packet_header(&[0x90, b'x', b'x', b'x', b'x']),
+            None
+        );
+        assert_eq!(parse_video_packet_header(&[0x90, b'h', b'v']), None);
+    }
+}
@@ -0,0 +1,268 @@
+use byteorder::{BigEndian, ReadBytesExt, WriteBytesExt};
+use bytes::Bytes;
+use sessions::Direction;
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::path::Path;
+use std::time::{Duration, Instant};
+use thiserror::Error;
+
+/// The bytes every capture file starts with, which includes the version of the format
+pub const CAPTURE_MAGIC: &[u8; 8] = b"RMLCAP01";
+
+const INBOUND_MARKER: u8 = 0;
+const OUTBOUND_MARKER: u8 = 1;
+
+/// Errors that can occur while reading a capture file
+#[derive(Debug, Error)]
+pub enum CaptureError {
+    /// The capture file could not be read or written
+    #[error("An IO error occurred with the capture file: {0}")]
+    Io(#[from] io::Error),
+
+    /// The file did not start with `CAPTURE_MAGIC`, so it's either not a capture file or was
+    /// written by an incompatible version of this crate
+    #[error("The file is not a supported capture file")]
+    InvalidMagic,
+
+    /// A record had a direction marker other than the inbound or outbound marker
+    #[error("Capture record had an unknown direction marker of {0}")]
+    UnknownDirection(u8),
+}
+
+/// A single read from, or write to, a captured transport
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub struct CaptureRecord {
+    /// `Inbound` for bytes read from the transport, `Outbound` for bytes written to it
+    pub direction: Direction,
+
+    /// How long after the capture started the bytes were read or written.  A duration is used
+    /// instead of an `Instant`, since instants have no meaning outside of the process that
+    /// created them.  Timestamps are recorded to the microsecond.
+    pub timestamp: Duration,
+
+    /// The bytes that were read or written
+    pub data: Bytes,
+}
+
+/// Wraps a transport (such as a `TcpStream`) and records every byte read from and written to it,
+/// so the whole connection (including the handshake) can be saved with `dump_to_file()` and
+/// attached to bug reports.
+///
+/// All records are kept in memory until the transport is dropped, so this is intended for
+/// reproducing problems rather than being left on for long lived connections.
+pub struct CapturingTransport<T: Read + Write> {
+    inner: T,
+    started_at: Instant,
+    records: Vec<CaptureRecord>,
+}
+
+impl<T: Read + Write> CapturingTransport<T> {
+    /// Starts capturing the bytes passing through the specified transport
+    pub fn new(inner: T) -> CapturingTransport<T> {
+        CapturingTransport {
+            inner,
+            started_at: Instant::now(),
+            records: Vec::new(),
+        }
+    }
+
+    /// Returns everything that has been captured so far, in the order it was read or written
+    pub fn records(&self) -> &[CaptureRecord] {
+        &self.records
+    }
+
+    /// Returns a reference to the wrapped transport
+    pub fn get_ref(&self) -> &T {
+        &self.inner
+    }
+
+    /// Consumes the capturing transport, returning the wrapped transport
+    pub fn into_inner(self) -> T {
+        self.inner
+    }
+
+    /// Writes everything that has been captured so far to the specified file, replacing it if
+    /// it already exists.
+    pub fn dump_to_file(&self, path: &Path) -> Result<(), CaptureError> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        writer.write_all(CAPTURE_MAGIC)?;
+        for record in &self.records {
+            let marker = match record.direction {
+                Direction::Inbound => INBOUND_MARKER,
+                Direction::Outbound => OUTBOUND_MARKER,
+            };
+
+            writer.write_u8(marker)?;
+            writer.write_u64::<BigEndian>(record.timestamp.as_micros() as u64)?;
+            writer.write_u32::<BigEndian>(record.data.len() as u32)?;
+            writer.write_all(&record.data)?;
+        }
+
+        writer.flush()?;
+        Ok(())
+    }
+
+    fn record(&mut self, direction: Direction, data: &[u8]) {
+        if data.is_empty() {
+            return;
+        }
+
+        // Truncated to what the capture format stores, so dumped records load back unchanged
+        let elapsed_micros = self.started_at.elapsed().as_micros() as u64;
+        self.records.push(CaptureRecord {
+            direction,
+            timestamp: Duration::from_micros(elapsed_micros),
+            data: Bytes::copy_from_slice(data),
+        });
+    }
+}
+
+impl<T: Read + Write> Read for CapturingTransport<T> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let bytes_read = self.inner.read(buf)?;
+        self.record(Direction::Inbound, &buf[..bytes_read]);
+        Ok(bytes_read)
+    }
+}
+
+impl<T: Read + Write> Write for CapturingTransport<T> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let bytes_written = self.inner.write(buf)?;
+        self.record(Direction::Outbound, &buf[..bytes_written]);
+        Ok(bytes_written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// Replays the records of a capture one at a time, without loading the whole capture into
+/// memory first.
+pub struct CaptureReader<R: Read> {
+    reader: R,
+}
+
+impl CaptureReader<BufReader<File>> {
+    /// Opens the specified capture file for reading
+    pub fn open(path: &Path) -> Result<CaptureReader<BufReader<File>>, CaptureError> {
+        CaptureReader::new(BufReader::new(File::open(path)?))
+    }
+}
+
+impl<R: Read> CaptureReader<R> {
+    /// Reads a capture from the specified reader, returning an error if it does not start with
+    /// the capture file header.
+    pub fn new(mut reader: R) -> Result<CaptureReader<R>, CaptureError> {
+        let mut magic = [0_u8; 8];
+        reader.read_exact(&mut magic)?;
+        if &magic != CAPTURE_MAGIC {
+            return Err(CaptureError::InvalidMagic);
+        }
+
+        Ok(CaptureReader { reader })
+    }
+
+    fn read_record(&mut self) -> Result<Option<CaptureRecord>, CaptureError> {
+        let marker = match self.reader.read_u8() {
+            Ok(marker) => marker,
+            Err(ref error) if error.kind() == io::ErrorKind::UnexpectedEof => return Ok(None),
+            Err(error) => return Err(error.into()),
+        };
+
+        let direction = match marker {
+            INBOUND_MARKER => Direction::Inbound,
+            OUTBOUND_MARKER => Direction::Outbound,
+            x => return Err(CaptureError::UnknownDirection(x)),
+        };
+
+        let timestamp = Duration::from_micros(self.reader.read_u64::<BigEndian>()?);
+        let length = self.reader.read_u32::<BigEndian>()? as u64;
+
+        // Read through `take()` so a corrupt length can't cause a huge allocation up front
+        let mut data = Vec::new();
+        (&mut self.reader).take(length).read_to_end(&mut data)?;
+        if data.len() as u64 != length {
+            return Err(io::Error::from(io::ErrorKind::UnexpectedEof).into());
+        }
+
+        Ok(Some(CaptureRecord {
+            direction,
+            timestamp,
+            data: Bytes::from(data),
+        }))
+    }
+}
+
+impl<R: Read> Iterator for CaptureReader<R> {
+    type Item = Result<CaptureRecord, CaptureError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.read_record().transpose()
+    }
+}
+
+/// Reads every record from the specified capture file
+pub fn load_from_file(path: &Path) -> Result<Vec<CaptureRecord>, CaptureError> {
+    CaptureReader::open(path)?.collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    #[test]
+    fn captured_bytes_can_be_dumped_and_loaded() {
+        // A cursor's reads and writes share a position, so the write replaces bytes 4 and 5
+        let mut transport = CapturingTransport::new(Cursor::new(vec![1_u8, 2, 3, 4, 5, 6]));
+        let mut buffer = [0_u8; 3];
+        transport.read_exact(&mut buffer).unwrap();
+        transport.write_all(&[9, 8]).unwrap();
+        transport.read_exact(&mut buffer[..1]).unwrap();
+
+        let path =
+            std::env::temp_dir().join(format!("rml_rtmp_capture_{}.bin", std::process::id()));
+        transport.dump_to_file(&path).unwrap();
+        let records = load_from_file(&path);
+        std::fs::remove_file(&path).unwrap();
+
+        let records = records.unwrap();
+        assert_eq!(records, transport.records(), "Loaded records did not match");
+
+        let summary = records
+            .iter()
+            .map(|record| (record.direction, &record.data[..]))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            summary,
+            vec![
+                (Direction::Inbound, &[1_u8, 2, 3][..]),
+                (Direction::Outbound, &[9_u8, 8][..]),
+                (Direction::Inbound, &[6_u8][..]),
+            ]
+        );
+    }
+
+    #[test]
+    fn error_when_capture_does_not_start_with_magic() {
+        match CaptureReader::new(Cursor::new(b"NOTACAPTURE".to_vec())) {
+            Err(CaptureError::InvalidMagic) => (),
+            Err(x) => panic!("Expected InvalidMagic error, instead received {:?}", x),
+            Ok(_) => panic!("Expected InvalidMagic error, instead received a reader"),
+        }
+    }
+
+    #[test]
+    fn error_when_record_is_truncated() {
+        let mut bytes = CAPTURE_MAGIC.to_vec();
+        bytes.extend_from_slice(&[INBOUND_MARKER, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 10, 1, 2]);
+        let mut reader = CaptureReader::new(Cursor::new(bytes)).unwrap();
+
+        match reader.next() {
+            Some(Err(CaptureError::Io(_))) => (),
+            x => panic!("Expected an IO error, instead received {:?}", x),
+        }
+    }
+}
@@ -0,0 +1,10 @@
+//! Tools for diagnosing problems with peers, most notably capturing the raw bytes of a
+//! connection so that a misbehaving client or server can be reproduced without access to it.
+//!
+//! Capture files can be read back with `CaptureReader` or `load_from_file()`, or displayed as
+//! RTMP messages by the `rtmp-log-reader` tool's `--load-capture` mode.
+
+mod capture;
+
+pub use self::capture::{load_from_file, CaptureError, CaptureReader, CaptureRecord};
+pub use self::capture::{CapturingTransport, CAPTURE_MAGIC};
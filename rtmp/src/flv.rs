@@ -0,0 +1,222 @@
+/*!
+This module contains utilities for converting RTMP message payloads into the FLV file format
+(<https://www.adobe.com/content/dam/acom/en/devnet/flv/video_file_format_spec_v10.pdf>).
+
+RTMP audio, video, and script data messages contain the exact same bytes as FLV tag bodies, so
+converting an RTMP stream into an FLV file only requires wrapping each payload in an FLV tag
+header.  This is useful for debugging, as it allows a captured RTMP stream to be played back in
+any media player that supports FLV files, as well as for recording live streams to disk with the
+`FlvWriter`.
+
+## Examples
+
+```
+# extern crate bytes;
+# extern crate rml_rtmp;
+use rml_rtmp::flv::payloads_to_flv_bytes;
+use rml_rtmp::messages::MessagePayload;
+use rml_rtmp::time::RtmpTimestamp;
+use bytes::Bytes;
+
+let video = MessagePayload {
+    timestamp: RtmpTimestamp::new(0),
+    type_id: 9,
+    message_stream_id: 1,
+    data: Bytes::from(vec![0x17, 0x01, 0x00, 0x00, 0x00]),
+};
+
+let flv = payloads_to_flv_bytes(vec![video]);
+assert_eq!(&flv[..3], b"FLV");
+```
+*/
+
+use byteorder::{BigEndian, WriteBytesExt};
+use messages::MessagePayload;
+use std::io::{self, Write};
+
+const FLV_HEADER_SIZE: u32 = 9;
+const FLV_TAG_HEADER_SIZE: u32 = 11;
+const AUDIO_TYPE_ID: u8 = 8;
+const VIDEO_TYPE_ID: u8 = 9;
+const SCRIPT_DATA_TYPE_ID: u8 = 18;
+
+/// Writes FLV tags to an underlying writer as RTMP payloads become available, allowing a stream
+/// to be saved to disk while it is being received.
+///
+/// Since the contents of the stream are not known ahead of time the FLV header always signals
+/// that both audio and video tags are present.
+pub struct FlvWriter<W: Write> {
+    writer: W,
+}
+
+impl<W: Write> FlvWriter<W> {
+    /// Creates a new writer, immediately writing the FLV file header to the underlying writer
+    pub fn new(mut writer: W) -> io::Result<FlvWriter<W>> {
+        write_header(&mut writer, true, true)?;
+        Ok(FlvWriter { writer })
+    }
+
+    /// Writes a single tag containing the specified data.  Only audio (type 8), video (type 9),
+    /// and script data (type 18) tags can be represented in an FLV file, so tags of any other
+    /// type are ignored.
+    pub fn write_tag(&mut self, type_id: u8, timestamp: u32, data: &[u8]) -> io::Result<()> {
+        if !is_flv_tag_type(type_id) {
+            return Ok(());
+        }
+
+        write_tag(&mut self.writer, type_id, timestamp, data)
+    }
+
+    /// Flushes any buffered tags to the underlying writer
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+
+    /// Returns the underlying writer
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+}
+
+/// Converts a sequence of RTMP message payloads into the bytes of a complete FLV file.
+///
+/// One FLV tag is written for each audio (type 8), video (type 9), and script data (type 18)
+/// payload.  All other payloads are not representable in an FLV file and are skipped.
+pub fn payloads_to_flv_bytes<I>(payloads: I) -> Vec<u8>
+where
+    I: IntoIterator<Item = MessagePayload>,
+{
+    let payloads = payloads
+        .into_iter()
+        .filter(|payload| is_flv_tag_type(payload.type_id))
+        .collect::<Vec<_>>();
+
+    let has_audio = payloads.iter().any(|p| p.type_id == AUDIO_TYPE_ID);
+    let has_video = payloads.iter().any(|p| p.type_id == VIDEO_TYPE_ID);
+
+    // Writes to a vector cannot fail, so unwrapping is safe
+    let mut bytes = Vec::new();
+    write_header(&mut bytes, has_audio, has_video).unwrap();
+
+    for payload in payloads {
+        write_tag(
+            &mut bytes,
+            payload.type_id,
+            payload.timestamp.value,
+            &payload.data[..],
+        )
+        .unwrap();
+    }
+
+    bytes
+}
+
+fn is_flv_tag_type(type_id: u8) -> bool {
+    matches!(type_id, AUDIO_TYPE_ID | VIDEO_TYPE_ID | SCRIPT_DATA_TYPE_ID)
+}
+
+fn write_header<W: Write>(writer: &mut W, has_audio: bool, has_video: bool) -> io::Result<()> {
+    let mut flags = 0_u8;
+    if has_audio {
+        flags |= 0b00000100;
+    }
+
+    if has_video {
+        flags |= 0b00000001;
+    }
+
+    writer.write_all(b"FLV")?;
+    writer.write_u8(1)?; // version
+    writer.write_u8(flags)?;
+    writer.write_u32::<BigEndian>(FLV_HEADER_SIZE)?;
+    writer.write_u32::<BigEndian>(0)?; // PreviousTagSize0
+    Ok(())
+}
+
+fn write_tag<W: Write>(writer: &mut W, type_id: u8, timestamp: u32, data: &[u8]) -> io::Result<()> {
+    let data_size = data.len() as u32;
+
+    writer.write_u8(type_id)?;
+    writer.write_u24::<BigEndian>(data_size)?;
+    writer.write_u24::<BigEndian>(timestamp & 0x00ffffff)?;
+    writer.write_u8((timestamp >> 24) as u8)?;
+    writer.write_u24::<BigEndian>(0)?; // stream id is always zero
+    writer.write_all(data)?;
+    writer.write_u32::<BigEndian>(FLV_TAG_HEADER_SIZE + data_size)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bytes::Bytes;
+    use time::RtmpTimestamp;
+
+    fn payload(type_id: u8, timestamp: u32, data: Vec<u8>) -> MessagePayload {
+        MessagePayload {
+            timestamp: RtmpTimestamp::new(timestamp),
+            type_id,
+            message_stream_id: 1,
+            data: Bytes::from(data),
+        }
+    }
+
+    #[test]
+    fn writes_flv_header_with_audio_and_video_flags() {
+        let payloads = vec![payload(8, 0, vec![1]), payload(9, 0, vec![2])];
+        let bytes = payloads_to_flv_bytes(payloads);
+
+        assert_eq!(
+            &bytes[..13],
+            &[b'F', b'L', b'V', 1, 5, 0, 0, 0, 9, 0, 0, 0, 0],
+            "Unexpected flv header"
+        );
+    }
+
+    #[test]
+    fn writes_tag_for_video_payload() {
+        let payloads = vec![payload(9, 0x01020304, vec![5, 6, 7])];
+        let bytes = payloads_to_flv_bytes(payloads);
+
+        assert_eq!(
+            &bytes[13..],
+            &[9, 0, 0, 3, 2, 3, 4, 1, 0, 0, 0, 5, 6, 7, 0, 0, 0, 14],
+            "Unexpected flv tag"
+        );
+    }
+
+    #[test]
+    fn flv_writer_writes_header_and_tags() {
+        let mut writer = FlvWriter::new(Vec::new()).unwrap();
+        writer.write_tag(8, 5, &[1, 2]).unwrap();
+        writer.write_tag(20, 5, &[3]).unwrap();
+
+        let bytes = writer.into_inner();
+        assert_eq!(
+            &bytes[..],
+            &[
+                b'F', b'L', b'V', 1, 5, 0, 0, 0, 9, 0, 0, 0, 0, 8, 0, 0, 2, 0, 0, 5, 0, 0, 0, 0, 1,
+                2, 0, 0, 0, 13
+            ],
+            "Unexpected flv bytes"
+        );
+    }
+
+    #[test]
+    fn non_media_payloads_are_skipped() {
+        let payloads = vec![
+            payload(20, 0, vec![1, 2, 3]),
+            payload(18, 0, vec![4]),
+            payload(1, 0, vec![0, 0, 0, 128]),
+        ];
+
+        let bytes = payloads_to_flv_bytes(payloads);
+
+        assert_eq!(bytes[4], 0, "Expected no audio or video flags");
+        assert_eq!(
+            &bytes[13..],
+            &[18, 0, 0, 1, 0, 0, 0, 0, 0, 0, 0, 4, 0, 0, 0, 12],
+            "Expected only the script data tag"
+        );
+    }
+}
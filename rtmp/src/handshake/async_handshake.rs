@@ -0,0 +1,174 @@
+use std::future::Future;
+use std::io;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+
+use super::{Handshake, HandshakeError, HandshakeProcessResult, PeerType};
+
+/// A future that performs a whole handshake over an async stream, such as a tokio `TcpStream`.
+/// It resolves to any bytes the peer sent after the handshake completed, which need to be passed
+/// to the session handling the connection.  Since `&mut TcpStream` is itself a stream, the
+/// connection can be kept for the session with
+/// `AsyncHandshake::perform_server_handshake(&mut stream).await`.
+pub struct AsyncHandshake<S> {
+    handshake: Handshake,
+    stream: S,
+    has_started: bool,
+    outbound_bytes: Vec<u8>,
+    outbound_offset: usize,
+    remaining_bytes: Option<Vec<u8>>,
+    buffer: [u8; 4096],
+}
+
+impl<S> AsyncHandshake<S>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    /// Performs the handshake as the client side of the connection
+    pub fn perform_client_handshake(stream: S) -> AsyncHandshake<S> {
+        AsyncHandshake::new(Handshake::new(PeerType::Client), stream)
+    }
+
+    /// Performs the handshake as the server side of the connection
+    pub fn perform_server_handshake(stream: S) -> AsyncHandshake<S> {
+        AsyncHandshake::new(Handshake::new(PeerType::Server), stream)
+    }
+
+    /// Performs the handshake using an already configured `Handshake`
+    pub fn new(handshake: Handshake, stream: S) -> AsyncHandshake<S> {
+        AsyncHandshake {
+            handshake,
+            stream,
+            has_started: false,
+            outbound_bytes: Vec::new(),
+            outbound_offset: 0,
+            remaining_bytes: None,
+            buffer: [0; 4096],
+        }
+    }
+
+    /// Writes any pending outbound bytes, returning `Poll::Ready` once they are all written
+    fn poll_write_outbound(&mut self, cx: &mut Context) -> Poll<Result<(), HandshakeError>> {
+        while self.outbound_offset < self.outbound_bytes.len() {
+            let bytes = &self.outbound_bytes[self.outbound_offset..];
+            match Pin::new(&mut self.stream).poll_write(cx, bytes) {
+                Poll::Ready(Ok(0)) => {
+                    return Poll::Ready(Err(io::Error::from(io::ErrorKind::WriteZero).into()))
+                }
+
+                Poll::Ready(Ok(count)) => self.outbound_offset += count,
+                Poll::Ready(Err(error)) => return Poll::Ready(Err(error.into())),
+                Poll::Pending => return Poll::Pending,
+            }
+        }
+
+        if !self.outbound_bytes.is_empty() {
+            match Pin::new(&mut self.stream).poll_flush(cx) {
+                Poll::Ready(Ok(())) => (),
+                Poll::Ready(Err(error)) => return Poll::Ready(Err(error.into())),
+                Poll::Pending => return Poll::Pending,
+            }
+
+            self.outbound_bytes.clear();
+            self.outbound_offset = 0;
+        }
+
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl<S> Future for AsyncHandshake<S>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    type Output = Result<Vec<u8>, HandshakeError>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Self::Output> {
+        let this = self.get_mut();
+        if !this.has_started {
+            this.has_started = true;
+            this.outbound_bytes = this.handshake.generate_outbound_p0_and_p1()?;
+        }
+
+        loop {
+            match this.poll_write_outbound(cx) {
+                Poll::Ready(Ok(())) => (),
+                Poll::Ready(Err(error)) => return Poll::Ready(Err(error)),
+                Poll::Pending => return Poll::Pending,
+            }
+
+            if let Some(remaining_bytes) = this.remaining_bytes.take() {
+                return Poll::Ready(Ok(remaining_bytes));
+            }
+
+            let mut read_buffer = ReadBuf::new(&mut this.buffer);
+            match Pin::new(&mut this.stream).poll_read(cx, &mut read_buffer) {
+                Poll::Ready(Ok(())) => (),
+                Poll::Ready(Err(error)) => return Poll::Ready(Err(error.into())),
+                Poll::Pending => return Poll::Pending,
+            }
+
+            if read_buffer.filled().is_empty() {
+                let error = io::Error::from(io::ErrorKind::UnexpectedEof);
+                return Poll::Ready(Err(error.into()));
+            }
+
+            match this.handshake.process_bytes(read_buffer.filled())? {
+                HandshakeProcessResult::InProgress { response_bytes } => {
+                    this.outbound_bytes = response_bytes;
+                }
+
+                HandshakeProcessResult::Completed {
+                    response_bytes,
+                    remaining_bytes,
+                } => {
+                    this.outbound_bytes = response_bytes;
+                    this.remaining_bytes = Some(remaining_bytes);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::net::{TcpListener, TcpStream};
+    use tokio::runtime::Builder;
+
+    #[test]
+    fn client_and_server_complete_handshake_over_loopback() {
+        // This crate uses the 2015 edition so `#[tokio::test]` and `.await` are not available,
+        // but the futures can still be run to completion on a tokio runtime
+        let runtime = Builder::new_current_thread().enable_io().build().unwrap();
+
+        let listener = runtime.block_on(TcpListener::bind("127.0.0.1:0")).unwrap();
+        let address = listener.local_addr().unwrap();
+        let client_stream = runtime.block_on(TcpStream::connect(address)).unwrap();
+        let (server_stream, _) = runtime.block_on(listener.accept()).unwrap();
+
+        let server = runtime.spawn(AsyncHandshake::perform_server_handshake(server_stream));
+        let client_result =
+            runtime.block_on(AsyncHandshake::perform_client_handshake(client_stream));
+        let server_result = runtime.block_on(server).unwrap();
+
+        assert_eq!(client_result.unwrap(), Vec::<u8>::new());
+        assert_eq!(server_result.unwrap(), Vec::<u8>::new());
+    }
+
+    #[test]
+    fn error_when_peer_closes_connection_during_handshake() {
+        let runtime = Builder::new_current_thread().enable_io().build().unwrap();
+
+        let listener = runtime.block_on(TcpListener::bind("127.0.0.1:0")).unwrap();
+        let address = listener.local_addr().unwrap();
+        let client_stream = runtime.block_on(TcpStream::connect(address)).unwrap();
+        drop(runtime.block_on(listener.accept()).unwrap());
+
+        match runtime.block_on(AsyncHandshake::perform_client_handshake(client_stream)) {
+            Err(HandshakeError::Io(_)) => (),
+            x => panic!("Expected an IO error, instead received {:?}", x),
+        }
+    }
+}
@@ -0,0 +1,32 @@
+/// The version bytes sent in packet 1 by default, which were copied from a JW Player handshake
+const DEFAULT_VERSION_BYTES: [u8; 4] = [128, 0, 7, 2];
+
+/// Configuration options that govern how a handshake is performed
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HandshakeConfig {
+    /// The version bytes sent in bytes 4-7 of our packet 1.  Peers that only support the
+    /// handshake from the original RTMP specification may send (and expect) `[0, 0, 0, 0]`.
+    pub version_bytes: [u8; 4],
+
+    /// If true, the peer's packet 1 must contain a valid Flash Player 9 digest, and the
+    /// handshake fails with `HandshakeError::UnknownPacket1Format` if it does not.  If false,
+    /// a packet 1 without a valid digest is treated as an original RTMP specification handshake
+    /// and echoed back as our packet 2.
+    pub require_fp9_handshake: bool,
+}
+
+impl HandshakeConfig {
+    /// Creates a new configuration object with default values
+    pub fn new() -> HandshakeConfig {
+        HandshakeConfig {
+            version_bytes: DEFAULT_VERSION_BYTES,
+            require_fp9_handshake: false,
+        }
+    }
+}
+
+impl Default for HandshakeConfig {
+    fn default() -> Self {
+        HandshakeConfig::new()
+    }
+}
@@ -43,6 +43,11 @@ pub enum HandshakeError {
     #[error("Invalid handshake packet 2 received")]
     InvalidP2Packet,
 
+    /// A handshake can only be cloned before it has processed any of the peer's packet 1, as
+    /// after that point it contains peer specific state.
+    #[error("Handshake cannot be cloned at its current stage")]
+    CannotCloneAtCurrentStage,
+
     /// This occurs when an IO error is encountered while reading the input.
     #[error("_0")]
     Io(#[from] io::Error),
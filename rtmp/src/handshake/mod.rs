@@ -19,13 +19,31 @@ exactly and verifies that bytes 4-7 are zeroes.  At this point in time any (with
 of h.264 video) all clients and servers should work against the fp9 method so this should not
 be an issue.
 
+When the `async` feature is enabled, `AsyncHandshake` performs the whole handshake over a tokio
+stream and resolves to the bytes received after it completed.
+
+Peers that need to be strict about which handshake is used can create a `Handshake` with a
+`HandshakeConfig`, which controls the version bytes we send in packet 1 and whether a packet 1
+without a valid fp9 digest is rejected instead of being treated as the original handshake.
+
 **Note:** At this point of time we only accept (and send) command bytes of 3, meaning that
 no encryption is used.
 
+When the `debug` feature is enabled a `Handshake` can be created with
+`Handshake::with_debug_logger()`, which reports the command byte, digest offsets, and the
+computed and received digests as the handshake progresses.  This is useful for diagnosing why a
+handshake with a particular client or server fails.
+
 */
 
+#[cfg(feature = "async")]
+mod async_handshake;
+mod config;
 mod errors;
 
+#[cfg(feature = "async")]
+pub use self::async_handshake::AsyncHandshake;
+pub use self::config::HandshakeConfig;
 pub use self::errors::HandshakeError;
 
 use hmac::{Hmac, Mac, NewMac};
@@ -67,7 +85,7 @@ pub enum HandshakeProcessResult {
 ///
 /// This only matters due to the FP9 handshaking process, where the client and server use different
 /// calculations for packet generation.
-#[derive(Debug, Eq, PartialEq)]
+#[derive(Debug, Eq, PartialEq, Clone, Copy)]
 pub enum PeerType {
     /// Handshake being represented as a server
     Server,
@@ -142,8 +160,16 @@ pub struct Handshake {
     input_buffer: Vec<u8>,
     sent_p1: [u8; RTMP_PACKET_SIZE],
     sent_digest: [u8; SHA256_DIGEST_LENGTH],
+    config: HandshakeConfig,
+    #[cfg(feature = "debug")]
+    debug_logger: Option<DebugLogger>,
 }
 
+/// A function that is passed each line of debug output from a `Handshake`.  Lines never contain
+/// newline characters.
+#[cfg(feature = "debug")]
+pub type DebugLogger = Box<dyn Fn(&str) + Send>;
+
 impl Handshake {
     /// Creates a new handshake handling instance.
     ///
@@ -151,6 +177,11 @@ impl Handshake {
     /// are the client or the server, and thus this must be specified when creating a new
     /// `Handshake` instance.
     pub fn new(peer_type: PeerType) -> Handshake {
+        Handshake::new_with_config(peer_type, HandshakeConfig::new())
+    }
+
+    /// Creates a new handshake handling instance that uses the specified configuration
+    pub fn new_with_config(peer_type: PeerType, config: HandshakeConfig) -> Handshake {
         Handshake {
             current_stage: Stage::NeedToSendP0AndP1,
             command_byte: 0_u8,
@@ -158,7 +189,44 @@ impl Handshake {
             sent_p1: [0_u8; RTMP_PACKET_SIZE],
             peer_type,
             sent_digest: [0_u8; SHA256_DIGEST_LENGTH],
+            config,
+            #[cfg(feature = "debug")]
+            debug_logger: None,
+        }
+    }
+
+    /// Creates a new handshake handling instance that reports the intermediate values of the
+    /// handshake to the specified logger.
+    #[cfg(feature = "debug")]
+    pub fn with_debug_logger(peer_type: PeerType, logger: DebugLogger) -> Handshake {
+        let mut handshake = Handshake::new(peer_type);
+        handshake.debug_logger = Some(logger);
+        handshake
+    }
+
+    /// Creates a copy of the handshake, including the packet 1 that was already sent to the peer.
+    /// This is mostly useful for testing alternative code paths with the same handshake.
+    ///
+    /// Cloning is only allowed after our packets 0 and 1 have been generated but before the
+    /// peer's packet 1 has been processed, as any later stage contains peer specific state.
+    /// Debug loggers cannot be cloned, so the copy will not have one.
+    pub fn try_clone(&self) -> Result<Handshake, HandshakeError> {
+        match self.current_stage {
+            Stage::WaitingForPacket0 | Stage::WaitingForPacket1 => (),
+            _ => return Err(HandshakeError::CannotCloneAtCurrentStage),
         }
+
+        Ok(Handshake {
+            current_stage: self.current_stage.clone(),
+            peer_type: self.peer_type,
+            command_byte: self.command_byte,
+            input_buffer: self.input_buffer.clone(),
+            sent_p1: self.sent_p1,
+            sent_digest: self.sent_digest,
+            config: self.config.clone(),
+            #[cfg(feature = "debug")]
+            debug_logger: None,
+        })
     }
 
     /// Creates the packets 0 and 1 that should get sent to the peer.  This is only strictly
@@ -168,16 +236,11 @@ impl Handshake {
     ///
     /// For now this only sends a command byte of 3 (no encryption).
     pub fn generate_outbound_p0_and_p1(&mut self) -> Result<Vec<u8>, HandshakeError> {
-        const ADOBE_VERSION: [u8; 4] = [128_u8, 0_u8, 7_u8, 2_u8]; // Copied from jw player handshake
-
-        // Leave time field as zero, version field as ADOBE_VERSION, and the rest of the packet
-        // should be random data.  Part of the random data will be used to determine placement
-        // of the digest offset
+        // Leave time field as zero, version field as the configured version bytes, and the rest
+        // of the packet should be random data.  Part of the random data will be used to determine
+        // placement of the digest offset
         fill_with_random_data(&mut self.sent_p1[8..1532]);
-        self.sent_p1[4] = ADOBE_VERSION[0];
-        self.sent_p1[5] = ADOBE_VERSION[1];
-        self.sent_p1[6] = ADOBE_VERSION[2];
-        self.sent_p1[7] = ADOBE_VERSION[3];
+        self.sent_p1[4..8].copy_from_slice(&self.config.version_bytes);
 
         let (digest_offset, constant_key) = match self.peer_type {
             PeerType::Server => (get_server_digest_offset(&self.sent_p1), GENUINE_FMS_CONST),
@@ -196,6 +259,14 @@ impl Handshake {
             self.sent_p1[(digest_offset as usize) + index] = self.sent_digest[index];
         }
 
+        self.debug_log(|| {
+            format!(
+                "Sending p1 with digest offset {} and digest {}",
+                digest_offset,
+                to_hex(&self.sent_digest)
+            )
+        });
+
         let mut output = vec![3_u8];
         output.extend_from_slice(&self.sent_p1);
 
@@ -278,6 +349,7 @@ impl Handshake {
         }
 
         self.command_byte = self.input_buffer.remove(0);
+        self.debug_log(|| format!("Received command byte {}", self.command_byte));
         if self.command_byte != 3_u8 {
             return Err(HandshakeError::BadVersionId);
         };
@@ -314,8 +386,25 @@ impl Handshake {
             PeerType::Client => GENUINE_FMS_CONST.as_bytes().to_vec(),
         };
 
+        self.debug_log(|| {
+            let offset = get_client_digest_offset(&received_packet_1);
+            describe_received_digest(&received_packet_1, offset, &p1_key)
+        });
+
+        self.debug_log(|| {
+            let offset = get_server_digest_offset(&received_packet_1);
+            describe_received_digest(&received_packet_1, offset, &p1_key)
+        });
+
         let received_digest = match get_digest_for_received_packet(&received_packet_1, &p1_key) {
             Ok(digest) => digest,
+            Err(HandshakeError::UnknownPacket1Format) if self.config.require_fp9_handshake => {
+                self.debug_log(|| {
+                    "No digest matched and the fp9 handshake is required".to_string()
+                });
+                return Err(HandshakeError::UnknownPacket1Format);
+            }
+
             Err(HandshakeError::UnknownPacket1Format) => {
                 // Since no digest was found chances are that this handshake is
                 // not a fp9 handshake but instead is the handshake from the
@@ -327,6 +416,10 @@ impl Handshake {
                 // of 0 should be specified in the p1 packet, but some RTMP
                 // destinations such as YouTube provide a non-zero version while
                 // still expecting an original handshake.
+                self.debug_log(|| {
+                    "No digest matched, responding with the original handshake".to_string()
+                });
+
                 self.current_stage = Stage::WaitingForPacket2;
                 return Ok(HandshakeProcessResult::InProgress {
                     response_bytes: received_packet_1.to_vec(),
@@ -349,6 +442,15 @@ impl Handshake {
         let hmac1 = calc_hmac(&received_digest, &p2_key[..]);
         let hmac2 = calc_hmac(&output_packet[..P2_SIG_START_INDEX], &hmac1);
 
+        self.debug_log(|| {
+            format!(
+                "Sending p2 for received digest {} with key hmac {} and signature {}",
+                to_hex(&received_digest),
+                to_hex(&hmac1),
+                to_hex(&hmac2)
+            )
+        });
+
         // the hmac2 signature is written to the end of the p2 packet
         for index in 0..SHA256_DIGEST_LENGTH {
             output_packet[P2_SIG_START_INDEX + index] = hmac2[index];
@@ -379,6 +481,7 @@ impl Handshake {
         // If the peer sent back a p2 that is an exact copy of our p1, accept it as that mean's it
         // is the old style handshake
         if &self.sent_p1[..] == &received_packet_2[..] {
+            self.debug_log(|| "Received p2 is an exact copy of the sent p1".to_string());
             self.current_stage = Stage::Complete;
             let remaining_bytes = self.input_buffer.drain(..).collect();
             return Ok(HandshakeProcessResult::Completed {
@@ -395,6 +498,17 @@ impl Handshake {
 
         peer_key.extend_from_slice(&RANDOM_CRUD[..]);
 
+        self.debug_log(|| {
+            let hmac1 = calc_hmac(&self.sent_digest, &peer_key[..]);
+            let hmac2 = calc_hmac(&received_packet_2[..P2_SIG_START_INDEX], &hmac1);
+            format!(
+                "Received p2 signature {}, expected signature {} with key hmac {}",
+                to_hex(&received_packet_2[P2_SIG_START_INDEX..]),
+                to_hex(&hmac2),
+                to_hex(&hmac1)
+            )
+        });
+
         // TODO: Re-enable P2 verification.
         // Verification of packet 2 had to be commented out for flash players to work.  For some
         // reason flash players are failing the p2 validation even though VLC, ffmpeg, and others
@@ -416,6 +530,37 @@ impl Handshake {
             remaining_bytes: bytes_left,
         })
     }
+
+    /// Passes the message to the debug logger, if one was provided.  The message is only
+    /// formed when there is a logger to receive it.
+    #[cfg(feature = "debug")]
+    fn debug_log<F: FnOnce() -> String>(&self, message: F) {
+        if let Some(ref logger) = self.debug_logger {
+            logger(&message());
+        }
+    }
+
+    #[cfg(not(feature = "debug"))]
+    fn debug_log<F: FnOnce() -> String>(&self, _message: F) {}
+}
+
+fn describe_received_digest(packet: &[u8; RTMP_PACKET_SIZE], offset: u32, key: &[u8]) -> String {
+    let parts = match get_message_parts(packet, offset) {
+        Ok(parts) => parts,
+        Err(error) => return format!("Digest offset {} could not be read: {}", offset, error),
+    };
+
+    let computed = calc_hmac_from_parts(&parts.before_digest, &parts.after_digest, key);
+    format!(
+        "Digest offset {}: computed digest {}, received digest {}",
+        offset,
+        to_hex(&computed),
+        to_hex(&parts.digest)
+    )
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
 }
 
 fn get_digest_for_received_packet(
@@ -796,6 +941,110 @@ mod tests {
         assert_eq!(handshake.current_stage, Stage::Complete);
     }
 
+    #[test]
+    fn error_when_original_handshake_received_and_fp9_handshake_required() {
+        let mut c0_and_c1 = [0_u8; RTMP_PACKET_SIZE + 1];
+        c0_and_c1[0] = 3;
+        fill_with_random_data(&mut c0_and_c1[9..RTMP_PACKET_SIZE + 1]);
+
+        let mut config = HandshakeConfig::new();
+        config.require_fp9_handshake = true;
+        let mut handshake = Handshake::new_with_config(PeerType::Server, config);
+        handshake.generate_outbound_p0_and_p1().unwrap();
+
+        match handshake.process_bytes(&c0_and_c1) {
+            Err(HandshakeError::UnknownPacket1Format) => (),
+            x => panic!(
+                "Expected UnknownPacket1Format error, instead received {:?}",
+                x
+            ),
+        }
+    }
+
+    #[test]
+    fn fp9_handshake_still_accepted_when_required() {
+        let mut config = HandshakeConfig::new();
+        config.require_fp9_handshake = true;
+        let mut handshake = Handshake::new_with_config(PeerType::Server, config);
+        handshake.generate_outbound_p0_and_p1().unwrap();
+
+        let mut c0_and_c1 = JWPLAYER_C0.to_vec();
+        c0_and_c1.extend_from_slice(&JWPLAYER_C1);
+        match handshake.process_bytes(&c0_and_c1) {
+            Ok(HandshakeProcessResult::InProgress { .. }) => (),
+            x => panic!("Unexpected response of {:?}", x),
+        }
+
+        assert_eq!(handshake.current_stage, Stage::WaitingForPacket2);
+    }
+
+    #[test]
+    fn sends_configured_version_bytes_in_p1() {
+        let mut config = HandshakeConfig::new();
+        config.version_bytes = [0, 0, 0, 0];
+        let mut handshake = Handshake::new_with_config(PeerType::Client, config);
+
+        let c0_and_c1 = handshake.generate_outbound_p0_and_p1().unwrap();
+        assert_eq!(&c0_and_c1[5..9], &[0_u8, 0, 0, 0]);
+
+        let mut default_handshake = Handshake::new(PeerType::Client);
+        let c0_and_c1 = default_handshake.generate_outbound_p0_and_p1().unwrap();
+        assert_eq!(&c0_and_c1[5..9], &[128_u8, 0, 7, 2]);
+    }
+
+    #[test]
+    fn cloned_handshake_produces_same_p2_response() {
+        let mut c0_and_c1 = [0_u8; RTMP_PACKET_SIZE + 1];
+        c0_and_c1[0] = 3;
+        fill_with_random_data(&mut c0_and_c1[9..RTMP_PACKET_SIZE + 1]);
+
+        let mut handshake = Handshake::new(PeerType::Server);
+        handshake.generate_outbound_p0_and_p1().unwrap();
+
+        let mut clone = handshake.try_clone().unwrap();
+        assert_eq!(
+            clone.sent_p1[..],
+            handshake.sent_p1[..],
+            "Unexpected sent p1"
+        );
+        assert_eq!(
+            clone.sent_digest, handshake.sent_digest,
+            "Unexpected sent digest"
+        );
+
+        let original_p2 = match handshake.process_bytes(&c0_and_c1) {
+            Ok(HandshakeProcessResult::InProgress {
+                response_bytes: data,
+            }) => data,
+            x => panic!("Unexpected process_bytes response: {:?}", x),
+        };
+
+        let cloned_p2 = match clone.process_bytes(&c0_and_c1) {
+            Ok(HandshakeProcessResult::InProgress {
+                response_bytes: data,
+            }) => data,
+            x => panic!("Unexpected process_bytes response: {:?}", x),
+        };
+
+        assert_eq!(original_p2, cloned_p2, "Expected identical p2 responses");
+        assert_eq!(clone.current_stage, Stage::WaitingForPacket2);
+    }
+
+    #[test]
+    fn cannot_clone_handshake_after_peer_p1_processed() {
+        let mut c0_and_c1 = [0_u8; RTMP_PACKET_SIZE + 1];
+        c0_and_c1[0] = 3;
+
+        let mut handshake = Handshake::new(PeerType::Server);
+        handshake.process_bytes(&c0_and_c1).unwrap();
+
+        match handshake.try_clone() {
+            Err(HandshakeError::CannotCloneAtCurrentStage) => (),
+            Err(x) => panic!("Unexpected error: {:?}", x),
+            Ok(_) => panic!("Expected clone to fail"),
+        }
+    }
+
     #[test]
     fn can_handshake_with_itself() {
         // This is the best way to verify we can handle the fp9 handshake method
@@ -841,6 +1090,49 @@ mod tests {
         assert_eq!(server.current_stage, Stage::Complete);
     }
 
+    #[test]
+    #[cfg(feature = "debug")]
+    fn debug_logger_reports_handshake_details() {
+        use std::sync::{Arc, Mutex};
+
+        let lines = Arc::new(Mutex::new(Vec::new()));
+        let logged_lines = lines.clone();
+        let logger =
+            Box::new(move |line: &str| logged_lines.lock().unwrap().push(line.to_string()));
+
+        let mut client = Handshake::new(PeerType::Client);
+        let mut server = Handshake::with_debug_logger(PeerType::Server, logger);
+
+        let c0_and_c1 = client.generate_outbound_p0_and_p1().unwrap();
+        let s0_s1_and_s2 = match server.process_bytes(&c0_and_c1[..]) {
+            Ok(HandshakeProcessResult::InProgress { response_bytes }) => response_bytes,
+            x => panic!("Unexpected process_bytes response: {:?}", x),
+        };
+
+        let c2 = match client.process_bytes(&s0_s1_and_s2[..]) {
+            Ok(HandshakeProcessResult::Completed { response_bytes, .. }) => response_bytes,
+            x => panic!("Unexpected process_bytes response: {:?}", x),
+        };
+
+        match server.process_bytes(&c2[..]) {
+            Ok(HandshakeProcessResult::Completed { .. }) => (),
+            x => panic!("Unexpected process_bytes response: {:?}", x),
+        }
+
+        let lines = lines.lock().unwrap();
+        let has_line = |prefix: &str| lines.iter().any(|line| line.starts_with(prefix));
+
+        assert!(has_line("Received command byte 3"), "No command byte line");
+        assert!(has_line("Sending p1 with digest offset"), "No p1 line");
+        assert!(has_line("Digest offset"), "No received digest line");
+        assert!(has_line("Sending p2 for received digest"), "No p2 line");
+        assert!(has_line("Received p2 signature"), "No received p2 line");
+        assert!(
+            lines.iter().all(|line| !line.contains('\n')),
+            "Log line contained a newline"
+        );
+    }
+
     #[test]
     fn sends_outbound_p0_p1_if_p0_received_and_outbound_p0_and_p1_not_yet_sent() {
         let mut handshake = Handshake::new(PeerType::Server);
@@ -65,25 +65,43 @@ These higher level structs are meant to be integrated *AFTER* a successful hands
 
 */
 
+#![deny(missing_docs)]
+
 extern crate byteorder;
 extern crate bytes;
 extern crate hmac;
 extern crate rand;
 pub extern crate rml_amf0;
+#[cfg(feature = "tls")]
+extern crate rustls;
+#[cfg(feature = "serde")]
+extern crate serde;
+#[cfg(all(test, feature = "serde"))]
+extern crate serde_json;
 extern crate sha2;
 extern crate thiserror;
+#[cfg(feature = "async")]
+extern crate tokio;
+#[cfg(feature = "ws-transport")]
+extern crate tungstenite;
 
-#[cfg(test)]
+#[cfg(any(test, feature = "test-utils"))]
 #[macro_use]
-mod test_utils {
-    #[macro_use]
-    pub mod assert_vec_match_macro;
-    #[macro_use]
-    pub mod assert_vec_contains_macro;
-}
+pub mod test_utils;
 
 pub mod chunk_io;
+pub mod codec_utils;
+pub mod flv;
 pub mod handshake;
 pub mod messages;
+pub mod recording;
+pub mod relay;
 pub mod sessions;
 pub mod time;
+pub mod url;
+
+#[cfg(feature = "capture")]
+pub mod diagnostics;
+
+#[cfg(feature = "ws-transport")]
+pub mod transport;
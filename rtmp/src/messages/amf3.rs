@@ -0,0 +1,443 @@
+//! A basic AMF3 decoder for the AMF3 values that clients embed in AMF0 encoded messages.
+//!
+//! AMF3 values are upgraded to their AMF0 equivalents as they are read, so the rest of the
+//! library only has to deal with `Amf0Value`s.  Only the types that show up in RTMP commands
+//! are supported, and other types (such as dates, XML, and byte arrays) cause an
+//! `UnsupportedAmf3Marker` error.
+
+use byteorder::{BigEndian, ReadBytesExt};
+use rml_amf0::Amf0Value;
+use std::collections::HashMap;
+use std::io::Read;
+
+use messages::MessageDeserializationError;
+
+/// The AMF0 marker that signals the next value is AMF3 encoded
+pub const AVMPLUS_OBJECT_MARKER: u8 = 0x11;
+
+const UNDEFINED_MARKER: u8 = 0x00;
+const NULL_MARKER: u8 = 0x01;
+const FALSE_MARKER: u8 = 0x02;
+const TRUE_MARKER: u8 = 0x03;
+const INTEGER_MARKER: u8 = 0x04;
+const DOUBLE_MARKER: u8 = 0x05;
+const STRING_MARKER: u8 = 0x06;
+const ARRAY_MARKER: u8 = 0x09;
+const OBJECT_MARKER: u8 = 0x0a;
+
+// Matches the default maximum depth of the AMF0 deserializer
+const MAX_DEPTH: usize = 64;
+
+/// The most values the AMF3 values of a single message may expand into, with referenced objects
+/// counting all of the values they contain each time they are referenced
+pub const MAX_VALUES_PER_MESSAGE: usize = 65_536;
+
+struct Traits {
+    is_dynamic: bool,
+    sealed_member_names: Vec<String>,
+}
+
+/// Keeps track of the values that later values can refer back to.  Each AMF3 value embedded in
+/// an AMF0 stream starts with empty reference tables.
+struct Decoder<'a, R: Read> {
+    bytes: &'a mut R,
+    strings: Vec<String>,
+    objects: Vec<Amf0Value>,
+    object_sizes: Vec<usize>,
+    traits: Vec<Traits>,
+    depth: usize,
+    values_remaining: &'a mut usize,
+}
+
+/// Reads a single AMF3 value, converting it into its AMF0 equivalent.  `values_remaining` is
+/// shared by all of the AMF3 values in a message, and is reduced by the number of values read
+/// (including every value copied out of a referenced object).  An error is returned if it would
+/// go below zero.
+pub fn deserialize_value<R: Read>(
+    bytes: &mut R,
+    values_remaining: &mut usize,
+) -> Result<Amf0Value, MessageDeserializationError> {
+    let mut decoder = Decoder {
+        bytes,
+        strings: Vec::new(),
+        objects: Vec::new(),
+        object_sizes: Vec::new(),
+        traits: Vec::new(),
+        depth: 0,
+        values_remaining,
+    };
+
+    decoder.read_value()
+}
+
+impl<'a, R: Read> Decoder<'a, R> {
+    fn read_value(&mut self) -> Result<Amf0Value, MessageDeserializationError> {
+        self.use_values(1)?;
+        let marker = self.bytes.read_u8()?;
+        match marker {
+            UNDEFINED_MARKER => Ok(Amf0Value::Undefined),
+            NULL_MARKER => Ok(Amf0Value::Null),
+            FALSE_MARKER => Ok(Amf0Value::Boolean(false)),
+            TRUE_MARKER => Ok(Amf0Value::Boolean(true)),
+            INTEGER_MARKER => {
+                // Integers are 29 bit signed values
+                let value = self.read_u29()? as i32;
+                let value = (value << 3) >> 3;
+                Ok(Amf0Value::Number(value as f64))
+            }
+
+            DOUBLE_MARKER => Ok(Amf0Value::Number(self.bytes.read_f64::<BigEndian>()?)),
+            STRING_MARKER => Ok(Amf0Value::Utf8String(self.read_string()?)),
+            ARRAY_MARKER => self.read_container(Self::read_array),
+            OBJECT_MARKER => self.read_container(Self::read_object),
+            marker => Err(MessageDeserializationError::UnsupportedAmf3Marker { marker }),
+        }
+    }
+
+    fn read_container<F>(&mut self, read: F) -> Result<Amf0Value, MessageDeserializationError>
+    where
+        F: FnOnce(&mut Self) -> Result<Amf0Value, MessageDeserializationError>,
+    {
+        if self.depth >= MAX_DEPTH {
+            return Err(MessageDeserializationError::InvalidMessageFormat);
+        }
+
+        self.depth += 1;
+        let result = read(self);
+        self.depth -= 1;
+        result
+    }
+
+    fn use_values(&mut self, count: usize) -> Result<(), MessageDeserializationError> {
+        if count > *self.values_remaining {
+            return Err(MessageDeserializationError::Amf3ValueLimitExceeded {
+                limit: MAX_VALUES_PER_MESSAGE,
+            });
+        }
+
+        *self.values_remaining -= count;
+        Ok(())
+    }
+
+    /// Reserves a place in the reference table for an object or array that's about to be read,
+    /// returning its index and the number of values remaining before its contents are read
+    fn reserve_object(&mut self) -> (usize, usize) {
+        self.objects.push(Amf0Value::Null);
+        self.object_sizes.push(1);
+        (self.objects.len() - 1, *self.values_remaining)
+    }
+
+    /// Stores a fully read object or array in its reserved place, along with the number of
+    /// values it contains so references to it can be counted against the message's limit
+    fn store_object(&mut self, index: usize, values_remaining: usize, object: &Amf0Value) {
+        self.objects[index] = object.clone();
+        self.object_sizes[index] = 1 + values_remaining - *self.values_remaining;
+    }
+
+    /// Reads a variable length unsigned 29 bit integer.  The first three bytes contribute 7 bits
+    /// each, with the high bit signaling another byte follows, and the fourth contributes 8.
+    fn read_u29(&mut self) -> Result<u32, MessageDeserializationError> {
+        let mut value = 0_u32;
+        for _ in 0..3 {
+            let byte = self.bytes.read_u8()?;
+            value = (value << 7) | (byte & 0x7f) as u32;
+            if byte & 0x80 == 0 {
+                return Ok(value);
+            }
+        }
+
+        let byte = self.bytes.read_u8()?;
+        Ok((value << 8) | byte as u32)
+    }
+
+    fn read_string(&mut self) -> Result<String, MessageDeserializationError> {
+        let header = self.read_u29()?;
+        if header & 1 == 0 {
+            return self
+                .strings
+                .get((header >> 1) as usize)
+                .cloned()
+                .ok_or(MessageDeserializationError::InvalidMessageFormat);
+        }
+
+        // Read through `take()` so a corrupt length can't cause a huge allocation up front
+        let length = (header >> 1) as usize;
+        let mut buffer = Vec::new();
+        self.bytes
+            .by_ref()
+            .take(length as u64)
+            .read_to_end(&mut buffer)?;
+        if buffer.len() != length {
+            return Err(MessageDeserializationError::InvalidMessageFormat);
+        }
+
+        let value = String::from_utf8(buffer)
+            .map_err(|_| MessageDeserializationError::InvalidMessageFormat)?;
+
+        // Empty strings are never sent by reference
+        if !value.is_empty() {
+            self.strings.push(value.clone());
+        }
+
+        Ok(value)
+    }
+
+    fn read_object_reference(
+        &mut self,
+        header: u32,
+    ) -> Result<Amf0Value, MessageDeserializationError> {
+        let index = (header >> 1) as usize;
+        let size = *self
+            .object_sizes
+            .get(index)
+            .ok_or(MessageDeserializationError::InvalidMessageFormat)?;
+
+        self.use_values(size)?;
+        Ok(self.objects[index].clone())
+    }
+
+    /// Reads an array.  Arrays with only dense values become strict arrays, while arrays with
+    /// named values become ECMA arrays with the dense values keyed by their index.
+    fn read_array(&mut self) -> Result<Amf0Value, MessageDeserializationError> {
+        let header = self.read_u29()?;
+        if header & 1 == 0 {
+            return self.read_object_reference(header);
+        }
+
+        // Reserve the array's place in the reference table before reading its values
+        let (index, values_remaining) = self.reserve_object();
+
+        let mut properties = HashMap::new();
+        loop {
+            let key = self.read_string()?;
+            if key.is_empty() {
+                break;
+            }
+
+            let value = self.read_value()?;
+            properties.insert(key, value);
+        }
+
+        let dense_count = (header >> 1) as usize;
+        let mut values = Vec::new();
+        for _ in 0..dense_count {
+            values.push(self.read_value()?);
+        }
+
+        let array = if properties.is_empty() {
+            Amf0Value::StrictArray(values)
+        } else {
+            for (index, value) in values.into_iter().enumerate() {
+                properties.insert(index.to_string(), value);
+            }
+
+            Amf0Value::EcmaArray(properties)
+        };
+
+        self.store_object(index, values_remaining, &array);
+        Ok(array)
+    }
+
+    fn read_object(&mut self) -> Result<Amf0Value, MessageDeserializationError> {
+        let header = self.read_u29()?;
+        if header & 1 == 0 {
+            return self.read_object_reference(header);
+        }
+
+        let traits_index = if header & 2 == 0 {
+            let index = (header >> 2) as usize;
+            if index >= self.traits.len() {
+                return Err(MessageDeserializationError::InvalidMessageFormat);
+            }
+
+            index
+        } else {
+            if header & 4 != 0 {
+                // Externalizable objects have a class specific format that can't be read
+                return Err(MessageDeserializationError::UnsupportedAmf3Marker {
+                    marker: OBJECT_MARKER,
+                });
+            }
+
+            let _class_name = self.read_string()?;
+            let mut sealed_member_names = Vec::new();
+            for _ in 0..(header >> 4) {
+                sealed_member_names.push(self.read_string()?);
+            }
+
+            self.traits.push(Traits {
+                is_dynamic: header & 8 != 0,
+                sealed_member_names,
+            });
+
+            self.traits.len() - 1
+        };
+
+        let (index, values_remaining) = self.reserve_object();
+
+        let mut properties = HashMap::new();
+        for member_index in 0..self.traits[traits_index].sealed_member_names.len() {
+            let value = self.read_value()?;
+            let name = self.traits[traits_index].sealed_member_names[member_index].clone();
+            properties.insert(name, value);
+        }
+
+        if self.traits[traits_index].is_dynamic {
+            loop {
+                let key = self.read_string()?;
+                if key.is_empty() {
+                    break;
+                }
+
+                let value = self.read_value()?;
+                properties.insert(key, value);
+            }
+        }
+
+        let object = Amf0Value::Object(properties);
+        self.store_object(index, values_remaining, &object);
+        Ok(object)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{deserialize_value, MAX_VALUES_PER_MESSAGE};
+    use rml_amf0::Amf0Value;
+    use std::collections::HashMap;
+    use std::io::Cursor;
+
+    use messages::MessageDeserializationError;
+
+    #[test]
+    fn can_read_simple_values() {
+        assert_eq!(read(&[0x00]), Amf0Value::Undefined);
+        assert_eq!(read(&[0x01]), Amf0Value::Null);
+        assert_eq!(read(&[0x02]), Amf0Value::Boolean(false));
+        assert_eq!(read(&[0x03]), Amf0Value::Boolean(true));
+        assert_eq!(
+            read(&[0x05, 0x40, 0x09, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00]),
+            Amf0Value::Number(3.125)
+        );
+        assert_eq!(
+            read(&[0x06, 0x0b, b'h', b'e', b'l', b'l', b'o']),
+            Amf0Value::Utf8String("hello".to_string())
+        );
+    }
+
+    #[test]
+    fn can_read_variable_length_integers() {
+        assert_eq!(read(&[0x04, 0x7f]), Amf0Value::Number(127.0));
+        assert_eq!(read(&[0x04, 0x81, 0x00]), Amf0Value::Number(128.0));
+        assert_eq!(
+            read(&[0x04, 0xbf, 0xff, 0xff, 0xff]),
+            Amf0Value::Number(268435455.0)
+        );
+        assert_eq!(
+            read(&[0x04, 0xff, 0xff, 0xff, 0xff]),
+            Amf0Value::Number(-1.0)
+        );
+    }
+
+    #[test]
+    fn can_read_dense_array_with_string_references() {
+        let bytes = [
+            0x09, 0x07, 0x01, // array of 3 dense values, no named values
+            0x06, 0x05, b'a', b'b', // "ab"
+            0x06, 0x00, // reference to "ab"
+            0x01, // null
+        ];
+
+        let expected = Amf0Value::StrictArray(vec![
+            Amf0Value::Utf8String("ab".to_string()),
+            Amf0Value::Utf8String("ab".to_string()),
+            Amf0Value::Null,
+        ]);
+
+        assert_eq!(read(&bytes), expected);
+    }
+
+    #[test]
+    fn can_read_objects_with_sealed_and_dynamic_members() {
+        let bytes = [
+            0x0a, 0x1b, // inline dynamic traits with 1 sealed member
+            0x01, // anonymous class name
+            0x03, b'a', // sealed member name "a"
+            0x04, 0x01, // a = 1
+            0x03, b'b', 0x03, // b = true (dynamic)
+            0x01, // end of dynamic members
+        ];
+
+        let mut properties = HashMap::new();
+        properties.insert("a".to_string(), Amf0Value::Number(1.0));
+        properties.insert("b".to_string(), Amf0Value::Boolean(true));
+
+        assert_eq!(read(&bytes), Amf0Value::Object(properties));
+    }
+
+    #[test]
+    fn unsupported_types_return_error() {
+        let mut values_remaining = MAX_VALUES_PER_MESSAGE;
+        let result = deserialize_value(&mut Cursor::new(vec![0x0c, 0x01]), &mut values_remaining);
+        match result {
+            Err(MessageDeserializationError::UnsupportedAmf3Marker { marker: 0x0c }) => (),
+            x => panic!(
+                "Expected UnsupportedAmf3Marker error, instead received {:?}",
+                x
+            ),
+        }
+    }
+
+    #[test]
+    fn error_when_repeated_references_exceed_value_limit() {
+        // Each array holds two references to the array before it, doubling the number of values
+        // the message expands into with every level
+        let mut bytes = vec![0x09, 0x01, 0x01]; // empty array (reference 1, after the outer array)
+        for level in 1..40_u8 {
+            let previous = level << 1;
+            bytes.extend_from_slice(&[0x09, 0x05, 0x01]); // array of 2 dense values
+            bytes.extend_from_slice(&[0x09, previous, 0x09, previous]);
+        }
+
+        // Wrap the levels in an outer array so they're read as a single value
+        let mut message = vec![0x09, 0x51, 0x01]; // array of 40 dense values
+        message.extend_from_slice(&bytes);
+
+        let mut values_remaining = MAX_VALUES_PER_MESSAGE;
+        let result = deserialize_value(&mut Cursor::new(message), &mut values_remaining);
+        match result {
+            Err(MessageDeserializationError::Amf3ValueLimitExceeded { limit }) => {
+                assert_eq!(limit, MAX_VALUES_PER_MESSAGE, "Unexpected limit");
+            }
+
+            x => panic!(
+                "Expected Amf3ValueLimitExceeded error, instead received {:?}",
+                x
+            ),
+        }
+    }
+
+    #[test]
+    fn value_limit_is_shared_across_values() {
+        let bytes = [0x09, 0x05, 0x01, 0x01, 0x01]; // array of 2 nulls
+        let mut values_remaining = 5;
+        let mut cursor = Cursor::new(bytes.to_vec());
+        deserialize_value(&mut cursor, &mut values_remaining).unwrap();
+        assert_eq!(values_remaining, 2, "Unexpected number of values remaining");
+
+        cursor.set_position(0);
+        let result = deserialize_value(&mut cursor, &mut values_remaining);
+        match result {
+            Err(MessageDeserializationError::Amf3ValueLimitExceeded { .. }) => (),
+            x => panic!(
+                "Expected Amf3ValueLimitExceeded error, instead received {:?}",
+                x
+            ),
+        }
+    }
+
+    fn read(bytes: &[u8]) -> Amf0Value {
+        let mut values_remaining = MAX_VALUES_PER_MESSAGE;
+        deserialize_value(&mut Cursor::new(bytes.to_vec()), &mut values_remaining).unwrap()
+    }
+}
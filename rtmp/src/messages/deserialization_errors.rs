@@ -18,6 +18,22 @@ pub enum MessageDeserializationError {
     #[error("The message did no contain valid Amf0 encoded values: {0}")]
     Amf0DeserializationError(#[from] Amf0DeserializationError),
 
+    /// The message contained an AMF3 value of a type that can't be converted into an AMF0 value
+    #[error("The message contained an unsupported AMF3 value with marker {marker}")]
+    UnsupportedAmf3Marker {
+        /// The AMF3 type marker of the value
+        marker: u8,
+    },
+
+    /// The message's AMF3 values expanded into more values than are allowed for a single
+    /// message.  Object references are counted once per use, so a small message that refers to
+    /// the same objects repeatedly can't make the deserializer do an unbounded amount of work.
+    #[error("The message's AMF3 values expanded into more than {limit} values")]
+    Amf3ValueLimitExceeded {
+        /// The maximum number of values allowed in a message
+        limit: usize,
+    },
+
     /// Failed to read the values from the input buffer
     #[error("An IO error occurred while reading the input: {0}")]
     Io(#[from] io::Error),
@@ -6,11 +6,19 @@ use std::fmt;
 use time::RtmpTimestamp;
 
 /// Represents a raw RTMP message
-#[derive(PartialEq)]
+#[derive(PartialEq, Clone)]
 pub struct MessagePayload {
+    /// The timestamp of the message, in milliseconds
     pub timestamp: RtmpTimestamp,
+
+    /// The RTMP message type id, which determines how `data` is interpreted
     pub type_id: u8,
+
+    /// The id of the message stream the message belongs to, with 0 being used for protocol
+    /// control messages and connection level commands
     pub message_stream_id: u32,
+
+    /// The raw bytes of the message body
     pub data: Bytes,
 }
 
@@ -24,12 +32,12 @@ impl fmt::Debug for MessagePayload {
     }
 }
 
-impl MessagePayload {
+impl Default for MessagePayload {
     /// Creates a new message payload with default values.
     ///
     /// This is mostly used when all information about a message is not known at creation time
     /// but instead is built up over time (e.g. RTMP chunk deserialization process).
-    pub fn new() -> MessagePayload {
+    fn default() -> MessagePayload {
         MessagePayload {
             timestamp: RtmpTimestamp::new(0),
             message_stream_id: 0,
@@ -37,13 +45,32 @@ impl MessagePayload {
             data: Bytes::new(),
         }
     }
+}
+
+impl MessagePayload {
+    /// Creates a new message payload directly from its raw parts, without needing to
+    /// go through an `RtmpMessage` first.
+    pub fn new(
+        type_id: u8,
+        timestamp: RtmpTimestamp,
+        message_stream_id: u32,
+        data: Bytes,
+    ) -> MessagePayload {
+        MessagePayload {
+            timestamp,
+            type_id,
+            message_stream_id,
+            data,
+        }
+    }
 
     /// Deserializes the message data in the specified payload into its corresponding
     /// `RtmpMessage`.
     ///
     /// Note that flash clients (like Wowza's test client) lie and mark amf0 data and commands as
-    /// amf3 values.  It is unknown why this happens, but any Amf3 command/data messages that are
-    /// seen are deserialized as amf0.  So far this has not caused any issues.
+    /// amf3 values.  It is unknown why this happens, but any Amf3 data messages that are seen are
+    /// deserialized as amf0.  Amf3 command messages are deserialized as amf0 as well, except that
+    /// any values switched to amf3 encoding are decoded and converted to their amf0 equivalents.
     pub fn to_rtmp_message(&self) -> Result<RtmpMessage, MessageDeserializationError> {
         match self.type_id {
             1 => types::set_chunk_size::deserialize(self.data.clone()),
@@ -55,21 +82,19 @@ impl MessagePayload {
             8 => types::audio_data::deserialize(self.data.clone()),
             9 => types::video_data::deserialize(self.data.clone()),
             18 => types::amf0_data::deserialize(self.data.clone()),
+            19 => types::shared_object::deserialize(self.data.clone()),
+            22 => types::aggregate::deserialize(
+                self.data.clone(),
+                self.timestamp,
+                self.message_stream_id,
+            ),
             20 => types::amf0_command::deserialize(self.data.clone()),
 
             // For some reason Flash players (like wowza's test player) send messages
             // that are flagged as amf3 encoded, but in reality they are amf0 encoded
             15 => types::amf0_data::deserialize(self.data.clone()),
 
-            17 => {
-                // Fake amf3 commands usually seem to have a 0 in front of the amf0 data.
-                if self.data.len() > 0 && self.data[0] == 0x00 {
-                    let slice = self.data.slice(1..);
-                    types::amf0_command::deserialize(slice)
-                } else {
-                    types::amf0_command::deserialize(self.data.clone())
-                }
-            }
+            17 => types::amf0_command::deserialize_amf3(self.data.clone()),
 
             _ => Ok(RtmpMessage::Unknown {
                 type_id: self.type_id,
@@ -88,6 +113,7 @@ impl MessagePayload {
         message_stream_id: u32,
     ) -> Result<MessagePayload, MessageSerializationError> {
         let type_id = message.get_message_type_id();
+        let capacity = message.approximate_byte_size();
 
         let bytes = match message {
             RtmpMessage::Unknown { type_id: _, data } => data,
@@ -108,9 +134,19 @@ impl MessagePayload {
                 transaction_id,
                 command_object,
                 additional_arguments,
+                capacity,
             )?,
 
-            RtmpMessage::Amf0Data { values } => types::amf0_data::serialize(values)?,
+            RtmpMessage::Amf0Data { values } => types::amf0_data::serialize(values, capacity)?,
+
+            RtmpMessage::SharedObject {
+                name,
+                version,
+                persistence,
+                events,
+            } => types::shared_object::serialize(name, version, persistence, events, capacity)?,
+
+            RtmpMessage::Aggregate { messages } => types::aggregate::serialize(messages, capacity)?,
 
             RtmpMessage::AudioData { data } => types::audio_data::serialize(data)?,
 
@@ -147,10 +183,128 @@ impl MessagePayload {
 mod tests {
     use super::{MessagePayload, RtmpMessage};
     use bytes::{BufMut, Bytes, BytesMut};
-    use messages::{PeerBandwidthLimitType, UserControlEventType};
+    use messages::{
+        MessageDeserializationError, PeerBandwidthLimitType, SharedObjectEvent,
+        UserControlEventType,
+    };
+    use rml_amf0;
     use rml_amf0::Amf0Value;
+    use std::collections::HashMap;
     use time::RtmpTimestamp;
 
+    #[test]
+    fn can_create_payload_from_raw_parts() {
+        let data = Bytes::from(vec![1_u8, 2_u8, 3_u8]);
+        let payload = MessagePayload::new(200, RtmpTimestamp::new(55), 12, data.clone());
+
+        assert_eq!(payload.type_id, 200, "Incorrect type id");
+        assert_eq!(
+            payload.timestamp,
+            RtmpTimestamp::new(55),
+            "Incorrect timestamp"
+        );
+        assert_eq!(payload.message_stream_id, 12, "Incorrect stream id");
+        assert_eq!(payload.data, data, "Incorrect data");
+
+        let message = payload.to_rtmp_message().unwrap();
+        assert_eq!(message, RtmpMessage::Unknown { type_id: 200, data });
+    }
+
+    #[test]
+    fn approximate_byte_size_never_underestimates_serialized_size() {
+        let mut properties = HashMap::new();
+        properties.insert("app".to_string(), Amf0Value::Utf8String("live".to_string()));
+        properties.insert("number".to_string(), Amf0Value::Number(23.0));
+
+        let messages = vec![
+            RtmpMessage::Unknown {
+                type_id: 200,
+                data: Bytes::from(vec![1_u8, 2_u8, 3_u8]),
+            },
+            RtmpMessage::Abort { stream_id: 23 },
+            RtmpMessage::Acknowledgement {
+                sequence_number: 55,
+            },
+            RtmpMessage::Amf0Command {
+                command_name: "connect".to_string(),
+                transaction_id: 1.0,
+                command_object: Amf0Value::Object(properties.clone()),
+                additional_arguments: vec![Amf0Value::Null, Amf0Value::Boolean(true)],
+            },
+            RtmpMessage::Amf0Data {
+                values: vec![
+                    Amf0Value::Utf8String("onMetaData".to_string()),
+                    Amf0Value::Object(properties),
+                    Amf0Value::StrictArray(vec![Amf0Value::Undefined]),
+                ],
+            },
+            RtmpMessage::AudioData {
+                data: Bytes::from(vec![1_u8, 2_u8, 3_u8, 4_u8]),
+            },
+            RtmpMessage::SetChunkSize { size: 4096 },
+            RtmpMessage::SetPeerBandwidth {
+                size: 1000,
+                limit_type: PeerBandwidthLimitType::Dynamic,
+            },
+            RtmpMessage::UserControl {
+                event_type: UserControlEventType::SetBufferLength,
+                stream_id: Some(1),
+                buffer_length: Some(2000),
+                timestamp: None,
+            },
+            RtmpMessage::UserControl {
+                event_type: UserControlEventType::PingRequest,
+                stream_id: None,
+                buffer_length: None,
+                timestamp: Some(RtmpTimestamp::new(100)),
+            },
+            RtmpMessage::SharedObject {
+                name: "chat".to_string(),
+                version: 1,
+                persistence: true,
+                events: vec![
+                    SharedObjectEvent::Use,
+                    SharedObjectEvent::Change {
+                        key: "topic".to_string(),
+                        value: Amf0Value::Utf8String("hello".to_string()),
+                    },
+                    SharedObjectEvent::SendMessage {
+                        values: vec![Amf0Value::Utf8String("onMessage".to_string())],
+                    },
+                    SharedObjectEvent::Status {
+                        code: "SharedObject.BadPersistence".to_string(),
+                        level: "error".to_string(),
+                    },
+                ],
+            },
+            RtmpMessage::Aggregate {
+                messages: vec![
+                    MessagePayload::new(8, RtmpTimestamp::new(0), 1, Bytes::from(vec![1_u8; 10])),
+                    MessagePayload::new(9, RtmpTimestamp::new(0), 1, Bytes::from(vec![2_u8; 20])),
+                ],
+            },
+            RtmpMessage::VideoData {
+                data: Bytes::from(vec![5_u8; 100]),
+            },
+            RtmpMessage::WindowAcknowledgement { size: 5000 },
+        ];
+
+        for message in messages {
+            let approximate_size = message.approximate_byte_size();
+            let description = format!("{:?}", message);
+            let payload =
+                MessagePayload::from_rtmp_message(message, RtmpTimestamp::new(0), 1).unwrap();
+
+            assert!(
+                approximate_size >= payload.data.len(),
+                "Approximate size of {} was less than actual size of {} for {}",
+                approximate_size,
+                payload.data.len(),
+                description
+            );
+        }
+    }
+
     #[test]
     fn can_get_payload_from_abort_message() {
         let timestamp = RtmpTimestamp::new(55);
@@ -403,6 +557,25 @@ mod tests {
         assert_eq!(result, message);
     }
 
+    #[test]
+    fn can_get_rtmp_message_for_shared_object_payload() {
+        let message = RtmpMessage::SharedObject {
+            name: "chat".to_string(),
+            version: 2,
+            persistence: false,
+            events: vec![SharedObjectEvent::Change {
+                key: "topic".to_string(),
+                value: Amf0Value::Utf8String("hello".to_string()),
+            }],
+        };
+        let payload =
+            MessagePayload::from_rtmp_message(message.clone(), RtmpTimestamp::new(0), 15).unwrap();
+        let result = payload.to_rtmp_message().unwrap();
+
+        assert_eq!(payload.type_id, 19, "Incorrect type id");
+        assert_eq!(result, message);
+    }
+
     #[test]
     fn can_get_rtmp_message_for_audio_data_payload() {
         let message = RtmpMessage::AudioData {
@@ -499,6 +672,72 @@ mod tests {
         assert_eq!(result, message);
     }
 
+    #[test]
+    fn can_get_rtmp_message_for_connect_command_with_amf3_command_object() {
+        let mut data = vec![0x00]; // format byte
+        data.extend(
+            rml_amf0::serialize(&vec![
+                Amf0Value::Utf8String("connect".to_string()),
+                Amf0Value::Number(1.0),
+            ])
+            .unwrap(),
+        );
+
+        data.extend_from_slice(&[
+            0x11, // switch to AMF3
+            0x0a, 0x0b, 0x01, // anonymous dynamic object
+            0x07, b'a', b'p', b'p', 0x06, 0x09, b'l', b'i', b'v', b'e', // app = "live"
+            0x1d, b'o', b'b', b'j', b'e', b'c', b't', b'E', b'n', b'c', b'o', b'd', b'i', b'n',
+            b'g', 0x04, 0x03, // objectEncoding = 3
+            0x09, b'f', b'p', b'a', b'd', 0x02, // fpad = false
+            0x01, // end of dynamic members
+        ]);
+
+        let payload = MessagePayload::new(17, RtmpTimestamp::new(0), 0, Bytes::from(data));
+        let result = payload.to_rtmp_message().unwrap();
+
+        let mut properties = HashMap::new();
+        properties.insert("app".to_string(), Amf0Value::Utf8String("live".to_string()));
+        properties.insert("objectEncoding".to_string(), Amf0Value::Number(3.0));
+        properties.insert("fpad".to_string(), Amf0Value::Boolean(false));
+
+        let expected = RtmpMessage::Amf0Command {
+            command_name: "connect".to_string(),
+            transaction_id: 1.0,
+            command_object: Amf0Value::Object(properties),
+            additional_arguments: vec![],
+        };
+
+        assert_eq!(result, expected);
+    }
+
+    #[test]
+    fn error_when_amf3_command_references_expand_past_value_limit() {
+        let mut data = vec![0x00]; // format byte
+        data.extend(
+            rml_amf0::serialize(&vec![
+                Amf0Value::Utf8String("connect".to_string()),
+                Amf0Value::Number(1.0),
+            ])
+            .unwrap(),
+        );
+
+        // An array of arrays, where each array holds two references to the one before it
+        data.extend_from_slice(&[0x11, 0x09, 0x51, 0x01, 0x09, 0x01, 0x01]);
+        for level in 1..40_u8 {
+            data.extend_from_slice(&[0x09, 0x05, 0x01, 0x09, level << 1, 0x09, level << 1]);
+        }
+
+        let payload = MessagePayload::new(17, RtmpTimestamp::new(0), 0, Bytes::from(data));
+        match payload.to_rtmp_message() {
+            Err(MessageDeserializationError::Amf3ValueLimitExceeded { .. }) => (),
+            x => panic!(
+                "Expected Amf3ValueLimitExceeded error, instead received {:?}",
+                x
+            ),
+        }
+    }
+
     #[test]
     fn can_get_rtmp_message_for_amf0_data_payload_flagged_as_amf3() {
         let message = RtmpMessage::Amf0Data {
@@ -6,6 +6,7 @@ and deserializing these messages into payloads.
 meant for, the timestamp for the message and what type of message it is.
 */
 
+mod amf3;
 mod deserialization_errors;
 mod message_payload;
 mod serialization_errors;
@@ -72,63 +73,211 @@ pub enum UserControlEventType {
     BufferReady,
 }
 
+/// Events that can be contained in a shared object message (RTMP spec section 7.1.3)
+#[derive(PartialEq, Debug, Clone)]
+pub enum SharedObjectEvent {
+    /// The client wants to start using the shared object
+    Use,
+
+    /// The client no longer needs the shared object
+    Release,
+
+    /// The client requests that a property of the shared object be changed
+    RequestChange {
+        /// The name of the property
+        key: String,
+
+        /// The requested value of the property
+        value: Amf0Value,
+    },
+
+    /// Notifies the peer that a property of the shared object has changed
+    Change {
+        /// The name of the property
+        key: String,
+
+        /// The new value of the property
+        value: Amf0Value,
+    },
+
+    /// The server accepted a `RequestChange` event for the property
+    Success {
+        /// The name of the property that was changed
+        key: String,
+    },
+
+    /// Broadcasts a message to all clients using the shared object
+    SendMessage {
+        /// The values of the message, usually starting with the name of the method to invoke
+        values: Vec<Amf0Value>,
+    },
+
+    /// Notifies the client of an error or warning related to the shared object
+    Status {
+        /// The status code
+        code: String,
+
+        /// The level of the status, such as `error` or `warning`
+        level: String,
+    },
+
+    /// Notifies the client that all properties of the shared object should be cleared
+    Clear,
+
+    /// Notifies the client that a property of the shared object was deleted (called `Remove`
+    /// in the RTMP spec)
+    Delete {
+        /// The name of the property
+        key: String,
+    },
+
+    /// The client requests that a property of the shared object be deleted
+    RequestDelete {
+        /// The name of the property
+        key: String,
+    },
+
+    /// The server has accepted the client's `Use` event
+    UseSuccess,
+
+    /// An event with a type that we do not know about, which is kept as is so it can be passed
+    /// along without failing the rest of the message
+    Unknown {
+        /// The event type that was not recognized
+        event_type: u8,
+
+        /// The raw bytes of the event's data
+        data: Bytes,
+    },
+}
+
 /// An enumeration of all types of RTMP messages that are supported
 #[derive(PartialEq, Debug, Clone)]
 pub enum RtmpMessage {
     /// This type of message is used when an RTMP message is encountered with a type id that
     /// we do not know about
-    Unknown { type_id: u8, data: Bytes },
+    Unknown {
+        /// The message type id that was not recognized
+        type_id: u8,
+
+        /// The raw bytes of the message body
+        data: Bytes,
+    },
 
     /// Notifies the peer that if it is waiting for chunks to complete a message that it should
-    /// discard the chunks it has already received.
-    Abort { stream_id: u32 },
+    /// discard the chunks it has already received (RTMP spec section 5.4.2).
+    Abort {
+        /// The chunk stream id whose partially received message should be discarded
+        stream_id: u32,
+    },
 
     /// An acknowledgement sent to confirm how many bytes that has been received since the prevoius
-    /// acknowledgement.
-    Acknowledgement { sequence_number: u32 },
+    /// acknowledgement (RTMP spec section 5.4.3).
+    Acknowledgement {
+        /// The number of bytes received so far
+        sequence_number: u32,
+    },
 
-    /// A command being sent, encoded with amf0 values
+    /// A command being sent, encoded with amf0 values (RTMP spec section 7.1.1)
     Amf0Command {
+        /// The name of the command, such as `connect` or `publish`
         command_name: String,
+
+        /// Identifies the request a response belongs to, or 0 if no response is expected
         transaction_id: f64,
+
+        /// The command object, which is `Amf0Value::Null` for commands without one
         command_object: Amf0Value,
+
+        /// Any values that follow the command object
         additional_arguments: Vec<Amf0Value>,
     },
 
-    /// A message containing an array of data encoded as amf0 values
-    Amf0Data { values: Vec<Amf0Value> },
+    /// A message containing an array of data encoded as amf0 values (RTMP spec section 7.1.2)
+    Amf0Data {
+        /// The values contained in the message
+        values: Vec<Amf0Value>,
+    },
+
+    /// A message containing events for a shared object, encoded with amf0 values (RTMP spec
+    /// section 7.1.3)
+    SharedObject {
+        /// The name of the shared object
+        name: String,
+
+        /// The version of the shared object the events apply to
+        version: u32,
 
-    /// A message containing audio data
-    AudioData { data: Bytes },
+        /// If the shared object is persisted by the server
+        persistence: bool,
+
+        /// The events that occurred on the shared object
+        events: Vec<SharedObjectEvent>,
+    },
+
+    /// A single message containing multiple audio, video and data messages, to reduce the
+    /// overhead of sending each of them in their own chunks (RTMP spec section 7.1.6)
+    Aggregate {
+        /// The payloads of the contained messages, with their timestamps and message stream ids
+        /// already adjusted to the aggregate message's
+        messages: Vec<MessagePayload>,
+    },
+
+    /// A message containing audio data (RTMP spec section 7.1.4)
+    AudioData {
+        /// The audio data, in the same format as the body of an FLV audio tag
+        data: Bytes,
+    },
 
     /// Tells the peer that the maximum chunk size for RTMP chunks it will be sending is changing
-    /// to the specified size.
-    SetChunkSize { size: u32 },
+    /// to the specified size (RTMP spec section 5.4.1).
+    SetChunkSize {
+        /// The new maximum chunk size, in bytes
+        size: u32,
+    },
 
-    /// Indicates that the peer should limit its output bandwidth
+    /// Indicates that the peer should limit its output bandwidth (RTMP spec section 5.4.5)
     SetPeerBandwidth {
+        /// The acknowledgement window size, in bytes
         size: u32,
+
+        /// How the peer should apply the limit
         limit_type: PeerBandwidthLimitType,
     },
 
     /// Notifies the peer of an event, such as a stream being
-    /// created or telling the peer how much of a buffer it should have.
+    /// created or telling the peer how much of a buffer it should have (RTMP spec section 6.2).
     UserControl {
+        /// The type of event being raised
         event_type: UserControlEventType,
+
+        /// The message stream the event applies to, for events that pertain to a stream
         stream_id: Option<u32>,
+
+        /// The buffer length in milliseconds, only present for `SetBufferLength` events
         buffer_length: Option<u32>,
+
+        /// The timestamp of ping requests and responses
         timestamp: Option<RtmpTimestamp>,
     },
 
-    /// A message containing video data
-    VideoData { data: Bytes },
+    /// A message containing video data (RTMP spec section 7.1.5)
+    VideoData {
+        /// The video data, in the same format as the body of an FLV video tag
+        data: Bytes,
+    },
 
     /// Notifies the peer how many bytes should be received before sending an `Acknowledgement`
-    /// message
-    WindowAcknowledgement { size: u32 },
+    /// message (RTMP spec section 5.4.4)
+    WindowAcknowledgement {
+        /// The number of bytes that can be received before an acknowledgement is sent
+        size: u32,
+    },
 }
 
 impl RtmpMessage {
+    /// Converts the message into a payload with the specified timestamp and message stream id,
+    /// so it can be serialized into RTMP chunks.
     pub fn into_message_payload(
         self,
         timestamp: RtmpTimestamp,
@@ -137,6 +286,57 @@ impl RtmpMessage {
         MessagePayload::from_rtmp_message(self, timestamp, message_stream_id)
     }
 
+    /// Returns an estimate of how many bytes this message's payload will take up once it has
+    /// been serialized.  This is meant as a hint for pre-allocating buffers and is not guaranteed
+    /// to be exact, though it should never be smaller than the actual serialized size.
+    pub fn approximate_byte_size(&self) -> usize {
+        match *self {
+            RtmpMessage::Unknown { ref data, .. } => data.len(),
+            RtmpMessage::Abort { .. } => 4,
+            RtmpMessage::Acknowledgement { .. } => 4,
+            RtmpMessage::Amf0Command {
+                ref command_name,
+                transaction_id: _,
+                ref command_object,
+                ref additional_arguments,
+            } => {
+                // command name string + transaction id number + all remaining values
+                let arguments_size: usize = additional_arguments
+                    .iter()
+                    .map(|value| value.byte_len())
+                    .sum();
+
+                3 + command_name.len() + 9 + command_object.byte_len() + arguments_size
+            }
+            RtmpMessage::Amf0Data { ref values } => {
+                values.iter().map(|value| value.byte_len()).sum()
+            }
+            RtmpMessage::SharedObject {
+                ref name,
+                ref events,
+                ..
+            } => {
+                // name string + version + flags + reserved + all events
+                let events_size: usize = events
+                    .iter()
+                    .map(types::shared_object::approximate_event_size)
+                    .sum();
+
+                2 + name.len() + 12 + events_size
+            }
+            RtmpMessage::Aggregate { ref messages } => {
+                types::aggregate::approximate_byte_size(messages)
+            }
+            RtmpMessage::AudioData { ref data } => data.len(),
+            RtmpMessage::SetChunkSize { .. } => 4,
+            RtmpMessage::SetPeerBandwidth { .. } => 5,
+            RtmpMessage::UserControl { .. } => 10,
+            RtmpMessage::VideoData { ref data } => data.len(),
+            RtmpMessage::WindowAcknowledgement { .. } => 4,
+        }
+    }
+
+    /// Returns the RTMP message type id that is used when this message is serialized
     pub fn get_message_type_id(&self) -> u8 {
         match *self {
             RtmpMessage::Unknown { type_id, data: _ } => type_id,
@@ -149,6 +349,13 @@ impl RtmpMessage {
                 additional_arguments: _,
             } => 20_u8,
             RtmpMessage::Amf0Data { values: _ } => 18_u8,
+            RtmpMessage::SharedObject {
+                name: _,
+                version: _,
+                persistence: _,
+                events: _,
+            } => 19_u8,
+            RtmpMessage::Aggregate { messages: _ } => 22_u8,
             RtmpMessage::AudioData { data: _ } => 8_u8,
             RtmpMessage::SetChunkSize { size: _ } => 1_u8,
             RtmpMessage::SetPeerBandwidth {
@@ -16,6 +16,13 @@ pub enum MessageSerializationError {
     #[error("The values provided could not be serialized into valid AMF0 encoded data")]
     Amf0SerializationError(#[from] Amf0SerializationError),
 
+    /// A message in an aggregate message was larger than the 24 bit length field allows
+    #[error("Cannot aggregate a message of {size} bytes, as the maximum is 16777215 bytes")]
+    AggregateSubMessageTooLarge {
+        /// The size of the message's data in bytes
+        size: usize,
+    },
+
     /// Failed to read the values from the input buffer
     #[error("An IO error occurred while writing the output")]
     Io(#[from] io::Error),
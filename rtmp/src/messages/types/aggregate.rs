@@ -0,0 +1,183 @@
+use byteorder::{BigEndian, ReadBytesExt, WriteBytesExt};
+use bytes::Bytes;
+use std::io::{Cursor, Write};
+
+use messages::{MessageDeserializationError, MessageSerializationError};
+use messages::{MessagePayload, RtmpMessage};
+use time::RtmpTimestamp;
+
+// type + data size + timestamp + extended timestamp + stream id
+const SUB_MESSAGE_HEADER_SIZE: usize = 11;
+const BACK_POINTER_SIZE: usize = 4;
+const MAX_SUB_MESSAGE_SIZE: usize = 0xFFFFFF;
+const AGGREGATE_TYPE_ID: u8 = 22;
+
+pub fn serialize(
+    messages: Vec<MessagePayload>,
+    capacity: usize,
+) -> Result<Bytes, MessageSerializationError> {
+    let mut cursor = Cursor::new(Vec::with_capacity(capacity));
+    for message in messages {
+        if message.data.len() > MAX_SUB_MESSAGE_SIZE {
+            return Err(MessageSerializationError::AggregateSubMessageTooLarge {
+                size: message.data.len(),
+            });
+        }
+
+        cursor.write_u8(message.type_id)?;
+        cursor.write_u24::<BigEndian>(message.data.len() as u32)?;
+        cursor.write_u24::<BigEndian>(message.timestamp.value & 0xFFFFFF)?;
+        cursor.write_u8((message.timestamp.value >> 24) as u8)?;
+        cursor.write_u24::<BigEndian>(message.message_stream_id & 0xFFFFFF)?;
+        cursor.write_all(&message.data)?;
+        cursor.write_u32::<BigEndian>((SUB_MESSAGE_HEADER_SIZE + message.data.len()) as u32)?;
+    }
+
+    let bytes = Bytes::from(cursor.into_inner());
+    Ok(bytes)
+}
+
+/// Deserializes the sub-messages of an aggregate message.  The timestamp and message stream id
+/// of the aggregate message take precedence over the ones of its sub-messages, so the first
+/// sub-message is given the aggregate's timestamp and later sub-messages keep their offset
+/// from the first.
+///
+/// Aggregates nested inside of an aggregate are rejected, as sessions handle sub-messages the
+/// same way as top level messages and a peer could otherwise nest them deep enough to exhaust
+/// the stack.
+pub fn deserialize(
+    data: Bytes,
+    timestamp: RtmpTimestamp,
+    message_stream_id: u32,
+) -> Result<RtmpMessage, MessageDeserializationError> {
+    let mut cursor = Cursor::new(data);
+    let mut first_timestamp = None;
+    let mut messages = Vec::new();
+
+    while (cursor.position() as usize) < cursor.get_ref().len() {
+        let type_id = cursor.read_u8()?;
+        if type_id == AGGREGATE_TYPE_ID {
+            return Err(MessageDeserializationError::InvalidMessageFormat);
+        }
+
+        let length = cursor.read_u24::<BigEndian>()? as usize;
+        let lower_timestamp = cursor.read_u24::<BigEndian>()?;
+        let upper_timestamp = cursor.read_u8()? as u32;
+        let _stream_id = cursor.read_u24::<BigEndian>()?;
+
+        let start = cursor.position() as usize;
+        if start + length + BACK_POINTER_SIZE > cursor.get_ref().len() {
+            return Err(MessageDeserializationError::InvalidMessageFormat);
+        }
+
+        let sub_message_data = cursor.get_ref().slice(start..start + length);
+        cursor.set_position((start + length + BACK_POINTER_SIZE) as u64);
+
+        let sub_message_timestamp = RtmpTimestamp::new((upper_timestamp << 24) | lower_timestamp);
+        let first_timestamp = *first_timestamp.get_or_insert(sub_message_timestamp);
+
+        messages.push(MessagePayload::new(
+            type_id,
+            timestamp + (sub_message_timestamp - first_timestamp),
+            message_stream_id,
+            sub_message_data,
+        ));
+    }
+
+    Ok(RtmpMessage::Aggregate { messages })
+}
+
+pub fn approximate_byte_size(messages: &[MessagePayload]) -> usize {
+    messages
+        .iter()
+        .map(|message| SUB_MESSAGE_HEADER_SIZE + message.data.len() + BACK_POINTER_SIZE)
+        .sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{deserialize, serialize};
+    use byteorder::{BigEndian, WriteBytesExt};
+    use bytes::Bytes;
+    use std::io::{Cursor, Write};
+
+    use messages::{MessagePayload, RtmpMessage};
+    use time::RtmpTimestamp;
+
+    #[test]
+    fn can_serialize_message() {
+        let messages = vec![MessagePayload::new(
+            9,
+            RtmpTimestamp::new(0x01020304),
+            1,
+            Bytes::from(vec![1_u8, 2_u8]),
+        )];
+
+        let raw_message = serialize(messages, 0).unwrap();
+
+        let mut cursor = Cursor::new(Vec::new());
+        cursor.write_u8(9).unwrap();
+        cursor.write_u24::<BigEndian>(2).unwrap();
+        cursor.write_u24::<BigEndian>(0x020304).unwrap();
+        cursor.write_u8(0x01).unwrap();
+        cursor.write_u24::<BigEndian>(1).unwrap();
+        cursor.write_all(&[1_u8, 2_u8]).unwrap();
+        cursor.write_u32::<BigEndian>(13).unwrap();
+        let expected = cursor.into_inner();
+
+        assert_eq!(&raw_message[..], &expected[..]);
+    }
+
+    #[test]
+    fn sub_messages_are_offset_from_aggregate_timestamp() {
+        let messages = vec![
+            MessagePayload::new(8, RtmpTimestamp::new(1000), 0, Bytes::from(vec![1_u8])),
+            MessagePayload::new(9, RtmpTimestamp::new(1040), 0, Bytes::from(vec![2_u8])),
+        ];
+
+        let raw_message = serialize(messages, 0).unwrap();
+        let result = deserialize(raw_message, RtmpTimestamp::new(5000), 3).unwrap();
+
+        let expected = RtmpMessage::Aggregate {
+            messages: vec![
+                MessagePayload::new(8, RtmpTimestamp::new(5000), 3, Bytes::from(vec![1_u8])),
+                MessagePayload::new(9, RtmpTimestamp::new(5040), 3, Bytes::from(vec![2_u8])),
+            ],
+        };
+
+        assert_eq!(result, expected);
+    }
+
+    #[test]
+    fn error_when_sub_message_length_exceeds_message() {
+        let mut cursor = Cursor::new(Vec::new());
+        cursor.write_u8(9).unwrap();
+        cursor.write_u24::<BigEndian>(100).unwrap();
+        cursor.write_u32::<BigEndian>(0).unwrap();
+        cursor.write_u24::<BigEndian>(0).unwrap();
+        cursor.write_all(&[1_u8, 2_u8]).unwrap();
+
+        let result = deserialize(Bytes::from(cursor.into_inner()), RtmpTimestamp::new(0), 1);
+        assert!(result.is_err(), "Expected an error, got {:?}", result);
+    }
+
+    #[test]
+    fn error_when_sub_message_is_an_aggregate() {
+        let inner = serialize(
+            vec![MessagePayload::new(
+                9,
+                RtmpTimestamp::new(0),
+                1,
+                Bytes::from(vec![1_u8, 2_u8]),
+            )],
+            0,
+        )
+        .unwrap();
+
+        let messages = vec![MessagePayload::new(22, RtmpTimestamp::new(0), 1, inner)];
+        let raw_message = serialize(messages, 0).unwrap();
+
+        let result = deserialize(raw_message, RtmpTimestamp::new(0), 1);
+        assert!(result.is_err(), "Expected an error, got {:?}", result);
+    }
+}
@@ -1,8 +1,9 @@
 use bytes::Bytes;
 use rml_amf0;
-use rml_amf0::Amf0Value;
+use rml_amf0::{Amf0SerializationConfig, Amf0Value};
 use std::io::Cursor;
 
+use messages::amf3;
 use messages::RtmpMessage;
 use messages::{MessageDeserializationError, MessageSerializationError};
 
@@ -11,6 +12,7 @@ pub fn serialize(
     transaction_id: f64,
     command_object: Amf0Value,
     mut additional_arguments: Vec<Amf0Value>,
+    capacity: usize,
 ) -> Result<Bytes, MessageSerializationError> {
     let mut values = vec![
         Amf0Value::Utf8String(command_name),
@@ -19,14 +21,53 @@ pub fn serialize(
     ];
 
     values.append(&mut additional_arguments);
-    let bytes = rml_amf0::serialize(&values)?;
+    let mut bytes = Vec::with_capacity(capacity);
+    rml_amf0::serialize_into(&values, &mut bytes, &Amf0SerializationConfig::default())?;
 
     Ok(Bytes::from(bytes))
 }
 
 pub fn deserialize(data: Bytes) -> Result<RtmpMessage, MessageDeserializationError> {
     let mut cursor = Cursor::new(data);
-    let mut arguments = rml_amf0::deserialize(&mut cursor)?;
+    let arguments = rml_amf0::deserialize(&mut cursor)?;
+    from_arguments(arguments)
+}
+
+/// Deserializes a command sent with AMF3 encoding (type id 17).  These start with a format byte
+/// of 0 followed by AMF0 values, except that any value can be switched to AMF3 encoding with the
+/// AVM+ marker.  In practice the command name and transaction id are always AMF0, while the
+/// command object and arguments usually are AMF3.  AMF3 values are converted to AMF0 values, so
+/// the command is returned as a regular `Amf0Command`.
+pub fn deserialize_amf3(data: Bytes) -> Result<RtmpMessage, MessageDeserializationError> {
+    // Some clients leave out the format byte, so only skip it if it is present
+    let data = match data.first() {
+        Some(0x00) => data.slice(1..),
+        _ => data,
+    };
+
+    let mut cursor = Cursor::new(data);
+    let mut arguments = Vec::new();
+    let mut values_remaining = amf3::MAX_VALUES_PER_MESSAGE;
+    while (cursor.position() as usize) < cursor.get_ref().len() {
+        let value = if cursor.get_ref()[cursor.position() as usize] == amf3::AVMPLUS_OBJECT_MARKER {
+            cursor.set_position(cursor.position() + 1);
+            amf3::deserialize_value(&mut cursor, &mut values_remaining)?
+        } else {
+            rml_amf0::deserialize_one(&mut cursor)?
+        };
+
+        arguments.push(value);
+    }
+
+    from_arguments(arguments)
+}
+
+fn from_arguments(
+    mut arguments: Vec<Amf0Value>,
+) -> Result<RtmpMessage, MessageDeserializationError> {
+    if arguments.len() < 3 {
+        return Err(MessageDeserializationError::InvalidMessageFormat);
+    }
 
     let command_name: String;
     let transaction_id: f64;
@@ -95,6 +136,7 @@ mod tests {
             23.0,
             Amf0Value::Object(properties1),
             vec![Amf0Value::Boolean(true), Amf0Value::Number(52.0)],
+            0,
         )
         .unwrap();
 
@@ -1,13 +1,17 @@
 use bytes::Bytes;
 use rml_amf0;
-use rml_amf0::Amf0Value;
+use rml_amf0::{Amf0SerializationConfig, Amf0Value};
 use std::io::Cursor;
 
 use messages::RtmpMessage;
 use messages::{MessageDeserializationError, MessageSerializationError};
 
-pub fn serialize(values: Vec<Amf0Value>) -> Result<Bytes, MessageSerializationError> {
-    let bytes = rml_amf0::serialize(&values)?;
+pub fn serialize(
+    values: Vec<Amf0Value>,
+    capacity: usize,
+) -> Result<Bytes, MessageSerializationError> {
+    let mut bytes = Vec::with_capacity(capacity);
+    rml_amf0::serialize_into(&values, &mut bytes, &Amf0SerializationConfig::default())?;
 
     Ok(Bytes::from(bytes))
 }
@@ -32,7 +36,7 @@ mod tests {
     #[test]
     fn can_serialize_message() {
         let raw_message =
-            serialize(vec![Amf0Value::Boolean(true), Amf0Value::Number(52.0)]).unwrap();
+            serialize(vec![Amf0Value::Boolean(true), Amf0Value::Number(52.0)], 0).unwrap();
 
         let mut cursor = Cursor::new(raw_message);
         let result = rml_amf0::deserialize(&mut cursor).unwrap();
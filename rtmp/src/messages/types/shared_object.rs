@@ -0,0 +1,317 @@
+use byteorder::{BigEndian, ReadBytesExt, WriteBytesExt};
+use bytes::Bytes;
+use rml_amf0;
+use rml_amf0::Amf0SerializationError;
+use std::io::{Cursor, Read, Write};
+
+use messages::{MessageDeserializationError, MessageSerializationError};
+use messages::{RtmpMessage, SharedObjectEvent};
+
+const PERSISTENT_FLAG: u32 = 2;
+
+pub fn serialize(
+    name: String,
+    version: u32,
+    persistence: bool,
+    events: Vec<SharedObjectEvent>,
+    capacity: usize,
+) -> Result<Bytes, MessageSerializationError> {
+    let mut cursor = Cursor::new(Vec::with_capacity(capacity));
+    write_string(&mut cursor, &name)?;
+    cursor.write_u32::<BigEndian>(version)?;
+    cursor.write_u32::<BigEndian>(if persistence { PERSISTENT_FLAG } else { 0 })?;
+    cursor.write_u32::<BigEndian>(0)?; // reserved
+
+    for event in events {
+        let (event_type, data) = serialize_event(event)?;
+        cursor.write_u8(event_type)?;
+        cursor.write_u32::<BigEndian>(data.len() as u32)?;
+        cursor.write_all(&data)?;
+    }
+
+    let bytes = Bytes::from(cursor.into_inner());
+    Ok(bytes)
+}
+
+pub fn deserialize(data: Bytes) -> Result<RtmpMessage, MessageDeserializationError> {
+    let mut cursor = Cursor::new(data);
+    let name = read_string(&mut cursor)?;
+    let version = cursor.read_u32::<BigEndian>()?;
+    let persistence = cursor.read_u32::<BigEndian>()? != 0;
+    let _reserved = cursor.read_u32::<BigEndian>()?;
+
+    let mut events = Vec::new();
+    while (cursor.position() as usize) < cursor.get_ref().len() {
+        let event_type = cursor.read_u8()?;
+        let length = cursor.read_u32::<BigEndian>()? as usize;
+        let start = cursor.position() as usize;
+        if start + length > cursor.get_ref().len() {
+            return Err(MessageDeserializationError::InvalidMessageFormat);
+        }
+
+        let event_data = cursor.get_ref().slice(start..start + length);
+        cursor.set_position((start + length) as u64);
+        events.push(deserialize_event(event_type, event_data)?);
+    }
+
+    Ok(RtmpMessage::SharedObject {
+        name,
+        version,
+        persistence,
+        events,
+    })
+}
+
+pub fn approximate_event_size(event: &SharedObjectEvent) -> usize {
+    // event type + data length
+    let header_size = 5;
+    let data_size = match *event {
+        SharedObjectEvent::Use
+        | SharedObjectEvent::Release
+        | SharedObjectEvent::Clear
+        | SharedObjectEvent::UseSuccess => 0,
+
+        SharedObjectEvent::RequestChange { ref key, ref value }
+        | SharedObjectEvent::Change { ref key, ref value } => 2 + key.len() + value.byte_len(),
+
+        SharedObjectEvent::Success { ref key }
+        | SharedObjectEvent::Delete { ref key }
+        | SharedObjectEvent::RequestDelete { ref key } => 2 + key.len(),
+
+        SharedObjectEvent::SendMessage { ref values } => {
+            values.iter().map(|value| value.byte_len()).sum()
+        }
+
+        SharedObjectEvent::Status {
+            ref code,
+            ref level,
+        } => 2 + code.len() + 2 + level.len(),
+
+        SharedObjectEvent::Unknown { ref data, .. } => data.len(),
+    };
+
+    header_size + data_size
+}
+
+fn serialize_event(event: SharedObjectEvent) -> Result<(u8, Vec<u8>), MessageSerializationError> {
+    let mut cursor = Cursor::new(Vec::new());
+    let event_type = match event {
+        SharedObjectEvent::Use => 1,
+        SharedObjectEvent::Release => 2,
+        SharedObjectEvent::RequestChange { key, value } => {
+            write_string(&mut cursor, &key)?;
+            cursor.write_all(&rml_amf0::serialize(&vec![value])?)?;
+            3
+        }
+
+        SharedObjectEvent::Change { key, value } => {
+            write_string(&mut cursor, &key)?;
+            cursor.write_all(&rml_amf0::serialize(&vec![value])?)?;
+            4
+        }
+
+        SharedObjectEvent::Success { key } => {
+            write_string(&mut cursor, &key)?;
+            5
+        }
+
+        SharedObjectEvent::SendMessage { values } => {
+            cursor.write_all(&rml_amf0::serialize(&values)?)?;
+            6
+        }
+
+        SharedObjectEvent::Status { code, level } => {
+            write_string(&mut cursor, &code)?;
+            write_string(&mut cursor, &level)?;
+            7
+        }
+
+        SharedObjectEvent::Clear => 8,
+        SharedObjectEvent::Delete { key } => {
+            write_string(&mut cursor, &key)?;
+            9
+        }
+
+        SharedObjectEvent::RequestDelete { key } => {
+            write_string(&mut cursor, &key)?;
+            10
+        }
+
+        SharedObjectEvent::UseSuccess => 11,
+        SharedObjectEvent::Unknown { event_type, data } => {
+            cursor.write_all(&data)?;
+            event_type
+        }
+    };
+
+    Ok((event_type, cursor.into_inner()))
+}
+
+fn deserialize_event(
+    event_type: u8,
+    data: Bytes,
+) -> Result<SharedObjectEvent, MessageDeserializationError> {
+    let mut cursor = Cursor::new(data);
+    let event = match event_type {
+        1 => SharedObjectEvent::Use,
+        2 => SharedObjectEvent::Release,
+        3 => SharedObjectEvent::RequestChange {
+            key: read_string(&mut cursor)?,
+            value: rml_amf0::deserialize_one(&mut cursor)?,
+        },
+
+        4 => SharedObjectEvent::Change {
+            key: read_string(&mut cursor)?,
+            value: rml_amf0::deserialize_one(&mut cursor)?,
+        },
+
+        5 => SharedObjectEvent::Success {
+            key: read_string(&mut cursor)?,
+        },
+
+        6 => SharedObjectEvent::SendMessage {
+            values: rml_amf0::deserialize(&mut cursor)?,
+        },
+
+        7 => SharedObjectEvent::Status {
+            code: read_string(&mut cursor)?,
+            level: read_string(&mut cursor)?,
+        },
+
+        8 => SharedObjectEvent::Clear,
+        9 => SharedObjectEvent::Delete {
+            key: read_string(&mut cursor)?,
+        },
+
+        10 => SharedObjectEvent::RequestDelete {
+            key: read_string(&mut cursor)?,
+        },
+
+        11 => SharedObjectEvent::UseSuccess,
+        _ => SharedObjectEvent::Unknown {
+            event_type,
+            data: cursor.into_inner(),
+        },
+    };
+
+    Ok(event)
+}
+
+fn write_string<W: Write>(bytes: &mut W, value: &str) -> Result<(), MessageSerializationError> {
+    if value.len() > u16::MAX as usize {
+        return Err(Amf0SerializationError::NormalStringTooLong.into());
+    }
+
+    bytes.write_u16::<BigEndian>(value.len() as u16)?;
+    bytes.write_all(value.as_bytes())?;
+    Ok(())
+}
+
+fn read_string<R: Read>(bytes: &mut R) -> Result<String, MessageDeserializationError> {
+    let length = bytes.read_u16::<BigEndian>()?;
+    let mut buffer = vec![0; length as usize];
+    bytes.read_exact(&mut buffer)?;
+
+    match String::from_utf8(buffer) {
+        Ok(value) => Ok(value),
+        Err(_) => Err(MessageDeserializationError::InvalidMessageFormat),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{deserialize, serialize};
+    use byteorder::{BigEndian, WriteBytesExt};
+    use bytes::Bytes;
+    use rml_amf0::Amf0Value;
+    use std::io::{Cursor, Write};
+
+    use messages::{RtmpMessage, SharedObjectEvent};
+
+    #[test]
+    fn can_serialize_message() {
+        let events = vec![SharedObjectEvent::Use];
+        let raw_message = serialize("chat".to_string(), 3, true, events, 0).unwrap();
+
+        let mut cursor = Cursor::new(Vec::new());
+        cursor.write_u16::<BigEndian>(4).unwrap();
+        cursor.write_all(b"chat").unwrap();
+        cursor.write_u32::<BigEndian>(3).unwrap();
+        cursor.write_u32::<BigEndian>(2).unwrap();
+        cursor.write_u32::<BigEndian>(0).unwrap();
+        cursor.write_u8(1).unwrap();
+        cursor.write_u32::<BigEndian>(0).unwrap();
+        let expected = cursor.into_inner();
+
+        assert_eq!(&raw_message[..], &expected[..]);
+    }
+
+    #[test]
+    fn change_event_survives_round_trip() {
+        let events = vec![SharedObjectEvent::Change {
+            key: "topic".to_string(),
+            value: Amf0Value::Utf8String("hello".to_string()),
+        }];
+
+        let raw_message = serialize("chat".to_string(), 7, false, events.clone(), 0).unwrap();
+        let result = deserialize(raw_message).unwrap();
+
+        let expected = RtmpMessage::SharedObject {
+            name: "chat".to_string(),
+            version: 7,
+            persistence: false,
+            events,
+        };
+
+        assert_eq!(result, expected);
+    }
+
+    #[test]
+    fn unknown_events_are_passed_through() {
+        let mut cursor = Cursor::new(Vec::new());
+        cursor.write_u16::<BigEndian>(4).unwrap();
+        cursor.write_all(b"chat").unwrap();
+        cursor.write_u32::<BigEndian>(1).unwrap();
+        cursor.write_u32::<BigEndian>(0).unwrap();
+        cursor.write_u32::<BigEndian>(0).unwrap();
+        cursor.write_u8(42).unwrap();
+        cursor.write_u32::<BigEndian>(3).unwrap();
+        cursor.write_all(&[1, 2, 3]).unwrap();
+        cursor.write_u8(1).unwrap();
+        cursor.write_u32::<BigEndian>(0).unwrap();
+        let raw_message = cursor.into_inner();
+
+        let result = deserialize(Bytes::from(raw_message.clone())).unwrap();
+        let expected_events = vec![
+            SharedObjectEvent::Unknown {
+                event_type: 42,
+                data: Bytes::from(vec![1, 2, 3]),
+            },
+            SharedObjectEvent::Use,
+        ];
+
+        match result {
+            RtmpMessage::SharedObject { ref events, .. } => {
+                assert_eq!(events, &expected_events, "Unexpected events")
+            }
+            ref x => panic!("Expected shared object message, got {:?}", x),
+        }
+
+        let reserialized = serialize("chat".to_string(), 1, false, expected_events, 0).unwrap();
+        assert_eq!(&reserialized[..], &raw_message[..]);
+    }
+
+    #[test]
+    fn error_when_event_length_exceeds_message() {
+        let mut cursor = Cursor::new(Vec::new());
+        cursor.write_u16::<BigEndian>(0).unwrap();
+        cursor.write_u32::<BigEndian>(0).unwrap();
+        cursor.write_u32::<BigEndian>(0).unwrap();
+        cursor.write_u32::<BigEndian>(0).unwrap();
+        cursor.write_u8(5).unwrap();
+        cursor.write_u32::<BigEndian>(100).unwrap();
+
+        let result = deserialize(Bytes::from(cursor.into_inner()));
+        assert!(result.is_err(), "Expected an error, got {:?}", result);
+    }
+}
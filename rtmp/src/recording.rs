@@ -0,0 +1,107 @@
+/*!
+This module contains the types used to record streams that clients publish to a `ServerSession`
+into FLV files.  Recordings are started by calling `ServerSession::begin_recording()` on a stream
+that is being published to.
+*/
+
+use flv::FlvWriter;
+use std::fs::File;
+use std::io::{self, BufWriter};
+use std::path::Path;
+use std::sync::{Arc, Mutex, MutexGuard};
+
+struct RecordingState {
+    writer: Option<FlvWriter<BufWriter<File>>>,
+    error: Option<io::Error>,
+}
+
+/// A handle to an active recording of a published stream.
+///
+/// The recording ends when either `stop()` is called or the client stops publishing on the
+/// stream, whichever happens first.  Dropping the handle without calling `stop()` leaves the
+/// recording running until the client stops publishing.
+pub struct RecordingHandle {
+    stream_id: u32,
+    state: Arc<Mutex<RecordingState>>,
+}
+
+impl RecordingHandle {
+    /// The id of the stream being recorded
+    pub fn stream_id(&self) -> u32 {
+        self.stream_id
+    }
+
+    /// Stops the recording and flushes all written tags to the file.  If writing to the file
+    /// failed at any point while recording, that error is returned.
+    pub fn stop(self) -> io::Result<()> {
+        let mut state = lock(&self.state);
+        let writer = state.writer.take();
+        if let Some(error) = state.error.take() {
+            return Err(error);
+        }
+
+        match writer {
+            Some(mut writer) => writer.flush(),
+            None => Ok(()),
+        }
+    }
+}
+
+/// The session's side of a recording, which writes the stream's media into the file
+pub(crate) struct Recorder {
+    state: Arc<Mutex<RecordingState>>,
+}
+
+impl Recorder {
+    pub(crate) fn create(stream_id: u32, path: &Path) -> io::Result<(Recorder, RecordingHandle)> {
+        let writer = FlvWriter::new(BufWriter::new(File::create(path)?))?;
+        let state = Arc::new(Mutex::new(RecordingState {
+            writer: Some(writer),
+            error: None,
+        }));
+
+        let recorder = Recorder {
+            state: state.clone(),
+        };
+
+        let handle = RecordingHandle { stream_id, state };
+
+        Ok((recorder, handle))
+    }
+
+    /// Writes a tag to the recording, returning false if the recording is no longer active
+    pub(crate) fn write_tag(&self, type_id: u8, timestamp: u32, data: &[u8]) -> bool {
+        let mut state = lock(&self.state);
+        let result = match state.writer {
+            Some(ref mut writer) => writer.write_tag(type_id, timestamp, data),
+            None => return false,
+        };
+
+        match result {
+            Ok(()) => true,
+            Err(error) => {
+                // Keep the error around so it can be reported when the recording is stopped
+                state.writer = None;
+                state.error = Some(error);
+                false
+            }
+        }
+    }
+
+    /// Ends the recording, flushing everything that has been written so far
+    pub(crate) fn finish(self) {
+        let mut state = lock(&self.state);
+        if let Some(mut writer) = state.writer.take() {
+            if let Err(error) = writer.flush() {
+                state.error = Some(error);
+            }
+        }
+    }
+}
+
+fn lock<'a>(state: &'a Mutex<RecordingState>) -> MutexGuard<'a, RecordingState> {
+    // A poisoned lock only means a panic occurred while writing, the state itself is still valid
+    state
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+}
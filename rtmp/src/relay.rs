@@ -0,0 +1,278 @@
+/*!
+This module contains a relay that forwards a stream published to a `ServerSession` on to another
+RTMP server through a `ClientSession`.
+
+Destination servers expect the streams published to them to start at timestamp 0, but a source
+may have been running (or may use an arbitrary epoch) long before the relay was started.  The
+`TimestampNormalizingRelay` rebases the source's audio and video timestamps so the relayed
+stream starts at 0, and holds back sources that send faster than real time (such as a file being
+published without pacing) so the destination is not flooded.
+*/
+
+use bytes::Bytes;
+use chunk_io::{DropPolicy, Packet};
+use codec_utils::get_video_drop_policy;
+use sessions::{ClientSession, ClientSessionError, ClientSessionEvent, ClientSessionResult};
+use sessions::{ServerSession, ServerSessionError, ServerSessionEvent, ServerSessionResult};
+use std::time::{Duration, Instant};
+use thiserror::Error;
+use time::{RtmpTimestamp, TimestampRebaser};
+
+/// How much faster than real time a source may send media before the relay delays it
+pub const DEFAULT_MAX_SPEED: f64 = 1.5;
+
+/// Errors that can occur while relaying
+#[derive(Debug, Error)]
+pub enum RelayError {
+    /// The session receiving the stream from the source failed
+    #[error("The source session encountered an error: {0}")]
+    SourceSessionError(#[from] ServerSessionError),
+
+    /// The session publishing the stream to the destination failed
+    #[error("The destination session encountered an error: {0}")]
+    DestinationSessionError(#[from] ClientSessionError),
+}
+
+/// A single result of the relay processing bytes from the source or destination
+#[derive(Debug)]
+pub enum RelayResult {
+    /// A packet that needs to be sent to the source
+    SourcePacket(Packet),
+
+    /// A packet that needs to be sent to the destination once `send_at` has been reached.
+    /// Destination packets must be sent in the order they are returned, so a delayed packet
+    /// delays every packet after it as well.
+    DestinationPacket {
+        /// The packet to send
+        packet: Packet,
+
+        /// The earliest time the packet should be sent.  This is only in the future when the
+        /// source is sending media faster than the relay's maximum speed.
+        send_at: Instant,
+    },
+
+    /// An event raised by the source session that the relay does not handle itself, such as
+    /// connection and publish requests.  These should be handled through `source_session()`.
+    SourceEvent(ServerSessionEvent),
+
+    /// An event raised by the destination session, such as the publish request being accepted
+    DestinationEvent(ClientSessionEvent),
+}
+
+/// Relays the audio, video, and metadata published to a server session on to a client session
+/// that is publishing to another server, with timestamps rebased so the relayed stream starts
+/// at 0.
+///
+/// The relay does not perform any networking itself.  Bytes received from the source and the
+/// destination are passed to `handle_source_input()` and `handle_destination_input()`, and the
+/// resulting packets must be sent on by the caller.  Both sessions are owned by the relay, so
+/// requests raised by the source and the initial connection and publish requests of the
+/// destination are made through `source_session()` and `destination_session()`.
+///
+/// Media received before the destination session is publishing is dropped, and the first media
+/// forwarded after that becomes timestamp 0.
+pub struct TimestampNormalizingRelay {
+    source: ServerSession,
+    destination: ClientSession,
+    destination_publishing: bool,
+    rebaser: Option<TimestampRebaser>,
+    started_at: Option<Instant>,
+    last_send_at: Option<Instant>,
+    max_speed: Option<f64>,
+}
+
+impl TimestampNormalizingRelay {
+    /// Creates a relay from the session the source is publishing to and the session that will
+    /// publish to the destination.
+    pub fn new(source: ServerSession, destination: ClientSession) -> TimestampNormalizingRelay {
+        TimestampNormalizingRelay {
+            source,
+            destination,
+            destination_publishing: false,
+            rebaser: None,
+            started_at: None,
+            last_send_at: None,
+            max_speed: Some(DEFAULT_MAX_SPEED),
+        }
+    }
+
+    /// Sets how many times faster than real time the source may send media before it is delayed.
+    /// `None` disables throttling, so all media is sent as soon as it's received.  Speeds that
+    /// are zero, negative, or not finite can't be throttled to and are treated as `None`.
+    pub fn set_max_speed(&mut self, max_speed: Option<f64>) {
+        self.max_speed = max_speed.filter(|speed| speed.is_finite() && *speed > 0.0);
+    }
+
+    /// The session receiving the stream from the source
+    pub fn source_session(&mut self) -> &mut ServerSession {
+        &mut self.source
+    }
+
+    /// The session publishing the stream to the destination
+    pub fn destination_session(&mut self) -> &mut ClientSession {
+        &mut self.destination
+    }
+
+    /// Processes bytes received from the source, forwarding any media they contain to the
+    /// destination.  `now` is the time the bytes were received, and is used to throttle sources
+    /// that send faster than real time.
+    pub fn handle_source_input(
+        &mut self,
+        bytes: &[u8],
+        now: Instant,
+    ) -> Result<Vec<RelayResult>, RelayError> {
+        let mut results = Vec::new();
+        for result in self.source.handle_input(bytes)? {
+            match result {
+                ServerSessionResult::OutboundResponse(packet) => {
+                    results.push(RelayResult::SourcePacket(packet))
+                }
+
+                ServerSessionResult::RaisedEvent(event) => {
+                    if let Some(result) = self.relay_event(event, now)? {
+                        results.push(result);
+                    }
+                }
+
+                ServerSessionResult::UnhandleableMessageReceived(_) => (),
+            }
+        }
+
+        Ok(results)
+    }
+
+    /// Processes bytes received from the destination
+    pub fn handle_destination_input(
+        &mut self,
+        bytes: &[u8],
+        now: Instant,
+    ) -> Result<Vec<RelayResult>, RelayError> {
+        let mut results = Vec::new();
+        for result in self.destination.handle_input(bytes)? {
+            match result {
+                ClientSessionResult::OutboundResponse(packet) => {
+                    results.push(self.destination_packet(packet, now))
+                }
+
+                ClientSessionResult::RaisedEvent(event) => {
+                    if let ClientSessionEvent::PublishRequestAccepted = event {
+                        self.destination_publishing = true;
+                    }
+
+                    results.push(RelayResult::DestinationEvent(event))
+                }
+
+                ClientSessionResult::UnhandleableMessageReceived(_) => (),
+            }
+        }
+
+        Ok(results)
+    }
+
+    fn relay_event(
+        &mut self,
+        event: ServerSessionEvent,
+        now: Instant,
+    ) -> Result<Option<RelayResult>, RelayError> {
+        let is_publishing = self.destination_publishing;
+        let result = match event {
+            ServerSessionEvent::VideoDataReceived {
+                data, timestamp, ..
+            } => {
+                if !is_publishing {
+                    return Ok(None);
+                }
+
+                let drop_policy = get_video_drop_policy(&data);
+                let timestamp = self.normalize(timestamp, now);
+                let result = self
+                    .destination
+                    .publish_video_data(data, timestamp, drop_policy)?;
+
+                self.throttled_result(result, timestamp, now)
+            }
+
+            ServerSessionEvent::AudioDataReceived {
+                data, timestamp, ..
+            } => {
+                if !is_publishing {
+                    return Ok(None);
+                }
+
+                let drop_policy = get_audio_drop_policy(&data);
+                let timestamp = self.normalize(timestamp, now);
+                let result = self
+                    .destination
+                    .publish_audio_data(data, timestamp, drop_policy)?;
+
+                self.throttled_result(result, timestamp, now)
+            }
+
+            ServerSessionEvent::StreamMetadataChanged { metadata, .. } => {
+                if !is_publishing {
+                    return Ok(None);
+                }
+
+                let result = self.destination.publish_metadata(&metadata)?;
+                self.throttled_result(result, RtmpTimestamp::new(0), now)
+            }
+
+            event => Some(RelayResult::SourceEvent(event)),
+        };
+
+        Ok(result)
+    }
+
+    fn normalize(&mut self, timestamp: RtmpTimestamp, now: Instant) -> RtmpTimestamp {
+        if self.rebaser.is_none() {
+            self.rebaser = Some(TimestampRebaser::new(timestamp));
+            self.started_at = Some(now);
+        }
+
+        self.rebaser.unwrap().rebase(timestamp)
+    }
+
+    fn throttled_result(
+        &mut self,
+        result: ClientSessionResult,
+        timestamp: RtmpTimestamp,
+        now: Instant,
+    ) -> Option<RelayResult> {
+        let packet = match result {
+            ClientSessionResult::OutboundResponse(packet) => packet,
+            _ => return None,
+        };
+
+        // Media may not be sent before the stream's position at the maximum speed allows it
+        let mut send_at = now;
+        if let (Some(max_speed), Some(started_at)) = (self.max_speed, self.started_at) {
+            let stream_position = Duration::from_millis(timestamp.value as u64);
+            let earliest = started_at + stream_position.div_f64(max_speed);
+            if earliest > send_at {
+                send_at = earliest;
+            }
+        }
+
+        Some(self.destination_packet(packet, send_at))
+    }
+
+    fn destination_packet(&mut self, packet: Packet, send_at: Instant) -> RelayResult {
+        // Packets have to be sent in order, so none can be sent before an earlier delayed one
+        let send_at = match self.last_send_at {
+            Some(last_send_at) if last_send_at > send_at => last_send_at,
+            _ => send_at,
+        };
+
+        self.last_send_at = Some(send_at);
+        RelayResult::DestinationPacket { packet, send_at }
+    }
+}
+
+fn get_audio_drop_policy(data: &Bytes) -> DropPolicy {
+    // The AAC sequence header is required to decode any of the audio after it
+    if data.len() >= 2 && data[0] >> 4 == 10 && data[1] == 0 {
+        DropPolicy::MustDeliver
+    } else {
+        DropPolicy::DropUnderLoad
+    }
+}
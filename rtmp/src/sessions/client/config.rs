@@ -1,11 +1,26 @@
+use super::ClientSessionSubscriber;
+use std::sync::Arc;
+
 /// Configuration options that govern how a RTMP client session should operate
 #[derive(Clone)]
 pub struct ClientSessionConfig {
+    /// The version string sent in the `flashVer` property of the connect request
     pub flash_version: String,
+
+    /// The buffer length, in milliseconds, the server is told the client uses during playback
     pub playback_buffer_length_ms: u32,
+
+    /// The number of bytes the server should send before waiting for an acknowledgement
     pub window_ack_size: u32,
+
+    /// The maximum size of the RTMP chunks sent to the server
     pub chunk_size: u32,
+
+    /// The url sent in the `tcUrl` property of the connect request, if any
     pub tc_url: Option<String>,
+
+    /// An optional subscriber that is notified of lifecycle changes to the session
+    pub subscriber: Option<Arc<dyn ClientSessionSubscriber>>,
 }
 
 impl ClientSessionConfig {
@@ -17,6 +32,7 @@ impl ClientSessionConfig {
             window_ack_size: 2_500_000,
             chunk_size: 4096,
             tc_url: None,
+            subscriber: None,
         }
     }
 }
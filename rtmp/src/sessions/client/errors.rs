@@ -39,7 +39,10 @@ pub enum ClientSessionError {
     #[error(
         "The request could not be performed while the session is in the {current_state:?} state"
     )]
-    SessionInInvalidState { current_state: ClientState },
+    SessionInInvalidState {
+        /// The state the session was in when the request was made
+        current_state: ClientState,
+    },
 
     /// Encountered when attempting to send a message that requires having an active stream
     /// opened but none is marked down.  This is almost always a bug with the `ClientSession` as
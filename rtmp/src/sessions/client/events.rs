@@ -1,4 +1,5 @@
 use bytes::Bytes;
+use messages::PeerBandwidthLimitType;
 use rml_amf0::Amf0Value;
 use sessions::StreamMetadata;
 use time::RtmpTimestamp;
@@ -11,51 +12,137 @@ pub enum ClientSessionEvent {
     ConnectionRequestAccepted,
 
     /// The server has rejected the connection request
-    ConnectionRequestRejected { description: String },
+    ConnectionRequestRejected {
+        /// The reason the server gave for the rejection
+        description: String,
+    },
 
     /// The server has accepted our request to play video back from a stream key
     PlaybackRequestAccepted,
 
+    /// The server could not find the stream key we requested playback of.  The session is still
+    /// considered to be playing, so `stop_playback()` should be called before requesting
+    /// playback again.
+    PlaybackStreamNotFound {
+        /// The description the server gave for the failure
+        description: String,
+    },
+
+    /// The server reported that the connection is unable to sustain playback of the stream
+    PlaybackBadConnection {
+        /// The description the server gave for the failure
+        description: String,
+    },
+
+    /// The server reported an error with playback of the stream, other than the stream not
+    /// being found or a bad connection
+    PlaybackFailed {
+        /// The `code` property of the status, such as `NetStream.Play.Failed`
+        code: String,
+
+        /// The description the server gave for the failure
+        description: String,
+    },
+
     /// The server has accepted our request to publish video
     PublishRequestAccepted,
 
     /// The server has sent over new metadata for the stream
-    StreamMetadataReceived { metadata: StreamMetadata },
+    StreamMetadataReceived {
+        /// The metadata the server sent
+        metadata: StreamMetadata,
+    },
 
     /// The server has sent over video data for the stream
     VideoDataReceived {
+        /// The timestamp of the video data
         timestamp: RtmpTimestamp,
+
+        /// The video data, in the same format as the body of an FLV video tag
         data: Bytes,
     },
 
     /// The server has sent over audio data for the stream
     AudioDataReceived {
+        /// The timestamp of the audio data
         timestamp: RtmpTimestamp,
+
+        /// The audio data, in the same format as the body of an FLV audio tag
         data: Bytes,
     },
 
     /// The server sent an Amf0 command that was not able to be handled
     UnhandleableAmf0Command {
+        /// The name of the command
         command_name: String,
+
+        /// The transaction id of the command
         transaction_id: f64,
+
+        /// The command object sent with the command
         command_object: Amf0Value,
+
+        /// Any values that followed the command object
         additional_values: Vec<Amf0Value>,
     },
 
     /// The server sent us a result to a transaction that we don't know about
     UnknownTransactionResultReceived {
+        /// The transaction id of the result
         transaction_id: f64,
+
+        /// The command object sent with the result
         command_object: Amf0Value,
+
+        /// Any values that followed the command object
         additional_values: Vec<Amf0Value>,
     },
 
     /// The server sent an `onStatus` message with a `code` property that we don't know
     /// how to handle.
-    UnhandleableOnStatusCode { code: String },
+    UnhandleableOnStatusCode {
+        /// The `code` property of the status
+        code: String,
+    },
+
+    /// The server has sent an acknowledgement that it has received the specified number of bytes
+    AcknowledgementReceived {
+        /// The total number of bytes the server has received
+        bytes_received: u32,
+    },
+
+    /// The server has set a limit on how much bandwidth the client should use, so the client
+    /// can adjust the bitrate it sends
+    BandwidthLimitReceived {
+        /// The limit, in bytes
+        size: u32,
+
+        /// How the limit should be applied
+        limit_type: PeerBandwidthLimitType,
+    },
 
-    /// The client has sent an acknowledgement that they have received the specified number of bytes
-    AcknowledgementReceived { bytes_received: u32 },
+    /// The server has responded to a ping request
+    PingResponseReceived {
+        /// The timestamp that was sent in the ping request
+        timestamp: RtmpTimestamp,
+    },
+
+    /// The server has signaled that the stream being played has become functional
+    StreamBegin {
+        /// The id of the stream that began
+        stream_id: u32,
+    },
 
-    /// The client has responded to a ping request
-    PingResponseReceived { timestamp: RtmpTimestamp },
+    /// The server has signaled that playback of the stream is over
+    StreamEof {
+        /// The id of the stream that ended
+        stream_id: u32,
+    },
+
+    /// The server has signaled that there is currently no more data on the stream, such as
+    /// when a live source has paused
+    StreamDry {
+        /// The id of the stream that has gone dry
+        stream_id: u32,
+    },
 }
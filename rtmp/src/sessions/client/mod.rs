@@ -5,6 +5,7 @@ mod outstanding_transaction;
 mod publish_request_type;
 mod result;
 mod state;
+mod subscriber;
 
 #[cfg(test)]
 mod tests;
@@ -15,16 +16,17 @@ pub use self::events::ClientSessionEvent;
 pub use self::publish_request_type::PublishRequestType;
 pub use self::result::ClientSessionResult;
 pub use self::state::ClientState;
+pub use self::subscriber::ClientSessionSubscriber;
 
 use self::outstanding_transaction::{OutstandingTransaction, TransactionPurpose};
 use bytes::Bytes;
-use chunk_io::{ChunkDeserializer, ChunkSerializer, Packet};
-use messages::{RtmpMessage, UserControlEventType};
+use chunk_io::{ChunkDeserializer, ChunkSerializer, DropPolicy, Packet};
+use messages::{MessagePayload, PeerBandwidthLimitType, RtmpMessage, UserControlEventType};
 use rml_amf0::Amf0Value;
-use sessions::StreamMetadata;
+use sessions::{PlayStartValue, StreamMetadata};
 use std::collections::HashMap;
 use std::mem;
-use std::time::SystemTime;
+use std::time::{Duration, Instant, SystemTime};
 use time::RtmpTimestamp;
 
 type ClientResult = Result<Vec<ClientSessionResult>, ClientSessionError>;
@@ -51,6 +53,8 @@ type ClientResult = Result<Vec<ClientSessionResult>, ClientSessionError>;
 /// by either the `ClientSession` or the peer.
 pub struct ClientSession {
     start_time: SystemTime,
+    connection_start_time: Instant,
+    connected_at: Option<Instant>,
     serializer: ChunkSerializer,
     deserializer: ChunkDeserializer,
     config: ClientSessionConfig,
@@ -59,31 +63,42 @@ pub struct ClientSession {
     current_state: ClientState,
     connected_app_name: Option<String>,
     active_stream_id: Option<u32>,
+    publishing_stream_key: Option<String>,
     peer_window_ack_size: Option<u32>,
+    peer_bandwidth_limit: Option<u32>,
     bytes_received: u64,
     bytes_received_since_last_ack: u32,
+    keepalive_interval: Option<Duration>,
+    last_outbound_at: Instant,
 }
 
 impl ClientSession {
     /// Creates a new client session with the specified configuration
     ///
-    /// As part of the initial creation it automatically creates initial outbound RTMP messages,
-    /// such as setting the outbound chunk size
+    /// No messages currently need to be sent before the connection is requested, so the returned
+    /// results are empty.  The configured outbound chunk size is sent to the server once the
+    /// connection request has been accepted.
     pub fn new(
         config: ClientSessionConfig,
     ) -> Result<(ClientSession, Vec<ClientSessionResult>), ClientSessionError> {
         let session = ClientSession {
             start_time: SystemTime::now(),
+            connection_start_time: Instant::now(),
+            connected_at: None,
             serializer: ChunkSerializer::new(),
             deserializer: ChunkDeserializer::new(),
             next_transaction_id: 1,
             outstanding_transactions: HashMap::new(),
             current_state: ClientState::Disconnected,
             active_stream_id: None,
+            publishing_stream_key: None,
             connected_app_name: None,
             peer_window_ack_size: None,
+            peer_bandwidth_limit: None,
             bytes_received: 0,
             bytes_received_since_last_ack: 0,
+            keepalive_interval: None,
+            last_outbound_at: Instant::now(),
             config,
         };
 
@@ -105,7 +120,8 @@ impl ClientSession {
                     sequence_number: self.bytes_received_since_last_ack,
                 };
                 let ack_payload = ack_message.into_message_payload(self.get_epoch(), 0)?;
-                let ack_packet = self.serializer.serialize(&ack_payload, false, false)?;
+                let ack_packet =
+                    self.serialize_payload(&ack_payload, false, DropPolicy::MustDeliver)?;
 
                 self.bytes_received_since_last_ack = 0;
                 results.push(ClientSessionResult::OutboundResponse(ack_packet));
@@ -117,61 +133,7 @@ impl ClientSession {
             match self.deserializer.get_next_message(bytes_to_process)? {
                 None => break, // no more messages
                 Some(payload) => {
-                    let message = payload.to_rtmp_message()?;
-                    let mut message_results = match message {
-                        RtmpMessage::Acknowledgement { sequence_number } => {
-                            self.handle_acknowledgement(sequence_number)?
-                        }
-
-                        RtmpMessage::Amf0Command {
-                            command_name,
-                            transaction_id,
-                            command_object,
-                            additional_arguments,
-                        } => self.handle_amf0_command(
-                            command_name,
-                            transaction_id,
-                            command_object,
-                            additional_arguments,
-                        )?,
-
-                        RtmpMessage::Amf0Data { values } => {
-                            self.handle_amf0_data(values, payload.message_stream_id)?
-                        }
-
-                        RtmpMessage::AudioData { data } => self.handle_audio_data(
-                            payload.message_stream_id,
-                            data,
-                            payload.timestamp,
-                        )?,
-
-                        RtmpMessage::VideoData { data } => self.handle_video_data(
-                            payload.message_stream_id,
-                            data,
-                            payload.timestamp,
-                        )?,
-
-                        RtmpMessage::UserControl {
-                            event_type,
-                            timestamp,
-                            stream_id,
-                            buffer_length,
-                        } => self.handle_user_control(
-                            event_type,
-                            timestamp,
-                            stream_id,
-                            buffer_length,
-                        )?,
-
-                        RtmpMessage::WindowAcknowledgement { size } => {
-                            self.handle_window_ack_size(size)?
-                        }
-
-                        RtmpMessage::SetChunkSize { size } => self.handle_set_chunk_size(size)?,
-
-                        _ => vec![ClientSessionResult::UnhandleableMessageReceived(payload)],
-                    };
-
+                    let mut message_results = self.handle_payload(payload)?;
                     results.append(&mut message_results);
                     bytes_to_process = &[];
                 }
@@ -181,12 +143,13 @@ impl ClientSession {
         Ok(results)
     }
 
-    /// Forms an RTMP message requesting a connection to the specified application on the server.
-    /// An event will be raised when the request is accepted or rejected.
+    /// Forms the RTMP messages requesting a connection to the specified application on the
+    /// server.  All returned results must be handled in order.  An event will be raised when the
+    /// request is accepted or rejected.
     pub fn request_connection(
         &mut self,
         app_name: String,
-    ) -> Result<ClientSessionResult, ClientSessionError> {
+    ) -> Result<Vec<ClientSessionResult>, ClientSessionError> {
         match self.current_state {
             ClientState::Disconnected => (),
             _ => {
@@ -225,9 +188,9 @@ impl ClientSession {
         };
 
         let payload = message.into_message_payload(self.get_epoch(), 0)?;
-        let packet = self.serializer.serialize(&payload, false, false)?;
+        let packet = self.serialize_payload(&payload, false, DropPolicy::MustDeliver)?;
 
-        Ok(ClientSessionResult::OutboundResponse(packet))
+        Ok(vec![ClientSessionResult::OutboundResponse(packet)])
     }
 
     /// Starts the process of requesting playback on the server for the specified stream key.  An
@@ -236,6 +199,24 @@ impl ClientSession {
     pub fn request_playback(
         &mut self,
         stream_key: String,
+    ) -> Result<ClientSessionResult, ClientSessionError> {
+        self.create_play_request(stream_key, None)
+    }
+
+    /// Starts the process of requesting playback of the specified stream key, starting at
+    /// the specified position.  An event will be raised when the request is accepted or rejected.
+    pub fn request_playback_from(
+        &mut self,
+        stream_key: String,
+        start_at: PlayStartValue,
+    ) -> Result<ClientSessionResult, ClientSessionError> {
+        self.create_play_request(stream_key, Some(start_at))
+    }
+
+    fn create_play_request(
+        &mut self,
+        stream_key: String,
+        start_at: Option<PlayStartValue>,
     ) -> Result<ClientSessionResult, ClientSessionError> {
         match self.current_state {
             ClientState::Connected => (),
@@ -248,7 +229,10 @@ impl ClientSession {
 
         let transaction_id = self.get_next_transaction_id();
         let transaction = OutstandingTransaction::CreateStream {
-            purpose: TransactionPurpose::PlayRequest { stream_key },
+            purpose: TransactionPurpose::PlayRequest {
+                stream_key,
+                start_at,
+            },
         };
 
         self.outstanding_transactions
@@ -262,7 +246,53 @@ impl ClientSession {
         };
 
         let payload = message.into_message_payload(self.get_epoch(), 0)?;
-        let packet = self.serializer.serialize(&payload, false, false)?;
+        let packet = self.serialize_payload(&payload, false, DropPolicy::MustDeliver)?;
+
+        Ok(ClientSessionResult::OutboundResponse(packet))
+    }
+
+    /// Adds the specified stream key to the end of the current playlist.  This sends another `play`
+    /// command on the existing play stream with a `reset` flag of `false`, so servers that support
+    /// playlists will play it after the currently playing stream instead of replacing it.
+    ///
+    /// This can only be called once a playback request has been made.
+    pub fn append_playback(
+        &mut self,
+        stream_key: String,
+    ) -> Result<ClientSessionResult, ClientSessionError> {
+        match self.current_state {
+            ClientState::PlayRequested => (),
+            ClientState::Playing => (),
+            _ => {
+                return Err(ClientSessionError::SessionInInvalidState {
+                    current_state: self.current_state.clone(),
+                });
+            }
+        }
+
+        let stream_id = match self.active_stream_id {
+            Some(stream_id) => stream_id,
+            None => {
+                return Err(ClientSessionError::SessionInInvalidState {
+                    current_state: self.current_state.clone(),
+                });
+            }
+        };
+
+        let message = RtmpMessage::Amf0Command {
+            command_name: "play".to_string(),
+            transaction_id: 0.0,
+            command_object: Amf0Value::Null,
+            additional_arguments: vec![
+                Amf0Value::Utf8String(stream_key),
+                Amf0Value::Number(-2.0),   // start: live or recorded
+                Amf0Value::Number(-1.0),   // duration: play until the end
+                Amf0Value::Boolean(false), // reset: don't clear the existing playlist
+            ],
+        };
+
+        let payload = message.into_message_payload(self.get_epoch(), stream_id)?;
+        let packet = self.serialize_payload(&payload, false, DropPolicy::MustDeliver)?;
 
         Ok(ClientSessionResult::OutboundResponse(packet))
     }
@@ -302,7 +332,7 @@ impl ClientSession {
         };
 
         let payload = message.into_message_payload(self.get_epoch(), 0)?;
-        let packet = self.serializer.serialize(&payload, false, false)?;
+        let packet = self.serialize_payload(&payload, false, DropPolicy::MustDeliver)?;
 
         Ok(ClientSessionResult::OutboundResponse(packet))
     }
@@ -329,7 +359,7 @@ impl ClientSession {
                 };
 
                 let payload = message.into_message_payload(self.get_epoch(), stream_id)?;
-                let packet = self.serializer.serialize(&payload, false, false)?;
+                let packet = self.serialize_payload(&payload, false, DropPolicy::MustDeliver)?;
                 Ok(vec![ClientSessionResult::OutboundResponse(packet)])
             }
         }
@@ -345,7 +375,14 @@ impl ClientSession {
             _ => return Ok(Vec::new()), // Nothing to stop since we aren't performing playback
         }
 
+        if let ClientState::Publishing = self.current_state {
+            if let Some(ref subscriber) = self.config.subscriber {
+                subscriber.on_publish_stopped();
+            }
+        }
+
         self.current_state = ClientState::Connected;
+        self.publishing_stream_key = None;
         match mem::replace(&mut self.active_stream_id, None) {
             None => Ok(Vec::new()), // Should never happen since we should always have a valid stream id
             Some(stream_id) => {
@@ -357,12 +394,54 @@ impl ClientSession {
                 };
 
                 let payload = message.into_message_payload(self.get_epoch(), stream_id)?;
-                let packet = self.serializer.serialize(&payload, false, false)?;
+                let packet = self.serialize_payload(&payload, false, DropPolicy::MustDeliver)?;
                 Ok(vec![ClientSessionResult::OutboundResponse(packet)])
             }
         }
     }
 
+    /// Notifies the session that the underlying connection to the server has been closed, so any
+    /// subscriber can be informed.  The session is placed back into the disconnected state and
+    /// should not be used for any further communication.
+    pub fn notify_disconnected(&mut self, reason: Option<&str>) {
+        if let ClientState::Publishing = self.current_state {
+            if let Some(ref subscriber) = self.config.subscriber {
+                subscriber.on_publish_stopped();
+            }
+        }
+
+        self.current_state = ClientState::Disconnected;
+        self.active_stream_id = None;
+        self.publishing_stream_key = None;
+
+        if let Some(ref subscriber) = self.config.subscriber {
+            subscriber.on_disconnected(reason);
+        }
+    }
+
+    /// Returns when the session was created, which is when the connection to the server started
+    pub fn connection_start_time(&self) -> Instant {
+        self.connection_start_time
+    }
+
+    /// Returns when the server accepted the connection request, or `None` if it has not been
+    /// accepted yet
+    pub fn connected_at(&self) -> Option<Instant> {
+        self.connected_at
+    }
+
+    /// Returns how long it has been since the server accepted the connection request, or `None`
+    /// if it has not been accepted yet
+    pub fn connected_duration(&self) -> Option<Duration> {
+        self.connected_at.map(|time| time.elapsed())
+    }
+
+    /// Returns the most recent bandwidth limit, in bytes, that the server sent with a
+    /// `SetPeerBandwidth` message, or `None` if it has not sent one
+    pub fn peer_bandwidth_limit(&self) -> Option<u32> {
+        self.peer_bandwidth_limit
+    }
+
     /// Sends a ping request to the server.  An event will be raised when we get a response back
     pub fn send_ping_request(&mut self) -> Result<(Packet, RtmpTimestamp), ClientSessionError> {
         let current_epoch = self.get_epoch();
@@ -374,10 +453,39 @@ impl ClientSession {
         };
 
         let payload = message.into_message_payload(self.get_epoch(), 0)?;
-        let packet = self.serializer.serialize(&payload, false, false)?;
+        let packet = self.serialize_payload(&payload, false, DropPolicy::MustDeliver)?;
         Ok((packet, current_epoch))
     }
 
+    /// Enables sending ping requests to the server when nothing has been sent to it for the
+    /// specified number of milliseconds.  This keeps otherwise idle connections, such as a player
+    /// waiting for a stream to start, from being silently dropped by NAT routers.  Pings are only
+    /// sent when `check_keepalive()` is called.
+    pub fn enable_keepalive(&mut self, interval_ms: u32) {
+        self.keepalive_interval = Some(Duration::from_millis(interval_ms as u64));
+    }
+
+    /// Checks if a keep-alive ping request should be sent to the server, which is the case when
+    /// keep-alives are enabled and nothing has been sent to the server for at least the keep-alive
+    /// interval.  This should be called periodically from the application's main loop.
+    pub fn check_keepalive(
+        &mut self,
+        now: Instant,
+    ) -> Result<Option<ClientSessionResult>, ClientSessionError> {
+        let interval = match self.keepalive_interval {
+            Some(interval) => interval,
+            None => return Ok(None),
+        };
+
+        if now.saturating_duration_since(self.last_outbound_at) < interval {
+            return Ok(None);
+        }
+
+        let (packet, _) = self.send_ping_request()?;
+        self.last_outbound_at = now;
+        Ok(Some(ClientSessionResult::OutboundResponse(packet)))
+    }
+
     /// If publishing, this allows us to send encoder metadata to the server to send to all
     /// players.
     pub fn publish_metadata(
@@ -411,6 +519,8 @@ impl ClientSession {
 
         if let Some(x) = metadata.video_codec_id {
             properties.insert("videocodecid".to_string(), Amf0Value::Number(x as f64));
+        } else if let Some(ref x) = metadata.video_codec_name {
+            properties.insert("videocodecid".to_string(), Amf0Value::Utf8String(x.clone()));
         }
 
         if let Some(x) = metadata.video_frame_rate {
@@ -423,6 +533,8 @@ impl ClientSession {
 
         if let Some(x) = metadata.audio_codec_id {
             properties.insert("audiocodecid".to_string(), Amf0Value::Number(x as f64));
+        } else if let Some(ref x) = metadata.audio_codec_name {
+            properties.insert("audiocodecid".to_string(), Amf0Value::Utf8String(x.clone()));
         }
 
         if let Some(x) = metadata.audio_bitrate_kbps {
@@ -433,6 +545,10 @@ impl ClientSession {
             properties.insert("audiosamplerate".to_string(), Amf0Value::Number(x as f64));
         }
 
+        if let Some(x) = metadata.audio_sample_size {
+            properties.insert("audiosamplesize".to_string(), Amf0Value::Number(x as f64));
+        }
+
         if let Some(x) = metadata.audio_channels {
             properties.insert("audiochannels".to_string(), Amf0Value::Number(x as f64));
         }
@@ -445,6 +561,28 @@ impl ClientSession {
             properties.insert("encoder".to_string(), Amf0Value::Utf8String(x.clone()));
         }
 
+        if let Some(x) = metadata.color_depth {
+            properties.insert("colorDepth".to_string(), Amf0Value::Number(x as f64));
+        }
+
+        if let Some(x) = metadata.color_matrix_coefficients {
+            properties.insert(
+                "colorMatrixCoefficients".to_string(),
+                Amf0Value::Number(x as f64),
+            );
+        }
+
+        if let Some(x) = metadata.color_transfer_characteristics {
+            properties.insert(
+                "colorTransferCharacteristics".to_string(),
+                Amf0Value::Number(x as f64),
+            );
+        }
+
+        if let Some(x) = metadata.color_primaries {
+            properties.insert("colorPrimaries".to_string(), Amf0Value::Number(x as f64));
+        }
+
         let message = RtmpMessage::Amf0Data {
             values: vec![
                 Amf0Value::Utf8String("@setDataFrame".to_string()),
@@ -454,7 +592,7 @@ impl ClientSession {
         };
 
         let payload = message.into_message_payload(self.get_epoch(), active_stream_id)?;
-        let packet = self.serializer.serialize(&payload, false, false)?;
+        let packet = self.serialize_payload(&payload, false, DropPolicy::MustDeliver)?;
 
         Ok(ClientSessionResult::OutboundResponse(packet))
     }
@@ -464,7 +602,7 @@ impl ClientSession {
         &mut self,
         data: Bytes,
         timestamp: RtmpTimestamp,
-        can_be_dropped: bool,
+        drop_policy: DropPolicy,
     ) -> Result<ClientSessionResult, ClientSessionError> {
         match self.current_state {
             ClientState::Publishing => (),
@@ -482,9 +620,15 @@ impl ClientSession {
             }
         };
 
+        let data_length = data.len();
         let message = RtmpMessage::VideoData { data };
         let payload = message.into_message_payload(timestamp, active_stream_id)?;
-        let packet = self.serializer.serialize(&payload, false, can_be_dropped)?;
+        let packet = self.serialize_payload(&payload, false, drop_policy)?;
+
+        if let Some(ref subscriber) = self.config.subscriber {
+            subscriber.on_video_sent(data_length, timestamp);
+        }
+
         Ok(ClientSessionResult::OutboundResponse(packet))
     }
 
@@ -493,7 +637,7 @@ impl ClientSession {
         &mut self,
         data: Bytes,
         timestamp: RtmpTimestamp,
-        can_be_dropped: bool,
+        drop_policy: DropPolicy,
     ) -> Result<ClientSessionResult, ClientSessionError> {
         match self.current_state {
             ClientState::Publishing => (),
@@ -511,9 +655,15 @@ impl ClientSession {
             }
         };
 
+        let data_length = data.len();
         let message = RtmpMessage::AudioData { data };
         let payload = message.into_message_payload(timestamp, active_stream_id)?;
-        let packet = self.serializer.serialize(&payload, false, can_be_dropped)?;
+        let packet = self.serialize_payload(&payload, false, drop_policy)?;
+
+        if let Some(ref subscriber) = self.config.subscriber {
+            subscriber.on_audio_sent(data_length, timestamp);
+        }
+
         Ok(ClientSessionResult::OutboundResponse(packet))
     }
 
@@ -706,14 +856,19 @@ impl ClientSession {
             OutstandingTransaction::ConnectionRequested { app_name } => {
                 self.current_state = ClientState::Connected;
                 self.connected_app_name = Some(app_name);
+                self.connected_at = Some(Instant::now());
 
                 let message = RtmpMessage::WindowAcknowledgement {
                     size: self.config.window_ack_size,
                 };
                 let payload = message.into_message_payload(self.get_epoch(), 0)?;
-                let packet = self.serializer.serialize(&payload, false, false)?;
+                let packet = self.serialize_payload(&payload, false, DropPolicy::MustDeliver)?;
                 let event = ClientSessionEvent::ConnectionRequestAccepted;
 
+                if let Some(ref subscriber) = self.config.subscriber {
+                    subscriber.on_connected();
+                }
+
                 let chunk_size_packet = self
                     .serializer
                     .set_max_chunk_size(self.config.chunk_size, RtmpTimestamp::new(0))?;
@@ -740,7 +895,10 @@ impl ClientSession {
                 self.active_stream_id = Some(stream_id);
 
                 match purpose {
-                    TransactionPurpose::PlayRequest { stream_key } => {
+                    TransactionPurpose::PlayRequest {
+                        stream_key,
+                        start_at,
+                    } => {
                         self.current_state = ClientState::PlayRequested;
 
                         let buffer_message = RtmpMessage::UserControl {
@@ -752,19 +910,34 @@ impl ClientSession {
 
                         let buffer_payload =
                             buffer_message.into_message_payload(self.get_epoch(), 0)?;
-                        let buffer_packet =
-                            self.serializer.serialize(&buffer_payload, false, false)?;
+                        let buffer_packet = self.serialize_payload(
+                            &buffer_payload,
+                            false,
+                            DropPolicy::MustDeliver,
+                        )?;
+
+                        let mut play_arguments = vec![Amf0Value::Utf8String(stream_key)];
+                        if let Some(start_at) = start_at {
+                            let start = match start_at {
+                                PlayStartValue::LiveOrRecorded => -2.0,
+                                PlayStartValue::LiveOnly => -1.0,
+                                PlayStartValue::StartTimeInMillis(x) => x as f64 / 1000.0,
+                            };
+
+                            play_arguments.push(Amf0Value::Number(start));
+                        }
 
                         let play_message = RtmpMessage::Amf0Command {
                             command_name: "play".to_string(),
                             transaction_id: 0.0,
                             command_object: Amf0Value::Null,
-                            additional_arguments: vec![Amf0Value::Utf8String(stream_key)],
+                            additional_arguments: play_arguments,
                         };
 
                         let play_payload =
                             play_message.into_message_payload(self.get_epoch(), stream_id)?;
-                        let play_packet = self.serializer.serialize(&play_payload, false, false)?;
+                        let play_packet =
+                            self.serialize_payload(&play_payload, false, DropPolicy::MustDeliver)?;
 
                         Ok(vec![
                             ClientSessionResult::OutboundResponse(buffer_packet),
@@ -777,6 +950,7 @@ impl ClientSession {
                         request_type,
                     } => {
                         self.current_state = ClientState::PublishRequested;
+                        self.publishing_stream_key = Some(stream_key.clone());
 
                         let publish_type_string = match request_type {
                             PublishRequestType::Live => "live".to_string(),
@@ -796,8 +970,11 @@ impl ClientSession {
 
                         let publish_payload =
                             publish_message.into_message_payload(self.get_epoch(), stream_id)?;
-                        let publish_packet =
-                            self.serializer.serialize(&publish_payload, false, false)?;
+                        let publish_packet = self.serialize_payload(
+                            &publish_payload,
+                            false,
+                            DropPolicy::MustDeliver,
+                        )?;
                         Ok(vec![ClientSessionResult::OutboundResponse(publish_packet)])
                     }
                 }
@@ -825,9 +1002,36 @@ impl ClientSession {
             }
         };
 
+        let is_error = match properties.remove("level") {
+            Some(Amf0Value::Utf8String(level)) => level == "error",
+            _ => false,
+        };
+
+        let description = properties
+            .remove("description")
+            .and_then(|value| value.get_string())
+            .unwrap_or_default();
+
         match code.as_ref() {
             "NetStream.Play.Start" => self.handle_play_start(),
             "NetStream.Publish.Start" => self.handle_publish_start(),
+            "NetStream.Play.StreamNotFound" => {
+                let event = ClientSessionEvent::PlaybackStreamNotFound { description };
+                Ok(vec![ClientSessionResult::RaisedEvent(event)])
+            }
+
+            "NetStream.Play.BadConnection" => {
+                let event = ClientSessionEvent::PlaybackBadConnection { description };
+                Ok(vec![ClientSessionResult::RaisedEvent(event)])
+            }
+
+            x if x == "NetStream.Play.Failed" || (is_error && x.starts_with("NetStream.Play.")) => {
+                let event = ClientSessionEvent::PlaybackFailed {
+                    code: x.to_string(),
+                    description,
+                };
+                Ok(vec![ClientSessionResult::RaisedEvent(event)])
+            }
 
             x => {
                 let event = ClientSessionEvent::UnhandleableOnStatusCode {
@@ -841,6 +1045,9 @@ impl ClientSession {
     fn handle_play_start(&mut self) -> ClientResult {
         match self.current_state {
             ClientState::PlayRequested => (),
+
+            // Servers send another play start when an appended playlist item begins
+            ClientState::Playing => return Ok(Vec::new()),
             _ => {
                 return Err(ClientSessionError::SessionInInvalidState {
                     current_state: self.current_state.clone(),
@@ -865,6 +1072,11 @@ impl ClientSession {
         };
 
         self.current_state = ClientState::Publishing;
+        if let Some(ref subscriber) = self.config.subscriber {
+            let stream_key = self.publishing_stream_key.as_deref().unwrap_or("");
+            subscriber.on_publish_started(stream_key);
+        }
+
         let event = ClientSessionEvent::PublishRequestAccepted;
         Ok(vec![ClientSessionResult::RaisedEvent(event)])
     }
@@ -877,6 +1089,7 @@ impl ClientSession {
 
         let properties = match data.remove(0) {
             Amf0Value::Object(properties) => properties,
+            Amf0Value::EcmaArray(properties) => properties,
             _ => return Ok(Vec::new()), // malformed so ignore it
         };
 
@@ -887,6 +1100,69 @@ impl ClientSession {
         Ok(vec![ClientSessionResult::RaisedEvent(event)])
     }
 
+    fn handle_payload(&mut self, payload: MessagePayload) -> ClientResult {
+        let message = payload.to_rtmp_message()?;
+        let results = match message {
+            RtmpMessage::Acknowledgement { sequence_number } => {
+                self.handle_acknowledgement(sequence_number)?
+            }
+
+            RtmpMessage::Amf0Command {
+                command_name,
+                transaction_id,
+                command_object,
+                additional_arguments,
+            } => self.handle_amf0_command(
+                command_name,
+                transaction_id,
+                command_object,
+                additional_arguments,
+            )?,
+
+            RtmpMessage::Amf0Data { values } => {
+                self.handle_amf0_data(values, payload.message_stream_id)?
+            }
+
+            RtmpMessage::AudioData { data } => {
+                self.handle_audio_data(payload.message_stream_id, data, payload.timestamp)?
+            }
+
+            RtmpMessage::VideoData { data } => {
+                self.handle_video_data(payload.message_stream_id, data, payload.timestamp)?
+            }
+
+            RtmpMessage::UserControl {
+                event_type,
+                timestamp,
+                stream_id,
+                buffer_length,
+            } => self.handle_user_control(event_type, timestamp, stream_id, buffer_length)?,
+
+            RtmpMessage::WindowAcknowledgement { size } => self.handle_window_ack_size(size)?,
+
+            RtmpMessage::SetChunkSize { size } => self.handle_set_chunk_size(size)?,
+
+            RtmpMessage::SetPeerBandwidth { size, limit_type } => {
+                self.handle_set_peer_bandwidth(size, limit_type)?
+            }
+
+            RtmpMessage::Aggregate { messages } => self.handle_aggregate(messages)?,
+
+            _ => vec![ClientSessionResult::UnhandleableMessageReceived(payload)],
+        };
+
+        Ok(results)
+    }
+
+    fn handle_aggregate(&mut self, messages: Vec<MessagePayload>) -> ClientResult {
+        let mut results = Vec::new();
+        for payload in messages {
+            results.append(&mut self.handle_payload(payload)?);
+        }
+
+        Ok(results)
+    }
+
     fn handle_acknowledgement(&mut self, sequence_number: u32) -> ClientResult {
         let event = ClientSessionEvent::AcknowledgementReceived {
             bytes_received: sequence_number,
@@ -899,16 +1175,63 @@ impl ClientSession {
         Ok(Vec::new())
     }
 
+    fn handle_set_peer_bandwidth(
+        &mut self,
+        size: u32,
+        limit_type: PeerBandwidthLimitType,
+    ) -> ClientResult {
+        let event = ClientSessionEvent::BandwidthLimitReceived { size, limit_type };
+        self.peer_bandwidth_limit = Some(size);
+        Ok(vec![ClientSessionResult::RaisedEvent(event)])
+    }
+
     fn handle_user_control(
         &mut self,
         event_type: UserControlEventType,
         timestamp: Option<RtmpTimestamp>,
-        _stream_id: Option<u32>,
+        stream_id: Option<u32>,
         _buffer_length: Option<u32>,
     ) -> ClientResult {
         match event_type {
             UserControlEventType::PingRequest => self.handle_ping_request(timestamp),
             UserControlEventType::PingResponse => self.handle_ping_response(timestamp),
+            UserControlEventType::StreamBegin => self
+                .handle_play_stream_event(stream_id, |stream_id| ClientSessionEvent::StreamBegin {
+                    stream_id,
+                }),
+            UserControlEventType::StreamEof => self
+                .handle_play_stream_event(stream_id, |stream_id| ClientSessionEvent::StreamEof {
+                    stream_id,
+                }),
+            UserControlEventType::StreamDry => self
+                .handle_play_stream_event(stream_id, |stream_id| ClientSessionEvent::StreamDry {
+                    stream_id,
+                }),
+            _ => Ok(Vec::new()),
+        }
+    }
+
+    fn handle_play_stream_event<F>(
+        &mut self,
+        stream_id: Option<u32>,
+        create_event: F,
+    ) -> ClientResult
+    where
+        F: FnOnce(u32) -> ClientSessionEvent,
+    {
+        // Only events for the stream we are playing are relevant, as stream begin messages are
+        // also sent for streams we are publishing on.
+        match self.current_state {
+            ClientState::PlayRequested | ClientState::Playing => (),
+            _ => return Ok(Vec::new()),
+        }
+
+        match stream_id {
+            Some(stream_id) if Some(stream_id) == self.active_stream_id => {
+                let event = create_event(stream_id);
+                Ok(vec![ClientSessionResult::RaisedEvent(event)])
+            }
+
             _ => Ok(Vec::new()),
         }
     }
@@ -922,7 +1245,7 @@ impl ClientSession {
         };
 
         let payload = message.into_message_payload(self.get_epoch(), 0)?;
-        let packet = self.serializer.serialize(&payload, false, false)?;
+        let packet = self.serialize_payload(&payload, false, DropPolicy::MustDeliver)?;
         Ok(vec![ClientSessionResult::OutboundResponse(packet)])
     }
 
@@ -937,6 +1260,20 @@ impl ClientSession {
         Ok(Vec::new())
     }
 
+    fn serialize_payload(
+        &mut self,
+        payload: &MessagePayload,
+        force_uncompressed: bool,
+        drop_policy: DropPolicy,
+    ) -> Result<Packet, ClientSessionError> {
+        let packet = self
+            .serializer
+            .serialize(payload, force_uncompressed, drop_policy)?;
+
+        self.last_outbound_at = Instant::now();
+        Ok(packet)
+    }
+
     fn get_epoch(&self) -> RtmpTimestamp {
         match self.start_time.elapsed() {
             Ok(duration) => {
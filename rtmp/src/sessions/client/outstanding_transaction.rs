@@ -1,8 +1,10 @@
 use super::PublishRequestType;
+use sessions::PlayStartValue;
 
 pub enum TransactionPurpose {
     PlayRequest {
         stream_key: String,
+        start_at: Option<PlayStartValue>,
     },
 
     PublishRequest {
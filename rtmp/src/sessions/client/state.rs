@@ -1,3 +1,4 @@
+/// The current stage of the client session's connection, playback, and publishing lifecycle
 #[derive(Clone, Debug)]
 pub enum ClientState {
     /// Client has not connected to an application on the server yet,
@@ -0,0 +1,30 @@
+use time::RtmpTimestamp;
+
+/// Receives notifications about lifecycle changes of a `ClientSession`.
+///
+/// A subscriber can be set on the `ClientSessionConfig` to observe a session (e.g. for metrics
+/// or audit logging) without having to inspect every `ClientSessionResult` it returns.  All
+/// methods have empty default implementations so only the notifications of interest need to be
+/// implemented.
+///
+/// Notifications are raised synchronously from within the session's method calls, so
+/// implementations should return quickly.
+pub trait ClientSessionSubscriber: Send + Sync {
+    /// Called when the server has accepted the session's connection request
+    fn on_connected(&self) {}
+
+    /// Called when the server has accepted the session's request to publish on a stream key
+    fn on_publish_started(&self, _stream_key: &str) {}
+
+    /// Called when the session stops publishing
+    fn on_publish_stopped(&self) {}
+
+    /// Called when the session has been notified that its connection has been closed
+    fn on_disconnected(&self, _reason: Option<&str>) {}
+
+    /// Called each time video data has been packaged to be sent to the server
+    fn on_video_sent(&self, _bytes: usize, _timestamp: RtmpTimestamp) {}
+
+    /// Called each time audio data has been packaged to be sent to the server
+    fn on_audio_sent(&self, _bytes: usize, _timestamp: RtmpTimestamp) {}
+}
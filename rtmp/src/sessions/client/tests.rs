@@ -1,11 +1,18 @@
 use super::*;
 use bytes::Bytes;
 use bytes::BytesMut;
-use chunk_io::{ChunkDeserializer, ChunkSerializer, Packet};
-use messages::{MessagePayload, RtmpMessage, UserControlEventType};
+use chunk_io::{ChunkDeserializer, ChunkSerializer, DropPolicy, Packet};
+use messages::{
+    MessageDeserializationError, MessagePayload, PeerBandwidthLimitType, RtmpMessage,
+    UserControlEventType,
+};
 use rand;
 use rml_amf0::Amf0Value;
+use sessions::{PlayStartValue, ServerSessionConfig, ServerSessionEvent};
 use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use test_utils::{MockRtmpConnection, MockRtmpServer};
 
 #[test]
 fn new_session_and_successful_connect_creates_set_chunk_size_message() {
@@ -41,7 +48,7 @@ fn can_send_connect_request() {
     consume_results(&mut deserializer, initial_results);
 
     let results = session.request_connection(app_name.clone()).unwrap();
-    let (mut responses, _) = split_results(&mut deserializer, vec![results]);
+    let (mut responses, _) = split_results(&mut deserializer, results);
 
     assert_eq!(responses.len(), 1, "Expected 1 response");
     match responses.remove(0) {
@@ -109,7 +116,7 @@ fn can_send_connect_request_with_tc_url() {
     consume_results(&mut deserializer, initial_results);
 
     let results = session.request_connection(app_name.clone()).unwrap();
-    let (mut responses, _) = split_results(&mut deserializer, vec![results]);
+    let (mut responses, _) = split_results(&mut deserializer, results);
 
     assert_eq!(responses.len(), 1, "Expected 1 response");
     match responses.remove(0) {
@@ -180,7 +187,7 @@ fn can_process_connect_success_response() {
     consume_results(&mut deserializer, initial_results);
 
     let results = session.request_connection(app_name.clone()).unwrap();
-    consume_results(&mut deserializer, vec![results]);
+    consume_results(&mut deserializer, results);
 
     let response = get_connect_success_response(&mut serializer);
     let results = session.handle_input(&response.bytes[..]).unwrap();
@@ -196,6 +203,53 @@ fn can_process_connect_success_response() {
     }
 }
 
+#[test]
+fn connection_times_recorded_when_connect_request_accepted() {
+    let before_creation = Instant::now();
+    let config = ClientSessionConfig::new();
+    let mut deserializer = ChunkDeserializer::new();
+    let mut serializer = ChunkSerializer::new();
+    let (mut session, initial_results) = ClientSession::new(config.clone()).unwrap();
+    consume_results(&mut deserializer, initial_results);
+
+    assert!(
+        session.connection_start_time() >= before_creation,
+        "Expected start time to be set when the session was created"
+    );
+    assert_eq!(session.connected_at(), None, "Expected no connected time");
+    assert_eq!(
+        session.connected_duration(),
+        None,
+        "Expected no connected duration"
+    );
+
+    let results = session.request_connection("test".to_string()).unwrap();
+    consume_results(&mut deserializer, results);
+    assert_eq!(
+        session.connected_at(),
+        None,
+        "Expected no connected time before the server responds"
+    );
+
+    let response = get_connect_success_response(&mut serializer);
+    let results = session.handle_input(&response.bytes[..]).unwrap();
+    consume_results(&mut deserializer, results);
+
+    match session.connected_at() {
+        Some(connected_at) => assert!(
+            connected_at >= session.connection_start_time(),
+            "Expected connection to be accepted after the session started"
+        ),
+
+        None => panic!("Expected a connected time after the connection was accepted"),
+    }
+
+    assert!(
+        session.connected_duration().is_some(),
+        "Expected a connected duration"
+    );
+}
+
 #[test]
 fn event_raised_when_connect_request_rejected() {
     let app_name = "test".to_string();
@@ -206,7 +260,7 @@ fn event_raised_when_connect_request_rejected() {
     consume_results(&mut deserializer, initial_results);
 
     let results = session.request_connection(app_name.clone()).unwrap();
-    consume_results(&mut deserializer, vec![results]);
+    consume_results(&mut deserializer, results);
 
     let response = get_connect_error_response(&mut serializer);
     let results = session.handle_input(&response.bytes[..]).unwrap();
@@ -235,7 +289,7 @@ fn error_thrown_when_connect_request_made_after_successful_connection() {
     consume_results(&mut deserializer, initial_results);
 
     let results = session.request_connection(app_name.clone()).unwrap();
-    consume_results(&mut deserializer, vec![results]);
+    consume_results(&mut deserializer, results);
 
     let response = get_connect_success_response(&mut serializer);
     let results = session.handle_input(&response.bytes[..]).unwrap();
@@ -261,7 +315,7 @@ fn successful_connect_request_sends_window_ack_size() {
     consume_results(&mut deserializer, initial_results);
 
     let results = session.request_connection(app_name.clone()).unwrap();
-    consume_results(&mut deserializer, vec![results]);
+    consume_results(&mut deserializer, results);
 
     let response = get_connect_success_response(&mut serializer);
     let results = session.handle_input(&response.bytes[..]).unwrap();
@@ -423,6 +477,57 @@ fn successful_play_request_workflow() {
     }
 }
 
+#[test]
+fn active_play_session_raises_event_for_metadata_sent_as_ecma_array() {
+    let config = ClientSessionConfig::new();
+    let mut deserializer = ChunkDeserializer::new();
+    let mut serializer = ChunkSerializer::new();
+    let (mut session, initial_results) = ClientSession::new(config.clone()).unwrap();
+    consume_results(&mut deserializer, initial_results);
+
+    perform_successful_connect(
+        "test".to_string(),
+        &mut session,
+        &mut serializer,
+        &mut deserializer,
+    );
+    let stream_id =
+        perform_successful_play_request(config, &mut session, &mut serializer, &mut deserializer);
+
+    let mut properties = HashMap::new();
+    properties.insert("width".to_string(), Amf0Value::Number(1280_f64));
+    properties.insert("height".to_string(), Amf0Value::Number(720_f64));
+
+    let message = RtmpMessage::Amf0Data {
+        values: vec![
+            Amf0Value::Utf8String("onMetaData".to_string()),
+            Amf0Value::EcmaArray(properties),
+        ],
+    };
+
+    let payload = message
+        .into_message_payload(RtmpTimestamp::new(0), stream_id)
+        .unwrap();
+    let packet = serializer
+        .serialize(&payload, false, DropPolicy::MustDeliver)
+        .unwrap();
+    let results = session.handle_input(&packet.bytes[..]).unwrap();
+    let (_, mut events) = split_results(&mut deserializer, results);
+
+    assert_eq!(events.len(), 1, "Unexpected number of events received");
+    match events.remove(0) {
+        ClientSessionEvent::StreamMetadataReceived { metadata } => {
+            assert_eq!(metadata.video_width, Some(1280), "Unexpected video width");
+            assert_eq!(metadata.video_height, Some(720), "Unexpected video height");
+        }
+
+        x => panic!(
+            "Expected metadata received event, instead received: {:?}",
+            x
+        ),
+    }
+}
+
 #[test]
 fn active_play_session_raises_events_when_stream_metadata_changes() {
     let config = ClientSessionConfig::new();
@@ -467,7 +572,9 @@ fn active_play_session_raises_events_when_stream_metadata_changes() {
     let payload = message
         .into_message_payload(RtmpTimestamp::new(0), stream_id)
         .unwrap();
-    let packet = serializer.serialize(&payload, false, false).unwrap();
+    let packet = serializer
+        .serialize(&payload, false, DropPolicy::MustDeliver)
+        .unwrap();
     let results = session.handle_input(&packet.bytes[..]).unwrap();
     let (_, mut events) = split_results(&mut deserializer, results);
 
@@ -546,7 +653,61 @@ fn active_play_session_raises_events_when_video_data_received() {
     let payload = message
         .into_message_payload(RtmpTimestamp::new(1234), stream_id)
         .unwrap();
-    let packet = serializer.serialize(&payload, false, false).unwrap();
+    let packet = serializer
+        .serialize(&payload, false, DropPolicy::MustDeliver)
+        .unwrap();
+    let results = session.handle_input(&packet.bytes[..]).unwrap();
+    let (_, mut events) = split_results(&mut deserializer, results);
+
+    assert_eq!(events.len(), 1, "Unexpected number of events received");
+    match events.remove(0) {
+        ClientSessionEvent::VideoDataReceived { data, timestamp } => {
+            assert_eq!(timestamp, RtmpTimestamp::new(1234), "Unexpected timestamp");
+            assert_eq!(&data[..], &video_data[..], "Unexpected video data");
+        }
+
+        x => panic!(
+            "Expected video data received event, instead received: {:?}",
+            x
+        ),
+    }
+}
+
+#[test]
+fn large_video_data_reassembled_after_server_changes_chunk_size() {
+    let config = ClientSessionConfig::new();
+    let mut deserializer = ChunkDeserializer::new();
+    let mut serializer = ChunkSerializer::new();
+    let (mut session, initial_results) = ClientSession::new(config.clone()).unwrap();
+    consume_results(&mut deserializer, initial_results);
+
+    perform_successful_connect(
+        "test".to_string(),
+        &mut session,
+        &mut serializer,
+        &mut deserializer,
+    );
+    let stream_id =
+        perform_successful_play_request(config, &mut session, &mut serializer, &mut deserializer);
+
+    let chunk_size_packet = serializer
+        .set_max_chunk_size(4096, RtmpTimestamp::new(0))
+        .unwrap();
+    let results = session.handle_input(&chunk_size_packet.bytes[..]).unwrap();
+    assert_eq!(results.len(), 0, "Expected no results from set chunk size");
+
+    // Larger than the default 128 byte chunk size but fits in a single 4096 byte chunk, so it
+    // can only be read correctly if the session applied the new chunk size
+    let video_data = Bytes::from(vec![7_u8; 3000]);
+    let message = RtmpMessage::VideoData {
+        data: video_data.clone(),
+    };
+    let payload = message
+        .into_message_payload(RtmpTimestamp::new(1234), stream_id)
+        .unwrap();
+    let packet = serializer
+        .serialize(&payload, false, DropPolicy::MustDeliver)
+        .unwrap();
     let results = session.handle_input(&packet.bytes[..]).unwrap();
     let (_, mut events) = split_results(&mut deserializer, results);
 
@@ -588,7 +749,9 @@ fn active_play_session_raises_events_when_audio_data_received() {
     let payload = message
         .into_message_payload(RtmpTimestamp::new(1234), stream_id)
         .unwrap();
-    let packet = serializer.serialize(&payload, false, false).unwrap();
+    let packet = serializer
+        .serialize(&payload, false, DropPolicy::MustDeliver)
+        .unwrap();
     let results = session.handle_input(&packet.bytes[..]).unwrap();
     let (_, mut events) = split_results(&mut deserializer, results);
 
@@ -606,6 +769,184 @@ fn active_play_session_raises_events_when_audio_data_received() {
     }
 }
 
+#[test]
+fn active_play_session_raises_events_for_each_message_in_aggregate() {
+    let config = ClientSessionConfig::new();
+    let mut deserializer = ChunkDeserializer::new();
+    let mut serializer = ChunkSerializer::new();
+    let (mut session, initial_results) = ClientSession::new(config.clone()).unwrap();
+    consume_results(&mut deserializer, initial_results);
+
+    perform_successful_connect(
+        "test".to_string(),
+        &mut session,
+        &mut serializer,
+        &mut deserializer,
+    );
+    let stream_id =
+        perform_successful_play_request(config, &mut session, &mut serializer, &mut deserializer);
+
+    let audio_data = Bytes::from(vec![1, 2, 3]);
+    let video_data = Bytes::from(vec![4, 5, 6, 7]);
+    let message = RtmpMessage::Aggregate {
+        messages: vec![
+            MessagePayload::new(8, RtmpTimestamp::new(1000), stream_id, audio_data.clone()),
+            MessagePayload::new(9, RtmpTimestamp::new(1020), stream_id, video_data.clone()),
+        ],
+    };
+    let payload = message
+        .into_message_payload(RtmpTimestamp::new(1000), stream_id)
+        .unwrap();
+    let packet = serializer
+        .serialize(&payload, false, DropPolicy::MustDeliver)
+        .unwrap();
+    let results = session.handle_input(&packet.bytes[..]).unwrap();
+    let (_, mut events) = split_results(&mut deserializer, results);
+
+    assert_eq!(events.len(), 2, "Unexpected number of events received");
+    match events.remove(0) {
+        ClientSessionEvent::AudioDataReceived { data, timestamp } => {
+            assert_eq!(
+                timestamp,
+                RtmpTimestamp::new(1000),
+                "Unexpected audio timestamp"
+            );
+            assert_eq!(&data[..], &audio_data[..], "Unexpected audio data");
+        }
+
+        x => panic!(
+            "Expected audio data received event, instead received: {:?}",
+            x
+        ),
+    }
+
+    match events.remove(0) {
+        ClientSessionEvent::VideoDataReceived { data, timestamp } => {
+            assert_eq!(
+                timestamp,
+                RtmpTimestamp::new(1020),
+                "Unexpected video timestamp"
+            );
+            assert_eq!(&data[..], &video_data[..], "Unexpected video data");
+        }
+
+        x => panic!(
+            "Expected video data received event, instead received: {:?}",
+            x
+        ),
+    }
+}
+
+#[test]
+fn error_when_aggregate_contains_another_aggregate() {
+    let config = ClientSessionConfig::new();
+    let mut deserializer = ChunkDeserializer::new();
+    let mut serializer = ChunkSerializer::new();
+    let (mut session, initial_results) = ClientSession::new(config.clone()).unwrap();
+    consume_results(&mut deserializer, initial_results);
+
+    perform_successful_connect(
+        "test".to_string(),
+        &mut session,
+        &mut serializer,
+        &mut deserializer,
+    );
+    let stream_id =
+        perform_successful_play_request(config, &mut session, &mut serializer, &mut deserializer);
+
+    let inner = RtmpMessage::Aggregate {
+        messages: vec![MessagePayload::new(
+            9,
+            RtmpTimestamp::new(1000),
+            stream_id,
+            Bytes::from(vec![1, 2, 3]),
+        )],
+    };
+    let message = RtmpMessage::Aggregate {
+        messages: vec![inner
+            .into_message_payload(RtmpTimestamp::new(1000), stream_id)
+            .unwrap()],
+    };
+    let payload = message
+        .into_message_payload(RtmpTimestamp::new(1000), stream_id)
+        .unwrap();
+    let packet = serializer
+        .serialize(&payload, false, DropPolicy::MustDeliver)
+        .unwrap();
+
+    match session.handle_input(&packet.bytes[..]) {
+        Err(ClientSessionError::MessageDeserializationError(
+            MessageDeserializationError::InvalidMessageFormat,
+        )) => (),
+        x => panic!("Expected InvalidMessageFormat error, instead got {:?}", x),
+    }
+}
+
+#[test]
+fn active_play_session_raises_event_when_stream_begin_received() {
+    let (stream_id, event) = receive_user_control_on_play_stream(UserControlEventType::StreamBegin);
+    assert_eq!(event, ClientSessionEvent::StreamBegin { stream_id });
+}
+
+#[test]
+fn active_play_session_raises_event_when_stream_eof_received() {
+    let (stream_id, event) = receive_user_control_on_play_stream(UserControlEventType::StreamEof);
+    assert_eq!(event, ClientSessionEvent::StreamEof { stream_id });
+}
+
+#[test]
+fn active_play_session_raises_event_when_stream_dry_received() {
+    let (stream_id, event) = receive_user_control_on_play_stream(UserControlEventType::StreamDry);
+    assert_eq!(event, ClientSessionEvent::StreamDry { stream_id });
+}
+
+#[test]
+fn event_raised_when_requested_stream_not_found() {
+    let event = receive_play_status("error", "NetStream.Play.StreamNotFound");
+    assert_eq!(
+        event,
+        ClientSessionEvent::PlaybackStreamNotFound {
+            description: "some description".to_string()
+        }
+    );
+}
+
+#[test]
+fn event_raised_when_playback_has_bad_connection() {
+    let event = receive_play_status("error", "NetStream.Play.BadConnection");
+    assert_eq!(
+        event,
+        ClientSessionEvent::PlaybackBadConnection {
+            description: "some description".to_string()
+        }
+    );
+}
+
+#[test]
+fn event_raised_when_playback_fails() {
+    for code in &["NetStream.Play.Failed", "NetStream.Play.Forbidden"] {
+        let event = receive_play_status("error", code);
+        assert_eq!(
+            event,
+            ClientSessionEvent::PlaybackFailed {
+                code: code.to_string(),
+                description: "some description".to_string(),
+            }
+        );
+    }
+}
+
+#[test]
+fn non_error_play_status_is_unhandleable() {
+    let event = receive_play_status("status", "NetStream.Play.Reset");
+    assert_eq!(
+        event,
+        ClientSessionEvent::UnhandleableOnStatusCode {
+            code: "NetStream.Play.Reset".to_string()
+        }
+    );
+}
+
 #[test]
 fn can_receive_audio_data_prior_to_play_request_being_accepted() {
     let app_name = "test".to_string();
@@ -733,7 +1074,9 @@ fn can_receive_audio_data_prior_to_play_request_being_accepted() {
     let payload = message
         .into_message_payload(RtmpTimestamp::new(1234), created_stream_id)
         .unwrap();
-    let packet = serializer.serialize(&payload, false, false).unwrap();
+    let packet = serializer
+        .serialize(&payload, false, DropPolicy::MustDeliver)
+        .unwrap();
     let results = session.handle_input(&packet.bytes[..]).unwrap();
     let (_, mut events) = split_results(&mut deserializer, results);
 
@@ -878,7 +1221,9 @@ fn can_receive_video_data_prior_to_play_request_being_accepted() {
     let payload = message
         .into_message_payload(RtmpTimestamp::new(1234), created_stream_id)
         .unwrap();
-    let packet = serializer.serialize(&payload, false, false).unwrap();
+    let packet = serializer
+        .serialize(&payload, false, DropPolicy::MustDeliver)
+        .unwrap();
     let results = session.handle_input(&packet.bytes[..]).unwrap();
     let (_, mut events) = split_results(&mut deserializer, results);
 
@@ -897,7 +1242,7 @@ fn can_receive_video_data_prior_to_play_request_being_accepted() {
 }
 
 #[test]
-fn can_stop_playback() {
+fn play_request_includes_requested_start_time() {
     let config = ClientSessionConfig::new();
     let mut deserializer = ChunkDeserializer::new();
     let mut serializer = ChunkSerializer::new();
@@ -910,23 +1255,163 @@ fn can_stop_playback() {
         &mut serializer,
         &mut deserializer,
     );
-    let stream_id =
-        perform_successful_play_request(config, &mut session, &mut serializer, &mut deserializer);
 
-    let results = session.stop_playback().unwrap();
-    let (mut responses, _) = split_results(&mut deserializer, results);
+    let result = session
+        .request_playback_from("abcd".to_string(), PlayStartValue::StartTimeInMillis(1500))
+        .unwrap();
+    let (mut responses, _) = split_results(&mut deserializer, vec![result]);
+    let transaction_id = match responses.remove(0) {
+        (_, RtmpMessage::Amf0Command { transaction_id, .. }) => transaction_id,
+        x => panic!("Unexpected response seen: {:?}", x),
+    };
 
-    assert_eq!(responses.len(), 1, "Unexpected number of responses");
-    match responses.remove(0) {
-        (
-            payload,
+    let (_, create_stream_response) =
+        get_create_stream_success_response(transaction_id, &mut serializer);
+    let results = session
+        .handle_input(&create_stream_response.bytes[..])
+        .unwrap();
+    let (responses, _) = split_results(&mut deserializer, results);
+
+    let play_arguments = responses
+        .into_iter()
+        .filter_map(|(_, message)| match message {
             RtmpMessage::Amf0Command {
                 command_name,
-                transaction_id,
-                command_object,
                 additional_arguments,
-            },
-        ) => {
+                ..
+            } if command_name == "play" => Some(additional_arguments),
+            _ => None,
+        })
+        .next()
+        .expect("No play command sent");
+
+    assert_eq!(
+        play_arguments,
+        vec![
+            Amf0Value::Utf8String("abcd".to_string()),
+            Amf0Value::Number(1.5),
+        ],
+        "Unexpected play arguments"
+    );
+}
+
+#[test]
+fn can_append_to_playlist_of_active_play_session() {
+    let config = ClientSessionConfig::new();
+    let mut deserializer = ChunkDeserializer::new();
+    let mut serializer = ChunkSerializer::new();
+    let (mut session, initial_results) = ClientSession::new(config.clone()).unwrap();
+    consume_results(&mut deserializer, initial_results);
+
+    perform_successful_connect(
+        "test".to_string(),
+        &mut session,
+        &mut serializer,
+        &mut deserializer,
+    );
+    let stream_id =
+        perform_successful_play_request(config, &mut session, &mut serializer, &mut deserializer);
+
+    let result = session.append_playback("efgh".to_string()).unwrap();
+    let (mut responses, _) = split_results(&mut deserializer, vec![result]);
+
+    assert_eq!(responses.len(), 1, "Unexpected number of responses");
+    match responses.remove(0) {
+        (
+            payload,
+            RtmpMessage::Amf0Command {
+                command_name,
+                additional_arguments,
+                ..
+            },
+        ) => {
+            assert_eq!(
+                payload.message_stream_id, stream_id,
+                "Unexpected message stream id"
+            );
+            assert_eq!(command_name, "play", "Unexpected command name");
+            assert_eq!(
+                additional_arguments[0],
+                Amf0Value::Utf8String("efgh".to_string()),
+                "Unexpected stream key"
+            );
+            assert_eq!(
+                additional_arguments[1],
+                Amf0Value::Number(-2.0),
+                "Unexpected start argument"
+            );
+            assert_eq!(
+                additional_arguments[3],
+                Amf0Value::Boolean(false),
+                "Unexpected reset argument"
+            );
+        }
+
+        x => panic!("Expected Amf0 command, instead received: {:?}", x),
+    }
+
+    // Servers announce the appended item starting with another play start status
+    let play_start = get_play_success_response(&mut serializer, stream_id);
+    let results = session.handle_input(&play_start.bytes[..]).unwrap();
+    assert_eq!(
+        results.len(),
+        0,
+        "Expected no results for appended item starting"
+    );
+}
+
+#[test]
+fn cannot_append_playback_when_not_playing() {
+    let config = ClientSessionConfig::new();
+    let mut deserializer = ChunkDeserializer::new();
+    let mut serializer = ChunkSerializer::new();
+    let (mut session, initial_results) = ClientSession::new(config.clone()).unwrap();
+    consume_results(&mut deserializer, initial_results);
+
+    perform_successful_connect(
+        "test".to_string(),
+        &mut session,
+        &mut serializer,
+        &mut deserializer,
+    );
+
+    match session.append_playback("efgh".to_string()) {
+        Err(ClientSessionError::SessionInInvalidState { .. }) => (),
+        x => panic!("Expected invalid state error, instead received {:?}", x),
+    }
+}
+
+#[test]
+fn can_stop_playback() {
+    let config = ClientSessionConfig::new();
+    let mut deserializer = ChunkDeserializer::new();
+    let mut serializer = ChunkSerializer::new();
+    let (mut session, initial_results) = ClientSession::new(config.clone()).unwrap();
+    consume_results(&mut deserializer, initial_results);
+
+    perform_successful_connect(
+        "test".to_string(),
+        &mut session,
+        &mut serializer,
+        &mut deserializer,
+    );
+    let stream_id =
+        perform_successful_play_request(config, &mut session, &mut serializer, &mut deserializer);
+
+    let results = session.stop_playback().unwrap();
+    let (mut responses, _) = split_results(&mut deserializer, results);
+
+    assert_eq!(responses.len(), 1, "Unexpected number of responses");
+    match responses.remove(0) {
+        (
+            payload,
+            RtmpMessage::Amf0Command {
+                command_name,
+                transaction_id,
+                command_object,
+                additional_arguments,
+            },
+        ) => {
             assert_eq!(
                 payload.message_stream_id, stream_id,
                 "Unexpected message stream id"
@@ -975,7 +1460,9 @@ fn automatically_responds_to_ping_requests() {
     let payload = message
         .into_message_payload(RtmpTimestamp::new(6000), 0)
         .unwrap();
-    let packet = serializer.serialize(&payload, false, false).unwrap();
+    let packet = serializer
+        .serialize(&payload, false, DropPolicy::MustDeliver)
+        .unwrap();
     let results = session.handle_input(&packet.bytes[..]).unwrap();
     let (mut responses, _) = split_results(&mut deserializer, results);
 
@@ -1031,7 +1518,9 @@ fn event_raised_when_ping_response_received() {
     let payload = message
         .into_message_payload(RtmpTimestamp::new(6000), 0)
         .unwrap();
-    let packet = serializer.serialize(&payload, false, false).unwrap();
+    let packet = serializer
+        .serialize(&payload, false, DropPolicy::MustDeliver)
+        .unwrap();
     let results = session.handle_input(&packet.bytes[..]).unwrap();
     let (_, mut events) = split_results(&mut deserializer, results);
 
@@ -1093,6 +1582,73 @@ fn can_send_ping_request() {
     }
 }
 
+#[test]
+fn no_keepalive_ping_sent_before_interval_elapses() {
+    let config = ClientSessionConfig::new();
+    let mut deserializer = ChunkDeserializer::new();
+    let mut serializer = ChunkSerializer::new();
+    let (mut session, initial_results) = ClientSession::new(config.clone()).unwrap();
+    consume_results(&mut deserializer, initial_results);
+
+    perform_successful_connect(
+        "test".to_string(),
+        &mut session,
+        &mut serializer,
+        &mut deserializer,
+    );
+
+    let later = Instant::now() + Duration::from_secs(60);
+    assert_eq!(
+        session.check_keepalive(later).unwrap(),
+        None,
+        "No ping expected when keep-alives are not enabled"
+    );
+
+    session.enable_keepalive(10_000);
+    assert_eq!(session.check_keepalive(Instant::now()).unwrap(), None);
+    assert_eq!(
+        session
+            .check_keepalive(Instant::now() + Duration::from_millis(9_000))
+            .unwrap(),
+        None
+    );
+}
+
+#[test]
+fn single_keepalive_ping_sent_after_interval_elapses() {
+    let config = ClientSessionConfig::new();
+    let mut deserializer = ChunkDeserializer::new();
+    let mut serializer = ChunkSerializer::new();
+    let (mut session, initial_results) = ClientSession::new(config.clone()).unwrap();
+    consume_results(&mut deserializer, initial_results);
+
+    perform_successful_connect(
+        "test".to_string(),
+        &mut session,
+        &mut serializer,
+        &mut deserializer,
+    );
+
+    session.enable_keepalive(10_000);
+    let later = Instant::now() + Duration::from_millis(10_000);
+    let result = session.check_keepalive(later).unwrap();
+    let (responses, _) = split_results(&mut deserializer, result.into_iter().collect());
+    assert_eq!(responses.len(), 1, "Expected one ping request to be sent");
+    match responses[0].1 {
+        RtmpMessage::UserControl {
+            event_type: UserControlEventType::PingRequest,
+            ..
+        } => (),
+        ref x => panic!("Expected PingRequest being sent, instead found {:?}", x),
+    }
+
+    assert_eq!(
+        session.check_keepalive(later).unwrap(),
+        None,
+        "No additional ping expected until another interval has elapsed"
+    );
+}
+
 #[test]
 fn sends_ack_after_receiving_window_ack_bytes() {
     let config = ClientSessionConfig::new();
@@ -1115,7 +1671,7 @@ fn sends_ack_after_receiving_window_ack_bytes() {
         .into_message_payload(RtmpTimestamp::new(0), 0)
         .unwrap();
     let window_ack_packet = serializer
-        .serialize(&window_ack_payload, false, false)
+        .serialize(&window_ack_payload, false, DropPolicy::MustDeliver)
         .unwrap();
     let results = session.handle_input(&window_ack_packet.bytes[..]).unwrap();
     consume_results(&mut deserializer, results);
@@ -1128,7 +1684,9 @@ fn sends_ack_after_receiving_window_ack_bytes() {
     let video_payload = video_message
         .into_message_payload(RtmpTimestamp::new(0), 0)
         .unwrap();
-    let video_packet = serializer.serialize(&video_payload, false, false).unwrap();
+    let video_packet = serializer
+        .serialize(&video_payload, false, DropPolicy::MustDeliver)
+        .unwrap();
     let results = session.handle_input(&video_packet.bytes[..]).unwrap();
     let (mut responses, _) = split_results(&mut deserializer, results);
 
@@ -1146,7 +1704,9 @@ fn sends_ack_after_receiving_window_ack_bytes() {
     let video_payload = video_message
         .into_message_payload(RtmpTimestamp::new(0), 0)
         .unwrap();
-    let video_packet = serializer.serialize(&video_payload, false, false).unwrap();
+    let video_packet = serializer
+        .serialize(&video_payload, false, DropPolicy::MustDeliver)
+        .unwrap();
     let results = session.handle_input(&video_packet.bytes[..]).unwrap();
     let (responses, _) = split_results(&mut deserializer, results);
     assert_eq!(responses.len(), 0, "Expected no responses");
@@ -1159,7 +1719,9 @@ fn sends_ack_after_receiving_window_ack_bytes() {
     let video_payload = video_message
         .into_message_payload(RtmpTimestamp::new(0), 0)
         .unwrap();
-    let video_packet = serializer.serialize(&video_payload, false, false).unwrap();
+    let video_packet = serializer
+        .serialize(&video_payload, false, DropPolicy::MustDeliver)
+        .unwrap();
     let results = session.handle_input(&video_packet.bytes[..]).unwrap();
     let (mut responses, _) = split_results(&mut deserializer, results);
     assert_eq!(responses.len(), 1, "Unexpected number of responses");
@@ -1169,6 +1731,71 @@ fn sends_ack_after_receiving_window_ack_bytes() {
     }
 }
 
+#[test]
+fn ack_threshold_uses_server_window_ack_size_instead_of_configured_size() {
+    let mut config = ClientSessionConfig::new();
+    config.window_ack_size = 50;
+
+    let mut deserializer = ChunkDeserializer::new();
+    let mut serializer = ChunkSerializer::new();
+    let (mut session, initial_results) = ClientSession::new(config.clone()).unwrap();
+    consume_results(&mut deserializer, initial_results);
+
+    perform_successful_connect(
+        "test".to_string(),
+        &mut session,
+        &mut serializer,
+        &mut deserializer,
+    );
+    let _ =
+        perform_successful_play_request(config, &mut session, &mut serializer, &mut deserializer);
+
+    let window_ack_message = RtmpMessage::WindowAcknowledgement { size: 300 };
+    let window_ack_payload = window_ack_message
+        .into_message_payload(RtmpTimestamp::new(0), 0)
+        .unwrap();
+    let window_ack_packet = serializer
+        .serialize(&window_ack_payload, false, DropPolicy::MustDeliver)
+        .unwrap();
+    let results = session.handle_input(&window_ack_packet.bytes[..]).unwrap();
+    consume_results(&mut deserializer, results);
+
+    // More than the client's configured window, but less than the server's
+    let video_message = RtmpMessage::VideoData {
+        data: Bytes::from(vec![1; 150]),
+    };
+    let video_payload = video_message
+        .into_message_payload(RtmpTimestamp::new(0), 0)
+        .unwrap();
+    let video_packet = serializer
+        .serialize(&video_payload, false, DropPolicy::MustDeliver)
+        .unwrap();
+    let results = session.handle_input(&video_packet.bytes[..]).unwrap();
+    let (responses, _) = split_results(&mut deserializer, results);
+    assert_eq!(
+        responses.len(),
+        0,
+        "Expected no ack before the server's window was reached"
+    );
+
+    let video_message = RtmpMessage::VideoData {
+        data: Bytes::from(vec![1; 150]),
+    };
+    let video_payload = video_message
+        .into_message_payload(RtmpTimestamp::new(0), 0)
+        .unwrap();
+    let video_packet = serializer
+        .serialize(&video_payload, false, DropPolicy::MustDeliver)
+        .unwrap();
+    let results = session.handle_input(&video_packet.bytes[..]).unwrap();
+    let (mut responses, _) = split_results(&mut deserializer, results);
+    assert_eq!(responses.len(), 1, "Unexpected number of responses");
+    match responses.remove(0) {
+        (_, RtmpMessage::Acknowledgement { .. }) => (),
+        x => panic!("Expected Acknowledgement, instead received: {:?}", x),
+    }
+}
+
 #[test]
 fn event_raised_when_server_sends_an_acknowledgement() {
     let config = ClientSessionConfig::new();
@@ -1190,7 +1817,9 @@ fn event_raised_when_server_sends_an_acknowledgement() {
     let payload = message
         .into_message_payload(RtmpTimestamp::new(0), 0)
         .unwrap();
-    let packet = serializer.serialize(&payload, false, false).unwrap();
+    let packet = serializer
+        .serialize(&payload, false, DropPolicy::MustDeliver)
+        .unwrap();
     let results = session.handle_input(&packet.bytes[..]).unwrap();
     let (_, mut events) = split_results(&mut deserializer, results);
 
@@ -1210,6 +1839,55 @@ fn event_raised_when_server_sends_an_acknowledgement() {
     }
 }
 
+#[test]
+fn event_raised_and_limit_stored_when_server_sends_peer_bandwidth() {
+    let config = ClientSessionConfig::new();
+    let mut deserializer = ChunkDeserializer::new();
+    let mut serializer = ChunkSerializer::new();
+    let (mut session, initial_results) = ClientSession::new(config.clone()).unwrap();
+    consume_results(&mut deserializer, initial_results);
+
+    perform_successful_connect(
+        "test".to_string(),
+        &mut session,
+        &mut serializer,
+        &mut deserializer,
+    );
+
+    assert_eq!(
+        session.peer_bandwidth_limit(),
+        None,
+        "Unexpected initial limit"
+    );
+
+    let message = RtmpMessage::SetPeerBandwidth {
+        size: 500000,
+        limit_type: PeerBandwidthLimitType::Hard,
+    };
+    let payload = message
+        .into_message_payload(RtmpTimestamp::new(0), 0)
+        .unwrap();
+    let packet = serializer
+        .serialize(&payload, false, DropPolicy::MustDeliver)
+        .unwrap();
+    let results = session.handle_input(&packet.bytes[..]).unwrap();
+    let (_, mut events) = split_results(&mut deserializer, results);
+
+    assert_eq!(events.len(), 1, "Unexpected number of events");
+    assert_eq!(
+        events.remove(0),
+        ClientSessionEvent::BandwidthLimitReceived {
+            size: 500000,
+            limit_type: PeerBandwidthLimitType::Hard,
+        }
+    );
+    assert_eq!(
+        session.peer_bandwidth_limit(),
+        Some(500000),
+        "Incorrect stored limit"
+    );
+}
+
 #[test]
 fn successful_publish_request_workflow() {
     let stream_key = "test-key".to_string();
@@ -1342,6 +2020,7 @@ fn publisher_can_send_metadata() {
     metadata.audio_codec_id = Some(7);
     metadata.audio_bitrate_kbps = Some(104);
     metadata.audio_sample_rate = Some(105);
+    metadata.audio_sample_size = Some(16);
     metadata.audio_channels = Some(106);
     metadata.audio_is_stereo = Some(true);
     metadata.encoder = Some("encoder".to_string());
@@ -1415,6 +2094,11 @@ fn publisher_can_send_metadata() {
                         Some(&Amf0Value::Number(105.0)),
                         "Unexpected audio sample rate"
                     );
+                    assert_eq!(
+                        properties.get("audiosamplesize"),
+                        Some(&Amf0Value::Number(16.0)),
+                        "Unexpected audio sample size"
+                    );
                     assert_eq!(
                         properties.get("audiochannels"),
                         Some(&Amf0Value::Number(106.0)),
@@ -1459,7 +2143,11 @@ fn publisher_can_send_video_data() {
 
     let data = Bytes::from(vec![1, 2, 3, 4, 5]);
     let result = session
-        .publish_video_data(data.clone(), RtmpTimestamp::new(1234), false)
+        .publish_video_data(
+            data.clone(),
+            RtmpTimestamp::new(1234),
+            DropPolicy::MustDeliver,
+        )
         .unwrap();
     let (mut responses, _) = split_results(&mut deserializer, vec![result]);
 
@@ -1501,7 +2189,11 @@ fn publisher_can_send_audio_data() {
 
     let data = Bytes::from(vec![1, 2, 3, 4, 5]);
     let result = session
-        .publish_audio_data(data.clone(), RtmpTimestamp::new(1234), false)
+        .publish_audio_data(
+            data.clone(),
+            RtmpTimestamp::new(1234),
+            DropPolicy::MustDeliver,
+        )
         .unwrap();
     let (mut responses, _) = split_results(&mut deserializer, vec![result]);
 
@@ -1524,6 +2216,60 @@ fn publisher_can_send_audio_data() {
     }
 }
 
+#[test]
+fn published_bytes_slices_are_sent_unchanged() {
+    let config = ClientSessionConfig::new();
+    let mut deserializer = ChunkDeserializer::new();
+    let mut serializer = ChunkSerializer::new();
+    let (mut session, initial_results) = ClientSession::new(config.clone()).unwrap();
+    consume_results(&mut deserializer, initial_results);
+
+    perform_successful_connect(
+        "test".to_string(),
+        &mut session,
+        &mut serializer,
+        &mut deserializer,
+    );
+    perform_successful_publish_request(&mut session, &mut serializer, &mut deserializer);
+
+    // Demuxers commonly hand out slices of a larger buffer, and these are larger than the
+    // chunk size so they are split across multiple chunks
+    let buffer = Bytes::from((0..20_000).map(|x| (x % 251) as u8).collect::<Vec<_>>());
+    let video = buffer.slice(100..9_000);
+    let audio = buffer.slice(9_000..19_000);
+
+    let video_result = session
+        .publish_video_data(
+            video.clone(),
+            RtmpTimestamp::new(10),
+            DropPolicy::MustDeliver,
+        )
+        .unwrap();
+    let audio_result = session
+        .publish_audio_data(
+            audio.clone(),
+            RtmpTimestamp::new(20),
+            DropPolicy::MustDeliver,
+        )
+        .unwrap();
+
+    let (responses, _) = split_results(&mut deserializer, vec![video_result, audio_result]);
+
+    assert_eq!(responses.len(), 2, "Unexpected number of responses");
+    assert_eq!(responses[0].0.type_id, 9, "Expected video message type");
+    assert_eq!(
+        &responses[0].0.data[..],
+        &video[..],
+        "Unexpected video payload"
+    );
+    assert_eq!(responses[1].0.type_id, 8, "Expected audio message type");
+    assert_eq!(
+        &responses[1].0.data[..],
+        &audio[..],
+        "Unexpected audio payload"
+    );
+}
+
 #[test]
 fn can_stop_publishing() {
     let config = ClientSessionConfig::new();
@@ -1578,6 +2324,163 @@ fn can_stop_publishing() {
     }
 }
 
+#[test]
+fn can_connect_and_publish_against_mock_server() {
+    let server = MockRtmpServer::new(ServerSessionConfig::new());
+    let mut connection = server.client_connection();
+    let (mut session, initial_results) = ClientSession::new(ClientSessionConfig::new()).unwrap();
+    exchange_with_mock_server(&mut session, &mut connection, initial_results);
+
+    let results = session.request_connection("test".to_string()).unwrap();
+    let events = exchange_with_mock_server(&mut session, &mut connection, results);
+    assert_vec_contains!(events, ClientSessionEvent::ConnectionRequestAccepted);
+
+    let result = session
+        .request_publishing("key".to_string(), PublishRequestType::Live)
+        .unwrap();
+    let events = exchange_with_mock_server(&mut session, &mut connection, vec![result]);
+    assert_vec_contains!(events, ClientSessionEvent::PublishRequestAccepted);
+
+    let server_events = server.server_read_events();
+    assert_vec_contains!(server_events, ServerSessionEvent::ConnectionRequested {
+        request_id: _,
+        ref app_name,
+        object_encoding: _,
+    } if app_name == "test");
+    assert_vec_contains!(server_events, ServerSessionEvent::PublishStreamRequested {
+        request_id: _,
+        ref app_name,
+        ref stream_key,
+        mode: _,
+        stream_id: _,
+    } if app_name == "test" && stream_key == "key");
+}
+
+#[test]
+fn subscriber_notified_of_publishing_lifecycle() {
+    let subscriber = Arc::new(RecordingSubscriber::default());
+    let mut config = ClientSessionConfig::new();
+    config.subscriber = Some(subscriber.clone());
+
+    let mut deserializer = ChunkDeserializer::new();
+    let mut serializer = ChunkSerializer::new();
+    let (mut session, initial_results) = ClientSession::new(config).unwrap();
+    consume_results(&mut deserializer, initial_results);
+
+    perform_successful_connect(
+        "test".to_string(),
+        &mut session,
+        &mut serializer,
+        &mut deserializer,
+    );
+    let _ = perform_successful_publish_request(&mut session, &mut serializer, &mut deserializer);
+
+    let _ = session
+        .publish_video_data(
+            Bytes::from(vec![1, 2, 3]),
+            RtmpTimestamp::new(10),
+            DropPolicy::MustDeliver,
+        )
+        .unwrap();
+    let _ = session
+        .publish_audio_data(
+            Bytes::from(vec![4, 5]),
+            RtmpTimestamp::new(20),
+            DropPolicy::MustDeliver,
+        )
+        .unwrap();
+    let _ = session.stop_publishing().unwrap();
+    session.notify_disconnected(Some("closed by test"));
+
+    let notifications = subscriber.notifications.lock().unwrap();
+    assert_eq!(
+        *notifications,
+        vec![
+            "connected".to_string(),
+            "publish started: abcd".to_string(),
+            "video sent: 3 bytes at 10".to_string(),
+            "audio sent: 2 bytes at 20".to_string(),
+            "publish stopped".to_string(),
+            "disconnected: Some(\"closed by test\")".to_string(),
+        ],
+        "Unexpected subscriber notifications"
+    );
+}
+
+#[test]
+fn disconnecting_while_publishing_notifies_subscriber_that_publishing_stopped() {
+    let subscriber = Arc::new(RecordingSubscriber::default());
+    let mut config = ClientSessionConfig::new();
+    config.subscriber = Some(subscriber.clone());
+
+    let mut deserializer = ChunkDeserializer::new();
+    let mut serializer = ChunkSerializer::new();
+    let (mut session, initial_results) = ClientSession::new(config).unwrap();
+    consume_results(&mut deserializer, initial_results);
+
+    perform_successful_connect(
+        "test".to_string(),
+        &mut session,
+        &mut serializer,
+        &mut deserializer,
+    );
+    let _ = perform_successful_publish_request(&mut session, &mut serializer, &mut deserializer);
+    session.notify_disconnected(None);
+
+    let notifications = subscriber.notifications.lock().unwrap();
+    assert_eq!(
+        &notifications[2..],
+        &[
+            "publish stopped".to_string(),
+            "disconnected: None".to_string()
+        ],
+        "Unexpected subscriber notifications"
+    );
+}
+
+#[derive(Default)]
+struct RecordingSubscriber {
+    notifications: Mutex<Vec<String>>,
+}
+
+impl RecordingSubscriber {
+    fn record(&self, notification: String) {
+        self.notifications.lock().unwrap().push(notification);
+    }
+}
+
+impl ClientSessionSubscriber for RecordingSubscriber {
+    fn on_connected(&self) {
+        self.record("connected".to_string());
+    }
+
+    fn on_publish_started(&self, stream_key: &str) {
+        self.record(format!("publish started: {}", stream_key));
+    }
+
+    fn on_publish_stopped(&self) {
+        self.record("publish stopped".to_string());
+    }
+
+    fn on_disconnected(&self, reason: Option<&str>) {
+        self.record(format!("disconnected: {:?}", reason));
+    }
+
+    fn on_video_sent(&self, bytes: usize, timestamp: RtmpTimestamp) {
+        self.record(format!(
+            "video sent: {} bytes at {}",
+            bytes, timestamp.value
+        ));
+    }
+
+    fn on_audio_sent(&self, bytes: usize, timestamp: RtmpTimestamp) {
+        self.record(format!(
+            "audio sent: {} bytes at {}",
+            bytes, timestamp.value
+        ));
+    }
+}
+
 fn split_results(
     deserializer: &mut ChunkDeserializer,
     mut results: Vec<ClientSessionResult>,
@@ -1618,6 +2521,35 @@ fn split_results(
     (responses, events)
 }
 
+fn exchange_with_mock_server(
+    session: &mut ClientSession,
+    connection: &mut MockRtmpConnection,
+    mut results: Vec<ClientSessionResult>,
+) -> Vec<ClientSessionEvent> {
+    // Keep passing bytes back and forth until the server has nothing left to say
+    let mut events = Vec::new();
+    loop {
+        for result in results.drain(..) {
+            match result {
+                ClientSessionResult::OutboundResponse(packet) => {
+                    connection.send_bytes(&packet.bytes)
+                }
+                ClientSessionResult::RaisedEvent(event) => events.push(event),
+                _ => (),
+            }
+        }
+
+        let bytes = connection.take_received_bytes();
+        if bytes.is_empty() {
+            break;
+        }
+
+        results = session.handle_input(&bytes).unwrap();
+    }
+
+    events
+}
+
 fn consume_results(deserializer: &mut ChunkDeserializer, results: Vec<ClientSessionResult>) {
     // Needed to keep the deserializer up to date
     split_results(deserializer, results);
@@ -1656,7 +2588,9 @@ fn get_connect_success_response(serializer: &mut ChunkSerializer) -> Packet {
     let payload = message
         .into_message_payload(RtmpTimestamp::new(0), 0)
         .unwrap();
-    serializer.serialize(&payload, false, false).unwrap()
+    serializer
+        .serialize(&payload, false, DropPolicy::MustDeliver)
+        .unwrap()
 }
 
 fn get_connect_error_response(serializer: &mut ChunkSerializer) -> Packet {
@@ -1692,7 +2626,9 @@ fn get_connect_error_response(serializer: &mut ChunkSerializer) -> Packet {
     let payload = message
         .into_message_payload(RtmpTimestamp::new(0), 0)
         .unwrap();
-    serializer.serialize(&payload, false, false).unwrap()
+    serializer
+        .serialize(&payload, false, DropPolicy::MustDeliver)
+        .unwrap()
 }
 
 fn get_create_stream_success_response(
@@ -1710,7 +2646,9 @@ fn get_create_stream_success_response(
     let payload = message
         .into_message_payload(RtmpTimestamp::new(0), 0)
         .unwrap();
-    let packet = serializer.serialize(&payload, false, false).unwrap();
+    let packet = serializer
+        .serialize(&payload, false, DropPolicy::MustDeliver)
+        .unwrap();
     (stream_id, packet)
 }
 
@@ -1739,7 +2677,9 @@ fn get_play_success_response(serializer: &mut ChunkSerializer, stream_id: u32) -
     let payload = message
         .into_message_payload(RtmpTimestamp::new(0), stream_id)
         .unwrap();
-    serializer.serialize(&payload, false, false).unwrap()
+    serializer
+        .serialize(&payload, false, DropPolicy::MustDeliver)
+        .unwrap()
 }
 
 fn get_publish_success_response(serializer: &mut ChunkSerializer, stream_id: u32) -> Packet {
@@ -1767,7 +2707,9 @@ fn get_publish_success_response(serializer: &mut ChunkSerializer, stream_id: u32
     let payload = message
         .into_message_payload(RtmpTimestamp::new(0), stream_id)
         .unwrap();
-    serializer.serialize(&payload, false, false).unwrap()
+    serializer
+        .serialize(&payload, false, DropPolicy::MustDeliver)
+        .unwrap()
 }
 
 fn perform_successful_connect(
@@ -1777,7 +2719,7 @@ fn perform_successful_connect(
     deserializer: &mut ChunkDeserializer,
 ) {
     let results = session.request_connection(app_name).unwrap();
-    consume_results(deserializer, vec![results]);
+    consume_results(deserializer, results);
 
     let response = get_connect_success_response(serializer);
     let results = session.handle_input(&response.bytes[..]).unwrap();
@@ -1793,6 +2735,101 @@ fn perform_successful_connect(
     }
 }
 
+fn receive_user_control_on_play_stream(
+    event_type: UserControlEventType,
+) -> (u32, ClientSessionEvent) {
+    let config = ClientSessionConfig::new();
+    let mut deserializer = ChunkDeserializer::new();
+    let mut serializer = ChunkSerializer::new();
+    let (mut session, initial_results) = ClientSession::new(config.clone()).unwrap();
+    consume_results(&mut deserializer, initial_results);
+
+    perform_successful_connect(
+        "test".to_string(),
+        &mut session,
+        &mut serializer,
+        &mut deserializer,
+    );
+    let stream_id =
+        perform_successful_play_request(config, &mut session, &mut serializer, &mut deserializer);
+
+    let message = RtmpMessage::UserControl {
+        event_type,
+        stream_id: Some(stream_id),
+        buffer_length: None,
+        timestamp: None,
+    };
+    let payload = message
+        .into_message_payload(RtmpTimestamp::new(0), 0)
+        .unwrap();
+    let packet = serializer
+        .serialize(&payload, false, DropPolicy::MustDeliver)
+        .unwrap();
+    let results = session.handle_input(&packet.bytes[..]).unwrap();
+    let (_, mut events) = split_results(&mut deserializer, results);
+
+    assert_eq!(events.len(), 1, "Unexpected number of events received");
+    (stream_id, events.remove(0))
+}
+
+fn receive_play_status(level: &str, code: &str) -> ClientSessionEvent {
+    let config = ClientSessionConfig::new();
+    let mut deserializer = ChunkDeserializer::new();
+    let mut serializer = ChunkSerializer::new();
+    let (mut session, initial_results) = ClientSession::new(config.clone()).unwrap();
+    consume_results(&mut deserializer, initial_results);
+
+    perform_successful_connect(
+        "test".to_string(),
+        &mut session,
+        &mut serializer,
+        &mut deserializer,
+    );
+
+    let result = session.request_playback("abcd".to_string()).unwrap();
+    let (mut responses, _) = split_results(&mut deserializer, vec![result]);
+    let transaction_id = match responses.remove(0) {
+        (_, RtmpMessage::Amf0Command { transaction_id, .. }) => transaction_id,
+        x => panic!("Unexpected response seen: {:?}", x),
+    };
+
+    let (stream_id, create_stream_response) =
+        get_create_stream_success_response(transaction_id, &mut serializer);
+    let results = session
+        .handle_input(&create_stream_response.bytes[..])
+        .unwrap();
+    consume_results(&mut deserializer, results);
+
+    let mut properties = HashMap::new();
+    properties.insert(
+        "level".to_string(),
+        Amf0Value::Utf8String(level.to_string()),
+    );
+    properties.insert("code".to_string(), Amf0Value::Utf8String(code.to_string()));
+    properties.insert(
+        "description".to_string(),
+        Amf0Value::Utf8String("some description".to_string()),
+    );
+
+    let message = RtmpMessage::Amf0Command {
+        command_name: "onStatus".to_string(),
+        transaction_id: 0.0,
+        command_object: Amf0Value::Null,
+        additional_arguments: vec![Amf0Value::Object(properties)],
+    };
+    let payload = message
+        .into_message_payload(RtmpTimestamp::new(0), stream_id)
+        .unwrap();
+    let packet = serializer
+        .serialize(&payload, false, DropPolicy::MustDeliver)
+        .unwrap();
+    let results = session.handle_input(&packet.bytes[..]).unwrap();
+    let (_, mut events) = split_results(&mut deserializer, results);
+
+    assert_eq!(events.len(), 1, "Unexpected number of events received");
+    events.remove(0)
+}
+
 fn perform_successful_play_request(
     config: ClientSessionConfig,
     session: &mut ClientSession,
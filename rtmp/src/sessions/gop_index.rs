@@ -0,0 +1,166 @@
+use time::RtmpTimestamp;
+
+/// A keyframe that has been recorded in a `GopIndex`
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+pub struct GopEntry {
+    /// The timestamp of the keyframe
+    pub timestamp: RtmpTimestamp,
+
+    /// Where the keyframe starts in the buffered stream, in whatever unit of offset the
+    /// application uses for its buffer
+    pub byte_offset: u64,
+}
+
+/// Tracks where each keyframe (and thus each group of pictures) starts in a buffered stream, so
+/// relay and VOD servers can quickly find the keyframe to start sending from when seeking or
+/// when a player joins a stream that is already in progress.
+///
+/// Entries are kept ordered by timestamp, so lookups are a binary search.  Timestamps are
+/// compared with `RtmpTimestamp`'s wrapping rules, so the index should not hold entries spanning
+/// more than 2<sup>31</sup> - 1 milliseconds.
+///
+/// ```
+/// use rml_rtmp::sessions::GopIndex;
+/// use rml_rtmp::time::RtmpTimestamp;
+///
+/// let mut index = GopIndex::new();
+/// index.record_frame(RtmpTimestamp::new(0), true, 0);
+/// index.record_frame(RtmpTimestamp::new(33), false, 5000);
+/// index.record_frame(RtmpTimestamp::new(2000), true, 90000);
+///
+/// let keyframe = index.find_nearest_keyframe(RtmpTimestamp::new(1500)).unwrap();
+/// assert_eq!(keyframe.byte_offset, 0);
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct GopIndex {
+    entries: Vec<GopEntry>,
+}
+
+impl GopIndex {
+    /// Creates an index without any keyframes
+    pub fn new() -> GopIndex {
+        GopIndex {
+            entries: Vec::new(),
+        }
+    }
+
+    /// Records a video frame that has been added to the buffered stream.  Only keyframes are
+    /// added to the index, so all frames can be passed in as they are received.
+    pub fn record_frame(&mut self, timestamp: RtmpTimestamp, is_keyframe: bool, byte_offset: u64) {
+        if !is_keyframe {
+            return;
+        }
+
+        let entry = GopEntry {
+            timestamp,
+            byte_offset,
+        };
+
+        // Frames almost always arrive in timestamp order, but keep the index sorted if not
+        let index = self
+            .entries
+            .partition_point(|existing| existing.timestamp <= timestamp);
+        self.entries.insert(index, entry);
+    }
+
+    /// Returns the latest keyframe at or before the target timestamp, which is where playback
+    /// has to start from to show the frame at that timestamp.  Returns `None` if no keyframe
+    /// has been recorded at or before the target.
+    pub fn find_nearest_keyframe(&self, target_timestamp: RtmpTimestamp) -> Option<GopEntry> {
+        let index = self
+            .entries
+            .partition_point(|entry| entry.timestamp <= target_timestamp);
+
+        match index {
+            0 => None,
+            x => Some(self.entries[x - 1]),
+        }
+    }
+
+    /// Returns the most recently recorded keyframe
+    pub fn latest_keyframe(&self) -> Option<GopEntry> {
+        self.entries.last().copied()
+    }
+
+    /// Removes all keyframes with a timestamp before the specified one, for when the start of
+    /// the buffered stream has been discarded
+    pub fn remove_entries_before(&mut self, timestamp: RtmpTimestamp) {
+        let index = self
+            .entries
+            .partition_point(|entry| entry.timestamp < timestamp);
+        self.entries.drain(..index);
+    }
+
+    /// Returns the number of keyframes in the index
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Returns true if no keyframes have been recorded
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finds_nearest_preceding_keyframe() {
+        let mut index = GopIndex::new();
+        for (timestamp, is_keyframe, byte_offset) in &[
+            (0, true, 0),
+            (33, false, 100),
+            (66, false, 200),
+            (1000, true, 300),
+            (1033, false, 400),
+            (2000, true, 500),
+        ] {
+            index.record_frame(RtmpTimestamp::new(*timestamp), *is_keyframe, *byte_offset);
+        }
+
+        assert_eq!(index.len(), 3, "Only keyframes should be indexed");
+
+        let offset_at = |timestamp| {
+            index
+                .find_nearest_keyframe(RtmpTimestamp::new(timestamp))
+                .map(|entry| entry.byte_offset)
+        };
+
+        assert_eq!(offset_at(0), Some(0));
+        assert_eq!(offset_at(999), Some(0));
+        assert_eq!(offset_at(1000), Some(300));
+        assert_eq!(offset_at(1500), Some(300));
+        assert_eq!(offset_at(5000), Some(500));
+    }
+
+    #[test]
+    fn no_keyframe_found_before_first_entry() {
+        let mut index = GopIndex::new();
+        assert_eq!(index.find_nearest_keyframe(RtmpTimestamp::new(10)), None);
+
+        index.record_frame(RtmpTimestamp::new(500), true, 0);
+        assert_eq!(index.find_nearest_keyframe(RtmpTimestamp::new(499)), None);
+    }
+
+    #[test]
+    fn can_remove_entries_before_timestamp() {
+        let mut index = GopIndex::new();
+        index.record_frame(RtmpTimestamp::new(0), true, 0);
+        index.record_frame(RtmpTimestamp::new(1000), true, 100);
+        index.record_frame(RtmpTimestamp::new(2000), true, 200);
+
+        index.remove_entries_before(RtmpTimestamp::new(1000));
+
+        assert_eq!(index.len(), 2, "Unexpected number of entries");
+        assert_eq!(index.find_nearest_keyframe(RtmpTimestamp::new(500)), None);
+        assert_eq!(
+            index.latest_keyframe(),
+            Some(GopEntry {
+                timestamp: RtmpTimestamp::new(2000),
+                byte_offset: 200,
+            })
+        );
+    }
+}
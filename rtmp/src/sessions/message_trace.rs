@@ -0,0 +1,88 @@
+use messages::MessagePayload;
+use time::RtmpTimestamp;
+
+/// Which way a traced message was travelling, relative to the session that traced it
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+pub enum Direction {
+    /// The message was received from the peer
+    Inbound,
+
+    /// The message is being sent to the peer
+    Outbound,
+}
+
+/// Describes a single RTMP message that a session has received or sent, for debugging
+/// interoperability issues without having to capture and decode the raw connection.
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub struct MessageTrace {
+    /// Whether the message was received or sent
+    pub direction: Direction,
+
+    /// The name of the message's type, such as `Amf0Command` or `VideoData`
+    pub message_type: &'static str,
+
+    /// The timestamp of the message
+    pub timestamp: RtmpTimestamp,
+
+    /// The message stream id the message was sent on
+    pub stream_id: u32,
+
+    /// The size of the message.  For inbound messages this is the size of the deserialized
+    /// payload, while for outbound messages it is the size of the serialized packet including
+    /// its chunk headers.
+    pub byte_count: usize,
+}
+
+impl MessageTrace {
+    /// Creates a trace of the specified message payload
+    pub fn new(direction: Direction, payload: &MessagePayload, byte_count: usize) -> MessageTrace {
+        MessageTrace {
+            direction,
+            message_type: message_type_name(payload.type_id),
+            timestamp: payload.timestamp,
+            stream_id: payload.message_stream_id,
+            byte_count,
+        }
+    }
+}
+
+fn message_type_name(type_id: u8) -> &'static str {
+    match type_id {
+        1 => "SetChunkSize",
+        2 => "Abort",
+        3 => "Acknowledgement",
+        4 => "UserControl",
+        5 => "WindowAcknowledgement",
+        6 => "SetPeerBandwidth",
+        8 => "AudioData",
+        9 => "VideoData",
+        15 | 18 => "Amf0Data",
+        17 | 20 => "Amf0Command",
+        19 => "SharedObject",
+        22 => "Aggregate",
+        _ => "Unknown",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bytes::Bytes;
+
+    #[test]
+    fn trace_describes_payload() {
+        let payload = MessagePayload::new(9, RtmpTimestamp::new(1234), 5, Bytes::from(vec![1, 2]));
+        let trace = MessageTrace::new(Direction::Outbound, &payload, 14);
+
+        assert_eq!(
+            trace,
+            MessageTrace {
+                direction: Direction::Outbound,
+                message_type: "VideoData",
+                timestamp: RtmpTimestamp::new(1234),
+                stream_id: 5,
+                byte_count: 14,
+            }
+        );
+    }
+}
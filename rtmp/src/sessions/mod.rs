@@ -10,9 +10,16 @@ A single session represents a single peer in an RTMP connection, so if multiple
 being managed (in any direction) each connection should have its own, distinct, session instance.
 
 It is also expected that a session has been created *after* handshaking has been completed.
+
+Servers hosting multiple RTMP applications can use the `RtmpApplicationRouter` to create each
+connection's session with the configuration of the application it connects to.
 */
 
 mod client;
+mod gop_index;
+mod message_trace;
+mod router;
+mod rtmp_session;
 mod server;
 
 pub use self::client::ClientSession;
@@ -20,33 +27,119 @@ pub use self::client::ClientSessionConfig;
 pub use self::client::ClientSessionError;
 pub use self::client::ClientSessionEvent;
 pub use self::client::ClientSessionResult;
+pub use self::client::ClientSessionSubscriber;
 pub use self::client::ClientState;
 pub use self::client::PublishRequestType;
 
+pub use self::gop_index::GopEntry;
+pub use self::gop_index::GopIndex;
+
+pub use self::message_trace::Direction;
+pub use self::message_trace::MessageTrace;
+
+pub use self::router::RtmpApplicationRouter;
+pub use self::router::RtmpApplicationRouterError;
+pub use self::router::MAX_BYTES_BEFORE_CONNECT;
+
+pub use self::rtmp_session::BoxedError;
+pub use self::rtmp_session::RtmpSession;
+pub use self::rtmp_session::SessionOutput;
+
+pub use self::server::ConnectionAuthCallback;
+pub use self::server::LoggingMiddleware;
+pub use self::server::MiddlewareDecision;
+pub use self::server::PlayStartValue;
 pub use self::server::PublishMode;
+pub use self::server::RequestId;
+pub use self::server::ServerMiddleware;
 pub use self::server::ServerSession;
 pub use self::server::ServerSessionConfig;
 pub use self::server::ServerSessionError;
 pub use self::server::ServerSessionEvent;
 pub use self::server::ServerSessionResult;
+pub use self::server::VideoStreamSender;
 
 use rml_amf0::Amf0Value;
 use std::collections::HashMap;
 
-/// Contains the metadata information a stream may advertise on publishing
+/// Contains the metadata information a stream may advertise on publishing.  With the `serde`
+/// feature enabled, fields without a value are left out when serialized.
 #[derive(PartialEq, Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(::serde::Serialize, ::serde::Deserialize))]
 pub struct StreamMetadata {
+    /// The width of the video in pixels (the `width` property)
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
     pub video_width: Option<u32>,
+
+    /// The height of the video in pixels (the `height` property)
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
     pub video_height: Option<u32>,
+
+    /// The FLV codec id of the video (the `videocodecid` property, when it is a number)
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
     pub video_codec_id: Option<u32>,
+
+    /// The name of the video codec, such as `avc1` (the `videocodecid` property, when it is a
+    /// string)
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
+    pub video_codec_name: Option<String>,
+
+    /// The number of video frames per second (the `framerate` property)
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
     pub video_frame_rate: Option<f32>,
+
+    /// The video bitrate in kilobits per second (the `videodatarate` property)
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
     pub video_bitrate_kbps: Option<u32>,
+
+    /// The FLV codec id of the audio (the `audiocodecid` property, when it is a number)
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
     pub audio_codec_id: Option<u32>,
+
+    /// The name of the audio codec, such as `mp4a` (the `audiocodecid` property, when it is a
+    /// string)
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
+    pub audio_codec_name: Option<String>,
+
+    /// The audio bitrate in kilobits per second (the `audiodatarate` property)
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
     pub audio_bitrate_kbps: Option<u32>,
+
+    /// The number of audio samples per second (the `audiosamplerate` property)
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
     pub audio_sample_rate: Option<u32>,
+
+    /// The number of bits in each audio sample, usually 8 or 16 (the `audiosamplesize` property)
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
+    pub audio_sample_size: Option<u32>,
+
+    /// The number of audio channels (the `audiochannels` property)
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
     pub audio_channels: Option<u32>,
+
+    /// Whether the audio is in stereo (the `stereo` property)
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
     pub audio_is_stereo: Option<bool>,
+
+    /// The name of the software that encoded the stream (the `encoder` property)
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
     pub encoder: Option<String>,
+
+    /// The number of bits per color component (the `colorDepth` property)
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
+    pub color_depth: Option<u32>,
+
+    /// The color matrix coefficients, as defined by ITU-T H.273 (the `colorMatrixCoefficients` property)
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
+    pub color_matrix_coefficients: Option<u32>,
+
+    /// The transfer characteristics, as defined by ITU-T H.273 (the `colorTransferCharacteristics` property)
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
+    pub color_transfer_characteristics: Option<u32>,
+
+    /// The color primaries, as defined by ITU-T H.273 (the `colorPrimaries` property)
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
+    pub color_primaries: Option<u32>,
 }
 
 impl StreamMetadata {
@@ -56,14 +149,21 @@ impl StreamMetadata {
             video_width: None,
             video_height: None,
             video_codec_id: None,
+            video_codec_name: None,
             video_frame_rate: None,
             video_bitrate_kbps: None,
             audio_codec_id: None,
+            audio_codec_name: None,
             audio_bitrate_kbps: None,
             audio_sample_rate: None,
+            audio_sample_size: None,
             audio_channels: None,
             audio_is_stereo: None,
             encoder: None,
+            color_depth: None,
+            color_matrix_coefficients: None,
+            color_transfer_characteristics: None,
+            color_primaries: None,
         }
     }
 
@@ -83,9 +183,11 @@ impl StreamMetadata {
                     None => (),
                 },
 
-                "videocodecid" => match value.get_number() {
-                    Some(x) => self.video_codec_id = Some(x as u32),
-                    None => (),
+                // Some encoders identify codecs by name instead of by FLV codec id
+                "videocodecid" => match value {
+                    Amf0Value::Number(x) => self.video_codec_id = Some(x as u32),
+                    Amf0Value::Utf8String(x) => self.video_codec_name = Some(x),
+                    _ => (),
                 },
 
                 "videodatarate" => match value.get_number() {
@@ -98,9 +200,10 @@ impl StreamMetadata {
                     None => (),
                 },
 
-                "audiocodecid" => match value.get_number() {
-                    Some(x) => self.audio_codec_id = Some(x as u32),
-                    None => (),
+                "audiocodecid" => match value {
+                    Amf0Value::Number(x) => self.audio_codec_id = Some(x as u32),
+                    Amf0Value::Utf8String(x) => self.audio_codec_name = Some(x),
+                    _ => (),
                 },
 
                 "audiodatarate" => match value.get_number() {
@@ -113,6 +216,12 @@ impl StreamMetadata {
                     None => (),
                 },
 
+                "audiosamplesize" => {
+                    if let Some(x) = value.get_u32() {
+                        self.audio_sample_size = Some(x);
+                    }
+                }
+
                 "audiochannels" => match value.get_number() {
                     Some(x) => self.audio_channels = Some(x as u32),
                     None => (),
@@ -128,8 +237,177 @@ impl StreamMetadata {
                     None => (),
                 },
 
+                "colorDepth" => {
+                    if let Some(x) = value.get_u32() {
+                        self.color_depth = Some(x);
+                    }
+                }
+
+                "colorMatrixCoefficients" => {
+                    if let Some(x) = value.get_u32() {
+                        self.color_matrix_coefficients = Some(x);
+                    }
+                }
+
+                "colorTransferCharacteristics" => {
+                    if let Some(x) = value.get_u32() {
+                        self.color_transfer_characteristics = Some(x);
+                    }
+                }
+
+                "colorPrimaries" => {
+                    if let Some(x) = value.get_u32() {
+                        self.color_primaries = Some(x);
+                    }
+                }
+
                 _ => (),
             }
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn can_apply_numeric_and_named_codec_values() {
+        let mut properties = HashMap::new();
+        properties.insert("videocodecid".to_string(), Amf0Value::Number(7.0));
+        properties.insert(
+            "audiocodecid".to_string(),
+            Amf0Value::Utf8String("mp4a".to_string()),
+        );
+
+        let mut metadata = StreamMetadata::new();
+        metadata.apply_metadata_values(properties);
+
+        let mut properties = HashMap::new();
+        properties.insert(
+            "videocodecid".to_string(),
+            Amf0Value::Utf8String("avc1".to_string()),
+        );
+        properties.insert("audiocodecid".to_string(), Amf0Value::Number(10.0));
+        metadata.apply_metadata_values(properties);
+
+        assert_eq!(
+            metadata.video_codec_id,
+            Some(7),
+            "Unexpected video codec id"
+        );
+        assert_eq!(
+            metadata.video_codec_name,
+            Some("avc1".to_string()),
+            "Unexpected video codec name"
+        );
+        assert_eq!(
+            metadata.audio_codec_id,
+            Some(10),
+            "Unexpected audio codec id"
+        );
+        assert_eq!(
+            metadata.audio_codec_name,
+            Some("mp4a".to_string()),
+            "Unexpected audio codec name"
+        );
+    }
+
+    #[test]
+    fn can_apply_color_metadata_values() {
+        let mut properties = HashMap::new();
+        properties.insert("colorDepth".to_string(), Amf0Value::Number(10.0));
+        properties.insert(
+            "colorMatrixCoefficients".to_string(),
+            Amf0Value::Number(9.0),
+        );
+        properties.insert(
+            "colorTransferCharacteristics".to_string(),
+            Amf0Value::Number(16.0),
+        );
+        properties.insert("colorPrimaries".to_string(), Amf0Value::Number(9.0));
+
+        let mut metadata = StreamMetadata::new();
+        metadata.apply_metadata_values(properties);
+
+        assert_eq!(metadata.color_depth, Some(10), "Unexpected color depth");
+        assert_eq!(
+            metadata.color_matrix_coefficients,
+            Some(9),
+            "Unexpected matrix coefficients"
+        );
+        assert_eq!(
+            metadata.color_transfer_characteristics,
+            Some(16),
+            "Unexpected transfer characteristics"
+        );
+        assert_eq!(
+            metadata.color_primaries,
+            Some(9),
+            "Unexpected color primaries"
+        );
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn fully_populated_metadata_round_trips_through_json() {
+        let metadata = StreamMetadata {
+            video_width: Some(1920),
+            video_height: Some(1080),
+            video_codec_id: Some(7),
+            video_codec_name: Some("avc1".to_string()),
+            video_frame_rate: Some(29.97),
+            video_bitrate_kbps: Some(6000),
+            audio_codec_id: Some(10),
+            audio_codec_name: Some("mp4a".to_string()),
+            audio_bitrate_kbps: Some(160),
+            audio_sample_rate: Some(48000),
+            audio_sample_size: Some(16),
+            audio_channels: Some(2),
+            audio_is_stereo: Some(true),
+            encoder: Some("obs-output module".to_string()),
+            color_depth: Some(10),
+            color_matrix_coefficients: Some(9),
+            color_transfer_characteristics: Some(16),
+            color_primaries: Some(9),
+        };
+
+        let json = ::serde_json::to_string(&metadata).unwrap();
+        let result: StreamMetadata = ::serde_json::from_str(&json).unwrap();
+
+        assert_eq!(result, metadata);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn empty_metadata_fields_are_left_out_of_json() {
+        let mut metadata = StreamMetadata::new();
+        metadata.video_width = Some(640);
+
+        let json = ::serde_json::to_string(&metadata).unwrap();
+        assert_eq!(json, "{\"video_width\":640}");
+
+        let result: StreamMetadata = ::serde_json::from_str(&json).unwrap();
+        assert_eq!(result, metadata);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn publish_mode_and_timestamp_serialize_as_plain_values() {
+        use time::RtmpTimestamp;
+
+        let json = ::serde_json::to_string(&PublishMode::Append).unwrap();
+        assert_eq!(json, "\"append\"");
+        assert_eq!(
+            ::serde_json::from_str::<PublishMode>("\"record\"").unwrap(),
+            PublishMode::Record
+        );
+
+        let json = ::serde_json::to_string(&RtmpTimestamp::new(1234)).unwrap();
+        assert_eq!(json, "1234");
+        assert_eq!(
+            ::serde_json::from_str::<RtmpTimestamp>("1234").unwrap(),
+            RtmpTimestamp::new(1234)
+        );
+    }
+}
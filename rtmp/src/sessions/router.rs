@@ -0,0 +1,379 @@
+use chunk_io::ChunkDeserializer;
+use messages::RtmpMessage;
+use rml_amf0::Amf0Value;
+use sessions::{ServerSession, ServerSessionConfig, ServerSessionError, ServerSessionResult};
+use std::collections::HashMap;
+use thiserror::Error;
+
+/// The most bytes that will be held for a connection while waiting for its `connect` command.
+/// Clients only send a handful of small messages before connecting, so a connection exceeding
+/// this is not going to connect.
+pub const MAX_BYTES_BEFORE_CONNECT: usize = 64 * 1024;
+
+/// Errors that can occur when routing bytes to a server session
+#[derive(Debug, Error)]
+pub enum RtmpApplicationRouterError {
+    /// The client requested a connection to an application that has not been registered
+    #[error("No application has been registered with the name '{app_name}'")]
+    UnknownApplication {
+        /// The name of the application the client requested
+        app_name: String,
+    },
+
+    /// The connection sent more bytes than are held while waiting for a `connect` command
+    #[error("More than {limit} bytes were received before a connect command")]
+    TooManyBytesBeforeConnect {
+        /// The most bytes that are held before a `connect` command is received
+        limit: usize,
+    },
+
+    /// The server session handling the connection encountered an error
+    #[error("{0}")]
+    SessionError(#[from] ServerSessionError),
+}
+
+/// Routes connections to server sessions based on the RTMP application they connect to, so a
+/// single server can host multiple applications (such as `live` and `recordings`) that each
+/// have their own session configuration.
+///
+/// Since the application name is only known once the client's `connect` command has been
+/// received, bytes for a new connection are held until that command has arrived.  A server
+/// session is then created with the application's configuration and all held bytes are passed
+/// to it, so the responses the session sends on creation are returned along with its response
+/// to the `connect` command.  If the `connect` command can't be routed, or more than
+/// `MAX_BYTES_BEFORE_CONNECT` bytes arrive before it, an error is returned and the held bytes
+/// are discarded.
+pub struct RtmpApplicationRouter {
+    apps: HashMap<String, ServerSessionConfig>,
+    connections: HashMap<usize, RoutedConnection>,
+}
+
+enum RoutedConnection {
+    AwaitingConnect {
+        received_bytes: Vec<u8>,
+        deserializer: Box<ChunkDeserializer>,
+    },
+
+    Routed {
+        app_name: String,
+        session: Box<ServerSession>,
+    },
+}
+
+impl RtmpApplicationRouter {
+    /// Creates a router without any registered applications
+    pub fn new() -> RtmpApplicationRouter {
+        RtmpApplicationRouter {
+            apps: HashMap::new(),
+            connections: HashMap::new(),
+        }
+    }
+
+    /// Allows clients to connect to the specified application, with their sessions using the
+    /// specified configuration.  Registering an application that is already registered replaces
+    /// the configuration used for new connections.
+    pub fn register_app(&mut self, name: &str, config: ServerSessionConfig) {
+        self.apps.insert(name.to_string(), config);
+    }
+
+    /// Stops new connections to the specified application from being accepted.  Connections
+    /// that have already been routed to the application keep their sessions until they are
+    /// removed.
+    pub fn deregister_app(&mut self, name: &str) {
+        self.apps.remove(name);
+    }
+
+    /// Passes bytes received from a connection (after handshaking has completed) to the server
+    /// session of the application it connected to.
+    pub fn handle_input(
+        &mut self,
+        connection_id: usize,
+        bytes: &[u8],
+    ) -> Result<Vec<ServerSessionResult>, RtmpApplicationRouterError> {
+        let connection = self.connections.entry(connection_id).or_insert_with(|| {
+            RoutedConnection::AwaitingConnect {
+                received_bytes: Vec::new(),
+                deserializer: Box::new(ChunkDeserializer::new()),
+            }
+        });
+
+        let connect_app_name = match *connection {
+            RoutedConnection::Routed {
+                ref mut session, ..
+            } => return Ok(session.handle_input(bytes)?),
+
+            RoutedConnection::AwaitingConnect {
+                ref mut received_bytes,
+                ref mut deserializer,
+            } => {
+                if received_bytes.len() + bytes.len() > MAX_BYTES_BEFORE_CONNECT {
+                    Err(RtmpApplicationRouterError::TooManyBytesBeforeConnect {
+                        limit: MAX_BYTES_BEFORE_CONNECT,
+                    })
+                } else {
+                    received_bytes.extend_from_slice(bytes);
+                    find_connect_app_name(deserializer, bytes).map_err(|error| error.into())
+                }
+            }
+        };
+
+        // The connection either gets routed or fails from here, so the held bytes are no
+        // longer needed for it
+        let app_name = match connect_app_name {
+            Ok(Some(app_name)) => app_name,
+            Ok(None) => return Ok(Vec::new()),
+            Err(error) => {
+                self.connections.remove(&connection_id);
+                return Err(error);
+            }
+        };
+
+        let received_bytes = match self.connections.remove(&connection_id) {
+            Some(RoutedConnection::AwaitingConnect { received_bytes, .. }) => received_bytes,
+            _ => Vec::new(),
+        };
+
+        let config = match self.apps.get(&app_name) {
+            Some(config) => config.clone(),
+            None => return Err(RtmpApplicationRouterError::UnknownApplication { app_name }),
+        };
+
+        let (mut session, mut results) = ServerSession::new(config)?;
+        results.extend(session.handle_input(&received_bytes)?);
+        self.connections.insert(
+            connection_id,
+            RoutedConnection::Routed {
+                app_name,
+                session: Box::new(session),
+            },
+        );
+
+        Ok(results)
+    }
+
+    /// Returns the session of a connection to the specified application.  When multiple
+    /// connections have been routed to the application the one with the lowest connection id
+    /// is returned, so `connection_session()` should be used to address a specific connection.
+    pub fn route_connection(&mut self, app_name: &str) -> Option<&mut ServerSession> {
+        let connection_id = self
+            .connections
+            .iter()
+            .filter_map(|(id, connection)| match *connection {
+                RoutedConnection::Routed {
+                    app_name: ref name, ..
+                } if name == app_name => Some(*id),
+                _ => None,
+            })
+            .min()?;
+
+        self.connection_session(connection_id)
+    }
+
+    /// Returns the session of the specified connection, if it has been routed to an application
+    pub fn connection_session(&mut self, connection_id: usize) -> Option<&mut ServerSession> {
+        match self.connections.get_mut(&connection_id) {
+            Some(RoutedConnection::Routed {
+                ref mut session, ..
+            }) => Some(&mut **session),
+            _ => None,
+        }
+    }
+
+    /// Returns the name of the application the specified connection has been routed to
+    pub fn connection_app_name(&self, connection_id: usize) -> Option<&str> {
+        match self.connections.get(&connection_id) {
+            Some(RoutedConnection::Routed { ref app_name, .. }) => Some(app_name),
+            _ => None,
+        }
+    }
+
+    /// Removes all state for a connection that has been closed
+    pub fn remove_connection(&mut self, connection_id: usize) {
+        self.connections.remove(&connection_id);
+    }
+}
+
+impl Default for RtmpApplicationRouter {
+    fn default() -> Self {
+        RtmpApplicationRouter::new()
+    }
+}
+
+fn find_connect_app_name(
+    deserializer: &mut ChunkDeserializer,
+    bytes: &[u8],
+) -> Result<Option<String>, ServerSessionError> {
+    let mut bytes_to_process = bytes;
+    while let Some(payload) = deserializer.get_next_message(bytes_to_process)? {
+        bytes_to_process = &[];
+        match payload.to_rtmp_message()? {
+            RtmpMessage::SetChunkSize { size } => deserializer.set_max_chunk_size(size as usize)?,
+            RtmpMessage::Amf0Command {
+                ref command_name,
+                command_object: Amf0Value::Object(ref properties),
+                ..
+            } if command_name == "connect" => {
+                return match properties.get("app").and_then(|value| value.as_str()) {
+                    Some(app) => Ok(Some(app.strip_suffix('/').unwrap_or(app).to_string())),
+                    None => Err(ServerSessionError::NoAppNameForConnectionRequest),
+                };
+            }
+
+            _ => (),
+        }
+    }
+
+    Ok(None)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bytes::Bytes;
+    use chunk_io::{ChunkSerializer, DropPolicy};
+    use sessions::{RequestId, ServerSessionEvent};
+    use time::RtmpTimestamp;
+
+    #[test]
+    fn connections_are_routed_to_the_session_of_their_app() {
+        let mut router = RtmpApplicationRouter::new();
+        router.register_app("live", ServerSessionConfig::new());
+        router.register_app("recordings", ServerSessionConfig::new());
+
+        let live_request_id = assert_connection_requested(&mut router, 1, "live");
+        let recordings_request_id = assert_connection_requested(&mut router, 2, "recordings");
+
+        assert_eq!(router.connection_app_name(1), Some("live"));
+        assert_eq!(router.connection_app_name(2), Some("recordings"));
+
+        router
+            .route_connection("live")
+            .expect("No session for live app")
+            .accept_request(live_request_id)
+            .unwrap();
+
+        router
+            .route_connection("recordings")
+            .expect("No session for recordings app")
+            .accept_request(recordings_request_id)
+            .unwrap();
+    }
+
+    #[test]
+    fn connecting_to_unregistered_app_returns_error() {
+        let mut router = RtmpApplicationRouter::new();
+        router.register_app("live", ServerSessionConfig::new());
+        router.deregister_app("live");
+
+        match router.handle_input(1, &create_connect_bytes("live")) {
+            Err(RtmpApplicationRouterError::UnknownApplication { ref app_name })
+                if app_name == "live" => {}
+            x => panic!("Expected unknown application error, got {:?}", x),
+        }
+
+        assert!(
+            router.connections.is_empty(),
+            "Connection was not removed after failing to route"
+        );
+    }
+
+    #[test]
+    fn error_when_too_many_bytes_received_before_connect() {
+        let mut router = RtmpApplicationRouter::new();
+        router.register_app("live", ServerSessionConfig::new());
+
+        // Video data is sent instead of a connect command
+        let message = RtmpMessage::VideoData {
+            data: Bytes::from(vec![0x17; 1000]),
+        };
+
+        let payload = message
+            .into_message_payload(RtmpTimestamp::new(0), 1)
+            .unwrap();
+
+        let mut serializer = ChunkSerializer::new();
+        let mut total_bytes = 0;
+        loop {
+            let packet = serializer
+                .serialize(&payload, false, DropPolicy::MustDeliver)
+                .unwrap();
+
+            total_bytes += packet.bytes.len();
+            match router.handle_input(1, &packet.bytes) {
+                Ok(ref results) if results.is_empty() => {
+                    assert!(
+                        total_bytes <= MAX_BYTES_BEFORE_CONNECT,
+                        "No error after {} bytes",
+                        total_bytes
+                    );
+                }
+
+                Err(RtmpApplicationRouterError::TooManyBytesBeforeConnect { limit }) => {
+                    assert_eq!(limit, MAX_BYTES_BEFORE_CONNECT, "Unexpected limit");
+                    break;
+                }
+
+                x => panic!("Expected too many bytes error, got {:?}", x),
+            }
+        }
+
+        assert!(
+            router.connections.is_empty(),
+            "Connection was not removed after exceeding the limit"
+        );
+    }
+
+    fn assert_connection_requested(
+        router: &mut RtmpApplicationRouter,
+        connection_id: usize,
+        expected_app_name: &str,
+    ) -> RequestId {
+        let results = router
+            .handle_input(connection_id, &create_connect_bytes(expected_app_name))
+            .unwrap();
+
+        let mut events = results.into_iter().filter_map(|result| match result {
+            ServerSessionResult::RaisedEvent(event) => Some(event),
+            _ => None,
+        });
+
+        match events.next() {
+            Some(ServerSessionEvent::ConnectionRequested {
+                request_id,
+                app_name,
+                ..
+            }) => {
+                assert_eq!(app_name, expected_app_name, "Unexpected app name");
+                request_id
+            }
+
+            x => panic!("Expected connection requested event, got {:?}", x),
+        }
+    }
+
+    fn create_connect_bytes(app_name: &str) -> Vec<u8> {
+        let mut properties = HashMap::new();
+        properties.insert(
+            "app".to_string(),
+            Amf0Value::Utf8String(app_name.to_string()),
+        );
+
+        let message = RtmpMessage::Amf0Command {
+            command_name: "connect".to_string(),
+            transaction_id: 1.0,
+            command_object: Amf0Value::Object(properties),
+            additional_arguments: vec![],
+        };
+
+        let payload = message
+            .into_message_payload(RtmpTimestamp::new(0), 0)
+            .unwrap();
+
+        let mut serializer = ChunkSerializer::new();
+        let packet = serializer
+            .serialize(&payload, false, DropPolicy::MustDeliver)
+            .unwrap();
+
+        packet.bytes.to_vec()
+    }
+}
@@ -0,0 +1,131 @@
+use chunk_io::Packet;
+use sessions::{ClientSession, ClientSessionResult, ServerSession, ServerSessionResult};
+use std::any::Any;
+use std::error::Error;
+
+/// An error returned by a session, boxed so callers don't need to know which type of session
+/// they are working with.
+pub type BoxedError = Box<dyn Error + Send + Sync>;
+
+/// An output of a session that is not specific to the type of session that produced it
+pub enum SessionOutput {
+    /// A packet that needs to be sent to the peer.  Like the `OutboundResponse` results of each
+    /// session, these must be sent in the order they are returned.
+    Send(Packet),
+
+    /// Something the application may want to act on.  This is a `ServerSessionEvent` or
+    /// `ClientSessionEvent` for raised events, or the `MessagePayload` of a message the session
+    /// could not handle, and can be recovered with `downcast()`.
+    Event(Box<dyn Any + Send>),
+}
+
+/// The bytes in, bytes out interface shared by `ServerSession` and `ClientSession`.  This allows
+/// the code moving bytes between a connection and its session to be written once for both
+/// session types, with only the handling of events needing to know which type it has.
+///
+/// ```
+/// use rml_rtmp::sessions::{BoxedError, RtmpSession, SessionOutput};
+/// use std::io::Write;
+///
+/// fn handle_bytes(
+///     session: &mut dyn RtmpSession,
+///     bytes: &[u8],
+///     connection: &mut dyn Write,
+/// ) -> Result<(), BoxedError> {
+///     for output in session.process_bytes(bytes)? {
+///         match output {
+///             SessionOutput::Send(packet) => connection.write_all(&packet.bytes)?,
+///             SessionOutput::Event(_) => (),
+///         }
+///     }
+///
+///     Ok(())
+/// }
+/// ```
+pub trait RtmpSession {
+    /// Passes bytes received from the peer to the session, returning everything the session
+    /// produced in response to them.
+    fn process_bytes(&mut self, data: &[u8]) -> Result<Vec<SessionOutput>, BoxedError>;
+}
+
+impl RtmpSession for ServerSession {
+    fn process_bytes(&mut self, data: &[u8]) -> Result<Vec<SessionOutput>, BoxedError> {
+        let results = self.handle_input(data)?;
+        Ok(results.into_iter().map(SessionOutput::from).collect())
+    }
+}
+
+impl RtmpSession for ClientSession {
+    fn process_bytes(&mut self, data: &[u8]) -> Result<Vec<SessionOutput>, BoxedError> {
+        let results = self.handle_input(data)?;
+        Ok(results.into_iter().map(SessionOutput::from).collect())
+    }
+}
+
+impl From<ServerSessionResult> for SessionOutput {
+    fn from(result: ServerSessionResult) -> Self {
+        match result {
+            ServerSessionResult::OutboundResponse(packet) => SessionOutput::Send(packet),
+            ServerSessionResult::RaisedEvent(event) => SessionOutput::Event(Box::new(event)),
+            ServerSessionResult::UnhandleableMessageReceived(payload) => {
+                SessionOutput::Event(Box::new(payload))
+            }
+        }
+    }
+}
+
+impl From<ClientSessionResult> for SessionOutput {
+    fn from(result: ClientSessionResult) -> Self {
+        match result {
+            ClientSessionResult::OutboundResponse(packet) => SessionOutput::Send(packet),
+            ClientSessionResult::RaisedEvent(event) => SessionOutput::Event(Box::new(event)),
+            ClientSessionResult::UnhandleableMessageReceived(payload) => {
+                SessionOutput::Event(Box::new(payload))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sessions::{ClientSessionConfig, ServerSessionConfig, ServerSessionEvent};
+
+    #[test]
+    fn server_and_client_sessions_can_be_driven_through_trait() {
+        let (mut server, server_results) = ServerSession::new(ServerSessionConfig::new()).unwrap();
+        let (mut client, mut client_results) =
+            ClientSession::new(ClientSessionConfig::new()).unwrap();
+        client_results.extend(client.request_connection("live".to_string()).unwrap());
+
+        let server_bytes = packet_bytes(server_results.into_iter().map(SessionOutput::from));
+        let client_bytes = packet_bytes(client_results.into_iter().map(SessionOutput::from));
+
+        let client: &mut dyn RtmpSession = &mut client;
+        client.process_bytes(&server_bytes).unwrap();
+
+        let server: &mut dyn RtmpSession = &mut server;
+        let server_outputs = server.process_bytes(&client_bytes).unwrap();
+        let connection_requested = server_outputs.into_iter().any(|output| match output {
+            SessionOutput::Event(event) => match event.downcast::<ServerSessionEvent>() {
+                Ok(event) => matches!(*event, ServerSessionEvent::ConnectionRequested { .. }),
+                Err(_) => false,
+            },
+
+            SessionOutput::Send(_) => false,
+        });
+
+        assert!(connection_requested, "Expected a ConnectionRequested event");
+    }
+
+    fn packet_bytes(outputs: impl Iterator<Item = SessionOutput>) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        for output in outputs {
+            if let SessionOutput::Send(packet) = output {
+                bytes.extend_from_slice(&packet.bytes);
+            }
+        }
+
+        bytes
+    }
+}
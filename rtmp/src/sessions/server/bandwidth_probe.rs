@@ -0,0 +1,60 @@
+use std::collections::HashMap;
+use std::time::Instant;
+
+/// The number of padding bytes included in each `onBWCheck` probe, in the order they are sent
+pub const PROBE_PAYLOAD_SIZES: [usize; 4] = [1_024, 4_096, 16_384, 32_768];
+
+/// Tracks the `onBWCheck` probes that have been sent to the client and when the client's
+/// responses to them were received.
+///
+/// All probes are sent back to back, so the client receives them at the rate the connection
+/// allows.  The bandwidth is estimated from the bytes of every probe after the first divided by
+/// the time between receiving the client's responses to the first and last probes.  The times
+/// are taken from the server's clock, as most clients don't set the timestamps of their
+/// responses.
+pub struct BandwidthProbe {
+    outstanding_probes: HashMap<u32, usize>,
+    first_response_at: Option<Instant>,
+    last_response_at: Option<Instant>,
+    measured_bytes: u64,
+}
+
+impl BandwidthProbe {
+    pub fn new() -> BandwidthProbe {
+        BandwidthProbe {
+            outstanding_probes: HashMap::new(),
+            first_response_at: None,
+            last_response_at: None,
+            measured_bytes: 0,
+        }
+    }
+
+    pub fn probe_sent(&mut self, transaction_id: u32, probe_size: usize) {
+        self.outstanding_probes.insert(transaction_id, probe_size);
+    }
+
+    pub fn is_waiting_for(&self, transaction_id: u32) -> bool {
+        self.outstanding_probes.contains_key(&transaction_id)
+    }
+
+    /// Records the client's response to a probe, received at `received_at`.  Once all probes
+    /// have been responded to the estimated bandwidth in bits per second is returned.
+    pub fn response_received(&mut self, transaction_id: u32, received_at: Instant) -> Option<u64> {
+        let probe_size = self.outstanding_probes.remove(&transaction_id)?;
+        if self.first_response_at.is_none() {
+            self.first_response_at = Some(received_at);
+        } else {
+            self.measured_bytes += probe_size as u64;
+        }
+
+        self.last_response_at = Some(received_at);
+        if !self.outstanding_probes.is_empty() {
+            return None;
+        }
+
+        let first = self.first_response_at?;
+        let last = self.last_response_at?;
+        let elapsed_us = last.saturating_duration_since(first).as_micros().max(1) as u64;
+        Some(self.measured_bytes * 8 * 1_000_000 / elapsed_us)
+    }
+}
@@ -1,11 +1,75 @@
+use super::{ServerMiddleware, ServerSessionError};
+use messages::PeerBandwidthLimitType;
+use rml_amf0::Amf0Value;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+/// A function that decides if a connection request should be allowed.  It is passed the
+/// requested application name and all the properties of the connect command's object, and
+/// returns `false` if the connection should be rejected.
+pub type ConnectionAuthCallback =
+    Arc<dyn Fn(&str, &HashMap<String, Amf0Value>) -> bool + Send + Sync>;
+
+/// The smallest chunk size allowed by the RTMP specification
+pub const MIN_CHUNK_SIZE: u32 = 1;
+
+/// The largest chunk size allowed, as no RTMP message can be larger than this
+pub const MAX_CHUNK_SIZE: u32 = 16_777_215;
+
 /// The configuration options that govern how a RTMP server session should operate
 #[derive(Clone)]
 pub struct ServerSessionConfig {
+    /// The server version string sent in the `fmsVer` property of connect responses
     pub fms_version: String,
+
+    /// The maximum size of RTMP chunks the session will send.  Must be between 1 and 16777215.
+    /// 4096 is a good default, though 65536 or larger gives better throughput for video.
     pub chunk_size: u32,
+
+    /// The bandwidth limit, in bytes, sent to the client in a `SetPeerBandwidth` message
     pub peer_bandwidth: u32,
+
+    /// How the client should apply the `peer_bandwidth` limit.  Defaults to `Dynamic`.
+    pub peer_bandwidth_limit_type: PeerBandwidthLimitType,
+
+    /// The number of bytes the client should send before waiting for an acknowledgement
     pub window_ack_size: u32,
+
+    /// Whether an `onBWDone` command is sent to the client when the session is created
     pub send_on_bw_done_message_on_start: bool,
+
+    /// The maximum number of streams a client may have open at once.  Any `createStream`
+    /// requests past this limit are rejected.
+    pub max_streams: u32,
+
+    /// When set, this is called for every connection request.  If it returns `false` the
+    /// session automatically rejects the connection without raising a `ConnectionRequested`
+    /// event.
+    pub auth_callback: Option<ConnectionAuthCallback>,
+
+    /// The maximum chunk size to expect from the peer until it sends a `SetChunkSize` message.
+    /// The RTMP specification says this should be 128.  This has to match the size of the chunks
+    /// the peer actually sends, so it should only be changed to support peers that split their
+    /// messages into larger chunks without ever announcing it with a `SetChunkSize` message.
+    pub peer_max_chunk_size: usize,
+
+    /// The largest audio message, in bytes, the client may send.  Larger messages cause
+    /// `handle_input()` to return an `InboundMessageTooLarge` error.  Defaults to 1 MB.
+    pub max_inbound_audio_bytes: usize,
+
+    /// The largest video message, in bytes, the client may send.  Larger messages cause
+    /// `handle_input()` to return an `InboundMessageTooLarge` error.  Defaults to 4 MB.
+    pub max_inbound_video_bytes: usize,
+
+    /// Middleware that every message received from the client is passed through, in order,
+    /// before the session handles it.  Empty by default.
+    pub middleware: Vec<Arc<dyn ServerMiddleware>>,
+
+    /// The most audio and video packets that may be waiting to be sent on a single stream.  When
+    /// set, each packet returned by `send_video_data()` or `send_audio_data()` counts against its
+    /// stream until the application reports it was sent with `outbound_packet_sent()`, and
+    /// further packets are refused with a `ClientOutputQueueFull` error.  Unlimited by default.
+    pub max_outbound_queue_per_stream: Option<usize>,
 }
 
 impl ServerSessionConfig {
@@ -14,9 +78,39 @@ impl ServerSessionConfig {
         ServerSessionConfig {
             fms_version: "FMS/3,0,1,1233".to_string(),
             peer_bandwidth: 2_500_000,
+            peer_bandwidth_limit_type: PeerBandwidthLimitType::Dynamic,
             window_ack_size: 1_073_741_824,
             chunk_size: 4096,
             send_on_bw_done_message_on_start: true,
+            peer_max_chunk_size: 128,
+            max_streams: 10,
+            auth_callback: None,
+            max_inbound_audio_bytes: 1024 * 1024,
+            max_inbound_video_bytes: 4 * 1024 * 1024,
+            middleware: Vec::new(),
+            max_outbound_queue_per_stream: None,
         }
     }
+
+    /// Creates a new server session config with overridable defaults and the specified
+    /// chunk size, returning an error if the chunk size is not valid.
+    pub fn with_chunk_size(chunk_size: u32) -> Result<ServerSessionConfig, ServerSessionError> {
+        let mut config = ServerSessionConfig::new();
+        config.chunk_size = chunk_size;
+        config.validate()?;
+
+        Ok(config)
+    }
+
+    /// Verifies that the configured values are allowed by the RTMP specification.  This is
+    /// automatically checked when a `ServerSession` is created.
+    pub fn validate(&self) -> Result<(), ServerSessionError> {
+        if self.chunk_size < MIN_CHUNK_SIZE || self.chunk_size > MAX_CHUNK_SIZE {
+            return Err(ServerSessionError::InvalidChunkSize {
+                chunk_size: self.chunk_size,
+            });
+        }
+
+        Ok(())
+    }
 }
@@ -1,6 +1,8 @@
 use chunk_io::{ChunkDeserializationError, ChunkSerializationError};
 
+use super::RequestId;
 use messages::{MessageDeserializationError, MessageSerializationError};
+use std::io;
 use thiserror::Error;
 
 /// Error state when a server session encounters an error
@@ -32,7 +34,7 @@ pub enum ServerSessionError {
     #[error(
         "Attempted to accept or reject request id {0} but no outstanding requests have that id"
     )]
-    InvalidOutstandingRequest(u32),
+    InvalidOutstandingRequest(RequestId),
 
     /// A connection request was made without a valid RTMP app name
     #[error("The connection request did not have a non-empty RTMP app name")]
@@ -44,5 +46,55 @@ pub enum ServerSessionError {
 
     /// An action was attempted to be performed on a inactive stream
     #[error("The '{action}' action was attempted on non-existant stream id {stream_id}")]
-    ActionAttemptedOnInactiveStream { action: String, stream_id: u32 },
+    ActionAttemptedOnInactiveStream {
+        /// The name of the action that was attempted
+        action: String,
+
+        /// The stream id the action was attempted on
+        stream_id: u32,
+    },
+
+    /// The configured chunk size is outside of the range allowed by the RTMP specification
+    #[error("Chunk size of {chunk_size} is invalid, it must be between 1 and 16777215")]
+    InvalidChunkSize {
+        /// The chunk size that was configured
+        chunk_size: u32,
+    },
+
+    /// A bandwidth probe was started before the client's connection request was accepted
+    #[error("A bandwidth probe can only be started once the client has connected")]
+    BandwidthProbeRequiresConnection,
+
+    /// A bandwidth probe was started while a previous probe was still waiting on responses
+    #[error("A bandwidth probe is already in progress")]
+    BandwidthProbeAlreadyInProgress,
+
+    /// A recording was requested on a stream that clients are not publishing to
+    #[error("Stream id {stream_id} cannot be recorded since it is not being published to")]
+    RecordingRequiresPublishingStream {
+        /// The stream id the recording was requested for
+        stream_id: u32,
+    },
+
+    /// The client sent an audio or video message larger than the session is configured to allow
+    #[error("Received a {type_name} message of {size} bytes, which is larger than allowed")]
+    InboundMessageTooLarge {
+        /// The type of message that was received (`audio` or `video`)
+        type_name: &'static str,
+
+        /// The size of the message, in bytes
+        size: usize,
+    },
+
+    /// Audio or video could not be prepared for a stream since its outbound queue is full.  The
+    /// application should hold back data from the publisher until the client catches up.
+    #[error("The outbound queue for stream id {stream_id} is full")]
+    ClientOutputQueueFull {
+        /// The stream id whose queue is full
+        stream_id: u32,
+    },
+
+    /// The file a stream was going to be recorded to could not be created
+    #[error("Failed to create the recording file: {0}")]
+    RecordingFileCreationFailed(#[from] io::Error),
 }
@@ -1,11 +1,13 @@
-use super::PublishMode;
+use super::{PublishMode, RequestId};
 use bytes::Bytes;
+use messages::UserControlEventType;
 use rml_amf0::Amf0Value;
 use sessions::StreamMetadata;
 use time::RtmpTimestamp;
 
 /// Represents where RTMP playback should start from
 #[derive(PartialEq, Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(::serde::Serialize, ::serde::Deserialize))]
 pub enum PlayStartValue {
     /// If a live stream exists for the specified stream keyplay it, if not
     /// play the recorded stream with a matching name
@@ -14,91 +16,197 @@ pub enum PlayStartValue {
     /// Only play live streams with the provided stream key
     LiveOnly,
 
-    /// Play the recorded stream for the stream key at the specified start time
-    StartTimeInSeconds(u32),
+    /// Play the recorded stream for the stream key starting at the specified number of
+    /// milliseconds into the stream
+    StartTimeInMillis(u32),
 }
 
 /// An event that a server session can raise
 #[derive(Debug, PartialEq, Clone)]
 pub enum ServerSessionEvent {
     /// The client is changing the maximum size of the RTMP chunks they will be sending
-    ClientChunkSizeChanged { new_chunk_size: u32 },
+    ClientChunkSizeChanged {
+        /// The new maximum chunk size, in bytes
+        new_chunk_size: u32,
+    },
+
+    /// The client is requesting a connection on the specified RTMP application name.
+    ///
+    /// The object encoding is the AMF version the client specified in its connect command (0 for
+    /// AMF0 and 3 for AMF3), or 0 if it did not specify one.
+    #[non_exhaustive]
+    ConnectionRequested {
+        /// The id to pass to the session when accepting or rejecting the request
+        request_id: RequestId,
+
+        /// The name of the application the client wants to connect to
+        app_name: String,
 
-    /// The client is requesting a connection on the specified RTMP application name
-    ConnectionRequested { request_id: u32, app_name: String },
+        /// The AMF version of the object encoding the client requested
+        object_encoding: f64,
+    },
 
     /// The client is requesting a stream key be released for use.
     ReleaseStreamRequested {
-        request_id: u32,
+        /// The id to pass to the session when accepting or rejecting the request
+        request_id: RequestId,
+
+        /// The name of the application the client is connected to
         app_name: String,
+
+        /// The stream key the request applies to
         stream_key: String,
     },
 
     /// The client is requesting the ability to publish on the specified stream key,
     PublishStreamRequested {
-        request_id: u32,
+        /// The id to pass to the session when accepting or rejecting the request
+        request_id: RequestId,
+
+        /// The name of the application the client is connected to
         app_name: String,
+
+        /// The stream key the request applies to
         stream_key: String,
+
+        /// How the client wants the published stream to be handled
         mode: PublishMode,
+
+        /// The id of the message stream the request was made on
+        stream_id: u32,
     },
 
     /// The client is finished publishing on the specified stream key
     PublishStreamFinished {
+        /// The name of the application the client is connected to
         app_name: String,
+
+        /// The stream key the request applies to
         stream_key: String,
     },
 
     /// The client is changing metadata properties of the stream being published
     StreamMetadataChanged {
+        /// The name of the application the client is connected to
         app_name: String,
+
+        /// The stream key the request applies to
         stream_key: String,
+
+        /// The new metadata for the stream
         metadata: StreamMetadata,
     },
 
     /// Audio data was received from the client
     AudioDataReceived {
+        /// The name of the application the client is connected to
         app_name: String,
+
+        /// The stream key the request applies to
         stream_key: String,
+
+        /// The audio data, in the same format as the body of an FLV audio tag
         data: Bytes,
+
+        /// The timestamp of the data
         timestamp: RtmpTimestamp,
     },
 
     /// Video data received from the client
     VideoDataReceived {
+        /// The name of the application the client is connected to
         app_name: String,
+
+        /// The stream key the request applies to
         stream_key: String,
+
+        /// The video data, in the same format as the body of an FLV video tag
         data: Bytes,
+
+        /// The timestamp of the data
         timestamp: RtmpTimestamp,
     },
 
     /// The client sent an Amf0 command that was not able to be handled
     UnhandleableAmf0Command {
+        /// The name of the command
         command_name: String,
+
+        /// The transaction id of the command
         transaction_id: f64,
+
+        /// The command object sent with the command
         command_object: Amf0Value,
+
+        /// Any values that followed the command object
         additional_values: Vec<Amf0Value>,
     },
 
     /// The client is requesting playback of the specified stream
     PlayStreamRequested {
-        request_id: u32,
+        /// The id to pass to the session when accepting or rejecting the request
+        request_id: RequestId,
+
+        /// The name of the application the client is connected to
         app_name: String,
+
+        /// The stream key the request applies to
         stream_key: String,
+
+        /// Where playback should start from
         start_at: PlayStartValue,
+
+        /// How many milliseconds of the stream should be played, if limited
         duration: Option<u32>,
+
+        /// Whether any previous playlist should be flushed before playing
         reset: bool,
+
+        /// The id of the message stream the request was made on
         stream_id: u32,
     },
 
     /// The client is finished with playback of the specified stream
     PlayStreamFinished {
+        /// The name of the application the client is connected to
         app_name: String,
+
+        /// The stream key the request applies to
         stream_key: String,
     },
 
     /// The client has sent an acknowledgement that they have received the specified number of bytes
-    AcknowledgementReceived { bytes_received: u32 },
+    AcknowledgementReceived {
+        /// The total number of bytes the client has received
+        bytes_received: u32,
+    },
 
     /// The client has responded to a ping request
-    PingResponseReceived { timestamp: RtmpTimestamp },
+    PingResponseReceived {
+        /// The timestamp that was sent in the ping request
+        timestamp: RtmpTimestamp,
+    },
+
+    /// The client has responded to every packet of a bandwidth probe started with
+    /// `ServerSession::start_bandwidth_probe()`
+    BandwidthMeasured {
+        /// The measured bandwidth of the connection
+        bits_per_second: u64,
+    },
+
+    /// The client sent a user control event that the session does not handle itself, such as
+    /// `SetBufferLength` or `BufferReady`
+    UnhandledUserControlEvent {
+        /// The type of user control event that was received
+        event_type: UserControlEventType,
+
+        /// The stream the event applies to, if the event type carries one
+        stream_id: Option<u32>,
+
+        /// The buffer length in milliseconds, if the event type carries one
+        buffer_length: Option<u32>,
+
+        /// The timestamp sent with the event, if the event type carries one
+        timestamp: Option<RtmpTimestamp>,
+    },
 }
@@ -0,0 +1,113 @@
+use messages::RtmpMessage;
+use std::fmt;
+
+/// What a `ServerMiddleware` wants the session to do with an inbound message
+#[derive(PartialEq, Debug, Clone)]
+pub enum MiddlewareDecision {
+    /// The message should be handled as normal
+    Allow,
+
+    /// The message should be ignored, as if it was never received.  No later middleware sees it.
+    Drop,
+
+    /// The message should be handled as if this message had been received instead
+    Replace(RtmpMessage),
+}
+
+/// Inspects messages received from the client before the server session acts on them, allowing
+/// applications to filter or rewrite messages (such as rejecting commands or stripping metadata)
+/// without having to deserialize the chunk stream themselves.
+///
+/// Middleware is run in the order it appears in `ServerSessionConfig::middleware`, with each one
+/// seeing the message as it was left by the previous one.  The sub-messages of aggregate messages
+/// are intercepted individually.
+pub trait ServerMiddleware: Send + Sync {
+    /// Decides what should happen to a message received on the specified message stream
+    fn intercept_inbound(&self, message: &RtmpMessage, stream_id: u32) -> MiddlewareDecision;
+}
+
+/// A reference middleware that passes a description of every inbound message to a logger
+/// and allows all of them through.
+pub struct LoggingMiddleware {
+    logger: Box<dyn Fn(String) + Send + Sync>,
+}
+
+impl LoggingMiddleware {
+    /// Creates middleware that calls the logger with a line describing each inbound message
+    pub fn new(logger: impl Fn(String) + Send + Sync + 'static) -> LoggingMiddleware {
+        LoggingMiddleware {
+            logger: Box::new(logger),
+        }
+    }
+}
+
+impl ServerMiddleware for LoggingMiddleware {
+    fn intercept_inbound(&self, message: &RtmpMessage, stream_id: u32) -> MiddlewareDecision {
+        (self.logger)(format!(
+            "Inbound message on stream {}: {}",
+            stream_id,
+            MessageDescription(message)
+        ));
+
+        MiddlewareDecision::Allow
+    }
+}
+
+impl fmt::Debug for LoggingMiddleware {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("LoggingMiddleware")
+    }
+}
+
+// Media messages are summarized, since logging every byte of every frame isn't useful
+struct MessageDescription<'a>(&'a RtmpMessage);
+
+impl<'a> fmt::Display for MessageDescription<'a> {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        match *self.0 {
+            RtmpMessage::AudioData { ref data } => {
+                write!(formatter, "AudioData ({} bytes)", data.len())
+            }
+            RtmpMessage::VideoData { ref data } => {
+                write!(formatter, "VideoData ({} bytes)", data.len())
+            }
+            ref message => write!(formatter, "{:?}", message),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bytes::Bytes;
+    use std::sync::{Arc, Mutex};
+
+    #[test]
+    fn logging_middleware_logs_and_allows_messages() {
+        let lines = Arc::new(Mutex::new(Vec::new()));
+        let logged_lines = lines.clone();
+        let middleware =
+            LoggingMiddleware::new(move |line| logged_lines.lock().unwrap().push(line));
+
+        let video = RtmpMessage::VideoData {
+            data: Bytes::from(vec![1_u8, 2_u8, 3_u8]),
+        };
+        let chunk_size = RtmpMessage::SetChunkSize { size: 4096 };
+
+        assert_eq!(
+            middleware.intercept_inbound(&video, 1),
+            MiddlewareDecision::Allow
+        );
+        assert_eq!(
+            middleware.intercept_inbound(&chunk_size, 0),
+            MiddlewareDecision::Allow
+        );
+        assert_eq!(
+            *lines.lock().unwrap(),
+            vec![
+                "Inbound message on stream 1: VideoData (3 bytes)".to_string(),
+                "Inbound message on stream 0: SetChunkSize { size: 4096 }".to_string(),
+            ]
+        );
+    }
+}
@@ -1,32 +1,45 @@
 mod active_stream;
+mod bandwidth_probe;
 mod config;
 mod errors;
 mod events;
+mod middleware;
 mod outstanding_requests;
 mod publish_mode;
+mod request_id;
 mod result;
 mod session_state;
+mod video_stream_sender;
 
 #[cfg(test)]
 mod tests;
 
 use self::active_stream::{ActiveStream, StreamState};
-use self::outstanding_requests::OutstandingRequest;
+use self::bandwidth_probe::{BandwidthProbe, PROBE_PAYLOAD_SIZES};
+use self::outstanding_requests::{OutstandingRequest, TimedRequest};
 use self::session_state::SessionState;
 use bytes::Bytes;
-use chunk_io::{ChunkDeserializer, ChunkSerializer, Packet};
-use messages::{PeerBandwidthLimitType, RtmpMessage, UserControlEventType};
+use chunk_io::{ChunkDeserializer, ChunkSerializer, DropPolicy, Packet};
+use messages::{MessagePayload, PeerBandwidthLimitType, RtmpMessage, UserControlEventType};
+use recording::{Recorder, RecordingHandle};
 use rml_amf0::Amf0Value;
-use sessions::StreamMetadata;
+use sessions::{Direction, MessageTrace, StreamMetadata};
 use std::collections::HashMap;
-use std::time::SystemTime;
+#[cfg(feature = "async")]
+use std::future::{self, Future};
+use std::path::Path;
+use std::sync::Arc;
+use std::time::{Duration, Instant, SystemTime};
 use time::RtmpTimestamp;
 
-pub use self::config::ServerSessionConfig;
+pub use self::config::{ConnectionAuthCallback, ServerSessionConfig};
 pub use self::errors::ServerSessionError;
 pub use self::events::{PlayStartValue, ServerSessionEvent};
+pub use self::middleware::{LoggingMiddleware, MiddlewareDecision, ServerMiddleware};
 pub use self::publish_mode::PublishMode;
+pub use self::request_id::RequestId;
 pub use self::result::ServerSessionResult;
+pub use self::video_stream_sender::VideoStreamSender;
 
 /// A session that represents the server side of a single RTMP connection.
 ///
@@ -50,16 +63,30 @@ pub struct ServerSession {
     serializer: ChunkSerializer,
     deserializer: ChunkDeserializer,
     connected_app_name: Option<String>,
-    outstanding_requests: HashMap<u32, OutstandingRequest>,
-    next_request_number: u32,
+    outstanding_requests: HashMap<RequestId, TimedRequest>,
+    next_request_id: RequestId,
     current_state: SessionState,
     fms_version: String,
     object_encoding: f64,
     active_streams: HashMap<u32, ActiveStream>,
     next_stream_id: u32,
+    max_streams: u32,
+    auth_callback: Option<ConnectionAuthCallback>,
+    window_ack_size: u32,
     peer_window_ack_size: Option<u32>,
     bytes_received: u64,
     bytes_received_since_last_ack: u32,
+    bandwidth_probe: Option<BandwidthProbe>,
+    next_outbound_transaction_id: u32,
+    recordings: HashMap<u32, Recorder>,
+    connect_properties: Option<HashMap<String, Amf0Value>>,
+    max_inbound_audio_bytes: usize,
+    max_inbound_video_bytes: usize,
+    auto_accept: bool,
+    message_tracer: Option<Box<dyn Fn(MessageTrace) + Send>>,
+    middleware: Vec<Arc<dyn ServerMiddleware>>,
+    max_outbound_queue_per_stream: Option<usize>,
+    outbound_queue_depths: HashMap<u32, usize>,
 }
 
 impl ServerSession {
@@ -71,23 +98,43 @@ impl ServerSession {
     pub fn new(
         config: ServerSessionConfig,
     ) -> Result<(ServerSession, Vec<ServerSessionResult>), ServerSessionError> {
+        config.validate()?;
+
         let mut session = ServerSession {
             start_time: SystemTime::now(),
             serializer: ChunkSerializer::new(),
             deserializer: ChunkDeserializer::new(),
             connected_app_name: None,
             outstanding_requests: HashMap::new(),
-            next_request_number: 0,
+            next_request_id: RequestId::new(0),
             current_state: SessionState::Started,
             fms_version: config.fms_version,
             object_encoding: 0.0,
             active_streams: HashMap::new(),
             next_stream_id: 1,
+            max_streams: config.max_streams,
+            auth_callback: config.auth_callback,
+            window_ack_size: config.window_ack_size,
             peer_window_ack_size: None,
             bytes_received: 0,
             bytes_received_since_last_ack: 0,
+            bandwidth_probe: None,
+            next_outbound_transaction_id: 1,
+            recordings: HashMap::new(),
+            connect_properties: None,
+            max_inbound_audio_bytes: config.max_inbound_audio_bytes,
+            max_inbound_video_bytes: config.max_inbound_video_bytes,
+            auto_accept: false,
+            message_tracer: None,
+            middleware: config.middleware,
+            max_outbound_queue_per_stream: config.max_outbound_queue_per_stream,
+            outbound_queue_depths: HashMap::new(),
         };
 
+        session
+            .deserializer
+            .set_max_chunk_size(config.peer_max_chunk_size)?;
+
         let mut results = Vec::with_capacity(4);
 
         let chunk_size_packet = session
@@ -99,9 +146,8 @@ impl ServerSession {
             size: config.window_ack_size,
         };
         let window_ack_payload = window_ack_message.into_message_payload(session.get_epoch(), 0)?;
-        let window_ack_packet = session
-            .serializer
-            .serialize(&window_ack_payload, true, false)?;
+        let window_ack_packet =
+            session.serialize_payload(&window_ack_payload, true, DropPolicy::MustDeliver)?;
         results.push(ServerSessionResult::OutboundResponse(window_ack_packet));
 
         let begin_message = RtmpMessage::UserControl {
@@ -112,15 +158,17 @@ impl ServerSession {
         };
 
         let begin_payload = begin_message.into_message_payload(session.get_epoch(), 0)?;
-        let begin_packet = session.serializer.serialize(&begin_payload, true, false)?;
+        let begin_packet =
+            session.serialize_payload(&begin_payload, true, DropPolicy::MustDeliver)?;
         results.push(ServerSessionResult::OutboundResponse(begin_packet));
 
         let peer_message = RtmpMessage::SetPeerBandwidth {
             size: config.peer_bandwidth,
-            limit_type: PeerBandwidthLimitType::Dynamic,
+            limit_type: config.peer_bandwidth_limit_type.clone(),
         };
         let peer_payload = peer_message.into_message_payload(session.get_epoch(), 0)?;
-        let peer_packet = session.serializer.serialize(&peer_payload, true, false)?;
+        let peer_packet =
+            session.serialize_payload(&peer_payload, true, DropPolicy::MustDeliver)?;
         results.push(ServerSessionResult::OutboundResponse(peer_packet));
 
         if config.send_on_bw_done_message_on_start {
@@ -132,15 +180,26 @@ impl ServerSession {
             };
 
             let bw_done_payload = bw_done_message.into_message_payload(session.get_epoch(), 0)?;
-            let bw_done_packet = session
-                .serializer
-                .serialize(&bw_done_payload, true, false)?;
+            let bw_done_packet =
+                session.serialize_payload(&bw_done_payload, true, DropPolicy::MustDeliver)?;
             results.push(ServerSessionResult::OutboundResponse(bw_done_packet));
         }
 
         Ok((session, results))
     }
 
+    /// Creates a new server session that automatically accepts all connection, publish, and
+    /// play requests.  No events are raised for these requests, so this is only meant for
+    /// testing and simple relays where every client should be allowed.  A configured
+    /// `auth_callback` is still used to reject connection requests.
+    pub fn new_permissive(
+        config: ServerSessionConfig,
+    ) -> Result<(ServerSession, Vec<ServerSessionResult>), ServerSessionError> {
+        let (mut session, results) = ServerSession::new(config)?;
+        session.auto_accept = true;
+        Ok((session, results))
+    }
+
     /// Takes in bytes that are encoding RTMP chunks and returns any responses or events that can
     /// be reacted to.
     pub fn handle_input(
@@ -150,14 +209,19 @@ impl ServerSession {
         let mut results = Vec::new();
         self.bytes_received += bytes.len() as u64;
 
-        if let Some(peer_ack_size) = self.peer_window_ack_size {
+        // Clients are expected to tell us how often to acknowledge, but some encoders never do
+        // and will stall without receiving acknowledgements, so fall back to our own window size
+        let ack_size = self.peer_window_ack_size.unwrap_or(self.window_ack_size);
+        if ack_size > 0 {
             self.bytes_received_since_last_ack += bytes.len() as u32;
-            if self.bytes_received_since_last_ack >= peer_ack_size {
+            if self.bytes_received_since_last_ack >= ack_size {
+                // The sequence number is the total bytes received so far, wrapping at 32 bits
                 let ack_message = RtmpMessage::Acknowledgement {
-                    sequence_number: self.bytes_received_since_last_ack,
+                    sequence_number: self.bytes_received as u32,
                 };
                 let ack_payload = ack_message.into_message_payload(self.get_epoch(), 0)?;
-                let ack_packet = self.serializer.serialize(&ack_payload, false, false)?;
+                let ack_packet =
+                    self.serialize_payload(&ack_payload, false, DropPolicy::MustDeliver)?;
 
                 self.bytes_received_since_last_ack = 0;
                 results.push(ServerSessionResult::OutboundResponse(ack_packet));
@@ -170,70 +234,15 @@ impl ServerSession {
             match self.deserializer.get_next_message(bytes_to_process)? {
                 None => break,
                 Some(payload) => {
-                    let message = payload.to_rtmp_message()?;
-
-                    let mut message_results = match message {
-                        RtmpMessage::Abort { stream_id } => self.handle_abort_message(stream_id)?,
-
-                        RtmpMessage::Acknowledgement { sequence_number } => {
-                            self.handle_acknowledgement_message(sequence_number)?
-                        }
-
-                        RtmpMessage::Amf0Command {
-                            command_name,
-                            transaction_id,
-                            command_object,
-                            additional_arguments,
-                        } => self.handle_amf0_command(
-                            payload.message_stream_id,
-                            command_name,
-                            transaction_id,
-                            command_object,
-                            additional_arguments,
-                        )?,
-
-                        RtmpMessage::Amf0Data { values } => {
-                            self.handle_amf0_data(values, payload.message_stream_id)?
-                        }
-
-                        RtmpMessage::AudioData { data } => self.handle_audio_data(
-                            data,
-                            payload.message_stream_id,
-                            payload.timestamp,
-                        )?,
-
-                        RtmpMessage::SetChunkSize { size } => self.handle_set_chunk_size(size)?,
-
-                        RtmpMessage::SetPeerBandwidth { size, limit_type } => {
-                            self.handle_set_peer_bandwidth(size, limit_type)?
-                        }
-
-                        RtmpMessage::UserControl {
-                            event_type,
-                            stream_id,
-                            buffer_length,
-                            timestamp,
-                        } => self.handle_user_control(
-                            event_type,
-                            stream_id,
-                            buffer_length,
-                            timestamp,
-                        )?,
-
-                        RtmpMessage::VideoData { data } => self.handle_video_data(
-                            data,
-                            payload.message_stream_id,
-                            payload.timestamp,
-                        )?,
-
-                        RtmpMessage::WindowAcknowledgement { size } => {
-                            self.handle_window_acknowledgement(size)?
-                        }
-
-                        _ => vec![ServerSessionResult::UnhandleableMessageReceived(payload)],
-                    };
+                    if let Some(ref tracer) = self.message_tracer {
+                        tracer(MessageTrace::new(
+                            Direction::Inbound,
+                            &payload,
+                            payload.data.len(),
+                        ));
+                    }
 
-                    results.append(&mut message_results);
+                    results.append(&mut self.handle_payload(payload)?);
                     bytes_to_process = &[];
                 }
             }
@@ -242,13 +251,21 @@ impl ServerSession {
         Ok(results)
     }
 
+    /// Calls the logger with a trace of every message received from the client and every
+    /// packet serialized to be sent to it, which is useful when debugging interoperability
+    /// issues with other RTMP implementations.  Messages are traced as they are deserialized, so
+    /// the sub-messages of aggregate messages are not traced individually.
+    pub fn enable_message_tracing(&mut self, logger: impl Fn(MessageTrace) + Send + 'static) {
+        self.message_tracer = Some(Box::new(logger));
+    }
+
     /// Tells the server session that it should accept an outstanding request
     pub fn accept_request(
         &mut self,
-        request_id: u32,
+        request_id: RequestId,
     ) -> Result<Vec<ServerSessionResult>, ServerSessionError> {
         let request = match self.outstanding_requests.remove(&request_id) {
-            Some(x) => x,
+            Some(x) => x.request,
             None => return Err(ServerSessionError::InvalidRequestId),
         };
 
@@ -256,7 +273,8 @@ impl ServerSession {
             OutstandingRequest::ConnectionRequest {
                 app_name,
                 transaction_id,
-            } => self.accept_connection_request(app_name, transaction_id),
+                properties,
+            } => self.accept_connection_request(app_name, transaction_id, properties),
 
             OutstandingRequest::PublishRequested {
                 stream_key,
@@ -271,15 +289,27 @@ impl ServerSession {
         }
     }
 
+    /// Async version of `accept_request()` for servers running on an async runtime.  The
+    /// request is currently accepted synchronously when this is called, with the returned
+    /// future resolving immediately, but async servers that use this will not have to change
+    /// if accepting requests ever becomes more expensive.
+    #[cfg(feature = "async")]
+    pub fn accept_request_async(
+        &mut self,
+        request_id: RequestId,
+    ) -> impl Future<Output = Result<Vec<ServerSessionResult>, ServerSessionError>> {
+        future::ready(self.accept_request(request_id))
+    }
+
     /// Tells the server session that it should reject an outstanding request
     pub fn reject_request(
         &mut self,
-        request_id: u32,
+        request_id: RequestId,
         code: &str,
         description: &str,
     ) -> Result<Vec<ServerSessionResult>, ServerSessionError> {
         let request = match self.outstanding_requests.remove(&request_id) {
-            Some(x) => x,
+            Some(x) => x.request,
             None => return Err(ServerSessionError::InvalidRequestId),
         };
 
@@ -294,13 +324,48 @@ impl ServerSession {
         Ok(vec![ServerSessionResult::OutboundResponse(packet)])
     }
 
+    /// Rejects every outstanding request that was raised more than `timeout_ms` milliseconds
+    /// before `now`, so clients get an error response instead of timing out themselves when the
+    /// application is slow to decide.  This should be called periodically, such as from the
+    /// application's event loop.  Once a request has timed out its id can no longer be passed
+    /// to `accept_request()` or `reject_request()`.
+    pub fn check_request_timeouts(
+        &mut self,
+        now: Instant,
+        timeout_ms: u64,
+    ) -> Result<Vec<ServerSessionResult>, ServerSessionError> {
+        let timeout = Duration::from_millis(timeout_ms);
+        let mut timed_out_requests = self
+            .outstanding_requests
+            .iter()
+            .filter(|(_, x)| now.saturating_duration_since(x.requested_at) > timeout)
+            .map(|(request_id, x)| (x.requested_at, *request_id))
+            .collect::<Vec<_>>();
+
+        // Respond in the order the requests were made
+        timed_out_requests.sort_by_key(|(requested_at, _)| *requested_at);
+
+        let mut results = Vec::new();
+        for (_, request_id) in timed_out_requests {
+            let code = match self.outstanding_requests[&request_id].request {
+                OutstandingRequest::ConnectionRequest { .. } => "NetConnection.Connect.Rejected",
+                OutstandingRequest::PublishRequested { .. } => "NetStream.Publish.Failed",
+                OutstandingRequest::PlayRequested { .. } => "NetStream.Play.Failed",
+            };
+
+            results.append(&mut self.reject_request(request_id, code, "Request timed out")?);
+        }
+
+        Ok(results)
+    }
+
     /// Prepares metadata information to be sent to the client
     pub fn send_metadata(
         &mut self,
         stream_id: u32,
         metadata: &StreamMetadata,
     ) -> Result<Packet, ServerSessionError> {
-        let mut properties = HashMap::with_capacity(11);
+        let mut properties = HashMap::with_capacity(16);
 
         metadata
             .video_width
@@ -310,9 +375,16 @@ impl ServerSession {
             .video_height
             .map(|x| properties.insert("height".to_string(), Amf0Value::Number(x as f64)));
 
-        metadata
-            .video_codec_id
-            .map(|x| properties.insert("videocodecid".to_string(), Amf0Value::Number(x as f64)));
+        // Codec ids are preferred, as that is what most clients expect
+        match (metadata.video_codec_id, &metadata.video_codec_name) {
+            (Some(x), _) => {
+                properties.insert("videocodecid".to_string(), Amf0Value::Number(x as f64))
+            }
+            (None, Some(x)) => {
+                properties.insert("videocodecid".to_string(), Amf0Value::Utf8String(x.clone()))
+            }
+            (None, None) => None,
+        };
 
         metadata
             .video_bitrate_kbps
@@ -322,9 +394,16 @@ impl ServerSession {
             .video_frame_rate
             .map(|x| properties.insert("framerate".to_string(), Amf0Value::Number(x as f64)));
 
-        metadata
-            .audio_codec_id
-            .map(|x| properties.insert("audiocodecid".to_string(), Amf0Value::Number(x as f64)));
+        // Codec ids are preferred, as that is what most clients expect
+        match (metadata.audio_codec_id, &metadata.audio_codec_name) {
+            (Some(x), _) => {
+                properties.insert("audiocodecid".to_string(), Amf0Value::Number(x as f64))
+            }
+            (None, Some(x)) => {
+                properties.insert("audiocodecid".to_string(), Amf0Value::Utf8String(x.clone()))
+            }
+            (None, None) => None,
+        };
 
         metadata
             .audio_bitrate_kbps
@@ -334,6 +413,10 @@ impl ServerSession {
             .audio_sample_rate
             .map(|x| properties.insert("audiosamplerate".to_string(), Amf0Value::Number(x as f64)));
 
+        metadata
+            .audio_sample_size
+            .map(|x| properties.insert("audiosamplesize".to_string(), Amf0Value::Number(x as f64)));
+
         metadata
             .audio_channels
             .map(|x| properties.insert("audiochannels".to_string(), Amf0Value::Number(x as f64)));
@@ -347,6 +430,28 @@ impl ServerSession {
             .as_ref()
             .map(|x| properties.insert("encoder".to_string(), Amf0Value::Utf8String(x.clone())));
 
+        metadata
+            .color_depth
+            .map(|x| properties.insert("colorDepth".to_string(), Amf0Value::Number(x as f64)));
+
+        metadata.color_matrix_coefficients.map(|x| {
+            properties.insert(
+                "colorMatrixCoefficients".to_string(),
+                Amf0Value::Number(x as f64),
+            )
+        });
+
+        metadata.color_transfer_characteristics.map(|x| {
+            properties.insert(
+                "colorTransferCharacteristics".to_string(),
+                Amf0Value::Number(x as f64),
+            )
+        });
+
+        metadata
+            .color_primaries
+            .map(|x| properties.insert("colorPrimaries".to_string(), Amf0Value::Number(x as f64)));
+
         let message = RtmpMessage::Amf0Data {
             values: vec![
                 Amf0Value::Utf8String("onMetaData".to_string()),
@@ -355,38 +460,107 @@ impl ServerSession {
         };
 
         let payload = message.into_message_payload(self.get_epoch(), stream_id)?;
-        let packet = self.serializer.serialize(&payload, false, false)?;
+        let packet = self.serialize_payload(&payload, false, DropPolicy::MustDeliver)?;
+        Ok(packet)
+    }
+
+    /// Prepares a custom AMF0 data message (e.g. timed text cues) to be sent to the client on
+    /// the specified stream.  The values are sent as is, unlike `send_metadata()` which wraps
+    /// them in an `onMetaData` message.
+    pub fn send_amf0_data_message(
+        &mut self,
+        stream_id: u32,
+        values: Vec<Amf0Value>,
+    ) -> Result<Packet, ServerSessionError> {
+        let message = RtmpMessage::Amf0Data { values };
+        let payload = message.into_message_payload(self.get_epoch(), stream_id)?;
+        let packet = self.serialize_payload(&payload, false, DropPolicy::MustDeliver)?;
         Ok(packet)
     }
 
-    /// Prepare video data to be sent to the client
+    /// Prepare video data to be sent to the client.
+    ///
+    /// Since `Bytes` is reference counted, relaying the same video data to many clients only
+    /// requires passing a clone of the `Bytes` to each client's session, which does not copy the
+    /// underlying video data.
     pub fn send_video_data(
         &mut self,
         stream_id: u32,
         data: Bytes,
         timestamp: RtmpTimestamp,
-        can_be_dropped: bool,
+        drop_policy: DropPolicy,
     ) -> Result<Packet, ServerSessionError> {
+        self.verify_outbound_queue_has_space(stream_id)?;
+
         let message = RtmpMessage::VideoData { data };
         let payload = message.into_message_payload(timestamp, stream_id)?;
-        let packet = self.serializer.serialize(&payload, false, can_be_dropped)?;
+        let packet = self.serialize_payload(&payload, false, drop_policy)?;
+        self.increment_outbound_queue_depth(stream_id);
         Ok(packet)
     }
 
-    /// Prepare audio data to be sent to the client
+    /// Starts sending a video message to the client in parts, so a large frame can be sent as it
+    /// is produced rather than having to be in memory all at once.  The total length of the
+    /// video data must be known up front, as RTMP sends it in the message's first chunk header.
+    ///
+    /// The returned `VideoStreamSender` produces the packets for each part of the frame, and the
+    /// session cannot be used for anything else until it has been finished.  The whole frame
+    /// counts as a single packet towards the stream's outbound queue.
+    pub fn begin_video_stream(
+        &mut self,
+        stream_id: u32,
+        timestamp: RtmpTimestamp,
+        length: u32,
+    ) -> Result<VideoStreamSender<'_>, ServerSessionError> {
+        self.verify_outbound_queue_has_space(stream_id)?;
+
+        let message = self
+            .serializer
+            .begin_partial_message(9, timestamp, stream_id, length)?;
+
+        self.increment_outbound_queue_depth(stream_id);
+        Ok(VideoStreamSender::new(self, message, stream_id, timestamp))
+    }
+
+    /// Prepare audio data to be sent to the client.
+    ///
+    /// Since `Bytes` is reference counted, relaying the same audio data to many clients only
+    /// requires passing a clone of the `Bytes` to each client's session, which does not copy the
+    /// underlying audio data.
     pub fn send_audio_data(
         &mut self,
         stream_id: u32,
         data: Bytes,
         timestamp: RtmpTimestamp,
-        can_be_dropped: bool,
+        drop_policy: DropPolicy,
     ) -> Result<Packet, ServerSessionError> {
+        self.verify_outbound_queue_has_space(stream_id)?;
+
         let message = RtmpMessage::AudioData { data };
         let payload = message.into_message_payload(timestamp, stream_id)?;
-        let packet = self.serializer.serialize(&payload, false, can_be_dropped)?;
+        let packet = self.serialize_payload(&payload, false, drop_policy)?;
+        self.increment_outbound_queue_depth(stream_id);
         Ok(packet)
     }
 
+    /// Tells the session that an audio or video packet for the specified stream has been sent
+    /// to the client, freeing up space in the stream's outbound queue.  This only needs to be
+    /// called when `max_outbound_queue_per_stream` is configured.
+    pub fn outbound_packet_sent(&mut self, stream_id: u32) {
+        if let Some(depth) = self.outbound_queue_depths.get_mut(&stream_id) {
+            *depth = depth.saturating_sub(1);
+        }
+    }
+
+    /// Returns how many audio and video packets for the specified stream have not yet been
+    /// reported as sent with `outbound_packet_sent()`
+    pub fn outbound_queue_depth(&self, stream_id: u32) -> usize {
+        self.outbound_queue_depths
+            .get(&stream_id)
+            .cloned()
+            .unwrap_or(0)
+    }
+
     /// Sends a ping request to the client
     pub fn send_ping_request(&mut self) -> Result<(Packet, RtmpTimestamp), ServerSessionError> {
         let epoch = self.get_epoch();
@@ -398,10 +572,131 @@ impl ServerSession {
         };
 
         let payload = message.into_message_payload(epoch.clone(), 0)?;
-        let packet = self.serializer.serialize(&payload, false, false)?;
+        let packet = self.serialize_payload(&payload, false, DropPolicy::MustDeliver)?;
         Ok((packet, epoch))
     }
 
+    /// Tells the client that the specified stream is playing back a recording rather than a
+    /// live stream.  This is not sent automatically when a play request is accepted, since the
+    /// session does not know where the stream's data comes from, so servers serving recorded
+    /// content should send it after accepting a play request and before sending any data.
+    pub fn notify_stream_is_recorded(
+        &mut self,
+        stream_id: u32,
+    ) -> Result<Packet, ServerSessionError> {
+        self.send_user_control(
+            stream_id,
+            UserControlEventType::StreamIsRecorded,
+            None,
+            None,
+        )
+    }
+
+    /// Sends an arbitrary user control event to the client, for events that do not have a
+    /// dedicated helper (such as `StreamDry` when a live source stops sending data, or
+    /// `BufferEmpty` and `BufferReady` for buffer management).  The `buffer_length` and
+    /// `timestamp` values are only serialized for the event types that carry them.
+    pub fn send_user_control(
+        &mut self,
+        stream_id: u32,
+        event_type: UserControlEventType,
+        buffer_length: Option<u32>,
+        timestamp: Option<RtmpTimestamp>,
+    ) -> Result<Packet, ServerSessionError> {
+        let message = RtmpMessage::UserControl {
+            event_type,
+            stream_id: Some(stream_id),
+            timestamp,
+            buffer_length,
+        };
+
+        let payload = message.into_message_payload(self.get_epoch(), 0)?;
+        let packet = self.serialize_payload(&payload, false, DropPolicy::MustDeliver)?;
+        Ok(packet)
+    }
+
+    /// Returns all properties of the command object sent with the client's `connect` command,
+    /// such as `flashVer`, `swfUrl`, `pageUrl` and any custom properties.  This is `None` until a
+    /// connection request has been accepted.
+    pub fn connect_properties(&self) -> Option<&HashMap<String, Amf0Value>> {
+        self.connect_properties.as_ref()
+    }
+
+    /// Starts recording the audio and video that the client publishes on the specified stream
+    /// into an FLV file at the given path.  This is mostly useful for streams published with
+    /// `PublishMode::Record`.
+    ///
+    /// The recording continues until the returned handle is stopped or the client stops
+    /// publishing on the stream.  Starting a new recording on a stream that is already being
+    /// recorded ends the previous recording.
+    pub fn begin_recording<P: AsRef<Path>>(
+        &mut self,
+        stream_id: u32,
+        path: P,
+    ) -> Result<RecordingHandle, ServerSessionError> {
+        match self.active_streams.get(&stream_id) {
+            Some(stream) => match stream.current_state {
+                StreamState::Publishing { .. } => (),
+                _ => {
+                    return Err(ServerSessionError::RecordingRequiresPublishingStream { stream_id })
+                }
+            },
+
+            None => {
+                return Err(ServerSessionError::ActionAttemptedOnInactiveStream {
+                    action: "begin_recording".to_string(),
+                    stream_id,
+                })
+            }
+        }
+
+        let (recorder, handle) = Recorder::create(stream_id, path.as_ref())?;
+        self.finish_recording(stream_id);
+        self.recordings.insert(stream_id, recorder);
+
+        Ok(handle)
+    }
+
+    /// Starts measuring the bandwidth available to send data to the client.
+    ///
+    /// This sends a series of `onBWCheck` commands padded to increasing sizes.  Once the client
+    /// has responded to all of them a `ServerSessionEvent::BandwidthMeasured` event is raised with
+    /// the estimated bandwidth.  The estimate is only accurate if all returned packets are sent to
+    /// the client immediately and back to back.
+    pub fn start_bandwidth_probe(
+        &mut self,
+    ) -> Result<Vec<ServerSessionResult>, ServerSessionError> {
+        if self.current_state != SessionState::Connected {
+            return Err(ServerSessionError::BandwidthProbeRequiresConnection);
+        }
+
+        if self.bandwidth_probe.is_some() {
+            return Err(ServerSessionError::BandwidthProbeAlreadyInProgress);
+        }
+
+        let mut probe = BandwidthProbe::new();
+        let mut results = Vec::with_capacity(PROBE_PAYLOAD_SIZES.len());
+        for probe_size in PROBE_PAYLOAD_SIZES.iter() {
+            let transaction_id = self.next_outbound_transaction_id;
+            self.next_outbound_transaction_id += 1;
+
+            let message = RtmpMessage::Amf0Command {
+                command_name: "onBWCheck".to_string(),
+                transaction_id: transaction_id as f64,
+                command_object: Amf0Value::Null,
+                additional_arguments: vec![Amf0Value::Utf8String("0".repeat(*probe_size))],
+            };
+
+            let payload = message.into_message_payload(self.get_epoch(), 0)?;
+            let packet = self.serialize_payload(&payload, false, DropPolicy::MustDeliver)?;
+            probe.probe_sent(transaction_id, packet.bytes.len());
+            results.push(ServerSessionResult::OutboundResponse(packet));
+        }
+
+        self.bandwidth_probe = Some(probe);
+        Ok(results)
+    }
+
     /// Changes stream to Completed, and sends out an
     /// `onStatus(code: NetStream.Play.Complete)`
     pub fn finish_playing(&mut self, stream_id: u32) -> Result<Packet, ServerSessionError> {
@@ -443,7 +738,128 @@ impl ServerSession {
 
         let payload = status_message.into_message_payload(self.get_epoch(), stream_id)?;
 
-        Ok(self.serializer.serialize(&payload, false, false)?)
+        self.serialize_payload(&payload, false, DropPolicy::MustDeliver)
+    }
+
+    fn serialize_payload(
+        &mut self,
+        payload: &MessagePayload,
+        force_uncompressed: bool,
+        drop_policy: DropPolicy,
+    ) -> Result<Packet, ServerSessionError> {
+        let packet = self
+            .serializer
+            .serialize(payload, force_uncompressed, drop_policy)?;
+
+        if let Some(ref tracer) = self.message_tracer {
+            tracer(MessageTrace::new(
+                Direction::Outbound,
+                payload,
+                packet.bytes.len(),
+            ));
+        }
+
+        Ok(packet)
+    }
+
+    fn handle_payload(
+        &mut self,
+        payload: MessagePayload,
+    ) -> Result<Vec<ServerSessionResult>, ServerSessionError> {
+        let mut message = payload.to_rtmp_message()?;
+        for middleware in &self.middleware {
+            match middleware.intercept_inbound(&message, payload.message_stream_id) {
+                MiddlewareDecision::Allow => (),
+                MiddlewareDecision::Drop => return Ok(Vec::new()),
+                MiddlewareDecision::Replace(replacement) => message = replacement,
+            }
+        }
+
+        let results = match message {
+            RtmpMessage::Abort { stream_id } => self.handle_abort_message(stream_id)?,
+
+            RtmpMessage::Acknowledgement { sequence_number } => {
+                self.handle_acknowledgement_message(sequence_number)?
+            }
+
+            RtmpMessage::Amf0Command {
+                command_name,
+                transaction_id,
+                command_object,
+                additional_arguments,
+            } => self.handle_amf0_command(
+                payload.message_stream_id,
+                command_name,
+                transaction_id,
+                command_object,
+                additional_arguments,
+            )?,
+
+            RtmpMessage::Amf0Data { values } => {
+                self.handle_amf0_data(values, payload.message_stream_id)?
+            }
+
+            RtmpMessage::Aggregate { messages } => self.handle_aggregate(messages)?,
+
+            RtmpMessage::AudioData { data } => {
+                self.handle_audio_data(data, payload.message_stream_id, payload.timestamp)?
+            }
+
+            RtmpMessage::SetChunkSize { size } => self.handle_set_chunk_size(size)?,
+
+            RtmpMessage::SetPeerBandwidth { size, limit_type } => {
+                self.handle_set_peer_bandwidth(size, limit_type)?
+            }
+
+            RtmpMessage::UserControl {
+                event_type,
+                stream_id,
+                buffer_length,
+                timestamp,
+            } => self.handle_user_control(event_type, stream_id, buffer_length, timestamp)?,
+
+            RtmpMessage::VideoData { data } => {
+                self.handle_video_data(data, payload.message_stream_id, payload.timestamp)?
+            }
+
+            RtmpMessage::WindowAcknowledgement { size } => {
+                self.handle_window_acknowledgement(size)?
+            }
+
+            _ => vec![ServerSessionResult::UnhandleableMessageReceived(payload)],
+        };
+
+        Ok(results)
+    }
+
+    fn verify_outbound_queue_has_space(&self, stream_id: u32) -> Result<(), ServerSessionError> {
+        match self.max_outbound_queue_per_stream {
+            Some(limit) if self.outbound_queue_depth(stream_id) >= limit => {
+                Err(ServerSessionError::ClientOutputQueueFull { stream_id })
+            }
+
+            _ => Ok(()),
+        }
+    }
+
+    fn increment_outbound_queue_depth(&mut self, stream_id: u32) {
+        // Depths are only tracked when limited, so applications that never report sent packets
+        // don't accumulate counts
+        if self.max_outbound_queue_per_stream.is_some() {
+            *self.outbound_queue_depths.entry(stream_id).or_insert(0) += 1;
+        }
+    }
+
+    fn handle_aggregate(
+        &mut self,
+        messages: Vec<MessagePayload>,
+    ) -> Result<Vec<ServerSessionResult>, ServerSessionError> {
+        let mut results = Vec::new();
+        for payload in messages {
+            results.append(&mut self.handle_payload(payload)?);
+        }
+
+        Ok(results)
     }
 
     fn handle_abort_message(
@@ -473,11 +889,14 @@ impl ServerSession {
     ) -> Result<Vec<ServerSessionResult>, ServerSessionError> {
         let results = match name.as_str() {
             "connect" => self.handle_command_connect(transaction_id, command_object)?,
-            "closeStream" => self.handle_command_close_stream(additional_args)?,
+            "closeStream" => self.handle_command_close_stream(stream_id, additional_args)?,
             "createStream" => self.handle_command_create_stream(transaction_id)?,
             "deleteStream" => self.handle_command_delete_stream(additional_args)?,
             "play" => self.handle_command_play(stream_id, transaction_id, additional_args)?,
             "publish" => self.handle_command_publish(stream_id, transaction_id, additional_args)?,
+            "_result" if self.is_bandwidth_probe_response(transaction_id) => {
+                self.handle_bandwidth_probe_response(transaction_id)
+            }
 
             _ => vec![ServerSessionResult::RaisedEvent(
                 ServerSessionEvent::UnhandleableAmf0Command {
@@ -492,57 +911,82 @@ impl ServerSession {
         Ok(results)
     }
 
+    fn is_bandwidth_probe_response(&self, transaction_id: f64) -> bool {
+        match self.bandwidth_probe {
+            Some(ref probe) => probe.is_waiting_for(transaction_id as u32),
+            None => false,
+        }
+    }
+
+    fn handle_bandwidth_probe_response(&mut self, transaction_id: f64) -> Vec<ServerSessionResult> {
+        let bits_per_second = match self.bandwidth_probe {
+            Some(ref mut probe) => {
+                match probe.response_received(transaction_id as u32, Instant::now()) {
+                    Some(bits_per_second) => bits_per_second,
+                    None => return Vec::new(),
+                }
+            }
+
+            None => return Vec::new(),
+        };
+
+        self.bandwidth_probe = None;
+        let event = ServerSessionEvent::BandwidthMeasured { bits_per_second };
+        vec![ServerSessionResult::RaisedEvent(event)]
+    }
+
     fn handle_command_connect(
         &mut self,
         transaction_id: f64,
         command_object: Amf0Value,
     ) -> Result<Vec<ServerSessionResult>, ServerSessionError> {
-        let mut properties = match command_object {
+        let properties = match command_object {
             Amf0Value::Object(properties) => properties,
             _ => return Err(ServerSessionError::NoAppNameForConnectionRequest),
         };
 
-        let app_name = match properties.remove("app") {
-            Some(value) => match value {
-                Amf0Value::Utf8String(mut app) => {
-                    if app.ends_with("/") {
-                        app.pop();
-                    }
-
-                    app
-                }
-                _ => return Err(ServerSessionError::NoAppNameForConnectionRequest),
-            },
+        let app_name = match properties.get("app").and_then(|value| value.as_str()) {
+            Some(app) => app.strip_suffix('/').unwrap_or(app).to_string(),
             None => return Err(ServerSessionError::NoAppNameForConnectionRequest),
         };
 
-        self.object_encoding = match properties.remove("objectEncoding") {
-            Some(value) => match value {
-                Amf0Value::Number(number) => number,
-                _ => 0.0,
-            },
-            None => 0.0,
+        if let Some(ref auth_callback) = self.auth_callback {
+            if !auth_callback(&app_name, &properties) {
+                let packet = self.create_error_packet(
+                    "NetConnection.Connect.Rejected",
+                    "Connection rejected",
+                    transaction_id,
+                    0,
+                )?;
+
+                return Ok(vec![ServerSessionResult::OutboundResponse(packet)]);
+            }
+        }
+
+        self.object_encoding = match properties.get("objectEncoding") {
+            Some(Amf0Value::Number(number)) => *number,
+            _ => 0.0,
         };
 
         let request = OutstandingRequest::ConnectionRequest {
             app_name: app_name.clone(),
             transaction_id,
+            properties,
         };
 
-        let request_number = self.next_request_number;
-        self.next_request_number = self.next_request_number + 1;
-        self.outstanding_requests.insert(request_number, request);
-
-        let event = ServerSessionEvent::ConnectionRequested {
-            app_name: app_name,
-            request_id: request_number,
-        };
-
-        Ok(vec![ServerSessionResult::RaisedEvent(event)])
+        let object_encoding = self.object_encoding;
+        self.raise_request(request, |request_id| {
+            ServerSessionEvent::ConnectionRequested {
+                app_name,
+                request_id,
+                object_encoding,
+            }
+        })
     }
 
     fn handle_command_close_stream(
         &mut self,
+        message_stream_id: u32,
         mut arguments: Vec<Amf0Value>,
     ) -> Result<Vec<ServerSessionResult>, ServerSessionError> {
         if self.current_state != SessionState::Connected {
@@ -554,16 +998,19 @@ impl ServerSession {
             None => return Ok(Vec::new()),
         };
 
-        // First argument should be the stream id to close
-        if arguments.len() == 0 {
-            return Ok(Vec::new());
-        }
-
-        let stream_id = match arguments.remove(0) {
-            Amf0Value::Number(x) => x as u32,
-            _ => return Ok(Vec::new()),
+        // First argument should be the stream id to close, but many clients send `closeStream`
+        // without any arguments on the message stream they want closed
+        let stream_id = if arguments.is_empty() {
+            message_stream_id
+        } else {
+            match arguments.remove(0).get_u32() {
+                Some(x) => x,
+                None => return Ok(Vec::new()),
+            }
         };
 
+        self.finish_recording(stream_id);
+
         let stream = match self.active_streams.get_mut(&stream_id) {
             Some(x) => x,
             None => return Ok(Vec::new()),
@@ -607,6 +1054,17 @@ impl ServerSession {
         &mut self,
         transaction_id: f64,
     ) -> Result<Vec<ServerSessionResult>, ServerSessionError> {
+        if self.active_streams.len() >= self.max_streams as usize {
+            let packet = self.create_error_packet(
+                "NetStream.Failed",
+                "Maximum stream count exceeded",
+                transaction_id,
+                0,
+            )?;
+
+            return Ok(vec![ServerSessionResult::OutboundResponse(packet)]);
+        }
+
         let new_stream_id = self.next_stream_id;
         self.next_stream_id = self.next_stream_id + 1;
 
@@ -645,11 +1103,14 @@ impl ServerSession {
         }
 
         // First argument is expected to be the stream id
-        let stream_id = match arguments.remove(0) {
-            Amf0Value::Number(x) => x as u32,
-            _ => return Ok(Vec::new()),
+        let stream_id = match arguments.remove(0).get_u32() {
+            Some(x) => x,
+            None => return Ok(Vec::new()),
         };
 
+        self.finish_recording(stream_id);
+        self.outbound_queue_depths.remove(&stream_id);
+
         let stream = match self.active_streams.remove(&stream_id) {
             Some(stream) => stream,
             None => return Ok(Vec::new()),
@@ -773,18 +1234,15 @@ impl ServerSession {
             stream_id,
         };
 
-        let request_number = self.next_request_number;
-        self.next_request_number = self.next_request_number + 1;
-        self.outstanding_requests.insert(request_number, request);
-
-        let event = ServerSessionEvent::PublishStreamRequested {
-            request_id: request_number,
-            app_name,
-            stream_key,
-            mode,
-        };
-
-        Ok(vec![ServerSessionResult::RaisedEvent(event)])
+        self.raise_request(request, |request_id| {
+            ServerSessionEvent::PublishStreamRequested {
+                request_id,
+                app_name,
+                stream_key,
+                mode,
+                stream_id,
+            }
+        })
     }
 
     fn handle_command_play(
@@ -847,7 +1305,8 @@ impl ServerSession {
                     } else if x == -1.0 {
                         PlayStartValue::LiveOnly
                     } else if x >= 0.0 {
-                        PlayStartValue::StartTimeInSeconds(x as u32)
+                        // The start value is in seconds but may have sub-second precision
+                        PlayStartValue::StartTimeInMillis((x * 1000.0).round() as u32)
                     } else {
                         PlayStartValue::LiveOrRecorded // Invalid value so return default
                     }
@@ -889,20 +1348,44 @@ impl ServerSession {
             stream_id,
         };
 
-        let request_number = self.next_request_number;
-        self.next_request_number = self.next_request_number + 1;
-        self.outstanding_requests.insert(request_number, request);
+        self.raise_request(request, |request_id| {
+            ServerSessionEvent::PlayStreamRequested {
+                request_id,
+                app_name,
+                stream_key,
+                start_at,
+                duration,
+                reset,
+                stream_id,
+            }
+        })
+    }
 
-        let event = ServerSessionEvent::PlayStreamRequested {
-            request_id: request_number,
-            app_name,
-            stream_key,
-            start_at,
-            duration,
-            reset,
-            stream_id,
-        };
+    /// Stores the request as outstanding and raises the event for it, unless the session was
+    /// created with `new_permissive()`, in which case the request is accepted immediately.
+    fn raise_request<F>(
+        &mut self,
+        request: OutstandingRequest,
+        create_event: F,
+    ) -> Result<Vec<ServerSessionResult>, ServerSessionError>
+    where
+        F: FnOnce(RequestId) -> ServerSessionEvent,
+    {
+        let request_id = self.next_request_id;
+        self.next_request_id = request_id.next();
+        self.outstanding_requests.insert(
+            request_id,
+            TimedRequest {
+                request,
+                requested_at: Instant::now(),
+            },
+        );
+
+        if self.auto_accept {
+            return self.accept_request(request_id);
+        }
 
+        let event = create_event(request_id);
         Ok(vec![ServerSessionResult::RaisedEvent(event)])
     }
 
@@ -963,11 +1446,12 @@ impl ServerSession {
             None => return Ok(Vec::new()), // Return nothing since this was not sent on an active stream
         };
 
+        // Encoders send the metadata as either an object or an ECMA array
         let mut metadata = StreamMetadata::new();
-        let object = data.remove(1);
-        let properties_option = object.get_object_properties();
-        match properties_option {
-            Some(properties) => metadata.apply_metadata_values(properties),
+        match data.remove(1) {
+            Amf0Value::Object(properties) | Amf0Value::EcmaArray(properties) => {
+                metadata.apply_metadata_values(properties)
+            }
             _ => (),
         }
 
@@ -981,11 +1465,18 @@ impl ServerSession {
     }
 
     fn handle_audio_data(
-        &self,
+        &mut self,
         data: Bytes,
         stream_id: u32,
         timestamp: RtmpTimestamp,
     ) -> Result<Vec<ServerSessionResult>, ServerSessionError> {
+        if data.len() > self.max_inbound_audio_bytes {
+            return Err(ServerSessionError::InboundMessageTooLarge {
+                type_name: "audio",
+                size: data.len(),
+            });
+        }
+
         if self.current_state != SessionState::Connected {
             // Audio data sent before connected, just ignore it.
             return Ok(Vec::new());
@@ -1010,6 +1501,8 @@ impl ServerSession {
             None => return Ok(Vec::new()), // Audio sent over an invalid stream, ignore it
         };
 
+        self.record_tag(stream_id, 8, timestamp, &data);
+
         let event = ServerSessionEvent::AudioDataReceived {
             stream_key: publish_stream_key,
             app_name,
@@ -1039,8 +1532,8 @@ impl ServerSession {
     fn handle_user_control(
         &mut self,
         event_type: UserControlEventType,
-        _stream_id: Option<u32>,
-        _buffer_length: Option<u32>,
+        stream_id: Option<u32>,
+        buffer_length: Option<u32>,
         timestamp: Option<RtmpTimestamp>,
     ) -> Result<Vec<ServerSessionResult>, ServerSessionError> {
         match event_type {
@@ -1053,7 +1546,7 @@ impl ServerSession {
                 };
 
                 let payload = message.into_message_payload(self.get_epoch(), 0)?;
-                let response = self.serializer.serialize(&payload, false, false)?;
+                let response = self.serialize_payload(&payload, false, DropPolicy::MustDeliver)?;
                 Ok(vec![ServerSessionResult::OutboundResponse(response)])
             }
 
@@ -1063,16 +1556,32 @@ impl ServerSession {
                 Ok(vec![ServerSessionResult::RaisedEvent(event)])
             }
 
-            _ => Ok(Vec::new()),
+            _ => {
+                let event = ServerSessionEvent::UnhandledUserControlEvent {
+                    event_type,
+                    stream_id,
+                    buffer_length,
+                    timestamp,
+                };
+
+                Ok(vec![ServerSessionResult::RaisedEvent(event)])
+            }
         }
     }
 
     fn handle_video_data(
-        &self,
+        &mut self,
         data: Bytes,
         stream_id: u32,
         timestamp: RtmpTimestamp,
     ) -> Result<Vec<ServerSessionResult>, ServerSessionError> {
+        if data.len() > self.max_inbound_video_bytes {
+            return Err(ServerSessionError::InboundMessageTooLarge {
+                type_name: "video",
+                size: data.len(),
+            });
+        }
+
         if self.current_state != SessionState::Connected {
             // Video data sent before connected, just ignore it.
             return Ok(Vec::new());
@@ -1097,6 +1606,8 @@ impl ServerSession {
             None => return Ok(Vec::new()), // Video sent over an invalid stream, ignore it
         };
 
+        self.record_tag(stream_id, 9, timestamp, &data);
+
         let event = ServerSessionEvent::VideoDataReceived {
             stream_key: publish_stream_key,
             app_name,
@@ -1119,8 +1630,10 @@ impl ServerSession {
         &mut self,
         app_name: String,
         transaction_id: f64,
+        properties: HashMap<String, Amf0Value>,
     ) -> Result<Vec<ServerSessionResult>, ServerSessionError> {
         self.connected_app_name = Some(app_name.clone());
+        self.connect_properties = Some(properties);
         self.current_state = SessionState::Connected;
 
         let mut command_object_properties = HashMap::new();
@@ -1149,11 +1662,28 @@ impl ServerSession {
         };
 
         let payload = message.into_message_payload(self.get_epoch(), 0)?;
-        let packet = self.serializer.serialize(&payload, false, false)?;
+        let packet = self.serialize_payload(&payload, false, DropPolicy::MustDeliver)?;
 
         Ok(vec![ServerSessionResult::OutboundResponse(packet)])
     }
 
+    fn record_tag(&mut self, stream_id: u32, type_id: u8, timestamp: RtmpTimestamp, data: &Bytes) {
+        let is_recording = match self.recordings.get(&stream_id) {
+            Some(recorder) => recorder.write_tag(type_id, timestamp.value, &data[..]),
+            None => return,
+        };
+
+        if !is_recording {
+            self.recordings.remove(&stream_id);
+        }
+    }
+
+    fn finish_recording(&mut self, stream_id: u32) {
+        if let Some(recorder) = self.recordings.remove(&stream_id) {
+            recorder.finish();
+        }
+    }
+
     fn accept_publish_request(
         &mut self,
         stream_id: u32,
@@ -1190,9 +1720,8 @@ impl ServerSession {
 
         let stream_begin_payload =
             stream_begin_message.into_message_payload(self.get_epoch(), stream_id)?;
-        let stream_begin_packet = self
-            .serializer
-            .serialize(&stream_begin_payload, false, false)?;
+        let stream_begin_packet =
+            self.serialize_payload(&stream_begin_payload, false, DropPolicy::MustDeliver)?;
 
         let status_object =
             create_status_object("status", "NetStream.Publish.Start", description.as_ref());
@@ -1205,9 +1734,8 @@ impl ServerSession {
 
         let publish_start_payload =
             publish_start_message.into_message_payload(self.get_epoch(), stream_id)?;
-        let publish_packet = self
-            .serializer
-            .serialize(&publish_start_payload, false, false)?;
+        let publish_packet =
+            self.serialize_payload(&publish_start_payload, false, DropPolicy::MustDeliver)?;
 
         Ok(vec![
             ServerSessionResult::OutboundResponse(stream_begin_packet),
@@ -1284,21 +1812,24 @@ impl ServerSession {
 
         let stream_begin_payload =
             stream_begin_message.into_message_payload(self.get_epoch(), stream_id)?;
-        let stream_begin_packet = self
-            .serializer
-            .serialize(&stream_begin_payload, false, false)?;
+        let stream_begin_packet =
+            self.serialize_payload(&stream_begin_payload, false, DropPolicy::MustDeliver)?;
 
         let start_payload = start_message.into_message_payload(self.get_epoch(), stream_id)?;
-        let start_packet = self.serializer.serialize(&start_payload, false, false)?;
+        let start_packet =
+            self.serialize_payload(&start_payload, false, DropPolicy::MustDeliver)?;
 
         let data1_payload = data1_message.into_message_payload(self.get_epoch(), stream_id)?;
-        let data1_packet = self.serializer.serialize(&data1_payload, false, false)?;
+        let data1_packet =
+            self.serialize_payload(&data1_payload, false, DropPolicy::MustDeliver)?;
 
         let data2_payload = data2_message.into_message_payload(self.get_epoch(), stream_id)?;
-        let data2_packet = self.serializer.serialize(&data2_payload, false, false)?;
+        let data2_packet =
+            self.serialize_payload(&data2_payload, false, DropPolicy::MustDeliver)?;
 
         let reset_payload = reset_message.into_message_payload(self.get_epoch(), stream_id)?;
-        let reset_packet = self.serializer.serialize(&reset_payload, false, false)?;
+        let reset_packet =
+            self.serialize_payload(&reset_payload, false, DropPolicy::MustDeliver)?;
 
         Ok(vec![
             ServerSessionResult::OutboundResponse(reset_packet),
@@ -1324,7 +1855,7 @@ impl ServerSession {
         };
 
         let payload = message.into_message_payload(self.get_epoch(), stream_id)?;
-        let packet = self.serializer.serialize(&payload, false, false)?;
+        let packet = self.serialize_payload(&payload, false, DropPolicy::MustDeliver)?;
         Ok(packet)
     }
 
@@ -1343,7 +1874,7 @@ impl ServerSession {
         };
 
         let payload = message.into_message_payload(self.get_epoch(), stream_id)?;
-        let packet = self.serializer.serialize(&payload, false, false)?;
+        let packet = self.serialize_payload(&payload, false, DropPolicy::MustDeliver)?;
         Ok(packet)
     }
 
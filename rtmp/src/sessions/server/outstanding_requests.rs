@@ -1,9 +1,13 @@
 use super::PublishMode;
+use rml_amf0::Amf0Value;
+use std::collections::HashMap;
+use std::time::Instant;
 
 pub enum OutstandingRequest {
     ConnectionRequest {
         app_name: String,
         transaction_id: f64,
+        properties: HashMap<String, Amf0Value>,
     },
 
     PublishRequested {
@@ -17,3 +21,9 @@ pub enum OutstandingRequest {
         stream_id: u32,
     },
 }
+
+/// An outstanding request along with when it was raised, so it can be timed out
+pub struct TimedRequest {
+    pub request: OutstandingRequest,
+    pub requested_at: Instant,
+}
@@ -1,5 +1,8 @@
-/// The type of publishing being performed or requested
+/// The type of publishing being performed or requested.  With the `serde` feature enabled it is
+/// serialized as a lowercase string, such as `"live"`.
 #[derive(Eq, PartialEq, Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(::serde::Serialize, ::serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
 pub enum PublishMode {
     /// Live data is being published without recording it in a file
     Live,
@@ -0,0 +1,48 @@
+use std::fmt;
+
+/// Identifies a request raised by a `ServerSession` that is waiting to be accepted or rejected.
+///
+/// Request ids can only be obtained from the events a session raises, so an arbitrary number
+/// cannot be passed to `accept_request()` or `reject_request()`:
+///
+/// ```compile_fail
+/// # use rml_rtmp::sessions::{ServerSession, ServerSessionConfig};
+/// let (mut session, _) = ServerSession::new(ServerSessionConfig::new()).unwrap();
+/// session.accept_request(0_u32);
+/// ```
+#[derive(Eq, PartialEq, Hash, Clone, Copy, Debug)]
+pub struct RequestId(u32);
+
+impl RequestId {
+    pub(crate) fn new(value: u32) -> RequestId {
+        RequestId(value)
+    }
+
+    /// Returns the id that follows this one
+    pub(crate) fn next(self) -> RequestId {
+        RequestId(self.0.wrapping_add(1))
+    }
+}
+
+impl fmt::Display for RequestId {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::RequestId;
+
+    #[test]
+    fn request_ids_with_same_value_are_equal() {
+        assert_eq!(RequestId::new(5), RequestId::new(5));
+        assert_ne!(RequestId::new(5), RequestId::new(6));
+        assert_eq!(RequestId::new(5).next(), RequestId::new(6));
+    }
+
+    #[test]
+    fn request_id_displays_as_its_number() {
+        assert_eq!(RequestId::new(42).to_string(), "42");
+    }
+}
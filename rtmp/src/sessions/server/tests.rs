@@ -1,9 +1,15 @@
 use super::*;
 use bytes::BytesMut;
-use chunk_io::ChunkDeserializer;
-use messages::{MessagePayload, PeerBandwidthLimitType, RtmpMessage, UserControlEventType};
+use chunk_io::{ChunkDeserializer, ChunkSerializationError};
+use messages::{
+    MessageDeserializationError, MessagePayload, PeerBandwidthLimitType, RtmpMessage,
+    UserControlEventType,
+};
 use rml_amf0::Amf0Value;
 use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
 
 const DEFAULT_CHUNK_SIZE: u32 = 1111;
 const DEFAULT_PEER_BANDWIDTH: u32 = 2222;
@@ -86,13 +92,298 @@ fn on_bw_done_not_sent_when_config_disables_it() {
     }
 }
 
+#[test]
+fn error_when_creating_session_with_invalid_chunk_size() {
+    for chunk_size in [0, 16_777_216] {
+        let mut config = get_basic_config();
+        config.chunk_size = chunk_size;
+
+        match ServerSession::new(config) {
+            Err(ServerSessionError::InvalidChunkSize { chunk_size: x }) if x == chunk_size => (),
+            Err(x) => panic!("Unexpected error: {:?}", x),
+            Ok(_) => panic!("Expected an error for chunk size {}", chunk_size),
+        }
+    }
+}
+
+#[test]
+fn config_with_chunk_size_validates_chunk_size() {
+    match ServerSessionConfig::with_chunk_size(0) {
+        Err(ServerSessionError::InvalidChunkSize { chunk_size: 0 }) => (),
+        Err(x) => panic!("Unexpected error: {:?}", x),
+        Ok(_) => panic!("Expected an error for a chunk size of 0"),
+    }
+
+    let config = ServerSessionConfig::with_chunk_size(65536).unwrap();
+    assert_eq!(config.chunk_size, 65536, "Unexpected chunk size");
+}
+
+#[test]
+fn configured_peer_max_chunk_size_is_used_for_inbound_chunks() {
+    let mut config = get_basic_config();
+    config.peer_max_chunk_size = 4096;
+    let (mut deserializer, mut serializer, mut session) = common_setup(&config);
+
+    // Don't send the set chunk size packet, as the server should already be expecting it
+    let _ = serializer
+        .set_max_chunk_size(4096, RtmpTimestamp::new(0))
+        .unwrap();
+
+    let app_name = "a".repeat(500);
+    let connect_payload = create_connect_message(app_name.clone(), 15, 0, 0.0);
+    let connect_packet = serializer
+        .serialize(&connect_payload, true, DropPolicy::MustDeliver)
+        .unwrap();
+    let connect_results = session.handle_input(&connect_packet.bytes[..]).unwrap();
+
+    let (_, events) = split_results(&mut deserializer, connect_results);
+    assert_vec_contains!(events, ServerSessionEvent::ConnectionRequested {
+        app_name: ref x,
+        request_id: _,
+        object_encoding: _,
+    } if x == &app_name);
+}
+
+#[test]
+fn connection_requested_event_contains_object_encoding() {
+    let (mut deserializer, mut serializer, mut session) = common_basic_setup();
+    let connect_payload = create_connect_message("some_app".to_string(), 15, 0, 3.0);
+    let connect_packet = serializer
+        .serialize(&connect_payload, true, DropPolicy::MustDeliver)
+        .unwrap();
+    let connect_results = session.handle_input(&connect_packet.bytes[..]).unwrap();
+
+    let (_, events) = split_results(&mut deserializer, connect_results);
+    assert_vec_contains!(events, ServerSessionEvent::ConnectionRequested {
+        app_name: _,
+        request_id: _,
+        object_encoding,
+    } if *object_encoding == 3.0);
+}
+
+#[test]
+fn connection_requested_event_defaults_object_encoding_to_zero() {
+    let (mut deserializer, mut serializer, mut session) = common_basic_setup();
+
+    let mut properties = HashMap::new();
+    properties.insert(
+        "app".to_string(),
+        Amf0Value::Utf8String("some_app".to_string()),
+    );
+
+    let message = RtmpMessage::Amf0Command {
+        command_name: "connect".to_string(),
+        transaction_id: 1.0,
+        command_object: Amf0Value::Object(properties),
+        additional_arguments: vec![],
+    };
+
+    let connect_payload = message
+        .into_message_payload(RtmpTimestamp::new(15), 0)
+        .unwrap();
+    let connect_packet = serializer
+        .serialize(&connect_payload, true, DropPolicy::MustDeliver)
+        .unwrap();
+    let connect_results = session.handle_input(&connect_packet.bytes[..]).unwrap();
+
+    let (_, events) = split_results(&mut deserializer, connect_results);
+    assert_vec_contains!(events, ServerSessionEvent::ConnectionRequested {
+        app_name: _,
+        request_id: _,
+        object_encoding,
+    } if *object_encoding == 0.0);
+}
+
+#[test]
+fn connect_properties_available_after_connection_accepted() {
+    let (mut deserializer, mut serializer, mut session) = common_basic_setup();
+
+    let mut properties = HashMap::new();
+    properties.insert(
+        "app".to_string(),
+        Amf0Value::Utf8String("some_app".to_string()),
+    );
+    properties.insert(
+        "flashVer".to_string(),
+        Amf0Value::Utf8String("FMLE/3.0".to_string()),
+    );
+
+    let message = RtmpMessage::Amf0Command {
+        command_name: "connect".to_string(),
+        transaction_id: 1.0,
+        command_object: Amf0Value::Object(properties),
+        additional_arguments: vec![],
+    };
+
+    let connect_payload = message
+        .into_message_payload(RtmpTimestamp::new(15), 0)
+        .unwrap();
+    let connect_packet = serializer
+        .serialize(&connect_payload, true, DropPolicy::MustDeliver)
+        .unwrap();
+    let connect_results = session.handle_input(&connect_packet.bytes[..]).unwrap();
+    let (_, events) = split_results(&mut deserializer, connect_results);
+
+    assert!(
+        session.connect_properties().is_none(),
+        "Expected no connect properties before the connection is accepted"
+    );
+
+    let request_id = match events[0] {
+        ServerSessionEvent::ConnectionRequested { request_id, .. } => request_id,
+        _ => panic!("First event was not as expected: {:?}", events[0]),
+    };
+
+    let accept_results = session.accept_request(request_id).unwrap();
+    consume_results(&mut deserializer, accept_results);
+
+    let properties = session
+        .connect_properties()
+        .expect("Expected connect properties after accepting the connection");
+
+    assert_eq!(
+        properties.get("flashVer"),
+        Some(&Amf0Value::Utf8String("FMLE/3.0".to_string())),
+        "Unexpected flashVer property"
+    );
+    assert_eq!(
+        properties.get("app"),
+        Some(&Amf0Value::Utf8String("some_app".to_string())),
+        "Unexpected app property"
+    );
+}
+
+#[test]
+fn connection_request_raised_when_auth_callback_allows_it() {
+    let mut config = get_basic_config();
+    config.auth_callback = Some(Arc::new(|app_name, properties| {
+        app_name == "some_app" && properties.get("objectEncoding") == Some(&Amf0Value::Number(3.0))
+    }));
+
+    let (mut deserializer, mut serializer, mut session) = common_setup(&config);
+    let connect_payload = create_connect_message("some_app".to_string(), 15, 0, 3.0);
+    let connect_packet = serializer
+        .serialize(&connect_payload, true, DropPolicy::MustDeliver)
+        .unwrap();
+    let connect_results = session.handle_input(&connect_packet.bytes[..]).unwrap();
+
+    let (responses, events) = split_results(&mut deserializer, connect_results);
+    assert_eq!(responses.len(), 0, "Expected no responses");
+    assert_vec_contains!(events, ServerSessionEvent::ConnectionRequested {
+        app_name: ref x,
+        request_id: _,
+        object_encoding: _,
+    } if x == "some_app");
+}
+
+#[test]
+fn connection_automatically_rejected_when_auth_callback_denies_it() {
+    let mut config = get_basic_config();
+    config.auth_callback = Some(Arc::new(|app_name, _| app_name != "some_app"));
+
+    let (mut deserializer, mut serializer, mut session) = common_setup(&config);
+    let connect_payload = create_connect_message("some_app".to_string(), 15, 0, 0.0);
+    let connect_packet = serializer
+        .serialize(&connect_payload, true, DropPolicy::MustDeliver)
+        .unwrap();
+    let connect_results = session.handle_input(&connect_packet.bytes[..]).unwrap();
+
+    let (responses, events) = split_results(&mut deserializer, connect_results);
+    assert_eq!(events.len(), 0, "Expected no events");
+    assert_eq!(responses.len(), 1, "Unexpected number of responses");
+    match responses[0] {
+        (
+            _,
+            RtmpMessage::Amf0Command {
+                ref command_name,
+                transaction_id,
+                ref additional_arguments,
+                ..
+            },
+        ) if command_name == "_error" && transaction_id == 1.0 => {
+            match additional_arguments.first() {
+                Some(Amf0Value::Object(properties)) => assert_eq!(
+                    properties.get("code"),
+                    Some(&Amf0Value::Utf8String(
+                        "NetConnection.Connect.Rejected".to_string()
+                    )),
+                    "Unexpected error code"
+                ),
+
+                x => panic!("Expected status object, instead received {:?}", x),
+            }
+        }
+
+        ref x => panic!("Expected _error response, instead received {:?}", x),
+    }
+}
+
+#[test]
+fn connection_request_rejected_once_it_times_out() {
+    let config = get_basic_config();
+    let (mut deserializer, mut serializer, mut session) = common_setup(&config);
+    let connect_payload = create_connect_message("some_app".to_string(), 15, 0, 0.0);
+    let connect_packet = serializer
+        .serialize(&connect_payload, true, DropPolicy::MustDeliver)
+        .unwrap();
+    let connect_results = session.handle_input(&connect_packet.bytes[..]).unwrap();
+    let (_, events) = split_results(&mut deserializer, connect_results);
+    let request_id = match events.first() {
+        Some(ServerSessionEvent::ConnectionRequested { request_id, .. }) => *request_id,
+        x => panic!("Expected ConnectionRequested event, instead got {:?}", x),
+    };
+
+    let requested_at = Instant::now();
+    let results = session.check_request_timeouts(requested_at, 5000).unwrap();
+    assert_eq!(results.len(), 0, "Expected no results before the timeout");
+
+    let results = session
+        .check_request_timeouts(requested_at + Duration::from_millis(5001), 5000)
+        .unwrap();
+    let (responses, events) = split_results(&mut deserializer, results);
+    assert_eq!(events.len(), 0, "Expected no events");
+    assert_eq!(responses.len(), 1, "Unexpected number of responses");
+    match responses[0] {
+        (
+            _,
+            RtmpMessage::Amf0Command {
+                ref command_name,
+                transaction_id,
+                ref additional_arguments,
+                ..
+            },
+        ) if command_name == "_error" && transaction_id == 1.0 => {
+            match additional_arguments.first() {
+                Some(Amf0Value::Object(properties)) => assert_eq!(
+                    properties.get("code"),
+                    Some(&Amf0Value::Utf8String(
+                        "NetConnection.Connect.Rejected".to_string()
+                    )),
+                    "Unexpected error code"
+                ),
+
+                x => panic!("Expected status object, instead received {:?}", x),
+            }
+        }
+
+        ref x => panic!("Expected _error response, instead received {:?}", x),
+    }
+
+    match session.accept_request(request_id) {
+        Err(ServerSessionError::InvalidRequestId) => (),
+        x => panic!("Expected InvalidRequestId error, instead got {:?}", x),
+    }
+}
+
 #[test]
 fn can_accept_connection_request() {
     let config = get_basic_config();
     let (mut deserializer, mut serializer, mut session) = common_setup(&config);
 
     let connect_payload = create_connect_message("some_app".to_string(), 15, 0, 0.0);
-    let connect_packet = serializer.serialize(&connect_payload, true, false).unwrap();
+    let connect_packet = serializer
+        .serialize(&connect_payload, true, DropPolicy::MustDeliver)
+        .unwrap();
     let connect_results = session.handle_input(&connect_packet.bytes[..]).unwrap();
     assert_eq!(
         connect_results.len(),
@@ -106,6 +397,7 @@ fn can_accept_connection_request() {
         ServerSessionEvent::ConnectionRequested {
             ref app_name,
             request_id,
+            object_encoding: _,
         } if app_name == "some_app" => request_id,
         _ => panic!("First event was not as expected: {:?}", events[0]),
     };
@@ -184,7 +476,9 @@ fn connect_request_strips_trailing_slash() {
     let (mut deserializer, mut serializer, mut session) = common_basic_setup();
 
     let connect_payload = create_connect_message("some_app/".to_string(), 15, 0, 0.0);
-    let connect_packet = serializer.serialize(&connect_payload, true, false).unwrap();
+    let connect_packet = serializer
+        .serialize(&connect_payload, true, DropPolicy::MustDeliver)
+        .unwrap();
     let connect_results = session.handle_input(&connect_packet.bytes[..]).unwrap();
     assert_eq!(
         connect_results.len(),
@@ -198,6 +492,7 @@ fn connect_request_strips_trailing_slash() {
         ServerSessionEvent::ConnectionRequested {
             ref app_name,
             request_id: _,
+            object_encoding: _,
         } => assert_eq!(app_name, "some_app", "Unexpected app name"),
         _ => panic!("First event was not as expected: {:?}", events[0]),
     };
@@ -209,7 +504,9 @@ fn accepted_connection_responds_with_same_object_encoding_value_as_connection_re
     let (mut deserializer, mut serializer, mut session) = common_setup(&config);
 
     let connect_payload = create_connect_message("some_app".to_string(), 15, 0, 3.0);
-    let connect_packet = serializer.serialize(&connect_payload, true, false).unwrap();
+    let connect_packet = serializer
+        .serialize(&connect_payload, true, DropPolicy::MustDeliver)
+        .unwrap();
     let connect_results = session.handle_input(&connect_packet.bytes[..]).unwrap();
     assert_eq!(
         connect_results.len(),
@@ -223,6 +520,7 @@ fn accepted_connection_responds_with_same_object_encoding_value_as_connection_re
         ServerSessionEvent::ConnectionRequested {
             ref app_name,
             request_id,
+            object_encoding: _,
         } if app_name == "some_app" => request_id,
         _ => panic!("First event was not as expected: {:?}", events[0]),
     };
@@ -311,7 +609,9 @@ fn can_create_stream_on_connected_session() {
     let payload = message
         .into_message_payload(RtmpTimestamp::new(0), 0)
         .unwrap();
-    let packet = serializer.serialize(&payload, true, false).unwrap();
+    let packet = serializer
+        .serialize(&payload, true, DropPolicy::MustDeliver)
+        .unwrap();
     let results = session.handle_input(&packet.bytes[..]).unwrap();
     let (responses, _) = split_results(&mut deserializer, results);
 
@@ -366,7 +666,7 @@ fn can_accept_live_publishing_to_requested_stream_key() {
         .into_message_payload(RtmpTimestamp::new(0), stream_id)
         .unwrap();
     let publish_packet = serializer
-        .serialize(&publish_payload, false, false)
+        .serialize(&publish_payload, false, DropPolicy::MustDeliver)
         .unwrap();
     let publish_results = session.handle_input(&publish_packet.bytes[..]).unwrap();
     let (_, events) = split_results(&mut deserializer, publish_results);
@@ -378,6 +678,7 @@ fn can_accept_live_publishing_to_requested_stream_key() {
             ref stream_key,
             request_id: returned_request_id,
             mode: PublishMode::Live,
+            stream_id: _,
         } if app_name == "some_app" && stream_key == "stream_key" => returned_request_id,
 
         _ => panic!("Unexpected first event found: {:?}", events[0]),
@@ -463,7 +764,7 @@ fn can_receive_and_raise_event_for_metadata_from_obs() {
         .into_message_payload(RtmpTimestamp::new(0), stream_id)
         .unwrap();
     let metadata_packet = serializer
-        .serialize(&metadata_payload, false, false)
+        .serialize(&metadata_payload, false, DropPolicy::MustDeliver)
         .unwrap();
     let metadata_results = session.handle_input(&metadata_packet.bytes[..]).unwrap();
     let (_, mut events) = split_results(&mut deserializer, metadata_results);
@@ -505,6 +806,11 @@ fn can_receive_and_raise_event_for_metadata_from_obs() {
                 Some(48000),
                 "Unexpected audio sample rate"
             );
+            assert_eq!(
+                metadata.audio_sample_size,
+                Some(16),
+                "Unexpected audio sample size"
+            );
             assert_eq!(
                 metadata.audio_channels,
                 Some(2),
@@ -526,6 +832,56 @@ fn can_receive_and_raise_event_for_metadata_from_obs() {
     }
 }
 
+#[test]
+fn can_receive_metadata_sent_as_ecma_array() {
+    let (mut deserializer, mut serializer, mut session) = common_basic_setup();
+    perform_connection(
+        TEST_APP_NAME,
+        &mut session,
+        &mut serializer,
+        &mut deserializer,
+    );
+    let stream_id = create_active_stream(&mut session, &mut serializer, &mut deserializer);
+    start_publishing(
+        TEST_STREAM_KEY,
+        stream_id,
+        &mut session,
+        &mut serializer,
+        &mut deserializer,
+    );
+
+    let mut properties = HashMap::new();
+    properties.insert("width".to_string(), Amf0Value::Number(1280_f64));
+    properties.insert("height".to_string(), Amf0Value::Number(720_f64));
+
+    let message = RtmpMessage::Amf0Data {
+        values: vec![
+            Amf0Value::Utf8String("@setDataFrame".to_string()),
+            Amf0Value::Utf8String("onMetaData".to_string()),
+            Amf0Value::EcmaArray(properties),
+        ],
+    };
+
+    let payload = message
+        .into_message_payload(RtmpTimestamp::new(0), stream_id)
+        .unwrap();
+    let packet = serializer
+        .serialize(&payload, false, DropPolicy::MustDeliver)
+        .unwrap();
+    let results = session.handle_input(&packet.bytes[..]).unwrap();
+    let (_, mut events) = split_results(&mut deserializer, results);
+
+    assert_eq!(events.len(), 1, "Unexpected number of metadata events");
+    match events.remove(0) {
+        ServerSessionEvent::StreamMetadataChanged { metadata, .. } => {
+            assert_eq!(metadata.video_width, Some(1280), "Unexpected video width");
+            assert_eq!(metadata.video_height, Some(720), "Unexpected video height");
+        }
+
+        event => panic!("Unexpected event received: {:?}", event),
+    }
+}
+
 #[test]
 fn can_receive_audio_data_on_published_stream() {
     let (mut deserializer, mut serializer, mut session) = common_basic_setup();
@@ -550,7 +906,9 @@ fn can_receive_audio_data_on_published_stream() {
     let payload = message
         .into_message_payload(RtmpTimestamp::new(1234), stream_id)
         .unwrap();
-    let packet = serializer.serialize(&payload, false, false).unwrap();
+    let packet = serializer
+        .serialize(&payload, false, DropPolicy::MustDeliver)
+        .unwrap();
     let results = session.handle_input(&packet.bytes[..]).unwrap();
     let (_, mut events) = split_results(&mut deserializer, results);
 
@@ -597,7 +955,9 @@ fn can_receive_video_data_on_published_stream() {
     let payload = message
         .into_message_payload(RtmpTimestamp::new(1234), stream_id)
         .unwrap();
-    let packet = serializer.serialize(&payload, false, false).unwrap();
+    let packet = serializer
+        .serialize(&payload, false, DropPolicy::MustDeliver)
+        .unwrap();
     let results = session.handle_input(&packet.bytes[..]).unwrap();
     let (_, mut events) = split_results(&mut deserializer, results);
 
@@ -621,7 +981,7 @@ fn can_receive_video_data_on_published_stream() {
 }
 
 #[test]
-fn publish_finished_event_raised_when_delete_stream_invoked_on_publishing_stream() {
+fn can_receive_aggregate_of_audio_and_video_data_on_published_stream() {
     let (mut deserializer, mut serializer, mut session) = common_basic_setup();
     perform_connection(
         TEST_APP_NAME,
@@ -638,40 +998,63 @@ fn publish_finished_event_raised_when_delete_stream_invoked_on_publishing_stream
         &mut deserializer,
     );
 
-    let message = RtmpMessage::Amf0Command {
-        command_name: "deleteStream".to_string(),
-        transaction_id: 4_f64,
-        command_object: Amf0Value::Null,
-        additional_arguments: vec![Amf0Value::Number(stream_id as f64)],
+    let message = RtmpMessage::Aggregate {
+        messages: vec![
+            MessagePayload::new(8, RtmpTimestamp::new(0), 0, Bytes::from(vec![1_u8, 2_u8])),
+            MessagePayload::new(9, RtmpTimestamp::new(40), 0, Bytes::from(vec![3_u8, 4_u8])),
+        ],
     };
-
     let payload = message
-        .into_message_payload(RtmpTimestamp::new(1234), stream_id)
+        .into_message_payload(RtmpTimestamp::new(1000), stream_id)
+        .unwrap();
+    let packet = serializer
+        .serialize(&payload, false, DropPolicy::MustDeliver)
         .unwrap();
-    let packet = serializer.serialize(&payload, false, false).unwrap();
     let results = session.handle_input(&packet.bytes[..]).unwrap();
     let (_, mut events) = split_results(&mut deserializer, results);
 
-    assert_eq!(events.len(), 1, "Unexpected number of events returned");
+    assert_eq!(events.len(), 2, "Unexpected number of events returned");
+    match events.remove(0) {
+        ServerSessionEvent::AudioDataReceived {
+            stream_key,
+            data,
+            timestamp,
+            ..
+        } => {
+            assert_eq!(stream_key, TEST_STREAM_KEY, "Unexpected audio stream key");
+            assert_eq!(
+                timestamp,
+                RtmpTimestamp::new(1000),
+                "Unexpected audio timestamp"
+            );
+            assert_eq!(&data[..], &[1_u8, 2_u8], "Unexpected audio data");
+        }
+
+        event => panic!("Expected AudioDataReceived event, instead got: {:?}", event),
+    }
 
     match events.remove(0) {
-        ServerSessionEvent::PublishStreamFinished {
-            app_name,
+        ServerSessionEvent::VideoDataReceived {
             stream_key,
+            data,
+            timestamp,
+            ..
         } => {
-            assert_eq!(app_name, TEST_APP_NAME, "Unexpected app name");
-            assert_eq!(stream_key, TEST_STREAM_KEY, "Unexpected stream key");
+            assert_eq!(stream_key, TEST_STREAM_KEY, "Unexpected video stream key");
+            assert_eq!(
+                timestamp,
+                RtmpTimestamp::new(1040),
+                "Unexpected video timestamp"
+            );
+            assert_eq!(&data[..], &[3_u8, 4_u8], "Unexpected video data");
         }
 
-        event => panic!(
-            "Expected PublishStreamFinished event, instead got: {:?}",
-            event
-        ),
+        event => panic!("Expected VideoDataReceived event, instead got: {:?}", event),
     }
 }
 
 #[test]
-fn publish_finished_event_raised_when_close_stream_invoked_on_publishing_stream() {
+fn can_publish_and_play_on_separate_streams_of_same_connection() {
     let (mut deserializer, mut serializer, mut session) = common_basic_setup();
     perform_connection(
         TEST_APP_NAME,
@@ -679,27 +1062,201 @@ fn publish_finished_event_raised_when_close_stream_invoked_on_publishing_stream(
         &mut serializer,
         &mut deserializer,
     );
-    let stream_id = create_active_stream(&mut session, &mut serializer, &mut deserializer);
+    let publish_stream_id = create_active_stream(&mut session, &mut serializer, &mut deserializer);
     start_publishing(
         TEST_STREAM_KEY,
-        stream_id,
+        publish_stream_id,
         &mut session,
         &mut serializer,
         &mut deserializer,
     );
 
-    let message = RtmpMessage::Amf0Command {
-        command_name: "closeStream".to_string(),
-        transaction_id: 4_f64,
-        command_object: Amf0Value::Null,
-        additional_arguments: vec![Amf0Value::Number(stream_id as f64)],
-    };
+    let play_stream_id = create_active_stream(&mut session, &mut serializer, &mut deserializer);
+    assert_ne!(
+        publish_stream_id, play_stream_id,
+        "Both streams were given the same id"
+    );
 
-    let payload = message
-        .into_message_payload(RtmpTimestamp::new(1234), stream_id)
-        .unwrap();
-    let packet = serializer.serialize(&payload, false, false).unwrap();
-    let results = session.handle_input(&packet.bytes[..]).unwrap();
+    start_playing(
+        "other_key",
+        play_stream_id,
+        &mut session,
+        &mut serializer,
+        &mut deserializer,
+    );
+
+    let message = RtmpMessage::VideoData {
+        data: Bytes::from(vec![1_u8, 2_u8, 3_u8]),
+    };
+    let payload = message
+        .into_message_payload(RtmpTimestamp::new(1234), publish_stream_id)
+        .unwrap();
+    let packet = serializer
+        .serialize(&payload, false, DropPolicy::MustDeliver)
+        .unwrap();
+    let results = session.handle_input(&packet.bytes[..]).unwrap();
+    let (responses, mut events) = split_results(&mut deserializer, results);
+
+    assert_eq!(responses.len(), 0, "Unexpected responses returned");
+    assert_eq!(events.len(), 1, "Unexpected number of events returned");
+    match events.remove(0) {
+        ServerSessionEvent::VideoDataReceived {
+            stream_key, data, ..
+        } => {
+            assert_eq!(stream_key, TEST_STREAM_KEY, "Unexpected stream key");
+            assert_eq!(&data[..], &[1_u8, 2_u8, 3_u8], "Unexpected data");
+        }
+
+        event => panic!("Expected VideoDataReceived event, instead got: {:?}", event),
+    }
+
+    let packet = session
+        .send_video_data(
+            play_stream_id,
+            Bytes::from(vec![4_u8, 5_u8]),
+            RtmpTimestamp::new(500),
+            DropPolicy::MustDeliver,
+        )
+        .unwrap();
+    let payload = deserializer
+        .get_next_message(&packet.bytes[..])
+        .unwrap()
+        .unwrap();
+
+    assert_eq!(
+        payload.message_stream_id, play_stream_id,
+        "Video was not sent on the play stream"
+    );
+    match payload.to_rtmp_message().unwrap() {
+        RtmpMessage::VideoData { data } => assert_eq!(&data[..], &[4_u8, 5_u8]),
+        x => panic!("Expected video data message, received: {:?}", x),
+    }
+}
+
+#[test]
+fn error_returned_when_video_data_larger_than_configured_max() {
+    let mut config = get_basic_config();
+    config.max_inbound_video_bytes = 10;
+    let (mut deserializer, mut serializer, mut session) = common_setup(&config);
+    perform_connection(
+        TEST_APP_NAME,
+        &mut session,
+        &mut serializer,
+        &mut deserializer,
+    );
+    let stream_id = create_active_stream(&mut session, &mut serializer, &mut deserializer);
+    start_publishing(
+        TEST_STREAM_KEY,
+        stream_id,
+        &mut session,
+        &mut serializer,
+        &mut deserializer,
+    );
+
+    let message = RtmpMessage::VideoData {
+        data: Bytes::from(vec![1_u8; 11]),
+    };
+    let payload = message
+        .into_message_payload(RtmpTimestamp::new(1234), stream_id)
+        .unwrap();
+    let packet = serializer
+        .serialize(&payload, false, DropPolicy::MustDeliver)
+        .unwrap();
+
+    match session.handle_input(&packet.bytes[..]) {
+        Err(ServerSessionError::InboundMessageTooLarge { type_name, size }) => {
+            assert_eq!(type_name, "video", "Unexpected type name");
+            assert_eq!(size, 11, "Unexpected size");
+        }
+
+        x => panic!("Expected InboundMessageTooLarge error, instead got {:?}", x),
+    }
+}
+
+#[test]
+fn publish_finished_event_raised_when_delete_stream_invoked_on_publishing_stream() {
+    let (mut deserializer, mut serializer, mut session) = common_basic_setup();
+    perform_connection(
+        TEST_APP_NAME,
+        &mut session,
+        &mut serializer,
+        &mut deserializer,
+    );
+    let stream_id = create_active_stream(&mut session, &mut serializer, &mut deserializer);
+    start_publishing(
+        TEST_STREAM_KEY,
+        stream_id,
+        &mut session,
+        &mut serializer,
+        &mut deserializer,
+    );
+
+    let message = RtmpMessage::Amf0Command {
+        command_name: "deleteStream".to_string(),
+        transaction_id: 4_f64,
+        command_object: Amf0Value::Null,
+        additional_arguments: vec![Amf0Value::Number(stream_id as f64)],
+    };
+
+    let payload = message
+        .into_message_payload(RtmpTimestamp::new(1234), stream_id)
+        .unwrap();
+    let packet = serializer
+        .serialize(&payload, false, DropPolicy::MustDeliver)
+        .unwrap();
+    let results = session.handle_input(&packet.bytes[..]).unwrap();
+    let (_, mut events) = split_results(&mut deserializer, results);
+
+    assert_eq!(events.len(), 1, "Unexpected number of events returned");
+
+    match events.remove(0) {
+        ServerSessionEvent::PublishStreamFinished {
+            app_name,
+            stream_key,
+        } => {
+            assert_eq!(app_name, TEST_APP_NAME, "Unexpected app name");
+            assert_eq!(stream_key, TEST_STREAM_KEY, "Unexpected stream key");
+        }
+
+        event => panic!(
+            "Expected PublishStreamFinished event, instead got: {:?}",
+            event
+        ),
+    }
+}
+
+#[test]
+fn publish_finished_event_raised_when_close_stream_invoked_on_publishing_stream() {
+    let (mut deserializer, mut serializer, mut session) = common_basic_setup();
+    perform_connection(
+        TEST_APP_NAME,
+        &mut session,
+        &mut serializer,
+        &mut deserializer,
+    );
+    let stream_id = create_active_stream(&mut session, &mut serializer, &mut deserializer);
+    start_publishing(
+        TEST_STREAM_KEY,
+        stream_id,
+        &mut session,
+        &mut serializer,
+        &mut deserializer,
+    );
+
+    let message = RtmpMessage::Amf0Command {
+        command_name: "closeStream".to_string(),
+        transaction_id: 4_f64,
+        command_object: Amf0Value::Null,
+        additional_arguments: vec![Amf0Value::Number(stream_id as f64)],
+    };
+
+    let payload = message
+        .into_message_payload(RtmpTimestamp::new(1234), stream_id)
+        .unwrap();
+    let packet = serializer
+        .serialize(&payload, false, DropPolicy::MustDeliver)
+        .unwrap();
+    let results = session.handle_input(&packet.bytes[..]).unwrap();
     let (_, mut events) = split_results(&mut deserializer, results);
 
     assert_eq!(events.len(), 1, "Unexpected number of events returned");
@@ -720,6 +1277,58 @@ fn publish_finished_event_raised_when_close_stream_invoked_on_publishing_stream(
     }
 }
 
+#[test]
+fn close_stream_without_arguments_closes_stream_it_was_sent_on() {
+    let (mut deserializer, mut serializer, mut session) = common_basic_setup();
+    perform_connection(
+        TEST_APP_NAME,
+        &mut session,
+        &mut serializer,
+        &mut deserializer,
+    );
+    let stream_id = create_active_stream(&mut session, &mut serializer, &mut deserializer);
+    assert_eq!(stream_id, 1, "Unexpected stream id");
+    start_publishing(
+        TEST_STREAM_KEY,
+        stream_id,
+        &mut session,
+        &mut serializer,
+        &mut deserializer,
+    );
+
+    let message = RtmpMessage::Amf0Command {
+        command_name: "closeStream".to_string(),
+        transaction_id: 0_f64,
+        command_object: Amf0Value::Null,
+        additional_arguments: Vec::new(),
+    };
+
+    let payload = message
+        .into_message_payload(RtmpTimestamp::new(1234), stream_id)
+        .unwrap();
+    let packet = serializer
+        .serialize(&payload, false, DropPolicy::MustDeliver)
+        .unwrap();
+    let results = session.handle_input(&packet.bytes[..]).unwrap();
+    let (_, mut events) = split_results(&mut deserializer, results);
+
+    assert_eq!(events.len(), 1, "Unexpected number of events returned");
+    match events.remove(0) {
+        ServerSessionEvent::PublishStreamFinished {
+            app_name,
+            stream_key,
+        } => {
+            assert_eq!(app_name, TEST_APP_NAME, "Unexpected app name");
+            assert_eq!(stream_key, TEST_STREAM_KEY, "Unexpected stream key");
+        }
+
+        event => panic!(
+            "Expected PublishStreamFinished event, instead got: {:?}",
+            event
+        ),
+    }
+}
+
 #[test]
 fn can_request_publishing_on_closed_stream() {
     let (mut deserializer, mut serializer, mut session) = common_basic_setup();
@@ -753,7 +1362,7 @@ fn can_request_publishing_on_closed_stream() {
         .into_message_payload(RtmpTimestamp::new(2000), stream_id)
         .unwrap();
     let publish_packet = serializer
-        .serialize(&publish_payload, false, false)
+        .serialize(&publish_payload, false, DropPolicy::MustDeliver)
         .unwrap();
     let publish_results = session.handle_input(&publish_packet.bytes[..]).unwrap();
     let (_, events) = split_results(&mut deserializer, publish_results);
@@ -765,6 +1374,7 @@ fn can_request_publishing_on_closed_stream() {
             ref stream_key,
             request_id: _,
             mode: PublishMode::Live,
+            stream_id: _,
         } => {
             assert_eq!(app_name, &TEST_APP_NAME, "Unexpected app name");
             assert_eq!(stream_key, &TEST_STREAM_KEY, "Unexpected stream key");
@@ -795,7 +1405,9 @@ fn can_accept_play_command_with_no_optional_parameters_to_requested_stream_key()
     let play_payload = message
         .into_message_payload(RtmpTimestamp::new(0), stream_id)
         .unwrap();
-    let play_packet = serializer.serialize(&play_payload, false, false).unwrap();
+    let play_packet = serializer
+        .serialize(&play_payload, false, DropPolicy::MustDeliver)
+        .unwrap();
     let play_results = session.handle_input(&play_packet.bytes[..]).unwrap();
     let (_, mut events) = split_results(&mut deserializer, play_results);
 
@@ -937,7 +1549,9 @@ fn can_accept_play_command_with_all_optional_parameters_to_requested_stream_key(
     let play_payload = message
         .into_message_payload(RtmpTimestamp::new(0), stream_id)
         .unwrap();
-    let play_packet = serializer.serialize(&play_payload, false, false).unwrap();
+    let play_packet = serializer
+        .serialize(&play_payload, false, DropPolicy::MustDeliver)
+        .unwrap();
     let play_results = session.handle_input(&play_packet.bytes[..]).unwrap();
     let (_, mut events) = split_results(&mut deserializer, play_results);
 
@@ -956,7 +1570,7 @@ fn can_accept_play_command_with_all_optional_parameters_to_requested_stream_key(
             assert_eq!(stream_key, TEST_STREAM_KEY, "Unexpected stream key");
             assert_eq!(
                 start_at,
-                PlayStartValue::StartTimeInSeconds(5),
+                PlayStartValue::StartTimeInMillis(5000),
                 "Unexpected start at"
             );
             assert_eq!(duration, Some(25), "Unexpected duration");
@@ -972,6 +1586,45 @@ fn can_accept_play_command_with_all_optional_parameters_to_requested_stream_key(
     consume_results(&mut deserializer, accept_results);
 }
 
+#[test]
+fn play_command_start_time_retains_sub_second_precision() {
+    let (mut deserializer, mut serializer, mut session) = common_basic_setup();
+    perform_connection(
+        TEST_APP_NAME,
+        &mut session,
+        &mut serializer,
+        &mut deserializer,
+    );
+    let stream_id = create_active_stream(&mut session, &mut serializer, &mut deserializer);
+
+    let message = RtmpMessage::Amf0Command {
+        command_name: "play".to_string(),
+        transaction_id: 4.0,
+        command_object: Amf0Value::Null,
+        additional_arguments: vec![
+            Amf0Value::Utf8String(TEST_STREAM_KEY.to_string()),
+            Amf0Value::Number(1.5),
+        ],
+    };
+
+    let play_payload = message
+        .into_message_payload(RtmpTimestamp::new(0), stream_id)
+        .unwrap();
+    let play_packet = serializer
+        .serialize(&play_payload, false, DropPolicy::MustDeliver)
+        .unwrap();
+    let play_results = session.handle_input(&play_packet.bytes[..]).unwrap();
+    let (_, events) = split_results(&mut deserializer, play_results);
+
+    assert_vec_contains!(
+        events,
+        ServerSessionEvent::PlayStreamRequested {
+            start_at: PlayStartValue::StartTimeInMillis(1500),
+            ..
+        }
+    );
+}
+
 #[test]
 fn play_finished_event_when_close_stream_invoked() {
     let (mut deserializer, mut serializer, mut session) = common_basic_setup();
@@ -1001,7 +1654,9 @@ fn play_finished_event_when_close_stream_invoked() {
     let payload = message
         .into_message_payload(RtmpTimestamp::new(1234), stream_id)
         .unwrap();
-    let packet = serializer.serialize(&payload, false, false).unwrap();
+    let packet = serializer
+        .serialize(&payload, false, DropPolicy::MustDeliver)
+        .unwrap();
     let results = session.handle_input(&packet.bytes[..]).unwrap();
     let (_, mut events) = split_results(&mut deserializer, results);
 
@@ -1052,7 +1707,9 @@ fn play_finished_event_when_delete_stream_invoked_on_playing_stream() {
     let payload = message
         .into_message_payload(RtmpTimestamp::new(1234), stream_id)
         .unwrap();
-    let packet = serializer.serialize(&payload, false, false).unwrap();
+    let packet = serializer
+        .serialize(&payload, false, DropPolicy::MustDeliver)
+        .unwrap();
     let results = session.handle_input(&packet.bytes[..]).unwrap();
     let (_, mut events) = split_results(&mut deserializer, results);
 
@@ -1096,14 +1753,21 @@ fn can_send_metadata_to_playing_stream() {
         audio_bitrate_kbps: Some(100),
         audio_channels: Some(101),
         audio_codec_id: Some(7),
+        audio_codec_name: None,
         audio_is_stereo: Some(true),
         audio_sample_rate: Some(103),
+        audio_sample_size: Some(16),
         encoder: Some("104".to_string()),
         video_bitrate_kbps: Some(105),
         video_codec_id: Some(10),
+        video_codec_name: None,
         video_frame_rate: Some(107.0),
         video_height: Some(108),
         video_width: Some(109),
+        color_depth: None,
+        color_matrix_coefficients: None,
+        color_transfer_characteristics: None,
+        color_primaries: None,
     };
 
     let packet = session.send_metadata(stream_id, &metadata).unwrap();
@@ -1166,6 +1830,11 @@ fn can_send_metadata_to_playing_stream() {
                         Some(&Amf0Value::Number(103.0)),
                         "Unexpected audiosamplerate"
                     );
+                    assert_eq!(
+                        properties.get("audiosamplesize"),
+                        Some(&Amf0Value::Number(16.0)),
+                        "Unexpected audiosamplesize"
+                    );
                     assert_eq!(
                         properties.get("audiochannels"),
                         Some(&Amf0Value::Number(101.0)),
@@ -1195,7 +1864,7 @@ fn can_send_metadata_to_playing_stream() {
 }
 
 #[test]
-fn can_send_video_data_to_playing_stream() {
+fn can_send_metadata_with_codec_names_to_playing_stream() {
     let (mut deserializer, mut serializer, mut session) = common_basic_setup();
     perform_connection(
         TEST_APP_NAME,
@@ -1212,11 +1881,11 @@ fn can_send_video_data_to_playing_stream() {
         &mut deserializer,
     );
 
-    let original_data = Bytes::from(vec![1_u8, 2_u8, 3_u8]);
-    let timestamp = RtmpTimestamp::new(500);
-    let packet = session
-        .send_video_data(stream_id, original_data.clone(), timestamp.clone(), false)
-        .unwrap();
+    let mut metadata = StreamMetadata::new();
+    metadata.audio_codec_name = Some("mp4a".to_string());
+    metadata.video_codec_name = Some("avc1".to_string());
+
+    let packet = session.send_metadata(stream_id, &metadata).unwrap();
     let payload = deserializer
         .get_next_message(&packet.bytes[..])
         .unwrap()
@@ -1224,22 +1893,200 @@ fn can_send_video_data_to_playing_stream() {
     let message = payload.to_rtmp_message().unwrap();
 
     match message {
-        RtmpMessage::VideoData { data: message_data } => {
-            assert_eq!(
-                payload.timestamp, timestamp,
-                "Serialized timestamp did not match original timestamp"
-            );
-            assert_eq!(
-                &message_data[..],
-                &original_data[..],
-                "Packetized data did not match original data"
-            );
-        }
-
-        x => panic!("Expected video data message, received: {:?}", x),
-    }
-}
-
+        RtmpMessage::Amf0Data { mut values } => {
+            assert_eq!(values.len(), 2, "2 amf0 data values expected");
+            match values.remove(1) {
+                Amf0Value::Object(properties) => {
+                    assert_eq!(
+                        properties.get("videocodecid"),
+                        Some(&Amf0Value::Utf8String("avc1".to_string())),
+                        "Unexpected videocodecid"
+                    );
+                    assert_eq!(
+                        properties.get("audiocodecid"),
+                        Some(&Amf0Value::Utf8String("mp4a".to_string())),
+                        "Unexpected audiocodecid"
+                    );
+                }
+
+                x => panic!(
+                    "Expected Amf0 object with metadata, instead received: {:?}",
+                    x
+                ),
+            }
+        }
+
+        x => panic!("Expected Amf0 data, instead received: {:?}", x),
+    }
+}
+
+#[test]
+fn can_send_video_data_to_playing_stream() {
+    let (mut deserializer, mut serializer, mut session) = common_basic_setup();
+    perform_connection(
+        TEST_APP_NAME,
+        &mut session,
+        &mut serializer,
+        &mut deserializer,
+    );
+    let stream_id = create_active_stream(&mut session, &mut serializer, &mut deserializer);
+    start_playing(
+        TEST_STREAM_KEY,
+        stream_id,
+        &mut session,
+        &mut serializer,
+        &mut deserializer,
+    );
+
+    let original_data = Bytes::from(vec![1_u8, 2_u8, 3_u8]);
+    let timestamp = RtmpTimestamp::new(500);
+    let packet = session
+        .send_video_data(
+            stream_id,
+            original_data.clone(),
+            timestamp.clone(),
+            DropPolicy::MustDeliver,
+        )
+        .unwrap();
+    let payload = deserializer
+        .get_next_message(&packet.bytes[..])
+        .unwrap()
+        .unwrap();
+    let message = payload.to_rtmp_message().unwrap();
+
+    match message {
+        RtmpMessage::VideoData { data: message_data } => {
+            assert_eq!(
+                payload.timestamp, timestamp,
+                "Serialized timestamp did not match original timestamp"
+            );
+            assert_eq!(
+                &message_data[..],
+                &original_data[..],
+                "Packetized data did not match original data"
+            );
+        }
+
+        x => panic!("Expected video data message, received: {:?}", x),
+    }
+}
+
+#[test]
+fn can_send_video_data_to_playing_stream_in_parts() {
+    let (mut deserializer, mut serializer, mut session) = common_basic_setup();
+    perform_connection(
+        TEST_APP_NAME,
+        &mut session,
+        &mut serializer,
+        &mut deserializer,
+    );
+    let stream_id = create_active_stream(&mut session, &mut serializer, &mut deserializer);
+    start_playing(
+        TEST_STREAM_KEY,
+        stream_id,
+        &mut session,
+        &mut serializer,
+        &mut deserializer,
+    );
+
+    // Large enough to span multiple chunks, with parts that don't line up with chunk boundaries
+    let original_data = (0..20_000_u32).map(|x| x as u8).collect::<Vec<_>>();
+    let timestamp = RtmpTimestamp::new(500);
+    let mut bytes = Vec::new();
+    {
+        let mut sender = session
+            .begin_video_stream(stream_id, timestamp, original_data.len() as u32)
+            .unwrap();
+
+        for part in original_data.chunks(1_000) {
+            let packet = sender.send_chunk(part).unwrap();
+            assert_eq!(packet.drop_policy, DropPolicy::MustDeliver);
+            bytes.extend(packet.bytes);
+        }
+
+        assert_eq!(sender.remaining_length(), 0, "Unexpected remaining length");
+        sender.finish().unwrap();
+    }
+
+    let payload = deserializer.get_next_message(&bytes).unwrap().unwrap();
+    match payload.to_rtmp_message().unwrap() {
+        RtmpMessage::VideoData { data } => {
+            assert_eq!(payload.timestamp, timestamp, "Unexpected timestamp");
+            assert_eq!(payload.message_stream_id, stream_id, "Unexpected stream id");
+            assert_eq!(&data[..], &original_data[..], "Unexpected video data");
+        }
+
+        x => panic!("Expected video data message, received: {:?}", x),
+    }
+}
+
+#[test]
+fn error_when_finishing_video_stream_before_all_data_sent() {
+    let (_, _, mut session) = common_basic_setup();
+    let mut sender = session
+        .begin_video_stream(1, RtmpTimestamp::new(0), 10)
+        .unwrap();
+
+    sender.send_chunk(&[1_u8, 2_u8, 3_u8]).unwrap();
+    match sender.finish() {
+        Err(ServerSessionError::ChunkSerializationError(
+            ChunkSerializationError::PartialMessageIncomplete {
+                message_length: 10,
+                bytes_serialized: 3,
+            },
+        )) => (),
+        x => panic!(
+            "Expected PartialMessageIncomplete error, instead got {:?}",
+            x
+        ),
+    }
+}
+
+#[test]
+fn can_send_custom_amf0_data_to_playing_stream() {
+    let (mut deserializer, mut serializer, mut session) = common_basic_setup();
+    perform_connection(
+        TEST_APP_NAME,
+        &mut session,
+        &mut serializer,
+        &mut deserializer,
+    );
+    let stream_id = create_active_stream(&mut session, &mut serializer, &mut deserializer);
+    start_playing(
+        TEST_STREAM_KEY,
+        stream_id,
+        &mut session,
+        &mut serializer,
+        &mut deserializer,
+    );
+
+    let values = vec![
+        Amf0Value::Utf8String("onCuePoint".to_string()),
+        Amf0Value::Number(12.5),
+    ];
+
+    let packet = session
+        .send_amf0_data_message(stream_id, values.clone())
+        .unwrap();
+    let payload = deserializer
+        .get_next_message(&packet.bytes[..])
+        .unwrap()
+        .unwrap();
+
+    assert_eq!(payload.type_id, 18, "Unexpected message type id");
+    assert_eq!(
+        payload.message_stream_id, stream_id,
+        "Unexpected message stream id"
+    );
+
+    match payload.to_rtmp_message().unwrap() {
+        RtmpMessage::Amf0Data {
+            values: message_values,
+        } => assert_eq!(message_values, values, "Unexpected amf0 values"),
+        x => panic!("Expected amf0 data message, received: {:?}", x),
+    }
+}
+
 #[test]
 fn can_send_audio_data_to_playing_stream() {
     let (mut deserializer, mut serializer, mut session) = common_basic_setup();
@@ -1261,7 +2108,12 @@ fn can_send_audio_data_to_playing_stream() {
     let original_data = Bytes::from(vec![1_u8, 2_u8, 3_u8]);
     let timestamp = RtmpTimestamp::new(500);
     let packet = session
-        .send_audio_data(stream_id, original_data.clone(), timestamp.clone(), false)
+        .send_audio_data(
+            stream_id,
+            original_data.clone(),
+            timestamp.clone(),
+            DropPolicy::MustDeliver,
+        )
         .unwrap();
     let payload = deserializer
         .get_next_message(&packet.bytes[..])
@@ -1306,7 +2158,9 @@ fn automatically_responds_to_ping_requests() {
     let payload = message
         .into_message_payload(RtmpTimestamp::new(6000), 0)
         .unwrap();
-    let packet = serializer.serialize(&payload, false, false).unwrap();
+    let packet = serializer
+        .serialize(&payload, false, DropPolicy::MustDeliver)
+        .unwrap();
     let results = session.handle_input(&packet.bytes[..]).unwrap();
     let (mut responses, _) = split_results(&mut deserializer, results);
 
@@ -1333,12 +2187,707 @@ fn automatically_responds_to_ping_requests() {
             assert_eq!(timestamp, RtmpTimestamp::new(5230), "Unexpected timestamp");
         }
 
-        x => panic!("Expected PingResponse, found {:?}", x),
+        x => panic!("Expected PingResponse, found {:?}", x),
+    }
+}
+
+#[test]
+fn responds_to_ping_requests_received_before_connection() {
+    let (mut deserializer, mut serializer, mut session) = common_basic_setup();
+
+    let message = RtmpMessage::UserControl {
+        event_type: UserControlEventType::PingRequest,
+        timestamp: Some(RtmpTimestamp::new(4_000_000_000)),
+        stream_id: None,
+        buffer_length: None,
+    };
+
+    let payload = message
+        .into_message_payload(RtmpTimestamp::new(0), 0)
+        .unwrap();
+    let packet = serializer
+        .serialize(&payload, false, DropPolicy::MustDeliver)
+        .unwrap();
+    let results = session.handle_input(&packet.bytes[..]).unwrap();
+    let (responses, _) = split_results(&mut deserializer, results);
+
+    assert_vec_contains!(responses, (_, RtmpMessage::UserControl {
+        event_type: UserControlEventType::PingResponse,
+        timestamp: Some(timestamp),
+        stream_id: None,
+        buffer_length: None,
+    }) if *timestamp == RtmpTimestamp::new(4_000_000_000));
+}
+
+#[test]
+fn error_when_aggregates_are_nested_before_connection() {
+    let (_, mut serializer, mut session) = common_basic_setup();
+
+    let payload = MessagePayload::new(22, RtmpTimestamp::new(0), 0, nested_aggregate(100_000));
+    let packet = serializer
+        .serialize(&payload, false, DropPolicy::MustDeliver)
+        .unwrap();
+
+    match session.handle_input(&packet.bytes[..]) {
+        Err(ServerSessionError::MessageDeserializationError(
+            MessageDeserializationError::InvalidMessageFormat,
+        )) => (),
+        x => panic!("Expected InvalidMessageFormat error, instead got {:?}", x),
+    }
+}
+
+#[test]
+fn traces_inbound_messages_and_outbound_packets_when_tracing_enabled() {
+    let (_, mut serializer, mut session) = common_basic_setup();
+    let traces = Arc::new(Mutex::new(Vec::new()));
+    let logged_traces = traces.clone();
+    session.enable_message_tracing(move |trace| logged_traces.lock().unwrap().push(trace));
+
+    let message = RtmpMessage::UserControl {
+        event_type: UserControlEventType::PingRequest,
+        timestamp: Some(RtmpTimestamp::new(500)),
+        stream_id: None,
+        buffer_length: None,
+    };
+
+    let payload = message
+        .into_message_payload(RtmpTimestamp::new(25), 0)
+        .unwrap();
+    let packet = serializer
+        .serialize(&payload, false, DropPolicy::MustDeliver)
+        .unwrap();
+    let results = session.handle_input(&packet.bytes[..]).unwrap();
+    let response_size = match results.first() {
+        Some(ServerSessionResult::OutboundResponse(packet)) => packet.bytes.len(),
+        x => panic!("Expected outbound response, instead received {:?}", x),
+    };
+
+    let traces = traces.lock().unwrap();
+    assert_eq!(traces.len(), 2, "Unexpected number of traces");
+    assert_eq!(
+        traces[0],
+        MessageTrace {
+            direction: Direction::Inbound,
+            message_type: "UserControl",
+            timestamp: RtmpTimestamp::new(25),
+            stream_id: 0,
+            byte_count: payload.data.len(),
+        }
+    );
+    assert_eq!(traces[1].direction, Direction::Outbound);
+    assert_eq!(traces[1].message_type, "UserControl");
+    assert_eq!(traces[1].byte_count, response_size);
+}
+
+#[test]
+fn event_raised_when_ping_response_received() {
+    let (mut deserializer, mut serializer, mut session) = common_basic_setup();
+    perform_connection(
+        TEST_APP_NAME,
+        &mut session,
+        &mut serializer,
+        &mut deserializer,
+    );
+
+    let message = RtmpMessage::UserControl {
+        event_type: UserControlEventType::PingResponse,
+        timestamp: Some(RtmpTimestamp::new(5230)),
+        stream_id: None,
+        buffer_length: None,
+    };
+
+    let payload = message
+        .into_message_payload(RtmpTimestamp::new(6000), 0)
+        .unwrap();
+    let packet = serializer
+        .serialize(&payload, false, DropPolicy::MustDeliver)
+        .unwrap();
+    let results = session.handle_input(&packet.bytes[..]).unwrap();
+    let (_, mut events) = split_results(&mut deserializer, results);
+
+    assert_eq!(events.len(), 1, "One event expected");
+    match events.remove(0) {
+        ServerSessionEvent::PingResponseReceived { timestamp } => {
+            assert_eq!(
+                timestamp,
+                RtmpTimestamp::new(5230),
+                "Unexpected timestamp received"
+            );
+        }
+
+        x => panic!("Expected PingResponse event, instead received {:?}", x),
+    }
+}
+
+#[test]
+fn event_raised_for_unhandled_user_control_event() {
+    let (mut deserializer, mut serializer, mut session) = common_basic_setup();
+    perform_connection(
+        TEST_APP_NAME,
+        &mut session,
+        &mut serializer,
+        &mut deserializer,
+    );
+
+    let message = RtmpMessage::UserControl {
+        event_type: UserControlEventType::BufferReady,
+        timestamp: None,
+        stream_id: Some(1),
+        buffer_length: None,
+    };
+
+    let payload = message
+        .into_message_payload(RtmpTimestamp::new(6000), 0)
+        .unwrap();
+    let packet = serializer
+        .serialize(&payload, false, DropPolicy::MustDeliver)
+        .unwrap();
+    let results = session.handle_input(&packet.bytes[..]).unwrap();
+    let (responses, mut events) = split_results(&mut deserializer, results);
+
+    assert_eq!(responses.len(), 0, "No responses expected");
+    assert_eq!(events.len(), 1, "One event expected");
+    match events.remove(0) {
+        ServerSessionEvent::UnhandledUserControlEvent {
+            event_type,
+            stream_id,
+            buffer_length,
+            timestamp,
+        } => {
+            assert_eq!(
+                event_type,
+                UserControlEventType::BufferReady,
+                "Unexpected event type"
+            );
+            assert_eq!(stream_id, Some(1), "Unexpected stream id");
+            assert_eq!(buffer_length, None, "Unexpected buffer length");
+            assert_eq!(timestamp, None, "Unexpected timestamp");
+        }
+
+        x => panic!(
+            "Expected UnhandledUserControlEvent, instead received {:?}",
+            x
+        ),
+    }
+}
+
+#[test]
+fn can_send_ping_request() {
+    let (mut deserializer, mut serializer, mut session) = common_basic_setup();
+    perform_connection(
+        TEST_APP_NAME,
+        &mut session,
+        &mut serializer,
+        &mut deserializer,
+    );
+
+    let (packet, sent_timestamp) = session.send_ping_request().unwrap();
+    let payload = deserializer
+        .get_next_message(&packet.bytes[..])
+        .unwrap()
+        .unwrap();
+    let message = payload.to_rtmp_message().unwrap();
+
+    match message {
+        RtmpMessage::UserControl {
+            event_type,
+            timestamp: Some(timestamp),
+            buffer_length: None,
+            stream_id: None,
+        } => {
+            assert_eq!(
+                event_type,
+                UserControlEventType::PingRequest,
+                "Unexpected user control event type"
+            );
+            assert_eq!(
+                timestamp, sent_timestamp,
+                "Unexpected timestamp in outbound message"
+            );
+        }
+
+        x => panic!("Expected PingRequest being sent, instead found {:?}", x),
+    }
+}
+
+#[test]
+fn can_notify_client_that_stream_is_recorded() {
+    let (mut deserializer, mut serializer, mut session) = common_basic_setup();
+    perform_connection(
+        TEST_APP_NAME,
+        &mut session,
+        &mut serializer,
+        &mut deserializer,
+    );
+    let stream_id = create_active_stream(&mut session, &mut serializer, &mut deserializer);
+    start_playing(
+        TEST_STREAM_KEY,
+        stream_id,
+        &mut session,
+        &mut serializer,
+        &mut deserializer,
+    );
+
+    let packet = session.notify_stream_is_recorded(stream_id).unwrap();
+    let payload = deserializer
+        .get_next_message(&packet.bytes[..])
+        .unwrap()
+        .unwrap();
+
+    match payload.to_rtmp_message().unwrap() {
+        RtmpMessage::UserControl {
+            event_type,
+            stream_id: Some(recorded_stream_id),
+            ..
+        } => {
+            assert_eq!(
+                event_type,
+                UserControlEventType::StreamIsRecorded,
+                "Unexpected user control event type"
+            );
+            assert_eq!(recorded_stream_id, stream_id, "Unexpected stream id");
+        }
+
+        x => panic!(
+            "Expected StreamIsRecorded being sent, instead found {:?}",
+            x
+        ),
+    }
+}
+
+#[test]
+fn can_send_arbitrary_user_control_events() {
+    let (mut deserializer, mut serializer, mut session) = common_basic_setup();
+    perform_connection(
+        TEST_APP_NAME,
+        &mut session,
+        &mut serializer,
+        &mut deserializer,
+    );
+    let stream_id = create_active_stream(&mut session, &mut serializer, &mut deserializer);
+
+    let events = [
+        (UserControlEventType::StreamDry, 2_u8),
+        (UserControlEventType::BufferEmpty, 31_u8),
+    ];
+
+    for &(ref event_type, event_type_id) in events.iter() {
+        let packet = session
+            .send_user_control(stream_id, event_type.clone(), None, None)
+            .unwrap();
+
+        let payload = deserializer
+            .get_next_message(&packet.bytes[..])
+            .unwrap()
+            .unwrap();
+
+        assert_eq!(payload.type_id, 4, "Unexpected message type id");
+        assert_eq!(payload.message_stream_id, 0, "Unexpected message stream id");
+        assert_eq!(
+            &payload.data[..],
+            &[0, event_type_id, 0, 0, 0, stream_id as u8][..],
+            "Unexpected user control payload"
+        );
+
+        match payload.to_rtmp_message().unwrap() {
+            RtmpMessage::UserControl {
+                event_type: received_type,
+                stream_id: Some(received_stream_id),
+                ..
+            } => {
+                assert_eq!(&received_type, event_type, "Unexpected event type");
+                assert_eq!(received_stream_id, stream_id, "Unexpected stream id");
+            }
+
+            x => panic!("Expected user control message, instead found {:?}", x),
+        }
+    }
+}
+
+#[test]
+fn can_finish_playing_stream() {
+    let (mut deserializer, mut serializer, mut session) = common_basic_setup();
+    perform_connection(
+        TEST_APP_NAME,
+        &mut session,
+        &mut serializer,
+        &mut deserializer,
+    );
+    let stream_id = create_active_stream(&mut session, &mut serializer, &mut deserializer);
+    start_playing(
+        TEST_STREAM_KEY,
+        stream_id,
+        &mut session,
+        &mut serializer,
+        &mut deserializer,
+    );
+
+    let packet = session.finish_playing(stream_id).unwrap();
+    let payload = deserializer
+        .get_next_message(&packet.bytes[..])
+        .unwrap()
+        .unwrap();
+    let message = payload.to_rtmp_message().unwrap();
+
+    verify_is_onstatus(&message, "status", "NetStream.Play.Complete");
+}
+
+#[test]
+fn sends_ack_after_receiving_window_ack_bytes() {
+    let (mut deserializer, mut serializer, mut session) = common_basic_setup();
+    perform_connection(
+        TEST_APP_NAME,
+        &mut session,
+        &mut serializer,
+        &mut deserializer,
+    );
+
+    let window_ack_message = RtmpMessage::WindowAcknowledgement { size: 100 };
+    let window_ack_payload = window_ack_message
+        .into_message_payload(RtmpTimestamp::new(0), 0)
+        .unwrap();
+    let window_ack_packet = serializer
+        .serialize(&window_ack_payload, false, DropPolicy::MustDeliver)
+        .unwrap();
+    let results = session.handle_input(&window_ack_packet.bytes[..]).unwrap();
+    consume_results(&mut deserializer, results);
+
+    let mut bytes = BytesMut::new();
+    bytes.extend_from_slice(&[1; 101]);
+    let video_message = RtmpMessage::VideoData {
+        data: bytes.freeze(),
+    };
+    let video_payload = video_message
+        .into_message_payload(RtmpTimestamp::new(0), 0)
+        .unwrap();
+    let video_packet = serializer
+        .serialize(&video_payload, false, DropPolicy::MustDeliver)
+        .unwrap();
+    let results = session.handle_input(&video_packet.bytes[..]).unwrap();
+    let (mut responses, _) = split_results(&mut deserializer, results);
+
+    assert_eq!(responses.len(), 1, "Unexpected number of responses");
+    match responses.remove(0) {
+        (_, RtmpMessage::Acknowledgement { sequence_number: _ }) => (), // No good way to predict sequence number
+        x => panic!("Expected Acknowledgement, instead received: {:?}", x),
+    }
+
+    let mut bytes = BytesMut::new();
+    bytes.extend_from_slice(&[1; 1]);
+    let video_message = RtmpMessage::VideoData {
+        data: bytes.freeze(),
+    };
+    let video_payload = video_message
+        .into_message_payload(RtmpTimestamp::new(0), 0)
+        .unwrap();
+    let video_packet = serializer
+        .serialize(&video_payload, false, DropPolicy::MustDeliver)
+        .unwrap();
+    let results = session.handle_input(&video_packet.bytes[..]).unwrap();
+    let (responses, _) = split_results(&mut deserializer, results);
+    assert_eq!(responses.len(), 0, "Expected no responses");
+
+    let mut bytes = BytesMut::new();
+    bytes.extend_from_slice(&[1; 100]);
+    let video_message = RtmpMessage::VideoData {
+        data: bytes.freeze(),
+    };
+    let video_payload = video_message
+        .into_message_payload(RtmpTimestamp::new(0), 0)
+        .unwrap();
+    let video_packet = serializer
+        .serialize(&video_payload, false, DropPolicy::MustDeliver)
+        .unwrap();
+    let results = session.handle_input(&video_packet.bytes[..]).unwrap();
+    let (mut responses, _) = split_results(&mut deserializer, results);
+    assert_eq!(responses.len(), 1, "Unexpected number of responses");
+    match responses.remove(0) {
+        (_, RtmpMessage::Acknowledgement { sequence_number: _ }) => (), // No good way to predict sequence number
+        x => panic!("Expected Acknowledgement, instead received: {:?}", x),
+    }
+}
+
+#[test]
+fn sends_ack_with_total_bytes_received_when_own_window_size_crossed() {
+    let mut config = get_basic_config();
+    config.window_ack_size = 1000;
+
+    let (mut deserializer, mut serializer, mut session) = common_setup(&config);
+    perform_connection(
+        TEST_APP_NAME,
+        &mut session,
+        &mut serializer,
+        &mut deserializer,
+    );
+
+    let video_message = RtmpMessage::VideoData {
+        data: Bytes::from(vec![1_u8; 2000]),
+    };
+    let video_payload = video_message
+        .into_message_payload(RtmpTimestamp::new(0), 0)
+        .unwrap();
+    let video_packet = serializer
+        .serialize(&video_payload, false, DropPolicy::MustDeliver)
+        .unwrap();
+
+    let bytes_until_ack = (1000 - session.bytes_received_since_last_ack) as usize;
+    let results = session
+        .handle_input(&video_packet.bytes[..bytes_until_ack - 1])
+        .unwrap();
+    assert_eq!(results.len(), 0, "Expected no results before threshold");
+
+    let results = session
+        .handle_input(&video_packet.bytes[bytes_until_ack - 1..bytes_until_ack])
+        .unwrap();
+    let (mut responses, _) = split_results(&mut deserializer, results);
+    assert_eq!(responses.len(), 1, "Unexpected number of responses");
+    match responses.remove(0) {
+        (_, RtmpMessage::Acknowledgement { sequence_number }) => assert_eq!(
+            sequence_number, session.bytes_received as u32,
+            "Unexpected sequence number"
+        ),
+
+        x => panic!("Expected Acknowledgement, instead received: {:?}", x),
+    }
+}
+
+#[test]
+fn event_raised_when_client_sends_an_acknowledgement() {
+    let (mut deserializer, mut serializer, mut session) = common_basic_setup();
+    perform_connection(
+        TEST_APP_NAME,
+        &mut session,
+        &mut serializer,
+        &mut deserializer,
+    );
+
+    let message = RtmpMessage::Acknowledgement {
+        sequence_number: 1234,
+    };
+    let payload = message
+        .into_message_payload(RtmpTimestamp::new(0), 0)
+        .unwrap();
+    let packet = serializer
+        .serialize(&payload, false, DropPolicy::MustDeliver)
+        .unwrap();
+    let results = session.handle_input(&packet.bytes[..]).unwrap();
+    let (_, mut events) = split_results(&mut deserializer, results);
+
+    assert_eq!(events.len(), 1, "Unexpected number of events");
+    match events.remove(0) {
+        ServerSessionEvent::AcknowledgementReceived { bytes_received } => {
+            assert_eq!(
+                bytes_received, 1234,
+                "Incorrect number of bytes received in event"
+            );
+        }
+
+        x => panic!(
+            "Expected acknowledgement received event, instead got: {:?}",
+            x
+        ),
+    }
+}
+
+#[test]
+fn bandwidth_probe_measures_bandwidth_from_client_response_timings() {
+    let config = get_basic_config();
+    let (mut deserializer, mut serializer, mut session) = common_setup(&config);
+    perform_connection("some_app", &mut session, &mut serializer, &mut deserializer);
+
+    let results = session.start_bandwidth_probe().unwrap();
+    let packet_sizes: Vec<usize> = results
+        .iter()
+        .map(|result| match result {
+            ServerSessionResult::OutboundResponse(packet) => packet.bytes.len(),
+            x => panic!("Unexpected probe result: {:?}", x),
+        })
+        .collect();
+
+    let (responses, _) = split_results(&mut deserializer, results);
+    let mut probe_transaction_ids = Vec::new();
+    let mut last_probe_size = 0;
+    for (_, message) in responses {
+        match message {
+            RtmpMessage::Amf0Command {
+                ref command_name,
+                transaction_id,
+                ref additional_arguments,
+                ..
+            } if command_name == "onBWCheck" => {
+                let probe_size = match additional_arguments.first() {
+                    Some(Amf0Value::Utf8String(padding)) => padding.len(),
+                    x => panic!("Unexpected probe argument: {:?}", x),
+                };
+
+                assert!(
+                    probe_size > last_probe_size,
+                    "Probes should increase in size"
+                );
+                last_probe_size = probe_size;
+                probe_transaction_ids.push(transaction_id);
+            }
+
+            x => panic!("Expected onBWCheck command, instead received {:?}", x),
+        }
+    }
+
+    assert_eq!(
+        probe_transaction_ids.len(),
+        4,
+        "Unexpected number of probes"
+    );
+    match session.start_bandwidth_probe() {
+        Err(ServerSessionError::BandwidthProbeAlreadyInProgress) => (),
+        x => panic!(
+            "Expected probe already in progress error, got {:?}",
+            x.is_ok()
+        ),
+    }
+
+    // The mock client responds to the probes 50ms apart, and like most clients leaves the
+    // timestamps of its responses at zero
+    let mut events = Vec::new();
+    let first_response_at = Instant::now();
+    for (index, transaction_id) in probe_transaction_ids.into_iter().enumerate() {
+        if index > 0 {
+            thread::sleep(Duration::from_millis(50));
+        }
+
+        let response = RtmpMessage::Amf0Command {
+            command_name: "_result".to_string(),
+            transaction_id,
+            command_object: Amf0Value::Null,
+            additional_arguments: Vec::new(),
+        };
+
+        let payload = response
+            .into_message_payload(RtmpTimestamp::new(0), 0)
+            .unwrap();
+        let packet = serializer
+            .serialize(&payload, false, DropPolicy::MustDeliver)
+            .unwrap();
+        let results = session.handle_input(&packet.bytes[..]).unwrap();
+        let (_, mut new_events) = split_results(&mut deserializer, results);
+        events.append(&mut new_events);
+    }
+
+    // The responses were at least 150ms apart, but may have been slowed down further
+    let elapsed_ms = first_response_at.elapsed().as_millis() as u64;
+    let measured_bits = packet_sizes[1..].iter().sum::<usize>() as u64 * 8;
+    let max_bits_per_second = measured_bits * 1000 / 150;
+    let min_bits_per_second = measured_bits * 1000 / (elapsed_ms + 1);
+    assert_eq!(events.len(), 1, "Unexpected number of events");
+    match events[0] {
+        ServerSessionEvent::BandwidthMeasured { bits_per_second } => assert!(
+            bits_per_second >= min_bits_per_second && bits_per_second <= max_bits_per_second,
+            "Expected bandwidth between {} and {}, but was {}",
+            min_bits_per_second,
+            max_bits_per_second,
+            bits_per_second
+        ),
+        ref x => panic!("Expected bandwidth measured event, instead got {:?}", x),
+    }
+}
+
+#[test]
+fn create_stream_rejected_when_max_streams_reached() {
+    let mut config = get_basic_config();
+    config.max_streams = 2;
+
+    let (mut deserializer, mut serializer, mut session) = common_setup(&config);
+    perform_connection(
+        TEST_APP_NAME,
+        &mut session,
+        &mut serializer,
+        &mut deserializer,
+    );
+
+    create_active_stream(&mut session, &mut serializer, &mut deserializer);
+    create_active_stream(&mut session, &mut serializer, &mut deserializer);
+
+    let message = RtmpMessage::Amf0Command {
+        command_name: "createStream".to_string(),
+        transaction_id: 5.0,
+        command_object: Amf0Value::Null,
+        additional_arguments: Vec::new(),
+    };
+
+    let payload = message
+        .into_message_payload(RtmpTimestamp::new(0), 0)
+        .unwrap();
+    let packet = serializer
+        .serialize(&payload, false, DropPolicy::MustDeliver)
+        .unwrap();
+    let results = session.handle_input(&packet.bytes[..]).unwrap();
+    let (responses, _) = split_results(&mut deserializer, results);
+
+    assert_eq!(responses.len(), 1, "Unexpected number of responses");
+    match responses[0] {
+        (
+            _,
+            RtmpMessage::Amf0Command {
+                ref command_name,
+                transaction_id,
+                ref additional_arguments,
+                ..
+            },
+        ) if command_name == "_error" && transaction_id == 5.0 => {
+            match additional_arguments.first() {
+                Some(Amf0Value::Object(properties)) => assert_eq!(
+                    properties.get("code"),
+                    Some(&Amf0Value::Utf8String("NetStream.Failed".to_string())),
+                    "Unexpected error code"
+                ),
+
+                x => panic!("Expected status object, instead received {:?}", x),
+            }
+        }
+
+        ref x => panic!("Expected _error response, instead received {:?}", x),
+    }
+
+    assert_eq!(
+        session.active_streams.len(),
+        2,
+        "Unexpected number of streams"
+    );
+}
+
+#[test]
+fn initial_peer_bandwidth_uses_configured_limit_type() {
+    let mut config = get_basic_config();
+    config.peer_bandwidth_limit_type = PeerBandwidthLimitType::Hard;
+
+    let mut deserializer = ChunkDeserializer::new();
+    let (_, results) = ServerSession::new(config).unwrap();
+    let (responses, _) = split_results(&mut deserializer, results);
+
+    let payload = responses
+        .into_iter()
+        .filter_map(|(payload, message)| match message {
+            RtmpMessage::SetPeerBandwidth { .. } => Some(payload),
+            _ => None,
+        })
+        .next()
+        .expect("No set peer bandwidth message sent");
+
+    assert_eq!(payload.data.len(), 5, "Unexpected payload length");
+    assert_eq!(payload.data[4], 0, "Expected hard limit type byte");
+}
+
+#[test]
+fn error_when_starting_bandwidth_probe_before_connection() {
+    let (_, _, mut session) = common_basic_setup();
+    match session.start_bandwidth_probe() {
+        Err(ServerSessionError::BandwidthProbeRequiresConnection) => (),
+        x => panic!("Expected connection required error, got {:?}", x.is_ok()),
     }
 }
 
 #[test]
-fn event_raised_when_ping_response_received() {
+fn can_record_video_published_on_stream() {
+    let path = std::env::temp_dir().join(format!("rml_rtmp_recording_{}.flv", std::process::id()));
     let (mut deserializer, mut serializer, mut session) = common_basic_setup();
     perform_connection(
         TEST_APP_NAME,
@@ -1346,37 +2895,49 @@ fn event_raised_when_ping_response_received() {
         &mut serializer,
         &mut deserializer,
     );
+    let stream_id = create_active_stream(&mut session, &mut serializer, &mut deserializer);
+    start_publishing(
+        TEST_STREAM_KEY,
+        stream_id,
+        &mut session,
+        &mut serializer,
+        &mut deserializer,
+    );
 
-    let message = RtmpMessage::UserControl {
-        event_type: UserControlEventType::PingResponse,
-        timestamp: Some(RtmpTimestamp::new(5230)),
-        stream_id: None,
-        buffer_length: None,
-    };
+    let handle = session.begin_recording(stream_id, &path).unwrap();
+    assert_eq!(
+        handle.stream_id(),
+        stream_id,
+        "Unexpected recording stream id"
+    );
 
+    let message = RtmpMessage::VideoData {
+        data: Bytes::from(vec![1_u8, 2_u8, 3_u8]),
+    };
     let payload = message
-        .into_message_payload(RtmpTimestamp::new(6000), 0)
+        .into_message_payload(RtmpTimestamp::new(1234), stream_id)
+        .unwrap();
+    let packet = serializer
+        .serialize(&payload, false, DropPolicy::MustDeliver)
         .unwrap();
-    let packet = serializer.serialize(&payload, false, false).unwrap();
     let results = session.handle_input(&packet.bytes[..]).unwrap();
-    let (_, mut events) = split_results(&mut deserializer, results);
+    consume_results(&mut deserializer, results);
 
-    assert_eq!(events.len(), 1, "One event expected");
-    match events.remove(0) {
-        ServerSessionEvent::PingResponseReceived { timestamp } => {
-            assert_eq!(
-                timestamp,
-                RtmpTimestamp::new(5230),
-                "Unexpected timestamp received"
-            );
-        }
+    handle.stop().unwrap();
+    let contents = std::fs::read(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
 
-        x => panic!("Expected PingResponse event, instead received {:?}", x),
-    }
+    assert_eq!(&contents[..3], b"FLV", "Expected flv header");
+    assert_eq!(
+        &contents[13..],
+        &[9, 0, 0, 3, 0, 4, 210, 0, 0, 0, 0, 1, 2, 3, 0, 0, 0, 14],
+        "Unexpected video tag"
+    );
 }
 
 #[test]
-fn can_send_ping_request() {
+fn cannot_record_stream_that_is_not_being_published_to() {
+    let path = std::env::temp_dir().join("rml_rtmp_unpublished_recording.flv");
     let (mut deserializer, mut serializer, mut session) = common_basic_setup();
     perform_connection(
         TEST_APP_NAME,
@@ -1384,39 +2945,100 @@ fn can_send_ping_request() {
         &mut serializer,
         &mut deserializer,
     );
+    let stream_id = create_active_stream(&mut session, &mut serializer, &mut deserializer);
 
-    let (packet, sent_timestamp) = session.send_ping_request().unwrap();
-    let payload = deserializer
-        .get_next_message(&packet.bytes[..])
-        .unwrap()
+    match session.begin_recording(stream_id, &path) {
+        Err(ServerSessionError::RecordingRequiresPublishingStream { stream_id: x }) => {
+            assert_eq!(x, stream_id, "Unexpected stream id in error");
+        }
+
+        x => panic!(
+            "Expected publishing stream required error, got {:?}",
+            x.is_ok()
+        ),
+    }
+
+    assert!(!path.exists(), "Expected no recording file to be created");
+}
+
+#[test]
+fn permissive_session_accepts_connection_and_publish_requests_automatically() {
+    let mut deserializer = ChunkDeserializer::new();
+    let mut serializer = ChunkSerializer::new();
+    let (mut session, initial_results) = ServerSession::new_permissive(get_basic_config()).unwrap();
+    consume_results(&mut deserializer, initial_results);
+
+    let connect_payload = create_connect_message(TEST_APP_NAME.to_string(), 15, 0, 0.0);
+    let connect_packet = serializer
+        .serialize(&connect_payload, true, DropPolicy::MustDeliver)
         .unwrap();
-    let message = payload.to_rtmp_message().unwrap();
+    let results = session.handle_input(&connect_packet.bytes[..]).unwrap();
+    let (responses, events) = split_results(&mut deserializer, results);
+
+    assert_eq!(events.len(), 0, "Expected no events from connect request");
+    assert!(
+        responses.iter().any(|(_, message)| match message {
+            RtmpMessage::Amf0Command { command_name, .. } => command_name == "_result",
+            _ => false,
+        }),
+        "No connection success response found"
+    );
 
-    match message {
-        RtmpMessage::UserControl {
-            event_type,
-            timestamp: Some(timestamp),
-            buffer_length: None,
-            stream_id: None,
-        } => {
-            assert_eq!(
-                event_type,
-                UserControlEventType::PingRequest,
-                "Unexpected user control event type"
-            );
-            assert_eq!(
-                timestamp, sent_timestamp,
-                "Unexpected timestamp in outbound message"
-            );
-        }
+    let stream_id = create_active_stream(&mut session, &mut serializer, &mut deserializer);
+    let message = RtmpMessage::Amf0Command {
+        command_name: "publish".to_string(),
+        transaction_id: 5.0,
+        command_object: Amf0Value::Null,
+        additional_arguments: vec![
+            Amf0Value::Utf8String(TEST_STREAM_KEY.to_string()),
+            Amf0Value::Utf8String("live".to_string()),
+        ],
+    };
+    let publish_payload = message
+        .into_message_payload(RtmpTimestamp::new(0), stream_id)
+        .unwrap();
+    let publish_packet = serializer
+        .serialize(&publish_payload, false, DropPolicy::MustDeliver)
+        .unwrap();
+    let results = session.handle_input(&publish_packet.bytes[..]).unwrap();
+    let (responses, events) = split_results(&mut deserializer, results);
 
-        x => panic!("Expected PingRequest being sent, instead found {:?}", x),
+    assert_eq!(events.len(), 0, "Expected no events from publish request");
+    let (_, last_response) = responses.last().expect("No publish responses");
+    verify_is_onstatus(last_response, "status", "NetStream.Publish.Start");
+
+    let video_payload = RtmpMessage::VideoData {
+        data: Bytes::from(vec![1, 2, 3]),
+    }
+    .into_message_payload(RtmpTimestamp::new(0), stream_id)
+    .unwrap();
+    let video_packet = serializer
+        .serialize(&video_payload, false, DropPolicy::MustDeliver)
+        .unwrap();
+    let results = session.handle_input(&video_packet.bytes[..]).unwrap();
+    let (_, events) = split_results(&mut deserializer, results);
+
+    match events.first() {
+        Some(ServerSessionEvent::VideoDataReceived { .. }) => (),
+        x => panic!("Expected video data received event, found {:?}", x),
     }
 }
 
 #[test]
-fn can_finish_playing_stream() {
-    let (mut deserializer, mut serializer, mut session) = common_basic_setup();
+fn middleware_can_drop_inbound_messages() {
+    struct DropVideo;
+    impl ServerMiddleware for DropVideo {
+        fn intercept_inbound(&self, message: &RtmpMessage, _: u32) -> MiddlewareDecision {
+            match message {
+                RtmpMessage::VideoData { .. } => MiddlewareDecision::Drop,
+                _ => MiddlewareDecision::Allow,
+            }
+        }
+    }
+
+    let mut config = get_basic_config();
+    config.middleware.push(Arc::new(DropVideo));
+    let (mut deserializer, mut serializer, mut session) = common_setup(&config);
     perform_connection(
         TEST_APP_NAME,
         &mut session,
@@ -1424,7 +3046,7 @@ fn can_finish_playing_stream() {
         &mut deserializer,
     );
     let stream_id = create_active_stream(&mut session, &mut serializer, &mut deserializer);
-    start_playing(
+    start_publishing(
         TEST_STREAM_KEY,
         stream_id,
         &mut session,
@@ -1432,119 +3054,131 @@ fn can_finish_playing_stream() {
         &mut deserializer,
     );
 
-    let packet = session.finish_playing(stream_id).unwrap();
-    let payload = deserializer
-        .get_next_message(&packet.bytes[..])
-        .unwrap()
+    let video_payload = RtmpMessage::VideoData {
+        data: Bytes::from(vec![1, 2, 3]),
+    }
+    .into_message_payload(RtmpTimestamp::new(0), stream_id)
+    .unwrap();
+    let video_packet = serializer
+        .serialize(&video_payload, false, DropPolicy::MustDeliver)
         .unwrap();
-    let message = payload.to_rtmp_message().unwrap();
+    let results = session.handle_input(&video_packet.bytes[..]).unwrap();
+    let (responses, events) = split_results(&mut deserializer, results);
 
-    verify_is_onstatus(&message, "status", "NetStream.Play.Complete");
+    assert_eq!(responses.len(), 0, "Unexpected responses returned");
+    assert_eq!(
+        events.len(),
+        0,
+        "Expected dropped message to raise no events"
+    );
 }
 
 #[test]
-fn sends_ack_after_receiving_window_ack_bytes() {
-    let (mut deserializer, mut serializer, mut session) = common_basic_setup();
+fn middleware_can_replace_inbound_messages() {
+    struct ReplaceVideo;
+    impl ServerMiddleware for ReplaceVideo {
+        fn intercept_inbound(&self, message: &RtmpMessage, _: u32) -> MiddlewareDecision {
+            match message {
+                RtmpMessage::VideoData { .. } => {
+                    MiddlewareDecision::Replace(RtmpMessage::VideoData {
+                        data: Bytes::from(vec![9, 9]),
+                    })
+                }
+                _ => MiddlewareDecision::Allow,
+            }
+        }
+    }
+
+    let mut config = get_basic_config();
+    config.middleware.push(Arc::new(ReplaceVideo));
+    let (mut deserializer, mut serializer, mut session) = common_setup(&config);
     perform_connection(
         TEST_APP_NAME,
         &mut session,
         &mut serializer,
         &mut deserializer,
     );
+    let stream_id = create_active_stream(&mut session, &mut serializer, &mut deserializer);
+    start_publishing(
+        TEST_STREAM_KEY,
+        stream_id,
+        &mut session,
+        &mut serializer,
+        &mut deserializer,
+    );
 
-    let window_ack_message = RtmpMessage::WindowAcknowledgement { size: 100 };
-    let window_ack_payload = window_ack_message
-        .into_message_payload(RtmpTimestamp::new(0), 0)
-        .unwrap();
-    let window_ack_packet = serializer
-        .serialize(&window_ack_payload, false, false)
-        .unwrap();
-    let results = session.handle_input(&window_ack_packet.bytes[..]).unwrap();
-    consume_results(&mut deserializer, results);
-
-    let mut bytes = BytesMut::new();
-    bytes.extend_from_slice(&[1; 101]);
-    let video_message = RtmpMessage::VideoData {
-        data: bytes.freeze(),
-    };
-    let video_payload = video_message
-        .into_message_payload(RtmpTimestamp::new(0), 0)
-        .unwrap();
-    let video_packet = serializer.serialize(&video_payload, false, false).unwrap();
-    let results = session.handle_input(&video_packet.bytes[..]).unwrap();
-    let (mut responses, _) = split_results(&mut deserializer, results);
-
-    assert_eq!(responses.len(), 1, "Unexpected number of responses");
-    match responses.remove(0) {
-        (_, RtmpMessage::Acknowledgement { sequence_number: _ }) => (), // No good way to predict sequence number
-        x => panic!("Expected Acknowledgement, instead received: {:?}", x),
+    let video_payload = RtmpMessage::VideoData {
+        data: Bytes::from(vec![1, 2, 3]),
     }
-
-    let mut bytes = BytesMut::new();
-    bytes.extend_from_slice(&[1; 1]);
-    let video_message = RtmpMessage::VideoData {
-        data: bytes.freeze(),
-    };
-    let video_payload = video_message
-        .into_message_payload(RtmpTimestamp::new(0), 0)
+    .into_message_payload(RtmpTimestamp::new(0), stream_id)
+    .unwrap();
+    let video_packet = serializer
+        .serialize(&video_payload, false, DropPolicy::MustDeliver)
         .unwrap();
-    let video_packet = serializer.serialize(&video_payload, false, false).unwrap();
     let results = session.handle_input(&video_packet.bytes[..]).unwrap();
-    let (responses, _) = split_results(&mut deserializer, results);
-    assert_eq!(responses.len(), 0, "Expected no responses");
+    let (_, events) = split_results(&mut deserializer, results);
 
-    let mut bytes = BytesMut::new();
-    bytes.extend_from_slice(&[1; 100]);
-    let video_message = RtmpMessage::VideoData {
-        data: bytes.freeze(),
-    };
-    let video_payload = video_message
-        .into_message_payload(RtmpTimestamp::new(0), 0)
-        .unwrap();
-    let video_packet = serializer.serialize(&video_payload, false, false).unwrap();
-    let results = session.handle_input(&video_packet.bytes[..]).unwrap();
-    let (mut responses, _) = split_results(&mut deserializer, results);
-    assert_eq!(responses.len(), 1, "Unexpected number of responses");
-    match responses.remove(0) {
-        (_, RtmpMessage::Acknowledgement { sequence_number: _ }) => (), // No good way to predict sequence number
-        x => panic!("Expected Acknowledgement, instead received: {:?}", x),
+    assert_eq!(events.len(), 1, "Unexpected number of events returned");
+    match events[0] {
+        ServerSessionEvent::VideoDataReceived { ref data, .. } => {
+            assert_eq!(
+                &data[..],
+                &[9_u8, 9_u8],
+                "Expected the replacement video data"
+            )
+        }
+
+        ref event => panic!("Expected VideoDataReceived event, instead got: {:?}", event),
     }
 }
 
 #[test]
-fn event_raised_when_client_sends_an_acknowledgement() {
-    let (mut deserializer, mut serializer, mut session) = common_basic_setup();
-    perform_connection(
-        TEST_APP_NAME,
-        &mut session,
-        &mut serializer,
-        &mut deserializer,
-    );
+fn error_when_sending_media_to_stream_with_full_outbound_queue() {
+    let mut config = get_basic_config();
+    config.max_outbound_queue_per_stream = Some(2);
+    let (_, _, mut session) = common_setup(&config);
 
-    let message = RtmpMessage::Acknowledgement {
-        sequence_number: 1234,
-    };
-    let payload = message
-        .into_message_payload(RtmpTimestamp::new(0), 0)
+    let data = Bytes::from(vec![1_u8, 2_u8, 3_u8]);
+    let timestamp = RtmpTimestamp::new(0);
+    session
+        .send_video_data(1, data.clone(), timestamp, DropPolicy::DropUnderLoad)
         .unwrap();
-    let packet = serializer.serialize(&payload, false, false).unwrap();
-    let results = session.handle_input(&packet.bytes[..]).unwrap();
-    let (_, mut events) = split_results(&mut deserializer, results);
+    session
+        .send_audio_data(1, data.clone(), timestamp, DropPolicy::DropUnderLoad)
+        .unwrap();
+    assert_eq!(session.outbound_queue_depth(1), 2, "Unexpected queue depth");
 
-    assert_eq!(events.len(), 1, "Unexpected number of events");
-    match events.remove(0) {
-        ServerSessionEvent::AcknowledgementReceived { bytes_received } => {
-            assert_eq!(
-                bytes_received, 1234,
-                "Incorrect number of bytes received in event"
-            );
-        }
+    match session.send_video_data(1, data.clone(), timestamp, DropPolicy::DropUnderLoad) {
+        Err(ServerSessionError::ClientOutputQueueFull { stream_id: 1 }) => (),
+        x => panic!("Expected ClientOutputQueueFull error, instead got {:?}", x),
+    }
 
-        x => panic!(
-            "Expected acknowledgement received event, instead got: {:?}",
-            x
-        ),
+    // Other streams have their own queues
+    session
+        .send_video_data(2, data.clone(), timestamp, DropPolicy::DropUnderLoad)
+        .unwrap();
+
+    session.outbound_packet_sent(1);
+    session
+        .send_video_data(1, data, timestamp, DropPolicy::DropUnderLoad)
+        .unwrap();
+}
+
+#[test]
+fn outbound_queue_not_tracked_when_unlimited() {
+    let (_, _, mut session) = common_basic_setup();
+    for _ in 0..100 {
+        session
+            .send_video_data(
+                1,
+                Bytes::from(vec![1_u8]),
+                RtmpTimestamp::new(0),
+                DropPolicy::DropUnderLoad,
+            )
+            .unwrap();
     }
+
+    assert_eq!(session.outbound_queue_depth(1), 0, "Unexpected queue depth");
 }
 
 fn get_basic_config() -> ServerSessionConfig {
@@ -1552,8 +3186,16 @@ fn get_basic_config() -> ServerSessionConfig {
         chunk_size: DEFAULT_CHUNK_SIZE,
         fms_version: "fms_version".to_string(),
         peer_bandwidth: DEFAULT_PEER_BANDWIDTH,
+        peer_bandwidth_limit_type: PeerBandwidthLimitType::Dynamic,
         window_ack_size: DEFAULT_WINDOW_ACK_SIZE,
         send_on_bw_done_message_on_start: true,
+        peer_max_chunk_size: 128,
+        max_streams: 10,
+        auth_callback: None,
+        max_inbound_audio_bytes: 1024 * 1024,
+        max_inbound_video_bytes: 4 * 1024 * 1024,
+        middleware: Vec::new(),
+        max_outbound_queue_per_stream: None,
     }
 }
 
@@ -1567,10 +3209,91 @@ fn common_setup(
     (deserializer, serializer, session)
 }
 
+#[cfg(feature = "async")]
+#[test]
+fn async_accept_request_returns_same_results_as_sync_version() {
+    // This crate uses the 2015 edition so `#[tokio::test]` and `.await` are not available,
+    // but the future can still be run to completion on a tokio runtime
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .build()
+        .unwrap();
+
+    let mut messages = Vec::new();
+    for &use_async in [false, true].iter() {
+        let (mut deserializer, mut serializer, mut session) = common_basic_setup();
+        let connect_payload = create_connect_message(TEST_APP_NAME.to_string(), 15, 0, 0.0);
+        let connect_packet = serializer
+            .serialize(&connect_payload, true, DropPolicy::MustDeliver)
+            .unwrap();
+        let results = session.handle_input(&connect_packet.bytes[..]).unwrap();
+        let (_, events) = split_results(&mut deserializer, results);
+        let request_id = match events[0] {
+            ServerSessionEvent::ConnectionRequested { request_id, .. } => request_id,
+            ref x => panic!("Expected connection requested event, found {:?}", x),
+        };
+
+        let results = if use_async {
+            runtime
+                .block_on(session.accept_request_async(request_id))
+                .unwrap()
+        } else {
+            session.accept_request(request_id).unwrap()
+        };
+
+        let (responses, _) = split_results(&mut deserializer, results);
+        let response_messages = responses
+            .into_iter()
+            .map(|(_, message)| message)
+            .collect::<Vec<_>>();
+
+        messages.push(response_messages);
+    }
+
+    assert!(
+        !messages[0].is_empty(),
+        "No responses to accepting the request"
+    );
+    assert_eq!(
+        messages[0], messages[1],
+        "Async results differed from sync results"
+    );
+}
+
 fn common_basic_setup() -> (ChunkDeserializer, ChunkSerializer, ServerSession) {
     common_setup(&get_basic_config())
 }
 
+/// Builds the data of an aggregate message whose only sub-message is another aggregate, nested
+/// `depth` times around a single video message.  Each level's header and back pointer are
+/// written around the level inside of it, so deep nesting doesn't need repeated copies.
+fn nested_aggregate(depth: usize) -> Bytes {
+    let video_data = [1_u8, 2_u8];
+    let mut headers = Vec::new();
+    let mut back_pointers = Vec::new();
+    let mut length = video_data.len() as u32;
+    for level in 0..depth {
+        let type_id = if level == 0 { 9_u8 } else { 22_u8 };
+        let mut header = vec![type_id];
+        header.extend_from_slice(&length.to_be_bytes()[1..]);
+        header.extend_from_slice(&[0_u8; 7]);
+        headers.push(header);
+        back_pointers.push((11 + length).to_be_bytes());
+        length += 15;
+    }
+
+    let mut data = Vec::with_capacity(length as usize);
+    for header in headers.iter().rev() {
+        data.extend_from_slice(header);
+    }
+
+    data.extend_from_slice(&video_data);
+    for back_pointer in &back_pointers {
+        data.extend_from_slice(back_pointer);
+    }
+
+    Bytes::from(data)
+}
+
 fn split_results(
     deserializer: &mut ChunkDeserializer,
     mut results: Vec<ServerSessionResult>,
@@ -1646,7 +3369,9 @@ fn perform_connection(
     deserializer: &mut ChunkDeserializer,
 ) {
     let connect_payload = create_connect_message(app_name.to_string(), 15, 0, 0.0);
-    let connect_packet = serializer.serialize(&connect_payload, true, false).unwrap();
+    let connect_packet = serializer
+        .serialize(&connect_payload, true, DropPolicy::MustDeliver)
+        .unwrap();
     let connect_results = session.handle_input(&connect_packet.bytes[..]).unwrap();
     assert_eq!(
         connect_results.len(),
@@ -1660,6 +3385,7 @@ fn perform_connection(
         ServerSessionEvent::ConnectionRequested {
             ref app_name,
             request_id,
+            object_encoding: _,
         } if app_name == "some_app" => request_id,
         _ => panic!("First event was not as expected: {:?}", events[0]),
     };
@@ -1685,7 +3411,9 @@ fn create_active_stream(
     let payload = message
         .into_message_payload(RtmpTimestamp::new(0), 0)
         .unwrap();
-    let packet = serializer.serialize(&payload, true, false).unwrap();
+    let packet = serializer
+        .serialize(&payload, true, DropPolicy::MustDeliver)
+        .unwrap();
     let results = session.handle_input(&packet.bytes[..]).unwrap();
     let (responses, _) = split_results(deserializer, results);
 
@@ -1738,7 +3466,9 @@ fn close_stream(
     let payload = message
         .into_message_payload(RtmpTimestamp::new(0), stream_id)
         .unwrap();
-    let packet = serializer.serialize(&payload, false, false).unwrap();
+    let packet = serializer
+        .serialize(&payload, false, DropPolicy::MustDeliver)
+        .unwrap();
     let results = session.handle_input(&packet.bytes[..]).unwrap();
     consume_results(deserializer, results);
 }
@@ -1764,7 +3494,7 @@ fn start_publishing(
         .into_message_payload(RtmpTimestamp::new(0), stream_id)
         .unwrap();
     let publish_packet = serializer
-        .serialize(&publish_payload, false, false)
+        .serialize(&publish_payload, false, DropPolicy::MustDeliver)
         .unwrap();
     let publish_results = session.handle_input(&publish_packet.bytes[..]).unwrap();
     let (_, events) = split_results(deserializer, publish_results);
@@ -1776,6 +3506,7 @@ fn start_publishing(
             ref stream_key,
             request_id: returned_request_id,
             mode: PublishMode::Live,
+            stream_id: _,
         } if app_name == "some_app" && stream_key == "stream_key" => returned_request_id,
 
         _ => panic!("Unexpected first event found: {:?}", events[0]),
@@ -1807,7 +3538,9 @@ fn start_playing(
     let play_payload = message
         .into_message_payload(RtmpTimestamp::new(0), stream_id)
         .unwrap();
-    let play_packet = serializer.serialize(&play_payload, false, false).unwrap();
+    let play_packet = serializer
+        .serialize(&play_payload, false, DropPolicy::MustDeliver)
+        .unwrap();
     let play_results = session.handle_input(&play_packet.bytes[..]).unwrap();
     let (_, mut events) = split_results(deserializer, play_results);
 
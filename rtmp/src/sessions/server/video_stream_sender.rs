@@ -0,0 +1,70 @@
+use super::{ServerSession, ServerSessionError};
+use chunk_io::{Packet, PartialMessage};
+use sessions::{Direction, MessageTrace};
+use time::RtmpTimestamp;
+
+/// Sends a single video message to the client in parts, created by
+/// `ServerSession::begin_video_stream()`.
+///
+/// The sender holds on to the session until the message is finished, since nothing else can be
+/// sent on the video chunk stream until the whole message has been sent.
+pub struct VideoStreamSender<'a> {
+    session: &'a mut ServerSession,
+    message: PartialMessage,
+    stream_id: u32,
+    timestamp: RtmpTimestamp,
+    bytes_sent: usize,
+}
+
+impl<'a> VideoStreamSender<'a> {
+    pub(super) fn new(
+        session: &'a mut ServerSession,
+        message: PartialMessage,
+        stream_id: u32,
+        timestamp: RtmpTimestamp,
+    ) -> VideoStreamSender<'a> {
+        VideoStreamSender {
+            session,
+            message,
+            stream_id,
+            timestamp,
+            bytes_sent: 0,
+        }
+    }
+
+    /// Returns how many bytes of the video message still need to be provided
+    pub fn remaining_length(&self) -> u32 {
+        self.message.remaining_length()
+    }
+
+    /// Prepares the next part of the video message to be sent to the client.  The returned
+    /// packets must be sent in order, and must all be sent, as dropping any one of them would
+    /// leave the client unable to read the rest of the message.
+    pub fn send_chunk(&mut self, data: &[u8]) -> Result<Packet, ServerSessionError> {
+        let packet = self
+            .session
+            .serializer
+            .serialize_partial(&mut self.message, data)?;
+
+        self.bytes_sent += packet.bytes.len();
+        Ok(packet)
+    }
+
+    /// Completes the video message, returning an error if less data was sent than the length
+    /// the message was started with.
+    pub fn finish(self) -> Result<(), ServerSessionError> {
+        self.message.finish()?;
+
+        if let Some(ref tracer) = self.session.message_tracer {
+            tracer(MessageTrace {
+                direction: Direction::Outbound,
+                message_type: "VideoData",
+                timestamp: self.timestamp,
+                stream_id: self.stream_id,
+                byte_count: self.bytes_sent,
+            });
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,237 @@
+use std::cmp::min;
+use std::io::{self, Read, Write};
+use std::sync::mpsc::{channel, Receiver, Sender, TryRecvError};
+
+/// One end of an in-process byte stream created by `loopback_pair()`.
+///
+/// Bytes written to one endpoint can be read from the other endpoint.  Reads never block,
+/// instead they behave like a non-blocking socket and return an error of kind
+/// `io::ErrorKind::WouldBlock` when no bytes are available.  Once the other endpoint has been
+/// dropped and all of its bytes have been read, reads return `Ok(0)`.
+pub struct LoopbackEndpoint {
+    sender: Sender<Vec<u8>>,
+    receiver: Receiver<Vec<u8>>,
+    buffer: Vec<u8>,
+}
+
+/// Creates two connected loopback endpoints, allowing a `ClientSession` and `ServerSession`
+/// to talk to each other without real sockets.
+///
+/// ## Examples
+///
+/// ```
+/// use std::io::{Read, Write};
+/// use rml_rtmp::test_utils::loopback_pair;
+///
+/// let (mut client, mut server) = loopback_pair();
+/// client.write_all(&[1, 2, 3]).unwrap();
+///
+/// let mut buffer = [0_u8; 10];
+/// let bytes_read = server.read(&mut buffer).unwrap();
+/// assert_eq!(&buffer[..bytes_read], &[1, 2, 3]);
+/// ```
+pub fn loopback_pair() -> (LoopbackEndpoint, LoopbackEndpoint) {
+    let (first_sender, first_receiver) = channel();
+    let (second_sender, second_receiver) = channel();
+
+    let first = LoopbackEndpoint {
+        sender: first_sender,
+        receiver: second_receiver,
+        buffer: Vec::new(),
+    };
+
+    let second = LoopbackEndpoint {
+        sender: second_sender,
+        receiver: first_receiver,
+        buffer: Vec::new(),
+    };
+
+    (first, second)
+}
+
+impl LoopbackEndpoint {
+    /// Reads all bytes currently available from the other endpoint
+    pub fn read_available(&mut self) -> io::Result<Vec<u8>> {
+        self.fill_buffer()?;
+        Ok(self.buffer.split_off(0))
+    }
+
+    fn fill_buffer(&mut self) -> io::Result<bool> {
+        loop {
+            match self.receiver.try_recv() {
+                Ok(bytes) => self.buffer.extend(bytes),
+                Err(TryRecvError::Empty) => return Ok(false),
+                Err(TryRecvError::Disconnected) => return Ok(true),
+            }
+        }
+    }
+}
+
+impl Read for LoopbackEndpoint {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let disconnected = self.fill_buffer()?;
+        if self.buffer.is_empty() {
+            if disconnected {
+                return Ok(0);
+            }
+
+            return Err(io::Error::new(
+                io::ErrorKind::WouldBlock,
+                "No bytes available from the other endpoint",
+            ));
+        }
+
+        let length = min(buf.len(), self.buffer.len());
+        buf[..length].copy_from_slice(&self.buffer[..length]);
+        let _ = self.buffer.drain(..length);
+        Ok(length)
+    }
+}
+
+impl Write for LoopbackEndpoint {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self.sender.send(buf.to_vec()) {
+            Ok(_) => Ok(buf.len()),
+            Err(_) => Err(io::Error::new(
+                io::ErrorKind::BrokenPipe,
+                "The other endpoint has been dropped",
+            )),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sessions::{ClientSession, ClientSessionConfig, ClientSessionEvent, ClientSessionResult};
+    use sessions::{ServerSession, ServerSessionConfig, ServerSessionEvent, ServerSessionResult};
+
+    #[test]
+    fn bytes_written_to_one_endpoint_can_be_read_from_the_other() {
+        let (mut first, mut second) = loopback_pair();
+        first.write_all(&[1, 2, 3]).unwrap();
+        first.write_all(&[4, 5]).unwrap();
+        second.write_all(&[6]).unwrap();
+
+        let mut buffer = [0_u8; 4];
+        assert_eq!(
+            second.read(&mut buffer).unwrap(),
+            4,
+            "Unexpected first read length"
+        );
+        assert_eq!(buffer, [1, 2, 3, 4], "Unexpected first read bytes");
+        assert_eq!(
+            second.read(&mut buffer).unwrap(),
+            1,
+            "Unexpected second read length"
+        );
+        assert_eq!(buffer[0], 5, "Unexpected second read bytes");
+        assert_eq!(first.read_available().unwrap(), vec![6], "Unexpected bytes");
+    }
+
+    #[test]
+    fn read_with_no_bytes_available_would_block() {
+        let (mut first, _second) = loopback_pair();
+        let mut buffer = [0_u8; 4];
+        match first.read(&mut buffer) {
+            Err(ref error) if error.kind() == io::ErrorKind::WouldBlock => (),
+            x => panic!("Unexpected read result: {:?}", x),
+        }
+    }
+
+    #[test]
+    fn read_returns_zero_bytes_after_other_endpoint_is_dropped() {
+        let (mut first, second) = loopback_pair();
+        drop(second);
+
+        let mut buffer = [0_u8; 4];
+        assert_eq!(
+            first.read(&mut buffer).unwrap(),
+            0,
+            "Expected end of stream"
+        );
+    }
+
+    #[test]
+    fn client_and_server_sessions_can_connect_over_loopback() {
+        let (mut client_endpoint, mut server_endpoint) = loopback_pair();
+        let (mut client, client_results) = ClientSession::new(ClientSessionConfig::new()).unwrap();
+        let (mut server, server_results) = ServerSession::new(ServerSessionConfig::new()).unwrap();
+
+        let mut client_events = write_client_results(&mut client_endpoint, client_results);
+        let mut server_events = Vec::new();
+        write_server_results(&mut server, &mut server_endpoint, server_results);
+
+        let results = client.request_connection("test".to_string()).unwrap();
+        client_events.extend(write_client_results(&mut client_endpoint, results));
+
+        for _ in 0..5 {
+            let bytes = server_endpoint.read_available().unwrap();
+            let results = server.handle_input(&bytes).unwrap();
+            server_events.extend(write_server_results(
+                &mut server,
+                &mut server_endpoint,
+                results,
+            ));
+
+            let bytes = client_endpoint.read_available().unwrap();
+            let results = client.handle_input(&bytes).unwrap();
+            client_events.extend(write_client_results(&mut client_endpoint, results));
+        }
+
+        assert_vec_contains!(server_events, ServerSessionEvent::ConnectionRequested {
+            request_id: _,
+            ref app_name,
+            object_encoding: _,
+        } if app_name == "test");
+        assert_vec_contains!(client_events, ClientSessionEvent::ConnectionRequestAccepted);
+    }
+
+    fn write_client_results(
+        endpoint: &mut LoopbackEndpoint,
+        results: Vec<ClientSessionResult>,
+    ) -> Vec<ClientSessionEvent> {
+        let mut events = Vec::new();
+        for result in results {
+            match result {
+                ClientSessionResult::OutboundResponse(packet) => {
+                    endpoint.write_all(&packet.bytes).unwrap()
+                }
+                ClientSessionResult::RaisedEvent(event) => events.push(event),
+                _ => (),
+            }
+        }
+
+        events
+    }
+
+    fn write_server_results(
+        session: &mut ServerSession,
+        endpoint: &mut LoopbackEndpoint,
+        results: Vec<ServerSessionResult>,
+    ) -> Vec<ServerSessionEvent> {
+        let mut events = Vec::new();
+        for result in results {
+            match result {
+                ServerSessionResult::OutboundResponse(packet) => {
+                    endpoint.write_all(&packet.bytes).unwrap()
+                }
+                ServerSessionResult::RaisedEvent(event) => {
+                    if let ServerSessionEvent::ConnectionRequested { request_id, .. } = event {
+                        let accept_results = session.accept_request(request_id).unwrap();
+                        events.extend(write_server_results(session, endpoint, accept_results));
+                    }
+
+                    events.push(event);
+                }
+                _ => (),
+            }
+        }
+
+        events
+    }
+}
@@ -0,0 +1,231 @@
+use handshake::{Handshake, HandshakeProcessResult, PeerType};
+use sessions::{ServerSession, ServerSessionConfig, ServerSessionEvent, ServerSessionResult};
+use std::collections::VecDeque;
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::thread;
+
+/// An in-memory RTMP server that can be used to test client code without any network I/O.
+///
+/// Each call to `client_connection()` spawns a thread running its own `ServerSession`, which
+/// communicates with the returned `MockRtmpConnection` over `std::sync::mpsc` channels.  The
+/// handshake is performed automatically, and all connection, publish, and play requests are
+/// automatically accepted by the server.
+///
+/// Every event raised by any of the server sessions can be retrieved via `server_read_events()`.
+///
+/// ## Examples
+///
+/// ```
+/// use rml_rtmp::sessions::{ServerSessionConfig, ServerSessionEvent};
+/// use rml_rtmp::sessions::{ClientSession, ClientSessionConfig, ClientSessionResult};
+/// use rml_rtmp::test_utils::MockRtmpServer;
+///
+/// let server = MockRtmpServer::new(ServerSessionConfig::new());
+/// let mut connection = server.client_connection();
+///
+/// let (mut session, _) = ClientSession::new(ClientSessionConfig::new()).unwrap();
+/// for result in session.request_connection("live".to_string()).unwrap() {
+///     match result {
+///         ClientSessionResult::OutboundResponse(packet) => connection.send_bytes(&packet.bytes),
+///         x => panic!("Unexpected connection result: {:?}", x),
+///     }
+/// }
+///
+/// let events = server.server_read_events();
+/// assert!(events.iter().any(|event| match event {
+///     ServerSessionEvent::ConnectionRequested { app_name, .. } => app_name == "live",
+///     _ => false,
+/// }));
+/// ```
+pub struct MockRtmpServer {
+    config: ServerSessionConfig,
+    event_sender: Sender<ServerSessionEvent>,
+    event_receiver: Receiver<ServerSessionEvent>,
+}
+
+/// The client side of a connection to a `MockRtmpServer`.
+///
+/// Every call to `send_bytes()` blocks until the server has processed the bytes, so any
+/// responses and events the server produced are available as soon as the call returns.
+pub struct MockRtmpConnection {
+    to_server: Sender<Vec<u8>>,
+    from_server: Receiver<Vec<u8>>,
+    received_bytes: Vec<u8>,
+}
+
+impl MockRtmpServer {
+    /// Creates a new mock server.  Each connection made to this server will have its own
+    /// server session created with the specified configuration.
+    pub fn new(config: ServerSessionConfig) -> MockRtmpServer {
+        let (event_sender, event_receiver) = channel();
+        MockRtmpServer {
+            config,
+            event_sender,
+            event_receiver,
+        }
+    }
+
+    /// Creates a new client connection to the server, with the handshake already completed.
+    ///
+    /// Any bytes the server sent immediately after the handshake (such as the initial chunk size
+    /// and window acknowledgement messages) are available via `take_received_bytes()`.
+    pub fn client_connection(&self) -> MockRtmpConnection {
+        let (to_server, server_input) = channel();
+        let (server_output, from_server) = channel();
+        let config = self.config.clone();
+        let event_sender = self.event_sender.clone();
+        thread::spawn(move || run_server(config, server_input, server_output, event_sender));
+
+        let mut connection = MockRtmpConnection {
+            to_server,
+            from_server,
+            received_bytes: Vec::new(),
+        };
+
+        let mut handshake = Handshake::new(PeerType::Client);
+        let p0_and_p1 = handshake
+            .generate_outbound_p0_and_p1()
+            .expect("Failed to generate client p0 and p1");
+
+        let mut server_bytes = connection.exchange(p0_and_p1);
+        loop {
+            match handshake.process_bytes(&server_bytes) {
+                Ok(HandshakeProcessResult::InProgress { response_bytes }) => {
+                    server_bytes = connection.exchange(response_bytes);
+                }
+
+                Ok(HandshakeProcessResult::Completed {
+                    response_bytes,
+                    remaining_bytes,
+                }) => {
+                    connection.received_bytes.extend(remaining_bytes);
+                    if !response_bytes.is_empty() {
+                        let response = connection.exchange(response_bytes);
+                        connection.received_bytes.extend(response);
+                    }
+
+                    break;
+                }
+
+                Err(error) => panic!("Client handshake with mock server failed: {:?}", error),
+            }
+        }
+
+        connection
+    }
+
+    /// Returns all events raised by server sessions since the last time this was called
+    pub fn server_read_events(&self) -> Vec<ServerSessionEvent> {
+        self.event_receiver.try_iter().collect()
+    }
+}
+
+impl MockRtmpConnection {
+    /// Sends the bytes to the server and waits for the server to process them.  Any bytes the
+    /// server responds with are stored until retrieved by `take_received_bytes()`.
+    pub fn send_bytes(&mut self, bytes: &[u8]) {
+        let response = self.exchange(bytes.to_vec());
+        self.received_bytes.extend(response);
+    }
+
+    /// Returns all bytes that have been received from the server and not yet taken
+    pub fn take_received_bytes(&mut self) -> Vec<u8> {
+        self.received_bytes.split_off(0)
+    }
+
+    fn exchange(&mut self, bytes: Vec<u8>) -> Vec<u8> {
+        self.to_server
+            .send(bytes)
+            .expect("Mock server is no longer running");
+
+        self.from_server
+            .recv()
+            .expect("Mock server is no longer running")
+    }
+}
+
+fn run_server(
+    config: ServerSessionConfig,
+    input: Receiver<Vec<u8>>,
+    output: Sender<Vec<u8>>,
+    events: Sender<ServerSessionEvent>,
+) {
+    let mut handshake = Handshake::new(PeerType::Server);
+    let mut session: Option<ServerSession> = None;
+
+    for bytes in input.iter() {
+        let mut response = Vec::new();
+        let mut results = VecDeque::new();
+
+        match session {
+            Some(ref mut session) => {
+                let new_results = session
+                    .handle_input(&bytes)
+                    .expect("Mock server session failed to handle input");
+
+                results.extend(new_results);
+            }
+
+            None => match handshake.process_bytes(&bytes) {
+                Ok(HandshakeProcessResult::InProgress { response_bytes }) => {
+                    response.extend(response_bytes);
+                }
+
+                Ok(HandshakeProcessResult::Completed {
+                    response_bytes,
+                    remaining_bytes,
+                }) => {
+                    response.extend(response_bytes);
+
+                    let (mut new_session, initial_results) = ServerSession::new(config.clone())
+                        .expect("Failed to create mock server session");
+
+                    results.extend(initial_results);
+                    let new_results = new_session
+                        .handle_input(&remaining_bytes)
+                        .expect("Mock server session failed to handle input");
+
+                    results.extend(new_results);
+                    session = Some(new_session);
+                }
+
+                Err(error) => panic!("Mock server handshake failed: {:?}", error),
+            },
+        }
+
+        while let Some(result) = results.pop_front() {
+            match result {
+                ServerSessionResult::OutboundResponse(packet) => response.extend(packet.bytes),
+                ServerSessionResult::UnhandleableMessageReceived(_) => (),
+                ServerSessionResult::RaisedEvent(event) => {
+                    let request_id = match event {
+                        ServerSessionEvent::ConnectionRequested { request_id, .. } => {
+                            Some(request_id)
+                        }
+                        ServerSessionEvent::PublishStreamRequested { request_id, .. } => {
+                            Some(request_id)
+                        }
+                        ServerSessionEvent::PlayStreamRequested { request_id, .. } => {
+                            Some(request_id)
+                        }
+                        _ => None,
+                    };
+
+                    if let (Some(request_id), Some(session)) = (request_id, session.as_mut()) {
+                        let accept_results = session
+                            .accept_request(request_id)
+                            .expect("Mock server failed to accept request");
+
+                        results.extend(accept_results);
+                    }
+
+                    let _ = events.send(event);
+                }
+            }
+        }
+
+        if output.send(response).is_err() {
+            break;
+        }
+    }
+}
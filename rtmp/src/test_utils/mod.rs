@@ -0,0 +1,18 @@
+/*!
+Utilities for testing code built on top of this crate without requiring any network I/O.
+
+This module is only available when the `test-utils` feature is enabled.
+*/
+
+#[cfg(test)]
+#[macro_use]
+mod assert_vec_match_macro;
+#[cfg(test)]
+#[macro_use]
+mod assert_vec_contains_macro;
+
+mod loopback;
+mod mock_server;
+
+pub use self::loopback::{loopback_pair, LoopbackEndpoint};
+pub use self::mock_server::{MockRtmpConnection, MockRtmpServer};
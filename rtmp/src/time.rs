@@ -53,13 +53,28 @@
 //! assert!(time > 20);
 //! assert!(time == 50);
 //! ```
+//!
+//! When relaying a stream to a player that joined after the stream started, a
+//! `TimestampRebaser` can be used so the player sees the stream start at timestamp 0:
+//!
+//! ```
+//! use rml_rtmp::time::{RtmpTimestamp, TimestampRebaser};
+//!
+//! let rebaser = TimestampRebaser::new(RtmpTimestamp::new(5000));
+//!
+//! assert_eq!(rebaser.rebase(RtmpTimestamp::new(5000)), 0);
+//! assert_eq!(rebaser.rebase(RtmpTimestamp::new(5033)), 33);
+//! ```
 
 use std::cmp::{max, min, Ordering};
 use std::num::Wrapping;
 use std::ops::{Add, Sub};
 
-/// The representation of a RTMP timestamp
+/// The representation of a RTMP timestamp.  With the `serde` feature enabled it is serialized as
+/// its plain `u32` value.
 #[derive(Eq, PartialEq, Debug, Copy, Clone)]
+#[cfg_attr(feature = "serde", derive(::serde::Serialize, ::serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(transparent))]
 pub struct RtmpTimestamp {
     /// The time (as milliseconds from an unknown epoch) being represented by the timestamp
     pub value: u32,
@@ -79,6 +94,35 @@ impl RtmpTimestamp {
     }
 }
 
+/// Shifts timestamps so that a stream appears to start at timestamp 0, such as when a player
+/// joins a stream that has already been running for some time.
+#[derive(Eq, PartialEq, Debug, Copy, Clone)]
+pub struct TimestampRebaser {
+    /// The timestamp that is rebased to 0
+    pub offset: RtmpTimestamp,
+}
+
+impl TimestampRebaser {
+    /// Creates a rebaser that treats the specified timestamp as the start of the stream
+    pub fn new(first_timestamp: RtmpTimestamp) -> Self {
+        TimestampRebaser {
+            offset: first_timestamp,
+        }
+    }
+
+    /// Returns the timestamp relative to the start of the stream.  The subtraction wraps around
+    /// the 32 bit boundary, so timestamps that have wrapped since the offset was captured are
+    /// still rebased correctly.  Timestamps that come before the offset (such as audio that
+    /// was interleaved slightly behind the first video frame) are rebased to 0.
+    pub fn rebase(&self, timestamp: RtmpTimestamp) -> RtmpTimestamp {
+        if timestamp < self.offset {
+            return RtmpTimestamp::new(0);
+        }
+
+        timestamp - self.offset
+    }
+}
+
 impl Add for RtmpTimestamp {
     type Output = RtmpTimestamp;
 
@@ -177,7 +221,7 @@ fn compare(value1: &u32, value2: &u32) -> Ordering {
 
 #[cfg(test)]
 mod tests {
-    use super::RtmpTimestamp;
+    use super::{RtmpTimestamp, TimestampRebaser};
 
     #[test]
     fn two_timestamps_can_be_added_together() {
@@ -293,4 +337,33 @@ mod tests {
 
         assert_eq!(time, 60);
     }
+
+    #[test]
+    fn rebaser_subtracts_first_timestamp() {
+        let rebaser = TimestampRebaser::new(RtmpTimestamp::new(5000));
+
+        assert_eq!(rebaser.rebase(RtmpTimestamp::new(5000)), 0);
+        assert_eq!(rebaser.rebase(RtmpTimestamp::new(6234)), 1234);
+    }
+
+    #[test]
+    fn rebaser_handles_timestamps_that_wrap_around() {
+        let rebaser = TimestampRebaser::new(RtmpTimestamp::new(u32::max_value() - 99));
+
+        assert_eq!(rebaser.rebase(RtmpTimestamp::new(u32::max_value())), 99);
+        assert_eq!(rebaser.rebase(RtmpTimestamp::new(0)), 100);
+        assert_eq!(rebaser.rebase(RtmpTimestamp::new(500)), 600);
+    }
+
+    #[test]
+    fn rebaser_clamps_timestamps_before_offset_to_zero() {
+        let rebaser = TimestampRebaser::new(RtmpTimestamp::new(50));
+
+        assert_eq!(rebaser.rebase(RtmpTimestamp::new(20)), 0);
+        assert_eq!(
+            rebaser.rebase(RtmpTimestamp::new(u32::max_value() - 10)),
+            0,
+            "Timestamp from before a wrap was not treated as before the offset"
+        );
+    }
 }
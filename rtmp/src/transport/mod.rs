@@ -0,0 +1,10 @@
+//! Adapters that allow RTMP traffic to be carried over transports other than raw TCP.
+//!
+//! The handshake, chunk, and session APIs in this crate only work against bytes, so any transport
+//! that can be exposed as `std::io::Read` and `std::io::Write` can be used to carry RTMP traffic.
+//! The adapters in this module perform that translation for transports that have their own
+//! framing.
+
+mod websocket;
+
+pub use self::websocket::WebSocketAdapter;
@@ -0,0 +1,196 @@
+use std::io::{self, Read, Write};
+use tungstenite::{Error, Message, WebSocket};
+
+/// Wraps a `tungstenite::WebSocket` so RTMP bytes can be sent and received as binary WebSocket
+/// messages.
+///
+/// The adapter implements `Read` and `Write`, allowing bytes read from it to be passed into a
+/// `Handshake`, `ChunkDeserializer`, or session, and any bytes those produce to be written back
+/// to it.  Each call to `write()` sends a single binary message, while incoming binary messages
+/// are buffered so they can be read in whatever sized pieces the caller requests.  Text
+/// messages are ignored, and ping, pong, and close frames are handled by `tungstenite`.  Reads
+/// return `Ok(0)` once the WebSocket has been closed.
+///
+/// Only the framing is handled by this adapter.  Accepting the WebSocket connection (including
+/// any HTTP upgrade request) must be done by the caller before the adapter is created.
+///
+/// ## Examples
+///
+/// ```no_run
+/// extern crate rml_rtmp;
+/// extern crate tungstenite;
+///
+/// use rml_rtmp::handshake::{Handshake, HandshakeProcessResult, PeerType};
+/// use rml_rtmp::transport::WebSocketAdapter;
+/// use std::io::{Read, Write};
+/// use std::net::TcpListener;
+/// use tungstenite::protocol::Role;
+/// use tungstenite::WebSocket;
+///
+/// # fn main() {
+/// let listener = TcpListener::bind("127.0.0.1:8080").unwrap();
+/// let (stream, _) = listener.accept().unwrap();
+///
+/// // The HTTP upgrade is assumed to have already been performed on this stream
+/// let socket = WebSocket::from_raw_socket(stream, Role::Server, None);
+/// let mut adapter = WebSocketAdapter::new(socket);
+///
+/// let mut handshake = Handshake::new(PeerType::Server);
+/// let mut buffer = [0_u8; 4096];
+/// loop {
+///     let bytes_read = adapter.read(&mut buffer).unwrap();
+///     match handshake.process_bytes(&buffer[..bytes_read]).unwrap() {
+///         HandshakeProcessResult::InProgress { response_bytes } => {
+///             adapter.write_all(&response_bytes).unwrap();
+///         }
+///
+///         HandshakeProcessResult::Completed { response_bytes, .. } => {
+///             adapter.write_all(&response_bytes).unwrap();
+///             break;
+///         }
+///     }
+/// }
+/// # }
+/// ```
+pub struct WebSocketAdapter<S: Read + Write> {
+    socket: WebSocket<S>,
+    buffer: Vec<u8>,
+}
+
+impl<S: Read + Write> WebSocketAdapter<S> {
+    /// Creates a new adapter around a WebSocket that has already been established
+    pub fn new(socket: WebSocket<S>) -> WebSocketAdapter<S> {
+        WebSocketAdapter {
+            socket,
+            buffer: Vec::new(),
+        }
+    }
+
+    /// Returns a reference to the wrapped WebSocket
+    pub fn get_ref(&self) -> &WebSocket<S> {
+        &self.socket
+    }
+
+    /// Returns a mutable reference to the wrapped WebSocket
+    pub fn get_mut(&mut self) -> &mut WebSocket<S> {
+        &mut self.socket
+    }
+
+    /// Consumes the adapter, returning the wrapped WebSocket.  Any bytes that were received but
+    /// not yet read are lost.
+    pub fn into_inner(self) -> WebSocket<S> {
+        self.socket
+    }
+}
+
+impl<S: Read + Write> Read for WebSocketAdapter<S> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        while self.buffer.is_empty() {
+            match self.socket.read() {
+                Ok(Message::Binary(data)) => self.buffer = data,
+                Ok(Message::Close(_)) => {
+                    // Make sure the close frame that was queued in response gets sent
+                    let _ = self.socket.flush();
+                    return Ok(0);
+                }
+
+                Ok(_) => (),
+                Err(Error::ConnectionClosed) | Err(Error::AlreadyClosed) => return Ok(0),
+                Err(error) => return Err(to_io_error(error)),
+            }
+        }
+
+        let length = buf.len().min(self.buffer.len());
+        buf[..length].copy_from_slice(&self.buffer[..length]);
+        let _ = self.buffer.drain(..length);
+        Ok(length)
+    }
+}
+
+impl<S: Read + Write> Write for WebSocketAdapter<S> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.socket
+            .send(Message::Binary(buf.to_vec()))
+            .map_err(to_io_error)?;
+
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.socket.flush().map_err(to_io_error)
+    }
+}
+
+fn to_io_error(error: Error) -> io::Error {
+    match error {
+        Error::Io(error) => error,
+        error => io::Error::other(error),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::{TcpListener, TcpStream};
+    use std::thread;
+    use tungstenite::protocol::Role;
+
+    #[test]
+    fn bytes_written_to_adapter_can_be_read_by_other_side() {
+        let (mut client, mut server) = create_adapter_pair();
+
+        client.write_all(&[1, 2, 3, 4, 5]).unwrap();
+        let mut buffer = [0_u8; 3];
+        server.read_exact(&mut buffer).unwrap();
+        assert_eq!(buffer, [1, 2, 3], "Unexpected first read");
+
+        server.read_exact(&mut buffer[..2]).unwrap();
+        assert_eq!(&buffer[..2], &[4, 5], "Unexpected second read");
+    }
+
+    #[test]
+    fn text_messages_are_ignored() {
+        let (mut client, mut server) = create_adapter_pair();
+
+        client
+            .get_mut()
+            .send(Message::Text("ignored".to_string()))
+            .unwrap();
+
+        client.write_all(&[6, 7]).unwrap();
+
+        let mut buffer = [0_u8; 2];
+        server.read_exact(&mut buffer).unwrap();
+        assert_eq!(buffer, [6, 7], "Unexpected bytes read");
+    }
+
+    #[test]
+    fn read_returns_zero_bytes_after_close() {
+        let (mut client, mut server) = create_adapter_pair();
+
+        client.get_mut().close(None).unwrap();
+        let handle = thread::spawn(move || {
+            // Keep reading so the close handshake is completed
+            let mut buffer = [0_u8; 1];
+            let _ = client.read(&mut buffer);
+        });
+
+        let mut buffer = [0_u8; 10];
+        assert_eq!(
+            server.read(&mut buffer).unwrap(),
+            0,
+            "Expected end of stream"
+        );
+        handle.join().unwrap();
+    }
+
+    fn create_adapter_pair() -> (WebSocketAdapter<TcpStream>, WebSocketAdapter<TcpStream>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let client_stream = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+        let (server_stream, _) = listener.accept().unwrap();
+
+        let client = WebSocket::from_raw_socket(client_stream, Role::Client, None);
+        let server = WebSocket::from_raw_socket(server_stream, Role::Server, None);
+        (WebSocketAdapter::new(client), WebSocketAdapter::new(server))
+    }
+}
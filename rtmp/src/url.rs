@@ -0,0 +1,246 @@
+/*!
+This module contains a parser for the urls used to address an RTMP stream, in the form of
+`rtmp://host[:port]/app/stream_key` or `rtmps://host[:port]/app/stream_key`.
+
+The rest of this crate is transport agnostic, so the parsed url only describes where to connect.
+When the `tls` feature is enabled `RtmpUrl::connect_tls()` can be used to establish the TLS
+connection that RTMPS requires.  The resulting stream can then be used for the `Handshake` and
+sessions exactly like a plain TCP stream would be.
+
+## Examples
+
+```
+use rml_rtmp::url::{RtmpScheme, RtmpUrl};
+
+let url = RtmpUrl::parse("rtmps://example.com/live/abc123").unwrap();
+assert_eq!(url.scheme, RtmpScheme::Rtmps);
+assert_eq!(url.port, 443);
+assert_eq!(url.tc_url(), "rtmps://example.com:443/live");
+```
+*/
+
+#[cfg(feature = "tls")]
+use rustls;
+#[cfg(feature = "tls")]
+use std::convert::TryFrom;
+#[cfg(feature = "tls")]
+use std::io::{self, Read, Write};
+#[cfg(feature = "tls")]
+use std::net::TcpStream;
+#[cfg(feature = "tls")]
+use std::sync::Arc;
+use thiserror::Error;
+
+/// The scheme of an RTMP url, which determines the transport the connection is made over
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RtmpScheme {
+    /// RTMP over plain TCP
+    Rtmp,
+
+    /// RTMP over a TLS encrypted TCP connection
+    Rtmps,
+}
+
+impl RtmpScheme {
+    /// The port that is connected to when the url does not specify one
+    pub fn default_port(&self) -> u16 {
+        match *self {
+            RtmpScheme::Rtmp => 1935,
+            RtmpScheme::Rtmps => 443,
+        }
+    }
+
+    /// The scheme as it appears at the start of a url
+    pub fn as_str(&self) -> &'static str {
+        match *self {
+            RtmpScheme::Rtmp => "rtmp",
+            RtmpScheme::Rtmps => "rtmps",
+        }
+    }
+}
+
+/// Errors that can occur when parsing an RTMP url
+#[derive(Debug, Error)]
+pub enum RtmpUrlParseError {
+    /// The url did not start with `rtmp://` or `rtmps://`
+    #[error("The url '{0}' does not start with rtmp:// or rtmps://")]
+    UnsupportedScheme(String),
+
+    /// The url was missing the host, application name, or stream key
+    #[error("The url '{0}' is not in the form scheme://host[:port]/app/stream_key")]
+    MissingComponent(String),
+
+    /// The port in the url could not be parsed as a number
+    #[error("The url '{0}' has an invalid port")]
+    InvalidPort(String),
+}
+
+/// Errors that can occur when establishing a TLS connection to an RTMP url
+#[cfg(feature = "tls")]
+#[derive(Debug, Error)]
+pub enum RtmpTlsConnectError {
+    /// The host of the url cannot be used as a TLS server name
+    #[error("The host '{0}' is not a valid TLS server name")]
+    InvalidServerName(String),
+
+    /// The TLS session could not be created
+    #[error("Failed to create the TLS session: {0}")]
+    Tls(#[from] rustls::Error),
+
+    /// Connecting to the server or performing the TLS handshake failed
+    #[error("Failed to establish the TLS connection: {0}")]
+    Io(#[from] io::Error),
+}
+
+/// The components of an RTMP url
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RtmpUrl {
+    /// Whether the connection is made over plain TCP or TLS
+    pub scheme: RtmpScheme,
+
+    /// The host name or IP address of the server
+    pub host: String,
+
+    /// The port of the server, which is the scheme's default port if the url did not have one
+    pub port: u16,
+
+    /// The name of the application to connect to
+    pub app: String,
+
+    /// The stream key to publish or play.  This is everything after the application name, so
+    /// it may itself contain slashes.
+    pub stream_key: String,
+}
+
+impl RtmpUrl {
+    /// Parses a url in the form of `rtmp://host[:port]/app/stream_key` or
+    /// `rtmps://host[:port]/app/stream_key`.  When no port is specified the scheme's default
+    /// port is used.
+    pub fn parse(url: &str) -> Result<RtmpUrl, RtmpUrlParseError> {
+        let (scheme, without_scheme) = if let Some(rest) = url.strip_prefix("rtmp://") {
+            (RtmpScheme::Rtmp, rest)
+        } else if let Some(rest) = url.strip_prefix("rtmps://") {
+            (RtmpScheme::Rtmps, rest)
+        } else {
+            return Err(RtmpUrlParseError::UnsupportedScheme(url.to_string()));
+        };
+
+        let missing = || RtmpUrlParseError::MissingComponent(url.to_string());
+        let mut parts = without_scheme.splitn(3, '/');
+        let authority = parts.next().filter(|x| !x.is_empty()).ok_or_else(missing)?;
+        let app = parts.next().filter(|x| !x.is_empty()).ok_or_else(missing)?;
+        let stream_key = parts.next().filter(|x| !x.is_empty()).ok_or_else(missing)?;
+
+        let (host, port) = match authority.rfind(':') {
+            Some(index) => {
+                let port = authority[index + 1..]
+                    .parse()
+                    .map_err(|_| RtmpUrlParseError::InvalidPort(url.to_string()))?;
+
+                (&authority[..index], port)
+            }
+
+            None => (authority, scheme.default_port()),
+        };
+
+        if host.is_empty() {
+            return Err(missing());
+        }
+
+        Ok(RtmpUrl {
+            scheme,
+            host: host.to_string(),
+            port,
+            app: app.to_string(),
+            stream_key: stream_key.to_string(),
+        })
+    }
+
+    /// The url of the application being connected to, as is sent in the `tcUrl` property of
+    /// the connect request
+    pub fn tc_url(&self) -> String {
+        format!(
+            "{}://{}:{}/{}",
+            self.scheme.as_str(),
+            self.host,
+            self.port,
+            self.app
+        )
+    }
+
+    /// Connects to the url's host and port and performs a TLS handshake with the server, using
+    /// the url's host as the server name.  The returned stream is ready for the RTMP handshake
+    /// to begin.
+    #[cfg(feature = "tls")]
+    pub fn connect_tls(
+        &self,
+        config: rustls::ClientConfig,
+    ) -> Result<impl Read + Write, RtmpTlsConnectError> {
+        let server_name = rustls::pki_types::ServerName::try_from(self.host.clone())
+            .map_err(|_| RtmpTlsConnectError::InvalidServerName(self.host.clone()))?;
+
+        let mut connection = rustls::ClientConnection::new(Arc::new(config), server_name)?;
+        let mut socket = TcpStream::connect((self.host.as_str(), self.port))?;
+        while connection.is_handshaking() {
+            connection.complete_io(&mut socket)?;
+        }
+
+        Ok(rustls::StreamOwned::new(connection, socket))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn can_parse_rtmp_url_with_default_port() {
+        let url = RtmpUrl::parse("rtmp://localhost/live/abc").unwrap();
+
+        assert_eq!(url.scheme, RtmpScheme::Rtmp, "Unexpected scheme");
+        assert_eq!(url.host, "localhost", "Unexpected host");
+        assert_eq!(url.port, 1935, "Unexpected port");
+        assert_eq!(url.app, "live", "Unexpected app");
+        assert_eq!(url.stream_key, "abc", "Unexpected stream key");
+    }
+
+    #[test]
+    fn can_parse_rtmps_url_with_default_port() {
+        let url = RtmpUrl::parse("rtmps://example.com/live/abc").unwrap();
+
+        assert_eq!(url.scheme, RtmpScheme::Rtmps, "Unexpected scheme");
+        assert_eq!(url.port, 443, "Unexpected port");
+        assert_eq!(
+            url.tc_url(),
+            "rtmps://example.com:443/live",
+            "Unexpected tc url"
+        );
+    }
+
+    #[test]
+    fn can_parse_url_with_explicit_port() {
+        let url = RtmpUrl::parse("rtmps://example.com:8443/live/abc/def").unwrap();
+
+        assert_eq!(url.host, "example.com", "Unexpected host");
+        assert_eq!(url.port, 8443, "Unexpected port");
+        assert_eq!(url.stream_key, "abc/def", "Unexpected stream key");
+    }
+
+    #[test]
+    fn invalid_urls_are_rejected() {
+        match RtmpUrl::parse("http://example.com/live/abc") {
+            Err(RtmpUrlParseError::UnsupportedScheme(_)) => (),
+            x => panic!("Expected unsupported scheme error, got {:?}", x),
+        }
+
+        match RtmpUrl::parse("rtmp://example.com/live") {
+            Err(RtmpUrlParseError::MissingComponent(_)) => (),
+            x => panic!("Expected missing component error, got {:?}", x),
+        }
+
+        match RtmpUrl::parse("rtmp://example.com:abc/live/key") {
+            Err(RtmpUrlParseError::InvalidPort(_)) => (),
+            x => panic!("Expected invalid port error, got {:?}", x),
+        }
+    }
+}
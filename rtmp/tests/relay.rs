@@ -0,0 +1,279 @@
+extern crate bytes;
+extern crate rml_rtmp;
+
+use bytes::Bytes;
+use rml_rtmp::chunk_io::DropPolicy;
+use rml_rtmp::relay::{RelayResult, TimestampNormalizingRelay};
+use rml_rtmp::sessions::{
+    ClientSession, ClientSessionConfig, ClientSessionEvent, ClientSessionResult,
+    PublishRequestType, ServerSession, ServerSessionConfig, ServerSessionEvent,
+    ServerSessionResult,
+};
+use rml_rtmp::time::RtmpTimestamp;
+use std::time::{Duration, Instant};
+
+/// Moves bytes between a publisher, the relay, and the server the relay is publishing to
+struct Harness {
+    publisher: ClientSession,
+    publisher_events: Vec<ClientSessionEvent>,
+    relay: TimestampNormalizingRelay,
+    destination: ServerSession,
+    destination_events: Vec<ServerSessionEvent>,
+    send_times: Vec<Instant>,
+}
+
+impl Harness {
+    fn new() -> (Harness, Vec<u8>) {
+        let (publisher, publisher_results) =
+            ClientSession::new(ClientSessionConfig::new()).unwrap();
+        let (source, source_results) =
+            ServerSession::new_permissive(ServerSessionConfig::new()).unwrap();
+        let (relay_client, relay_client_results) =
+            ClientSession::new(ClientSessionConfig::new()).unwrap();
+        let (destination, destination_results) =
+            ServerSession::new_permissive(ServerSessionConfig::new()).unwrap();
+
+        let mut harness = Harness {
+            publisher,
+            publisher_events: Vec::new(),
+            relay: TimestampNormalizingRelay::new(source, relay_client),
+            destination,
+            destination_events: Vec::new(),
+            send_times: Vec::new(),
+        };
+
+        let now = Instant::now();
+        harness.feed_publisher(server_bytes(source_results));
+        harness.feed_relay_destination(server_bytes(destination_results), now);
+        harness.feed_destination(client_bytes(relay_client_results), now);
+
+        (harness, client_bytes(publisher_results))
+    }
+
+    fn feed_publisher(&mut self, bytes: Vec<u8>) -> Vec<u8> {
+        let mut output = Vec::new();
+        for result in self.publisher.handle_input(&bytes).unwrap() {
+            match result {
+                ClientSessionResult::OutboundResponse(packet) => output.extend(packet.bytes),
+                ClientSessionResult::RaisedEvent(event) => self.publisher_events.push(event),
+                _ => (),
+            }
+        }
+
+        output
+    }
+
+    fn feed_relay_source(&mut self, bytes: Vec<u8>, now: Instant) -> (Vec<u8>, Vec<u8>) {
+        let results = self.relay.handle_source_input(&bytes, now).unwrap();
+        self.split_relay_results(results)
+    }
+
+    fn feed_relay_destination(&mut self, bytes: Vec<u8>, now: Instant) -> Vec<u8> {
+        let results = self.relay.handle_destination_input(&bytes, now).unwrap();
+        let (source_bytes, destination_bytes) = self.split_relay_results(results);
+        assert!(
+            source_bytes.is_empty(),
+            "Destination input produced source bytes"
+        );
+
+        destination_bytes
+    }
+
+    fn feed_destination(&mut self, bytes: Vec<u8>, now: Instant) {
+        let mut bytes = bytes;
+        while !bytes.is_empty() {
+            let mut response = Vec::new();
+            for result in self.destination.handle_input(&bytes).unwrap() {
+                match result {
+                    ServerSessionResult::OutboundResponse(packet) => response.extend(packet.bytes),
+                    ServerSessionResult::RaisedEvent(event) => self.destination_events.push(event),
+                    _ => (),
+                }
+            }
+
+            bytes = self.feed_relay_destination(response, now);
+        }
+    }
+
+    fn split_relay_results(&mut self, results: Vec<RelayResult>) -> (Vec<u8>, Vec<u8>) {
+        let mut source_bytes = Vec::new();
+        let mut destination_bytes = Vec::new();
+        for result in results {
+            match result {
+                RelayResult::SourcePacket(packet) => source_bytes.extend(packet.bytes),
+                RelayResult::DestinationPacket { packet, send_at } => {
+                    self.send_times.push(send_at);
+                    destination_bytes.extend(packet.bytes);
+                }
+                _ => (),
+            }
+        }
+
+        (source_bytes, destination_bytes)
+    }
+
+    /// Sends bytes from the publisher through the relay until every session has responded
+    fn publish(&mut self, bytes: Vec<u8>, now: Instant) {
+        let mut bytes = bytes;
+        while !bytes.is_empty() {
+            let (source_bytes, destination_bytes) = self.feed_relay_source(bytes, now);
+            self.feed_destination(destination_bytes, now);
+            bytes = self.feed_publisher(source_bytes);
+        }
+    }
+}
+
+fn server_bytes(results: Vec<ServerSessionResult>) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    for result in results {
+        if let ServerSessionResult::OutboundResponse(packet) = result {
+            bytes.extend(packet.bytes);
+        }
+    }
+
+    bytes
+}
+
+fn client_bytes(results: Vec<ClientSessionResult>) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    for result in results {
+        if let ClientSessionResult::OutboundResponse(packet) = result {
+            bytes.extend(packet.bytes);
+        }
+    }
+
+    bytes
+}
+
+fn start_relay() -> Harness {
+    let now = Instant::now();
+    let (mut harness, publisher_bytes) = Harness::new();
+    harness.publish(publisher_bytes, now);
+
+    let results = harness
+        .relay
+        .destination_session()
+        .request_connection("live".to_string())
+        .unwrap();
+    harness.feed_destination(client_bytes(results), now);
+
+    let result = harness
+        .relay
+        .destination_session()
+        .request_publishing("relayed".to_string(), PublishRequestType::Live)
+        .unwrap();
+    harness.feed_destination(client_bytes(vec![result]), now);
+
+    let mut results = harness
+        .publisher
+        .request_connection("live".to_string())
+        .unwrap();
+    harness.publish(client_bytes(results), now);
+
+    results = vec![harness
+        .publisher
+        .request_publishing("source".to_string(), PublishRequestType::Live)
+        .unwrap()];
+    harness.publish(client_bytes(results), now);
+
+    let publishing = harness
+        .publisher_events
+        .iter()
+        .any(|event| matches!(event, ClientSessionEvent::PublishRequestAccepted));
+
+    assert!(publishing, "Publisher's publish request was not accepted");
+    harness.send_times.clear();
+    harness
+}
+
+/// Publishes 120 inter-frames from a source whose timestamps started well before the relay, all
+/// received by the relay at the same instant
+fn publish_one_second_at_120fps(harness: &mut Harness, now: Instant) {
+    for frame in 0..120_u32 {
+        let timestamp = RtmpTimestamp::new(10_000 + frame * 1000 / 120);
+        let result = harness
+            .publisher
+            .publish_video_data(
+                Bytes::from(vec![0x27_u8, 1, 0, 0, 0]),
+                timestamp,
+                DropPolicy::MustDeliver,
+            )
+            .unwrap();
+
+        harness.publish(client_bytes(vec![result]), now);
+    }
+}
+
+#[test]
+fn relayed_timestamps_start_at_zero_and_increase_monotonically() {
+    let mut harness = start_relay();
+    let started_at = Instant::now();
+
+    publish_one_second_at_120fps(&mut harness, started_at);
+
+    let timestamps = harness
+        .destination_events
+        .iter()
+        .filter_map(|event| match event {
+            ServerSessionEvent::VideoDataReceived { timestamp, .. } => Some(timestamp.value),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+
+    assert_eq!(timestamps.len(), 120, "Unexpected number of relayed frames");
+    assert_eq!(timestamps[0], 0, "First relayed timestamp was not zero");
+    for pair in timestamps.windows(2) {
+        let spacing = pair[1] - pair[0];
+        assert!(
+            spacing == 8 || spacing == 9,
+            "Expected ~8ms between frames, but {} was followed by {}",
+            pair[0],
+            pair[1]
+        );
+    }
+}
+
+#[test]
+fn sources_faster_than_real_time_are_throttled() {
+    let mut harness = start_relay();
+    let started_at = Instant::now();
+
+    // The whole second of frames arrives at once, so must be spread over at least 1 / 1.5 seconds
+    publish_one_second_at_120fps(&mut harness, started_at);
+
+    let send_times = &harness.send_times;
+    assert!(
+        !send_times.is_empty(),
+        "No packets were sent to the destination"
+    );
+    for pair in send_times.windows(2) {
+        assert!(pair[0] <= pair[1], "Packets were scheduled out of order");
+    }
+
+    let last_frame_position = Duration::from_millis(119 * 1000 / 120);
+    let expected_last_send = started_at + last_frame_position.div_f64(1.5);
+    assert_eq!(*send_times.last().unwrap(), expected_last_send);
+}
+
+#[test]
+fn invalid_max_speeds_disable_throttling() {
+    for max_speed in [0.0, -1.5, f64::NAN, f64::INFINITY] {
+        let mut harness = start_relay();
+        harness.relay.set_max_speed(Some(max_speed));
+        let started_at = Instant::now();
+
+        publish_one_second_at_120fps(&mut harness, started_at);
+
+        let send_times = &harness.send_times;
+        assert!(
+            !send_times.is_empty(),
+            "No packets were sent to the destination with a max speed of {}",
+            max_speed
+        );
+        assert!(
+            send_times.iter().all(|sent_at| *sent_at == started_at),
+            "Packets were delayed with a max speed of {}",
+            max_speed
+        );
+    }
+}
@@ -0,0 +1,80 @@
+#![cfg(feature = "tls")]
+
+extern crate rcgen;
+extern crate rml_rtmp;
+extern crate rustls;
+
+use rml_rtmp::handshake::{Handshake, HandshakeProcessResult, PeerType};
+use rml_rtmp::url::RtmpUrl;
+use rustls::pki_types::{PrivateKeyDer, PrivatePkcs8KeyDer};
+use std::io::{Read, Write};
+use std::net::TcpListener;
+use std::sync::Arc;
+use std::thread;
+
+#[test]
+fn can_perform_rtmp_handshake_over_tls() {
+    let certified_key = rcgen::generate_simple_self_signed(vec!["127.0.0.1".to_string()]).unwrap();
+    let cert_der = certified_key.cert.der().clone();
+    let key_der = PrivateKeyDer::Pkcs8(PrivatePkcs8KeyDer::from(
+        certified_key.key_pair.serialize_der(),
+    ));
+
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let port = listener.local_addr().unwrap().port();
+
+    let server_cert = cert_der.clone();
+    let server = thread::spawn(move || {
+        let config = rustls::ServerConfig::builder()
+            .with_no_client_auth()
+            .with_single_cert(vec![server_cert], key_der)
+            .unwrap();
+
+        let (socket, _) = listener.accept().unwrap();
+        let connection = rustls::ServerConnection::new(Arc::new(config)).unwrap();
+        let mut stream = rustls::StreamOwned::new(connection, socket);
+
+        perform_handshake(&mut stream, Handshake::new(PeerType::Server));
+    });
+
+    let mut roots = rustls::RootCertStore::empty();
+    roots.add(cert_der).unwrap();
+    let config = rustls::ClientConfig::builder()
+        .with_root_certificates(roots)
+        .with_no_client_auth();
+
+    let url = RtmpUrl::parse(&format!("rtmps://127.0.0.1:{}/live/key", port)).unwrap();
+    let mut stream = url.connect_tls(config).unwrap();
+
+    let mut handshake = Handshake::new(PeerType::Client);
+    let p0_and_p1 = handshake.generate_outbound_p0_and_p1().unwrap();
+    stream.write_all(&p0_and_p1).unwrap();
+    stream.flush().unwrap();
+
+    perform_handshake(&mut stream, handshake);
+    server.join().unwrap();
+}
+
+fn perform_handshake<S: Read + Write>(stream: &mut S, mut handshake: Handshake) {
+    let mut buffer = [0_u8; 4096];
+    loop {
+        let bytes_read = stream.read(&mut buffer).unwrap();
+        assert!(
+            bytes_read > 0,
+            "Connection closed before handshake completed"
+        );
+
+        match handshake.process_bytes(&buffer[..bytes_read]).unwrap() {
+            HandshakeProcessResult::InProgress { response_bytes } => {
+                stream.write_all(&response_bytes).unwrap();
+                stream.flush().unwrap();
+            }
+
+            HandshakeProcessResult::Completed { response_bytes, .. } => {
+                stream.write_all(&response_bytes).unwrap();
+                stream.flush().unwrap();
+                return;
+            }
+        }
+    }
+}
@@ -0,0 +1,91 @@
+use rml_rtmp::chunk_io::ChunkDeserializer;
+use rml_rtmp::diagnostics::CaptureReader;
+use rml_rtmp::messages::{MessagePayload, RtmpMessage};
+use rml_rtmp::sessions::Direction;
+use std::path::Path;
+
+use {display_message, LoggedMessage};
+
+// Both peers send 3 handshake packets (e.g. C0, C1, and C2) before any chunks
+const HANDSHAKE_SIZE: usize = 1 + 1536 + 1536;
+
+/// The RTMP chunks sent in one direction of a capture
+struct CapturedStream {
+    deserializer: ChunkDeserializer,
+    handshake_bytes_remaining: usize,
+    current_index: usize,
+    last_message_end_index: usize,
+}
+
+impl CapturedStream {
+    fn new() -> CapturedStream {
+        CapturedStream {
+            deserializer: ChunkDeserializer::new(),
+            handshake_bytes_remaining: HANDSHAKE_SIZE,
+            current_index: 0,
+            last_message_end_index: 0,
+        }
+    }
+
+    /// Returns all messages completed by the captured bytes.  Bytes are passed to the
+    /// deserializer one at a time so each message's starting index is known.
+    fn add_bytes(&mut self, bytes: &[u8]) -> Vec<LoggedMessage> {
+        let handshake_bytes = self.handshake_bytes_remaining.min(bytes.len());
+        self.handshake_bytes_remaining -= handshake_bytes;
+
+        let mut messages = Vec::new();
+        for byte in &bytes[handshake_bytes..] {
+            self.current_index += 1;
+            let mut input = &[*byte][..];
+            while let Some(payload) = self.deserializer.get_next_message(input).unwrap() {
+                input = &[];
+
+                // Chunk size changes must be applied immediately, otherwise the rest of the
+                // capture will not be able to be read correctly
+                if let Ok(RtmpMessage::SetChunkSize { size }) = payload.to_rtmp_message() {
+                    self.deserializer.set_max_chunk_size(size as usize).unwrap();
+                }
+
+                messages.push(LoggedMessage {
+                    payload,
+                    start_index: self.last_message_end_index,
+                });
+
+                self.last_message_end_index = self.current_index;
+            }
+        }
+
+        messages
+    }
+}
+
+/// Displays the messages of a capture made with `rml_rtmp::diagnostics::CapturingTransport`, in
+/// the order the bytes containing them were read or written.  The capture is expected to
+/// include the handshake, which is skipped.  Indexes are counted from the end of the handshake.
+pub fn read_capture(file_name: String) -> Vec<MessagePayload> {
+    println!("Reading capture file: {}", file_name);
+    println!();
+
+    let reader = CaptureReader::open(Path::new(&file_name)).unwrap();
+    let mut inbound_stream = CapturedStream::new();
+    let mut outbound_stream = CapturedStream::new();
+    let mut message_number = 1;
+    let mut payloads = Vec::new();
+    for record in reader {
+        let record = record.unwrap();
+        let (prefix, stream) = match record.direction {
+            Direction::Inbound => ("In  ", &mut inbound_stream),
+            Direction::Outbound => ("Out ", &mut outbound_stream),
+        };
+
+        for message in stream.add_bytes(&record.data) {
+            println!("Captured at: {:?}", record.timestamp);
+            payloads.push(message.payload.clone());
+            display_message(message_number, prefix, message);
+            message_number += 1;
+        }
+    }
+
+    println!("Finished reading capture file!");
+    payloads
+}
@@ -0,0 +1,132 @@
+use rml_rtmp::chunk_io::ChunkDeserializer;
+use rml_rtmp::handshake::{Handshake, HandshakeProcessResult, PeerType};
+use rml_rtmp::messages::RtmpMessage;
+use rml_rtmp::sessions::{ClientSession, ClientSessionConfig, ClientSessionResult};
+use rml_rtmp::sessions::{Direction, MessageTrace};
+use std::io::{Read, Write};
+use std::net::TcpStream;
+
+/// Connects to a running RTMP server as a client, requests a connection to the specified
+/// application, and prints a trace of every message exchanged until the server disconnects.
+pub fn trace_live_server(address: &str, app_name: &str) {
+    println!("Connecting to {}", address);
+    let mut stream = TcpStream::connect(address).unwrap();
+    let remaining_bytes = perform_handshake(&mut stream);
+    println!(
+        "Handshake completed, requesting connection to app '{}'",
+        app_name
+    );
+    println!();
+
+    let mut tracer = LiveTracer {
+        stream,
+        session: ClientSession::new(ClientSessionConfig::new()).unwrap().0,
+        inbound_deserializer: ChunkDeserializer::new(),
+        outbound_deserializer: ChunkDeserializer::new(),
+    };
+
+    let results = tracer
+        .session
+        .request_connection(app_name.to_string())
+        .unwrap();
+    tracer.handle_results(results);
+    tracer.handle_input(&remaining_bytes);
+
+    let mut buffer = [0_u8; 4096];
+    loop {
+        let bytes_read = tracer.stream.read(&mut buffer).unwrap();
+        if bytes_read == 0 {
+            println!("Server closed the connection");
+            return;
+        }
+
+        tracer.handle_input(&buffer[..bytes_read]);
+    }
+}
+
+struct LiveTracer {
+    stream: TcpStream,
+    session: ClientSession,
+    inbound_deserializer: ChunkDeserializer,
+    outbound_deserializer: ChunkDeserializer,
+}
+
+impl LiveTracer {
+    fn handle_input(&mut self, bytes: &[u8]) {
+        print_traces(&mut self.inbound_deserializer, Direction::Inbound, bytes);
+
+        let results = self.session.handle_input(bytes).unwrap();
+        self.handle_results(results);
+    }
+
+    fn handle_results(&mut self, results: Vec<ClientSessionResult>) {
+        for result in results {
+            match result {
+                ClientSessionResult::OutboundResponse(packet) => {
+                    print_traces(
+                        &mut self.outbound_deserializer,
+                        Direction::Outbound,
+                        &packet.bytes,
+                    );
+                    self.stream.write_all(&packet.bytes).unwrap();
+                }
+
+                ClientSessionResult::RaisedEvent(event) => println!("Event: {:?}", event),
+                ClientSessionResult::UnhandleableMessageReceived(_) => (),
+            }
+        }
+    }
+}
+
+fn perform_handshake(stream: &mut TcpStream) -> Vec<u8> {
+    let mut handshake = Handshake::new(PeerType::Client);
+    let c0_and_c1 = handshake.generate_outbound_p0_and_p1().unwrap();
+    stream.write_all(&c0_and_c1).unwrap();
+
+    let mut buffer = [0_u8; 4096];
+    loop {
+        let bytes_read = stream.read(&mut buffer).unwrap();
+        if bytes_read == 0 {
+            panic!("Server closed the connection during the handshake");
+        }
+
+        match handshake.process_bytes(&buffer[..bytes_read]).unwrap() {
+            HandshakeProcessResult::InProgress { response_bytes } => {
+                stream.write_all(&response_bytes).unwrap();
+            }
+
+            HandshakeProcessResult::Completed {
+                response_bytes,
+                remaining_bytes,
+            } => {
+                stream.write_all(&response_bytes).unwrap();
+                return remaining_bytes;
+            }
+        }
+    }
+}
+
+/// Prints a trace of each message in the bytes.  A separate deserializer is used for each
+/// direction, since the chunk headers of each direction are compressed independently.
+fn print_traces(deserializer: &mut ChunkDeserializer, direction: Direction, bytes: &[u8]) {
+    let mut bytes_to_process = bytes;
+    while let Some(payload) = deserializer.get_next_message(bytes_to_process).unwrap() {
+        bytes_to_process = &[];
+
+        // Chunk size changes must be applied immediately for the following messages to be read
+        if let Ok(RtmpMessage::SetChunkSize { size }) = payload.to_rtmp_message() {
+            deserializer.set_max_chunk_size(size as usize).unwrap();
+        }
+
+        let trace = MessageTrace::new(direction, &payload, payload.data.len());
+        let prefix = match trace.direction {
+            Direction::Inbound => "S→C",
+            Direction::Outbound => "C→S",
+        };
+
+        println!(
+            "{} {}   Timestamp: {}   Stream_Id: {}   Bytes: {}",
+            prefix, trace.message_type, trace.timestamp.value, trace.stream_id, trace.byte_count
+        );
+    }
+}
@@ -1,15 +1,103 @@
 extern crate rml_rtmp;
 
+mod capture_replay;
+mod live_server;
+
 use rml_rtmp::chunk_io::ChunkDeserializer;
-use rml_rtmp::messages::RtmpMessage;
+use rml_rtmp::flv::payloads_to_flv_bytes;
+use rml_rtmp::messages::{MessagePayload, RtmpMessage};
+use rml_rtmp::rml_amf0::Amf0Value;
 use std::env;
 use std::fs::File;
-use std::io::Read;
+use std::io::{Read, Write};
+
+/// Reads RTMP messages out of a log file containing a single direction of an RTMP stream
+struct LogReader {
+    file: File,
+    deserializer: ChunkDeserializer,
+    current_index: usize,
+    last_message_end_index: usize,
+}
+
+/// A message read from a log file, along with the byte index it started at
+struct LoggedMessage {
+    payload: MessagePayload,
+    start_index: usize,
+}
+
+enum Direction {
+    ClientToServer,
+    ServerToClient,
+}
+
+impl LogReader {
+    fn new(file_name: &str) -> LogReader {
+        LogReader {
+            file: File::open(file_name).unwrap(),
+            deserializer: ChunkDeserializer::new(),
+            current_index: 0,
+            last_message_end_index: 0,
+        }
+    }
+
+    /// Reads the file until the next complete message is found.  Returns `None` once the end
+    /// of the file has been reached.
+    fn next_message(&mut self) -> Option<LoggedMessage> {
+        // only read one byte at a time to get a byte index for each message
+        let mut buffer = [0; 1];
+        loop {
+            // Check for any messages already buffered by the deserializer before reading more
+            let payload = match self.deserializer.get_next_message(&[0_u8; 0]).unwrap() {
+                Some(payload) => payload,
+                None => {
+                    let bytes_read = self.file.read(&mut buffer).unwrap();
+                    if bytes_read == 0 {
+                        return None;
+                    }
+
+                    self.current_index += 1;
+                    match self
+                        .deserializer
+                        .get_next_message(&buffer[..bytes_read])
+                        .unwrap()
+                    {
+                        Some(payload) => payload,
+                        None => continue,
+                    }
+                }
+            };
+
+            // Chunk size changes must be applied immediately, otherwise the rest of the file
+            // will not be able to be read correctly
+            if let Ok(RtmpMessage::SetChunkSize { size }) = payload.to_rtmp_message() {
+                self.deserializer.set_max_chunk_size(size as usize).unwrap();
+            }
+
+            let start_index = self.last_message_end_index;
+            self.last_message_end_index = self.current_index;
+
+            return Some(LoggedMessage {
+                payload,
+                start_index,
+            });
+        }
+    }
+}
 
 fn main() {
     println!("RTMP Log reader");
-    println!("This reads raw binary logged from a single direction in an RTMP stream,");
-    println!("usually logged via one of the Rust Media Libraries example applications");
+    println!("This reads raw binary logged from an RTMP stream, usually logged via one");
+    println!("of the Rust Media Libraries example applications.");
+    println!();
+    println!("Usage:");
+    println!("  rtmp-log-reader <file>                             (single direction)");
+    println!("  rtmp-log-reader --client <file> --server <file>    (both directions)");
+    println!("  rtmp-log-reader --live-server <host:port>          (trace a running server)");
+    println!("  rtmp-log-reader --load-capture <file>              (replay a connection capture)");
+    println!();
+    println!("Options:");
+    println!("  --output-flv <file>    Also writes all audio, video, and metadata to an flv file");
+    println!("  --app <name>           The app to connect to with --live-server (default: live)");
     println!();
 
     let args: Vec<String> = env::args().collect();
@@ -18,132 +106,270 @@ fn main() {
         return;
     }
 
-    println!("Reading file: {}", args[1]);
+    let mut single_file = None;
+    let mut client_file = None;
+    let mut server_file = None;
+    let mut flv_file = None;
+    let mut live_server = None;
+    let mut capture_file = None;
+    let mut app_name = "live".to_string();
+    let mut index = 1;
+    while index < args.len() {
+        match (args[index].as_str(), args.get(index + 1)) {
+            ("--client", Some(file)) => client_file = Some(file.clone()),
+            ("--server", Some(file)) => server_file = Some(file.clone()),
+            ("--output-flv", Some(file)) => flv_file = Some(file.clone()),
+            ("--live-server", Some(address)) => live_server = Some(address.clone()),
+            ("--load-capture", Some(file)) => capture_file = Some(file.clone()),
+            ("--app", Some(app)) => app_name = app.clone(),
+            (arg, _) if !arg.starts_with("--") && single_file.is_none() => {
+                single_file = Some(arg.to_string());
+                index += 1;
+                continue;
+            }
+
+            (arg, _) => {
+                println!("Invalid argument: {}", arg);
+                return;
+            }
+        }
+
+        index += 2;
+    }
+
+    if let Some(address) = live_server {
+        live_server::trace_live_server(&address, &app_name);
+        return;
+    }
+
+    let payloads = match (capture_file, single_file, client_file, server_file) {
+        (Some(file), None, None, None) => capture_replay::read_capture(file),
+        (Some(_), _, _, _) => {
+            println!("A capture file cannot be combined with other log files");
+            return;
+        }
+
+        (None, Some(file), None, None) => read_single_direction(file),
+        (None, None, Some(client_file), Some(server_file)) => {
+            read_both_directions(client_file, server_file)
+        }
+
+        (None, Some(_), _, _) => {
+            println!("A single file cannot be combined with --client and --server files");
+            return;
+        }
+
+        _ => {
+            println!("Both --client and --server files must be specified");
+            return;
+        }
+    };
+
+    if let Some(flv_file) = flv_file {
+        let bytes = payloads_to_flv_bytes(payloads);
+        let mut file = File::create(&flv_file).unwrap();
+        file.write_all(&bytes).unwrap();
+        println!("Wrote flv file: {}", flv_file);
+    }
+}
+
+fn read_single_direction(file_name: String) -> Vec<MessagePayload> {
+    println!("Reading file: {}", file_name);
     println!();
 
-    let file_name = args[1].clone();
-    let mut file = File::open(file_name).unwrap();
-    let mut deserializer = ChunkDeserializer::new();
+    let mut payloads = Vec::new();
+    let mut reader = LogReader::new(&file_name);
     let mut message_number = 1;
+    while let Some(message) = reader.next_message() {
+        payloads.push(message.payload.clone());
+        display_message(message_number, "", message);
+        message_number += 1;
+    }
 
-    // only read one byte at a time to get a byte index for each message
-    let mut buffer = [0; 1];
-    let mut current_index = 0;
-    let mut last_message_end_index = 0;
+    println!("Finished reading log file!");
+    payloads
+}
+
+/// Reads the logs of both sides of a connection, interleaving the messages from each side.
+///
+/// Since the logs contain no record of when bytes were received, messages are merged by their
+/// RTMP timestamps, with the client's message being displayed first when timestamps are equal.
+/// This is only a heuristic, as each peer has its own epoch, but it matches the real order well
+/// enough for the request/response flows that are usually being debugged.
+fn read_both_directions(client_file: String, server_file: String) -> Vec<MessagePayload> {
+    println!("Reading client to server file: {}", client_file);
+    println!("Reading server to client file: {}", server_file);
+    println!();
+
+    let mut client_reader = LogReader::new(&client_file);
+    let mut server_reader = LogReader::new(&server_file);
+    let mut next_client_message = client_reader.next_message();
+    let mut next_server_message = server_reader.next_message();
+    let mut message_number = 1;
+    let mut payloads = Vec::new();
 
     loop {
-        current_index += 1;
-        let bytes_read = file.read(&mut buffer).unwrap();
-        if bytes_read == 0 {
-            println!("Finished reading log file!");
-            return;
-        }
+        let direction = match (&next_client_message, &next_server_message) {
+            (None, None) => break,
+            (Some(_), None) => Direction::ClientToServer,
+            (None, Some(_)) => Direction::ServerToClient,
+            (Some(client), Some(server)) => {
+                if server.payload.timestamp < client.payload.timestamp {
+                    Direction::ServerToClient
+                } else {
+                    Direction::ClientToServer
+                }
+            }
+        };
 
-        let mut has_read_one_payload = false;
-        loop {
-            let bytes = if has_read_one_payload {
-                &[0_u8; 0]
-            } else {
-                &buffer[..bytes_read]
-            };
+        let message = match direction {
+            Direction::ClientToServer => {
+                let message = next_client_message.take().unwrap();
+                next_client_message = client_reader.next_message();
+                message
+            }
 
-            let payload = match deserializer.get_next_message(bytes).unwrap() {
-                Some(payload) => payload,
-                None => break,
-            };
+            Direction::ServerToClient => {
+                let message = next_server_message.take().unwrap();
+                next_server_message = server_reader.next_message();
+                message
+            }
+        };
 
-            println!(
-                "Message: {}   Timestamp: {}   Type: {}    Stream_Id: {}   index: {} ({:x})",
-                message_number,
-                payload.timestamp.value,
-                payload.type_id,
-                payload.message_stream_id,
-                last_message_end_index,
-                last_message_end_index
-            );
-
-            let message = payload.to_rtmp_message().unwrap();
-            match message {
-                RtmpMessage::Unknown {type_id, data}
-                    => {
-                    print!("Unknown {{ type_id: {}, data: ", type_id);
-                    for x in 0..data.len() {
-                        if x > 100 {
-                            print!(".. ({}) ", data.len());
-                            break;
-                        }
-
-                        print!("{:02x}", data[x]);
-                    }
-                    println!("}}");
-                },
+        payloads.push(message.payload.clone());
+        let prefix = match direction {
+            Direction::ClientToServer => "C→S ",
+            Direction::ServerToClient => "S→C ",
+        };
+
+        display_message(message_number, prefix, message);
+        message_number += 1;
+    }
+
+    println!("Finished reading log files!");
+    payloads
+}
 
-                RtmpMessage::Abort {stream_id}
-                    => println!("Abort {{ stream_id: {} }}", stream_id),
+/// Prints the message, with every line starting with the prefix (such as the direction the
+/// message was sent in)
+fn display_message(message_number: u32, prefix: &str, message: LoggedMessage) {
+    let payload = message.payload;
 
-                RtmpMessage::Acknowledgement { sequence_number }
-                    => println!("Acknowledgement {{ sequence_number: {} }}", sequence_number),
+    println!(
+        "{}Message: {}   Timestamp: {}   Type: {}    Stream_Id: {}   index: {} ({:x})",
+        prefix,
+        message_number,
+        payload.timestamp.value,
+        payload.type_id,
+        payload.message_stream_id,
+        message.start_index,
+        message.start_index
+    );
 
-                RtmpMessage::Amf0Command { command_name, transaction_id, command_object, additional_arguments }
-                    => println!("Amf0Command {{ command_name: {}, transaction_id: {}, command_object: {:?}, additional_arguments: {:?} }}",
-                               command_name, transaction_id, command_object, additional_arguments),
+    let message = payload.to_rtmp_message().unwrap();
+    print!("{}", prefix);
+    match message {
+        RtmpMessage::Unknown { type_id, data } => {
+            print!("Unknown {{ type_id: {}, data: ", type_id);
+            print_data(&data);
+            println!("}}");
+        }
 
-                RtmpMessage::Amf0Data { values }
-                    => println!("RtmpMessage::Amf0Data {{ values: {:?} }}", values),
+        RtmpMessage::Abort { stream_id } => println!("Abort {{ stream_id: {} }}", stream_id),
 
-                RtmpMessage::AudioData { data }
-                    => {
-                    print!("AudioData: {{ data: ");
-                    for x in 0..data.len() {
-                        if x > 100 {
-                            print!(".. ({}) ", data.len());
-                            break;
-                        }
+        RtmpMessage::Acknowledgement { sequence_number } => println!(
+            "Acknowledgement {{ sequence_number: {} }}",
+            sequence_number
+        ),
 
-                        print!("{:02x}", data[x]);
-                    }
-                    println!("}}", )
-                },
-
-                RtmpMessage::SetChunkSize { size }
-                    => {
-                    deserializer.set_max_chunk_size(size as usize).unwrap();
-                    println!("SetChunkSize {{ size: {} }}", size)
-                },
-
-                RtmpMessage::SetPeerBandwidth { size, limit_type }
-                    => println!("SetPeerBandwidth {{ size: {}, limit_type: {:?} }}", size, limit_type),
-
-                RtmpMessage::UserControl { event_type, stream_id, buffer_length, timestamp }
-                    => println!("UserControl {{ event_type: {:?}, stream_id: {:?}, buffer_length: {:?}, timestamp: {:?} }}",
-                                event_type, stream_id, buffer_length, timestamp),
-
-                RtmpMessage::VideoData { data }
-                    => {
-                    print!("VideoData {{ data: ");
-                    for x in 0..data.len() {
-                        if x > 100 {
-                            print!(".. ({}) ", data.len());
-                            break;
-                        }
-
-                        print!("{:02x}", data[x]);
-                    }
-                    println!("}}")
-                },
+        RtmpMessage::Amf0Command {
+            command_name,
+            transaction_id,
+            command_object,
+            additional_arguments,
+        } => println!(
+            "Amf0Command {{\n  command_name: \"{}\",\n  transaction_id: {},\n  command_object: {},\n  additional_arguments: {}\n}}",
+            command_name,
+            transaction_id,
+            command_object.pretty_print(1),
+            Amf0Value::StrictArray(additional_arguments).pretty_print(1)
+        ),
 
-                RtmpMessage::WindowAcknowledgement { size }
-                    => println!("WindowAcknowledgement {{ size: {} }}", size),
+        RtmpMessage::Amf0Data { values } => println!(
+            "Amf0Data {{\n  values: {}\n}}",
+            Amf0Value::StrictArray(values).pretty_print(1)
+        ),
+
+        RtmpMessage::SharedObject {
+            name,
+            version,
+            persistence,
+            events,
+        } => println!(
+            "SharedObject {{\n  name: \"{}\",\n  version: {},\n  persistence: {},\n  events: {:?}\n}}",
+            name, version, persistence, events
+        ),
+
+        RtmpMessage::Aggregate { messages } => {
+            println!("Aggregate {{ messages: [");
+            for message in messages {
+                print!(
+                    "  {{ type_id: {}, timestamp: {}, data: ",
+                    message.type_id, message.timestamp.value
+                );
+                print_data(&message.data);
+                println!("}}");
             }
+            println!("] }}");
+        }
+
+        RtmpMessage::AudioData { data } => {
+            print!("AudioData: {{ data: ");
+            print_data(&data);
+            println!("}}")
+        }
 
-            println!();
-            println!("Press enter to read next message");
-            let mut input = String::new();
-            std::io::stdin().read_line(&mut input).unwrap();
+        RtmpMessage::SetChunkSize { size } => println!("SetChunkSize {{ size: {} }}", size),
 
-            message_number += 1;
-            has_read_one_payload = true;
-            last_message_end_index = current_index;
+        RtmpMessage::SetPeerBandwidth { size, limit_type } => println!(
+            "SetPeerBandwidth {{ size: {}, limit_type: {:?} }}",
+            size, limit_type
+        ),
+
+        RtmpMessage::UserControl {
+            event_type,
+            stream_id,
+            buffer_length,
+            timestamp,
+        } => println!(
+            "UserControl {{ event_type: {:?}, stream_id: {:?}, buffer_length: {:?}, timestamp: {:?} }}",
+            event_type, stream_id, buffer_length, timestamp
+        ),
+
+        RtmpMessage::VideoData { data } => {
+            print!("VideoData {{ data: ");
+            print_data(&data);
+            println!("}}")
+        }
+
+        RtmpMessage::WindowAcknowledgement { size } => {
+            println!("WindowAcknowledgement {{ size: {} }}", size)
         }
     }
+
+    println!();
+    println!("Press enter to read next message");
+    let mut input = String::new();
+    std::io::stdin().read_line(&mut input).unwrap();
+}
+
+fn print_data(data: &[u8]) {
+    for x in 0..data.len() {
+        if x > 100 {
+            print!(".. ({}) ", data.len());
+            break;
+        }
+
+        print!("{:02x}", data[x]);
+    }
 }
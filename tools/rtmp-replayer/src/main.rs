@@ -0,0 +1,276 @@
+extern crate rml_rtmp;
+
+use rml_rtmp::chunk_io::ChunkDeserializer;
+use rml_rtmp::handshake::{Handshake, HandshakeProcessResult, PeerType};
+use rml_rtmp::messages::RtmpMessage;
+use rml_rtmp::rml_amf0::Amf0Value;
+use std::env;
+use std::fs::File;
+use std::io::{Read, Write};
+use std::net::{Shutdown, TcpStream};
+use std::thread;
+use std::time::Duration;
+
+struct Options {
+    host: String,
+    port: u16,
+    delay_ms: u64,
+    capture: String,
+    output: Option<String>,
+}
+
+fn main() {
+    println!("RTMP Replayer");
+    println!(
+        "Replays a capture of the bytes a client sent to an RTMP server (after the handshake)"
+    );
+    println!();
+
+    let options = match parse_options() {
+        Some(options) => options,
+        None => {
+            println!("Usage: rtmp-replayer --capture <file> [--host <host>] [--port <port>] [--delay-ms <ms>] [--output <file>]");
+            println!("  --capture   File containing the client to server bytes to replay");
+            println!("  --host      Host of the RTMP server to connect to (default: 127.0.0.1)");
+            println!("  --port      Port of the RTMP server to connect to (default: 1935)");
+            println!(
+                "  --delay-ms  Milliseconds to wait between each replayed message (default: 0)"
+            );
+            println!("  --output    File to write all bytes the server responds with");
+            return;
+        }
+    };
+
+    let mut capture = Vec::new();
+    File::open(&options.capture)
+        .unwrap()
+        .read_to_end(&mut capture)
+        .unwrap();
+
+    let messages = split_into_messages(&capture);
+    println!(
+        "Read {} messages ({} bytes) from {}",
+        messages.len(),
+        capture.len(),
+        options.capture
+    );
+
+    let address = format!("{}:{}", options.host, options.port);
+    let mut stream = TcpStream::connect(&address).unwrap();
+    println!("Connected to {}", address);
+
+    let remaining_bytes = perform_handshake(&mut stream);
+    println!("Handshaking Completed!");
+
+    let read_stream = stream.try_clone().unwrap();
+    let output = options.output.clone();
+    let reader = thread::spawn(move || read_responses(read_stream, remaining_bytes, output));
+
+    for (index, message) in messages.iter().enumerate() {
+        if index > 0 && options.delay_ms > 0 {
+            thread::sleep(Duration::from_millis(options.delay_ms));
+        }
+
+        if let Err(error) = stream.write_all(message) {
+            println!("Failed to send message {}: {}", index + 1, error);
+            break;
+        }
+    }
+
+    println!("Finished replaying capture, waiting for final responses");
+    thread::sleep(Duration::from_secs(2));
+    let _ = stream.shutdown(Shutdown::Both);
+    let _ = reader.join();
+}
+
+fn parse_options() -> Option<Options> {
+    let args: Vec<String> = env::args().collect();
+    let mut options = Options {
+        host: "127.0.0.1".to_string(),
+        port: 1935,
+        delay_ms: 0,
+        capture: String::new(),
+        output: None,
+    };
+
+    let mut index = 1;
+    while index < args.len() {
+        let value = match args.get(index + 1) {
+            Some(value) => value.clone(),
+            None => return None,
+        };
+
+        match args[index].as_str() {
+            "--host" => options.host = value,
+            "--port" => options.port = value.parse().ok()?,
+            "--delay-ms" => options.delay_ms = value.parse().ok()?,
+            "--capture" => options.capture = value,
+            "--output" => options.output = Some(value),
+            arg => {
+                println!("Unknown argument: {}", arg);
+                return None;
+            }
+        }
+
+        index += 2;
+    }
+
+    if options.capture.is_empty() {
+        return None;
+    }
+
+    Some(options)
+}
+
+/// Splits the captured bytes into the raw bytes of each RTMP message, so a delay can be
+/// added between each one.  The bytes of each message are kept exactly as they were captured.
+fn split_into_messages(capture: &[u8]) -> Vec<Vec<u8>> {
+    let mut deserializer = ChunkDeserializer::new();
+    let mut messages = Vec::new();
+    let mut message_start = 0;
+    for index in 0..capture.len() {
+        let payload = match deserializer.get_next_message(&capture[index..index + 1]) {
+            Ok(Some(payload)) => payload,
+            Ok(None) => continue,
+            Err(error) => {
+                println!(
+                    "Capture could not be parsed at byte {}: {}.  Remaining bytes will be sent as is",
+                    index, error
+                );
+
+                break;
+            }
+        };
+
+        if let Ok(RtmpMessage::SetChunkSize { size }) = payload.to_rtmp_message() {
+            deserializer.set_max_chunk_size(size as usize).unwrap();
+        }
+
+        messages.push(capture[message_start..index + 1].to_vec());
+        message_start = index + 1;
+    }
+
+    if message_start < capture.len() {
+        messages.push(capture[message_start..].to_vec());
+    }
+
+    messages
+}
+
+fn perform_handshake(stream: &mut TcpStream) -> Vec<u8> {
+    let mut handshake = Handshake::new(PeerType::Client);
+    let c0_and_c1 = handshake.generate_outbound_p0_and_p1().unwrap();
+    stream.write_all(&c0_and_c1).unwrap();
+
+    let mut read_buffer = [0_u8; 4096];
+    loop {
+        let bytes_read = stream.read(&mut read_buffer).unwrap();
+        if bytes_read == 0 {
+            panic!("Server closed the connection during the handshake");
+        }
+
+        match handshake.process_bytes(&read_buffer[..bytes_read]) {
+            Err(x) => panic!("Error returned: {:?}", x),
+            Ok(HandshakeProcessResult::InProgress { response_bytes }) => {
+                if !response_bytes.is_empty() {
+                    stream.write_all(&response_bytes).unwrap();
+                }
+            }
+
+            Ok(HandshakeProcessResult::Completed {
+                response_bytes,
+                remaining_bytes,
+            }) => {
+                if !response_bytes.is_empty() {
+                    stream.write_all(&response_bytes).unwrap();
+                }
+
+                return remaining_bytes;
+            }
+        }
+    }
+}
+
+fn read_responses(mut stream: TcpStream, initial_bytes: Vec<u8>, output: Option<String>) {
+    let mut output_file = output.map(|file_name| File::create(file_name).unwrap());
+    let mut deserializer = ChunkDeserializer::new();
+    let mut bytes = initial_bytes;
+    let mut read_buffer = [0_u8; 4096];
+
+    loop {
+        if let Some(ref mut file) = output_file {
+            file.write_all(&bytes).unwrap();
+        }
+
+        let mut input = &bytes[..];
+        loop {
+            let payload = match deserializer.get_next_message(input) {
+                Ok(Some(payload)) => payload,
+                Ok(None) => break,
+                Err(error) => {
+                    println!("Failed to deserialize server response: {}", error);
+                    return;
+                }
+            };
+
+            input = &[];
+            match payload.to_rtmp_message() {
+                Ok(RtmpMessage::SetChunkSize { size }) => {
+                    deserializer.set_max_chunk_size(size as usize).unwrap();
+                }
+
+                Ok(RtmpMessage::Amf0Command {
+                    command_name,
+                    additional_arguments,
+                    ..
+                }) => report_command(&command_name, additional_arguments),
+
+                Ok(_) => (),
+                Err(error) => println!("Failed to read server message: {}", error),
+            }
+        }
+
+        bytes = match stream.read(&mut read_buffer) {
+            Ok(0) => {
+                println!("Connection closed");
+                return;
+            }
+
+            Ok(bytes_read) => read_buffer[..bytes_read].to_vec(),
+            Err(_) => return, // connection was shut down
+        };
+    }
+}
+
+fn report_command(command_name: &str, arguments: Vec<Amf0Value>) {
+    let status = arguments
+        .into_iter()
+        .filter_map(|value| value.get_object_properties())
+        .next();
+
+    let (level, code, description) = match status {
+        Some(mut properties) => (
+            get_string(&mut properties, "level"),
+            get_string(&mut properties, "code"),
+            get_string(&mut properties, "description"),
+        ),
+
+        None => (String::new(), String::new(), String::new()),
+    };
+
+    if command_name == "_error" || level == "error" {
+        println!(
+            "Server returned error from {}: {} ({})",
+            command_name, code, description
+        );
+    } else if !code.is_empty() {
+        println!("Server returned {}: {}", command_name, code);
+    }
+}
+
+fn get_string(properties: &mut std::collections::HashMap<String, Amf0Value>, key: &str) -> String {
+    properties
+        .remove(key)
+        .and_then(|value| value.get_string())
+        .unwrap_or_default()
+}